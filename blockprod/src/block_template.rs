@@ -0,0 +1,122 @@
+// Copyright (c) 2022 RBB S.r.l
+// opensource@mintlayer.org
+// SPDX-License-Identifier: MIT
+// Licensed under the MIT License;
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://github.com/mintlayer/mintlayer-core/blob/master/LICENSE
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Block templates for external miners
+
+use common::{
+    chain::{
+        block::{consensus_data::BlockSignature, timestamp::BlockTimestamp},
+        signed_transaction::SignedTransaction,
+        Block, GenBlock,
+    },
+    primitives::{Amount, BlockHeight, Compact, Id, Idable},
+};
+
+/// Everything an external miner needs to produce a block on top of the current tip
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlockTemplate {
+    block: Block,
+    height: BlockHeight,
+    bits: Option<Compact>,
+    min_timestamp: BlockTimestamp,
+    max_timestamp: BlockTimestamp,
+    transaction_fees: Vec<Amount>,
+    reward: Amount,
+}
+
+impl BlockTemplate {
+    pub fn new(
+        block: Block,
+        height: BlockHeight,
+        bits: Option<Compact>,
+        min_timestamp: BlockTimestamp,
+        max_timestamp: BlockTimestamp,
+        transaction_fees: Vec<Amount>,
+        reward: Amount,
+    ) -> Self {
+        debug_assert_eq!(block.transactions().len(), transaction_fees.len());
+        Self {
+            block,
+            height,
+            bits,
+            min_timestamp,
+            max_timestamp,
+            transaction_fees,
+            reward,
+        }
+    }
+
+    /// The template is identified by the id of its (unsolved) block
+    pub fn id(&self) -> Id<Block> {
+        self.block.get_id()
+    }
+
+    /// The unsolved block; only the nonce has to be found if the block requires proof of work
+    pub fn block(&self) -> &Block {
+        &self.block
+    }
+
+    pub fn prev_block_id(&self) -> Id<GenBlock> {
+        self.block.prev_block_id()
+    }
+
+    pub fn height(&self) -> BlockHeight {
+        self.height
+    }
+
+    /// The target the block hash has to meet, or `None` if the block doesn't require proof of work
+    pub fn bits(&self) -> Option<Compact> {
+        self.bits
+    }
+
+    /// The block timestamp must be at least the median time past of the previous block
+    pub fn min_timestamp(&self) -> BlockTimestamp {
+        self.min_timestamp
+    }
+
+    /// The block timestamp must not be too far in the future
+    pub fn max_timestamp(&self) -> BlockTimestamp {
+        self.max_timestamp
+    }
+
+    pub fn transactions(&self) -> &Vec<SignedTransaction> {
+        self.block.transactions()
+    }
+
+    /// The fees of the transactions, in the same order as `transactions()`
+    pub fn transaction_fees(&self) -> &Vec<Amount> {
+        &self.transaction_fees
+    }
+
+    /// The block subsidy plus all transaction fees, which the block reward may claim
+    pub fn reward(&self) -> Amount {
+        self.reward
+    }
+}
+
+/// A block produced by an external miner
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BlockSolution {
+    /// A nonce that solves the proof of work of a previously returned block template
+    Nonce { template_id: Id<Block>, nonce: u128 },
+    /// Signatures of the block signers for a previously returned block template, which are added
+    /// to the signature of the block signing key the template may already carry
+    Signatures {
+        template_id: Id<Block>,
+        signatures: Vec<BlockSignature>,
+    },
+    /// A complete block, e.g. one with a custom block reward or timestamp
+    Block(Block),
+}
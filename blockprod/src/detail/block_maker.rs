@@ -24,6 +24,7 @@ use common::{
     primitives::{BlockHeight, Id, Idable},
     time_getter::TimeGetter,
 };
+use crypto::key::PrivateKey;
 use logging::log;
 use mempool::{
     tx_accumulator::{DefaultTxAccumulator, TransactionAccumulator},
@@ -33,6 +34,8 @@ use utils::tap_error_log::LogError;
 
 use crate::BlockProductionError;
 
+use super::sign_block;
+
 pub enum BlockMakerControlCommand {
     StopBecauseNewTip(Id<Block>, BlockHeight),
     JustStop,
@@ -46,6 +49,7 @@ pub struct BlockMaker {
     chainstate_handle: ChainstateHandle,
     mempool_handle: MempoolHandle,
     time_getter: TimeGetter,
+    block_signing_key: Option<PrivateKey>,
     current_tip_id: Id<Block>,
     current_tip_height: BlockHeight,
     block_maker_rx: crossbeam_channel::Receiver<BlockMakerControlCommand>,
@@ -63,6 +67,7 @@ impl BlockMaker {
         chainstate_handle: ChainstateHandle,
         mempool_handle: MempoolHandle,
         time_getter: TimeGetter,
+        block_signing_key: Option<PrivateKey>,
         current_tip_id: Id<Block>,
         current_tip_height: BlockHeight,
         block_maker_rx: crossbeam_channel::Receiver<BlockMakerControlCommand>,
@@ -72,6 +77,7 @@ impl BlockMaker {
             chainstate_handle,
             mempool_handle,
             time_getter,
+            block_signing_key,
             current_tip_id,
             current_tip_height,
             block_maker_rx,
@@ -99,13 +105,19 @@ impl BlockMaker {
     ) -> Result<Block, BlockProductionError> {
         // TODO: this isn't efficient. We have to create the header first, then see if it obeys consensus rules, then construct the full block
         let current_time = self.time_getter.get_time();
-        let block = Block::new(
+        let mut block = Block::new(
             accumulator.transactions().clone(),
             current_tip_id.into(),
             BlockTimestamp::from_duration_since_epoch(current_time),
             common::chain::block::ConsensusData::None,
             BlockReward::new(vec![]), // TODO: define consensus and rewards through NetworkUpgrades
         )?;
+        sign_block(
+            &self.chain_config,
+            self.block_signing_key.as_ref(),
+            self.current_tip_height.next_height(),
+            &mut block,
+        )?;
         Ok(block)
     }
 
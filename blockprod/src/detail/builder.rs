@@ -21,6 +21,7 @@ use common::{
     primitives::{BlockHeight, Id},
     time_getter::TimeGetter,
 };
+use crypto::key::PrivateKey;
 use futures::FutureExt;
 use logging::log;
 use mempool::{MempoolEvent, MempoolHandle};
@@ -37,6 +38,7 @@ pub struct PerpetualBlockBuilder {
     chainstate_handle: ChainstateHandle,
     mempool_handle: MempoolHandle,
     time_getter: TimeGetter,
+    block_signing_key: Option<PrivateKey>,
     builder_rx: mpsc::UnboundedReceiver<BlockBuilderControlCommand>,
     block_makers_tx: crossbeam_channel::Sender<BlockMakerControlCommand>,
     block_maker_rx: crossbeam_channel::Receiver<BlockMakerControlCommand>,
@@ -55,6 +57,7 @@ impl PerpetualBlockBuilder {
         chainstate_handle: ChainstateHandle,
         mempool_handle: MempoolHandle,
         time_getter: TimeGetter,
+        block_signing_key: Option<PrivateKey>,
         builder_rx: mpsc::UnboundedReceiver<BlockBuilderControlCommand>,
         enabled: bool,
     ) -> Self {
@@ -64,6 +67,7 @@ impl PerpetualBlockBuilder {
             chainstate_handle,
             mempool_handle,
             time_getter,
+            block_signing_key,
             builder_rx,
             block_makers_tx: block_makers_tx.clone(),
             block_maker_rx,
@@ -104,6 +108,7 @@ impl PerpetualBlockBuilder {
         let chainstate_handle = self.chainstate_handle.clone();
         let mempool_handle = self.mempool_handle.clone();
         let time_getter = self.time_getter.clone();
+        let block_signing_key = self.block_signing_key.clone();
         let command_receiver = self.block_maker_rx.clone();
         tokio::spawn(async move {
             BlockMaker::new(
@@ -111,6 +116,7 @@ impl PerpetualBlockBuilder {
                 chainstate_handle,
                 mempool_handle,
                 time_getter,
+                block_signing_key,
                 current_tip_id,
                 current_tip_height,
                 command_receiver,
@@ -16,24 +16,83 @@
 mod block_maker;
 pub mod builder;
 
-use std::sync::Arc;
+use std::{collections::VecDeque, sync::Arc};
 
-use chainstate::ChainstateHandle;
-use common::{chain::ChainConfig, time_getter::TimeGetter};
-use mempool::MempoolHandle;
+use chainstate::{BlockSource, ChainstateError, ChainstateHandle};
+use common::{
+    chain::{
+        block::{
+            consensus_data::{BlockSignature, PoWData, SignedBlockData},
+            timestamp::BlockTimestamp,
+            BlockReward, ConsensusData,
+        },
+        timelock::OutputTimeLock,
+        tokens::OutputValue,
+        Block, ChainConfig, Destination, OutputPurpose, RequiredConsensus, TxOutput,
+    },
+    primitives::{BlockHeight, Id, Idable},
+    time_getter::TimeGetter,
+};
+use crypto::key::PrivateKey;
+use mempool::{tx_accumulator::DefaultTxAccumulator, MempoolHandle};
 use tokio::sync::mpsc;
 
-use crate::{interface::BlockProductionInterface, BlockProductionError};
+use crate::{
+    block_template::{BlockSolution, BlockTemplate},
+    interface::BlockProductionInterface,
+    BlockProductionError,
+};
 
 use self::builder::BlockBuilderControlCommand;
 
+/// The number of most recent block templates kept around, so that solutions for them can be
+/// submitted by nonce
+const MAX_BLOCK_TEMPLATES: usize = 16;
+
+fn requires_signed_blocks(chain_config: &ChainConfig, height: BlockHeight) -> bool {
+    match chain_config.net_upgrade().consensus_status(height) {
+        RequiredConsensus::SignedBlocks => true,
+        RequiredConsensus::PoW(_)
+        | RequiredConsensus::PoS
+        | RequiredConsensus::DSA
+        | RequiredConsensus::IgnoreConsensus => false,
+    }
+}
+
+fn block_signature(
+    block_signing_key: &PrivateKey,
+    block: &Block,
+) -> Result<BlockSignature, BlockProductionError> {
+    BlockSignature::sign(block_signing_key, &block.header().signing_hash())
+        .map_err(BlockProductionError::BlockSigningFailed)
+}
+
+/// Signs the block with the block signing key if the chain requires signed blocks at its height
+fn sign_block(
+    chain_config: &ChainConfig,
+    block_signing_key: Option<&PrivateKey>,
+    height: BlockHeight,
+    block: &mut Block,
+) -> Result<(), BlockProductionError> {
+    if requires_signed_blocks(chain_config, height) {
+        let key = block_signing_key.ok_or(BlockProductionError::MissingBlockSigningKey)?;
+        let signature = block_signature(key, block)?;
+        block.update_consensus_data(ConsensusData::SignedBlock(SignedBlockData::new(vec![
+            signature,
+        ])));
+    }
+    Ok(())
+}
+
 #[allow(dead_code)]
 pub struct BlockProduction {
     chain_config: Arc<ChainConfig>,
     chainstate_handle: ChainstateHandle,
     mempool_handle: MempoolHandle,
     time_getter: TimeGetter,
+    block_signing_key: Option<PrivateKey>,
     builder_tx: mpsc::UnboundedSender<BlockBuilderControlCommand>,
+    block_templates: VecDeque<BlockTemplate>,
 }
 
 impl BlockProduction {
@@ -42,6 +101,7 @@ impl BlockProduction {
         chainstate_handle: ChainstateHandle,
         mempool_handle: MempoolHandle,
         time_getter: TimeGetter,
+        block_signing_key: Option<PrivateKey>,
         builder_tx: mpsc::UnboundedSender<BlockBuilderControlCommand>,
     ) -> Result<Self, BlockProductionError> {
         let block_production = Self {
@@ -49,12 +109,166 @@ impl BlockProduction {
             chainstate_handle,
             mempool_handle,
             time_getter,
+            block_signing_key,
             builder_tx,
+            block_templates: VecDeque::new(),
         };
         Ok(block_production)
     }
+
+    async fn make_block_template(
+        &self,
+        reward_destination: Option<Destination>,
+    ) -> Result<BlockTemplate, BlockProductionError> {
+        let (prev_block_index, min_timestamp) = self
+            .chainstate_handle
+            .call(|chainstate| -> Result<_, ChainstateError> {
+                let prev_block_index = chainstate.get_best_block_index()?;
+                let median_time_past =
+                    chainstate.calculate_median_time_past(&prev_block_index.block_id())?;
+                Ok((prev_block_index, median_time_past))
+            })
+            .await??;
+        let prev_block_id = prev_block_index.block_id();
+        let height = prev_block_index.block_height().next_height();
+
+        let current_time = self.time_getter.get_time();
+        let max_timestamp = BlockTimestamp::from_duration_since_epoch(
+            current_time + *self.chain_config.max_future_block_time_offset(),
+        );
+        let timestamp = std::cmp::max(
+            BlockTimestamp::from_duration_since_epoch(current_time),
+            min_timestamp,
+        );
+
+        let bits = self
+            .chainstate_handle
+            .call(move |chainstate| {
+                chainstate.calculate_new_block_work_required(&prev_block_id, timestamp)
+            })
+            .await??;
+
+        let max_block_size = self.chain_config.max_block_size_from_txs();
+        let accumulator = self
+            .mempool_handle
+            .call_async(move |mempool| {
+                mempool.collect_txs(Box::new(DefaultTxAccumulator::new(max_block_size)))
+            })
+            .await?
+            .map_err(|_| BlockProductionError::MempoolChannelClosed)?;
+
+        let reward = (self.chain_config.block_subsidy_at_height(&height)
+            + accumulator.total_fees())
+        .ok_or(BlockProductionError::BlockRewardOverflow)?;
+
+        let consensus_data = match bits {
+            Some(bits) => ConsensusData::PoW(PoWData::new(bits, 0)),
+            None if requires_signed_blocks(&self.chain_config, height) => {
+                ConsensusData::SignedBlock(SignedBlockData::default())
+            }
+            None => ConsensusData::None,
+        };
+        let reward_outputs = match reward_destination {
+            Some(destination) => {
+                let maturity_distance = consensus_data.reward_maturity_distance(&self.chain_config);
+                let block_count = u64::try_from(i64::from(maturity_distance)).map_err(|_| {
+                    BlockProductionError::InvalidRewardMaturityDistance(maturity_distance)
+                })?;
+                vec![TxOutput::new(
+                    OutputValue::Coin(reward),
+                    OutputPurpose::LockThenTransfer(
+                        destination,
+                        OutputTimeLock::ForBlockCount(block_count),
+                    ),
+                )]
+            }
+            None => vec![],
+        };
+
+        let mut block = Block::new(
+            accumulator.transactions().clone(),
+            prev_block_id,
+            timestamp,
+            consensus_data,
+            BlockReward::new(reward_outputs),
+        )?;
+        // The other block signers add their signatures when the template is submitted
+        if let (ConsensusData::SignedBlock(_), Some(key)) = (
+            block.header().consensus_data(),
+            self.block_signing_key.as_ref(),
+        ) {
+            let signature = block_signature(key, &block)?;
+            block.update_consensus_data(ConsensusData::SignedBlock(SignedBlockData::new(vec![
+                signature,
+            ])));
+        }
+
+        Ok(BlockTemplate::new(
+            block,
+            height,
+            bits,
+            min_timestamp,
+            max_timestamp,
+            accumulator.transaction_fees().clone(),
+            reward,
+        ))
+    }
+
+    fn block_template(
+        &self,
+        template_id: Id<Block>,
+    ) -> Result<&BlockTemplate, BlockProductionError> {
+        self.block_templates
+            .iter()
+            .find(|template| template.id() == template_id)
+            .ok_or(BlockProductionError::UnknownBlockTemplate(template_id))
+    }
+
+    fn solved_template_block(
+        &self,
+        template_id: Id<Block>,
+        nonce: u128,
+    ) -> Result<Block, BlockProductionError> {
+        let template = self.block_template(template_id)?;
+        let bits = template
+            .bits()
+            .ok_or(BlockProductionError::BlockTemplateWithoutPoW(template_id))?;
+
+        let mut block = template.block().clone();
+        block.update_consensus_data(ConsensusData::PoW(PoWData::new(bits, nonce)));
+        Ok(block)
+    }
+
+    /// Adds the signatures to the ones the template is already signed with, a key signing more
+    /// than once only keeps its first signature
+    fn signed_template_block(
+        &self,
+        template_id: Id<Block>,
+        signatures: Vec<BlockSignature>,
+    ) -> Result<Block, BlockProductionError> {
+        let mut block = self.block_template(template_id)?.block().clone();
+        let mut block_signatures = match block.header().consensus_data() {
+            ConsensusData::SignedBlock(data) => data.signatures().clone(),
+            ConsensusData::None | ConsensusData::PoW(_) | ConsensusData::PoS(_) => {
+                return Err(BlockProductionError::BlockTemplateWithoutSignatures(
+                    template_id,
+                ))
+            }
+        };
+        for signature in signatures {
+            if block_signatures.iter().all(|s| s.public_key() != signature.public_key()) {
+                block_signatures.push(signature);
+            }
+        }
+
+        block.update_consensus_data(ConsensusData::SignedBlock(SignedBlockData::new(
+            block_signatures,
+        )));
+        Ok(block)
+    }
 }
 
+#[async_trait::async_trait]
 impl BlockProductionInterface for BlockProduction {
     fn stop(&self) -> Result<(), BlockProductionError> {
         self.builder_tx
@@ -69,4 +283,52 @@ impl BlockProductionInterface for BlockProduction {
             .map_err(|_| BlockProductionError::BlockBuilderChannelClosed)?;
         Ok(())
     }
+
+    async fn get_block_template(
+        &mut self,
+        reward_destination: Option<Destination>,
+    ) -> Result<BlockTemplate, BlockProductionError> {
+        let template = self.make_block_template(reward_destination).await?;
+        if self.block_templates.len() == MAX_BLOCK_TEMPLATES {
+            self.block_templates.pop_front();
+        }
+        self.block_templates.push_back(template.clone());
+        Ok(template)
+    }
+
+    async fn submit_block_solution(
+        &mut self,
+        solution: BlockSolution,
+    ) -> Result<Id<Block>, BlockProductionError> {
+        let block = match solution {
+            BlockSolution::Nonce { template_id, nonce } => {
+                self.solved_template_block(template_id, nonce)?
+            }
+            BlockSolution::Signatures {
+                template_id,
+                signatures,
+            } => self.signed_template_block(template_id, signatures)?,
+            BlockSolution::Block(block) => block,
+        };
+        let block_id = block.get_id();
+
+        self.chainstate_handle
+            .call_mut(move |chainstate| {
+                let block = chainstate.preliminary_block_check(block)?;
+                chainstate.process_block(block, BlockSource::Local)
+            })
+            .await??;
+        Ok(block_id)
+    }
+
+    fn sign_block(&self, block: Block) -> Result<BlockSignature, BlockProductionError> {
+        let key = self
+            .block_signing_key
+            .as_ref()
+            .ok_or(BlockProductionError::MissingBlockSigningKey)?;
+        block_signature(key, &block)
+    }
 }
+
+#[cfg(test)]
+mod tests;
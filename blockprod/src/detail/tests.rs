@@ -0,0 +1,306 @@
+// Copyright (c) 2022 RBB S.r.l
+// opensource@mintlayer.org
+// SPDX-License-Identifier: MIT
+// Licensed under the MIT License;
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://github.com/mintlayer/mintlayer-core/blob/master/LICENSE
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+use chainstate::{make_chainstate, ChainstateConfig, DefaultTransactionVerificationStrategy};
+use common::{
+    chain::{
+        config::{create_regtest, BlockSigners, Builder as ConfigBuilder},
+        ConsensusUpgrade, GenBlock, NetUpgrades, UpgradeVersion,
+    },
+    primitives::{time, Compact, H256},
+};
+use consensus::pow::check_proof_of_work;
+use crypto::key::{KeyKind, PublicKey};
+
+use super::*;
+
+async fn setup(
+    chain_config: Arc<ChainConfig>,
+    time_getter: TimeGetter,
+    block_signing_key: Option<PrivateKey>,
+) -> BlockProduction {
+    let storage = chainstate_storage::inmemory::Store::new_empty().unwrap();
+    let mut manager = subsystem::Manager::new("blockprod-test");
+    let chainstate = manager.add_subsystem(
+        "chainstate",
+        make_chainstate(
+            Arc::clone(&chain_config),
+            ChainstateConfig::new(),
+            storage,
+            DefaultTransactionVerificationStrategy::new(),
+            None,
+            Default::default(),
+        )
+        .unwrap(),
+    );
+    let mempool = manager.add_subsystem(
+        "mempool",
+        mempool::make_mempool(
+            Arc::clone(&chain_config),
+            chainstate.clone(),
+            Default::default(),
+            Default::default(),
+            mempool::SystemUsageEstimator {},
+            None,
+        )
+        .unwrap(),
+    );
+    tokio::spawn(async move { manager.main().await });
+
+    let (builder_tx, _builder_rx) = mpsc::unbounded_channel();
+    BlockProduction::new(
+        chain_config,
+        chainstate,
+        mempool,
+        time_getter,
+        block_signing_key,
+        builder_tx,
+    )
+    .unwrap()
+}
+
+// Returns the first nonce for which the proof of work of the template block is (in)valid
+fn find_nonce(template: &BlockTemplate, valid: bool) -> u128 {
+    let bits = template.bits().unwrap();
+    let mut block = template.block().clone();
+    (0..)
+        .find(|nonce| {
+            block.update_consensus_data(ConsensusData::PoW(PoWData::new(bits, *nonce)));
+            check_proof_of_work(block.get_id().get(), bits).unwrap() == valid
+        })
+        .unwrap()
+}
+
+// A chain that requires `threshold` of the keys to sign every block after the genesis
+fn signed_blocks_chain_config(keys: &[PrivateKey], threshold: usize) -> ChainConfig {
+    let public_keys = keys.iter().map(PublicKey::from_private_key).collect();
+    let upgrades = vec![
+        (
+            BlockHeight::new(0),
+            UpgradeVersion::ConsensusUpgrade(ConsensusUpgrade::IgnoreConsensus),
+        ),
+        (
+            BlockHeight::new(1),
+            UpgradeVersion::ConsensusUpgrade(ConsensusUpgrade::SignedBlocks),
+        ),
+    ];
+    ConfigBuilder::test_chain()
+        .net_upgrades(NetUpgrades::initialize(upgrades).unwrap())
+        .block_signers(BlockSigners::new(public_keys, threshold).unwrap())
+        .build()
+}
+
+async fn best_block_id(block_production: &BlockProduction) -> Id<GenBlock> {
+    block_production
+        .chainstate_handle
+        .call(|chainstate| chainstate.get_best_block_id())
+        .await
+        .unwrap()
+        .unwrap()
+}
+
+#[tokio::test]
+async fn block_template_on_top_of_tip() {
+    let chain_config = Arc::new(create_regtest());
+    let mut block_production = setup(Arc::clone(&chain_config), Default::default(), None).await;
+
+    let template = block_production.get_block_template(None).await.unwrap();
+    assert_eq!(template.height(), BlockHeight::new(1));
+    assert_eq!(template.prev_block_id(), chain_config.genesis_block_id());
+    assert_eq!(
+        template.bits(),
+        Some(Compact::from(
+            chain_config.get_proof_of_work_config().limit()
+        ))
+    );
+    assert!(template.transactions().is_empty());
+    assert!(template.min_timestamp() <= template.block().timestamp());
+    assert!(template.block().timestamp() <= template.max_timestamp());
+    assert_eq!(
+        template.reward(),
+        chain_config.block_subsidy_at_height(&BlockHeight::new(1))
+    );
+}
+
+#[tokio::test]
+async fn submit_valid_nonce() {
+    let chain_config = Arc::new(create_regtest());
+    let mut block_production = setup(chain_config, Default::default(), None).await;
+
+    let template = block_production.get_block_template(None).await.unwrap();
+    let nonce = find_nonce(&template, true);
+    let block_id = block_production
+        .submit_block_solution(BlockSolution::Nonce {
+            template_id: template.id(),
+            nonce,
+        })
+        .await
+        .unwrap();
+
+    assert_ne!(block_id, template.id());
+    assert_eq!(best_block_id(&block_production).await, block_id);
+}
+
+#[tokio::test]
+async fn submit_bad_nonce() {
+    let chain_config = Arc::new(create_regtest());
+    let genesis_id = chain_config.genesis_block_id();
+    let mut block_production = setup(chain_config, Default::default(), None).await;
+
+    let template = block_production.get_block_template(None).await.unwrap();
+    let nonce = find_nonce(&template, false);
+    let result = block_production
+        .submit_block_solution(BlockSolution::Nonce {
+            template_id: template.id(),
+            nonce,
+        })
+        .await;
+
+    assert!(matches!(
+        result,
+        Err(BlockProductionError::ChainstateError(_))
+    ));
+    assert_eq!(best_block_id(&block_production).await, genesis_id);
+}
+
+#[tokio::test]
+async fn submit_nonce_for_unknown_template() {
+    let chain_config = Arc::new(create_regtest());
+    let mut block_production = setup(chain_config, Default::default(), None).await;
+
+    let template_id = Id::new(H256::zero());
+    assert_eq!(
+        block_production
+            .submit_block_solution(BlockSolution::Nonce {
+                template_id,
+                nonce: 0,
+            })
+            .await,
+        Err(BlockProductionError::UnknownBlockTemplate(template_id))
+    );
+}
+
+#[tokio::test]
+async fn oldest_block_templates_evicted() {
+    // Every template is made a second later than the previous one, so that they all differ
+    let seconds = Arc::new(AtomicU64::new(time::get().as_secs()));
+    let time_getter = {
+        let seconds = Arc::clone(&seconds);
+        TimeGetter::new(Arc::new(move || {
+            Duration::from_secs(seconds.fetch_add(1, Ordering::SeqCst))
+        }))
+    };
+    let chain_config = Arc::new(create_regtest());
+    let mut block_production = setup(chain_config, time_getter, None).await;
+
+    let mut templates = Vec::new();
+    for _ in 0..=MAX_BLOCK_TEMPLATES {
+        templates.push(block_production.get_block_template(None).await.unwrap());
+    }
+    assert_eq!(block_production.block_templates.len(), MAX_BLOCK_TEMPLATES);
+
+    let oldest_id = templates[0].id();
+    assert_eq!(
+        block_production.solved_template_block(oldest_id, 0),
+        Err(BlockProductionError::UnknownBlockTemplate(oldest_id))
+    );
+    for template in &templates[1..] {
+        assert!(block_production.solved_template_block(template.id(), 0).is_ok());
+    }
+}
+
+#[tokio::test]
+async fn submit_block_signatures() {
+    let keys: Vec<_> = (0..3)
+        .map(|_| PrivateKey::new_from_entropy(KeyKind::RistrettoSchnorr).0)
+        .collect();
+    let chain_config = Arc::new(signed_blocks_chain_config(&keys, 2));
+    let genesis_id = chain_config.genesis_block_id();
+    let mut block_production = setup(
+        Arc::clone(&chain_config),
+        Default::default(),
+        Some(keys[0].clone()),
+    )
+    .await;
+    let co_signer = setup(chain_config, Default::default(), Some(keys[1].clone())).await;
+
+    let template = block_production.get_block_template(None).await.unwrap();
+    assert_eq!(template.bits(), None);
+
+    // The signature of the template alone is below the threshold
+    let result = block_production
+        .submit_block_solution(BlockSolution::Signatures {
+            template_id: template.id(),
+            signatures: vec![],
+        })
+        .await;
+    assert!(matches!(
+        result,
+        Err(BlockProductionError::ChainstateError(_))
+    ));
+    assert_eq!(best_block_id(&block_production).await, genesis_id);
+
+    let signature = co_signer.sign_block(template.block().clone()).unwrap();
+    let block_id = block_production
+        .submit_block_solution(BlockSolution::Signatures {
+            template_id: template.id(),
+            signatures: vec![signature],
+        })
+        .await
+        .unwrap();
+    assert_eq!(best_block_id(&block_production).await, block_id);
+}
+
+#[tokio::test]
+async fn unsigned_block_template() {
+    let keys: Vec<_> = (0..2)
+        .map(|_| PrivateKey::new_from_entropy(KeyKind::RistrettoSchnorr).0)
+        .collect();
+    let chain_config = Arc::new(signed_blocks_chain_config(&keys, 2));
+    let mut block_production = setup(chain_config, Default::default(), None).await;
+
+    // Without a block signing key the template is left for the block signers to sign
+    let template = block_production.get_block_template(None).await.unwrap();
+    assert_eq!(
+        template.block().header().consensus_data(),
+        &ConsensusData::SignedBlock(SignedBlockData::default())
+    );
+    assert_eq!(
+        block_production.sign_block(template.block().clone()),
+        Err(BlockProductionError::MissingBlockSigningKey)
+    );
+}
+
+#[tokio::test]
+async fn submit_signatures_for_pow_template() {
+    let chain_config = Arc::new(create_regtest());
+    let mut block_production = setup(chain_config, Default::default(), None).await;
+
+    let template = block_production.get_block_template(None).await.unwrap();
+    assert_eq!(
+        block_production
+            .submit_block_solution(BlockSolution::Signatures {
+                template_id: template.id(),
+                signatures: vec![],
+            })
+            .await,
+        Err(BlockProductionError::BlockTemplateWithoutSignatures(
+            template.id()
+        ))
+    );
+}
@@ -13,8 +13,17 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use crate::BlockProductionError;
+use common::{
+    chain::{block::consensus_data::BlockSignature, Block, Destination},
+    primitives::Id,
+};
 
+use crate::{
+    block_template::{BlockSolution, BlockTemplate},
+    BlockProductionError,
+};
+
+#[async_trait::async_trait]
 pub trait BlockProductionInterface: Send {
     /// When called, the block builder will start creating blocks at the next tip in chainstate
     fn start(&self) -> Result<(), BlockProductionError>;
@@ -23,4 +32,22 @@ pub trait BlockProductionInterface: Send {
     /// and won't attempt to do it again for new tips in chainstate or mempool
     /// Call start() to enable again
     fn stop(&self) -> Result<(), BlockProductionError>;
+
+    /// Create a template for a new block on top of the current chainstate tip, with transactions
+    /// taken from the mempool. If a reward destination is given, the block reward of the template
+    /// pays the whole expected reward to it; otherwise the block reward is left empty.
+    async fn get_block_template(
+        &mut self,
+        reward_destination: Option<Destination>,
+    ) -> Result<BlockTemplate, BlockProductionError>;
+
+    /// Submit a block found by an external miner to chainstate and return its id
+    async fn submit_block_solution(
+        &mut self,
+        solution: BlockSolution,
+    ) -> Result<Id<Block>, BlockProductionError>;
+
+    /// Sign the block with the block signing key, e.g. a block template made by another block
+    /// signer, which then submits the signature along with the template
+    fn sign_block(&self, block: Block) -> Result<BlockSignature, BlockProductionError>;
 }
@@ -13,15 +13,18 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+pub mod block_template;
 pub mod rpc;
 
 use std::sync::Arc;
 
-use chainstate::ChainstateHandle;
+use chainstate::{ChainstateError, ChainstateHandle};
 use common::{
-    chain::{block::BlockCreationError, ChainConfig},
+    chain::{block::BlockCreationError, Block, ChainConfig},
+    primitives::{BlockDistance, Id},
     time_getter::TimeGetter,
 };
+use crypto::key::{PrivateKey, SignatureError};
 use detail::{builder::PerpetualBlockBuilder, BlockProduction};
 use interface::BlockProductionInterface;
 use mempool::MempoolHandle;
@@ -42,6 +45,22 @@ pub enum BlockProductionError {
     SubsystemCallError(#[from] CallError),
     #[error("Block creation error: {0}")]
     FailedToConstructBlock(#[from] BlockCreationError),
+    #[error("Chainstate error: {0}")]
+    ChainstateError(#[from] ChainstateError),
+    #[error("Block reward overflow")]
+    BlockRewardOverflow,
+    #[error("Invalid block reward maturity distance {0:?}")]
+    InvalidRewardMaturityDistance(BlockDistance),
+    #[error("Unknown block template {0}")]
+    UnknownBlockTemplate(Id<Block>),
+    #[error("Block template {0} doesn't require proof of work")]
+    BlockTemplateWithoutPoW(Id<Block>),
+    #[error("Block template {0} doesn't require block signatures")]
+    BlockTemplateWithoutSignatures(Id<Block>),
+    #[error("The chain requires signed blocks but no block signing key is configured")]
+    MissingBlockSigningKey,
+    #[error("Block signing failed: {0:?}")]
+    BlockSigningFailed(SignatureError),
 }
 
 mod detail;
@@ -57,6 +76,7 @@ pub async fn make_blockproduction(
     chainstate_handle: ChainstateHandle,
     mempool_handle: MempoolHandle,
     time_getter: TimeGetter,
+    block_signing_key: Option<PrivateKey>,
 ) -> Result<Box<dyn BlockProductionInterface>, BlockProductionError> {
     let (tx_builder, rx_builder) = mpsc::unbounded_channel();
 
@@ -65,12 +85,14 @@ pub async fn make_blockproduction(
         let chainstate_handle = chainstate_handle.clone();
         let mempool_handle = mempool_handle.clone();
         let time_getter = time_getter.clone();
+        let block_signing_key = block_signing_key.clone();
         tokio::spawn(async move {
             PerpetualBlockBuilder::new(
                 chain_config,
                 chainstate_handle,
                 mempool_handle,
                 time_getter,
+                block_signing_key,
                 rx_builder,
                 true, // TODO: take this from BlockProductionConfig
             )
@@ -84,6 +106,7 @@ pub async fn make_blockproduction(
         chainstate_handle,
         mempool_handle,
         time_getter,
+        block_signing_key,
         tx_builder,
     )?;
 
@@ -15,9 +15,86 @@
 
 //! Block production subsystem RPC handler
 
-use crate::BlockProductionError;
+use common::{
+    chain::{block::consensus_data::BlockSignature, Block, Destination, GenBlock},
+    primitives::{BlockHeight, Id},
+};
+use serialization::{Decode, Encode};
 use subsystem::subsystem::CallError;
 
+use crate::{
+    block_template::{BlockSolution, BlockTemplate},
+    BlockProductionError,
+};
+
+/// A transaction of a block template
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct RpcTemplateTransaction {
+    /// Hex-encoded transaction
+    pub tx: String,
+    /// Fee paid by the transaction, in atoms
+    pub fee: u128,
+}
+
+/// A block template for external miners
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct RpcBlockTemplate {
+    pub template_id: Id<Block>,
+    pub prev_block_id: Id<GenBlock>,
+    pub height: BlockHeight,
+    /// Compact target the block hash has to meet, `null` if the block needs no proof of work
+    pub bits: Option<u32>,
+    /// Allowed range of the block timestamp, in seconds since the epoch
+    pub min_timestamp: u64,
+    pub max_timestamp: u64,
+    pub transactions: Vec<RpcTemplateTransaction>,
+    /// Block subsidy plus transaction fees, in atoms
+    pub reward: u128,
+    /// Hex-encoded unsolved block; only the nonce has to be found
+    pub block: String,
+}
+
+impl From<BlockTemplate> for RpcBlockTemplate {
+    fn from(template: BlockTemplate) -> Self {
+        let transactions = template
+            .transactions()
+            .iter()
+            .zip(template.transaction_fees())
+            .map(|(tx, fee)| RpcTemplateTransaction {
+                tx: hex::encode(tx.encode()),
+                fee: fee.into_atoms(),
+            })
+            .collect();
+        Self {
+            template_id: template.id(),
+            prev_block_id: template.prev_block_id(),
+            height: template.height(),
+            bits: template.bits().map(|bits| bits.0),
+            min_timestamp: template.min_timestamp().as_int_seconds(),
+            max_timestamp: template.max_timestamp().as_int_seconds(),
+            transactions,
+            reward: template.reward().into_atoms(),
+            block: hex::encode(template.block().encode()),
+        }
+    }
+}
+
+/// A solved block, either as the nonce or the hex-encoded block signer signatures for a block
+/// template, or as a full hex-encoded block
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RpcBlockSolution {
+    Nonce {
+        template_id: Id<Block>,
+        nonce: u128,
+    },
+    Signatures {
+        template_id: Id<Block>,
+        signatures: Vec<String>,
+    },
+    Block(String),
+}
+
 #[rpc::rpc(server, namespace = "blockprod")]
 trait BlockProductionRpc {
     /// Stop block production
@@ -27,6 +104,23 @@ trait BlockProductionRpc {
     /// Start block production on the next chance (when new tip is available)
     #[method(name = "start")]
     async fn start(&self) -> rpc::Result<()>;
+
+    /// Get a template for a new block on top of the current tip. If a hex-encoded reward
+    /// destination is given, the block reward of the template pays the expected reward to it.
+    #[method(name = "get_block_template")]
+    async fn get_block_template(
+        &self,
+        reward_destination_hex: Option<String>,
+    ) -> rpc::Result<RpcBlockTemplate>;
+
+    /// Submit a block found by an external miner, returns the id of the block
+    #[method(name = "submit_block_solution")]
+    async fn submit_block_solution(&self, solution: RpcBlockSolution) -> rpc::Result<Id<Block>>;
+
+    /// Sign a hex-encoded block, e.g. a block template of another block signer, with the block
+    /// signing key of this node, returns the hex-encoded signature
+    #[method(name = "sign_block")]
+    async fn sign_block(&self, block_hex: String) -> rpc::Result<String>;
 }
 
 #[async_trait::async_trait]
@@ -38,6 +132,59 @@ impl BlockProductionRpcServer for super::BlockProductionHandle {
     async fn start(&self) -> rpc::Result<()> {
         handle_error(self.call(|this| this.start()).await)
     }
+
+    async fn get_block_template(
+        &self,
+        reward_destination_hex: Option<String>,
+    ) -> rpc::Result<RpcBlockTemplate> {
+        let reward_destination = reward_destination_hex
+            .map(|destination_hex| {
+                let data = hex::decode(destination_hex).map_err(rpc::Error::to_call_error)?;
+                Destination::decode(&mut &data[..]).map_err(rpc::Error::to_call_error)
+            })
+            .transpose()?;
+        let res = self
+            .call_async_mut(move |this| this.get_block_template(reward_destination))
+            .await;
+        handle_error(res).map(RpcBlockTemplate::from)
+    }
+
+    async fn submit_block_solution(&self, solution: RpcBlockSolution) -> rpc::Result<Id<Block>> {
+        let solution = match solution {
+            RpcBlockSolution::Nonce { template_id, nonce } => {
+                BlockSolution::Nonce { template_id, nonce }
+            }
+            RpcBlockSolution::Signatures {
+                template_id,
+                signatures,
+            } => {
+                let signatures = signatures
+                    .into_iter()
+                    .map(|signature_hex| {
+                        let data = hex::decode(signature_hex).map_err(rpc::Error::to_call_error)?;
+                        BlockSignature::decode(&mut &data[..]).map_err(rpc::Error::to_call_error)
+                    })
+                    .collect::<rpc::Result<_>>()?;
+                BlockSolution::Signatures {
+                    template_id,
+                    signatures,
+                }
+            }
+            RpcBlockSolution::Block(block_hex) => {
+                let data = hex::decode(block_hex).map_err(rpc::Error::to_call_error)?;
+                let block = Block::decode(&mut &data[..]).map_err(rpc::Error::to_call_error)?;
+                BlockSolution::Block(block)
+            }
+        };
+        handle_error(self.call_async_mut(move |this| this.submit_block_solution(solution)).await)
+    }
+
+    async fn sign_block(&self, block_hex: String) -> rpc::Result<String> {
+        let data = hex::decode(block_hex).map_err(rpc::Error::to_call_error)?;
+        let block = Block::decode(&mut &data[..]).map_err(rpc::Error::to_call_error)?;
+        let signature = handle_error(self.call(move |this| this.sign_block(block)).await)?;
+        Ok(hex::encode(signature.encode()))
+    }
 }
 
 fn handle_error<T>(e: Result<Result<T, BlockProductionError>, CallError>) -> rpc::Result<T> {
@@ -24,7 +24,7 @@ pub use chainstate::{
     chainstate_interface::ChainstateInterface, ChainstateConfig, ChainstateError as Error,
     DefaultTransactionVerificationStrategy,
 };
-pub use common::chain::ChainConfig;
+pub use common::{chain::ChainConfig, time_getter::TimeGetter};
 pub use config::{ChainstateLauncherConfig, StorageBackendConfig};
 
 /// Subdirectory under `datadir` where LMDB chainstate database is placed
@@ -34,6 +34,7 @@ fn make_chainstate_and_storage_impl<B: 'static + storage::Backend>(
     storage_backend: B,
     chain_config: Arc<ChainConfig>,
     chainstate_config: ChainstateConfig,
+    time_getter: TimeGetter,
 ) -> Result<Box<dyn ChainstateInterface>, Error> {
     let storage = chainstate_storage::Store::new(storage_backend)
         .map_err(|e| Error::FailedToInitializeChainstate(e.into()))?;
@@ -43,16 +44,19 @@ fn make_chainstate_and_storage_impl<B: 'static + storage::Backend>(
         storage,
         DefaultTransactionVerificationStrategy::new(),
         None,
-        Default::default(),
+        time_getter,
     )?;
     Ok(chainstate)
 }
 
 /// Create chainstate together with its storage
+///
+/// The `time_getter` is used to check the block timestamps against the current time.
 pub fn make_chainstate(
     datadir: &std::path::Path,
     chain_config: Arc<ChainConfig>,
     config: ChainstateLauncherConfig,
+    time_getter: TimeGetter,
 ) -> Result<Box<dyn ChainstateInterface>, Error> {
     let ChainstateLauncherConfig {
         storage_backend,
@@ -64,11 +68,11 @@ pub fn make_chainstate(
     match storage_backend {
         StorageBackendConfig::Lmdb => {
             let storage = storage_lmdb::Lmdb::new(datadir.join(SUBDIRECTORY_LMDB));
-            make_chainstate_and_storage_impl(storage, chain_config, chainstate_config)
+            make_chainstate_and_storage_impl(storage, chain_config, chainstate_config, time_getter)
         }
         StorageBackendConfig::InMemory => {
             let storage = storage_inmemory::InMemory::new();
-            make_chainstate_and_storage_impl(storage, chain_config, chainstate_config)
+            make_chainstate_and_storage_impl(storage, chain_config, chainstate_config, time_getter)
         }
     }
 }
@@ -22,7 +22,7 @@ use super::{
 };
 use crate::BlockError;
 use chainstate_types::GetAncestorError;
-use consensus::{ConsensusPoWError, ConsensusVerificationError};
+use consensus::{ConsensusPoWError, ConsensusSignedBlockError, ConsensusVerificationError};
 
 // TODO: use a ban_score macro in a form similar to thiserror::Error in order to define the ban score
 //       value of an error on the error enum arms instead of separately like in this file
@@ -59,6 +59,7 @@ impl BanScore for OrphanCheckError {
             OrphanCheckError::StorageError(_) => 0,
             OrphanCheckError::PrevBlockIndexNotFound(_) => 100,
             OrphanCheckError::LocalOrphan => 0,
+            OrphanCheckError::PeerOrphan => 0,
         }
     }
 }
@@ -226,6 +227,7 @@ impl BanScore for ConsensusVerificationError {
             ConsensusVerificationError::PrevBlockNotFound(_, _) => 100,
             ConsensusVerificationError::ConsensusTypeMismatch(_) => 100,
             ConsensusVerificationError::PoWError(err) => err.ban_score(),
+            ConsensusVerificationError::SignedBlockError(err) => err.ban_score(),
             ConsensusVerificationError::UnsupportedConsensusType => 100,
         }
     }
@@ -245,6 +247,18 @@ impl BanScore for ConsensusPoWError {
     }
 }
 
+impl BanScore for ConsensusSignedBlockError {
+    fn ban_score(&self) -> u32 {
+        match self {
+            ConsensusSignedBlockError::NoBlockSigners => 0,
+            ConsensusSignedBlockError::UnknownSigner(_) => 100,
+            ConsensusSignedBlockError::DuplicateSigner(_) => 100,
+            ConsensusSignedBlockError::InvalidSignature(_) => 100,
+            ConsensusSignedBlockError::NotEnoughSignatures(_, _, _) => 100,
+        }
+    }
+}
+
 impl BanScore for BlockSizeError {
     fn ban_score(&self) -> u32 {
         match self {
@@ -704,9 +704,12 @@ impl<'a, S: BlockchainStorageWrite, O: OrphanBlocksMut, V: TransactionVerificati
             .log_err()?
             .is_some();
 
-        if block_source == BlockSource::Local && !block_index_found {
+        if !block_index_found {
             self.new_orphan_block(block).log_err()?;
-            return Err(OrphanCheckError::LocalOrphan);
+            return Err(match block_source {
+                BlockSource::Local => OrphanCheckError::LocalOrphan,
+                BlockSource::Peer => OrphanCheckError::PeerOrphan,
+            });
         }
         Ok(block)
     }
@@ -115,6 +115,8 @@ pub enum OrphanCheckError {
     PrevBlockIndexNotFound(PropertyQueryError),
     #[error("Orphan that was submitted legitimately through a local source")]
     LocalOrphan,
+    #[error("Orphan that was received from a peer, it's kept until its parent arrives")]
+    PeerOrphan,
 }
 
 #[derive(Error, Debug, PartialEq, Eq, Clone)]
@@ -26,7 +26,7 @@ use common::chain::{
     ChainConfig, OutPointSourceId, TxMainChainIndex,
 };
 use common::chain::{OutPoint, Transaction};
-use common::primitives::{Amount, BlockHeight, Id};
+use common::primitives::{Amount, BlockHeight, Compact, Id};
 use utils::eventhandler::EventHandler;
 
 use crate::{ChainstateError, ChainstateEvent};
@@ -94,6 +94,14 @@ pub trait ChainstateInterface: Send {
         &self,
         starting_block: &Id<GenBlock>,
     ) -> Result<BlockTimestamp, ChainstateError>;
+
+    /// Returns the bits that a new block with the given timestamp on top of `prev_block_id` must
+    /// satisfy, or `None` if such a block doesn't require proof of work
+    fn calculate_new_block_work_required(
+        &self,
+        prev_block_id: &Id<GenBlock>,
+        new_block_time: BlockTimestamp,
+    ) -> Result<Option<Compact>, ChainstateError>;
     fn is_already_an_orphan(&self, block_id: &Id<Block>) -> bool;
     fn orphans_count(&self) -> usize;
     fn get_ancestor(
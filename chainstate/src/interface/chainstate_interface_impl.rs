@@ -26,7 +26,7 @@ use common::chain::config::ChainConfig;
 use common::chain::tokens::OutputValue;
 use common::chain::tokens::TokenAuxiliaryData;
 use common::chain::{OutPoint, TxInput};
-use common::chain::{OutPointSourceId, Transaction, TxMainChainIndex};
+use common::chain::{OutPointSourceId, RequiredConsensus, Transaction, TxMainChainIndex};
 use common::primitives::Amount;
 
 use chainstate_types::PropertyQueryError;
@@ -35,7 +35,7 @@ use common::{
         block::{Block, BlockHeader, GenBlock},
         tokens::{RPCTokenInfo, TokenId},
     },
-    primitives::{id::WithId, BlockHeight, Id},
+    primitives::{id::WithId, BlockHeight, Compact, Id},
 };
 use utils::eventhandler::EventHandler;
 use utxo::{Utxo, UtxosView};
@@ -237,6 +237,44 @@ impl<S: BlockchainStorage, V: TransactionVerificationStrategy> ChainstateInterfa
         Ok(calculate_median_time_past(&dbtx, starting_block))
     }
 
+    fn calculate_new_block_work_required(
+        &self,
+        prev_block_id: &Id<GenBlock>,
+        new_block_time: common::chain::block::timestamp::BlockTimestamp,
+    ) -> Result<Option<Compact>, ChainstateError> {
+        let err_f = |e| ChainstateError::FailedToReadProperty(PropertyQueryError::from(e));
+        let dbtx = self.chainstate.make_db_tx_ro().map_err(err_f)?;
+        let prev_block_index = self
+            .chainstate
+            .query()
+            .map_err(ChainstateError::from)?
+            .get_gen_block_index(prev_block_id)
+            .map_err(ChainstateError::FailedToReadProperty)?
+            .ok_or(ChainstateError::FailedToReadProperty(
+                PropertyQueryError::PrevBlockIndexNotFound(*prev_block_id),
+            ))?;
+        let new_block_height = prev_block_index.block_height().next_height();
+
+        let chain_config = self.chainstate.chain_config();
+        match chain_config.net_upgrade().consensus_status(new_block_height) {
+            RequiredConsensus::PoW(pow_status) => {
+                consensus::pow::calculate_work_required_for_new_block(
+                    chain_config,
+                    &prev_block_index,
+                    new_block_time,
+                    &pow_status,
+                    &dbtx,
+                )
+                .map(Some)
+                .map_err(ChainstateError::from)
+            }
+            RequiredConsensus::IgnoreConsensus
+            | RequiredConsensus::PoS
+            | RequiredConsensus::DSA
+            | RequiredConsensus::SignedBlocks => Ok(None),
+        }
+    }
+
     fn is_already_an_orphan(&self, block_id: &Id<Block>) -> bool {
         self.chainstate.orphan_blocks_pool().is_already_an_orphan(block_id)
     }
@@ -34,7 +34,7 @@ use common::{
         tokens::{RPCTokenInfo, TokenId},
         Block, GenBlock,
     },
-    primitives::{BlockHeight, Id},
+    primitives::{BlockHeight, Compact, Id},
 };
 use utils::eventhandler::EventHandler;
 use utxo::Utxo;
@@ -161,6 +161,14 @@ impl<
         self.deref().calculate_median_time_past(starting_block)
     }
 
+    fn calculate_new_block_work_required(
+        &self,
+        prev_block_id: &Id<GenBlock>,
+        new_block_time: BlockTimestamp,
+    ) -> Result<Option<Compact>, ChainstateError> {
+        self.deref().calculate_new_block_work_required(prev_block_id, new_block_time)
+    }
+
     fn is_already_an_orphan(&self, block_id: &Id<Block>) -> bool {
         self.deref().is_already_an_orphan(block_id)
     }
@@ -27,7 +27,7 @@ use common::{
         block::{Block, BlockHeader, GenBlock},
         tokens::{RPCTokenInfo, TokenId},
     },
-    primitives::{BlockHeight, Id},
+    primitives::{BlockHeight, Compact, Id},
 };
 
 use crate::ChainstateConfig;
@@ -89,6 +89,11 @@ mockall::mock! {
         ) -> Result<Option<TxMainChainIndex>, ChainstateError>;
         fn subscribers(&self) -> &Vec<EventHandler<ChainstateEvent>>;
         fn calculate_median_time_past(&self, starting_block: &Id<GenBlock>) -> Result<BlockTimestamp, ChainstateError>;
+        fn calculate_new_block_work_required(
+            &self,
+            prev_block_id: &Id<GenBlock>,
+            new_block_time: BlockTimestamp,
+        ) -> Result<Option<Compact>, ChainstateError>;
         fn is_already_an_orphan(&self, block_id: &Id<Block>) -> bool;
         fn orphans_count(&self) -> usize;
         fn get_ancestor(
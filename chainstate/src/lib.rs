@@ -42,6 +42,7 @@ use common::{
     chain::{Block, ChainConfig, GenBlock},
     primitives::{BlockHeight, Id},
 };
+use consensus::ConsensusPoWError;
 
 use chainstate_interface::ChainstateInterface;
 use chainstate_interface_impl::ChainstateInterfaceImpl;
@@ -63,6 +64,8 @@ pub enum ChainstateError {
     FailedToReadProperty(#[from] PropertyQueryError),
     #[error("Block import error {0}")]
     BootstrapError(#[from] BootstrapError),
+    #[error("Failed to calculate the required work: {0}")]
+    FailedToCalculateWorkRequired(#[from] ConsensusPoWError),
 }
 
 impl subsystem::Subsystem for Box<dyn ChainstateInterface> {}
@@ -31,8 +31,12 @@ use common::chain::{signed_transaction::SignedTransaction, Transaction};
 use common::primitives::BlockDistance;
 use common::{
     chain::{
-        block::{consensus_data::PoWData, timestamp::BlockTimestamp, ConsensusData},
-        config::{create_unit_test_config, Builder as ConfigBuilder},
+        block::{
+            consensus_data::{BlockSignature, PoWData, SignedBlockData},
+            timestamp::BlockTimestamp,
+            ConsensusData,
+        },
+        config::{create_unit_test_config, BlockSigners, Builder as ConfigBuilder},
         timelock::OutputTimeLock,
         tokens::OutputValue,
         Block, ConsensusUpgrade, Destination, GenBlock, NetUpgrades, OutPointSourceId,
@@ -42,9 +46,9 @@ use common::{
     time_getter::TimeGetter,
     Uint256,
 };
-use consensus::{ConsensusPoWError, ConsensusVerificationError};
+use consensus::{ConsensusPoWError, ConsensusSignedBlockError, ConsensusVerificationError};
 use crypto::{
-    key::{KeyKind, PrivateKey},
+    key::{KeyKind, PrivateKey, PublicKey},
     random::Rng,
 };
 use rstest::rstest;
@@ -243,6 +247,35 @@ fn invalid_block_reward_types(#[case] seed: Seed) {
     });
 }
 
+// An orphan received from a peer is kept until its parent arrives, like a local one
+#[rstest]
+#[trace]
+#[case(Seed::from_entropy())]
+fn peer_orphan_kept(#[case] seed: Seed) {
+    utils::concurrency::model(move || {
+        let mut rng = make_seedable_rng(seed);
+        let mut tf = TestFramework::builder(&mut rng).build();
+
+        let missing_block =
+            tf.make_block_builder().add_test_transaction_from_best_block(&mut rng).build();
+        let orphan = tf
+            .make_block_builder()
+            .with_parent(missing_block.get_id().into())
+            .add_test_transaction_from_block(&missing_block, &mut rng)
+            .build();
+        assert_eq!(
+            tf.process_block(orphan.clone(), BlockSource::Peer).unwrap_err(),
+            ChainstateError::ProcessBlockError(BlockError::OrphanCheckFailed(
+                OrphanCheckError::PeerOrphan
+            ))
+        );
+        assert!(tf.chainstate.is_already_an_orphan(&orphan.get_id()));
+
+        let block_index = tf.process_block(missing_block, BlockSource::Peer).unwrap().unwrap();
+        assert_eq!(block_index.block_id(), &orphan.get_id());
+    });
+}
+
 #[rstest]
 #[trace]
 #[case(Seed::from_entropy())]
@@ -919,6 +952,73 @@ fn pow(#[case] seed: Seed) {
     tf.process_block(valid_block.clone(), BlockSource::Local).unwrap();
 }
 
+#[rstest]
+#[trace]
+#[case(Seed::from_entropy())]
+fn signed_blocks(#[case] seed: Seed) {
+    let mut rng = make_seedable_rng(seed);
+    let keys: Vec<_> = (0..3)
+        .map(|_| PrivateKey::new_from_rng(&mut rng, KeyKind::RistrettoSchnorr))
+        .collect();
+    let block_signers =
+        BlockSigners::new(keys.iter().map(|(_, pub_key)| pub_key.clone()).collect(), 2)
+            .expect("valid block signers");
+
+    let upgrades = vec![
+        (
+            BlockHeight::new(0),
+            UpgradeVersion::ConsensusUpgrade(ConsensusUpgrade::IgnoreConsensus),
+        ),
+        (
+            BlockHeight::new(1),
+            UpgradeVersion::ConsensusUpgrade(ConsensusUpgrade::SignedBlocks),
+        ),
+    ];
+    let net_upgrades = NetUpgrades::initialize(upgrades).expect("valid net-upgrades");
+    let chain_config = ConfigBuilder::test_chain()
+        .net_upgrades(net_upgrades)
+        .block_signers(block_signers)
+        .build();
+    let mut tf = TestFramework::builder(&mut rng).with_chain_config(chain_config).build();
+
+    let mut block = tf.make_block_builder().add_test_transaction_from_best_block(&mut rng).build();
+    let sign = |block: &mut Block, signers: &[(PrivateKey, PublicKey)]| {
+        let signing_hash = block.header().signing_hash();
+        let signatures = signers
+            .iter()
+            .map(|(key, _)| BlockSignature::sign(key, &signing_hash).unwrap())
+            .collect();
+        block.update_consensus_data(ConsensusData::SignedBlock(SignedBlockData::new(signatures)));
+    };
+
+    // An unsigned block is rejected
+    assert!(matches!(
+        tf.process_block(block.clone(), BlockSource::Local),
+        Err(ChainstateError::ProcessBlockError(
+            BlockError::CheckBlockFailed(CheckBlockError::ConsensusVerificationFailed(
+                ConsensusVerificationError::ConsensusTypeMismatch(_)
+            ))
+        ))
+    ));
+
+    // One signature is below the threshold
+    sign(&mut block, &keys[..1]);
+    assert!(matches!(
+        tf.process_block(block.clone(), BlockSource::Local),
+        Err(ChainstateError::ProcessBlockError(
+            BlockError::CheckBlockFailed(CheckBlockError::ConsensusVerificationFailed(
+                ConsensusVerificationError::SignedBlockError(
+                    ConsensusSignedBlockError::NotEnoughSignatures(_, 1, 2)
+                )
+            ))
+        ))
+    ));
+
+    sign(&mut block, &keys[1..]);
+    tf.process_block(block.clone(), BlockSource::Local).unwrap();
+    assert_eq!(tf.best_block_id(), Id::<GenBlock>::from(block.get_id()));
+}
+
 #[rstest]
 #[trace]
 #[case(Seed::from_entropy())]
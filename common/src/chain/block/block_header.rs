@@ -50,6 +50,17 @@ impl BlockHeader {
     pub fn header_size(&self) -> usize {
         self.encoded_size()
     }
+
+    /// The hash of the header without its consensus data, which is what block signers sign
+    pub fn signing_hash(&self) -> H256 {
+        id::hash_encoded(&(
+            &self.version,
+            &self.prev_block_id,
+            &self.tx_merkle_root,
+            &self.witness_merkle_root,
+            &self.timestamp,
+        ))
+    }
 }
 
 impl Idable for BlockHeader {
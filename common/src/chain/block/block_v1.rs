@@ -90,11 +90,11 @@ impl BlockV1 {
 
     pub fn block_reward_transactable(&self) -> BlockRewardTransactable {
         let inputs = match &self.header.consensus_data {
-            ConsensusData::None | ConsensusData::PoW(_) => None,
+            ConsensusData::None | ConsensusData::PoW(_) | ConsensusData::SignedBlock(_) => None,
             ConsensusData::PoS(data) => Some(data.kernel_inputs().as_ref()),
         };
         let witness = match &self.header.consensus_data {
-            ConsensusData::None | ConsensusData::PoW(_) => None,
+            ConsensusData::None | ConsensusData::PoW(_) | ConsensusData::SignedBlock(_) => None,
             ConsensusData::PoS(data) => Some(data.kernel_witness().as_ref()),
         };
 
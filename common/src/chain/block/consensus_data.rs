@@ -15,11 +15,12 @@
 
 use crate::chain::signature::inputsig::InputWitness;
 use crate::chain::ChainConfig;
-use crate::primitives::Compact;
+use crate::primitives::{Compact, H256};
 use crate::Uint256;
 use crate::{chain::TxInput, primitives::BlockDistance};
 
-use serialization::{Decode, Encode};
+use crypto::key::{PrivateKey, PublicKey, Signature, SignatureError};
+use serialization::{Decode, DecodeAll, Encode};
 
 #[derive(Debug, Clone, PartialEq, PartialOrd, Ord, Eq, Encode, Decode)]
 pub enum ConsensusData {
@@ -29,6 +30,8 @@ pub enum ConsensusData {
     PoW(PoWData),
     #[codec(index = 2)]
     PoS(PoSData),
+    #[codec(index = 3)]
+    SignedBlock(SignedBlockData),
 }
 
 impl ConsensusData {
@@ -37,6 +40,7 @@ impl ConsensusData {
             ConsensusData::None => Some(1u64.into()),
             ConsensusData::PoW(ref pow_data) => pow_data.get_block_proof(),
             ConsensusData::PoS(_) => Some(1u64.into()),
+            ConsensusData::SignedBlock(_) => Some(1u64.into()),
         }
     }
 
@@ -47,6 +51,9 @@ impl ConsensusData {
                 chain_config.get_proof_of_work_config().reward_maturity_distance()
             }
             ConsensusData::PoS(_) => BlockDistance::new(2000),
+            ConsensusData::SignedBlock(_) => {
+                chain_config.empty_consensus_reward_maturity_distance()
+            }
         }
     }
 }
@@ -85,6 +92,51 @@ impl PoSData {
     }
 }
 
+/// The signatures of the block signers over the block header hash (see
+/// [`BlockHeader::signing_hash`](super::BlockHeader::signing_hash))
+#[derive(Debug, Clone, Default, PartialEq, PartialOrd, Ord, Eq, Encode, Decode)]
+pub struct SignedBlockData {
+    signatures: Vec<BlockSignature>,
+}
+
+impl SignedBlockData {
+    pub fn new(signatures: Vec<BlockSignature>) -> Self {
+        Self { signatures }
+    }
+
+    pub fn signatures(&self) -> &Vec<BlockSignature> {
+        &self.signatures
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, PartialOrd, Ord, Eq, Encode, Decode)]
+pub struct BlockSignature {
+    public_key: PublicKey,
+    raw_signature: Vec<u8>,
+}
+
+impl BlockSignature {
+    pub fn sign(private_key: &PrivateKey, signing_hash: &H256) -> Result<Self, SignatureError> {
+        let signature = private_key.sign_message(&signing_hash.encode())?;
+        Ok(Self {
+            public_key: PublicKey::from_private_key(private_key),
+            raw_signature: signature.encode(),
+        })
+    }
+
+    pub fn public_key(&self) -> &PublicKey {
+        &self.public_key
+    }
+
+    /// Returns false if the signature can't be decoded or doesn't match the public key
+    pub fn verify(&self, signing_hash: &H256) -> bool {
+        match Signature::decode_all(&mut self.raw_signature.as_slice()) {
+            Ok(signature) => self.public_key.verify_message(&signature, &signing_hash.encode()),
+            Err(_) => false,
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, PartialOrd, Ord, Eq, Encode, Decode)]
 pub struct PoWData {
     bits: Compact,
@@ -431,4 +431,34 @@ mod tests {
 
         check_block_tag(&block);
     }
+
+    #[test]
+    fn block_signatures() {
+        use crate::chain::block::consensus_data::{BlockSignature, SignedBlockData};
+        use crypto::key::{KeyKind, PrivateKey};
+
+        let mut rng = make_pseudo_rng();
+        let (private_key, public_key) = PrivateKey::new_from_entropy(KeyKind::RistrettoSchnorr);
+
+        let mut block = Block::new(
+            Vec::new(),
+            Id::new(H256::from_low_u64_be(rng.gen())),
+            BlockTimestamp::from_int_seconds(rng.gen()),
+            ConsensusData::None,
+            BlockReward::new(Vec::new()),
+        )
+        .unwrap();
+
+        // The consensus data doesn't change what has to be signed
+        let signing_hash = block.header().signing_hash();
+        let signature = BlockSignature::sign(&private_key, &signing_hash).unwrap();
+        block.update_consensus_data(ConsensusData::SignedBlock(SignedBlockData::new(vec![
+            signature.clone(),
+        ])));
+        assert_eq!(block.header().signing_hash(), signing_hash);
+
+        assert_eq!(signature.public_key(), &public_key);
+        assert!(signature.verify(&signing_hash));
+        assert!(!signature.verify(&H256::from_low_u64_be(rng.gen())));
+    }
 }
@@ -0,0 +1,95 @@
+// Copyright (c) 2022 RBB S.r.l
+// opensource@mintlayer.org
+// SPDX-License-Identifier: MIT
+// Licensed under the MIT License;
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://github.com/mintlayer/mintlayer-core/blob/master/LICENSE
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crypto::key::PublicKey;
+
+/// The keys allowed to sign blocks on a chain using the signed blocks consensus, and the number of
+/// them that have to sign each block
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlockSigners {
+    public_keys: Vec<PublicKey>,
+    threshold: usize,
+}
+
+impl BlockSigners {
+    pub fn new(public_keys: Vec<PublicKey>, threshold: usize) -> anyhow::Result<Self> {
+        if threshold == 0 || threshold > public_keys.len() {
+            return Err(anyhow::Error::msg(format!(
+                "Block signers threshold must be between 1 and {}, got {threshold}",
+                public_keys.len()
+            )));
+        }
+        if public_keys.iter().enumerate().any(|(i, key)| public_keys[..i].contains(key)) {
+            return Err(anyhow::Error::msg(
+                "Block signers must not contain duplicate keys",
+            ));
+        }
+
+        Ok(Self {
+            public_keys,
+            threshold,
+        })
+    }
+
+    /// A single key signing every block
+    pub fn single(public_key: PublicKey) -> Self {
+        Self {
+            public_keys: vec![public_key],
+            threshold: 1,
+        }
+    }
+
+    pub fn public_keys(&self) -> &[PublicKey] {
+        &self.public_keys
+    }
+
+    pub fn threshold(&self) -> usize {
+        self.threshold
+    }
+
+    pub fn is_signer(&self, public_key: &PublicKey) -> bool {
+        self.public_keys.contains(public_key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crypto::key::{KeyKind, PrivateKey};
+
+    fn public_key() -> PublicKey {
+        PrivateKey::new_from_entropy(KeyKind::RistrettoSchnorr).1
+    }
+
+    #[test]
+    fn threshold_bounds() {
+        let keys = vec![public_key(), public_key(), public_key()];
+
+        assert!(BlockSigners::new(keys.clone(), 0).is_err());
+        assert!(BlockSigners::new(keys.clone(), 4).is_err());
+        assert!(BlockSigners::new(vec![], 1).is_err());
+
+        let signers = BlockSigners::new(keys.clone(), 2).unwrap();
+        assert_eq!(signers.threshold(), 2);
+        assert!(keys.iter().all(|key| signers.is_signer(key)));
+        assert!(!signers.is_signer(&public_key()));
+    }
+
+    #[test]
+    fn duplicate_keys() {
+        let key = public_key();
+        assert!(BlockSigners::new(vec![key.clone(), public_key(), key], 1).is_err());
+    }
+}
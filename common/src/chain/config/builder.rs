@@ -14,7 +14,10 @@
 // limitations under the License.
 
 use super::emission_schedule::{self, *};
-use super::{create_mainnet_genesis, create_unit_test_genesis, ChainConfig, ChainType};
+use super::{
+    create_mainnet_genesis, create_testnet_genesis, create_unit_test_genesis, BlockSigners,
+    ChainConfig, ChainType,
+};
 
 use crate::chain::{
     ConsensusUpgrade, Destination, Genesis, Mlt, NetUpgrades, PoWChainConfig, UpgradeVersion,
@@ -30,7 +33,7 @@ impl ChainType {
     fn default_genesis_init(&self) -> GenesisBlockInit {
         match self {
             ChainType::Mainnet => GenesisBlockInit::Mainnet,
-            ChainType::Testnet => todo!("Testnet genesis"),
+            ChainType::Testnet => GenesisBlockInit::Testnet,
             ChainType::Regtest => GenesisBlockInit::TEST,
             ChainType::Signet => GenesisBlockInit::TEST,
         }
@@ -38,7 +41,7 @@ impl ChainType {
 
     fn default_net_upgrades(&self) -> NetUpgrades<UpgradeVersion> {
         match self {
-            ChainType::Mainnet | ChainType::Regtest => {
+            ChainType::Mainnet | ChainType::Testnet | ChainType::Regtest => {
                 let pow_config = PoWChainConfig::new(*self);
                 let upgrades = vec![
                     (
@@ -54,10 +57,18 @@ impl ChainType {
                 ];
                 NetUpgrades::initialize(upgrades).expect("net upgrades")
             }
-            ChainType::Testnet => todo!("Testnet upgrades"),
             ChainType::Signet => NetUpgrades::unit_tests(),
         }
     }
+
+    fn default_emission_schedule_init(&self) -> EmissionScheduleInit {
+        match self {
+            ChainType::Testnet => EmissionScheduleInit::Testnet,
+            ChainType::Mainnet | ChainType::Regtest | ChainType::Signet => {
+                EmissionScheduleInit::Mainnet
+            }
+        }
+    }
 }
 
 // Builder support types
@@ -65,6 +76,7 @@ impl ChainType {
 #[derive(Clone)]
 enum EmissionScheduleInit {
     Mainnet,
+    Testnet,
     Table(emission_schedule::EmissionScheduleTabular),
     Fn(std::sync::Arc<emission_schedule::EmissionScheduleFn>),
 }
@@ -73,6 +85,7 @@ enum EmissionScheduleInit {
 enum GenesisBlockInit {
     UnitTest { premine_destination: Destination },
     Mainnet,
+    Testnet,
     Custom(Genesis),
 }
 
@@ -107,6 +120,7 @@ pub struct Builder {
     token_min_hash_len: usize,
     token_max_hash_len: usize,
     empty_consensus_reward_maturity_distance: BlockDistance,
+    block_signers: Option<BlockSigners>,
 }
 
 impl Builder {
@@ -124,7 +138,7 @@ impl Builder {
             max_future_block_time_offset: super::DEFAULT_MAX_FUTURE_BLOCK_TIME_OFFSET,
             target_block_spacing: super::DEFAULT_TARGET_BLOCK_SPACING,
             genesis_block: chain_type.default_genesis_init(),
-            emission_schedule: EmissionScheduleInit::Mainnet,
+            emission_schedule: chain_type.default_emission_schedule_init(),
             net_upgrades: chain_type.default_net_upgrades(),
             token_min_issuance_fee: super::TOKEN_MIN_ISSUANCE_FEE,
             token_max_uri_len: super::TOKEN_MAX_URI_LEN,
@@ -135,6 +149,7 @@ impl Builder {
             token_min_hash_len: super::TOKEN_MIN_HASH_LEN,
             token_max_hash_len: super::TOKEN_MAX_HASH_LEN,
             empty_consensus_reward_maturity_distance: BlockDistance::new(0),
+            block_signers: None,
         }
     }
 
@@ -170,6 +185,7 @@ impl Builder {
             token_min_hash_len,
             token_max_hash_len,
             empty_consensus_reward_maturity_distance,
+            block_signers,
         } = self;
 
         let emission_schedule = match emission_schedule {
@@ -178,10 +194,14 @@ impl Builder {
             EmissionScheduleInit::Mainnet => {
                 emission_schedule::mainnet_schedule_table(target_block_spacing).schedule()
             }
+            EmissionScheduleInit::Testnet => {
+                emission_schedule::testnet_schedule_table(target_block_spacing).schedule()
+            }
         };
 
         let genesis_block = match genesis_block {
             GenesisBlockInit::Mainnet => create_mainnet_genesis(),
+            GenesisBlockInit::Testnet => create_testnet_genesis(),
             GenesisBlockInit::Custom(genesis) => genesis,
             GenesisBlockInit::UnitTest {
                 premine_destination,
@@ -213,6 +233,7 @@ impl Builder {
             token_max_description_len,
             token_min_hash_len,
             token_max_hash_len,
+            block_signers,
         }
     }
 }
@@ -242,6 +263,12 @@ impl Builder {
     builder_method!(net_upgrades: NetUpgrades<UpgradeVersion>);
     builder_method!(empty_consensus_reward_maturity_distance: BlockDistance);
 
+    /// Set the keys that have to sign blocks when the signed blocks consensus is active
+    pub fn block_signers(mut self, block_signers: BlockSigners) -> Self {
+        self.block_signers = Some(block_signers);
+        self
+    }
+
     /// Set the genesis block to be the unit test version
     pub fn genesis_unittest(mut self, premine_destination: Destination) -> Self {
         self.genesis_block = GenesisBlockInit::UnitTest {
@@ -256,6 +283,12 @@ impl Builder {
         self
     }
 
+    /// Set genesis block to be the testnet genesis
+    pub fn genesis_testnet(mut self) -> Self {
+        self.genesis_block = GenesisBlockInit::Testnet;
+        self
+    }
+
     /// Specify a custom genesis block
     pub fn genesis_custom(mut self, genesis: Genesis) -> Self {
         self.genesis_block = GenesisBlockInit::Custom(genesis);
@@ -268,6 +301,12 @@ impl Builder {
         self
     }
 
+    /// Set emission schedule to the testnet schedule
+    pub fn emission_schedule_testnet(mut self) -> Self {
+        self.emission_schedule = EmissionScheduleInit::Testnet;
+        self
+    }
+
     /// Initialize an emission schedule using a table
     pub fn emission_schedule_tabular(mut self, es: EmissionScheduleTabular) -> Self {
         self.emission_schedule = EmissionScheduleInit::Table(es);
@@ -268,6 +268,7 @@ impl FromStr for EmissionScheduleTabular {
 // Emission schedule for mainnet
 
 pub const MAINNET_COIN_PREMINE: Mlt = Mlt::from_mlt(400_000_000);
+pub const TESTNET_COIN_PREMINE: Mlt = Mlt::from_mlt(400_000_000);
 
 pub fn mainnet_schedule_table(block_interval: Duration) -> EmissionScheduleTabular {
     // Check block interval is in whole seconds
@@ -286,6 +287,25 @@ pub fn mainnet_schedule_table(block_interval: Duration) -> EmissionScheduleTabul
     EmissionScheduleTabular::new(MAINNET_COIN_PREMINE, initial_subsidy, rewards)
 }
 
+/// Same subsidies as mainnet, but the subsidy steps down every month instead of every year, so that
+/// the whole schedule can be exercised on the testnet in a reasonable time
+pub fn testnet_schedule_table(block_interval: Duration) -> EmissionScheduleTabular {
+    // Check block interval is in whole seconds
+    assert!(
+        (block_interval.as_nanos() % 1_000_000_000) == 0,
+        "Block interval supported up to the resolution of 1 sec"
+    );
+
+    // Number of blocks emitted per 30 days
+    let blocks_per_month: u64 = (30 * 24 * 60 * 60) / block_interval.as_secs();
+    let months = (1..).map(|x| BlockHeight::new(blocks_per_month * x));
+    let initial_subsidy = Mlt::from_mlt(202);
+    let subsequent_subsidies =
+        [151, 113, 85, 64, 48, 36, 27, 20, 15, 0].iter().map(|x| Mlt::from_mlt(*x));
+    let rewards = months.zip(subsequent_subsidies).collect();
+    EmissionScheduleTabular::new(TESTNET_COIN_PREMINE, initial_subsidy, rewards)
+}
+
 #[cfg(test)]
 mod tests {
     use crate::primitives::Amount;
@@ -326,6 +346,19 @@ mod tests {
         );
     }
 
+    #[test]
+    fn testnet_schedule_display() {
+        let table = testnet_schedule_table(crate::chain::config::DEFAULT_TARGET_BLOCK_SPACING);
+        assert_eq!(
+            &format!("{table}"),
+            concat!(
+                "400000000+202,",
+                "21600:+151,43200:+113,64800:+85,86400:+64,108000:+48,",
+                "129600:+36,151200:+27,172800:+20,194400:+15,216000:+0",
+            )
+        )
+    }
+
     proptest! {
         #[test]
         fn table_parser_nocrash(input: String) {
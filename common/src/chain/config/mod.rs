@@ -13,8 +13,10 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+mod block_signers;
 mod builder;
 pub mod emission_schedule;
+pub use block_signers::BlockSigners;
 pub use builder::Builder;
 pub use emission_schedule::{EmissionSchedule, EmissionScheduleTabular};
 
@@ -90,6 +92,7 @@ pub struct ChainConfig {
     token_min_hash_len: usize,
     token_max_hash_len: usize,
     empty_consensus_reward_maturity_distance: BlockDistance,
+    block_signers: Option<BlockSigners>,
 }
 
 impl ChainConfig {
@@ -197,6 +200,11 @@ impl ChainConfig {
         self.empty_consensus_reward_maturity_distance
     }
 
+    /// The keys that sign blocks when the signed blocks consensus is active
+    pub fn block_signers(&self) -> Option<&BlockSigners> {
+        self.block_signers.as_ref()
+    }
+
     // TODO: this should be part of net-upgrades. There should be no canonical definition of PoW for any chain config
     pub const fn get_proof_of_work_config(&self) -> PoWChainConfig {
         PoWChainConfig::new(self.chain_type)
@@ -246,6 +254,35 @@ fn create_mainnet_genesis() -> Genesis {
     )
 }
 
+fn create_testnet_genesis() -> Genesis {
+    use crate::chain::transaction::TxOutput;
+
+    // The key is public on purpose, so that anyone testing can spend the premine
+    // Private key: "0080732e24bb0b704cb455e233b539f2c63ab411989a54984f84a6a2eb2e933e160f"
+    // Public key:  "008090f5aee58be97ce2f7c014fa97ffff8c459a0c491f8124950724a187d134e25c"
+    let genesis_mint_pubkey_hex_encoded =
+        "008090f5aee58be97ce2f7c014fa97ffff8c459a0c491f8124950724a187d134e25c";
+    let genesis_mint_pubkey_encoded = Vec::from_hex(genesis_mint_pubkey_hex_encoded)
+        .expect("Hex decoding of pubkey shouldn't fail");
+    let genesis_mint_pubkey = <crypto::key::PublicKey as serialization::DecodeAll>::decode_all(
+        &mut genesis_mint_pubkey_encoded.as_slice(),
+    )
+    .expect("Decoding genesis mint pubkey shouldn't fail");
+
+    let genesis_message = "Mintlayer testnet".to_string();
+
+    let output = TxOutput::new(
+        OutputValue::Coin(emission_schedule::TESTNET_COIN_PREMINE.to_amount_atoms()),
+        OutputPurpose::Transfer(Destination::PublicKey(genesis_mint_pubkey)),
+    );
+
+    Genesis::new(
+        genesis_message,
+        BlockTimestamp::from_int_seconds(1666094400),
+        vec![output],
+    )
+}
+
 fn create_unit_test_genesis(premine_destination: Destination) -> Genesis {
     use crate::chain::transaction::TxOutput;
 
@@ -267,6 +304,10 @@ pub fn create_mainnet() -> ChainConfig {
     Builder::new(ChainType::Mainnet).build()
 }
 
+pub fn create_testnet() -> ChainConfig {
+    Builder::new(ChainType::Testnet).build()
+}
+
 pub fn create_regtest() -> ChainConfig {
     Builder::new(ChainType::Regtest).build()
 }
@@ -291,6 +332,20 @@ mod tests {
         assert_eq!(config.chain_type(), &ChainType::Mainnet);
     }
 
+    #[test]
+    fn testnet_creation() {
+        let config = create_testnet();
+
+        assert_eq!(2, config.net_upgrades.len());
+        assert_eq!(config.chain_type(), &ChainType::Testnet);
+        assert_eq!(config.address_prefix(), "tmt");
+        assert_ne!(config.magic_bytes(), create_mainnet().magic_bytes());
+        assert_ne!(
+            config.genesis_block_id(),
+            create_mainnet().genesis_block_id()
+        );
+    }
+
     #[test]
     fn different_magic_bytes() {
         let config1 = Builder::new(ChainType::Regtest).build();
@@ -93,12 +93,18 @@ const fn allow_min_difficulty_blocks(chain_type: ChainType) -> bool {
 
 pub(crate) const fn limit(chain_type: ChainType) -> Uint256 {
     match chain_type {
-        ChainType::Mainnet | ChainType::Testnet => Uint256([
+        ChainType::Mainnet => Uint256([
             0xFFFFFFFFFFFFFFFF,
             0xFFFFFFFFFFFFFFFF,
             0xFFFFFFFFFFFFFFFF,
             0x00000000FFFFFFFF,
         ]),
+        ChainType::Testnet => Uint256([
+            0xFFFFFFFFFFFFFFFF,
+            0xFFFFFFFFFFFFFFFF,
+            0xFFFFFFFFFFFFFFFF,
+            0x000000FFFFFFFFFF,
+        ]),
         ChainType::Signet => Uint256([
             0x0000000000000000,
             0x0000000000000000,
@@ -116,7 +122,7 @@ pub(crate) const fn limit(chain_type: ChainType) -> Uint256 {
 
 #[cfg(test)]
 mod tests {
-    use crate::chain::config::{create_mainnet, ChainType};
+    use crate::chain::config::{create_mainnet, create_testnet, ChainType};
     use crate::chain::pow::{allow_min_difficulty_blocks, limit, no_retargeting};
     use crate::Uint256;
 
@@ -157,4 +163,27 @@ mod tests {
             assert!(mainnet_cfg.limit() < target_max);
         }
     }
+
+    #[test]
+    fn check_testnet_powconfig() {
+        let cfg = create_testnet();
+
+        let testnet_cfg = cfg.get_proof_of_work_config();
+
+        assert_eq!(testnet_cfg.limit(), limit(ChainType::Testnet));
+        assert!(testnet_cfg.limit() > limit(ChainType::Mainnet));
+
+        assert!(!testnet_cfg.no_retargeting());
+        assert!(testnet_cfg.allow_min_difficulty_blocks());
+
+        let target_max = Uint256([
+            0xFFFFFFFFFFFFFFFF,
+            0xFFFFFFFFFFFFFFFF,
+            0xFFFFFFFFFFFFFFFF,
+            0xFFFFFFFFFFFFFFFF,
+        ]);
+        let target_max =
+            target_max / Uint256::from_u64(testnet_cfg.target_timespan().as_secs() * 4);
+        assert!(testnet_cfg.limit() < target_max);
+    }
 }
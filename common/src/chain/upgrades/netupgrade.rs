@@ -65,6 +65,8 @@ pub enum ConsensusUpgrade {
     PoW { initial_difficulty: Compact },
     PoS,
     DSA,
+    // Blocks must be signed by the block signers of the chain config
+    SignedBlocks,
     IgnoreConsensus,
 }
 
@@ -74,6 +76,8 @@ pub enum RequiredConsensus {
     PoW(PoWStatus),
     PoS,
     DSA,
+    // Blocks must be signed by the block signers of the chain config
+    SignedBlocks,
     IgnoreConsensus,
 }
 
@@ -91,6 +95,7 @@ impl From<ConsensusUpgrade> for RequiredConsensus {
             }
             ConsensusUpgrade::PoS => RequiredConsensus::PoS,
             ConsensusUpgrade::DSA => RequiredConsensus::DSA,
+            ConsensusUpgrade::SignedBlocks => RequiredConsensus::SignedBlocks,
             ConsensusUpgrade::IgnoreConsensus => RequiredConsensus::IgnoreConsensus,
         }
     }
@@ -169,6 +174,7 @@ impl NetUpgrades<UpgradeVersion> {
             }
             ConsensusUpgrade::PoS => RequiredConsensus::PoS,
             ConsensusUpgrade::DSA => RequiredConsensus::DSA,
+            ConsensusUpgrade::SignedBlocks => RequiredConsensus::SignedBlocks,
             ConsensusUpgrade::IgnoreConsensus => RequiredConsensus::IgnoreConsensus,
         }
     }
@@ -0,0 +1,83 @@
+// Copyright (c) 2022 RBB S.r.l
+// opensource@mintlayer.org
+// SPDX-License-Identifier: MIT
+// Licensed under the MIT License;
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://github.com/mintlayer/mintlayer-core/blob/master/LICENSE
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use common::address::{pubkeyhash::PublicKeyHash, Address, AddressError};
+use common::chain::config::{create_mainnet, create_testnet};
+use common::chain::Destination;
+use common::primitives::{Amount, Idable};
+use crypto::key::{KeyKind, PrivateKey, PublicKey};
+use expect_test::expect;
+use serialization::DecodeAll;
+
+#[test]
+fn testnet_genesis_id() {
+    let config = create_testnet();
+
+    expect![[r#"
+        0x5a0a7ce362ea81e93e794c693626f2a88b7a751b42a17bbcf255bfef59be7859
+    "#]]
+    .assert_debug_eq(&config.genesis_block().get_id().get());
+}
+
+#[test]
+fn testnet_genesis_premine() {
+    let config = create_testnet();
+    let genesis = config.genesis_block();
+
+    // The premine is spendable with the published testnet key
+    let private_key =
+        hex::decode("0080732e24bb0b704cb455e233b539f2c63ab411989a54984f84a6a2eb2e933e160f")
+            .unwrap();
+    let private_key = PrivateKey::decode_all(&mut private_key.as_slice()).unwrap();
+    let public_key = PublicKey::from_private_key(&private_key);
+
+    assert_eq!(genesis.utxos().len(), 1);
+    let output = &genesis.utxos()[0];
+    assert_eq!(
+        output.purpose().destination(),
+        Some(&Destination::PublicKey(public_key))
+    );
+    assert_eq!(
+        output.value().coin_amount(),
+        Some(Amount::from_fixedpoint_str("400000000", config.coin_decimals()).unwrap())
+    );
+}
+
+#[test]
+fn testnet_address_roundtrip() {
+    let testnet = create_testnet();
+    let mainnet = create_mainnet();
+
+    let (_private_key, public_key) = PrivateKey::new_from_entropy(KeyKind::RistrettoSchnorr);
+    let public_key_hash = PublicKeyHash::from(&public_key);
+
+    let address = Address::from_public_key(&testnet, &public_key).unwrap();
+    assert!(address.get().starts_with("tmt1"));
+
+    let restored = PublicKeyHash::try_from(address.data(&testnet).unwrap()).unwrap();
+    assert_eq!(restored, public_key_hash);
+
+    // A testnet address is not valid on mainnet and vice versa
+    assert_eq!(
+        address.data(&mainnet),
+        Err(AddressError::InvalidPrefix("tmt".to_owned()))
+    );
+    let mainnet_address = Address::from_public_key(&mainnet, &public_key).unwrap();
+    assert_ne!(mainnet_address, address);
+    assert_eq!(
+        mainnet_address.data(&testnet),
+        Err(AddressError::InvalidPrefix("mtc".to_owned()))
+    );
+}
@@ -21,7 +21,7 @@ use common::{
     primitives::Id,
 };
 
-use crate::ConsensusPoWError;
+use crate::{ConsensusPoWError, ConsensusSignedBlockError};
 
 /// A consensus related error.
 #[derive(Error, Debug, PartialEq, Eq, Clone)]
@@ -36,6 +36,8 @@ pub enum ConsensusVerificationError {
     ConsensusTypeMismatch(String),
     #[error("PoW error: {0}")]
     PoWError(ConsensusPoWError),
+    #[error("Signed block error: {0}")]
+    SignedBlockError(ConsensusSignedBlockError),
     #[error("Unsupported consensus type")]
     UnsupportedConsensusType,
 }
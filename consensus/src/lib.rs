@@ -20,8 +20,10 @@ pub mod pow;
 pub use crate::{
     error::ConsensusVerificationError,
     pow::ConsensusPoWError,
+    signed_block::ConsensusSignedBlockError,
     validator::{validate_consensus, TransactionIndexHandle},
 };
 
 mod error;
+mod signed_block;
 mod validator;
@@ -16,7 +16,7 @@
 pub use self::{
     error::ConsensusPoWError,
     work::mine,
-    work::{check_pow_consensus, check_proof_of_work},
+    work::{calculate_work_required_for_new_block, check_pow_consensus, check_proof_of_work},
 };
 
 mod error;
@@ -15,7 +15,7 @@
 
 #![allow(dead_code)]
 
-use chainstate_types::{BlockIndex, BlockIndexHandle};
+use chainstate_types::{BlockIndex, BlockIndexHandle, GenBlockIndex};
 use common::{
     chain::{
         block::consensus_data::PoWData,
@@ -67,36 +67,70 @@ fn calculate_work_required<H: BlockIndexHandle>(
     pow_status: &PoWStatus,
     block_index_handle: &H,
 ) -> Result<Compact, ConsensusPoWError> {
-    match pow_status {
-        PoWStatus::Threshold { initial_difficulty } => Ok(*initial_difficulty),
-        PoWStatus::Ongoing => {
+    work_required(
+        chain_config,
+        header.timestamp(),
+        pow_status,
+        block_index_handle,
+        || {
             let prev_block_id = header
                 .prev_block_id()
                 .classify(chain_config)
                 .chain_block_id()
                 .expect("If PoWStatus is `Ongoing` then we cannot be at genesis");
 
-            let prev_block_index = match block_index_handle.get_block_index(&prev_block_id) {
-                Ok(id) => id,
-                Err(err) => {
-                    return Err(ConsensusPoWError::PrevBlockLoadError(
-                        prev_block_id,
-                        header.get_id(),
-                        err,
-                    ))
-                }
-            };
+            let prev_block_index =
+                block_index_handle.get_block_index(&prev_block_id).map_err(|err| {
+                    ConsensusPoWError::PrevBlockLoadError(prev_block_id, header.get_id(), err)
+                })?;
 
-            let prev_block_index = prev_block_index.ok_or_else(|| {
-                ConsensusPoWError::PrevBlockNotFound(prev_block_id, header.get_id())
-            })?;
+            prev_block_index
+                .ok_or_else(|| ConsensusPoWError::PrevBlockNotFound(prev_block_id, header.get_id()))
+        },
+    )
+}
 
-            PoW::new(chain_config).get_work_required(
-                &prev_block_index,
-                header.timestamp(),
-                block_index_handle,
-            )
-        }
+/// Calculates the bits required for a new block with the given timestamp on top of
+/// `prev_block_index`, before the block itself exists (e.g. to build a block template)
+pub fn calculate_work_required_for_new_block<H: BlockIndexHandle>(
+    chain_config: &ChainConfig,
+    prev_block_index: &GenBlockIndex,
+    new_block_time: BlockTimestamp,
+    pow_status: &PoWStatus,
+    block_index_handle: &H,
+) -> Result<Compact, ConsensusPoWError> {
+    work_required(
+        chain_config,
+        new_block_time,
+        pow_status,
+        block_index_handle,
+        || match prev_block_index {
+            GenBlockIndex::Block(prev_block_index) => Ok(prev_block_index.clone()),
+            GenBlockIndex::Genesis(_) => Err(ConsensusPoWError::NoPowDataInPreviousBlock),
+        },
+    )
+}
+
+// The previous block index is only needed once the proof of work is ongoing, so it is only obtained
+// from `get_prev_block_index` then
+fn work_required<H, F>(
+    chain_config: &ChainConfig,
+    new_block_time: BlockTimestamp,
+    pow_status: &PoWStatus,
+    block_index_handle: &H,
+    get_prev_block_index: F,
+) -> Result<Compact, ConsensusPoWError>
+where
+    H: BlockIndexHandle,
+    F: FnOnce() -> Result<BlockIndex, ConsensusPoWError>,
+{
+    match pow_status {
+        PoWStatus::Threshold { initial_difficulty } => Ok(*initial_difficulty),
+        PoWStatus::Ongoing => PoW::new(chain_config).get_work_required(
+            &get_prev_block_index()?,
+            new_block_time,
+            block_index_handle,
+        ),
     }
 }
 
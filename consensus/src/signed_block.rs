@@ -0,0 +1,189 @@
+// Copyright (c) 2021-2022 RBB S.r.l
+// opensource@mintlayer.org
+// SPDX-License-Identifier: MIT
+// Licensed under the MIT License;
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://github.com/mintlayer/mintlayer-core/blob/master/LICENSE
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::BTreeSet;
+
+use thiserror::Error;
+
+use common::{
+    chain::{
+        block::{consensus_data::SignedBlockData, Block, BlockHeader},
+        config::ChainConfig,
+    },
+    primitives::{Id, Idable},
+};
+
+/// A signed blocks consensus error.
+#[derive(Error, Debug, PartialEq, Eq, Clone)]
+pub enum ConsensusSignedBlockError {
+    #[error("Signed blocks consensus is active but the chain config has no block signers")]
+    NoBlockSigners,
+    #[error("Block {0} is signed by a key that isn't a block signer")]
+    UnknownSigner(Id<Block>),
+    #[error("Block {0} is signed more than once by the same key")]
+    DuplicateSigner(Id<Block>),
+    #[error("Invalid signature in block {0}")]
+    InvalidSignature(Id<Block>),
+    #[error("Block {0} has {1} signatures but {2} are required")]
+    NotEnoughSignatures(Id<Block>, usize, usize),
+}
+
+/// Checks that enough of the configured block signers have signed the header
+pub fn check_signed_block_consensus(
+    chain_config: &ChainConfig,
+    header: &BlockHeader,
+    data: &SignedBlockData,
+) -> Result<(), ConsensusSignedBlockError> {
+    let signers = chain_config.block_signers().ok_or(ConsensusSignedBlockError::NoBlockSigners)?;
+    let signing_hash = header.signing_hash();
+
+    let mut seen = BTreeSet::new();
+    for signature in data.signatures() {
+        if !signers.is_signer(signature.public_key()) {
+            return Err(ConsensusSignedBlockError::UnknownSigner(header.get_id()));
+        }
+        if !seen.insert(signature.public_key()) {
+            return Err(ConsensusSignedBlockError::DuplicateSigner(header.get_id()));
+        }
+        if !signature.verify(&signing_hash) {
+            return Err(ConsensusSignedBlockError::InvalidSignature(header.get_id()));
+        }
+    }
+
+    if seen.len() < signers.threshold() {
+        return Err(ConsensusSignedBlockError::NotEnoughSignatures(
+            header.get_id(),
+            seen.len(),
+            signers.threshold(),
+        ));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common::{
+        chain::{
+            block::{
+                consensus_data::BlockSignature, timestamp::BlockTimestamp, BlockReward,
+                ConsensusData,
+            },
+            config::{BlockSigners, Builder},
+        },
+        primitives::H256,
+    };
+    use crypto::key::{KeyKind, PrivateKey, PublicKey};
+
+    fn make_header(keys: &[PrivateKey]) -> BlockHeader {
+        make_header_at(keys, 1)
+    }
+
+    fn make_header_at(keys: &[PrivateKey], timestamp: u64) -> BlockHeader {
+        let mut block = Block::new(
+            Vec::new(),
+            Id::new(H256::zero()),
+            BlockTimestamp::from_int_seconds(timestamp),
+            ConsensusData::None,
+            BlockReward::new(Vec::new()),
+        )
+        .unwrap();
+        let signing_hash = block.header().signing_hash();
+        let signatures = keys
+            .iter()
+            .map(|key| BlockSignature::sign(key, &signing_hash).unwrap())
+            .collect();
+        block.update_consensus_data(ConsensusData::SignedBlock(SignedBlockData::new(signatures)));
+        block.header().clone()
+    }
+
+    fn check(
+        chain_config: &ChainConfig,
+        header: &BlockHeader,
+    ) -> Result<(), ConsensusSignedBlockError> {
+        match header.consensus_data() {
+            ConsensusData::SignedBlock(data) => {
+                check_signed_block_consensus(chain_config, header, data)
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn threshold_signatures() {
+        let keys: Vec<_> = (0..3)
+            .map(|_| PrivateKey::new_from_entropy(KeyKind::RistrettoSchnorr).0)
+            .collect();
+        let signers =
+            BlockSigners::new(keys.iter().map(PublicKey::from_private_key).collect(), 2).unwrap();
+        let chain_config = Builder::test_chain().block_signers(signers).build();
+
+        assert_eq!(check(&chain_config, &make_header(&keys[..2])), Ok(()));
+        assert_eq!(check(&chain_config, &make_header(&keys)), Ok(()));
+
+        let header = make_header(&keys[..1]);
+        assert_eq!(
+            check(&chain_config, &header),
+            Err(ConsensusSignedBlockError::NotEnoughSignatures(
+                header.get_id(),
+                1,
+                2
+            ))
+        );
+
+        let header = make_header(&[keys[0].clone(), keys[0].clone()]);
+        assert_eq!(
+            check(&chain_config, &header),
+            Err(ConsensusSignedBlockError::DuplicateSigner(header.get_id()))
+        );
+
+        let stranger = PrivateKey::new_from_entropy(KeyKind::RistrettoSchnorr).0;
+        let header = make_header(&[keys[0].clone(), stranger]);
+        assert_eq!(
+            check(&chain_config, &header),
+            Err(ConsensusSignedBlockError::UnknownSigner(header.get_id()))
+        );
+    }
+
+    #[test]
+    fn signature_of_another_block() {
+        let key = PrivateKey::new_from_entropy(KeyKind::RistrettoSchnorr).0;
+        let signers = BlockSigners::single(PublicKey::from_private_key(&key));
+        let chain_config = Builder::test_chain().block_signers(signers).build();
+
+        let signed_header = make_header_at(&[key.clone()], 1);
+        let header = make_header_at(&[key], 2);
+        let data = match signed_header.consensus_data() {
+            ConsensusData::SignedBlock(data) => data,
+            _ => unreachable!(),
+        };
+        assert_eq!(
+            check_signed_block_consensus(&chain_config, &header, data),
+            Err(ConsensusSignedBlockError::InvalidSignature(header.get_id()))
+        );
+    }
+
+    #[test]
+    fn no_block_signers() {
+        let key = PrivateKey::new_from_entropy(KeyKind::RistrettoSchnorr).0;
+        let chain_config = Builder::test_chain().build();
+
+        assert_eq!(
+            check(&chain_config, &make_header(&[key])),
+            Err(ConsensusSignedBlockError::NoBlockSigners)
+        );
+    }
+}
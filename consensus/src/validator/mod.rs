@@ -27,7 +27,10 @@ use common::{
     primitives::Idable,
 };
 
-use crate::{error::ConsensusVerificationError, pow::check_pow_consensus};
+use crate::{
+    error::ConsensusVerificationError, pow::check_pow_consensus,
+    signed_block::check_signed_block_consensus,
+};
 
 /// Checks if the given block identified by the header contains the correct consensus data.  
 pub fn validate_consensus<H: BlockIndexHandle>(
@@ -59,7 +62,7 @@ fn validate_pow_consensus<H: BlockIndexHandle>(
     block_index_handle: &H,
 ) -> Result<(), ConsensusVerificationError> {
     match header.consensus_data() {
-        ConsensusData::None | ConsensusData::PoS(_) => {
+        ConsensusData::None | ConsensusData::PoS(_) | ConsensusData::SignedBlock(_) => {
             Err(ConsensusVerificationError::ConsensusTypeMismatch(
                 "Chain configuration says we are PoW but block consensus data is not PoW.".into(),
             ))
@@ -74,7 +77,7 @@ fn validate_pow_consensus<H: BlockIndexHandle>(
 fn validate_ignore_consensus(header: &BlockHeader) -> Result<(), ConsensusVerificationError> {
     match header.consensus_data() {
         ConsensusData::None => Ok(()),
-        ConsensusData::PoW(_)|ConsensusData::PoS(_) => Err(ConsensusVerificationError::ConsensusTypeMismatch(
+        ConsensusData::PoW(_)|ConsensusData::PoS(_)|ConsensusData::SignedBlock(_) => Err(ConsensusVerificationError::ConsensusTypeMismatch(
             "Chain configuration says consensus should be empty but block consensus data is not `None`.".into(),
         )),
     }
@@ -82,13 +85,30 @@ fn validate_ignore_consensus(header: &BlockHeader) -> Result<(), ConsensusVerifi
 
 fn validate_pos_consensus(header: &BlockHeader) -> Result<(), ConsensusVerificationError> {
     match header.consensus_data() {
-        ConsensusData::None | ConsensusData::PoW(_)=>  Err(ConsensusVerificationError::ConsensusTypeMismatch(
+        ConsensusData::None | ConsensusData::PoW(_) | ConsensusData::SignedBlock(_) =>  Err(ConsensusVerificationError::ConsensusTypeMismatch(
             "Chain configuration says consensus should be empty but block consensus data is not `None`.".into(),
         )),
         ConsensusData::PoS(_) => Ok(()),
     }
 }
 
+fn validate_signed_block_consensus(
+    chain_config: &ChainConfig,
+    header: &BlockHeader,
+) -> Result<(), ConsensusVerificationError> {
+    match header.consensus_data() {
+        ConsensusData::None | ConsensusData::PoW(_) | ConsensusData::PoS(_) => {
+            Err(ConsensusVerificationError::ConsensusTypeMismatch(
+                "Chain configuration says blocks must be signed but block consensus data is not a block signature.".into(),
+            ))
+        }
+        ConsensusData::SignedBlock(data) => {
+            check_signed_block_consensus(chain_config, header, data)
+                .map_err(ConsensusVerificationError::SignedBlockError)
+        }
+    }
+}
+
 fn do_validate<H: BlockIndexHandle>(
     chain_config: &ChainConfig,
     header: &BlockHeader,
@@ -102,5 +122,6 @@ fn do_validate<H: BlockIndexHandle>(
         RequiredConsensus::IgnoreConsensus => validate_ignore_consensus(header),
         RequiredConsensus::PoS => validate_pos_consensus(header),
         RequiredConsensus::DSA => Err(ConsensusVerificationError::UnsupportedConsensusType),
+        RequiredConsensus::SignedBlocks => validate_signed_block_consensus(chain_config, header),
     }
 }
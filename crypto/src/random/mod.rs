@@ -24,7 +24,7 @@ pub mod distributions {
 }
 
 pub mod rngs {
-    pub use rand::rngs::OsRng;
+    pub use rand::rngs::{OsRng, StdRng};
 }
 
 #[must_use]
@@ -31,3 +31,69 @@ pub const MAX_MEMPOOL_SIZE_BYTES: usize = 300_000_000;
 pub const DEFAULT_MEMPOOL_EXPIRY: Duration = Duration::new(336 * 60 * 60, 0);
 
 pub const ROLLING_FEE_DECAY_INTERVAL: Time = Duration::new(10, 0);
+
+pub const FEE_ESTIMATES_SAVE_INTERVAL: Time = Duration::new(10 * 60, 0);
+
+pub const MAX_PACKAGE_COUNT: usize = 25;
+
+pub const DEFAULT_MAX_ANCESTOR_COUNT: usize = 25;
+pub const DEFAULT_MAX_ANCESTOR_SIZE_BYTES: usize = 101_000;
+pub const DEFAULT_MAX_DESCENDANT_COUNT: usize = 25;
+pub const DEFAULT_MAX_DESCENDANT_SIZE_BYTES: usize = 101_000;
+
+/// Limits on the size of the in-mempool packages (a transaction together with its unconfirmed
+/// ancestors or descendants) that are enforced when a new transaction is admitted.
+/// Counts and sizes include the transaction itself.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PackageLimits {
+    pub max_ancestor_count: usize,
+    pub max_ancestor_size: usize,
+    pub max_descendant_count: usize,
+    pub max_descendant_size: usize,
+}
+
+impl Default for PackageLimits {
+    fn default() -> Self {
+        Self {
+            max_ancestor_count: DEFAULT_MAX_ANCESTOR_COUNT,
+            max_ancestor_size: DEFAULT_MAX_ANCESTOR_SIZE_BYTES,
+            max_descendant_count: DEFAULT_MAX_DESCENDANT_COUNT,
+            max_descendant_size: DEFAULT_MAX_DESCENDANT_SIZE_BYTES,
+        }
+    }
+}
+
+/// The mempool configuration
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MempoolConfig {
+    /// The limits on the unconfirmed ancestors and descendants of a transaction
+    pub package_limits: PackageLimits,
+}
+
+impl MempoolConfig {
+    /// Creates a new mempool configuration instance.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_package_limits(mut self, package_limits: PackageLimits) -> Self {
+        self.package_limits = package_limits;
+        self
+    }
+}
+
+/// The largest number of blocks the fee estimator can be asked to confirm a transaction within
+pub const FEE_ESTIMATOR_MAX_TARGET_BLOCKS: usize = 48;
+/// The fee rate of the lowest fee estimator bucket, in atoms per kilobyte
+pub const FEE_ESTIMATOR_MIN_BUCKET_FEE_RATE: u128 = 1_000;
+/// The fee rate above which all transactions fall into the highest fee estimator bucket
+pub const FEE_ESTIMATOR_MAX_BUCKET_FEE_RATE: u128 = 10_000_000_000;
+/// Each fee estimator bucket covers fee rates this many percent higher than the previous one
+pub const FEE_ESTIMATOR_BUCKET_SPACING_PERCENT: u128 = 10;
+/// The fee estimator statistics are multiplied by this fraction (in thousandths) for every new
+/// block, so that old data gradually loses its weight
+pub const FEE_ESTIMATOR_DECAY_PER_MILLE: u64 = 998;
+/// The number of data points (transactions) the fee estimator needs before trusting the
+/// statistics of a group of buckets
+pub const FEE_ESTIMATOR_SUFFICIENT_DATA_POINTS: u64 = 2;
+pub const FEE_ESTIMATOR_DEFAULT_CONFIDENCE_PERCENT: u8 = 95;
@@ -29,6 +29,12 @@ pub enum Error {
     MempoolFull,
     #[error(transparent)]
     TxValidationError(#[from] TxValidationError),
+    #[error(transparent)]
+    PackageValidationError(#[from] PackageValidationError),
+    #[error(transparent)]
+    FeeEstimationError(#[from] FeeEstimationError),
+    #[error("Fee delta overflow")]
+    FeeDeltaOverflow,
     #[error("Subsystem failure")]
     SubsystemFailure,
     #[error("Send error")]
@@ -85,6 +91,11 @@ pub enum TxValidationError {
     InsufficientFeesToRelayRBF,
     #[error("Rolling fee threshold not met.")]
     RollingFeeThresholdNotMet { minimum_fee: Amount, tx_fee: Amount },
+    #[error("Package fee {package_fee:?} is below the minimum fee {minimum_fee:?} required for the package.")]
+    PackageFeeTooLow {
+        package_fee: Amount,
+        minimum_fee: Amount,
+    },
     #[error("Overflow encountered while computing fee with ancestors")]
     AncestorFeeOverflow,
     #[error("Overflow encountered while updating ancestor fee.")]
@@ -95,6 +106,22 @@ pub enum TxValidationError {
     GetParentError,
     #[error("Transaction is a descendant of expired transaction.")]
     DescendantOfExpiredTransaction,
+    #[error("Transaction would have {count} in-mempool ancestors (including itself), exceeding the limit of {limit}.")]
+    TooManyAncestors { count: usize, limit: usize },
+    #[error("Transaction would have in-mempool ancestors of total size {size}, exceeding the limit of {limit}.")]
+    AncestorSizeLimitExceeded { size: usize, limit: usize },
+    #[error("Ancestor {ancestor} would have {count} in-mempool descendants (including itself), exceeding the limit of {limit}.")]
+    TooManyDescendants {
+        ancestor: Id<Transaction>,
+        count: usize,
+        limit: usize,
+    },
+    #[error("Ancestor {ancestor} would have in-mempool descendants of total size {size}, exceeding the limit of {limit}.")]
+    DescendantSizeLimitExceeded {
+        ancestor: Id<Transaction>,
+        size: usize,
+        limit: usize,
+    },
     #[error("Chainstate error")]
     ChainstateError(#[from] ChainstateError),
     #[error("Subsystem call error")]
@@ -102,3 +129,40 @@ pub enum TxValidationError {
     #[error("Internal Error.")]
     InternalError,
 }
+
+#[derive(Debug, Error)]
+pub enum PackageValidationError {
+    #[error("Package is empty.")]
+    EmptyPackage,
+    #[error("Package has {count} transactions, exceeding the limit of {limit}.")]
+    TooManyTransactions { count: usize, limit: usize },
+    #[error("Package contains transaction {0} more than once.")]
+    DuplicateTransaction(Id<Transaction>),
+    #[error("Transaction {child} appears in the package before its parent {parent}.")]
+    NotTopologicallySorted {
+        child: Id<Transaction>,
+        parent: Id<Transaction>,
+    },
+    #[error(
+        "Transaction {tx} double spends an input of transaction {conflict} of the same package."
+    )]
+    ConflictingTransactions {
+        tx: Id<Transaction>,
+        conflict: Id<Transaction>,
+    },
+    #[error(
+        "Transaction {tx} spends an output of transaction {replaced} which the package replaces."
+    )]
+    SpendsReplacedTransaction {
+        tx: Id<Transaction>,
+        replaced: Id<Transaction>,
+    },
+}
+
+#[derive(Debug, Error)]
+pub enum FeeEstimationError {
+    #[error("Confirmation target of {target} blocks is not between 1 and {max} blocks.")]
+    InvalidTarget { target: usize, max: usize },
+    #[error("Confidence of {0}% is not between 1% and 100%.")]
+    InvalidConfidence(u8),
+}
@@ -15,15 +15,25 @@
 
 use std::sync::Arc;
 
-use crate::{error::Error, tx_accumulator::TransactionAccumulator, MempoolEvent};
+use crate::{error::Error, tx_accumulator::TransactionAccumulator, FeeRate, MempoolEvent};
 use common::{
     chain::{signed_transaction::SignedTransaction, Transaction},
-    primitives::Id,
+    primitives::{amount::SignedAmount, Id},
 };
 
 #[async_trait::async_trait]
 pub trait MempoolInterface: Send + Sync {
     async fn add_transaction(&mut self, tx: SignedTransaction) -> Result<(), Error>;
+
+    // Validates and adds a package of transactions, sorted so that parents come before their
+    // children. The fee checks are applied to the package as a whole, so a parent that doesn't
+    // pay enough on its own can be accepted together with its children. Returns the result for
+    // each transaction of the package, in the same order.
+    async fn add_package(
+        &mut self,
+        txs: Vec<SignedTransaction>,
+    ) -> Result<Vec<Result<(), Error>>, Error>;
+
     async fn get_all(&self) -> Result<Vec<SignedTransaction>, Error>;
 
     // Returns `true` if the mempool contains a transaction with the given id, `false` otherwise.
@@ -34,6 +44,24 @@ pub trait MempoolInterface: Send + Sync {
         tx_accumulator: Box<dyn TransactionAccumulator + Send>,
     ) -> Result<Box<dyn TransactionAccumulator>, Error>;
 
+    // Adds `fee_delta` to the fee the mempool considers the given transaction to pay when
+    // choosing transactions for blocks or for eviction. The transaction doesn't need to be in the
+    // mempool yet. Returns the total fee delta of the transaction.
+    async fn prioritise_transaction(
+        &mut self,
+        tx_id: Id<Transaction>,
+        fee_delta: SignedAmount,
+    ) -> Result<SignedAmount, Error>;
+
+    // Returns the lowest fee rate at which at least `confidence_percent` percent of the recently
+    // seen transactions got confirmed within `target_blocks` blocks after entering the mempool,
+    // or `None` if there is not enough data yet.
+    async fn estimate_fee(
+        &self,
+        target_blocks: usize,
+        confidence_percent: u8,
+    ) -> Result<Option<FeeRate>, Error>;
+
     async fn subscribe_to_events(
         &mut self,
         handler: Arc<dyn Fn(MempoolEvent) + Send + Sync>,
@@ -13,23 +13,27 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::path::PathBuf;
 use std::sync::Arc;
 
 use super::mempool_interface_impl::mempool_method_call::MempoolMethodCall;
 use crate::error::Error;
 use crate::get_memory_usage::GetMemoryUsage;
 use crate::tx_accumulator::TransactionAccumulator;
+use crate::MempoolConfig;
 use crate::MempoolEvent;
 use crate::MempoolInterface;
 use chainstate::chainstate_interface::ChainstateInterface;
 use common::chain::signed_transaction::SignedTransaction;
 use common::chain::ChainConfig;
 use common::chain::Transaction;
+use common::primitives::amount::SignedAmount;
 use common::primitives::Id;
 use common::time_getter::TimeGetter;
 use tokio::sync::mpsc;
 
 pub use crate::SystemUsageEstimator;
+pub use pool::FeeRate;
 use pool::Mempool;
 
 mod mempool_method_call;
@@ -43,19 +47,25 @@ impl MempoolInterfaceImpl {
     pub fn new<M: GetMemoryUsage + Sync + Send + 'static>(
         chain_config: Arc<ChainConfig>,
         chainstate_handle: subsystem::Handle<Box<dyn ChainstateInterface>>,
+        mempool_config: MempoolConfig,
         time_getter: TimeGetter,
         memory_usage_estimator: M,
+        fee_estimates_path: Option<PathBuf>,
     ) -> Result<Self, crate::error::Error> {
         let (sender, receiver) = tokio::sync::mpsc::unbounded_channel();
 
-        Mempool::new(
+        let mempool = Mempool::new(
             chain_config,
             chainstate_handle,
             time_getter,
             memory_usage_estimator,
             receiver,
         )
-        .run()?;
+        .with_package_limits(mempool_config.package_limits);
+        match fee_estimates_path {
+            Some(path) => mempool.with_fee_estimates_file(path).run()?,
+            None => mempool.run()?,
+        }
 
         Ok(Self { sender })
     }
@@ -71,6 +81,17 @@ impl MempoolInterface for MempoolInterfaceImpl {
         rrx.await.map_err(|_| Error::RecvError)?
     }
 
+    async fn add_package(
+        &mut self,
+        txs: Vec<SignedTransaction>,
+    ) -> Result<Vec<Result<(), Error>>, Error> {
+        let (rtx, rrx) = tokio::sync::oneshot::channel();
+        self.sender
+            .send(MempoolMethodCall::AddPackage { txs, rtx })
+            .map_err(|_| Error::SendError)?;
+        rrx.await.map_err(|_| Error::RecvError)?
+    }
+
     async fn get_all(&self) -> Result<Vec<SignedTransaction>, Error> {
         let (rtx, rrx) = tokio::sync::oneshot::channel();
         self.sender
@@ -102,6 +123,38 @@ impl MempoolInterface for MempoolInterfaceImpl {
         rrx.await.map_err(|_| Error::RecvError)
     }
 
+    async fn prioritise_transaction(
+        &mut self,
+        tx_id: Id<Transaction>,
+        fee_delta: SignedAmount,
+    ) -> Result<SignedAmount, Error> {
+        let (rtx, rrx) = tokio::sync::oneshot::channel();
+        self.sender
+            .send(MempoolMethodCall::PrioritiseTransaction {
+                tx_id,
+                fee_delta,
+                rtx,
+            })
+            .map_err(|_| Error::SendError)?;
+        rrx.await.map_err(|_| Error::RecvError)?
+    }
+
+    async fn estimate_fee(
+        &self,
+        target_blocks: usize,
+        confidence_percent: u8,
+    ) -> Result<Option<FeeRate>, Error> {
+        let (rtx, rrx) = tokio::sync::oneshot::channel();
+        self.sender
+            .send(MempoolMethodCall::EstimateFee {
+                target_blocks,
+                confidence_percent,
+                rtx,
+            })
+            .map_err(|_| Error::SendError)?;
+        Ok(rrx.await.map_err(|_| Error::RecvError)??)
+    }
+
     async fn subscribe_to_events(
         &mut self,
         handler: Arc<dyn Fn(MempoolEvent) + Send + Sync>,
@@ -17,11 +17,12 @@ use tokio::sync::oneshot;
 
 use common::{
     chain::{signed_transaction::SignedTransaction, Transaction},
-    primitives::Id,
+    primitives::{amount::SignedAmount, Id},
 };
 use utils::eventhandler::EventHandler;
 
-use crate::error::Error;
+use super::FeeRate;
+use crate::error::{Error, FeeEstimationError};
 use crate::{tx_accumulator::TransactionAccumulator, MempoolEvent};
 
 pub type MempoolEventHandler = EventHandler<MempoolEvent>;
@@ -31,6 +32,10 @@ pub enum MempoolMethodCall {
         tx: SignedTransaction,
         rtx: oneshot::Sender<Result<(), Error>>,
     },
+    AddPackage {
+        txs: Vec<SignedTransaction>,
+        rtx: oneshot::Sender<Result<Vec<Result<(), Error>>, Error>>,
+    },
     GetAll {
         rtx: oneshot::Sender<Vec<SignedTransaction>>,
     },
@@ -42,6 +47,16 @@ pub enum MempoolMethodCall {
         tx_id: Id<Transaction>,
         rtx: oneshot::Sender<bool>,
     },
+    PrioritiseTransaction {
+        tx_id: Id<Transaction>,
+        fee_delta: SignedAmount,
+        rtx: oneshot::Sender<Result<SignedAmount, Error>>,
+    },
+    EstimateFee {
+        target_blocks: usize,
+        confidence_percent: u8,
+        rtx: oneshot::Sender<Result<Option<FeeRate>, FeeEstimationError>>,
+    },
     SubscribeToEvents {
         handler: MempoolEventHandler,
         rtx: oneshot::Sender<()>,
@@ -0,0 +1,382 @@
+// Copyright (c) 2022 RBB S.r.l
+// opensource@mintlayer.org
+// SPDX-License-Identifier: MIT
+// Licensed under the MIT License;
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://github.com/mintlayer/mintlayer-core/blob/master/LICENSE
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use common::chain::transaction::Transaction;
+use common::primitives::amount::Amount;
+use common::primitives::BlockHeight;
+use common::primitives::Id;
+use serialization::{Decode, Encode};
+
+use super::feerate::FeeRate;
+use crate::config::*;
+use crate::error::FeeEstimationError;
+
+// The weight of a single transaction in the statistics. Counts are kept as fixed-point numbers so
+// that they can be decayed without resorting to floating point arithmetic.
+const TX_WEIGHT: u64 = 1_000_000;
+
+// Bumped whenever the layout of the persisted statistics changes
+const STATS_VERSION: u32 = 1;
+
+// The fee rates at which the buckets start. Bucket `i` holds the transactions whose fee rate is at
+// least `bounds[i]` and below `bounds[i + 1]`. Transactions below the lowest bound are not tracked.
+fn bucket_bounds() -> Vec<FeeRate> {
+    let mut bounds = vec![FEE_ESTIMATOR_MIN_BUCKET_FEE_RATE];
+    loop {
+        let last = *bounds.last().expect("bounds not empty");
+        if last >= FEE_ESTIMATOR_MAX_BUCKET_FEE_RATE {
+            break;
+        }
+        let next = last + std::cmp::max(1, last * FEE_ESTIMATOR_BUCKET_SPACING_PERCENT / 100);
+        bounds.push(next);
+    }
+    bounds
+        .into_iter()
+        .map(|atoms| FeeRate::new(Amount::from_atoms(atoms)))
+        .collect()
+}
+
+fn decay(value: u64) -> u64 {
+    let decayed = u128::from(value) * u128::from(FEE_ESTIMATOR_DECAY_PER_MILLE) / 1000;
+    u64::try_from(decayed).expect("decayed value is smaller")
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Encode, Decode)]
+struct BucketStats {
+    // The (decayed) number of transactions that got confirmed within `i + 1` blocks after
+    // entering the mempool, for each target `i`
+    confirmed_within: Vec<u64>,
+    // The (decayed) number of transactions that got confirmed, regardless of how long it took
+    confirmed: u64,
+}
+
+impl BucketStats {
+    fn new() -> Self {
+        Self {
+            confirmed_within: vec![0; FEE_ESTIMATOR_MAX_TARGET_BLOCKS],
+            confirmed: 0,
+        }
+    }
+
+    fn record_confirmation(&mut self, blocks_to_confirm: usize) {
+        self.confirmed += TX_WEIGHT;
+        for confirmed_within in self.confirmed_within.iter_mut().skip(blocks_to_confirm - 1) {
+            *confirmed_within += TX_WEIGHT;
+        }
+    }
+
+    fn decay(&mut self) {
+        self.confirmed = decay(self.confirmed);
+        for confirmed_within in self.confirmed_within.iter_mut() {
+            *confirmed_within = decay(*confirmed_within);
+        }
+    }
+}
+
+// The part of the fee estimator state that survives restarts
+#[derive(Debug, Clone, PartialEq, Eq, Encode, Decode)]
+struct FeeStats {
+    version: u32,
+    best_height: BlockHeight,
+    buckets: Vec<BucketStats>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct TrackedTx {
+    entry_height: BlockHeight,
+    bucket: usize,
+}
+
+// Estimates the fee rate a transaction needs to pay to get confirmed within a given number of
+// blocks, based on how long it took the transactions seen by the mempool to get confirmed.
+//
+// This is a simplified version of Bitcoin Core's `CBlockPolicyEstimator`: transactions are
+// bucketed by fee rate when they enter the mempool, and once they get confirmed, the number of
+// blocks they waited for is recorded in their bucket. Transactions still waiting in the mempool
+// for longer than the target count as failures.
+pub struct FeeEstimator {
+    bounds: Vec<FeeRate>,
+    stats: FeeStats,
+    tracked: BTreeMap<Id<Transaction>, TrackedTx>,
+}
+
+impl FeeEstimator {
+    pub fn new() -> Self {
+        let bounds = bucket_bounds();
+        let buckets = vec![BucketStats::new(); bounds.len()];
+        Self {
+            bounds,
+            stats: FeeStats {
+                version: STATS_VERSION,
+                best_height: BlockHeight::zero(),
+                buckets,
+            },
+            tracked: BTreeMap::new(),
+        }
+    }
+
+    // Reads the statistics saved by `save`. Fails if they were saved with a different layout.
+    pub fn load(path: &Path) -> std::io::Result<Self> {
+        let data = std::fs::read(path)?;
+        let stats = FeeStats::decode(&mut data.as_slice())
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+        let mut estimator = Self::new();
+        let layout_matches = stats.version == STATS_VERSION
+            && stats.buckets.len() == estimator.bounds.len()
+            && stats
+                .buckets
+                .iter()
+                .all(|bucket| bucket.confirmed_within.len() == FEE_ESTIMATOR_MAX_TARGET_BLOCKS);
+        if !layout_matches {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                "fee estimates saved with an incompatible layout",
+            ));
+        }
+        estimator.stats = stats;
+        Ok(estimator)
+    }
+
+    // The transactions that are waiting for confirmation are not saved, as the mempool does not
+    // survive restarts either. The statistics are written to a temporary file first, so that a
+    // crash in the middle of the write doesn't leave a truncated file behind.
+    pub fn save(&self, path: &Path) -> std::io::Result<()> {
+        let tmp_path = path.with_extension("tmp");
+        std::fs::write(&tmp_path, self.stats.encode())?;
+        std::fs::rename(&tmp_path, path)
+    }
+
+    pub fn best_height(&self) -> BlockHeight {
+        self.stats.best_height
+    }
+
+    pub fn set_best_height(&mut self, height: BlockHeight) {
+        self.stats.best_height = height
+    }
+
+    fn bucket_index(&self, fee_rate: FeeRate) -> Option<usize> {
+        self.bounds.iter().rposition(|bound| *bound <= fee_rate)
+    }
+
+    // Starts tracking a transaction that has just entered the mempool
+    pub fn process_transaction(&mut self, tx_id: Id<Transaction>, fee_rate: FeeRate) {
+        if let Some(bucket) = self.bucket_index(fee_rate) {
+            let entry_height = self.stats.best_height;
+            self.tracked.insert(
+                tx_id,
+                TrackedTx {
+                    entry_height,
+                    bucket,
+                },
+            );
+        }
+    }
+
+    // Records the confirmation of the tracked transactions included in a new block. Transactions
+    // for which `in_mempool` returns false have been evicted or replaced, and are no longer
+    // tracked.
+    pub fn process_block(
+        &mut self,
+        height: BlockHeight,
+        tx_ids: impl IntoIterator<Item = Id<Transaction>>,
+        in_mempool: impl Fn(&Id<Transaction>) -> bool,
+    ) {
+        for bucket in self.stats.buckets.iter_mut() {
+            bucket.decay();
+        }
+
+        for tx_id in tx_ids {
+            if let Some(tracked) = self.tracked.remove(&tx_id) {
+                let blocks_to_confirm = (height - tracked.entry_height)
+                    .and_then(|distance| usize::try_from(i64::from(distance)).ok())
+                    .unwrap_or(0)
+                    .max(1);
+                self.stats.buckets[tracked.bucket].record_confirmation(blocks_to_confirm);
+            }
+        }
+
+        self.tracked.retain(|tx_id, _| in_mempool(tx_id));
+        self.stats.best_height = height;
+    }
+
+    // Returns the lowest fee rate such that at least `confidence_percent` percent of the
+    // transactions paying it got confirmed within `target_blocks` blocks, or `None` if there is not
+    // enough data to tell
+    pub fn estimate_fee(
+        &self,
+        target_blocks: usize,
+        confidence_percent: u8,
+    ) -> Result<Option<FeeRate>, FeeEstimationError> {
+        utils::ensure!(
+            (1..=FEE_ESTIMATOR_MAX_TARGET_BLOCKS).contains(&target_blocks),
+            FeeEstimationError::InvalidTarget {
+                target: target_blocks,
+                max: FEE_ESTIMATOR_MAX_TARGET_BLOCKS,
+            }
+        );
+        utils::ensure!(
+            (1..=100).contains(&confidence_percent),
+            FeeEstimationError::InvalidConfidence(confidence_percent)
+        );
+
+        // Transactions that have been waiting in the mempool for at least the target number of
+        // blocks have already failed to confirm within it
+        let mut waiting_too_long = vec![0u64; self.bounds.len()];
+        for tracked in self.tracked.values() {
+            let waited = (self.stats.best_height - tracked.entry_height).map_or(0, i64::from);
+            if usize::try_from(waited).map_or(false, |waited| waited >= target_blocks) {
+                waiting_too_long[tracked.bucket] += TX_WEIGHT;
+            }
+        }
+
+        // Starting from the highest fee rate, buckets are grouped until there is enough data to
+        // judge the group. The estimate is the lowest bucket of the last group that passes.
+        let mut estimate = None;
+        let mut group_successes = 0u64;
+        let mut group_total = 0u64;
+        for (index, bucket) in self.stats.buckets.iter().enumerate().rev() {
+            group_successes += bucket.confirmed_within[target_blocks - 1];
+            group_total += bucket.confirmed + waiting_too_long[index];
+            if group_total < FEE_ESTIMATOR_SUFFICIENT_DATA_POINTS * TX_WEIGHT {
+                continue;
+            }
+            if u128::from(group_successes) * 100
+                < u128::from(group_total) * u128::from(confidence_percent)
+            {
+                break;
+            }
+            estimate = Some(self.bounds[index]);
+            group_successes = 0;
+            group_total = 0;
+        }
+        Ok(estimate)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fee_rate(atoms_per_kb: u128) -> FeeRate {
+        FeeRate::new(Amount::from_atoms(atoms_per_kb))
+    }
+
+    fn tx_id(n: u64) -> Id<Transaction> {
+        common::primitives::H256::from_low_u64_be(n).into()
+    }
+
+    // Adds `count` transactions paying `atoms_per_kb` and confirms them after `blocks` blocks
+    fn confirm_after(
+        estimator: &mut FeeEstimator,
+        first_id: u64,
+        count: u64,
+        atoms_per_kb: u128,
+        blocks: u64,
+    ) {
+        let ids: Vec<_> = (first_id..first_id + count).map(tx_id).collect();
+        for id in &ids {
+            estimator.process_transaction(*id, fee_rate(atoms_per_kb));
+        }
+        let start = u64::from(estimator.best_height());
+        for height in start + 1..start + blocks {
+            estimator.process_block(BlockHeight::new(height), Vec::new(), |_| true);
+        }
+        estimator.process_block(BlockHeight::new(start + blocks), ids, |_| true);
+    }
+
+    #[test]
+    fn no_data() {
+        let estimator = FeeEstimator::new();
+        assert_eq!(estimator.estimate_fee(1, 95).unwrap(), None);
+    }
+
+    #[test]
+    fn invalid_arguments() {
+        let estimator = FeeEstimator::new();
+        assert!(matches!(
+            estimator.estimate_fee(0, 95),
+            Err(FeeEstimationError::InvalidTarget { target: 0, .. })
+        ));
+        assert!(matches!(
+            estimator.estimate_fee(FEE_ESTIMATOR_MAX_TARGET_BLOCKS + 1, 95),
+            Err(FeeEstimationError::InvalidTarget { .. })
+        ));
+        assert!(matches!(
+            estimator.estimate_fee(1, 0),
+            Err(FeeEstimationError::InvalidConfidence(0))
+        ));
+        assert!(matches!(
+            estimator.estimate_fee(1, 101),
+            Err(FeeEstimationError::InvalidConfidence(101))
+        ));
+    }
+
+    #[test]
+    fn higher_fee_confirms_sooner() {
+        let mut estimator = FeeEstimator::new();
+        // High fee transactions get into the next block, low fee ones wait for 5 blocks
+        for round in 0..10 {
+            confirm_after(&mut estimator, round * 100, 10, 100_000, 1);
+            confirm_after(&mut estimator, round * 100 + 50, 10, 2_000, 5);
+        }
+
+        let next_block = estimator.estimate_fee(1, 95).unwrap().expect("estimate");
+        assert!(next_block > fee_rate(2_000));
+        assert!(next_block <= fee_rate(100_000));
+
+        let within_five = estimator.estimate_fee(5, 95).unwrap().expect("estimate");
+        assert!(within_five <= fee_rate(2_000));
+    }
+
+    #[test]
+    fn waiting_transactions_count_as_failures() {
+        let mut estimator = FeeEstimator::new();
+        confirm_after(&mut estimator, 0, 10, 2_000, 1);
+        assert!(estimator.estimate_fee(1, 95).unwrap().expect("estimate") <= fee_rate(2_000));
+
+        // Many more transactions paying the same fee rate are stuck
+        for id in 100..200 {
+            estimator.process_transaction(tx_id(id), fee_rate(2_000));
+        }
+        let height = estimator.best_height().next_height();
+        estimator.process_block(height, Vec::new(), |_| true);
+        assert_eq!(estimator.estimate_fee(1, 95).unwrap(), None);
+    }
+
+    #[test]
+    fn save_and_load() {
+        let mut estimator = FeeEstimator::new();
+        confirm_after(&mut estimator, 0, 10, 5_000, 2);
+
+        let dir = std::env::temp_dir().join(format!("fee_estimates_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("fee_estimates.dat");
+        estimator.save(&path).unwrap();
+        assert!(!path.with_extension("tmp").exists());
+        let loaded = FeeEstimator::load(&path).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(loaded.stats, estimator.stats);
+        for target in 1..=3 {
+            assert_eq!(
+                loaded.estimate_fee(target, 95).unwrap(),
+                estimator.estimate_fee(target, 95).unwrap()
+            );
+        }
+    }
+}
@@ -13,8 +13,10 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::collections::BTreeMap;
 use std::collections::BTreeSet;
 use std::num::NonZeroUsize;
+use std::path::PathBuf;
 use std::sync::Arc;
 use std::time::Duration;
 
@@ -31,6 +33,7 @@ use serialization::Encode;
 
 use common::chain::transaction::Transaction;
 use common::primitives::amount::Amount;
+use common::primitives::amount::SignedAmount;
 use common::primitives::Id;
 use common::primitives::Idable;
 
@@ -41,14 +44,18 @@ use utils::eventhandler::EventsController;
 use utils::tap_error_log::LogError;
 
 use crate::error::Error;
+use crate::error::FeeEstimationError;
+use crate::error::PackageValidationError;
 use crate::error::TxValidationError;
 use crate::get_memory_usage::GetMemoryUsage;
 use crate::interface::mempool_interface_impl::mempool_method_call::MempoolMethodCall;
 use crate::tx_accumulator::TransactionAccumulator;
 use crate::MempoolEvent;
-use feerate::FeeRate;
+use fee_estimator::FeeEstimator;
+pub use feerate::FeeRate;
 use feerate::INCREMENTAL_RELAY_FEE_RATE;
 use feerate::INCREMENTAL_RELAY_THRESHOLD;
+use package_selector::PackageSelector;
 use rolling_fee_rate::RollingFeeRate;
 use spends_unconfirmed::SpendsUnconfirmed;
 use store::Conflicts;
@@ -62,7 +69,9 @@ pub use crate::interface::mempool_interface::MempoolInterface;
 
 use crate::config::*;
 
+mod fee_estimator;
 mod feerate;
+mod package_selector;
 mod rolling_fee_rate;
 mod spends_unconfirmed;
 mod store;
@@ -70,8 +79,12 @@ mod try_get_fee;
 mod tx_with_fee;
 
 fn get_relay_fee(tx: &SignedTransaction) -> Amount {
+    get_relay_fee_for_size(tx.encoded_size())
+}
+
+fn get_relay_fee_for_size(size: usize) -> Amount {
     // TODO we should never reach the expect, but should this be an error anyway?
-    Amount::from_atoms(u128::try_from(tx.encoded_size() * RELAY_FEE_PER_BYTE).expect("Overflow"))
+    Amount::from_atoms(u128::try_from(size * RELAY_FEE_PER_BYTE).expect("Overflow"))
 }
 
 pub struct Mempool<M: GetMemoryUsage + 'static + Send + Sync> {
@@ -81,6 +94,10 @@ pub struct Mempool<M: GetMemoryUsage + 'static + Send + Sync> {
     rolling_fee_rate: RwLock<RollingFeeRate>,
     max_size: usize,
     max_tx_age: Duration,
+    package_limits: PackageLimits,
+    fee_estimator: FeeEstimator,
+    fee_estimates_path: Option<PathBuf>,
+    fee_estimates_saved_at: Option<Time>,
     chainstate_handle: subsystem::Handle<Box<dyn ChainstateInterface>>,
     clock: TimeGetter,
     memory_usage_estimator: M,
@@ -123,6 +140,10 @@ where
             chainstate_handle,
             max_size: MAX_MEMPOOL_SIZE_BYTES,
             max_tx_age: DEFAULT_MEMPOOL_EXPIRY,
+            package_limits: PackageLimits::default(),
+            fee_estimator: FeeEstimator::new(),
+            fee_estimates_path: None,
+            fee_estimates_saved_at: None,
             // TODO research whether we really need parking lot
             rolling_fee_rate: parking_lot::RwLock::new(RollingFeeRate::new(clock.get_time())),
             clock,
@@ -132,10 +153,36 @@ where
         }
     }
 
+    pub fn with_package_limits(mut self, package_limits: PackageLimits) -> Self {
+        self.package_limits = package_limits;
+        self
+    }
+
+    // Makes the fee estimator start from the statistics saved in the given file, if any, and save
+    // them there whenever a new block arrives
+    pub fn with_fee_estimates_file(mut self, path: PathBuf) -> Self {
+        if path.exists() {
+            match FeeEstimator::load(&path) {
+                Ok(fee_estimator) => self.fee_estimator = fee_estimator,
+                Err(e) => log::warn!("Failed to load fee estimates from {:?}: {}", path, e),
+            }
+        }
+        self.fee_estimates_path = Some(path);
+        self
+    }
+
     pub fn run(mut self) -> Result<(), Error> {
         tokio::spawn(async move {
             let event_receiver =
                 self.subscribe_to_chainstate_events().await.log_err().expect("chainstate dead");
+            let best_height = self
+                .chainstate_handle
+                .call(|this| this.get_best_block_height())
+                .await
+                .expect("chainstate dead")
+                .log_err()
+                .unwrap_or_else(|_| self.fee_estimator.best_height());
+            self.fee_estimator.set_best_height(best_height);
             self.mempool_event_loop(event_receiver).await
         });
         Ok(())
@@ -148,7 +195,7 @@ where
         loop {
             tokio::select! {
                 Some((block_id, block_height)) = chainstate_event_receiver.recv() =>{
-                    self.new_tip_set(block_id, block_height)
+                    self.new_tip_set(block_id, block_height).await
                 },
                 Some(method_call) = self.receiver.recv() => self.handle_mempool_method_call(method_call).await
             }
@@ -162,6 +209,11 @@ where
                     logging::log::error!("AddTransaction: Error sending response: {:?}", e);
                 }
             }
+            MempoolMethodCall::AddPackage { txs, rtx } => {
+                if let Err(e) = rtx.send(self.add_package(txs).await) {
+                    logging::log::error!("AddPackage: Error sending response: {:?}", e);
+                }
+            }
             MempoolMethodCall::GetAll { rtx } => {
                 if let Err(e) = rtx.send(self.get_all()) {
                     logging::log::error!("GetAll: Error sending response: {:?}", e);
@@ -183,6 +235,24 @@ where
                     logging::log::error!("ContainsTransaction: Error sending response: {:?}", e);
                 }
             }
+            MempoolMethodCall::PrioritiseTransaction {
+                tx_id,
+                fee_delta,
+                rtx,
+            } => {
+                if let Err(e) = rtx.send(self.prioritise_transaction(tx_id, fee_delta)) {
+                    logging::log::error!("PrioritiseTransaction: Error sending response: {:?}", e);
+                }
+            }
+            MempoolMethodCall::EstimateFee {
+                target_blocks,
+                confidence_percent,
+                rtx,
+            } => {
+                if let Err(e) = rtx.send(self.estimate_fee(target_blocks, confidence_percent)) {
+                    logging::log::error!("EstimateFee: Error sending response: {:?}", e);
+                }
+            }
             MempoolMethodCall::SubscribeToEvents { handler, rtx } => {
                 self.subscribe_to_events(handler);
                 if let Err(e) = rtx.send(()) {
@@ -295,14 +365,7 @@ where
         &self,
         tx: SignedTransaction,
     ) -> Result<TxMempoolEntry, TxValidationError> {
-        // Genesis transaction has no parent, hence the first filter_map
-        let parents = tx
-            .transaction()
-            .inputs()
-            .iter()
-            .filter_map(|input| input.outpoint().tx_id().get_tx_id().cloned())
-            .filter_map(|id| self.store.txs_by_id.contains_key(&id).then_some(id))
-            .collect::<BTreeSet<_>>();
+        let parents = self.unconfirmed_parents(&tx);
         let ancestor_ids =
             TxMempoolEntry::unconfirmed_ancestors_from_parents(parents.clone(), &self.store)?;
         let ancestors = BTreeSet::from(ancestor_ids)
@@ -313,7 +376,18 @@ where
 
         let fee = self.try_get_fee(&tx).await?;
         let time = self.clock.get_time();
-        TxMempoolEntry::new(tx, fee, parents, ancestors, time)
+        let fee_delta = self.store.fee_delta(&tx.transaction().get_id());
+        TxMempoolEntry::new(tx, fee, fee_delta, parents, ancestors, time)
+    }
+
+    fn unconfirmed_parents(&self, tx: &SignedTransaction) -> BTreeSet<Id<Transaction>> {
+        // Genesis transaction has no parent, hence the first filter_map
+        tx.transaction()
+            .inputs()
+            .iter()
+            .filter_map(|input| input.outpoint().tx_id().get_tx_id().cloned())
+            .filter_map(|id| self.store.txs_by_id.contains_key(&id).then_some(id))
+            .collect()
     }
 }
 
@@ -326,6 +400,15 @@ where
         &self,
         tx: &SignedTransaction,
     ) -> Result<Conflicts, TxValidationError> {
+        let (tx, conflicts) = self.validate_transaction_except_fees(tx).await?;
+        self.pays_minimum_fees(&tx)?;
+        Ok(conflicts)
+    }
+
+    async fn validate_transaction_except_fees(
+        &self,
+        tx: &SignedTransaction,
+    ) -> Result<(TxWithFee, Conflicts), TxValidationError> {
         // This validation function is based on Bitcoin Core's MemPoolAccept::PreChecks.
         // However, as of this stage it does not cover everything covered in Bitcoin Core
         //
@@ -395,11 +478,66 @@ where
 
         self.verify_inputs_available(tx.tx()).await?;
 
-        self.pays_minimum_relay_fees(&tx)?;
+        self.within_package_limits(&tx)?;
 
-        self.pays_minimum_mempool_fee(&tx)?;
+        Ok((tx, conflicts))
+    }
 
-        Ok(conflicts)
+    fn within_package_limits(&self, tx: &TxWithFee) -> Result<(), TxValidationError> {
+        let limits = &self.package_limits;
+        let size = tx.tx().encoded_size();
+        let ancestor_ids = TxMempoolEntry::unconfirmed_ancestors_from_parents(
+            self.unconfirmed_parents(tx.tx()),
+            &self.store,
+        )?;
+        let ancestors = ancestor_ids
+            .iter()
+            .map(|id| self.store.get_entry(id).expect("ancestors to exist"))
+            .collect::<Vec<_>>();
+
+        let count_with_ancestors = ancestors.len() + 1;
+        ensure!(
+            count_with_ancestors <= limits.max_ancestor_count,
+            TxValidationError::TooManyAncestors {
+                count: count_with_ancestors,
+                limit: limits.max_ancestor_count,
+            }
+        );
+
+        let size_with_ancestors =
+            ancestors.iter().map(|ancestor| ancestor.size()).sum::<usize>() + size;
+        ensure!(
+            size_with_ancestors <= limits.max_ancestor_size,
+            TxValidationError::AncestorSizeLimitExceeded {
+                size: size_with_ancestors,
+                limit: limits.max_ancestor_size,
+            }
+        );
+
+        // The new transaction becomes a descendant of each of its ancestors
+        for ancestor in ancestors {
+            let count_with_descendants = ancestor.count_with_descendants() + 1;
+            ensure!(
+                count_with_descendants <= limits.max_descendant_count,
+                TxValidationError::TooManyDescendants {
+                    ancestor: ancestor.tx_id(),
+                    count: count_with_descendants,
+                    limit: limits.max_descendant_count,
+                }
+            );
+
+            let size_with_descendants = ancestor.size_with_descendants() + size;
+            ensure!(
+                size_with_descendants <= limits.max_descendant_size,
+                TxValidationError::DescendantSizeLimitExceeded {
+                    ancestor: ancestor.tx_id(),
+                    size: size_with_descendants,
+                    limit: limits.max_descendant_size,
+                }
+            );
+        }
+
+        Ok(())
     }
 
     async fn verify_inputs_available(
@@ -429,6 +567,22 @@ where
             )
     }
 
+    fn pays_minimum_fees(&self, tx: &TxWithFee) -> Result<(), TxValidationError> {
+        self.pays_minimum_relay_fees(tx)?;
+        self.pays_minimum_mempool_fee(tx)
+    }
+
+    // The fee a package of transactions of the given total size has to pay in order to be
+    // accepted as a whole, even if some of its transactions don't pay enough on their own
+    fn get_update_minimum_package_fee(
+        &self,
+        package_size: usize,
+    ) -> Result<Amount, TxValidationError> {
+        let relay_fee = get_relay_fee_for_size(package_size);
+        let minimum_mempool_fee = self.get_update_min_fee_rate().compute_fee(package_size)?;
+        Ok(std::cmp::max(relay_fee, minimum_mempool_fee))
+    }
+
     fn pays_minimum_mempool_fee(&self, tx: &TxWithFee) -> Result<(), TxValidationError> {
         let tx_fee = tx.fee();
         let minimum_fee = self.get_update_minimum_mempool_fee(tx.tx())?;
@@ -641,9 +795,7 @@ where
     M: GetMemoryUsage + Send + Sync,
 {
     async fn finalize_tx(&mut self, tx: SignedTransaction) -> Result<(), Error> {
-        let entry = self.create_entry(tx).await?;
-        let id = entry.tx_id();
-        self.store.add_tx(entry)?;
+        let id = self.insert_tx(tx).await?;
         self.remove_expired_transactions();
         ensure!(
             self.store.txs_by_id.contains_key(&id),
@@ -655,6 +807,27 @@ where
         Ok(())
     }
 
+    async fn insert_tx(&mut self, tx: SignedTransaction) -> Result<Id<Transaction>, Error> {
+        let (id, fee_rate) = self.stage_tx(tx).await?;
+        self.fee_estimator.process_transaction(id, fee_rate);
+        Ok(id)
+    }
+
+    // Adds the transaction to the store without letting the fee estimator know about it yet
+    async fn stage_tx(
+        &mut self,
+        tx: SignedTransaction,
+    ) -> Result<(Id<Transaction>, FeeRate), Error> {
+        let entry = self.create_entry(tx).await?;
+        let id = entry.tx_id();
+        let fee_rate = FeeRate::from_total_tx_fee(
+            entry.fee(),
+            NonZeroUsize::new(entry.size()).expect("transaction cannot have zero size"),
+        )?;
+        self.store.add_tx(entry)?;
+        Ok((id, fee_rate))
+    }
+
     fn limit_mempool_size(&mut self) -> Result<(), Error> {
         let removed_fees = self.trim()?;
         if !removed_fees.is_empty() {
@@ -744,6 +917,188 @@ where
         Ok(())
     }
 
+    pub async fn add_package(
+        &mut self,
+        txs: Vec<SignedTransaction>,
+    ) -> Result<Vec<Result<(), Error>>, Error> {
+        check_package_topology(&txs)?;
+
+        // The package is validated as a whole before anything it replaces is removed from the
+        // mempool. Its transactions are staged in the store one by one, so that their children in
+        // the package can find the outputs they spend, and are removed again if the package turns
+        // out to be invalid.
+        let mut package = StagedPackage::new();
+        let mut results = match self.stage_package(txs, &mut package).await {
+            Ok(results) => results,
+            Err(err) => {
+                self.unstage_package(&package);
+                return Err(err);
+            }
+        };
+
+        match self.package_minimum_fee_not_met(&package) {
+            Ok(None) => self.commit_package(&package),
+            Ok(Some(minimum_fee)) => {
+                log::debug!(
+                    "add_package: package fee {:?} is below the minimum {:?}",
+                    package.fee,
+                    minimum_fee
+                );
+                self.unstage_package(&package);
+                for (index, _, _) in &package.txs {
+                    results[*index] = Err(TxValidationError::PackageFeeTooLow {
+                        package_fee: package.fee,
+                        minimum_fee,
+                    }
+                    .into());
+                }
+                self.store.assert_valid();
+                return Ok(results);
+            }
+            Err(err) => {
+                self.unstage_package(&package);
+                return Err(err.into());
+            }
+        }
+
+        self.remove_expired_transactions();
+        for (index, tx_id, _) in &package.txs {
+            if !self.contains_transaction(tx_id) {
+                results[*index] = Err(TxValidationError::DescendantOfExpiredTransaction.into());
+            }
+        }
+
+        self.limit_mempool_size()?;
+        for (index, tx_id, _) in &package.txs {
+            if results[*index].is_ok() && !self.contains_transaction(tx_id) {
+                results[*index] = Err(Error::MempoolFull);
+            }
+        }
+
+        self.store.assert_valid();
+        Ok(results)
+    }
+
+    // Validates the transactions of the package and inserts the valid ones into the store, without
+    // removing their conflicts or checking the fees of the package as a whole
+    async fn stage_package(
+        &mut self,
+        txs: Vec<SignedTransaction>,
+        package: &mut StagedPackage,
+    ) -> Result<Vec<Result<(), Error>>, Error> {
+        let mut results = Vec::with_capacity(txs.len());
+        for (index, tx) in txs.into_iter().enumerate() {
+            if let Some(conflict) = self.find_conflict_in_package(&tx, package) {
+                return Err(PackageValidationError::ConflictingTransactions {
+                    tx: tx.transaction().get_id(),
+                    conflict,
+                }
+                .into());
+            }
+
+            let (tx_with_fee, conflicts) = match self.validate_transaction_except_fees(&tx).await {
+                Ok(validated) => validated,
+                Err(err) => {
+                    results.push(Err(err.into()));
+                    continue;
+                }
+            };
+            let pays_minimum_fees = self.pays_minimum_fees(&tx_with_fee);
+
+            match self.stage_tx(tx).await {
+                Ok((tx_id, fee_rate)) => {
+                    if let Err(err) = pays_minimum_fees {
+                        log::debug!(
+                            "add_package: tx {} is below the fee threshold on its own: {}",
+                            tx_id,
+                            err
+                        );
+                        package.below_fee_threshold = true;
+                    }
+                    package.txs.push((index, tx_id, fee_rate));
+                    package.replaced.extend(BTreeSet::from(conflicts));
+                    package.fee =
+                        (package.fee + tx_with_fee.fee()).ok_or(TxValidationError::FeeOverflow)?;
+                    package.size += tx_with_fee.tx().encoded_size();
+                    results.push(Ok(()));
+                }
+                Err(err) => results.push(Err(err)),
+            }
+        }
+
+        for replaced_id in &package.replaced {
+            let replaced = self.store.get_entry(replaced_id).expect("replaced tx to exist");
+            let descendants = BTreeSet::from(replaced.unconfirmed_descendants(&self.store));
+            if let Some((_, tx_id, _)) =
+                package.txs.iter().find(|(_, id, _)| descendants.contains(id))
+            {
+                return Err(PackageValidationError::SpendsReplacedTransaction {
+                    tx: *tx_id,
+                    replaced: *replaced_id,
+                }
+                .into());
+            }
+        }
+
+        Ok(results)
+    }
+
+    fn find_conflict_in_package(
+        &self,
+        tx: &SignedTransaction,
+        package: &StagedPackage,
+    ) -> Option<Id<Transaction>> {
+        tx.transaction()
+            .inputs()
+            .iter()
+            .filter_map(|input| self.store.find_conflicting_tx(input.outpoint()))
+            .find(|conflict| package.txs.iter().any(|(_, tx_id, _)| tx_id == conflict))
+    }
+
+    // Returns the minimum fee the package has to pay if it pays less than that. A package that
+    // replaces transactions has to pay for all of them and for its own bandwidth, just like a
+    // single replacement transaction has to (BIP125 rules #3 and #4).
+    fn package_minimum_fee_not_met(
+        &self,
+        package: &StagedPackage,
+    ) -> Result<Option<Amount>, TxValidationError> {
+        if !package.below_fee_threshold && package.replaced.is_empty() {
+            return Ok(None);
+        }
+
+        let mut minimum_fee = self.get_update_minimum_package_fee(package.size)?;
+        if !package.replaced.is_empty() {
+            let replaced_fees = package
+                .replaced
+                .iter()
+                .map(|id| self.store.get_entry(id).expect("replaced tx to exist").fee())
+                .sum::<Option<Amount>>()
+                .ok_or(TxValidationError::ConflictsFeeOverflow)?;
+            let replacement_fee = (replaced_fees + get_relay_fee_for_size(package.size))
+                .ok_or(TxValidationError::FeeOverflow)?;
+            minimum_fee = std::cmp::max(minimum_fee, replacement_fee);
+        }
+
+        Ok((package.fee < minimum_fee).then_some(minimum_fee))
+    }
+
+    fn commit_package(&mut self, package: &StagedPackage) {
+        self.store.drop_conflicts(Conflicts::from(package.replaced.clone()));
+        for (_, tx_id, fee_rate) in &package.txs {
+            self.fee_estimator.process_transaction(*tx_id, *fee_rate);
+        }
+    }
+
+    fn unstage_package(&mut self, package: &StagedPackage) {
+        for (_, tx_id, _) in package.txs.iter().rev() {
+            self.store.remove_tx(tx_id, MempoolRemovalReason::PackageRejected);
+        }
+        // The staged transactions took over the outpoints they share with their conflicts
+        for replaced_id in &package.replaced {
+            self.store.restore_spent_outpoints(replaced_id);
+        }
+    }
+
     pub fn get_all(&self) -> Vec<SignedTransaction> {
         self.store
             .txs_by_descendant_score
@@ -758,21 +1113,21 @@ where
         &self,
         mut tx_accumulator: Box<dyn TransactionAccumulator>,
     ) -> Box<dyn TransactionAccumulator> {
-        let mut tx_iter = self.store.txs_by_ancestor_score.values().flatten().rev();
-        // TODO implement Iterator for MempoolStore so we don't need to use `expect` here
+        let mut package_selector = PackageSelector::new(&self.store);
         while !tx_accumulator.done() {
-            if let Some(tx_id) = tx_iter.next() {
-                let next_tx = self.store.txs_by_id.get(tx_id).expect("tx to exist");
+            if let Some(package) = package_selector.next_package() {
                 log::debug!(
-                    "collect_txs: next tx has ancestor score {:?}",
-                    next_tx.ancestor_score()
+                    "collect_txs: next package has {} transactions, ending with {}",
+                    package.len(),
+                    package.last().expect("package not empty").tx_id()
                 );
 
-                match tx_accumulator.add_tx(next_tx.tx().clone(), next_tx.fee()) {
-                    Ok(_) => (),
+                let txs = package.iter().map(|entry| (entry.tx().clone(), entry.fee())).collect();
+                match tx_accumulator.add_package(txs) {
+                    Ok(_) => package_selector.mark_selected(&package),
                     Err(err) => log::error!(
-                        "CRITICAL: Failed to add transaction {} from mempool. Error: {}",
-                        next_tx.tx().transaction().get_id(),
+                        "CRITICAL: Failed to add package ending with {} from mempool. Error: {}",
+                        package.last().expect("package not empty").tx_id(),
                         err
                     ),
                 }
@@ -791,18 +1146,152 @@ where
         self.events_controller.subscribe_to_events(handler)
     }
 
-    pub fn new_tip_set(&mut self, block_id: Id<Block>, block_height: BlockHeight) {
+    pub fn prioritise_transaction(
+        &mut self,
+        tx_id: Id<Transaction>,
+        fee_delta: SignedAmount,
+    ) -> Result<SignedAmount, Error> {
+        let total_delta = self.store.prioritise_tx(tx_id, fee_delta)?;
+        log::info!(
+            "Fee delta of tx {} set to {:?}",
+            tx_id,
+            total_delta.into_atoms()
+        );
+        self.store.assert_valid();
+        Ok(total_delta)
+    }
+
+    pub fn estimate_fee(
+        &self,
+        target_blocks: usize,
+        confidence_percent: u8,
+    ) -> Result<Option<FeeRate>, FeeEstimationError> {
+        self.fee_estimator.estimate_fee(target_blocks, confidence_percent)
+    }
+
+    pub async fn new_tip_set(&mut self, block_id: Id<Block>, block_height: BlockHeight) {
         log::info!(
             "new tip with block_id {:?} and block_height {:?}",
             block_id,
             block_height
         );
         // TODO(Roy) handle the new tip
-        let mut rolling_fee_rate = self.rolling_fee_rate.write();
-        (*rolling_fee_rate).set_block_since_last_rolling_fee_bump(true);
+        self.rolling_fee_rate.write().set_block_since_last_rolling_fee_bump(true);
+
+        let block = match self.chainstate_handle.call(move |this| this.get_block(block_id)).await {
+            Ok(Ok(Some(block))) => block,
+            Ok(Ok(None)) => {
+                log::warn!("New tip {} not found", block_id);
+                self.fee_estimator.set_best_height(block_height);
+                return;
+            }
+            Ok(Err(e)) => {
+                log::error!("Failed to get new tip {}: {}", block_id, e);
+                return;
+            }
+            Err(e) => {
+                log::error!("Chainstate call failed: {}", e);
+                return;
+            }
+        };
+
+        // The fee deltas of confirmed transactions are of no use anymore
+        for tx in block.transactions() {
+            self.store.clear_fee_delta(&tx.transaction().get_id());
+        }
+        self.update_fee_estimates(&block, block_height);
+    }
+
+    fn update_fee_estimates(&mut self, block: &Block, block_height: BlockHeight) {
+        let store = &self.store;
+        self.fee_estimator.process_block(
+            block_height,
+            block.transactions().iter().map(|tx| tx.transaction().get_id()),
+            |tx_id| store.txs_by_id.contains_key(tx_id),
+        );
+
+        // The estimates change little from block to block, so they are only saved every now and
+        // then rather than on every block
+        let now = self.clock.get_time();
+        let save_due = self.fee_estimates_saved_at.map_or(true, |saved_at| {
+            now >= saved_at + FEE_ESTIMATES_SAVE_INTERVAL
+        });
+        if let (Some(path), true) = (&self.fee_estimates_path, save_due) {
+            match self.fee_estimator.save(path) {
+                Ok(()) => self.fee_estimates_saved_at = Some(now),
+                Err(e) => log::error!("Failed to save fee estimates to {:?}: {}", path, e),
+            }
+        }
     }
 }
 
+// The transactions of a package which have been inserted into the store while the package is being
+// validated, along with the mempool transactions the package is going to replace
+struct StagedPackage {
+    // Staged transactions, with their positions in the package and their fee rates
+    txs: Vec<(usize, Id<Transaction>, FeeRate)>,
+    replaced: BTreeSet<Id<Transaction>>,
+    // Whether some of the staged transactions don't pay enough fees on their own
+    below_fee_threshold: bool,
+    fee: Amount,
+    size: usize,
+}
+
+impl StagedPackage {
+    fn new() -> Self {
+        Self {
+            txs: Vec::new(),
+            replaced: BTreeSet::new(),
+            below_fee_threshold: false,
+            fee: Amount::ZERO,
+            size: 0,
+        }
+    }
+}
+
+// Checks that the package is not too large, and that each transaction appears in it only once and
+// after all of its parents from the package
+fn check_package_topology(txs: &[SignedTransaction]) -> Result<(), PackageValidationError> {
+    ensure!(!txs.is_empty(), PackageValidationError::EmptyPackage);
+    ensure!(
+        txs.len() <= MAX_PACKAGE_COUNT,
+        PackageValidationError::TooManyTransactions {
+            count: txs.len(),
+            limit: MAX_PACKAGE_COUNT,
+        }
+    );
+
+    let mut positions = BTreeMap::new();
+    for (index, tx) in txs.iter().enumerate() {
+        let tx_id = tx.transaction().get_id();
+        ensure!(
+            positions.insert(tx_id, index).is_none(),
+            PackageValidationError::DuplicateTransaction(tx_id)
+        );
+    }
+
+    for (index, tx) in txs.iter().enumerate() {
+        let parents = tx
+            .transaction()
+            .inputs()
+            .iter()
+            .filter_map(|input| input.outpoint().tx_id().get_tx_id().cloned());
+        for parent in parents {
+            if let Some(parent_index) = positions.get(&parent) {
+                ensure!(
+                    *parent_index < index,
+                    PackageValidationError::NotTopologicallySorted {
+                        child: tx.transaction().get_id(),
+                        parent,
+                    }
+                );
+            }
+        }
+    }
+
+    Ok(())
+}
+
 fn has_duplicate_entry<T>(iter: T) -> bool
 where
     T: IntoIterator,
@@ -0,0 +1,169 @@
+// Copyright (c) 2022 RBB S.r.l
+// opensource@mintlayer.org
+// SPDX-License-Identifier: MIT
+// Licensed under the MIT License;
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://github.com/mintlayer/mintlayer-core/blob/master/LICENSE
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+use std::iter::Peekable;
+
+use common::chain::transaction::Transaction;
+use common::primitives::amount::Amount;
+use common::primitives::Id;
+
+use super::store::AncestorScore;
+use super::store::MempoolStore;
+use super::store::TxMempoolEntry;
+
+// The ancestor state of an entry some of whose ancestors have already been selected. The selected
+// ancestors are no longer part of the entry's package, so they must not count towards its score.
+#[derive(Debug, Clone, Copy)]
+struct ModifiedEntry {
+    fees_with_ancestors: Amount,
+    size_with_ancestors: usize,
+    score: AncestorScore,
+}
+
+impl ModifiedEntry {
+    fn new(entry: &TxMempoolEntry) -> Self {
+        Self {
+            fees_with_ancestors: entry.fees_with_ancestors(),
+            size_with_ancestors: entry.size_with_ancestors(),
+            score: entry.ancestor_score(),
+        }
+    }
+
+    fn without_ancestor(self, entry: &TxMempoolEntry, ancestor: &TxMempoolEntry) -> Self {
+        let fees_with_ancestors =
+            (self.fees_with_ancestors - ancestor.modified_fee()).expect("fee with ancestors");
+        let size_with_ancestors = self.size_with_ancestors - ancestor.size();
+        Self {
+            fees_with_ancestors,
+            size_with_ancestors,
+            score: AncestorScore::from_package(
+                fees_with_ancestors,
+                size_with_ancestors,
+                entry.modified_fee(),
+                entry.size(),
+            ),
+        }
+    }
+}
+
+type Candidates<'a> = Peekable<Box<dyn Iterator<Item = &'a Id<Transaction>> + 'a>>;
+
+// Selects mempool transactions for a block by ancestor score, i.e. each transaction is
+// considered together with all of its unconfirmed ancestors that have not been selected yet.
+// This way, a child paying a high fee pulls its low-fee parents into the block
+// (child-pays-for-parent).
+//
+// This is a simplified version of Bitcoin Core's `BlockAssembler::addPackageTxs`: entries whose
+// ancestors have all been skipped are taken straight from the ancestor score index, and entries
+// with some of their ancestors already selected are kept aside with their updated scores.
+pub struct PackageSelector<'a> {
+    store: &'a MempoolStore,
+    candidates: Candidates<'a>,
+    selected: BTreeSet<Id<Transaction>>,
+    modified: BTreeMap<Id<Transaction>, ModifiedEntry>,
+    modified_by_score: BTreeSet<(AncestorScore, Id<Transaction>)>,
+}
+
+impl<'a> PackageSelector<'a> {
+    pub fn new(store: &'a MempoolStore) -> Self {
+        let candidates: Box<dyn Iterator<Item = &'a Id<Transaction>> + 'a> =
+            Box::new(store.txs_by_ancestor_score.values().flatten().rev());
+        Self {
+            store,
+            candidates: candidates.peekable(),
+            selected: BTreeSet::new(),
+            modified: BTreeMap::new(),
+            modified_by_score: BTreeSet::new(),
+        }
+    }
+
+    // Returns the package with the best ancestor score among those not considered yet. The
+    // entries of the package are sorted so that parents always come before their children.
+    pub fn next_package(&mut self) -> Option<Vec<&'a TxMempoolEntry>> {
+        while let Some(id) = self.candidates.peek() {
+            if self.selected.contains(*id) || self.modified.contains_key(*id) {
+                self.candidates.next();
+            } else {
+                break;
+            }
+        }
+
+        let best_unmodified = self.candidates.peek().map(|id| {
+            let entry = self.store.get_entry(id).expect("entry to exist");
+            (entry.ancestor_score(), entry.tx_id())
+        });
+        let best_modified = self.modified_by_score.iter().next_back().copied();
+
+        let take_modified = match (best_unmodified, best_modified) {
+            (None, None) => return None,
+            (Some(_), None) => false,
+            (None, Some(_)) => true,
+            (Some((unmodified_score, _)), Some((modified_score, _))) => {
+                modified_score > unmodified_score
+            }
+        };
+        let next_id = if take_modified {
+            let (_, modified_id) = best_modified.expect("modified entry to exist");
+            self.remove_modified(&modified_id);
+            modified_id
+        } else {
+            self.candidates.next();
+            best_unmodified.expect("candidate to exist").1
+        };
+
+        let entry = self.store.get_entry(&next_id).expect("entry to exist");
+        let mut package = BTreeSet::from(entry.unconfirmed_ancestors(self.store))
+            .into_iter()
+            .filter(|ancestor_id| !self.selected.contains(ancestor_id))
+            .map(|ancestor_id| self.store.get_entry(&ancestor_id).expect("ancestor to exist"))
+            .chain(std::iter::once(entry))
+            .collect::<Vec<_>>();
+        // A transaction always has more ancestors than any of its ancestors
+        package.sort_by_key(|entry| entry.count_with_ancestors());
+        Some(package)
+    }
+
+    // Marks the package as included in the block, updating the scores of the descendants of its
+    // entries
+    pub fn mark_selected(&mut self, package: &[&'a TxMempoolEntry]) {
+        for entry in package {
+            self.selected.insert(entry.tx_id());
+            self.remove_modified(&entry.tx_id());
+        }
+
+        for ancestor in package {
+            for descendant_id in BTreeSet::from(ancestor.unconfirmed_descendants(self.store)) {
+                if self.selected.contains(&descendant_id) {
+                    continue;
+                }
+                let descendant = self.store.get_entry(&descendant_id).expect("descendant to exist");
+                let modified = self
+                    .remove_modified(&descendant_id)
+                    .unwrap_or_else(|| ModifiedEntry::new(descendant))
+                    .without_ancestor(descendant, ancestor);
+                self.modified_by_score.insert((modified.score, descendant_id));
+                self.modified.insert(descendant_id, modified);
+            }
+        }
+    }
+
+    fn remove_modified(&mut self, id: &Id<Transaction>) -> Option<ModifiedEntry> {
+        let modified = self.modified.remove(id)?;
+        self.modified_by_score.remove(&(modified.score, *id));
+        Some(modified)
+    }
+}
@@ -22,6 +22,7 @@ use common::chain::tokens::OutputValue;
 use common::chain::transaction::Transaction;
 use common::chain::OutPoint;
 use common::primitives::amount::Amount;
+use common::primitives::amount::SignedAmount;
 use common::primitives::Id;
 use common::primitives::Idable;
 use serialization::Encode;
@@ -62,10 +63,26 @@ newtype! {
 }
 
 newtype! {
-    #[derive(Debug, PartialEq, Eq, Ord, PartialOrd)]
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Ord, PartialOrd)]
     pub struct AncestorScore(Amount);
 }
 
+impl AncestorScore {
+    pub fn from_package(
+        fees_with_ancestors: Amount,
+        size_with_ancestors: usize,
+        fee: Amount,
+        size: usize,
+    ) -> Self {
+        std::cmp::min(
+            (fees_with_ancestors / u128::try_from(size_with_ancestors).expect("conversion"))
+                .expect("nonzero tx_size"),
+            (fee / u128::try_from(size).expect("conversion")).expect("nonzero tx size"),
+        )
+        .into()
+    }
+}
+
 #[derive(Debug)]
 pub struct MempoolStore {
     // This is the "main" data structure storing Mempool entries. All other structures in the
@@ -101,6 +118,10 @@ pub struct MempoolStore {
     // We keep the information of which outpoints are spent by entries currently in the mempool.
     // This allows us to recognize conflicts (double-spends) and handle them
     pub spender_txs: BTreeMap<OutPoint, Id<Transaction>>,
+
+    // Fee deltas set by the node operator (see `prioritise_tx`). They are kept here for
+    // transactions that have not arrived yet too, and are applied to them once they do.
+    fee_deltas: BTreeMap<Id<Transaction>, SignedAmount>,
 }
 
 // If a transaction is removed from the mempool for any reason other than inclusion in a block,
@@ -116,6 +137,7 @@ pub enum MempoolRemovalReason {
     Expiry,
     SizeLimit,
     Replaced,
+    PackageRejected,
 }
 
 impl MempoolStore {
@@ -126,6 +148,113 @@ impl MempoolStore {
             txs_by_id: BTreeMap::new(),
             txs_by_creation_time: BTreeMap::new(),
             spender_txs: BTreeMap::new(),
+            fee_deltas: BTreeMap::new(),
+        }
+    }
+
+    pub fn fee_delta(&self, tx_id: &Id<Transaction>) -> SignedAmount {
+        self.fee_deltas.get(tx_id).copied().unwrap_or(SignedAmount::ZERO)
+    }
+
+    pub fn clear_fee_delta(&mut self, tx_id: &Id<Transaction>) {
+        self.fee_deltas.remove(tx_id);
+    }
+
+    // Adds `delta` to the fee delta of the given transaction, returning the new total delta. The
+    // delta only affects the way the transaction is prioritised (i.e. its ancestor and descendant
+    // scores and those of its relatives), never the fee it actually pays.
+    pub fn prioritise_tx(
+        &mut self,
+        tx_id: Id<Transaction>,
+        delta: SignedAmount,
+    ) -> Result<SignedAmount, Error> {
+        let total_delta = (self.fee_delta(&tx_id) + delta).ok_or(Error::FeeDeltaOverflow)?;
+
+        if let Some(entry) = self.txs_by_id.get(&tx_id).cloned() {
+            let modified_fee = apply_fee_delta(entry.fee, total_delta);
+            let fee_diff = (modified_fee.into_signed().ok_or(Error::FeeDeltaOverflow)?
+                - entry.modified_fee.into_signed().ok_or(Error::FeeDeltaOverflow)?)
+            .ok_or(Error::FeeDeltaOverflow)?;
+            let ancestors = entry.unconfirmed_ancestors(self);
+            let descendants = entry.unconfirmed_descendants(self);
+
+            // Compute all the updated fees first, so that the store is left untouched on overflow
+            let update = |fees: Amount| {
+                fees.into_signed()
+                    .and_then(|fees| fees + fee_diff)
+                    .and_then(SignedAmount::into_unsigned)
+                    .ok_or(Error::FeeDeltaOverflow)
+            };
+            let fees_with_ancestors = update(entry.fees_with_ancestors)?;
+            let fees_with_descendants = update(entry.fees_with_descendants)?;
+            let ancestor_updates = ancestors
+                .iter()
+                .map(|id| Ok((*id, update(self.txs_by_id[id].fees_with_descendants)?)))
+                .collect::<Result<Vec<_>, Error>>()?;
+            let descendant_updates = descendants
+                .iter()
+                .map(|id| Ok((*id, update(self.txs_by_id[id].fees_with_ancestors)?)))
+                .collect::<Result<Vec<_>, Error>>()?;
+
+            // The descendant scores of the entry and its ancestors change, as do the ancestor
+            // scores of the entry and its descendants
+            let with_ancestors = ancestors.iter().chain(std::iter::once(&tx_id)).copied();
+            let with_descendants = descendants.iter().chain(std::iter::once(&tx_id)).copied();
+            for id in with_ancestors.clone() {
+                self.remove_from_descendant_score_index_only(&id);
+            }
+            for id in with_descendants.clone() {
+                self.remove_from_ancestor_score_index_only(&id);
+            }
+
+            {
+                let entry = self.txs_by_id.get_mut(&tx_id).expect("entry");
+                entry.modified_fee = modified_fee;
+                entry.fees_with_ancestors = fees_with_ancestors;
+                entry.fees_with_descendants = fees_with_descendants;
+            }
+            for (id, fees) in ancestor_updates {
+                self.txs_by_id.get_mut(&id).expect("ancestor").fees_with_descendants = fees;
+            }
+            for (id, fees) in descendant_updates {
+                self.txs_by_id.get_mut(&id).expect("descendant").fees_with_ancestors = fees;
+            }
+
+            for id in with_ancestors {
+                let score = self.txs_by_id[&id].descendant_score();
+                self.txs_by_descendant_score.entry(score).or_default().insert(id);
+            }
+            for id in with_descendants {
+                let score = self.txs_by_id[&id].ancestor_score();
+                self.txs_by_ancestor_score.entry(score).or_default().insert(id);
+            }
+        }
+
+        if total_delta == SignedAmount::ZERO {
+            self.fee_deltas.remove(&tx_id);
+        } else {
+            self.fee_deltas.insert(tx_id, total_delta);
+        }
+        Ok(total_delta)
+    }
+
+    fn remove_from_descendant_score_index_only(&mut self, id: &Id<Transaction>) {
+        let score = self.txs_by_id[id].descendant_score();
+        if let Some(entries) = self.txs_by_descendant_score.get_mut(&score) {
+            entries.remove(id);
+            if entries.is_empty() {
+                self.txs_by_descendant_score.remove(&score);
+            }
+        }
+    }
+
+    fn remove_from_ancestor_score_index_only(&mut self, id: &Id<Transaction>) {
+        let score = self.txs_by_id[id].ancestor_score();
+        if let Some(entries) = self.txs_by_ancestor_score.get_mut(&score) {
+            entries.remove(id);
+            if entries.is_empty() {
+                self.txs_by_ancestor_score.remove(&score);
+            }
         }
     }
 
@@ -227,7 +356,7 @@ impl MempoolStore {
     fn update_ancestor_state_for_add(&mut self, entry: &TxMempoolEntry) -> Result<(), Error> {
         for ancestor in entry.unconfirmed_ancestors(self).0 {
             let ancestor = self.txs_by_id.get_mut(&ancestor).expect("ancestor");
-            ancestor.fees_with_descendants = (ancestor.fees_with_descendants + entry.fee)
+            ancestor.fees_with_descendants = (ancestor.fees_with_descendants + entry.modified_fee)
                 .ok_or(TxValidationError::AncestorFeeUpdateOverflow)?;
             ancestor.size_with_descendants += entry.size();
             ancestor.count_with_descendants += 1;
@@ -238,8 +367,8 @@ impl MempoolStore {
     fn update_ancestor_state_for_drop(&mut self, entry: &TxMempoolEntry) {
         for ancestor in entry.unconfirmed_ancestors(self).0 {
             let ancestor = self.txs_by_id.get_mut(&ancestor).expect("ancestor");
-            ancestor.fees_with_descendants =
-                (ancestor.fees_with_descendants - entry.fee).expect("fee with descendants");
+            ancestor.fees_with_descendants = (ancestor.fees_with_descendants - entry.modified_fee)
+                .expect("fee with descendants");
             ancestor.size_with_descendants -= entry.size();
             ancestor.count_with_descendants -= 1;
         }
@@ -256,6 +385,14 @@ impl MempoolStore {
         self.spender_txs.retain(|_, id| *id != entry.tx_id())
     }
 
+    // Marks the outpoints spent by the given transaction as spent by it again, after a
+    // transaction double spending them has been removed from the store
+    pub fn restore_spent_outpoints(&mut self, tx_id: &Id<Transaction>) {
+        if let Some(entry) = self.txs_by_id.get(tx_id).cloned() {
+            self.mark_outpoints_as_spent(&entry)
+        }
+    }
+
     pub fn add_tx(&mut self, entry: TxMempoolEntry) -> Result<(), Error> {
         self.append_to_parents(&entry);
         self.update_ancestor_state_for_add(&entry)?;
@@ -330,8 +467,8 @@ impl MempoolStore {
     fn update_descendant_state_for_drop(&mut self, entry: &TxMempoolEntry) {
         for descendant in entry.unconfirmed_descendants(self).0 {
             let descendant = self.txs_by_id.get_mut(&descendant).expect("descendant");
-            descendant.fees_with_ancestors =
-                (descendant.fees_with_ancestors - entry.fee).expect("fee with descendants");
+            descendant.fees_with_ancestors = (descendant.fees_with_ancestors - entry.modified_fee)
+                .expect("fee with descendants");
             descendant.size_with_ancestors -= entry.size();
             descendant.count_with_ancestors -= 1;
         }
@@ -443,10 +580,22 @@ impl MempoolStore {
     }
 }
 
+// A negative fee delta can bring the modified fee down to zero, but not below
+fn apply_fee_delta(fee: Amount, delta: SignedAmount) -> Amount {
+    fee.into_signed()
+        .and_then(|fee| fee + delta)
+        .map_or(Amount::ZERO, |modified_fee| {
+            modified_fee.into_unsigned().unwrap_or(Amount::ZERO)
+        })
+}
+
 #[derive(Debug, Eq, Clone)]
 pub struct TxMempoolEntry {
     tx: SignedTransaction,
     fee: Amount,
+    // The fee with the operator's fee delta applied, which is used in place of the actual fee
+    // when prioritising the entry
+    modified_fee: Amount,
     parents: BTreeSet<Id<Transaction>>,
     children: BTreeSet<Id<Transaction>>,
     count_with_descendants: usize,
@@ -462,6 +611,7 @@ impl TxMempoolEntry {
     pub fn new(
         tx: SignedTransaction,
         fee: Amount,
+        fee_delta: SignedAmount,
         parents: BTreeSet<Id<Transaction>>,
         ancestors: BTreeSet<TxMempoolEntry>,
         creation_time: Time,
@@ -471,22 +621,24 @@ impl TxMempoolEntry {
                 + tx.encoded_size();
         let ancestor_fees = ancestors
             .iter()
-            .map(|ancestor| ancestor.fee())
+            .map(|ancestor| ancestor.modified_fee())
             .sum::<Option<_>>()
             .ok_or(TxValidationError::AncestorFeeOverflow)?;
+        let modified_fee = apply_fee_delta(fee, fee_delta);
         let fees_with_ancestors =
-            (fee + ancestor_fees).ok_or(TxValidationError::AncestorFeeOverflow)?;
+            (modified_fee + ancestor_fees).ok_or(TxValidationError::AncestorFeeOverflow)?;
         Ok(Self {
             size_with_ancestors,
             count_with_ancestors: 1 + ancestors.len(),
             size_with_descendants: tx.encoded_size(),
             tx,
             fee,
+            modified_fee,
             parents,
             children: BTreeSet::default(),
             count_with_descendants: 1,
             creation_time,
-            fees_with_descendants: fee,
+            fees_with_descendants: modified_fee,
             fees_with_ancestors,
         })
     }
@@ -499,16 +651,31 @@ impl TxMempoolEntry {
         self.fee
     }
 
+    pub fn modified_fee(&self) -> Amount {
+        self.modified_fee
+    }
+
     pub fn count_with_descendants(&self) -> usize {
         self.count_with_descendants
     }
 
+    pub fn size_with_descendants(&self) -> usize {
+        self.size_with_descendants
+    }
+
+    pub fn count_with_ancestors(&self) -> usize {
+        self.count_with_ancestors
+    }
+
+    pub fn size_with_ancestors(&self) -> usize {
+        self.size_with_ancestors
+    }
+
     #[cfg(test)]
     pub fn fees_with_descendants(&self) -> Amount {
         self.fees_with_descendants
     }
 
-    #[cfg(test)]
     pub fn fees_with_ancestors(&self) -> Amount {
         self.fees_with_ancestors
     }
@@ -518,7 +685,7 @@ impl TxMempoolEntry {
             (self.fees_with_descendants
                 / u128::try_from(self.size_with_descendants).expect("conversion"))
             .expect("nonzero tx_size"),
-            (self.fee / u128::try_from(self.tx.encoded_size()).expect("conversion"))
+            (self.modified_fee / u128::try_from(self.tx.encoded_size()).expect("conversion"))
                 .expect("nonzero tx size"),
         )
         .into()
@@ -527,20 +694,18 @@ impl TxMempoolEntry {
     pub fn ancestor_score(&self) -> AncestorScore {
         log::debug!("ancestor score for {:?}", self.tx_id());
         log::debug!(
-            "fees with ancestors: {:?}, size_with_ancestors: {}, fee: {:?}, size: {}",
+            "fees with ancestors: {:?}, size_with_ancestors: {}, modified fee: {:?}, size: {}",
             self.fees_with_ancestors,
             self.size_with_ancestors,
-            self.fee,
+            self.modified_fee,
             self.tx.encoded_size()
         );
-        std::cmp::min(
-            (self.fees_with_ancestors
-                / u128::try_from(self.size_with_ancestors).expect("conversion"))
-            .expect("nonzero tx_size"),
-            (self.fee / u128::try_from(self.tx.encoded_size()).expect("conversion"))
-                .expect("nonzero tx size"),
+        AncestorScore::from_package(
+            self.fees_with_ancestors,
+            self.size_with_ancestors,
+            self.modified_fee,
+            self.tx.encoded_size(),
         )
-        .into()
     }
 
     pub fn tx_id(&self) -> Id<Transaction> {
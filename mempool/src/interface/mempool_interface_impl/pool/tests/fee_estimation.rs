@@ -0,0 +1,90 @@
+// Copyright (c) 2022 RBB S.r.l
+// opensource@mintlayer.org
+// SPDX-License-Identifier: MIT
+// Licensed under the MIT License;
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://github.com/mintlayer/mintlayer-core/blob/master/LICENSE
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use super::*;
+
+#[rstest]
+#[trace]
+#[case(Seed::from_entropy())]
+#[tokio::test]
+async fn estimate_from_confirmed_block(#[case] seed: Seed) -> anyhow::Result<()> {
+    let mut rng = make_seedable_rng(seed);
+    let mut tf = TestFramework::builder(&mut rng).build();
+    let genesis = tf.genesis();
+
+    let num_txs = 2;
+    let mut tx_builder = TransactionBuilder::new().add_input(
+        TxInput::new(OutPointSourceId::BlockReward(genesis.get_id().into()), 0),
+        empty_witness(&mut rng),
+    );
+    for _ in 0..num_txs {
+        tx_builder = tx_builder.add_output(TxOutput::new(
+            OutputValue::Coin(Amount::from_atoms(100_000)),
+            OutputPurpose::Transfer(Destination::AnyoneCanSpend),
+        ))
+    }
+    let initial_tx = tx_builder.build();
+    let initial_tx_id = initial_tx.transaction().get_id();
+    let first_block = tf.make_block_builder().add_transaction(initial_tx).build();
+    let first_block_id = first_block.get_id();
+    tf.process_block(first_block, BlockSource::Local).expect("process_block");
+
+    let fee = 10_000;
+    let txs: Vec<_> = (0..num_txs)
+        .map(|index| {
+            TransactionBuilder::new()
+                .add_input(
+                    TxInput::new(OutPointSourceId::Transaction(initial_tx_id), index),
+                    empty_witness(&mut rng),
+                )
+                .add_output(TxOutput::new(
+                    OutputValue::Coin(Amount::from_atoms(100_000 - fee)),
+                    OutputPurpose::Transfer(Destination::AnyoneCanSpend),
+                ))
+                .build()
+        })
+        .collect();
+    let mut block_builder = tf.make_block_builder();
+    for tx in &txs {
+        block_builder = block_builder.add_transaction(tx.clone());
+    }
+    let second_block = block_builder.build();
+    let second_block_id = second_block.get_id();
+
+    let mut mempool = setup_with_chainstate(tf.chainstate()).await;
+    mempool.new_tip_set(first_block_id, BlockHeight::new(1)).await;
+    for tx in &txs {
+        mempool.add_transaction(tx.clone()).await?;
+    }
+    assert_eq!(mempool.estimate_fee(1, 95)?, None);
+
+    mempool
+        .chainstate_handle
+        .call_mut(|this| this.process_block(second_block, BlockSource::Local))
+        .await??;
+    mempool.new_tip_set(second_block_id, BlockHeight::new(2)).await;
+
+    let tx_fee_rate = FeeRate::from_total_tx_fee(
+        Amount::from_atoms(fee),
+        NonZeroUsize::new(txs[0].encoded_size()).expect("nonzero size"),
+    )?;
+    let estimate = mempool.estimate_fee(1, 95)?.expect("enough data for an estimate");
+    assert!(estimate <= tx_fee_rate);
+    assert!(matches!(
+        mempool.estimate_fee(0, 95),
+        Err(FeeEstimationError::InvalidTarget { target: 0, .. })
+    ));
+    Ok(())
+}
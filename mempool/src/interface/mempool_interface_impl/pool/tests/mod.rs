@@ -49,6 +49,9 @@ use test_utils::random::Seed;
 use tokio::sync::mpsc;
 
 mod expiry;
+mod fee_estimation;
+mod package;
+mod prioritisation;
 mod replacement;
 mod utils;
 
@@ -650,6 +653,7 @@ async fn tx_mempool_entry() -> anyhow::Result<()> {
     let entry1 = TxMempoolEntry::new(
         txs.get(0).unwrap().clone(),
         fee,
+        SignedAmount::ZERO,
         tx1_parents,
         entry_1_ancestors,
         time::get(),
@@ -660,6 +664,7 @@ async fn tx_mempool_entry() -> anyhow::Result<()> {
     let entry2 = TxMempoolEntry::new(
         txs.get(1).unwrap().clone(),
         fee,
+        SignedAmount::ZERO,
         tx2_parents,
         entry_2_ancestors,
         time::get(),
@@ -672,6 +677,7 @@ async fn tx_mempool_entry() -> anyhow::Result<()> {
     let entry3 = TxMempoolEntry::new(
         txs.get(2).unwrap().clone(),
         fee,
+        SignedAmount::ZERO,
         tx3_parents,
         tx3_ancestors,
         time::get(),
@@ -686,6 +692,7 @@ async fn tx_mempool_entry() -> anyhow::Result<()> {
     let entry4 = TxMempoolEntry::new(
         txs.get(3).unwrap().clone(),
         fee,
+        SignedAmount::ZERO,
         tx4_parents,
         tx4_ancestors,
         time::get(),
@@ -694,6 +701,7 @@ async fn tx_mempool_entry() -> anyhow::Result<()> {
     let entry5 = TxMempoolEntry::new(
         txs.get(4).unwrap().clone(),
         fee,
+        SignedAmount::ZERO,
         tx5_parents,
         tx5_ancestors,
         time::get(),
@@ -709,6 +717,7 @@ async fn tx_mempool_entry() -> anyhow::Result<()> {
     let entry6 = TxMempoolEntry::new(
         txs.get(5).unwrap().clone(),
         fee,
+        SignedAmount::ZERO,
         tx6_parents,
         tx6_ancestors,
         time::get(),
@@ -768,6 +777,7 @@ async fn test_bip125_max_replacements(
 
     let tx = tx_builder.build();
     let mut mempool = setup_with_chainstate(tf.chainstate()).await;
+    mempool.package_limits.max_descendant_count = num_potential_replacements;
     let input = tx.transaction().inputs().first().expect("one input").clone();
     let outputs = tx.transaction().outputs().clone();
     let tx_id = tx.transaction().get_id();
@@ -1091,7 +1101,7 @@ async fn rolling_fee(#[case] seed: Seed) -> anyhow::Result<()> {
         .chainstate_handle
         .call_mut(|this| this.process_block(block, BlockSource::Local))
         .await??;
-    mempool.new_tip_set(Id::new(H256::zero()), BlockHeight::new(1));
+    mempool.new_tip_set(Id::new(H256::zero()), BlockHeight::new(1)).await;
     // Because the rolling fee is only updated when we attempt to add a tx to the mempool
     // we need to submit a "dummy" tx to trigger these updates.
 
@@ -1614,6 +1624,7 @@ async fn no_empty_bags_in_indices(#[case] seed: Seed) -> anyhow::Result<()> {
     let outpoint_source_id = OutPointSourceId::Transaction(parent.transaction().get_id());
     mempool.add_transaction(parent).await?;
     let num_child_txs = num_outputs;
+    mempool.package_limits.max_descendant_count = num_child_txs + 1;
     let flags = 0;
     let locktime = 0;
     let fee = get_relay_fee_from_tx_size(estimate_tx_size(1, num_outputs));
@@ -0,0 +1,539 @@
+// Copyright (c) 2022 RBB S.r.l
+// opensource@mintlayer.org
+// SPDX-License-Identifier: MIT
+// Licensed under the MIT License;
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://github.com/mintlayer/mintlayer-core/blob/master/LICENSE
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crypto::random::{CryptoRng, Rng};
+
+use super::*;
+
+async fn mempool_with_parent(
+    rng: &mut (impl Rng + CryptoRng),
+    num_outputs: usize,
+) -> anyhow::Result<(Mempool<SystemUsageEstimator>, Id<Transaction>)> {
+    let tf = TestFramework::builder(rng).build();
+    let genesis = tf.genesis();
+    let mut tx_builder = TransactionBuilder::new().add_input(
+        TxInput::new(OutPointSourceId::BlockReward(genesis.get_id().into()), 0),
+        empty_witness(rng),
+    );
+    for _ in 0..num_outputs {
+        tx_builder = tx_builder.add_output(TxOutput::new(
+            OutputValue::Coin(Amount::from_atoms(1_000_000)),
+            OutputPurpose::Transfer(Destination::AnyoneCanSpend),
+        ));
+    }
+    let parent = tx_builder.build();
+    let parent_id = parent.transaction().get_id();
+
+    let mut mempool = setup_with_chainstate(tf.chainstate()).await;
+    mempool.add_transaction(parent).await?;
+    Ok((mempool, parent_id))
+}
+
+async fn spend_output(
+    mempool: &Mempool<SystemUsageEstimator>,
+    tx_id: Id<Transaction>,
+    index: u32,
+    fee: impl Into<Option<Amount>>,
+) -> anyhow::Result<SignedTransaction> {
+    let flags = 0;
+    let locktime = 0;
+    tx_spend_input(
+        mempool,
+        TxInput::new(OutPointSourceId::Transaction(tx_id), index),
+        InputWitness::NoSignature(Some(DUMMY_WITNESS_MSG.to_vec())),
+        fee,
+        flags,
+        locktime,
+    )
+    .await
+}
+
+async fn spend_first_output(
+    mempool: &Mempool<SystemUsageEstimator>,
+    tx_id: Id<Transaction>,
+) -> anyhow::Result<SignedTransaction> {
+    spend_output(mempool, tx_id, 0, None).await
+}
+
+#[rstest]
+#[trace]
+#[case(Seed::from_entropy())]
+#[tokio::test]
+async fn too_many_ancestors(#[case] seed: Seed) -> anyhow::Result<()> {
+    let mut rng = make_seedable_rng(seed);
+    let (mut mempool, parent_id) = mempool_with_parent(&mut rng, 1).await?;
+    mempool.package_limits.max_ancestor_count = 3;
+
+    let child = spend_first_output(&mempool, parent_id).await?;
+    let child_id = child.transaction().get_id();
+    mempool.add_transaction(child).await?;
+
+    let grandchild = spend_first_output(&mempool, child_id).await?;
+    let grandchild_id = grandchild.transaction().get_id();
+    mempool.add_transaction(grandchild).await?;
+
+    let great_grandchild = spend_first_output(&mempool, grandchild_id).await?;
+    let great_grandchild_id = great_grandchild.transaction().get_id();
+    assert!(matches!(
+        mempool.add_transaction(great_grandchild).await,
+        Err(Error::TxValidationError(
+            TxValidationError::TooManyAncestors { count: 4, limit: 3 }
+        ))
+    ));
+    assert!(!mempool.contains_transaction(&great_grandchild_id));
+    mempool.store.assert_valid();
+    Ok(())
+}
+
+#[rstest]
+#[trace]
+#[case(Seed::from_entropy())]
+#[tokio::test]
+async fn too_many_descendants(#[case] seed: Seed) -> anyhow::Result<()> {
+    let mut rng = make_seedable_rng(seed);
+    let num_outputs = 3;
+    let (mut mempool, parent_id) = mempool_with_parent(&mut rng, num_outputs).await?;
+    mempool.package_limits.max_descendant_count = num_outputs;
+
+    let mut children = Vec::new();
+    for i in 0..num_outputs {
+        let child = spend_output(&mempool, parent_id, u32::try_from(i).unwrap(), None).await?;
+        children.push(child);
+    }
+
+    let last_child = children.pop().expect("children not empty");
+    for child in children {
+        mempool.add_transaction(child).await?;
+    }
+    assert!(matches!(
+        mempool.add_transaction(last_child).await,
+        Err(Error::TxValidationError(TxValidationError::TooManyDescendants {
+            ancestor,
+            count,
+            limit
+        })) if ancestor == parent_id && count == num_outputs + 1 && limit == num_outputs
+    ));
+    mempool.store.assert_valid();
+    Ok(())
+}
+
+#[rstest]
+#[trace]
+#[case(Seed::from_entropy())]
+#[tokio::test]
+async fn descendant_size_limit(#[case] seed: Seed) -> anyhow::Result<()> {
+    let mut rng = make_seedable_rng(seed);
+    let (mut mempool, parent_id) = mempool_with_parent(&mut rng, 1).await?;
+
+    let child = spend_first_output(&mempool, parent_id).await?;
+    let child_id = child.transaction().get_id();
+    let child_size = child.encoded_size();
+    mempool.add_transaction(child).await?;
+
+    // Only the parent's descendants exceed the limit, the child's don't
+    let grandchild = spend_first_output(&mempool, child_id).await?;
+    let parent_size = mempool.store.get_entry(&parent_id).expect("parent").size();
+    mempool.package_limits.max_descendant_size =
+        parent_size + child_size + grandchild.encoded_size() - 1;
+    assert!(matches!(
+        mempool.add_transaction(grandchild).await,
+        Err(Error::TxValidationError(
+            TxValidationError::DescendantSizeLimitExceeded { ancestor, .. }
+        )) if ancestor == parent_id
+    ));
+    mempool.store.assert_valid();
+    Ok(())
+}
+
+// Selects transactions by their own fee rate, without taking their ancestors into account, which
+// is how block assembly worked before package selection. A transaction can only be selected once
+// all of its in-mempool parents have been.
+fn collect_txs_by_individual_fee_rate(
+    mempool: &Mempool<SystemUsageEstimator>,
+    size_limit: usize,
+) -> DefaultTxAccumulator {
+    let mut entries = mempool.store.txs_by_id.values().collect::<Vec<_>>();
+    entries.sort_by_key(|entry| {
+        std::cmp::Reverse(
+            (entry.fee() / u128::try_from(entry.size()).expect("conversion"))
+                .expect("nonzero tx size"),
+        )
+    });
+
+    let mut tx_accumulator = DefaultTxAccumulator::new(size_limit);
+    let mut selected = BTreeSet::new();
+    for entry in entries {
+        if tx_accumulator.done() {
+            break;
+        }
+        let parents_selected = entry
+            .tx()
+            .transaction()
+            .inputs()
+            .iter()
+            .filter_map(|input| input.outpoint().tx_id().get_tx_id().cloned())
+            .filter(|id| mempool.contains_transaction(id))
+            .all(|id| selected.contains(&id));
+        if parents_selected {
+            tx_accumulator.add_tx(entry.tx().clone(), entry.fee()).expect("fee overflow");
+            selected.insert(entry.tx_id());
+        }
+    }
+    tx_accumulator
+}
+
+#[rstest]
+#[trace]
+#[case(Seed::from_entropy())]
+#[tokio::test]
+async fn child_pays_for_parent(#[case] seed: Seed) -> anyhow::Result<()> {
+    let mut rng = make_seedable_rng(seed);
+    let (mut mempool, initial_tx_id) = mempool_with_confirmed_tx(&mut rng, 3).await;
+
+    // A parent paying only the relay fee, and a child paying enough for both of them
+    let parent_fee = Amount::from_atoms(get_relay_fee_from_tx_size(TX_SPEND_INPUT_SIZE));
+    let parent = spend_output(&mempool, initial_tx_id, 0, parent_fee).await?;
+    let parent_id = parent.transaction().get_id();
+    let parent_size = parent.encoded_size();
+    mempool.add_transaction(parent).await?;
+
+    let child = spend_output(&mempool, parent_id, 0, Amount::from_atoms(10_000)).await?;
+    let child_id = child.transaction().get_id();
+    let child_size = child.encoded_size();
+    mempool.add_transaction(child).await?;
+
+    // Unrelated transactions paying a better fee rate than the parent, but a worse one than the
+    // parent and child together
+    for index in 1..3 {
+        let tx = spend_output(&mempool, initial_tx_id, index, Amount::from_atoms(2_000)).await?;
+        mempool.add_transaction(tx).await?;
+    }
+
+    let size_limit = parent_size + child_size;
+    let returned_accumulator = mempool.collect_txs(Box::new(DefaultTxAccumulator::new(size_limit)));
+    let collected_ids = returned_accumulator
+        .transactions()
+        .iter()
+        .map(|tx| tx.transaction().get_id())
+        .collect::<Vec<_>>();
+    assert_eq!(collected_ids, vec![parent_id, child_id]);
+
+    let individual_fee_rate_accumulator = collect_txs_by_individual_fee_rate(&mempool, size_limit);
+    assert!(!individual_fee_rate_accumulator
+        .transactions()
+        .iter()
+        .any(|tx| tx.transaction().get_id() == child_id));
+    assert!(returned_accumulator.total_fees() > individual_fee_rate_accumulator.total_fees());
+    Ok(())
+}
+
+#[rstest]
+#[trace]
+#[case(Seed::from_entropy())]
+#[tokio::test]
+async fn collected_parents_precede_children(#[case] seed: Seed) -> anyhow::Result<()> {
+    let mut rng = make_seedable_rng(seed);
+    let (mut mempool, parent_id) = mempool_with_parent(&mut rng, 1).await?;
+
+    let mut tx_id = parent_id;
+    for _ in 0..5 {
+        let tx = spend_first_output(&mempool, tx_id).await?;
+        tx_id = tx.transaction().get_id();
+        mempool.add_transaction(tx).await?;
+    }
+
+    let returned_accumulator =
+        mempool.collect_txs(Box::new(DefaultTxAccumulator::new(MAX_BLOCK_SIZE_BYTES)));
+    let collected_txs = returned_accumulator.transactions();
+    assert_eq!(collected_txs.len(), 6);
+    let mut seen = BTreeSet::new();
+    for tx in collected_txs {
+        let parents = tx
+            .transaction()
+            .inputs()
+            .iter()
+            .filter_map(|input| input.outpoint().tx_id().get_tx_id().cloned())
+            .filter(|id| mempool.contains_transaction(id));
+        for parent in parents {
+            assert!(seen.contains(&parent));
+        }
+        seen.insert(tx.transaction().get_id());
+    }
+    Ok(())
+}
+
+// Spends the first output of the given transaction, which is assumed to be worth `input_value`,
+// into a single output
+fn spend_with_fee(
+    rng: &mut impl Rng,
+    tx_id: Id<Transaction>,
+    input_value: u128,
+    fee: u128,
+) -> SignedTransaction {
+    TransactionBuilder::new()
+        .add_input(
+            TxInput::new(OutPointSourceId::Transaction(tx_id), 0),
+            empty_witness(rng),
+        )
+        .add_output(TxOutput::new(
+            OutputValue::Coin(Amount::from_atoms(input_value - fee)),
+            OutputPurpose::Transfer(Destination::AnyoneCanSpend),
+        ))
+        .build()
+}
+
+#[rstest]
+#[trace]
+#[case(Seed::from_entropy())]
+#[tokio::test]
+async fn package_child_pays_for_parent(#[case] seed: Seed) -> anyhow::Result<()> {
+    let mut rng = make_seedable_rng(seed);
+    let (mut mempool, initial_tx_id) = mempool_with_confirmed_tx(&mut rng, 1).await;
+
+    let parent = spend_with_fee(&mut rng, initial_tx_id, CONFIRMED_OUTPUT_VALUE, 0);
+    let parent_id = parent.transaction().get_id();
+    let child = spend_with_fee(&mut rng, parent_id, CONFIRMED_OUTPUT_VALUE, 10_000);
+    let child_id = child.transaction().get_id();
+
+    // The parent alone doesn't pay the relay fee
+    assert!(matches!(
+        mempool.add_transaction(parent.clone()).await,
+        Err(Error::TxValidationError(
+            TxValidationError::InsufficientFeesToRelay { .. }
+        ))
+    ));
+    assert!(!mempool.contains_transaction(&parent_id));
+
+    let results = mempool.add_package(vec![parent, child]).await?;
+    assert_eq!(results.len(), 2);
+    assert!(results.iter().all(|result| result.is_ok()));
+    assert!(mempool.contains_transaction(&parent_id));
+    assert!(mempool.contains_transaction(&child_id));
+    mempool.store.assert_valid();
+    Ok(())
+}
+
+#[rstest]
+#[trace]
+#[case(Seed::from_entropy())]
+#[tokio::test]
+async fn package_fee_too_low(#[case] seed: Seed) -> anyhow::Result<()> {
+    let mut rng = make_seedable_rng(seed);
+    let (mut mempool, initial_tx_id) = mempool_with_confirmed_tx(&mut rng, 1).await;
+
+    let parent = spend_with_fee(&mut rng, initial_tx_id, CONFIRMED_OUTPUT_VALUE, 0);
+    let parent_id = parent.transaction().get_id();
+    let child = spend_with_fee(&mut rng, parent_id, CONFIRMED_OUTPUT_VALUE, 1);
+    let child_id = child.transaction().get_id();
+
+    let results = mempool.add_package(vec![parent, child]).await?;
+    assert_eq!(results.len(), 2);
+    assert!(results.iter().all(|result| matches!(
+        result,
+        Err(Error::TxValidationError(
+            TxValidationError::PackageFeeTooLow { .. }
+        ))
+    )));
+    assert!(!mempool.contains_transaction(&parent_id));
+    assert!(!mempool.contains_transaction(&child_id));
+    mempool.store.assert_valid();
+    Ok(())
+}
+
+#[rstest]
+#[trace]
+#[case(Seed::from_entropy())]
+#[tokio::test]
+async fn package_results_per_transaction(#[case] seed: Seed) -> anyhow::Result<()> {
+    let mut rng = make_seedable_rng(seed);
+    let (mut mempool, initial_tx_id) = mempool_with_confirmed_tx(&mut rng, 1).await;
+
+    let parent = spend_with_fee(&mut rng, initial_tx_id, CONFIRMED_OUTPUT_VALUE, 1_000);
+    let parent_id = parent.transaction().get_id();
+    let child = spend_with_fee(&mut rng, parent_id, CONFIRMED_OUTPUT_VALUE - 1_000, 1_000);
+    let invalid_tx = TransactionBuilder::new().build();
+
+    let results = mempool.add_package(vec![parent, child, invalid_tx]).await?;
+    assert_eq!(results.len(), 3);
+    assert!(results[0].is_ok());
+    assert!(results[1].is_ok());
+    assert!(matches!(
+        results[2],
+        Err(Error::TxValidationError(TxValidationError::NoInputs))
+    ));
+    assert_eq!(mempool.store.txs_by_id.len(), 2);
+    mempool.store.assert_valid();
+    Ok(())
+}
+
+#[rstest]
+#[trace]
+#[case(Seed::from_entropy())]
+#[tokio::test]
+async fn package_not_sorted(#[case] seed: Seed) -> anyhow::Result<()> {
+    let mut rng = make_seedable_rng(seed);
+    let (mut mempool, initial_tx_id) = mempool_with_confirmed_tx(&mut rng, 1).await;
+
+    let parent = spend_with_fee(&mut rng, initial_tx_id, CONFIRMED_OUTPUT_VALUE, 1_000);
+    let parent_id = parent.transaction().get_id();
+    let child = spend_with_fee(&mut rng, parent_id, CONFIRMED_OUTPUT_VALUE - 1_000, 1_000);
+    let child_id = child.transaction().get_id();
+
+    assert!(matches!(
+        mempool.add_package(vec![child, parent]).await,
+        Err(Error::PackageValidationError(
+            PackageValidationError::NotTopologicallySorted { child: c, parent: p }
+        )) if c == child_id && p == parent_id
+    ));
+    assert!(mempool.store.is_empty());
+    Ok(())
+}
+
+// A replaceable transaction spending the first output of the given confirmed transaction
+fn replaceable_spend(rng: &mut impl Rng, tx_id: Id<Transaction>, fee: u128) -> SignedTransaction {
+    TransactionBuilder::new()
+        .add_input(
+            TxInput::new(OutPointSourceId::Transaction(tx_id), 0),
+            empty_witness(rng),
+        )
+        .add_output(TxOutput::new(
+            OutputValue::Coin(Amount::from_atoms(CONFIRMED_OUTPUT_VALUE - fee)),
+            OutputPurpose::Transfer(Destination::AnyoneCanSpend),
+        ))
+        .with_flags(1)
+        .build()
+}
+
+#[rstest]
+#[trace]
+#[case(Seed::from_entropy())]
+#[tokio::test]
+async fn package_replacement_fee_too_low_rolls_back(#[case] seed: Seed) -> anyhow::Result<()> {
+    let mut rng = make_seedable_rng(seed);
+    let (mut mempool, initial_tx_id) = mempool_with_confirmed_tx(&mut rng, 1).await;
+
+    let original_fee = 1_000;
+    let original = replaceable_spend(&mut rng, initial_tx_id, original_fee);
+    let original_id = original.transaction().get_id();
+    let outpoint = original.transaction().inputs()[0].outpoint().clone();
+    mempool.add_transaction(original).await?;
+
+    // The replacement pays exactly enough to replace the original on its own, but not enough to
+    // also pay for the bandwidth of its child, which pays no fee at all
+    let replacement_size = spend_with_fee(&mut rng, initial_tx_id, CONFIRMED_OUTPUT_VALUE, 2_000)
+        .encoded_size() as u128;
+    let replacement_fee = original_fee + replacement_size;
+    let replacement = spend_with_fee(
+        &mut rng,
+        initial_tx_id,
+        CONFIRMED_OUTPUT_VALUE,
+        replacement_fee,
+    );
+    let replacement_id = replacement.transaction().get_id();
+    let child = spend_with_fee(
+        &mut rng,
+        replacement_id,
+        CONFIRMED_OUTPUT_VALUE - replacement_fee,
+        0,
+    );
+    let child_id = child.transaction().get_id();
+
+    let results = mempool.add_package(vec![replacement, child]).await?;
+    assert_eq!(results.len(), 2);
+    assert!(results.iter().all(|result| matches!(
+        result,
+        Err(Error::TxValidationError(
+            TxValidationError::PackageFeeTooLow { .. }
+        ))
+    )));
+    assert!(mempool.contains_transaction(&original_id));
+    assert!(!mempool.contains_transaction(&replacement_id));
+    assert!(!mempool.contains_transaction(&child_id));
+    assert_eq!(
+        mempool.store.find_conflicting_tx(&outpoint),
+        Some(original_id)
+    );
+    assert_eq!(mempool.store.txs_by_id.len(), 1);
+    mempool.store.assert_valid();
+    Ok(())
+}
+
+#[rstest]
+#[trace]
+#[case(Seed::from_entropy())]
+#[tokio::test]
+async fn package_replaces_conflicts(#[case] seed: Seed) -> anyhow::Result<()> {
+    let mut rng = make_seedable_rng(seed);
+    let (mut mempool, initial_tx_id) = mempool_with_confirmed_tx(&mut rng, 1).await;
+
+    let original_fee = 1_000;
+    let original = replaceable_spend(&mut rng, initial_tx_id, original_fee);
+    let original_id = original.transaction().get_id();
+    let outpoint = original.transaction().inputs()[0].outpoint().clone();
+    mempool.add_transaction(original).await?;
+
+    let replacement_fee = 2_000;
+    let replacement = spend_with_fee(
+        &mut rng,
+        initial_tx_id,
+        CONFIRMED_OUTPUT_VALUE,
+        replacement_fee,
+    );
+    let replacement_id = replacement.transaction().get_id();
+    let child = spend_with_fee(
+        &mut rng,
+        replacement_id,
+        CONFIRMED_OUTPUT_VALUE - replacement_fee,
+        1_000,
+    );
+    let child_id = child.transaction().get_id();
+
+    let results = mempool.add_package(vec![replacement, child]).await?;
+    assert!(results.iter().all(|result| result.is_ok()));
+    assert!(!mempool.contains_transaction(&original_id));
+    assert!(mempool.contains_transaction(&replacement_id));
+    assert!(mempool.contains_transaction(&child_id));
+    assert_eq!(
+        mempool.store.find_conflicting_tx(&outpoint),
+        Some(replacement_id)
+    );
+    mempool.store.assert_valid();
+    Ok(())
+}
+
+#[rstest]
+#[trace]
+#[case(Seed::from_entropy())]
+#[tokio::test]
+async fn package_with_conflicting_transactions(#[case] seed: Seed) -> anyhow::Result<()> {
+    let mut rng = make_seedable_rng(seed);
+    let (mut mempool, initial_tx_id) = mempool_with_confirmed_tx(&mut rng, 1).await;
+
+    let first = replaceable_spend(&mut rng, initial_tx_id, 1_000);
+    let first_id = first.transaction().get_id();
+    let second = spend_with_fee(&mut rng, initial_tx_id, CONFIRMED_OUTPUT_VALUE, 5_000);
+    let second_id = second.transaction().get_id();
+
+    assert!(matches!(
+        mempool.add_package(vec![first, second]).await,
+        Err(Error::PackageValidationError(
+            PackageValidationError::ConflictingTransactions { tx, conflict }
+        )) if tx == second_id && conflict == first_id
+    ));
+    assert!(mempool.store.is_empty());
+    assert!(mempool.store.spender_txs.is_empty());
+    mempool.store.assert_valid();
+    Ok(())
+}
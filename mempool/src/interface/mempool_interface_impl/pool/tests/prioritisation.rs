@@ -0,0 +1,178 @@
+// Copyright (c) 2022 RBB S.r.l
+// opensource@mintlayer.org
+// SPDX-License-Identifier: MIT
+// Licensed under the MIT License;
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://github.com/mintlayer/mintlayer-core/blob/master/LICENSE
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crypto::random::Rng;
+
+use super::*;
+
+// Spends the given output, which is assumed to be worth `CONFIRMED_OUTPUT_VALUE`, into a single
+// output
+fn spend_with_fee(
+    rng: &mut impl Rng,
+    tx_id: Id<Transaction>,
+    index: u32,
+    fee: u128,
+) -> SignedTransaction {
+    TransactionBuilder::new()
+        .add_input(
+            TxInput::new(OutPointSourceId::Transaction(tx_id), index),
+            empty_witness(rng),
+        )
+        .add_output(TxOutput::new(
+            OutputValue::Coin(Amount::from_atoms(CONFIRMED_OUTPUT_VALUE - fee)),
+            OutputPurpose::Transfer(Destination::AnyoneCanSpend),
+        ))
+        .build()
+}
+
+#[rstest]
+#[trace]
+#[case(Seed::from_entropy())]
+#[tokio::test]
+async fn prioritised_tx_collected_first(#[case] seed: Seed) -> anyhow::Result<()> {
+    let mut rng = make_seedable_rng(seed);
+    let (mut mempool, initial_tx_id) = mempool_with_confirmed_tx(&mut rng, 2).await;
+
+    let high_fee_tx = spend_with_fee(&mut rng, initial_tx_id, 0, 10_000);
+    let low_fee_tx = spend_with_fee(&mut rng, initial_tx_id, 1, 1_000);
+    let low_fee_tx_id = low_fee_tx.transaction().get_id();
+    mempool.add_transaction(high_fee_tx).await?;
+    mempool.add_transaction(low_fee_tx.clone()).await?;
+
+    let total_delta =
+        mempool.prioritise_transaction(low_fee_tx_id, SignedAmount::from_atoms(100_000))?;
+    assert_eq!(total_delta, SignedAmount::from_atoms(100_000));
+
+    // There is only room for one of the transactions
+    let tx_accumulator = DefaultTxAccumulator::new(low_fee_tx.encoded_size());
+    let tx_accumulator = mempool.collect_txs(Box::new(tx_accumulator));
+    assert_eq!(*tx_accumulator.transactions(), vec![low_fee_tx]);
+    // The delta doesn't change the fee that is actually paid
+    assert_eq!(tx_accumulator.total_fees(), Amount::from_atoms(1_000));
+    assert_eq!(
+        *tx_accumulator.transaction_fees(),
+        vec![Amount::from_atoms(1_000)]
+    );
+    Ok(())
+}
+
+#[rstest]
+#[trace]
+#[case(Seed::from_entropy())]
+#[tokio::test]
+async fn delta_applied_on_arrival(#[case] seed: Seed) -> anyhow::Result<()> {
+    let mut rng = make_seedable_rng(seed);
+    let (mut mempool, initial_tx_id) = mempool_with_confirmed_tx(&mut rng, 1).await;
+
+    let fee = 1_000;
+    let tx = spend_with_fee(&mut rng, initial_tx_id, 0, fee);
+    let tx_id = tx.transaction().get_id();
+
+    mempool.prioritise_transaction(tx_id, SignedAmount::from_atoms(5_000))?;
+    let total_delta = mempool.prioritise_transaction(tx_id, SignedAmount::from_atoms(-2_000))?;
+    assert_eq!(total_delta, SignedAmount::from_atoms(3_000));
+
+    mempool.add_transaction(tx).await?;
+    let entry = mempool.store.get_entry(&tx_id).expect("entry");
+    assert_eq!(entry.fee(), Amount::from_atoms(fee));
+    assert_eq!(entry.modified_fee(), Amount::from_atoms(fee + 3_000));
+    assert_eq!(entry.fees_with_ancestors(), entry.modified_fee());
+    Ok(())
+}
+
+#[rstest]
+#[trace]
+#[case(Seed::from_entropy())]
+#[tokio::test]
+async fn negative_delta_does_not_affect_admission(#[case] seed: Seed) -> anyhow::Result<()> {
+    let mut rng = make_seedable_rng(seed);
+    let (mut mempool, initial_tx_id) = mempool_with_confirmed_tx(&mut rng, 1).await;
+
+    let tx = spend_with_fee(&mut rng, initial_tx_id, 0, 1_000);
+    let tx_id = tx.transaction().get_id();
+    mempool.prioritise_transaction(tx_id, SignedAmount::from_atoms(-1_000_000))?;
+
+    mempool.add_transaction(tx).await?;
+    let entry = mempool.store.get_entry(&tx_id).expect("entry");
+    assert_eq!(entry.fee(), Amount::from_atoms(1_000));
+    assert_eq!(entry.modified_fee(), Amount::ZERO);
+    Ok(())
+}
+
+#[rstest]
+#[trace]
+#[case(Seed::from_entropy())]
+#[tokio::test]
+async fn prioritising_child_protects_parent_from_eviction(
+    #[case] seed: Seed,
+) -> anyhow::Result<()> {
+    let mut rng = make_seedable_rng(seed);
+    let (mut mempool, initial_tx_id) = mempool_with_confirmed_tx(&mut rng, 2).await;
+
+    let parent = spend_with_fee(&mut rng, initial_tx_id, 0, 1_000);
+    let parent_id = parent.transaction().get_id();
+    let unrelated = spend_with_fee(&mut rng, initial_tx_id, 1, 5_000);
+    let unrelated_id = unrelated.transaction().get_id();
+    mempool.add_transaction(parent).await?;
+    mempool.add_transaction(unrelated).await?;
+
+    let child = TransactionBuilder::new()
+        .add_input(
+            TxInput::new(OutPointSourceId::Transaction(parent_id), 0),
+            empty_witness(&mut rng),
+        )
+        .add_output(TxOutput::new(
+            OutputValue::Coin(Amount::from_atoms(CONFIRMED_OUTPUT_VALUE - 4_000)),
+            OutputPurpose::Transfer(Destination::AnyoneCanSpend),
+        ))
+        .build();
+    let child_id = child.transaction().get_id();
+    mempool.add_transaction(child).await?;
+
+    // Transactions with the lowest descendant score are the first to be evicted. The child pays a
+    // fee of 3_000, so the parent's descendant score is the lowest.
+    let first_to_evict = |mempool: &Mempool<SystemUsageEstimator>| -> Id<Transaction> {
+        *mempool
+            .store
+            .txs_by_descendant_score
+            .values()
+            .flatten()
+            .next()
+            .expect("not empty")
+    };
+    assert_eq!(first_to_evict(&mempool), parent_id);
+
+    mempool.prioritise_transaction(child_id, SignedAmount::from_atoms(10_000))?;
+    let parent_entry = mempool.store.get_entry(&parent_id).expect("parent");
+    assert_eq!(
+        parent_entry.fees_with_descendants(),
+        Amount::from_atoms(14_000)
+    );
+    let child_entry = mempool.store.get_entry(&child_id).expect("child");
+    assert_eq!(
+        child_entry.fees_with_ancestors(),
+        Amount::from_atoms(14_000)
+    );
+    assert_eq!(first_to_evict(&mempool), unrelated_id);
+
+    // Removing the delta restores the original order
+    mempool.prioritise_transaction(child_id, SignedAmount::from_atoms(-10_000))?;
+    assert_eq!(first_to_evict(&mempool), parent_id);
+    assert_eq!(
+        mempool.store.txs_by_ancestor_score.values().flatten().count(),
+        3
+    );
+    Ok(())
+}
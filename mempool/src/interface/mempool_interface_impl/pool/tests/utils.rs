@@ -16,6 +16,7 @@
 use common::chain::tokens::OutputValue;
 use common::chain::OutPoint;
 use common::primitives::H256;
+use crypto::random::{CryptoRng, Rng};
 
 use super::*;
 
@@ -79,3 +80,31 @@ pub fn estimate_tx_size(num_inputs: usize, num_outputs: usize) -> usize {
     );
     result
 }
+
+// Sets up a mempool on top of a chain in which a transaction with the given number of outputs has
+// been confirmed
+pub async fn mempool_with_confirmed_tx(
+    rng: &mut (impl Rng + CryptoRng),
+    num_outputs: usize,
+) -> (Mempool<SystemUsageEstimator>, Id<Transaction>) {
+    let mut tf = TestFramework::builder(rng).build();
+    let genesis = tf.genesis();
+
+    let mut tx_builder = TransactionBuilder::new().add_input(
+        TxInput::new(OutPointSourceId::BlockReward(genesis.get_id().into()), 0),
+        empty_witness(rng),
+    );
+    for _ in 0..num_outputs {
+        tx_builder = tx_builder.add_output(TxOutput::new(
+            OutputValue::Coin(Amount::from_atoms(CONFIRMED_OUTPUT_VALUE)),
+            OutputPurpose::Transfer(Destination::AnyoneCanSpend),
+        ))
+    }
+    let initial_tx = tx_builder.build();
+    let initial_tx_id = initial_tx.transaction().get_id();
+    let block = tf.make_block_builder().add_transaction(initial_tx).build();
+    tf.process_block(block, BlockSource::Local).expect("process_block");
+    (setup_with_chainstate(tf.chainstate()).await, initial_tx_id)
+}
+
+pub const CONFIRMED_OUTPUT_VALUE: u128 = 100_000;
@@ -19,9 +19,11 @@ use std::sync::Arc;
 
 use crate::error::Error;
 use crate::interface::mempool_interface::MempoolInterface;
+use crate::FeeRate;
 use crate::MempoolEvent;
 use common::chain::signed_transaction::SignedTransaction;
 use common::chain::transaction::Transaction;
+use common::primitives::amount::SignedAmount;
 use common::primitives::Id;
 
 use crate::tx_accumulator::TransactionAccumulator;
@@ -37,6 +39,14 @@ impl<
     async fn add_transaction(&mut self, tx: SignedTransaction) -> Result<(), Error> {
         self.deref_mut().add_transaction(tx).await
     }
+
+    async fn add_package(
+        &mut self,
+        txs: Vec<SignedTransaction>,
+    ) -> Result<Vec<Result<(), Error>>, Error> {
+        self.deref_mut().add_package(txs).await
+    }
+
     async fn get_all(&self) -> Result<Vec<SignedTransaction>, Error> {
         self.deref().get_all().await
     }
@@ -52,6 +62,22 @@ impl<
         self.deref().collect_txs(tx_accumulator).await
     }
 
+    async fn prioritise_transaction(
+        &mut self,
+        tx_id: Id<Transaction>,
+        fee_delta: SignedAmount,
+    ) -> Result<SignedAmount, Error> {
+        self.deref_mut().prioritise_transaction(tx_id, fee_delta).await
+    }
+
+    async fn estimate_fee(
+        &self,
+        target_blocks: usize,
+        confidence_percent: u8,
+    ) -> Result<Option<FeeRate>, Error> {
+        self.deref().estimate_fee(target_blocks, confidence_percent).await
+    }
+
     async fn subscribe_to_events(
         &mut self,
         handler: Arc<dyn Fn(MempoolEvent) + Send + Sync>,
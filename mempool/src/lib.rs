@@ -15,6 +15,7 @@
 
 #![deny(clippy::clone_on_ref_ptr)]
 
+use std::path::PathBuf;
 use std::sync::Arc;
 
 use chainstate::chainstate_interface::ChainstateInterface;
@@ -22,11 +23,13 @@ use common::chain::{Block, ChainConfig};
 use common::primitives::{BlockHeight, Id};
 use common::time_getter::TimeGetter;
 pub use interface::mempool_interface::MempoolInterface;
+pub use interface::mempool_interface_impl::FeeRate;
 
 use crate::error::Error as MempoolError;
 use crate::interface::mempool_interface_impl::MempoolInterfaceImpl;
 use get_memory_usage::GetMemoryUsage;
 
+pub use crate::config::{MempoolConfig, PackageLimits};
 pub use crate::get_memory_usage::SystemUsageEstimator;
 
 mod config;
@@ -45,13 +48,18 @@ impl subsystem::Subsystem for Box<dyn MempoolInterface> {}
 
 pub type MempoolHandle = subsystem::Handle<Box<dyn MempoolInterface>>;
 
+/// File under `datadir` where the fee estimator statistics are saved
+pub const FEE_ESTIMATES_FILE_NAME: &str = "fee_estimates.dat";
+
 pub type Result<T> = core::result::Result<T, MempoolError>;
 
 pub fn make_mempool<M>(
     chain_config: Arc<ChainConfig>,
     chainstate_handle: subsystem::Handle<Box<dyn ChainstateInterface>>,
+    mempool_config: MempoolConfig,
     time_getter: TimeGetter,
     memory_usage_estimator: M,
+    fee_estimates_path: Option<PathBuf>,
 ) -> crate::Result<Box<dyn MempoolInterface>>
 where
     M: GetMemoryUsage + 'static + Send + Sync,
@@ -59,7 +67,9 @@ where
     Ok(Box::new(MempoolInterfaceImpl::new(
         chain_config,
         chainstate_handle,
+        mempool_config,
         time_getter,
         memory_usage_estimator,
+        fee_estimates_path,
     )?))
 }
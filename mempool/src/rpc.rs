@@ -15,14 +15,99 @@
 
 //! Mempool subsystem RPC handler
 
+use common::chain::signed_transaction::SignedTransaction;
+use common::chain::Transaction;
+use common::primitives::amount::SignedAmount;
+use common::primitives::Id;
+use serialization::Decode;
+use subsystem::subsystem::CallError;
+
+use crate::config::FEE_ESTIMATOR_DEFAULT_CONFIDENCE_PERCENT;
+use crate::error::Error;
+
 #[rpc::rpc(server, namespace = "mempool")]
 trait MempoolRpc {
     #[method(name = "dummy")]
     fn dummy(&self) -> rpc::Result<String>;
+
+    /// Submit a package of hex-encoded transactions, sorted so that parents come before their
+    /// children. For each transaction, returns `null` if it was accepted, or the reason it was
+    /// rejected otherwise.
+    #[method(name = "submit_package")]
+    async fn submit_package(&self, txs_hex: Vec<String>) -> rpc::Result<Vec<Option<String>>>;
+
+    /// Add `fee_delta` atoms (which may be negative) to the fee the mempool considers the given
+    /// transaction to pay when selecting transactions for blocks and choosing which ones to evict.
+    /// The actual fee of the transaction is not affected. The transaction doesn't have to be in
+    /// the mempool yet. Returns the total fee delta of the transaction.
+    #[method(name = "prioritise_transaction")]
+    async fn prioritise_transaction(
+        &self,
+        tx_id: Id<Transaction>,
+        fee_delta: i128,
+    ) -> rpc::Result<i128>;
+
+    /// Estimate the fee rate, in atoms per kilobyte, a transaction needs to pay to get confirmed
+    /// within `target_blocks` blocks with the given confidence (in percent, 95 by default).
+    /// Returns `null` if the mempool has not seen enough transactions get confirmed yet.
+    #[method(name = "estimate_fee")]
+    async fn estimate_fee(
+        &self,
+        target_blocks: usize,
+        confidence_percent: Option<u8>,
+    ) -> rpc::Result<Option<u128>>;
 }
 
+#[async_trait::async_trait]
 impl MempoolRpcServer for super::MempoolHandle {
     fn dummy(&self) -> rpc::Result<String> {
         Ok("dummy".to_string())
     }
+
+    async fn submit_package(&self, txs_hex: Vec<String>) -> rpc::Result<Vec<Option<String>>> {
+        let txs = txs_hex
+            .into_iter()
+            .map(|tx_hex| {
+                let tx_data = hex::decode(tx_hex).map_err(rpc::Error::to_call_error)?;
+                SignedTransaction::decode(&mut &tx_data[..]).map_err(rpc::Error::to_call_error)
+            })
+            .collect::<rpc::Result<Vec<_>>>()?;
+        let res = self.call_async_mut(|this| Box::pin(this.add_package(txs))).await;
+        let results = handle_error(res)?;
+        Ok(results
+            .into_iter()
+            .map(|result| result.err().map(|err| err.to_string()))
+            .collect())
+    }
+
+    async fn prioritise_transaction(
+        &self,
+        tx_id: Id<Transaction>,
+        fee_delta: i128,
+    ) -> rpc::Result<i128> {
+        let fee_delta = SignedAmount::from_atoms(fee_delta);
+        let res = self
+            .call_async_mut(move |this| Box::pin(this.prioritise_transaction(tx_id, fee_delta)))
+            .await;
+        handle_error(res).map(|total_delta| total_delta.into_atoms())
+    }
+
+    async fn estimate_fee(
+        &self,
+        target_blocks: usize,
+        confidence_percent: Option<u8>,
+    ) -> rpc::Result<Option<u128>> {
+        let confidence_percent =
+            confidence_percent.unwrap_or(FEE_ESTIMATOR_DEFAULT_CONFIDENCE_PERCENT);
+        let res = self
+            .call_async(move |this| Box::pin(this.estimate_fee(target_blocks, confidence_percent)))
+            .await;
+        let fee_rate = handle_error(res)?;
+        Ok(fee_rate.map(|fee_rate| fee_rate.atoms_per_kb()))
+    }
+}
+
+fn handle_error<T>(e: Result<Result<T, Error>, CallError>) -> rpc::Result<T> {
+    e.map_err(rpc::Error::to_call_error)
+        .and_then(|r| r.map_err(rpc::Error::to_call_error))
 }
@@ -28,13 +28,24 @@ pub trait TransactionAccumulator: Send {
     /// Meaning: If this call returns an error, the callee should guarantee that &self never changed
     // TODO: Add a test for this property, at least for DefaultTxAccumulator
     fn add_tx(&mut self, tx: SignedTransaction, tx_fee: Amount) -> Result<(), TxAccumulatorError>;
+    /// Add a package of transactions together with their fees, ordered so that parents come
+    /// before their children. Either all transactions of the package are added or none are;
+    /// in the latter case the accumulator should be done
+    /// This method should not mutate self unless it's successful
+    fn add_package(
+        &mut self,
+        txs: Vec<(SignedTransaction, Amount)>,
+    ) -> Result<(), TxAccumulatorError>;
     fn done(&self) -> bool;
     fn transactions(&self) -> &Vec<SignedTransaction>;
+    /// The fees of the accumulated transactions, in the same order as `transactions()`
+    fn transaction_fees(&self) -> &Vec<Amount>;
     fn total_fees(&self) -> Amount;
 }
 
 pub struct DefaultTxAccumulator {
     txs: Vec<SignedTransaction>,
+    tx_fees: Vec<Amount>,
     total_size: usize,
     target_size: usize,
     done: bool,
@@ -45,6 +56,7 @@ impl DefaultTxAccumulator {
     pub fn new(target_size: usize) -> Self {
         Self {
             txs: Vec::new(),
+            tx_fees: Vec::new(),
             total_size: 0,
             target_size,
             done: false,
@@ -61,6 +73,29 @@ impl TransactionAccumulator for DefaultTxAccumulator {
                 TxAccumulatorError::FeeAccumulationError(self.total_fees, tx_fee),
             )?;
             self.txs.push(tx);
+            self.tx_fees.push(tx_fee);
+        } else {
+            self.done = true
+        };
+        Ok(())
+    }
+
+    fn add_package(
+        &mut self,
+        txs: Vec<(SignedTransaction, Amount)>,
+    ) -> Result<(), TxAccumulatorError> {
+        let package_size: usize = txs.iter().map(|(tx, _)| tx.encoded_size()).sum();
+        if self.total_size + package_size <= self.target_size {
+            self.total_fees = txs.iter().try_fold(self.total_fees, |total_fees, (_, tx_fee)| {
+                (total_fees + *tx_fee).ok_or(TxAccumulatorError::FeeAccumulationError(
+                    total_fees, *tx_fee,
+                ))
+            })?;
+            self.total_size += package_size;
+            for (tx, tx_fee) in txs {
+                self.txs.push(tx);
+                self.tx_fees.push(tx_fee);
+            }
         } else {
             self.done = true
         };
@@ -75,6 +110,10 @@ impl TransactionAccumulator for DefaultTxAccumulator {
         &self.txs
     }
 
+    fn transaction_fees(&self) -> &Vec<Amount> {
+        &self.tx_fees
+    }
+
     fn total_fees(&self) -> Amount {
         self.total_fees
     }
@@ -0,0 +1,47 @@
+// Copyright (c) 2022 RBB S.r.l
+// opensource@mintlayer.org
+// SPDX-License-Identifier: MIT
+// Licensed under the MIT License;
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://github.com/mintlayer/mintlayer-core/blob/master/LICENSE
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::path::PathBuf;
+
+use anyhow::{Context, Result};
+use crypto::key::PrivateKey;
+use serde::{Deserialize, Serialize};
+use serialization::DecodeAll;
+
+/// The block production subsystem configuration.
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct BlockProdConfigFile {
+    /// Path to a file with the hex encoded private key to sign blocks with on chains that require
+    /// signed blocks.
+    pub signing_key_file: Option<PathBuf>,
+}
+
+impl BlockProdConfigFile {
+    /// Reads the block signing key from the key file.
+    pub fn signing_key(&self) -> Result<Option<PrivateKey>> {
+        self.signing_key_file
+            .as_ref()
+            .map(|path| {
+                let key = std::fs::read_to_string(path).with_context(|| {
+                    format!("Failed to read the block signing key from the '{path:?}' file")
+                })?;
+                let bytes =
+                    hex::decode(key.trim()).context("Block signing key is not valid hex")?;
+                PrivateKey::decode_all(&mut bytes.as_slice())
+                    .context("Failed to decode the block signing key")
+            })
+            .transpose()
+    }
+}
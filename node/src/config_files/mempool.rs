@@ -0,0 +1,50 @@
+// Copyright (c) 2022 RBB S.r.l
+// opensource@mintlayer.org
+// SPDX-License-Identifier: MIT
+// Licensed under the MIT License;
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://github.com/mintlayer/mintlayer-core/blob/master/LICENSE
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use mempool::{MempoolConfig, PackageLimits};
+use serde::{Deserialize, Serialize};
+
+/// The mempool subsystem configuration.
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct MempoolConfigFile {
+    /// The maximum number of unconfirmed ancestors of a transaction, including itself.
+    pub max_ancestor_count: Option<usize>,
+
+    /// The maximum total size in bytes of the unconfirmed ancestors of a transaction.
+    pub max_ancestor_size: Option<usize>,
+
+    /// The maximum number of unconfirmed descendants of a transaction, including itself.
+    pub max_descendant_count: Option<usize>,
+
+    /// The maximum total size in bytes of the unconfirmed descendants of a transaction.
+    pub max_descendant_size: Option<usize>,
+}
+
+impl From<MempoolConfigFile> for MempoolConfig {
+    fn from(c: MempoolConfigFile) -> Self {
+        let default_limits = PackageLimits::default();
+        let package_limits = PackageLimits {
+            max_ancestor_count: c.max_ancestor_count.unwrap_or(default_limits.max_ancestor_count),
+            max_ancestor_size: c.max_ancestor_size.unwrap_or(default_limits.max_ancestor_size),
+            max_descendant_count: c
+                .max_descendant_count
+                .unwrap_or(default_limits.max_descendant_count),
+            max_descendant_size: c
+                .max_descendant_size
+                .unwrap_or(default_limits.max_descendant_size),
+        };
+        MempoolConfig::new().with_package_limits(package_limits)
+    }
+}
@@ -28,14 +28,18 @@ use serde::{Deserialize, Serialize};
 use crate::RunOptions;
 
 use self::{
+    blockprod::BlockProdConfigFile,
     chainstate::ChainstateConfigFile,
     chainstate_launcher::ChainstateLauncherConfigFile,
+    mempool::MempoolConfigFile,
     p2p::{MdnsConfigFile, P2pConfigFile},
     rpc::RpcConfigFile,
 };
 
+mod blockprod;
 mod chainstate;
 mod chainstate_launcher;
+mod mempool;
 mod p2p;
 mod rpc;
 
@@ -51,21 +55,29 @@ pub struct NodeConfigFile {
 
     // Subsystems configurations.
     pub chainstate: ChainstateLauncherConfigFile,
+    #[serde(default)]
+    pub mempool: MempoolConfigFile,
     pub p2p: P2pConfigFile,
     pub rpc: RpcConfigFile,
+    #[serde(default)]
+    pub blockprod: BlockProdConfigFile,
 }
 
 impl NodeConfigFile {
     /// Creates a new `Config` instance with the given data directory path.
     pub fn new(datadir: PathBuf) -> Result<Self> {
         let chainstate = ChainstateLauncherConfigFile::new();
+        let mempool = MempoolConfigFile::default();
         let p2p = P2pConfigFile::default();
         let rpc = RpcConfigFile::default();
+        let blockprod = BlockProdConfigFile::default();
         Ok(Self {
             datadir,
             chainstate,
+            mempool,
             p2p,
             rpc,
+            blockprod,
         })
     }
 
@@ -80,20 +92,26 @@ impl NodeConfigFile {
         let NodeConfigFile {
             datadir,
             chainstate,
+            mempool,
             p2p,
             rpc,
+            blockprod,
         } = toml::from_str(&config).context("Failed to parse config")?;
 
         let datadir = datadir_path_opt.clone().unwrap_or(datadir);
         let chainstate = chainstate_config(chainstate, options);
+        let mempool = mempool_config(mempool, options);
         let p2p = p2p_config(p2p, options);
         let rpc = rpc_config(rpc, options);
+        let blockprod = blockprod_config(blockprod, options);
 
         Ok(Self {
             datadir,
             chainstate,
+            mempool,
             p2p,
             rpc,
+            blockprod,
         })
     }
 }
@@ -131,18 +149,76 @@ fn chainstate_config(
     }
 }
 
+fn mempool_config(config: MempoolConfigFile, options: &RunOptions) -> MempoolConfigFile {
+    let MempoolConfigFile {
+        max_ancestor_count,
+        max_ancestor_size,
+        max_descendant_count,
+        max_descendant_size,
+    } = config;
+
+    let max_ancestor_count = options.mempool_max_ancestor_count.or(max_ancestor_count);
+    let max_ancestor_size = options.mempool_max_ancestor_size.or(max_ancestor_size);
+    let max_descendant_count = options.mempool_max_descendant_count.or(max_descendant_count);
+    let max_descendant_size = options.mempool_max_descendant_size.or(max_descendant_size);
+
+    MempoolConfigFile {
+        max_ancestor_count,
+        max_ancestor_size,
+        max_descendant_count,
+        max_descendant_size,
+    }
+}
+
 fn p2p_config(config: P2pConfigFile, options: &RunOptions) -> P2pConfigFile {
     let P2pConfigFile {
         bind_address,
         ban_threshold,
         outbound_connection_timeout,
         mdns_config: _,
+        enable_kademlia,
+        boot_nodes,
+        reserved_nodes,
+        external_addresses,
+        banned_subnets,
+        whitelisted_subnets,
+        ban_ipv6_prefix_len,
+        max_connections,
+        max_inbound_connections,
+        max_outbound_connections,
+        max_message_size,
+        max_announcement_size,
+        max_headers_per_message,
+        max_blocks_per_message,
+        max_block_list_size,
+        request_rate_limit_burst,
+        request_rate_limit_per_sec,
+        block_request_rate_limit_burst,
+        block_request_rate_limit_per_sec,
+        block_upload_rate_limit_burst,
+        block_upload_rate_limit_per_sec,
+        high_bandwidth_compact_blocks,
+        blocks_only,
+        capture_messages,
+        capture_max_size,
     } = config;
 
     let bind_address = options.p2p_addr.clone().or(bind_address);
     let ban_threshold = options.p2p_ban_threshold.or(ban_threshold);
     let outbound_connection_timeout =
         options.p2p_outbound_connection_timeout.or(outbound_connection_timeout);
+    let enable_kademlia = options.p2p_enable_kademlia.or(enable_kademlia);
+    let boot_nodes = options.p2p_boot_nodes.clone().or(boot_nodes);
+    let reserved_nodes = options.p2p_reserved_nodes.clone().or(reserved_nodes);
+    let external_addresses = options.p2p_external_addresses.clone().or(external_addresses);
+    let banned_subnets = options.p2p_banned_subnets.clone().or(banned_subnets);
+    let whitelisted_subnets = options.p2p_whitelisted_subnets.clone().or(whitelisted_subnets);
+    let max_connections = options.p2p_max_connections.or(max_connections);
+    let max_inbound_connections = options.p2p_max_inbound_connections.or(max_inbound_connections);
+    let max_outbound_connections =
+        options.p2p_max_outbound_connections.or(max_outbound_connections);
+    let blocks_only = options.p2p_blocks_only.or(blocks_only);
+    let capture_messages = options.p2p_capture_messages.or(capture_messages);
 
     let mdns_config = MdnsConfigFile::from_options(
         options.p2p_enable_mdns,
@@ -155,6 +231,31 @@ fn p2p_config(config: P2pConfigFile, options: &RunOptions) -> P2pConfigFile {
         ban_threshold,
         outbound_connection_timeout,
         mdns_config,
+        enable_kademlia,
+        boot_nodes,
+        reserved_nodes,
+        external_addresses,
+        banned_subnets,
+        whitelisted_subnets,
+        ban_ipv6_prefix_len,
+        max_connections,
+        max_inbound_connections,
+        max_outbound_connections,
+        max_message_size,
+        max_announcement_size,
+        max_headers_per_message,
+        max_blocks_per_message,
+        max_block_list_size,
+        request_rate_limit_burst,
+        request_rate_limit_per_sec,
+        block_request_rate_limit_burst,
+        block_request_rate_limit_per_sec,
+        block_upload_rate_limit_burst,
+        block_upload_rate_limit_per_sec,
+        high_bandwidth_compact_blocks,
+        blocks_only,
+        capture_messages,
+        capture_max_size,
     }
 }
 
@@ -192,3 +293,11 @@ fn rpc_config(config: RpcConfigFile, options: &RunOptions) -> RpcConfigFile {
         ws_enabled: Some(ws_enabled),
     }
 }
+
+fn blockprod_config(config: BlockProdConfigFile, options: &RunOptions) -> BlockProdConfigFile {
+    let BlockProdConfigFile { signing_key_file } = config;
+
+    let signing_key_file = options.blockprod_signing_key_file.clone().or(signing_key_file);
+
+    BlockProdConfigFile { signing_key_file }
+}
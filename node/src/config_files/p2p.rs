@@ -97,6 +97,57 @@ pub struct P2pConfigFile {
     pub outbound_connection_timeout: Option<u64>,
     /// Multicast DNS configuration.
     pub mdns_config: Option<MdnsConfigFile>,
+    /// Enable the Kademlia DHT peer discovery.
+    pub enable_kademlia: Option<bool>,
+    /// Addresses of the nodes dialed at startup and when the number of peers is low.
+    pub boot_nodes: Option<Vec<String>>,
+    /// Addresses of the nodes that are always kept connected and are never banned.
+    pub reserved_nodes: Option<Vec<String>>,
+    /// Addresses the node can be reached at by other nodes, they are announced to the peers.
+    pub external_addresses: Option<Vec<String>>,
+    /// Subnets whose peers are never allowed to connect.
+    pub banned_subnets: Option<Vec<String>>,
+    /// Subnets whose peers are never banned or evicted and aren't rate limited.
+    pub whitelisted_subnets: Option<Vec<String>>,
+    /// The prefix length of the subnet a misbehaving IPv6 peer is banned by.
+    pub ban_ipv6_prefix_len: Option<u8>,
+    /// The maximum number of active connections.
+    pub max_connections: Option<usize>,
+    /// The maximum number of inbound connections.
+    pub max_inbound_connections: Option<usize>,
+    /// The number of outbound connections the node tries to maintain.
+    pub max_outbound_connections: Option<usize>,
+    /// The maximum size of a message in bytes, unless its type has a more specific limit.
+    pub max_message_size: Option<usize>,
+    /// The maximum size of an announcement in bytes.
+    pub max_announcement_size: Option<usize>,
+    /// The maximum number of headers in a header list response.
+    pub max_headers_per_message: Option<usize>,
+    /// The maximum number of blocks requested or sent in one block list message.
+    pub max_blocks_per_message: Option<usize>,
+    /// The maximum size of a block list response in bytes.
+    pub max_block_list_size: Option<usize>,
+    /// The number of requests a peer may send at once before being rate limited.
+    pub request_rate_limit_burst: Option<usize>,
+    /// The number of requests per second a peer may send after the burst has been used up.
+    pub request_rate_limit_per_sec: Option<f64>,
+    /// The number of blocks a peer may request at once before being rate limited.
+    pub block_request_rate_limit_burst: Option<usize>,
+    /// The number of blocks per second a peer may request after the burst has been used up.
+    pub block_request_rate_limit_per_sec: Option<f64>,
+    /// The number of block bytes that may be sent to a peer at once before it's rate limited.
+    pub block_upload_rate_limit_burst: Option<usize>,
+    /// The number of block bytes per second that may be sent to a peer after the burst has
+    /// been used up.
+    pub block_upload_rate_limit_per_sec: Option<f64>,
+    /// Announce new blocks by pushing them as compact blocks instead of sending their headers only.
+    pub high_bandwidth_compact_blocks: Option<bool>,
+    /// Don't relay transactions, the transaction relay service isn't advertised to the peers.
+    pub blocks_only: Option<bool>,
+    /// Capture the syncing messages exchanged with each peer to the data directory.
+    pub capture_messages: Option<bool>,
+    /// The maximum total size of the capture files in bytes.
+    pub capture_max_size: Option<u64>,
 }
 
 impl From<P2pConfigFile> for P2pConfig {
@@ -107,6 +158,35 @@ impl From<P2pConfigFile> for P2pConfig {
             ban_threshold: c.ban_threshold.into(),
             outbound_connection_timeout: c.outbound_connection_timeout.into(),
             mdns_config: mdns_config.into(),
+            enable_kademlia: c.enable_kademlia.into(),
+            boot_nodes: c.boot_nodes.into(),
+            reserved_nodes: c.reserved_nodes.into(),
+            external_addresses: c.external_addresses.into(),
+            banned_subnets: c.banned_subnets.into(),
+            whitelisted_subnets: c.whitelisted_subnets.into(),
+            ban_ipv6_prefix_len: c.ban_ipv6_prefix_len.into(),
+            // The peer database file is in the data directory, it's set by the node runner.
+            peerdb_path: Default::default(),
+            max_connections: c.max_connections.into(),
+            max_inbound_connections: c.max_inbound_connections.into(),
+            max_outbound_connections: c.max_outbound_connections.into(),
+            max_message_size: c.max_message_size.into(),
+            max_announcement_size: c.max_announcement_size.into(),
+            max_headers_per_message: c.max_headers_per_message.into(),
+            max_blocks_per_message: c.max_blocks_per_message.into(),
+            max_block_list_size: c.max_block_list_size.into(),
+            request_rate_limit_burst: c.request_rate_limit_burst.into(),
+            request_rate_limit_per_sec: c.request_rate_limit_per_sec.into(),
+            block_request_rate_limit_burst: c.block_request_rate_limit_burst.into(),
+            block_request_rate_limit_per_sec: c.block_request_rate_limit_per_sec.into(),
+            block_upload_rate_limit_burst: c.block_upload_rate_limit_burst.into(),
+            block_upload_rate_limit_per_sec: c.block_upload_rate_limit_per_sec.into(),
+            high_bandwidth_compact_blocks: c.high_bandwidth_compact_blocks.into(),
+            blocks_only: c.blocks_only.into(),
+            // The capture directory depends on the data directory, it's set by the node runner.
+            message_capture_dir: Default::default(),
+            message_capture_max_size: c.capture_max_size.into(),
+            rng_seed: Default::default(),
         }
     }
 }
@@ -25,7 +25,8 @@ pub type Error = anyhow::Error;
 
 pub use config_files::{NodeConfigFile, StorageBackendConfigFile};
 pub use options::{Command, Options, RunOptions};
-pub use runner::{initialize, run};
+pub use regtest_options::{ChainConfigOptions, RegtestOptions};
+pub use runner::{initialize, regtest_chain_config, run};
 
 pub fn init_logging(_opts: &Options) {
     logging::init_logging::<&std::path::Path>(None)
@@ -92,10 +92,74 @@ pub struct RunOptions {
     #[clap(long)]
     pub p2p_mdns_query_interval: Option<u64>,
 
+    /// Enable the Kademlia DHT peer discovery.
+    #[clap(long)]
+    pub p2p_enable_kademlia: Option<bool>,
+
     /// The p2p timeout value in seconds.
     #[clap(long)]
     pub p2p_outbound_connection_timeout: Option<u64>,
 
+    /// Address of a node to dial at startup and when the number of peers is low.
+    /// Can be specified multiple times.
+    #[clap(long, value_name = "ADDR")]
+    pub p2p_boot_nodes: Option<Vec<String>>,
+
+    /// Address of a node to always keep connected to. Can be specified multiple times.
+    #[clap(long, value_name = "ADDR")]
+    pub p2p_reserved_nodes: Option<Vec<String>>,
+
+    /// Address the node can be reached at by other nodes, it's announced to the peers.
+    /// Can be specified multiple times.
+    #[clap(long, value_name = "ADDR")]
+    pub p2p_external_addresses: Option<Vec<String>>,
+
+    /// Subnet whose peers are never allowed to connect, e.g. `10.0.0.0/8` or `2001:db8::/64`.
+    /// Can be specified multiple times.
+    #[clap(long, value_name = "SUBNET")]
+    pub p2p_banned_subnets: Option<Vec<String>>,
+
+    /// Subnet whose peers are never banned or evicted and aren't rate limited.
+    /// Can be specified multiple times.
+    #[clap(long, value_name = "SUBNET")]
+    pub p2p_whitelisted_subnets: Option<Vec<String>>,
+
+    /// The maximum number of active connections.
+    #[clap(long)]
+    pub p2p_max_connections: Option<usize>,
+
+    /// The maximum number of inbound connections.
+    #[clap(long)]
+    pub p2p_max_inbound_connections: Option<usize>,
+
+    /// The number of outbound connections the node tries to maintain.
+    #[clap(long)]
+    pub p2p_max_outbound_connections: Option<usize>,
+
+    /// Don't relay transactions, the transaction relay service isn't advertised to the peers.
+    #[clap(long)]
+    pub p2p_blocks_only: Option<bool>,
+
+    /// The maximum number of unconfirmed ancestors of a mempool transaction, including itself.
+    #[clap(long)]
+    pub mempool_max_ancestor_count: Option<usize>,
+
+    /// The maximum total size in bytes of the unconfirmed ancestors of a mempool transaction.
+    #[clap(long)]
+    pub mempool_max_ancestor_size: Option<usize>,
+
+    /// The maximum number of unconfirmed descendants of a mempool transaction, including itself.
+    #[clap(long)]
+    pub mempool_max_descendant_count: Option<usize>,
+
+    /// The maximum total size in bytes of the unconfirmed descendants of a mempool transaction.
+    #[clap(long)]
+    pub mempool_max_descendant_size: Option<usize>,
+
+    /// Capture the syncing messages exchanged with each peer to the data directory.
+    #[clap(long)]
+    pub p2p_capture_messages: Option<bool>,
+
     /// Address to bind http RPC to.
     #[clap(long, value_name = "ADDR")]
     pub http_rpc_addr: Option<SocketAddr>,
@@ -111,6 +175,11 @@ pub struct RunOptions {
     /// Enable/Disable websocket RPC.
     #[clap(long)]
     pub ws_rpc_enabled: Option<bool>,
+
+    /// Path to a file with the hex encoded private key to sign blocks with on chains that require
+    /// signed blocks.
+    #[clap(long, value_name = "PATH")]
+    pub blockprod_signing_key_file: Option<PathBuf>,
 }
 
 impl Options {
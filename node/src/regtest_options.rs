@@ -61,4 +61,18 @@ pub struct ChainConfigOptions {
     /// The maximum smart contracts size ib block in bytes.
     #[clap(long)]
     pub chain_max_block_size_with_smart_contracts: Option<usize>,
+
+    /// Hex encoded public key of a block signer, blocks must be signed by the block signers
+    /// instead of being mined once the signed blocks consensus is active.
+    /// Can be specified multiple times.
+    #[clap(long, value_name = "KEY")]
+    pub chain_block_signers: Option<Vec<String>>,
+
+    /// The number of block signers that have to sign each block (1 by default).
+    #[clap(long)]
+    pub chain_block_signers_threshold: Option<usize>,
+
+    /// The height at which the signed blocks consensus becomes active (1 by default).
+    #[clap(long)]
+    pub chain_signed_blocks_height: Option<u64>,
 }
@@ -20,14 +20,21 @@ use std::{fs, path::Path, str::FromStr, sync::Arc, time::Duration};
 use anyhow::{anyhow, Context, Result};
 use paste::paste;
 
+use blockprod::rpc::BlockProductionRpcServer;
 use chainstate::rpc::ChainstateRpcServer;
 use common::{
-    chain::config::{
-        Builder as ChainConfigBuilder, ChainConfig, ChainType, EmissionScheduleTabular,
+    chain::{
+        config::{
+            BlockSigners, Builder as ChainConfigBuilder, ChainConfig, ChainType,
+            EmissionScheduleTabular,
+        },
+        ConsensusUpgrade, NetUpgrades, UpgradeVersion,
     },
-    primitives::semver::SemVer,
+    primitives::{semver::SemVer, BlockHeight},
 };
+use crypto::key::PublicKey;
 use logging::log;
+use serialization::DecodeAll;
 
 use mempool::rpc::MempoolRpcServer;
 
@@ -51,11 +58,15 @@ pub async fn initialize(
     let mut manager = subsystem::Manager::new("mintlayer");
     manager.install_signal_handlers();
 
+    // The local time adjusted by the clock offsets of the outbound peers
+    let network_time = p2p::peer_manager::network_time::NetworkTime::new();
+
     // Chainstate subsystem
     let chainstate = chainstate_launcher::make_chainstate(
         &node_config.datadir,
         Arc::clone(&chain_config),
         node_config.chainstate.into(),
+        network_time.adjust(Default::default()),
     )?;
     let chainstate = manager.add_subsystem("chainstate", chainstate);
 
@@ -65,32 +76,45 @@ pub async fn initialize(
         mempool::make_mempool(
             Arc::clone(&chain_config),
             chainstate.clone(),
+            node_config.mempool.into(),
             Default::default(),
             mempool::SystemUsageEstimator {},
+            Some(node_config.datadir.join(mempool::FEE_ESTIMATES_FILE_NAME)),
         )?,
     );
 
     // P2P subsystem
+    let capture_messages = node_config.p2p.capture_messages.unwrap_or(false);
+    let mut p2p_config: p2p::config::P2pConfig = node_config.p2p.into();
+    p2p_config.peerdb_path =
+        Some(node_config.datadir.join(p2p::peer_manager::peerdb_storage::PEERDB_FILE_NAME)).into();
+    if capture_messages {
+        p2p_config.message_capture_dir =
+            Some(node_config.datadir.join(p2p::sync::capture::MESSAGE_CAPTURE_DIR_NAME)).into();
+    }
     let p2p = manager.add_subsystem(
         "p2p",
         p2p::make_p2p::<p2p::net::libp2p::Libp2pService>(
             Arc::clone(&chain_config),
-            Arc::new(node_config.p2p.into()),
+            Arc::new(p2p_config),
             chainstate.clone(),
             mempool.clone(),
+            network_time,
+            Default::default(),
         )
         .await
         .expect("The p2p subsystem initialization failed"),
     );
 
     // Block production
-    let _block_prod = manager.add_subsystem(
+    let block_prod = manager.add_subsystem(
         "blockprod",
         blockprod::make_blockproduction(
             chain_config,
             chainstate.clone(),
             mempool.clone(),
             Default::default(),
+            node_config.blockprod.signing_key()?,
         )
         .await?,
     );
@@ -105,6 +129,7 @@ pub async fn initialize(
                 .register(chainstate.clone().into_rpc())
                 .register(mempool.into_rpc())
                 .register(p2p.clone().into_rpc())
+                .register(block_prod.clone().into_rpc())
                 .build()
                 .await?,
         );
@@ -136,7 +161,7 @@ pub async fn run(options: Options) -> Result<()> {
             .await
         }
         Command::Testnet(ref run_options) => {
-            let chain_config = ChainConfigBuilder::new(ChainType::Testnet).build();
+            let chain_config = common::chain::config::create_testnet();
             start(
                 &options.config_path(),
                 &options.data_dir,
@@ -172,7 +197,8 @@ async fn start(
     Ok(())
 }
 
-fn regtest_chain_config(options: &ChainConfigOptions) -> Result<ChainConfig> {
+/// Creates the regtest chain config, customized by the given options.
+pub fn regtest_chain_config(options: &ChainConfigOptions) -> Result<ChainConfig> {
     let ChainConfigOptions {
         chain_address_prefix,
         chain_max_future_block_time_offset,
@@ -183,6 +209,9 @@ fn regtest_chain_config(options: &ChainConfigOptions) -> Result<ChainConfig> {
         chain_max_block_header_size,
         chain_max_block_size_with_standard_txs,
         chain_max_block_size_with_smart_contracts,
+        chain_block_signers,
+        chain_block_signers_threshold,
+        chain_signed_blocks_height,
     } = options;
 
     let mut builder = ChainConfigBuilder::new(ChainType::Regtest);
@@ -220,5 +249,52 @@ fn regtest_chain_config(options: &ChainConfigOptions) -> Result<ChainConfig> {
     update_builder!(max_block_size_with_standard_txs);
     update_builder!(max_block_size_with_smart_contracts);
 
+    if let Some(public_keys) = chain_block_signers {
+        let public_keys = public_keys
+            .iter()
+            .map(|key| {
+                let bytes = hex::decode(key).context("Block signer key is not valid hex")?;
+                PublicKey::decode_all(&mut bytes.as_slice())
+                    .context("Failed to decode the block signer key")
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let block_signers =
+            BlockSigners::new(public_keys, chain_block_signers_threshold.unwrap_or(1))?;
+        let height = BlockHeight::new(chain_signed_blocks_height.unwrap_or(1));
+        builder = builder
+            .net_upgrades(signed_blocks_net_upgrades(height)?)
+            .block_signers(block_signers);
+    } else if chain_block_signers_threshold.is_some() || chain_signed_blocks_height.is_some() {
+        return Err(anyhow!("Signed blocks require at least one block signer"));
+    }
+
     Ok(builder.build())
 }
+
+/// The regtest net upgrades with the proof of work replaced by signed blocks at the given height.
+fn signed_blocks_net_upgrades(height: BlockHeight) -> Result<NetUpgrades<UpgradeVersion>> {
+    if height == BlockHeight::zero() {
+        return Err(anyhow!("Signed blocks can't be activated at the genesis"));
+    }
+
+    let mut upgrades = vec![(
+        BlockHeight::zero(),
+        UpgradeVersion::ConsensusUpgrade(ConsensusUpgrade::IgnoreConsensus),
+    )];
+    if height > BlockHeight::new(1) {
+        upgrades.push((
+            BlockHeight::new(1),
+            UpgradeVersion::ConsensusUpgrade(ConsensusUpgrade::PoW {
+                initial_difficulty: common::chain::config::create_regtest()
+                    .get_proof_of_work_config()
+                    .limit()
+                    .into(),
+            }),
+        ));
+    }
+    upgrades.push((
+        height,
+        UpgradeVersion::ConsensusUpgrade(ConsensusUpgrade::SignedBlocks),
+    ));
+    NetUpgrades::initialize(upgrades)
+}
@@ -19,7 +19,10 @@ use assert_cmd::Command;
 use directories::UserDirs;
 use tempfile::TempDir;
 
-use node::{NodeConfigFile, RunOptions, StorageBackendConfigFile};
+use common::{chain::RequiredConsensus, primitives::BlockHeight};
+use crypto::key::{KeyKind, PrivateKey};
+use node::{NodeConfigFile, Options, RunOptions, StorageBackendConfigFile};
+use serialization::Encode;
 
 const BIN_NAME: &str = env!("CARGO_BIN_EXE_node");
 const CONFIG_NAME: &str = "config.toml";
@@ -63,11 +66,27 @@ fn create_default_config() {
     assert_eq!(config.p2p.bind_address, None);
     assert_eq!(config.p2p.ban_threshold, None);
     assert_eq!(config.p2p.outbound_connection_timeout, None);
+    assert_eq!(config.p2p.enable_kademlia, None);
+    assert_eq!(config.p2p.boot_nodes, None);
+    assert_eq!(config.p2p.reserved_nodes, None);
+    assert_eq!(config.p2p.external_addresses, None);
+    assert_eq!(config.p2p.banned_subnets, None);
+    assert_eq!(config.p2p.whitelisted_subnets, None);
+    assert_eq!(config.p2p.max_connections, None);
+    assert_eq!(config.p2p.max_inbound_connections, None);
+    assert_eq!(config.p2p.max_outbound_connections, None);
+    assert_eq!(config.p2p.blocks_only, None);
+    assert_eq!(config.p2p.capture_messages, None);
+
+    assert_eq!(config.mempool.max_ancestor_count, None);
+    assert_eq!(config.mempool.max_descendant_count, None);
 
     assert_eq!(
         config.rpc.http_bind_address,
         Some(SocketAddr::from_str("127.0.0.1:3030").unwrap())
     );
+
+    assert_eq!(config.blockprod.signing_key_file, None);
 }
 
 // Check that the config fields are overwritten by the run options.
@@ -89,10 +108,21 @@ fn read_config_override_values() {
     let p2p_addr = "address";
     let p2p_ban_threshold = 3;
     let p2p_timeout = 10000;
+    let p2p_boot_node = "boot_node";
+    let p2p_reserved_node = "reserved_node";
+    let p2p_external_address = "external_address";
+    let p2p_banned_subnet = "10.0.0.0/8";
+    let p2p_whitelisted_subnet = "192.168.0.0/16";
+    let p2p_max_connections = 64;
+    let p2p_max_inbound_connections = 56;
+    let p2p_max_outbound_connections = 6;
+    let mempool_max_ancestor_count = 10;
+    let mempool_max_descendant_count = 12;
     let http_rpc_addr = SocketAddr::from_str("127.0.0.1:5432").unwrap();
     let ws_rpc_addr = SocketAddr::from_str("127.0.0.1:5433").unwrap();
     let enable_mdns = false;
     let backend_type = StorageBackendConfigFile::InMemory;
+    let blockprod_signing_key_file = data_dir.path().join("block_signing_key");
 
     let options = RunOptions {
         max_db_commit_attempts: Some(max_db_commit_attempts),
@@ -104,11 +134,27 @@ fn read_config_override_values() {
         p2p_enable_mdns: Some(enable_mdns),
         p2p_mdns_query_interval: None,
         p2p_enable_ipv6_mdns_discovery: None,
+        p2p_enable_kademlia: Some(true),
+        p2p_boot_nodes: Some(vec![p2p_boot_node.into()]),
+        p2p_reserved_nodes: Some(vec![p2p_reserved_node.into()]),
+        p2p_external_addresses: Some(vec![p2p_external_address.into()]),
+        p2p_banned_subnets: Some(vec![p2p_banned_subnet.into()]),
+        p2p_whitelisted_subnets: Some(vec![p2p_whitelisted_subnet.into()]),
+        p2p_max_connections: Some(p2p_max_connections),
+        p2p_max_inbound_connections: Some(p2p_max_inbound_connections),
+        p2p_max_outbound_connections: Some(p2p_max_outbound_connections),
+        p2p_blocks_only: Some(true),
+        p2p_capture_messages: Some(true),
+        mempool_max_ancestor_count: Some(mempool_max_ancestor_count),
+        mempool_max_ancestor_size: None,
+        mempool_max_descendant_count: Some(mempool_max_descendant_count),
+        mempool_max_descendant_size: None,
         http_rpc_addr: Some(http_rpc_addr),
         http_rpc_enabled: Some(true),
         ws_rpc_addr: Some(ws_rpc_addr),
         ws_rpc_enabled: Some(false),
         storage_backend: Some(backend_type.clone()),
+        blockprod_signing_key_file: Some(blockprod_signing_key_file.clone()),
     };
     let datadir_opt = Some(data_dir.path().into());
     let config = NodeConfigFile::read(&config_path, &datadir_opt, &options).unwrap();
@@ -131,6 +177,46 @@ fn read_config_override_values() {
     assert_eq!(config.p2p.bind_address, Some(p2p_addr.into()));
     assert_eq!(config.p2p.ban_threshold, Some(p2p_ban_threshold));
     assert_eq!(config.p2p.outbound_connection_timeout, Some(p2p_timeout));
+    assert_eq!(config.p2p.enable_kademlia, Some(true));
+    assert_eq!(config.p2p.boot_nodes, Some(vec![p2p_boot_node.into()]));
+    assert_eq!(
+        config.p2p.reserved_nodes,
+        Some(vec![p2p_reserved_node.into()])
+    );
+    assert_eq!(
+        config.p2p.external_addresses,
+        Some(vec![p2p_external_address.into()])
+    );
+    assert_eq!(
+        config.p2p.banned_subnets,
+        Some(vec![p2p_banned_subnet.into()])
+    );
+    assert_eq!(
+        config.p2p.whitelisted_subnets,
+        Some(vec![p2p_whitelisted_subnet.into()])
+    );
+    assert_eq!(config.p2p.max_connections, Some(p2p_max_connections));
+    assert_eq!(
+        config.p2p.max_inbound_connections,
+        Some(p2p_max_inbound_connections)
+    );
+    assert_eq!(
+        config.p2p.max_outbound_connections,
+        Some(p2p_max_outbound_connections)
+    );
+    assert_eq!(config.p2p.blocks_only, Some(true));
+    assert_eq!(config.p2p.capture_messages, Some(true));
+
+    assert_eq!(
+        config.mempool.max_ancestor_count,
+        Some(mempool_max_ancestor_count)
+    );
+    assert_eq!(config.mempool.max_ancestor_size, None);
+    assert_eq!(
+        config.mempool.max_descendant_count,
+        Some(mempool_max_descendant_count)
+    );
+    assert_eq!(config.mempool.max_descendant_size, None);
 
     assert_eq!(config.rpc.http_bind_address, Some(http_rpc_addr));
     assert!(config.rpc.http_enabled.unwrap());
@@ -139,6 +225,107 @@ fn read_config_override_values() {
     assert!(!config.rpc.ws_enabled.unwrap());
 
     assert_eq!(config.chainstate.storage_backend, backend_type);
+
+    assert_eq!(
+        config.blockprod.signing_key_file,
+        Some(blockprod_signing_key_file)
+    );
+}
+
+// Check that the block signing key is read from the key file.
+#[test]
+fn read_block_signing_key_file() {
+    let data_dir = TempDir::new().unwrap();
+
+    Command::new(BIN_NAME)
+        .arg("--datadir")
+        .arg(data_dir.path().to_str().unwrap())
+        .arg("create-config")
+        .assert()
+        .success();
+    let config_path = data_dir.path().join(CONFIG_NAME);
+
+    let (private_key, _) = PrivateKey::new_from_entropy(KeyKind::RistrettoSchnorr);
+    let key_path = data_dir.path().join("block_signing_key");
+    std::fs::write(
+        &key_path,
+        format!("{}\n", hex::encode(private_key.encode())),
+    )
+    .unwrap();
+
+    let options = RunOptions {
+        blockprod_signing_key_file: Some(key_path),
+        ..default_run_options()
+    };
+    let config = NodeConfigFile::read(&config_path, &None, &options).unwrap();
+
+    assert_eq!(config.blockprod.signing_key().unwrap(), Some(private_key));
+}
+
+// Check that the regtest chain options set the block signers and the signed blocks height.
+#[test]
+fn regtest_block_signers() {
+    let data_dir = TempDir::new().unwrap();
+    let public_keys: Vec<_> = (0..3)
+        .map(|_| PrivateKey::new_from_entropy(KeyKind::RistrettoSchnorr).1)
+        .collect();
+
+    let mut args = vec![
+        "node".to_owned(),
+        "--datadir".to_owned(),
+        data_dir.path().to_str().unwrap().to_owned(),
+        "regtest".to_owned(),
+    ];
+    for public_key in &public_keys {
+        args.push("--chain-block-signers".to_owned());
+        args.push(hex::encode(public_key.encode()));
+    }
+    args.extend(
+        ["--chain-block-signers-threshold", "2", "--chain-signed-blocks-height", "5"]
+            .map(str::to_owned),
+    );
+    let chain_config = match Options::from_args(args).unwrap().command {
+        node::Command::Regtest(options) => {
+            node::regtest_chain_config(&options.chain_config).unwrap()
+        }
+        command => panic!("Unexpected command: {command:?}"),
+    };
+
+    let block_signers = chain_config.block_signers().unwrap();
+    assert_eq!(block_signers.public_keys(), public_keys.as_slice());
+    assert_eq!(block_signers.threshold(), 2);
+
+    let net_upgrades = chain_config.net_upgrade();
+    assert!(matches!(
+        net_upgrades.consensus_status(BlockHeight::new(4)),
+        RequiredConsensus::PoW(_)
+    ));
+    assert!(matches!(
+        net_upgrades.consensus_status(BlockHeight::new(5)),
+        RequiredConsensus::SignedBlocks
+    ));
+}
+
+// Check that the signed blocks can't be enabled without block signers.
+#[test]
+fn regtest_signed_blocks_without_signers() {
+    let data_dir = TempDir::new().unwrap();
+
+    let options = Options::from_args([
+        "node",
+        "--datadir",
+        data_dir.path().to_str().unwrap(),
+        "regtest",
+        "--chain-signed-blocks-height",
+        "5",
+    ])
+    .unwrap();
+    let chain_config = match options.command {
+        node::Command::Regtest(options) => node::regtest_chain_config(&options.chain_config),
+        command => panic!("Unexpected command: {command:?}"),
+    };
+
+    assert!(chain_config.is_err());
 }
 
 // Check that the `--conf` option has the precedence over the default data directory value.
@@ -197,10 +384,26 @@ fn default_run_options() -> RunOptions {
         p2p_enable_mdns: None,
         p2p_mdns_query_interval: None,
         p2p_enable_ipv6_mdns_discovery: None,
+        p2p_enable_kademlia: None,
+        p2p_boot_nodes: None,
+        p2p_reserved_nodes: None,
+        p2p_external_addresses: None,
+        p2p_banned_subnets: None,
+        p2p_whitelisted_subnets: None,
+        p2p_max_connections: None,
+        p2p_max_inbound_connections: None,
+        p2p_max_outbound_connections: None,
+        p2p_blocks_only: None,
+        p2p_capture_messages: None,
+        mempool_max_ancestor_count: None,
+        mempool_max_ancestor_size: None,
+        mempool_max_descendant_count: None,
+        mempool_max_descendant_size: None,
         http_rpc_addr: None,
         http_rpc_enabled: None,
         ws_rpc_addr: None,
         ws_rpc_enabled: None,
         storage_backend: None,
+        blockprod_signing_key_file: None,
     }
 }
@@ -45,23 +45,35 @@ where
     let (tx_peer_manager, mut rx_peer_manager) = mpsc::unbounded_channel();
     let config = Arc::new(common::chain::config::create_unit_test_config());
     let handle = p2p_test_utils::start_chainstate(Arc::clone(&config)).await;
+    let mempool = p2p_test_utils::start_mempool(Arc::clone(&config), handle.clone()).await;
 
-    let (mut conn1, sync1) = S::start(A::make_address(), Arc::clone(&config), Default::default())
-        .await
-        .unwrap();
+    let (mut conn1, sync1) = S::start(
+        A::make_address(),
+        Arc::clone(&config),
+        Default::default(),
+        Default::default(),
+    )
+    .await
+    .unwrap();
 
     let mut sync1 = BlockSyncManager::<S>::new(
         Arc::clone(&config),
+        Default::default(),
         sync1,
         handle.clone(),
+        mempool,
         rx_sync,
         tx_peer_manager,
     );
 
-    let (mut conn2, mut sync2) =
-        S::start(A::make_address(), Arc::clone(&config), Default::default())
-            .await
-            .unwrap();
+    let (mut conn2, mut sync2) = S::start(
+        A::make_address(),
+        Arc::clone(&config),
+        Default::default(),
+        Default::default(),
+    )
+    .await
+    .unwrap();
 
     connect_services::<S>(&mut conn1, &mut conn2).await;
 
@@ -71,11 +83,11 @@ where
 
     let peer = *conn2.peer_id();
     tokio::spawn(async move {
-        sync1.register_peer(peer).await.unwrap();
+        sync1.register_peer(peer, net::types::Services::default()).await.unwrap();
         sync1.run().await
     });
 
-    // spawn `sync2` into background and spam an orphan block on the network
+    // spawn `sync2` into background and spam the header of an orphan block on the network
     tokio::spawn(async move {
         sync2.subscribe(&[net::types::PubSubTopic::Blocks]).await.unwrap();
 
@@ -96,7 +108,8 @@ where
             .unwrap();
 
         loop {
-            let res = sync2.make_announcement(Announcement::Block(blocks[2].clone())).await;
+            let res =
+                sync2.make_announcement(Announcement::Block(blocks[2].header().clone())).await;
 
             if res.is_ok() {
                 break;
@@ -109,12 +122,22 @@ where
         }
     });
 
-    match rx_peer_manager.recv().await {
-        Some(PeerManagerEvent::AdjustPeerScore(peer_id, score, _)) => {
-            assert_eq!(&peer_id, conn2.peer_id());
-            assert_eq!(score, 100);
+    // skip the peer statistics reported for the header response
+    loop {
+        match rx_peer_manager.recv().await {
+            Some(
+                PeerManagerEvent::ResponseTime(..)
+                | PeerManagerEvent::MessageSent(..)
+                | PeerManagerEvent::MessageReceived(..)
+                | PeerManagerEvent::BestKnownBlock(..),
+            ) => {}
+            Some(PeerManagerEvent::AdjustPeerScore(peer_id, score, _)) => {
+                assert_eq!(&peer_id, conn2.peer_id());
+                assert_eq!(score, 100);
+                break;
+            }
+            e => panic!("invalid event received: {e:?}"),
         }
-        e => panic!("invalid event received: {e:?}"),
     }
 }
 
@@ -130,19 +153,32 @@ where
     let (tx_peer_manager, mut rx_peer_manager) = mpsc::unbounded_channel();
     let config = Arc::new(common::chain::config::create_unit_test_config());
     let handle = p2p_test_utils::start_chainstate(Arc::clone(&config)).await;
+    let mempool = p2p_test_utils::start_mempool(Arc::clone(&config), handle.clone()).await;
 
-    let (mut conn1, sync1) = S::start(A::make_address(), Arc::clone(&config), Default::default())
-        .await
-        .unwrap();
-
-    let (mut conn2, _sync2) = S::start(A::make_address(), Arc::clone(&config), Default::default())
-        .await
-        .unwrap();
+    let (mut conn1, sync1) = S::start(
+        A::make_address(),
+        Arc::clone(&config),
+        Default::default(),
+        Default::default(),
+    )
+    .await
+    .unwrap();
+
+    let (mut conn2, _sync2) = S::start(
+        A::make_address(),
+        Arc::clone(&config),
+        Default::default(),
+        Default::default(),
+    )
+    .await
+    .unwrap();
 
     let mut sync1 = BlockSyncManager::<S>::new(
         Arc::clone(&config),
+        Default::default(),
         sync1,
         handle.clone(),
+        mempool,
         rx_p2p_sync,
         tx_peer_manager,
     );
@@ -158,14 +194,21 @@ where
     let remote_id = *conn2.peer_id();
 
     tokio::spawn(async move {
-        sync1.register_peer(remote_id).await.unwrap();
+        sync1.register_peer(remote_id, net::types::Services::default()).await.unwrap();
         let res = sync1.process_block_response(remote_id, vec![blocks[2].clone()]).await;
         sync1.handle_error(remote_id, res).await.unwrap();
     });
 
-    if let Some(PeerManagerEvent::AdjustPeerScore(peer_id, score, _)) = rx_peer_manager.recv().await
-    {
-        assert_eq!(remote_id, peer_id);
-        assert_eq!(score, 100);
+    // skip the message statistics reported for the header request
+    loop {
+        match rx_peer_manager.recv().await {
+            Some(PeerManagerEvent::MessageSent(..)) => {}
+            Some(PeerManagerEvent::AdjustPeerScore(peer_id, score, _)) => {
+                assert_eq!(remote_id, peer_id);
+                assert_eq!(score, 100);
+                break;
+            }
+            e => panic!("invalid event received: {e:?}"),
+        }
     }
 }
@@ -15,39 +15,46 @@
 
 use std::{fmt::Debug, sync::Arc};
 
-use tokio::{pin, select, time::Duration};
+use tokio::{pin, select, sync::mpsc, time::Duration};
 
 use common::{
     chain::{
-        block::{consensus_data::ConsensusData, timestamp::BlockTimestamp, Block, BlockReward},
+        block::{
+            consensus_data::{ConsensusData, PoSData},
+            timestamp::BlockTimestamp,
+            Block, BlockReward,
+        },
         signature::{
             inputsig::{InputWitness, StandardInputSignature},
             sighashtype,
         },
-        transaction::signed_transaction::SignedTransaction,
-        transaction::Transaction,
-        TxInput,
+        GenBlock, TxInput,
     },
-    primitives::{Id, H256},
+    primitives::{Compact, Id, Idable, H256},
 };
 use serialization::Encode;
 
 use p2p::{
+    config::P2pConfig,
     error::{P2pError, PublishError},
-    message::Announcement,
+    message::{
+        Announcement, CompactBlock, HeaderListResponse, PrefilledTransaction, Request, Response,
+    },
     net::{
-        mock::constants::ANNOUNCEMENT_MAX_SIZE,
-        types::{PubSubTopic, SyncingEvent},
+        types::{PubSubTopic, Services, SyncingEvent},
         ConnectivityService, NetworkingService, SyncingMessagingService,
     },
     peer_manager::helpers::connect_services,
+    sync::BlockSyncManager,
 };
-use p2p_test_utils::MakeTestAddress;
+use p2p_test_utils::{MakeTestAddress, TestBlockInfo};
 
 tests![
     block_announcement,
     block_announcement_no_subscription,
     block_announcement_too_big_message,
+    block_header_announcement_fetches_body,
+    compact_block_announcement_rebuilt_without_request,
 ];
 
 async fn block_announcement<A, S>()
@@ -58,14 +65,23 @@ where
     S::ConnectivityHandle: ConnectivityService<S>,
 {
     let config = Arc::new(common::chain::config::create_mainnet());
-    let (mut conn1, mut sync1) =
-        S::start(A::make_address(), Arc::clone(&config), Default::default())
-            .await
-            .unwrap();
-    let (mut conn2, mut sync2) =
-        S::start(A::make_address(), Arc::clone(&config), Default::default())
-            .await
-            .unwrap();
+    let max_announcement_size = *P2pConfig::default().max_announcement_size;
+    let (mut conn1, mut sync1) = S::start(
+        A::make_address(),
+        Arc::clone(&config),
+        Default::default(),
+        Default::default(),
+    )
+    .await
+    .unwrap();
+    let (mut conn2, mut sync2) = S::start(
+        A::make_address(),
+        Arc::clone(&config),
+        Default::default(),
+        Default::default(),
+    )
+    .await
+    .unwrap();
 
     connect_services::<S>(&mut conn1, &mut conn2).await;
 
@@ -83,7 +99,9 @@ where
                     ConsensusData::None,
                     BlockReward::new(Vec::new()),
                 )
-                .unwrap(),
+                .unwrap()
+                .header()
+                .clone(),
             ))
             .await;
 
@@ -94,15 +112,15 @@ where
     }
 
     // Poll an event from the network for server2.
-    let block = match sync2.poll_next().await.unwrap() {
+    let header = match sync2.poll_next().await.unwrap() {
         SyncingEvent::Announcement {
             peer_id: _,
             message_id: _,
-            announcement: Announcement::Block(block),
-        } => block,
+            announcement: Announcement::Block(header),
+        } => header,
         _ => panic!("Unexpected event"),
     };
-    assert_eq!(block.timestamp().as_int_seconds(), 1337u64);
+    assert_eq!(header.timestamp().as_int_seconds(), 1337u64);
     sync2
         .make_announcement(Announcement::Block(
             Block::new(
@@ -112,20 +130,25 @@ where
                 ConsensusData::None,
                 BlockReward::new(Vec::new()),
             )
-            .unwrap(),
+            .unwrap()
+            .header()
+            .clone(),
         ))
         .await
         .unwrap();
 
-    let block = match sync1.poll_next().await.unwrap() {
+    let header = match sync1.poll_next().await.unwrap() {
         SyncingEvent::Announcement {
             peer_id: _,
             message_id: _,
-            announcement: Announcement::Block(block),
-        } => block,
+            announcement: Announcement::Block(header),
+        } => header,
         _ => panic!("Unexpected event"),
     };
-    assert_eq!(block.timestamp(), BlockTimestamp::from_int_seconds(1338u64));
+    assert_eq!(
+        header.timestamp(),
+        BlockTimestamp::from_int_seconds(1338u64)
+    );
 }
 
 async fn block_announcement_no_subscription<A, S>()
@@ -136,13 +159,22 @@ where
     S::ConnectivityHandle: ConnectivityService<S>,
 {
     let config = Arc::new(common::chain::config::create_mainnet());
-    let (mut conn1, mut sync1) =
-        S::start(A::make_address(), Arc::clone(&config), Default::default())
-            .await
-            .unwrap();
-    let (mut conn2, _sync2) = S::start(A::make_address(), Arc::clone(&config), Default::default())
-        .await
-        .unwrap();
+    let (mut conn1, mut sync1) = S::start(
+        A::make_address(),
+        Arc::clone(&config),
+        Default::default(),
+        Default::default(),
+    )
+    .await
+    .unwrap();
+    let (mut conn2, _sync2) = S::start(
+        A::make_address(),
+        Arc::clone(&config),
+        Default::default(),
+        Default::default(),
+    )
+    .await
+    .unwrap();
 
     connect_services::<S>(&mut conn1, &mut conn2).await;
 
@@ -158,7 +190,9 @@ where
                     ConsensusData::None,
                     BlockReward::new(Vec::new()),
                 )
-                .unwrap(),
+                .unwrap()
+                .header()
+                .clone(),
             )) => {
                 assert_eq!(Err(P2pError::PublishError(PublishError::InsufficientPeers)), res);
             }
@@ -175,42 +209,50 @@ where
     S::ConnectivityHandle: ConnectivityService<S>,
 {
     let config = Arc::new(common::chain::config::create_mainnet());
-    let (mut conn1, mut sync1) =
-        S::start(A::make_address(), Arc::clone(&config), Default::default())
-            .await
-            .unwrap();
+    let (mut conn1, mut sync1) = S::start(
+        A::make_address(),
+        Arc::clone(&config),
+        Default::default(),
+        Default::default(),
+    )
+    .await
+    .unwrap();
 
-    let (mut conn2, mut sync2) =
-        S::start(A::make_address(), Arc::clone(&config), Default::default())
-            .await
-            .unwrap();
+    let (mut conn2, mut sync2) = S::start(
+        A::make_address(),
+        Arc::clone(&config),
+        Default::default(),
+        Default::default(),
+    )
+    .await
+    .unwrap();
 
     connect_services::<S>(&mut conn1, &mut conn2).await;
 
     sync1.subscribe(&[PubSubTopic::Blocks]).await.unwrap();
     sync2.subscribe(&[PubSubTopic::Blocks]).await.unwrap();
 
+    // Headers are small, so the only way to make the announcement too big is to stuff the
+    // consensus data
     let input = TxInput::new(config.genesis_block_id().into(), 0);
-    let signature = (0..ANNOUNCEMENT_MAX_SIZE).into_iter().map(|_| 0).collect::<Vec<u8>>();
-    let signatures = vec![InputWitness::Standard(StandardInputSignature::new(
+    let signature = (0..max_announcement_size).into_iter().map(|_| 0).collect::<Vec<u8>>();
+    let witness = InputWitness::Standard(StandardInputSignature::new(
         sighashtype::SigHashType::try_from(sighashtype::SigHashType::ALL).unwrap(),
         signature,
-    ))];
-    let txs = vec![SignedTransaction::new(
-        Transaction::new(0, vec![input], vec![], 0).unwrap(),
-        signatures,
-    )
-    .expect("invalid witness count")];
+    ));
+    let consensus_data = ConsensusData::PoS(PoSData::new(vec![input], vec![witness], Compact(0)));
 
     let message = Announcement::Block(
         Block::new(
-            txs,
+            vec![],
             Id::new(H256([0x04; 32])),
             BlockTimestamp::from_int_seconds(1337u64),
-            ConsensusData::None,
+            consensus_data,
             BlockReward::new(Vec::new()),
         )
-        .unwrap(),
+        .unwrap()
+        .header()
+        .clone(),
     );
     let encoded_size = message.encode().len();
 
@@ -218,7 +260,240 @@ where
         sync1.make_announcement(message).await,
         Err(P2pError::PublishError(PublishError::MessageTooLarge(
             Some(encoded_size),
-            Some(ANNOUNCEMENT_MAX_SIZE)
+            Some(max_announcement_size)
         )))
     );
 }
+
+// Announce the header of a new block to a `BlockSyncManager` and verify that it requests a compact
+// block from the announcing peer and accepts the block rebuilt from it.
+async fn block_header_announcement_fetches_body<A, S>()
+where
+    A: MakeTestAddress<Address = S::Address>,
+    S: NetworkingService + Debug + 'static,
+    S::SyncingMessagingHandle: SyncingMessagingService<S>,
+    S::ConnectivityHandle: ConnectivityService<S>,
+{
+    let (_tx_sync, rx_sync) = mpsc::unbounded_channel();
+    let (tx_peer_manager, _rx_peer_manager) = mpsc::unbounded_channel();
+    let config = Arc::new(common::chain::config::create_unit_test_config());
+    let handle = p2p_test_utils::start_chainstate(Arc::clone(&config)).await;
+    let mempool = p2p_test_utils::start_mempool(Arc::clone(&config), handle.clone()).await;
+
+    let (mut conn1, sync1) = S::start(
+        A::make_address(),
+        Arc::clone(&config),
+        Default::default(),
+        Default::default(),
+    )
+    .await
+    .unwrap();
+    let mut sync1 = BlockSyncManager::<S>::new(
+        Arc::clone(&config),
+        Default::default(),
+        sync1,
+        handle.clone(),
+        mempool,
+        rx_sync,
+        tx_peer_manager,
+    );
+
+    let (mut conn2, mut sync2) = S::start(
+        A::make_address(),
+        Arc::clone(&config),
+        Default::default(),
+        Default::default(),
+    )
+    .await
+    .unwrap();
+
+    connect_services::<S>(&mut conn1, &mut conn2).await;
+
+    let best_block = TestBlockInfo::from_genesis(config.genesis_block());
+    let block = p2p_test_utils::create_n_blocks(Arc::clone(&config), best_block, 1)
+        .pop()
+        .unwrap();
+    let block_id = block.get_id();
+
+    let peer = *conn2.peer_id();
+    tokio::spawn(async move {
+        sync1.register_peer(peer, Services::default()).await.unwrap();
+        sync1.run().await
+    });
+
+    sync2.subscribe(&[PubSubTopic::Blocks]).await.unwrap();
+    let request_id = match sync2.poll_next().await.unwrap() {
+        SyncingEvent::Request {
+            peer_id: _,
+            request_id,
+            request: Request::HeaderListRequest(_),
+        } => request_id,
+        e => panic!("Unexpected event type: {e:?}"),
+    };
+    sync2
+        .send_response(
+            request_id,
+            Response::HeaderListResponse(HeaderListResponse::new(Vec::new())),
+        )
+        .await
+        .unwrap();
+
+    loop {
+        let res = sync2.make_announcement(Announcement::Block(block.header().clone())).await;
+        match res {
+            Ok(()) => break,
+            Err(e) => assert_eq!(e, P2pError::PublishError(PublishError::InsufficientPeers)),
+        }
+    }
+
+    let request_id = match sync2.poll_next().await.unwrap() {
+        SyncingEvent::Request {
+            peer_id: _,
+            request_id,
+            request: Request::CompactBlockRequest(request),
+        } => {
+            assert_eq!(request.block_id(), &block_id);
+            request_id
+        }
+        e => panic!("Unexpected event type: {e:?}"),
+    };
+
+    // Send all transactions prefilled so that nothing has to be found in the mempool
+    let prefilled_txs = (0..)
+        .zip(block.transactions())
+        .map(|(index, tx)| PrefilledTransaction::new(index, tx.clone()))
+        .collect();
+    let compact_block = CompactBlock::new(
+        block.header().clone(),
+        0,
+        block.block_reward().clone(),
+        Vec::new(),
+        prefilled_txs,
+    );
+    sync2
+        .send_response(request_id, Response::CompactBlockResponse(compact_block))
+        .await
+        .unwrap();
+
+    // After the block is processed the headers are requested again
+    match sync2.poll_next().await.unwrap() {
+        SyncingEvent::Request {
+            peer_id: _,
+            request_id: _,
+            request: Request::HeaderListRequest(_),
+        } => {}
+        e => panic!("Unexpected event type: {e:?}"),
+    }
+    assert_eq!(
+        handle.call(|this| this.get_best_block_id()).await.unwrap().unwrap(),
+        Id::<GenBlock>::from(block_id)
+    );
+}
+
+// Push a new block as a compact block (high-bandwidth mode) to a `BlockSyncManager` and verify that
+// it rebuilds the block without requesting anything but the next headers.
+async fn compact_block_announcement_rebuilt_without_request<A, S>()
+where
+    A: MakeTestAddress<Address = S::Address>,
+    S: NetworkingService + Debug + 'static,
+    S::SyncingMessagingHandle: SyncingMessagingService<S>,
+    S::ConnectivityHandle: ConnectivityService<S>,
+{
+    let (_tx_sync, rx_sync) = mpsc::unbounded_channel();
+    let (tx_peer_manager, _rx_peer_manager) = mpsc::unbounded_channel();
+    let config = Arc::new(common::chain::config::create_unit_test_config());
+    let handle = p2p_test_utils::start_chainstate(Arc::clone(&config)).await;
+    let mempool = p2p_test_utils::start_mempool(Arc::clone(&config), handle.clone()).await;
+
+    let (mut conn1, sync1) = S::start(
+        A::make_address(),
+        Arc::clone(&config),
+        Default::default(),
+        Default::default(),
+    )
+    .await
+    .unwrap();
+    let mut sync1 = BlockSyncManager::<S>::new(
+        Arc::clone(&config),
+        Default::default(),
+        sync1,
+        handle.clone(),
+        mempool,
+        rx_sync,
+        tx_peer_manager,
+    );
+
+    let (mut conn2, mut sync2) = S::start(
+        A::make_address(),
+        Arc::clone(&config),
+        Default::default(),
+        Default::default(),
+    )
+    .await
+    .unwrap();
+
+    connect_services::<S>(&mut conn1, &mut conn2).await;
+
+    let best_block = TestBlockInfo::from_genesis(config.genesis_block());
+    let block = p2p_test_utils::create_n_blocks(Arc::clone(&config), best_block, 1)
+        .pop()
+        .unwrap();
+    let block_id = block.get_id();
+
+    let peer = *conn2.peer_id();
+    tokio::spawn(async move {
+        sync1.register_peer(peer, Services::default()).await.unwrap();
+        sync1.run().await
+    });
+
+    sync2.subscribe(&[PubSubTopic::Blocks]).await.unwrap();
+    let request_id = match sync2.poll_next().await.unwrap() {
+        SyncingEvent::Request {
+            peer_id: _,
+            request_id,
+            request: Request::HeaderListRequest(_),
+        } => request_id,
+        e => panic!("Unexpected event type: {e:?}"),
+    };
+    sync2
+        .send_response(
+            request_id,
+            Response::HeaderListResponse(HeaderListResponse::new(Vec::new())),
+        )
+        .await
+        .unwrap();
+
+    // Send all transactions prefilled so that nothing has to be found in the mempool
+    let prefilled_txs = (0..)
+        .zip(block.transactions())
+        .map(|(index, tx)| PrefilledTransaction::new(index, tx.clone()))
+        .collect::<Vec<_>>();
+    let compact_block = CompactBlock::new(
+        block.header().clone(),
+        0,
+        block.block_reward().clone(),
+        Vec::new(),
+        prefilled_txs,
+    );
+    loop {
+        let res = sync2.make_announcement(Announcement::CompactBlock(compact_block.clone())).await;
+        match res {
+            Ok(()) => break,
+            Err(e) => assert_eq!(e, P2pError::PublishError(PublishError::InsufficientPeers)),
+        }
+    }
+
+    // No compact block or block is requested, only the headers after the block is processed
+    match sync2.poll_next().await.unwrap() {
+        SyncingEvent::Request {
+            peer_id: _,
+            request_id: _,
+            request: Request::HeaderListRequest(_),
+        } => {}
+        e => panic!("Unexpected event type: {e:?}"),
+    }
+    assert_eq!(
+        handle.call(|this| this.get_best_block_id()).await.unwrap().unwrap(),
+        Id::<GenBlock>::from(block_id)
+    );
+}
@@ -38,7 +38,14 @@ where
     S::SyncingMessagingHandle: SyncingMessagingService<S>,
 {
     let config = Arc::new(common::chain::config::create_mainnet());
-    S::start(A::make_address(), config, Default::default()).await.unwrap();
+    S::start(
+        A::make_address(),
+        config,
+        Default::default(),
+        Default::default(),
+    )
+    .await
+    .unwrap();
 }
 
 // Check that connecting twice to the same address isn't possible.
@@ -52,13 +59,17 @@ where
     S::SyncingMessagingHandle: SyncingMessagingService<S> + Debug,
 {
     let config = Arc::new(common::chain::config::create_mainnet());
-    let (connectivity, _sync) =
-        S::start(A::make_address(), Arc::clone(&config), Default::default())
-            .await
-            .unwrap();
+    let (connectivity, _sync) = S::start(
+        A::make_address(),
+        Arc::clone(&config),
+        Default::default(),
+        Default::default(),
+    )
+    .await
+    .unwrap();
 
     let address = connectivity.local_addr().await.unwrap().unwrap();
-    let res = S::start(address, config, Default::default())
+    let res = S::start(address, config, Default::default(), Default::default())
         .await
         .expect_err("address is not in use");
     assert_eq!(
@@ -77,12 +88,22 @@ where
     S::SyncingMessagingHandle: SyncingMessagingService<S>,
 {
     let config = Arc::new(common::chain::config::create_mainnet());
-    let (mut service1, _) = S::start(A::make_address(), Arc::clone(&config), Default::default())
-        .await
-        .unwrap();
-    let (mut service2, _) = S::start(A::make_address(), Arc::clone(&config), Default::default())
-        .await
-        .unwrap();
+    let (mut service1, _) = S::start(
+        A::make_address(),
+        Arc::clone(&config),
+        Default::default(),
+        Default::default(),
+    )
+    .await
+    .unwrap();
+    let (mut service2, _) = S::start(
+        A::make_address(),
+        Arc::clone(&config),
+        Default::default(),
+        Default::default(),
+    )
+    .await
+    .unwrap();
 
     let conn_addr = service1.local_addr().await.unwrap().unwrap();
     let (res1, res2) = tokio::join!(service1.poll_next(), service2.connect(conn_addr));
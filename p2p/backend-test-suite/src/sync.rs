@@ -31,7 +31,7 @@ use p2p::{
     event::{PeerManagerEvent, SyncControlEvent},
     message::{BlockListRequest, BlockListResponse, HeaderListResponse, Request, Response},
     net::{
-        types::{ConnectivityEvent, SyncingEvent},
+        types::{ConnectivityEvent, Services, SyncingEvent},
         ConnectivityService, NetworkingService, SyncingMessagingService,
     },
     peer_manager::helpers::connect_services,
@@ -69,7 +69,10 @@ where
 
     // connect the two managers together so that they can exchange messages
     connect_services::<S>(&mut conn1, &mut conn2).await;
-    assert_eq!(mgr1.register_peer(*conn2.peer_id()).await, Ok(()));
+    assert_eq!(
+        mgr1.register_peer(*conn2.peer_id(), Services::default()).await,
+        Ok(())
+    );
 
     // ensure that only a header request is received from the remote and
     // as the nodes are tracking the same chain, no further messages are exchanged
@@ -111,7 +114,10 @@ where
 
     // add peer to the hashmap of known peers and send getheaders request to them
     connect_services::<S>(&mut conn1, &mut conn2).await;
-    assert_eq!(mgr1.register_peer(*conn2.peer_id()).await, Ok(()));
+    assert_eq!(
+        mgr1.register_peer(*conn2.peer_id(), Services::default()).await,
+        Ok(())
+    );
 
     let handle = tokio::spawn(async move {
         for _ in 0..9 {
@@ -204,8 +210,14 @@ where
 
     // add peer to the hashmap of known peers and send getheaders request to them
     connect_services::<T>(&mut conn1, &mut conn2).await;
-    assert_eq!(mgr1.register_peer(*conn2.peer_id()).await, Ok(()));
-    assert_eq!(mgr2.register_peer(*conn1.peer_id()).await, Ok(()));
+    assert_eq!(
+        mgr1.register_peer(*conn2.peer_id(), Services::default()).await,
+        Ok(())
+    );
+    assert_eq!(
+        mgr2.register_peer(*conn1.peer_id(), Services::default()).await,
+        Ok(())
+    );
 
     let handle = tokio::spawn(async move {
         for _ in 0..14 {
@@ -329,8 +341,14 @@ where
 
     // add peer to the hashmap of known peers and send getheaders request to them
     connect_services::<S>(&mut conn1, &mut conn2).await;
-    assert_eq!(mgr1.register_peer(*conn2.peer_id()).await, Ok(()));
-    assert_eq!(mgr2.register_peer(*conn1.peer_id()).await, Ok(()));
+    assert_eq!(
+        mgr1.register_peer(*conn2.peer_id(), Services::default()).await,
+        Ok(())
+    );
+    assert_eq!(
+        mgr2.register_peer(*conn1.peer_id(), Services::default()).await,
+        Ok(())
+    );
 
     let handle = tokio::spawn(async move {
         for _ in 0..24 {
@@ -473,8 +491,14 @@ where
 
     // add peer to the hashmap of known peers and send getheaders request to them
     connect_services::<S>(&mut conn1, &mut conn2).await;
-    assert_eq!(mgr1.register_peer(*conn2.peer_id()).await, Ok(()));
-    assert_eq!(mgr2.register_peer(*conn1.peer_id()).await, Ok(()));
+    assert_eq!(
+        mgr1.register_peer(*conn2.peer_id(), Services::default()).await,
+        Ok(())
+    );
+    assert_eq!(
+        mgr2.register_peer(*conn1.peer_id(), Services::default()).await,
+        Ok(())
+    );
 
     let handle = tokio::spawn(async move {
         for _ in 0..20 {
@@ -613,10 +637,22 @@ where
     connect_services::<S>(&mut conn1, &mut conn2).await;
     connect_services::<S>(&mut conn1, &mut conn3).await;
 
-    assert_eq!(mgr1.register_peer(*conn2.peer_id()).await, Ok(()));
-    assert_eq!(mgr1.register_peer(*conn3.peer_id()).await, Ok(()));
-    assert_eq!(mgr2.register_peer(*conn1.peer_id()).await, Ok(()));
-    assert_eq!(mgr3.register_peer(*conn1.peer_id()).await, Ok(()));
+    assert_eq!(
+        mgr1.register_peer(*conn2.peer_id(), Services::default()).await,
+        Ok(())
+    );
+    assert_eq!(
+        mgr1.register_peer(*conn3.peer_id(), Services::default()).await,
+        Ok(())
+    );
+    assert_eq!(
+        mgr2.register_peer(*conn1.peer_id(), Services::default()).await,
+        Ok(())
+    );
+    assert_eq!(
+        mgr3.register_peer(*conn1.peer_id(), Services::default()).await,
+        Ok(())
+    );
 
     let handle = tokio::spawn(async move {
         for _ in 0..18 {
@@ -726,10 +762,22 @@ where
     connect_services::<S>(&mut conn1, &mut conn2).await;
     connect_services::<S>(&mut conn1, &mut conn3).await;
 
-    assert_eq!(mgr1.register_peer(*conn2.peer_id()).await, Ok(()));
-    assert_eq!(mgr1.register_peer(*conn3.peer_id()).await, Ok(()));
-    assert_eq!(mgr2.register_peer(*conn1.peer_id()).await, Ok(()));
-    assert_eq!(mgr3.register_peer(*conn1.peer_id()).await, Ok(()));
+    assert_eq!(
+        mgr1.register_peer(*conn2.peer_id(), Services::default()).await,
+        Ok(())
+    );
+    assert_eq!(
+        mgr1.register_peer(*conn3.peer_id(), Services::default()).await,
+        Ok(())
+    );
+    assert_eq!(
+        mgr2.register_peer(*conn1.peer_id(), Services::default()).await,
+        Ok(())
+    );
+    assert_eq!(
+        mgr3.register_peer(*conn1.peer_id(), Services::default()).await,
+        Ok(())
+    );
 
     let (tx, mut rx) = mpsc::unbounded_channel();
     let handle = tokio::spawn(async move {
@@ -839,10 +887,22 @@ where
     connect_services::<S>(&mut conn1, &mut conn2).await;
     connect_services::<S>(&mut conn1, &mut conn3).await;
 
-    assert_eq!(mgr1.register_peer(*conn2.peer_id()).await, Ok(()));
-    assert_eq!(mgr1.register_peer(*conn3.peer_id()).await, Ok(()));
-    assert_eq!(mgr2.register_peer(*conn1.peer_id()).await, Ok(()));
-    assert_eq!(mgr3.register_peer(*conn1.peer_id()).await, Ok(()));
+    assert_eq!(
+        mgr1.register_peer(*conn2.peer_id(), Services::default()).await,
+        Ok(())
+    );
+    assert_eq!(
+        mgr1.register_peer(*conn3.peer_id(), Services::default()).await,
+        Ok(())
+    );
+    assert_eq!(
+        mgr2.register_peer(*conn1.peer_id(), Services::default()).await,
+        Ok(())
+    );
+    assert_eq!(
+        mgr3.register_peer(*conn1.peer_id(), Services::default()).await,
+        Ok(())
+    );
 
     let (tx, mut rx) = mpsc::unbounded_channel();
     let mut gethdr_received = HashSet::new();
@@ -958,7 +1018,10 @@ where
     let (mut mgr2, mut conn2, _, _) = make_sync_manager::<S>(A::make_address(), handle2).await;
 
     connect_services::<S>(&mut conn1, &mut conn2).await;
-    assert_eq!(mgr1.register_peer(*conn2.peer_id()).await, Ok(()));
+    assert_eq!(
+        mgr1.register_peer(*conn2.peer_id(), Services::default()).await,
+        Ok(())
+    );
 
     // ensure that only a header request is received from the remote and
     // as the nodes are tracking the same chain, no further messages are exchanged
@@ -989,7 +1052,10 @@ where
     p2p_test_utils::import_blocks(&mgr2_handle, blocks.clone()).await;
 
     connect_services::<S>(&mut conn1, &mut conn2).await;
-    assert_eq!(mgr1.register_peer(*conn2.peer_id()).await, Ok(()));
+    assert_eq!(
+        mgr1.register_peer(*conn2.peer_id(), Services::default()).await,
+        Ok(())
+    );
 
     let handle = tokio::spawn(async move {
         for _ in 0..9 {
@@ -1074,13 +1140,23 @@ where
     let (tx_peer_manager, rx_peer_manager) = mpsc::unbounded_channel();
 
     let config = Arc::new(common::chain::config::create_mainnet());
-    let (conn, sync) = T::start(addr, Arc::clone(&config), Default::default()).await.unwrap();
+    let (conn, sync) = T::start(
+        addr,
+        Arc::clone(&config),
+        Default::default(),
+        Default::default(),
+    )
+    .await
+    .unwrap();
+    let mempool = p2p_test_utils::start_mempool(Arc::clone(&config), handle.clone()).await;
 
     (
         BlockSyncManager::<T>::new(
             Arc::clone(&config),
+            Default::default(),
             sync,
             handle,
+            mempool,
             rx_p2p_sync,
             tx_peer_manager,
         ),
@@ -1194,6 +1270,16 @@ where
             Request::BlockListRequest(request) => {
                 mgr.process_block_request(peer_id, request_id, request.into_block_ids()).await?;
             }
+            Request::CompactBlockRequest(request) => {
+                mgr.process_compact_block_request(peer_id, request_id, *request.block_id())
+                    .await?;
+            }
+            Request::BlockTransactionsRequest(request) => {
+                mgr.process_block_transactions_request(peer_id, request_id, request).await?;
+            }
+            Request::AddrListRequest(_) => {
+                mgr.process_addr_list_request(peer_id, request_id).await?;
+            }
         },
         SyncingEvent::Response {
             peer_id,
@@ -1206,6 +1292,17 @@ where
             Response::BlockListResponse(response) => {
                 mgr.process_block_response(peer_id, response.into_blocks()).await?;
             }
+            Response::CompactBlockResponse(compact_block) => {
+                mgr.process_compact_block_response(peer_id, compact_block).await?;
+            }
+            Response::BlockTransactionsResponse(response) => {
+                let block_id = *response.block_id();
+                mgr.process_block_transactions_response(peer_id, block_id, response.into_txs())
+                    .await?;
+            }
+            Response::AddrListResponse(response) => {
+                mgr.process_addr_list_response(peer_id, response.into_addresses()).await?;
+            }
         },
         SyncingEvent::Error {
             peer_id,
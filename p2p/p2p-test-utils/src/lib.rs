@@ -15,6 +15,9 @@
 
 #![allow(clippy::unwrap_used)]
 
+pub mod replay;
+pub mod simulation;
+
 use std::{fmt::Debug, net::SocketAddr, sync::Arc};
 
 use libp2p::Multiaddr;
@@ -38,6 +41,7 @@ use common::{
     primitives::{time, Amount, Id, Idable},
 };
 use crypto::random::SliceRandom;
+use mempool::MempoolInterface;
 
 pub async fn get_tcp_socket() -> TcpStream {
     let port: u16 = portpicker::pick_unused_port().expect("No ports free");
@@ -123,14 +127,19 @@ fn create_utxo_data(
     ))
 }
 
-fn produce_test_block(config: &ChainConfig, prev_block: TestBlockInfo) -> Block {
-    produce_test_block_with_consensus_data(config, prev_block, ConsensusData::None)
+fn produce_test_block(
+    config: &ChainConfig,
+    prev_block: TestBlockInfo,
+    timestamp: BlockTimestamp,
+) -> Block {
+    produce_test_block_with_consensus_data(config, prev_block, ConsensusData::None, timestamp)
 }
 
 fn produce_test_block_with_consensus_data(
     _config: &ChainConfig,
     prev_block: TestBlockInfo,
     consensus_data: ConsensusData,
+    timestamp: BlockTimestamp,
 ) -> Block {
     // For each output we create a new input and output that will placed into a new block.
     // If value of original output is less than 1 then output will disappear in a new block.
@@ -151,7 +160,7 @@ fn produce_test_block_with_consensus_data(
         )
         .expect("invalid witness count")],
         prev_block.id,
-        BlockTimestamp::from_duration_since_epoch(time::get()),
+        timestamp,
         consensus_data,
         BlockReward::new(Vec::new()),
     )
@@ -201,19 +210,59 @@ pub async fn start_chainstate(
     handle
 }
 
+pub async fn start_mempool(
+    chain_config: Arc<ChainConfig>,
+    chainstate_handle: subsystem::Handle<Box<dyn ChainstateInterface>>,
+) -> subsystem::Handle<Box<dyn MempoolInterface>> {
+    let mut man = subsystem::Manager::new("TODO");
+    let handle = man.add_subsystem(
+        "mempool",
+        mempool::make_mempool(
+            chain_config,
+            chainstate_handle,
+            Default::default(),
+            Default::default(),
+            mempool::SystemUsageEstimator {},
+            None,
+        )
+        .unwrap(),
+    );
+    tokio::spawn(async move { man.main().await });
+    handle
+}
+
 pub fn create_block(config: Arc<ChainConfig>, parent: TestBlockInfo) -> Block {
-    produce_test_block(&config, parent)
+    produce_test_block(
+        &config,
+        parent,
+        BlockTimestamp::from_duration_since_epoch(time::get()),
+    )
 }
 
 pub fn create_n_blocks(
+    config: Arc<ChainConfig>,
+    prev: TestBlockInfo,
+    nblocks: usize,
+) -> Vec<Block> {
+    create_n_blocks_with_timestamp(
+        config,
+        prev,
+        nblocks,
+        BlockTimestamp::from_duration_since_epoch(time::get()),
+    )
+}
+
+/// Creates `nblocks` blocks on top of `prev`, all of them with the given timestamp
+pub fn create_n_blocks_with_timestamp(
     config: Arc<ChainConfig>,
     mut prev: TestBlockInfo,
     nblocks: usize,
+    timestamp: BlockTimestamp,
 ) -> Vec<Block> {
     let mut blocks: Vec<Block> = Vec::new();
 
     for _ in 0..nblocks {
-        let block = create_block(Arc::clone(&config), prev);
+        let block = produce_test_block(&config, prev, timestamp);
         prev = TestBlockInfo::from_block(&block);
         blocks.push(block.clone());
     }
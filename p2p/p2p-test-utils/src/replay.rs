@@ -0,0 +1,213 @@
+// Copyright (c) 2021-2022 RBB S.r.l
+// opensource@mintlayer.org
+// SPDX-License-Identifier: MIT
+// Licensed under the MIT License;
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://github.com/mintlayer/mintlayer-core/blob/master/LICENSE
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Replay of captured syncing messages
+//!
+//! The inbound messages of a capture file (see [`p2p::sync::capture`]) are sent to a fresh
+//! [`BlockSyncManager`] by a peer connected over the channel transport, so that the reaction of
+//! the sync manager to a misbehaving peer can be reproduced offline. Captured responses are sent
+//! in reply to the requests of the sync manager in the order the requests arrive.
+
+use std::{
+    collections::VecDeque,
+    path::Path,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use tokio::{sync::mpsc, time::timeout};
+
+use common::chain::config::ChainConfig;
+use p2p::{
+    error::{P2pError, PublishError},
+    event::{PeerManagerEvent, SyncControlEvent},
+    message::Announcement,
+    net::{
+        mock::{transport::ChannelMockTransport, MockService},
+        types::{Services, SyncingEvent},
+        ConnectivityService, NetworkingService, SyncingMessagingService,
+    },
+    peer_manager::helpers::connect_services,
+    sync::{
+        capture::{read_capture_file, CapturedPayload, Direction},
+        BlockSyncManager,
+    },
+};
+
+use crate::{start_mempool, ChainstateHandle};
+
+type ReplayService = MockService<ChannelMockTransport>;
+
+/// How long to wait for the sync manager to send a request that a captured response answers
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How long the sync manager is given to process the last replayed message
+const SETTLE_TIME: Duration = Duration::from_secs(1);
+
+/// How the sync manager reacted to the replayed messages
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ReplayReport {
+    /// Score adjustments the sync manager requested for the replayed peer, in order
+    pub score_adjustments: Vec<u32>,
+
+    /// Whether the sync manager requested the replayed peer to be disconnected
+    pub disconnect_requested: bool,
+}
+
+/// Replays the inbound messages of a capture file against the given chainstate
+///
+/// Panics if the capture file can't be read or the replaying peer fails.
+pub async fn replay_capture_file(
+    chain_config: Arc<ChainConfig>,
+    chainstate: ChainstateHandle,
+    path: &Path,
+) -> ReplayReport {
+    let messages = read_capture_file(path).unwrap();
+
+    let (mut local_conn, local_sync) = ReplayService::start(
+        0,
+        Arc::clone(&chain_config),
+        Default::default(),
+        Default::default(),
+    )
+    .await
+    .unwrap();
+    let (mut remote_conn, mut remote_sync) = ReplayService::start(
+        0,
+        Arc::clone(&chain_config),
+        Default::default(),
+        Default::default(),
+    )
+    .await
+    .unwrap();
+    connect_services::<ReplayService>(&mut local_conn, &mut remote_conn).await;
+    let local_peer_id = *local_conn.peer_id();
+    let remote_peer_id = *remote_conn.peer_id();
+
+    let (tx_sync, rx_sync) = mpsc::unbounded_channel();
+    let (tx_pm, rx_pm) = mpsc::unbounded_channel();
+    let mempool = start_mempool(Arc::clone(&chain_config), chainstate.clone()).await;
+    let mut sync_manager = BlockSyncManager::<ReplayService>::new(
+        chain_config,
+        Default::default(),
+        local_sync,
+        chainstate,
+        mempool,
+        rx_sync,
+        tx_pm,
+    );
+    tokio::spawn(async move {
+        let _ = sync_manager.run().await;
+    });
+    tx_sync
+        .send(SyncControlEvent::Connected(
+            remote_peer_id,
+            Services::default(),
+        ))
+        .unwrap();
+
+    let report = Arc::new(Mutex::new(ReplayReport::default()));
+    tokio::spawn(answer_peer_manager_events(rx_pm, Arc::clone(&report)));
+
+    let mut pending_requests = VecDeque::new();
+    for message in messages.into_iter().filter(|m| m.direction == Direction::Inbound) {
+        match message.payload {
+            CapturedPayload::Request(request) => {
+                remote_sync.send_request(local_peer_id, request).await.unwrap();
+            }
+            CapturedPayload::Response(response) => {
+                let request_id = match pending_requests.pop_front() {
+                    Some(request_id) => request_id,
+                    None => next_request(&mut remote_sync).await,
+                };
+                remote_sync.send_response(request_id, response).await.unwrap();
+            }
+            CapturedPayload::Announcement(announcement) => {
+                announce(&mut remote_sync, announcement).await;
+            }
+        }
+
+        // Collect the requests sent in the meantime without blocking
+        while let Ok(Ok(SyncingEvent::Request { request_id, .. })) =
+            timeout(Duration::ZERO, remote_sync.poll_next()).await
+        {
+            pending_requests.push_back(request_id);
+        }
+    }
+
+    tokio::time::sleep(SETTLE_TIME).await;
+    let report = report.lock().unwrap().clone();
+    report
+}
+
+/// Waits for the next request of the sync manager, skipping all other events
+async fn next_request(
+    sync: &mut <ReplayService as NetworkingService>::SyncingMessagingHandle,
+) -> <ReplayService as NetworkingService>::SyncingPeerRequestId {
+    loop {
+        let event = timeout(REQUEST_TIMEOUT, sync.poll_next())
+            .await
+            .expect("the sync manager didn't send a request for a captured response")
+            .unwrap();
+        if let SyncingEvent::Request { request_id, .. } = event {
+            return request_id;
+        }
+    }
+}
+
+/// Publishes an announcement once the sync manager has subscribed to its topic
+async fn announce(
+    sync: &mut <ReplayService as NetworkingService>::SyncingMessagingHandle,
+    announcement: Announcement,
+) {
+    let result = timeout(REQUEST_TIMEOUT, async {
+        loop {
+            match sync.make_announcement(announcement.clone()).await {
+                Err(P2pError::PublishError(PublishError::InsufficientPeers)) => {
+                    tokio::time::sleep(Duration::from_millis(100)).await
+                }
+                result => break result,
+            }
+        }
+    })
+    .await;
+    result.expect("the sync manager didn't subscribe to announcements").unwrap();
+}
+
+/// Plays the role of the peer manager, recording the reaction to the replayed peer
+async fn answer_peer_manager_events(
+    mut rx_pm: mpsc::UnboundedReceiver<PeerManagerEvent<ReplayService>>,
+    report: Arc<Mutex<ReplayReport>>,
+) {
+    while let Some(event) = rx_pm.recv().await {
+        match event {
+            PeerManagerEvent::AdjustPeerScore(_, score, tx) => {
+                report.lock().unwrap().score_adjustments.push(score);
+                let _ = tx.send(Ok(()));
+            }
+            PeerManagerEvent::Disconnect(_, tx) => {
+                report.lock().unwrap().disconnect_requested = true;
+                let _ = tx.send(Ok(()));
+            }
+            PeerManagerEvent::GetAddresses(_, tx) => {
+                let _ = tx.send(Vec::new());
+            }
+            PeerManagerEvent::AddressesReceived(_, _, tx) => {
+                let _ = tx.send(Ok(()));
+            }
+            _ => {}
+        }
+    }
+}
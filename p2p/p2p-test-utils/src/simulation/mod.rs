@@ -0,0 +1,257 @@
+// Copyright (c) 2021-2022 RBB S.r.l
+// opensource@mintlayer.org
+// SPDX-License-Identifier: MIT
+// Licensed under the MIT License;
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://github.com/mintlayer/mintlayer-core/blob/master/LICENSE
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Deterministic network simulation
+//!
+//! [`SimulatedNetwork`] runs complete nodes (chainstate, mempool and p2p over the mock backend)
+//! that talk through the [`SimulatedMockTransport`]. The latency, jitter and loss of the links
+//! can be configured and the network can be partitioned and healed. Tests should pause the tokio
+//! clock (`#[tokio::test(start_paused = true)]`) so that the delays take no real time and a run
+//! is reproduced from its seed; the time getter of the network follows the same clock and starts
+//! at a fixed time after the genesis block. The random number generators of the nodes are seeded
+//! from the seed of the network too.
+
+mod transport;
+
+use std::{collections::BTreeMap, sync::Arc, time::Duration};
+
+use tokio::time::Instant;
+
+use chainstate::{
+    chainstate_interface::ChainstateInterface, make_chainstate, ChainstateConfig,
+    DefaultTransactionVerificationStrategy,
+};
+use common::{
+    chain::{block::timestamp::BlockTimestamp, config::ChainConfig, Block, GenBlock},
+    primitives::Id,
+    time_getter::TimeGetter,
+};
+use crypto::random::{Rng, RngCore};
+use mempool::MempoolInterface;
+use p2p::{
+    config::P2pConfig, interface::p2p_interface::P2pInterface, make_p2p, net::mock::MockService,
+    peer_manager::network_time::NetworkTime,
+};
+use test_utils::random::{make_seedable_rng, Seed};
+
+use crate::{create_n_blocks_with_timestamp, TestBlockInfo};
+
+/// How long after the genesis block the clock of a simulated network starts
+const START_TIME_AFTER_GENESIS: Duration = Duration::from_secs(24 * 60 * 60);
+
+pub use transport::{
+    LinkConditions, SimulatedAddress, SimulatedMockListener, SimulatedMockStream,
+    SimulatedMockTransport,
+};
+
+pub type SimulatedService = MockService<SimulatedMockTransport>;
+
+/// A complete node of a simulated network
+pub struct SimulatedNode {
+    pub address: SimulatedAddress,
+    pub chainstate: subsystem::Handle<Box<dyn ChainstateInterface>>,
+    pub mempool: subsystem::Handle<Box<dyn MempoolInterface>>,
+    pub p2p: Box<dyn P2pInterface>,
+}
+
+pub struct SimulatedNetwork {
+    chain_config: Arc<ChainConfig>,
+    state: transport::SharedState,
+    time_getter: TimeGetter,
+    /// Generator of the seeds of the nodes
+    rng: Box<dyn RngCore + Send>,
+    nodes: Vec<SimulatedNode>,
+}
+
+impl SimulatedNetwork {
+    /// Creates an empty network whose random decisions are made by an RNG seeded with `seed`
+    pub fn new(chain_config: Arc<ChainConfig>, seed: Seed) -> Self {
+        let mut rng = make_seedable_rng(seed);
+        let state = transport::NetworkState::new(Box::new(make_seedable_rng(Seed(rng.gen()))));
+
+        let start = chain_config.genesis_block().timestamp().as_duration_since_epoch()
+            + START_TIME_AFTER_GENESIS;
+        let base = Instant::now();
+        let time_getter = TimeGetter::new(Arc::new(move || start + base.elapsed()));
+
+        Self {
+            chain_config,
+            state: Arc::new(std::sync::Mutex::new(state)),
+            time_getter,
+            rng: Box::new(rng),
+            nodes: Vec::new(),
+        }
+    }
+
+    /// Returns the time getter used by the nodes, it advances with the tokio clock
+    pub fn time_getter(&self) -> TimeGetter {
+        self.time_getter.clone()
+    }
+
+    pub fn chain_config(&self) -> &Arc<ChainConfig> {
+        &self.chain_config
+    }
+
+    /// Sets the conditions of all links, they apply to the messages sent from now on
+    pub fn set_conditions(&self, conditions: LinkConditions) {
+        self.state.lock().expect("Network mutex is poisoned").set_conditions(conditions);
+    }
+
+    /// Starts a new node and returns its index
+    pub async fn add_node(&mut self) -> usize {
+        let address = transport::register_node(&self.state);
+
+        let storage = chainstate_storage::inmemory::Store::new_empty().unwrap();
+        let mut man = subsystem::Manager::new("simulated-chainstate");
+        let chainstate = man.add_subsystem(
+            "chainstate",
+            make_chainstate(
+                Arc::clone(&self.chain_config),
+                ChainstateConfig::new(),
+                storage,
+                DefaultTransactionVerificationStrategy::new(),
+                None,
+                self.time_getter.clone(),
+            )
+            .unwrap(),
+        );
+        tokio::spawn(async move { man.main().await });
+
+        let mut man = subsystem::Manager::new("simulated-mempool");
+        let mempool = man.add_subsystem(
+            "mempool",
+            mempool::make_mempool(
+                Arc::clone(&self.chain_config),
+                chainstate.clone(),
+                Default::default(),
+                self.time_getter.clone(),
+                mempool::SystemUsageEstimator {},
+                None,
+            )
+            .unwrap(),
+        );
+        tokio::spawn(async move { man.main().await });
+
+        let p2p_config = P2pConfig {
+            bind_address: address.to_string().into(),
+            rng_seed: Some(self.rng.gen()).into(),
+            ..Default::default()
+        };
+        let p2p = make_p2p::<SimulatedService>(
+            Arc::clone(&self.chain_config),
+            Arc::new(p2p_config),
+            chainstate.clone(),
+            mempool.clone(),
+            NetworkTime::new(),
+            self.time_getter.clone(),
+        )
+        .await
+        .unwrap();
+
+        self.nodes.push(SimulatedNode {
+            address,
+            chainstate,
+            mempool,
+            p2p,
+        });
+        self.nodes.len() - 1
+    }
+
+    pub fn node(&self, index: usize) -> &SimulatedNode {
+        &self.nodes[index]
+    }
+
+    pub fn node_mut(&mut self, index: usize) -> &mut SimulatedNode {
+        &mut self.nodes[index]
+    }
+
+    /// Connects node `from` to node `to`
+    pub async fn connect(&mut self, from: usize, to: usize) -> p2p::Result<()> {
+        let address = self.nodes[to].address.to_string();
+        self.nodes[from].p2p.connect(address).await
+    }
+
+    /// Splits the network into the given groups of node indices
+    ///
+    /// The nodes that aren't listed form one more group. Messages between the groups are dropped,
+    /// so the connections between them are closed.
+    pub fn partition(&self, groups: &[&[usize]]) {
+        let partitions = groups
+            .iter()
+            .enumerate()
+            .flat_map(|(group, nodes)| {
+                nodes.iter().map(move |&node| (self.nodes[node].address, group + 1))
+            })
+            .collect::<BTreeMap<_, _>>();
+        self.state.lock().expect("Network mutex is poisoned").set_partitions(partitions);
+    }
+
+    /// Removes all partitions, the closed connections have to be opened again
+    pub fn heal(&self) {
+        self.state
+            .lock()
+            .expect("Network mutex is poisoned")
+            .set_partitions(BTreeMap::new());
+    }
+
+    /// Creates `count` blocks on top of `parent` with the current time of the network
+    pub fn create_blocks(&self, parent: TestBlockInfo, count: usize) -> Vec<Block> {
+        create_n_blocks_with_timestamp(
+            Arc::clone(&self.chain_config),
+            parent,
+            count,
+            BlockTimestamp::from_duration_since_epoch(self.time_getter.get_time()),
+        )
+    }
+
+    pub async fn best_block_id(&self, index: usize) -> Id<GenBlock> {
+        self.nodes[index]
+            .chainstate
+            .call(|this| this.get_best_block_id())
+            .await
+            .unwrap()
+            .unwrap()
+    }
+
+    pub async fn peer_count(&self, index: usize) -> usize {
+        self.nodes[index].p2p.get_peer_count().await.unwrap()
+    }
+
+    /// Advances the tokio clock in steps until the condition holds or the timeout expires
+    ///
+    /// Returns whether the condition holds.
+    pub async fn wait_until<F, Fut>(&self, timeout: Duration, mut condition: F) -> bool
+    where
+        F: FnMut() -> Fut,
+        Fut: std::future::Future<Output = bool>,
+    {
+        let deadline = Instant::now() + timeout;
+        while Instant::now() < deadline {
+            if condition().await {
+                return true;
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+        condition().await
+    }
+}
+
+impl Drop for SimulatedNetwork {
+    fn drop(&mut self) {
+        for node in &self.nodes {
+            transport::unregister_node(&node.address);
+        }
+    }
+}
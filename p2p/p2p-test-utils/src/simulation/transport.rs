@@ -0,0 +1,402 @@
+// Copyright (c) 2022 RBB S.r.l
+// opensource@mintlayer.org
+// SPDX-License-Identifier: MIT
+// Licensed under the MIT License;
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://github.com/mintlayer/mintlayer-core/blob/master/LICENSE
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Simulated transport for the mock networking backend
+//!
+//! The connections are in-process channels like those of the channel transport, but each
+//! message is delayed by the latency of the network and can be lost, and nodes in different
+//! partitions can't reach each other. The delays are measured with the tokio clock, so they take
+//! no real time when the clock is paused, and all random decisions are made by the seeded RNG of
+//! the network.
+//!
+//! Streams are reliable and ordered like TCP connections: jitter reorders the messages of
+//! different connections but never the messages of one connection, and a lost message resets the
+//! connection instead of leaving a gap in the stream.
+
+use std::{
+    collections::BTreeMap,
+    fmt, io,
+    net::{IpAddr, Ipv6Addr},
+    num::ParseIntError,
+    str::FromStr,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+
+use async_trait::async_trait;
+use once_cell::sync::Lazy;
+use tokio::{
+    sync::{
+        mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender},
+        oneshot,
+    },
+    time::Instant,
+};
+
+use crypto::random::{Rng, RngCore};
+use p2p::{
+    error::{DialError, P2pError},
+    net::{
+        mock::{
+            transport::{MockListener, MockStream, MockTransport},
+            types::Message,
+        },
+        AsBannableAddress, AsIpAddress, AsNetworkGroup, IsBannableAddress, IsIpv6Address,
+    },
+    Result,
+};
+
+/// Sending half of a link, the messages are queued with their delivery time
+type LinkSender = UnboundedSender<(Instant, Message)>;
+type MessageReceiver = UnboundedReceiver<Message>;
+type AcceptResponse = (LinkSender, MessageReceiver);
+type PendingConnection = (SimulatedAddress, oneshot::Sender<AcceptResponse>);
+
+pub(super) type SharedState = Arc<Mutex<NetworkState>>;
+
+/// Nodes of all simulated networks by their address
+static NODES: Lazy<Mutex<BTreeMap<SimulatedAddress, Node>>> =
+    Lazy::new(|| Mutex::new(BTreeMap::new()));
+
+static NEXT_ADDRESS: AtomicU64 = AtomicU64::new(1);
+
+/// Address of a node in a simulated network
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct SimulatedAddress(u64);
+
+impl fmt::Display for SimulatedAddress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl FromStr for SimulatedAddress {
+    type Err = ParseIntError;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        s.parse().map(Self)
+    }
+}
+
+impl AsBannableAddress for SimulatedAddress {
+    type BannableAddress = SimulatedAddress;
+
+    fn as_bannable(&self) -> Self::BannableAddress {
+        *self
+    }
+}
+
+impl AsIpAddress for SimulatedAddress {
+    fn as_ip_address(&self) -> IpAddr {
+        IpAddr::V6(Ipv6Addr::from(u128::from(self.0)))
+    }
+}
+
+impl IsBannableAddress for SimulatedAddress {
+    fn is_bannable(&self) -> bool {
+        true
+    }
+}
+
+impl IsIpv6Address for SimulatedAddress {
+    fn is_ipv6(&self) -> bool {
+        false
+    }
+}
+
+impl AsNetworkGroup for SimulatedAddress {
+    fn network_group(&self) -> Vec<u8> {
+        self.0.to_be_bytes().to_vec()
+    }
+}
+
+/// Conditions of the links between the nodes of a simulated network
+#[derive(Debug, Clone, Copy)]
+pub struct LinkConditions {
+    /// Time it takes for a message to reach the other side
+    pub latency: Duration,
+
+    /// Maximum random delay added to the latency of each message
+    pub jitter: Duration,
+
+    /// Probability that a message is lost, which resets its connection
+    pub loss_rate: f64,
+}
+
+impl Default for LinkConditions {
+    fn default() -> Self {
+        Self {
+            latency: Duration::from_millis(50),
+            jitter: Duration::ZERO,
+            loss_rate: 0.0,
+        }
+    }
+}
+
+pub(super) struct NetworkState {
+    rng: Box<dyn RngCore + Send>,
+    conditions: LinkConditions,
+
+    /// Partition of each node, the nodes that aren't listed are in partition zero
+    partitions: BTreeMap<SimulatedAddress, usize>,
+}
+
+impl NetworkState {
+    pub(super) fn new(rng: Box<dyn RngCore + Send>) -> Self {
+        Self {
+            rng,
+            conditions: LinkConditions::default(),
+            partitions: BTreeMap::new(),
+        }
+    }
+
+    pub(super) fn set_conditions(&mut self, conditions: LinkConditions) {
+        self.conditions = conditions;
+    }
+
+    pub(super) fn set_partitions(&mut self, partitions: BTreeMap<SimulatedAddress, usize>) {
+        self.partitions = partitions;
+    }
+
+    fn is_reachable(&self, from: &SimulatedAddress, to: &SimulatedAddress) -> bool {
+        self.partitions.get(from).unwrap_or(&0) == self.partitions.get(to).unwrap_or(&0)
+    }
+
+    /// Returns when a message sent now is delivered or `None` if it's lost
+    fn delivery_time(&mut self, from: &SimulatedAddress, to: &SimulatedAddress) -> Option<Instant> {
+        if !self.is_reachable(from, to) || self.rng.gen_bool(self.conditions.loss_rate) {
+            return None;
+        }
+
+        let jitter = self.rng.gen_range(0..=self.conditions.jitter.as_nanos() as u64);
+        Some(Instant::now() + self.conditions.latency + Duration::from_nanos(jitter))
+    }
+}
+
+struct Node {
+    network: SharedState,
+    listener: Option<UnboundedSender<PendingConnection>>,
+}
+
+/// Allocate an address for a new node of the network
+pub(super) fn register_node(network: &SharedState) -> SimulatedAddress {
+    let address = SimulatedAddress(NEXT_ADDRESS.fetch_add(1, Ordering::Relaxed));
+    let node = Node {
+        network: Arc::clone(network),
+        listener: None,
+    };
+    NODES.lock().expect("Nodes mutex is poisoned").insert(address, node);
+    address
+}
+
+pub(super) fn unregister_node(address: &SimulatedAddress) {
+    NODES.lock().expect("Nodes mutex is poisoned").remove(address);
+}
+
+/// Spawn a task that delivers the messages sent from one node to another
+///
+/// A single task per direction keeps the messages of a connection in order. The link is
+/// closed if the nodes can't reach each other anymore when a message is due.
+fn spawn_link(
+    network: SharedState,
+    from: SimulatedAddress,
+    to: SimulatedAddress,
+) -> (LinkSender, MessageReceiver) {
+    let (link_sender, mut link_receiver) = unbounded_channel::<(Instant, Message)>();
+    let (sender, receiver) = unbounded_channel();
+
+    tokio::spawn(async move {
+        while let Some((delivery_time, message)) = link_receiver.recv().await {
+            tokio::time::sleep_until(delivery_time).await;
+
+            let is_reachable =
+                network.lock().expect("Network mutex is poisoned").is_reachable(&from, &to);
+            if !is_reachable || sender.send(message).is_err() {
+                break;
+            }
+        }
+    });
+
+    (link_sender, receiver)
+}
+
+#[derive(Debug)]
+pub struct SimulatedMockTransport {}
+
+#[async_trait]
+impl MockTransport for SimulatedMockTransport {
+    type Address = SimulatedAddress;
+    type BannableAddress = SimulatedAddress;
+    type Listener = SimulatedMockListener;
+    type Stream = SimulatedMockStream;
+
+    async fn bind(address: Self::Address) -> Result<Self::Listener> {
+        let mut nodes = NODES.lock().expect("Nodes mutex is poisoned");
+        let node = nodes.get_mut(&address).ok_or(P2pError::DialError(DialError::IoError(
+            io::ErrorKind::AddrNotAvailable,
+        )))?;
+
+        if node.listener.is_some() {
+            return Err(P2pError::DialError(DialError::IoError(
+                io::ErrorKind::AddrInUse,
+            )));
+        }
+
+        let (sender, receiver) = unbounded_channel();
+        node.listener = Some(sender);
+
+        Ok(Self::Listener {
+            address,
+            network: Arc::clone(&node.network),
+            receiver,
+        })
+    }
+
+    async fn connect(_address: Self::Address) -> Result<Self::Stream> {
+        Err(P2pError::Other(
+            "simulated connections must be opened from a node of the network",
+        ))
+    }
+
+    async fn connect_from(
+        local_address: Self::Address,
+        address: Self::Address,
+    ) -> Result<Self::Stream> {
+        let (network, listener) = {
+            let nodes = NODES.lock().expect("Nodes mutex is poisoned");
+            let network = nodes
+                .get(&local_address)
+                .map(|node| Arc::clone(&node.network))
+                .ok_or(P2pError::DialError(DialError::NoAddresses))?;
+            let listener = nodes
+                .get(&address)
+                .filter(|node| Arc::ptr_eq(&node.network, &network))
+                .and_then(|node| node.listener.clone())
+                .ok_or(P2pError::DialError(DialError::NoAddresses))?;
+            (network, listener)
+        };
+
+        let is_reachable = network
+            .lock()
+            .expect("Network mutex is poisoned")
+            .is_reachable(&local_address, &address);
+        if !is_reachable {
+            return Err(P2pError::DialError(DialError::ConnectionRefusedOrTimedOut));
+        }
+
+        let (response_sender, response_receiver) = oneshot::channel();
+        listener
+            .send((local_address, response_sender))
+            .map_err(|_| P2pError::DialError(DialError::NoAddresses))?;
+        let (sender, receiver) = response_receiver.await.map_err(|_| P2pError::ChannelClosed)?;
+
+        Ok(Self::Stream {
+            local_address,
+            remote_address: address,
+            network,
+            sender: Some(sender),
+            receiver,
+        })
+    }
+}
+
+pub struct SimulatedMockListener {
+    address: SimulatedAddress,
+    network: SharedState,
+    receiver: UnboundedReceiver<PendingConnection>,
+}
+
+#[async_trait]
+impl MockListener<SimulatedMockStream, SimulatedAddress> for SimulatedMockListener {
+    async fn accept(&mut self) -> Result<(SimulatedMockStream, SimulatedAddress)> {
+        let (remote_address, response_sender) =
+            self.receiver.recv().await.ok_or(P2pError::ChannelClosed)?;
+
+        let (local_sender, remote_receiver) =
+            spawn_link(Arc::clone(&self.network), self.address, remote_address);
+        let (remote_sender, local_receiver) =
+            spawn_link(Arc::clone(&self.network), remote_address, self.address);
+        response_sender
+            .send((remote_sender, remote_receiver))
+            .map_err(|_| P2pError::ChannelClosed)?;
+
+        Ok((
+            SimulatedMockStream {
+                local_address: self.address,
+                remote_address,
+                network: Arc::clone(&self.network),
+                sender: Some(local_sender),
+                receiver: local_receiver,
+            },
+            remote_address,
+        ))
+    }
+
+    fn local_address(&self) -> Result<SimulatedAddress> {
+        Ok(self.address)
+    }
+}
+
+impl Drop for SimulatedMockListener {
+    fn drop(&mut self) {
+        if let Some(node) = NODES.lock().expect("Nodes mutex is poisoned").get_mut(&self.address) {
+            node.listener = None;
+        }
+    }
+}
+
+pub struct SimulatedMockStream {
+    local_address: SimulatedAddress,
+    remote_address: SimulatedAddress,
+    network: SharedState,
+
+    /// Becomes `None` once the connection is reset
+    sender: Option<LinkSender>,
+    receiver: MessageReceiver,
+}
+
+#[async_trait]
+impl MockStream for SimulatedMockStream {
+    async fn send(&mut self, msg: Message) -> Result<()> {
+        let delivery_time = self
+            .network
+            .lock()
+            .expect("Network mutex is poisoned")
+            .delivery_time(&self.local_address, &self.remote_address);
+
+        match (&self.sender, delivery_time) {
+            (Some(sender), Some(delivery_time)) => {
+                sender.send((delivery_time, msg)).map_err(|_| P2pError::ChannelClosed)
+            }
+            _ => {
+                // Closing the link makes the remote side see the end of the stream
+                self.sender = None;
+                Err(io::Error::from(io::ErrorKind::ConnectionReset).into())
+            }
+        }
+    }
+
+    async fn recv(&mut self) -> Result<Option<Message>> {
+        // Like the channel transport, return the `UnexpectedEof` error when the link is closed
+        self.receiver
+            .recv()
+            .await
+            .ok_or_else(|| io::Error::from(io::ErrorKind::UnexpectedEof).into())
+            .map(Some)
+    }
+}
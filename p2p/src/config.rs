@@ -13,8 +13,13 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::path::PathBuf;
+
+use crypto::random::{rngs::StdRng, SeedableRng};
 use utils::make_config_setting;
 
+use crate::net::types::{Service, Services};
+
 // TODO: does this constant make sense to be zero? Find the justification for it.
 pub const MDNS_DEFAULT_QUERY_INTERVAL: u64 = 0;
 pub const MDNS_DEFAULT_IPV6_STATE: bool = false;
@@ -25,6 +30,33 @@ make_config_setting!(OutboundConnectionTimeout, u64, 10);
 make_config_setting!(MdnsConfigSetting, MdnsConfig, MdnsConfig::Disabled);
 make_config_setting!(MdnsQueryInterval, u64, MDNS_DEFAULT_QUERY_INTERVAL);
 make_config_setting!(MdnsEnableIpV6Discovery, bool, MDNS_DEFAULT_IPV6_STATE);
+make_config_setting!(EnableKademlia, bool, false);
+make_config_setting!(BootNodes, Vec<String>, Vec::new());
+make_config_setting!(ReservedNodes, Vec<String>, Vec::new());
+make_config_setting!(ExternalAddresses, Vec<String>, Vec::new());
+make_config_setting!(BannedSubnets, Vec<String>, Vec::new());
+make_config_setting!(WhitelistedSubnets, Vec<String>, Vec::new());
+make_config_setting!(BanIpv6PrefixLen, u8, 64);
+make_config_setting!(PeerDbPath, Option<PathBuf>, None);
+make_config_setting!(MaxConnections, usize, 128);
+make_config_setting!(MaxInboundConnections, usize, 120);
+make_config_setting!(MaxOutboundConnections, usize, 8);
+make_config_setting!(MaxMessageSize, usize, 10 * 1024 * 1024);
+make_config_setting!(MaxAnnouncementSize, usize, 2 * 1024 * 1024);
+make_config_setting!(MaxHeadersPerMessage, usize, 2000);
+make_config_setting!(MaxBlocksPerMessage, usize, 16);
+make_config_setting!(MaxBlockListSize, usize, 10 * 1024 * 1024);
+make_config_setting!(RequestRateLimitBurst, usize, 500);
+make_config_setting!(RequestRateLimitPerSec, f64, 50.0);
+make_config_setting!(BlockRequestRateLimitBurst, usize, 2000);
+make_config_setting!(BlockRequestRateLimitPerSec, f64, 200.0);
+make_config_setting!(BlockUploadRateLimitBurst, usize, 256 * 1024 * 1024);
+make_config_setting!(BlockUploadRateLimitPerSec, f64, 32.0 * 1024.0 * 1024.0);
+make_config_setting!(HighBandwidthCompactBlocks, bool, true);
+make_config_setting!(BlocksOnly, bool, false);
+make_config_setting!(MessageCaptureDir, Option<PathBuf>, None);
+make_config_setting!(MessageCaptureMaxSize, u64, 1024 * 1024 * 1024);
+make_config_setting!(RngSeed, Option<u64>, None);
 
 /// Multicast DNS configuration.
 #[derive(Debug, Clone)]
@@ -55,4 +87,86 @@ pub struct P2pConfig {
     pub outbound_connection_timeout: OutboundConnectionTimeout,
     /// Multicast DNS configuration.
     pub mdns_config: MdnsConfigSetting,
+    /// Enable the Kademlia DHT peer discovery (libp2p only).
+    pub enable_kademlia: EnableKademlia,
+    /// Addresses of the nodes dialed at startup and when the number of peers is low.
+    pub boot_nodes: BootNodes,
+    /// Addresses of the nodes that are always kept connected and are never banned.
+    pub reserved_nodes: ReservedNodes,
+    /// Addresses the node can be reached at by other nodes, they are announced to the peers.
+    pub external_addresses: ExternalAddresses,
+    /// Subnets whose peers are never allowed to connect.
+    pub banned_subnets: BannedSubnets,
+    /// Subnets whose peers are never banned or evicted and aren't rate limited.
+    pub whitelisted_subnets: WhitelistedSubnets,
+    /// The prefix length of the subnet a misbehaving IPv6 peer is banned by.
+    pub ban_ipv6_prefix_len: BanIpv6PrefixLen,
+    /// The file the bans of misbehaving peers and the bans and whitelist entries created over RPC
+    /// are saved to, they are lost on restart if not set.
+    pub peerdb_path: PeerDbPath,
+    /// The maximum number of active connections.
+    pub max_connections: MaxConnections,
+    /// The maximum number of inbound connections.
+    pub max_inbound_connections: MaxInboundConnections,
+    /// The number of outbound connections the node tries to maintain.
+    pub max_outbound_connections: MaxOutboundConnections,
+    /// The maximum size of a message in bytes, unless its type has a more specific limit.
+    pub max_message_size: MaxMessageSize,
+    /// The maximum size of an announcement in bytes.
+    pub max_announcement_size: MaxAnnouncementSize,
+    /// The maximum number of headers in a header list response.
+    pub max_headers_per_message: MaxHeadersPerMessage,
+    /// The maximum number of blocks requested or sent in one block list message.
+    pub max_blocks_per_message: MaxBlocksPerMessage,
+    /// The maximum size of a block list response in bytes.
+    pub max_block_list_size: MaxBlockListSize,
+    /// The number of requests a peer may send at once before being rate limited.
+    pub request_rate_limit_burst: RequestRateLimitBurst,
+    /// The number of requests per second a peer may send after the burst has been used up.
+    pub request_rate_limit_per_sec: RequestRateLimitPerSec,
+    /// The number of blocks a peer may request at once before being rate limited.
+    pub block_request_rate_limit_burst: BlockRequestRateLimitBurst,
+    /// The number of blocks per second a peer may request after the burst has been used up.
+    pub block_request_rate_limit_per_sec: BlockRequestRateLimitPerSec,
+    /// The number of block bytes that may be sent to a peer at once before it's rate limited.
+    pub block_upload_rate_limit_burst: BlockUploadRateLimitBurst,
+    /// The number of block bytes per second that may be sent to a peer after the burst has
+    /// been used up.
+    pub block_upload_rate_limit_per_sec: BlockUploadRateLimitPerSec,
+    /// Announce new blocks by pushing them as compact blocks instead of sending their headers only.
+    pub high_bandwidth_compact_blocks: HighBandwidthCompactBlocks,
+    /// Don't relay transactions, the transaction relay service isn't advertised to the peers.
+    pub blocks_only: BlocksOnly,
+    /// The directory the syncing messages are captured to, the capture is disabled if not set.
+    pub message_capture_dir: MessageCaptureDir,
+    /// The maximum total size of the capture files in bytes, the capture stops once it's reached.
+    pub message_capture_max_size: MessageCaptureMaxSize,
+    /// Seed of the random number generators, they are seeded from the OS entropy if not set.
+    ///
+    /// Only the network simulations set it so that a run can be reproduced.
+    pub rng_seed: RngSeed,
+}
+
+impl P2pConfig {
+    /// Make the random number generator of a p2p component
+    ///
+    /// The generators of different components are derived from the seed and the component name,
+    /// so they don't produce the same values.
+    pub fn make_rng(&self, component: &str) -> StdRng {
+        match *self.rng_seed {
+            Some(seed) => StdRng::seed_from_u64(
+                component.bytes().fold(seed, |acc, byte| acc.rotate_left(8) ^ u64::from(byte)),
+            ),
+            None => StdRng::from_entropy(),
+        }
+    }
+
+    /// The services advertised to the peers during the handshake
+    pub fn local_services(&self) -> Services {
+        if *self.blocks_only {
+            Services::default().without(Service::TxRelay)
+        } else {
+            Services::default()
+        }
+    }
 }
@@ -25,5 +25,13 @@ pub const PING_TIMEOUT: Duration = Duration::from_secs(60);
 pub const PING_INTERVAL: Duration = Duration::from_secs(60);
 pub const PING_MAX_RETRIES: u32 = 3;
 
-/// Maximum message size
+/// Maximum size of a message accepted by the transports
+///
+/// The lower limits of the individual message types are configured in [`crate::config::P2pConfig`].
 pub const MAX_MESSAGE_SIZE: usize = 10 * 1024 * 1024;
+
+/// Version of the peer-to-peer protocol implemented by this node
+pub const PROTOCOL_VERSION: u32 = 1;
+
+/// Oldest version of the peer-to-peer protocol that the node can still talk to
+pub const MIN_PROTOCOL_VERSION: u32 = 1;
@@ -43,6 +43,18 @@ pub enum ProtocolError {
     InvalidState(&'static str, &'static str),
     #[error("Unable to convert the address to a bannable form: {0}")]
     UnableToConvertAddressToBannable(String),
+    #[error("Peer stalled the block download")]
+    StalledBlockDownload,
+    #[error("Peer sent too many addresses: {0}")]
+    TooManyAddresses(usize),
+    #[error("Peer uses an unsupported protocol version. Minimum version {0}, their version {1}")]
+    UnsupportedProtocolVersion(u32, u32),
+    #[error("Peer sent a {0} message of {1} bytes, the limit is {2} bytes")]
+    MessageTooLarge(&'static str, usize, usize),
+    #[error("Peer sent a {0} message with {1} items, the limit is {2} items")]
+    TooManyItems(&'static str, usize, usize),
+    #[error("Peer exceeded the request rate limit")]
+    RequestRateLimitExceeded,
 }
 
 /// Peer state errors (Errors either for an individual peer or for the [`PeerManager`])
@@ -64,6 +76,10 @@ pub enum PeerError {
     TooManyPeers,
     #[error("Connection to address {0} already pending")]
     Pending(String),
+    #[error("Subnet {0} is not banned")]
+    SubnetNotBanned(String),
+    #[error("Subnet {0} is not whitelisted")]
+    SubnetNotWhitelisted(String),
 }
 
 /// PubSub errors for announcements
@@ -116,6 +132,8 @@ pub enum DialError {
     IoError(std::io::ErrorKind),
     #[error("Failed to negotiate transport protocol")]
     Transport,
+    #[error("Encrypted handshake failed: {0}")]
+    HandshakeFailed(String),
 }
 
 /// Low-level connection errors caused by libp2p
@@ -136,6 +154,8 @@ pub enum ConversionError {
     InvalidPeerId(String),
     #[error("Invalid address: `{0}`")]
     InvalidAddress(String),
+    #[error("Invalid subnet: `{0}`")]
+    InvalidSubnet(String),
     #[error("Failed to decode data: `{0}`")]
     DecodeError(serialization::Error),
 }
@@ -298,6 +318,12 @@ impl BanScore for ProtocolError {
             ProtocolError::InvalidProtocol => 100,
             ProtocolError::InvalidState(_, _) => 100,
             ProtocolError::UnableToConvertAddressToBannable(_) => 100,
+            ProtocolError::StalledBlockDownload => 20,
+            ProtocolError::TooManyAddresses(_) => 20,
+            ProtocolError::UnsupportedProtocolVersion(_, _) => 100,
+            ProtocolError::MessageTooLarge(_, _, _) => 100,
+            ProtocolError::TooManyItems(_, _, _) => 100,
+            ProtocolError::RequestRateLimitExceeded => 20,
         }
     }
 }
@@ -328,6 +354,7 @@ impl BanScore for ConversionError {
         match self {
             ConversionError::InvalidPeerId(_) => 0,
             ConversionError::InvalidAddress(_) => 0,
+            ConversionError::InvalidSubnet(_) => 0,
             ConversionError::DecodeError(_) => 100,
         }
     }
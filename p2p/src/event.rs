@@ -13,11 +13,20 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::time::Duration;
+
 use tokio::sync::oneshot;
 
-use common::chain::block::Block;
+use common::{chain::block::Block, primitives::Id};
 
-use crate::net::NetworkingService;
+use crate::{
+    interface::types::{BannedSubnet, ConnectedPeer, SyncProgress},
+    message::PeerAddress,
+    net::{
+        types::{Services, Subnet},
+        NetworkingService,
+    },
+};
 
 #[derive(Debug)]
 pub enum PeerManagerEvent<T: NetworkingService> {
@@ -39,8 +48,54 @@ pub enum PeerManagerEvent<T: NetworkingService> {
     /// Get peer IDs of connected peers
     GetConnectedPeers(oneshot::Sender<Vec<String>>),
 
+    /// Get detailed information about the connected peers
+    GetPeerInfo(oneshot::Sender<Vec<ConnectedPeer>>),
+
+    /// Ban a subnet for the given duration or permanently
+    BanSubnet(Subnet, Option<Duration>, oneshot::Sender<crate::Result<()>>),
+
+    /// Remove the ban of a subnet
+    UnbanSubnet(Subnet, oneshot::Sender<crate::Result<()>>),
+
+    /// Get the banned subnets
+    GetBannedSubnets(oneshot::Sender<Vec<BannedSubnet>>),
+
+    /// Add a subnet to the whitelist
+    AddToWhitelist(Subnet, oneshot::Sender<crate::Result<()>>),
+
+    /// Remove a subnet from the whitelist
+    RemoveFromWhitelist(Subnet, oneshot::Sender<crate::Result<()>>),
+
+    /// Get the whitelisted subnets
+    GetWhitelist(oneshot::Sender<Vec<String>>),
+
     /// Adjust peer score
     AdjustPeerScore(T::PeerId, u32, oneshot::Sender<crate::Result<()>>),
+
+    /// Get the addresses to send to a peer that requested them
+    GetAddresses(T::PeerId, oneshot::Sender<Vec<PeerAddress>>),
+
+    /// Addresses received from a peer
+    AddressesReceived(
+        T::PeerId,
+        Vec<PeerAddress>,
+        oneshot::Sender<crate::Result<()>>,
+    ),
+
+    /// A peer responded to a request in the given time
+    ResponseTime(T::PeerId, Duration),
+
+    /// A peer delivered a block that was new to the local node
+    BlockDelivered(T::PeerId),
+
+    /// The best block a peer is known to have
+    BestKnownBlock(T::PeerId, Id<Block>),
+
+    /// A message of the given type and size was sent to a peer
+    MessageSent(T::PeerId, &'static str, usize),
+
+    /// A message of the given type and size was received from a peer
+    MessageReceived(T::PeerId, &'static str, usize),
 }
 
 #[derive(Debug)]
@@ -51,9 +106,20 @@ pub enum SyncEvent {
 
 #[derive(Debug)]
 pub enum SyncControlEvent<T: NetworkingService> {
-    /// Peer connected
-    Connected(T::PeerId),
+    /// Peer connected, together with the services it advertised
+    Connected(T::PeerId, Services),
 
     /// Peer disconnected
     Disconnected(T::PeerId),
+
+    /// Peer was added to (`true`) or removed from (`false`) the whitelist
+    ///
+    /// Whitelisted peers are not rate limited.
+    Whitelisted(T::PeerId, bool),
+
+    /// Get the progress of the block synchronization
+    GetSyncProgress(oneshot::Sender<SyncProgress>),
+
+    /// Request a block from the connected peers and submit it to the chainstate
+    FetchBlock(Id<Block>, oneshot::Sender<crate::Result<()>>),
 }
@@ -16,3 +16,4 @@
 pub mod p2p_interface;
 pub mod p2p_interface_impl;
 pub mod p2p_interface_impl_delegation;
+pub mod types;
@@ -13,6 +13,10 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use common::{chain::Block, primitives::Id};
+
+use super::types::{BannedSubnet, ConnectedPeer, SyncProgress};
+
 #[async_trait::async_trait]
 pub trait P2pInterface: Send + Sync {
     async fn connect(&mut self, addr: String) -> crate::Result<()>;
@@ -26,4 +30,22 @@ pub trait P2pInterface: Send + Sync {
     async fn get_peer_id(&self) -> crate::Result<String>;
 
     async fn get_connected_peers(&self) -> crate::Result<Vec<String>>;
+
+    async fn get_peer_info(&self) -> crate::Result<Vec<ConnectedPeer>>;
+
+    async fn get_sync_progress(&self) -> crate::Result<SyncProgress>;
+
+    async fn fetch_block(&self, block_id: Id<Block>) -> crate::Result<()>;
+
+    async fn ban_subnet(&self, subnet: String, duration_secs: Option<u64>) -> crate::Result<()>;
+
+    async fn unban_subnet(&self, subnet: String) -> crate::Result<()>;
+
+    async fn get_banned_subnets(&self) -> crate::Result<Vec<BannedSubnet>>;
+
+    async fn add_to_whitelist(&self, subnet: String) -> crate::Result<()>;
+
+    async fn remove_from_whitelist(&self, subnet: String) -> crate::Result<()>;
+
+    async fn get_whitelist(&self) -> crate::Result<Vec<String>>;
 }
@@ -13,18 +13,23 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::str::FromStr;
+use std::{str::FromStr, time::Duration};
 
 use tokio::sync::oneshot;
 
+use common::{chain::Block, primitives::Id};
+
 use crate::{
     error::{ConversionError, P2pError},
-    event::PeerManagerEvent,
-    net::NetworkingService,
+    event::{PeerManagerEvent, SyncControlEvent},
+    net::{types::Subnet, NetworkingService},
     P2p,
 };
 
-use super::p2p_interface::P2pInterface;
+use super::{
+    p2p_interface::P2pInterface,
+    types::{BannedSubnet, ConnectedPeer, SyncProgress},
+};
 
 #[async_trait::async_trait]
 impl<T> P2pInterface for P2p<T>
@@ -94,4 +99,89 @@ where
             .map_err(P2pError::from)?;
         rx.await.map_err(P2pError::from)
     }
+
+    async fn get_peer_info(&self) -> crate::Result<Vec<ConnectedPeer>> {
+        let (tx, rx) = oneshot::channel();
+        self.tx_peer_manager
+            .send(PeerManagerEvent::GetPeerInfo(tx))
+            .map_err(P2pError::from)?;
+        rx.await.map_err(P2pError::from)
+    }
+
+    async fn get_sync_progress(&self) -> crate::Result<SyncProgress> {
+        let (tx, rx) = oneshot::channel();
+        self.tx_sync_manager
+            .send(SyncControlEvent::GetSyncProgress(tx))
+            .map_err(P2pError::from)?;
+        rx.await.map_err(P2pError::from)
+    }
+
+    async fn fetch_block(&self, block_id: Id<Block>) -> crate::Result<()> {
+        let (tx, rx) = oneshot::channel();
+        self.tx_sync_manager
+            .send(SyncControlEvent::FetchBlock(block_id, tx))
+            .map_err(P2pError::from)?;
+        rx.await.map_err(P2pError::from)?
+    }
+
+    async fn ban_subnet(&self, subnet: String, duration_secs: Option<u64>) -> crate::Result<()> {
+        let (tx, rx) = oneshot::channel();
+        self.tx_peer_manager
+            .send(PeerManagerEvent::BanSubnet(
+                subnet.parse::<Subnet>().map_err(P2pError::ConversionError)?,
+                duration_secs.map(Duration::from_secs),
+                tx,
+            ))
+            .map_err(P2pError::from)?;
+        rx.await.map_err(P2pError::from)?
+    }
+
+    async fn unban_subnet(&self, subnet: String) -> crate::Result<()> {
+        let (tx, rx) = oneshot::channel();
+        self.tx_peer_manager
+            .send(PeerManagerEvent::UnbanSubnet(
+                subnet.parse::<Subnet>().map_err(P2pError::ConversionError)?,
+                tx,
+            ))
+            .map_err(P2pError::from)?;
+        rx.await.map_err(P2pError::from)?
+    }
+
+    async fn get_banned_subnets(&self) -> crate::Result<Vec<BannedSubnet>> {
+        let (tx, rx) = oneshot::channel();
+        self.tx_peer_manager
+            .send(PeerManagerEvent::GetBannedSubnets(tx))
+            .map_err(P2pError::from)?;
+        rx.await.map_err(P2pError::from)
+    }
+
+    async fn add_to_whitelist(&self, subnet: String) -> crate::Result<()> {
+        let (tx, rx) = oneshot::channel();
+        self.tx_peer_manager
+            .send(PeerManagerEvent::AddToWhitelist(
+                subnet.parse::<Subnet>().map_err(P2pError::ConversionError)?,
+                tx,
+            ))
+            .map_err(P2pError::from)?;
+        rx.await.map_err(P2pError::from)?
+    }
+
+    async fn remove_from_whitelist(&self, subnet: String) -> crate::Result<()> {
+        let (tx, rx) = oneshot::channel();
+        self.tx_peer_manager
+            .send(PeerManagerEvent::RemoveFromWhitelist(
+                subnet.parse::<Subnet>().map_err(P2pError::ConversionError)?,
+                tx,
+            ))
+            .map_err(P2pError::from)?;
+        rx.await.map_err(P2pError::from)?
+    }
+
+    async fn get_whitelist(&self) -> crate::Result<Vec<String>> {
+        let (tx, rx) = oneshot::channel();
+        self.tx_peer_manager
+            .send(PeerManagerEvent::GetWhitelist(tx))
+            .map_err(P2pError::from)?;
+        rx.await.map_err(P2pError::from)
+    }
 }
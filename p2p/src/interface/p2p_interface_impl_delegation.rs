@@ -15,7 +15,12 @@
 
 use std::ops::{Deref, DerefMut};
 
-use super::p2p_interface::P2pInterface;
+use common::{chain::Block, primitives::Id};
+
+use super::{
+    p2p_interface::P2pInterface,
+    types::{BannedSubnet, ConnectedPeer, SyncProgress},
+};
 
 #[async_trait::async_trait]
 impl<T: Deref<Target = dyn P2pInterface> + DerefMut<Target = dyn P2pInterface> + Send + Sync>
@@ -44,4 +49,40 @@ impl<T: Deref<Target = dyn P2pInterface> + DerefMut<Target = dyn P2pInterface> +
     async fn get_connected_peers(&self) -> crate::Result<Vec<String>> {
         self.deref().get_connected_peers().await
     }
+
+    async fn get_peer_info(&self) -> crate::Result<Vec<ConnectedPeer>> {
+        self.deref().get_peer_info().await
+    }
+
+    async fn get_sync_progress(&self) -> crate::Result<SyncProgress> {
+        self.deref().get_sync_progress().await
+    }
+
+    async fn fetch_block(&self, block_id: Id<Block>) -> crate::Result<()> {
+        self.deref().fetch_block(block_id).await
+    }
+
+    async fn ban_subnet(&self, subnet: String, duration_secs: Option<u64>) -> crate::Result<()> {
+        self.deref().ban_subnet(subnet, duration_secs).await
+    }
+
+    async fn unban_subnet(&self, subnet: String) -> crate::Result<()> {
+        self.deref().unban_subnet(subnet).await
+    }
+
+    async fn get_banned_subnets(&self) -> crate::Result<Vec<BannedSubnet>> {
+        self.deref().get_banned_subnets().await
+    }
+
+    async fn add_to_whitelist(&self, subnet: String) -> crate::Result<()> {
+        self.deref().add_to_whitelist(subnet).await
+    }
+
+    async fn remove_from_whitelist(&self, subnet: String) -> crate::Result<()> {
+        self.deref().remove_from_whitelist(subnet).await
+    }
+
+    async fn get_whitelist(&self) -> crate::Result<Vec<String>> {
+        self.deref().get_whitelist().await
+    }
 }
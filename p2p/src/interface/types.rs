@@ -0,0 +1,104 @@
+// Copyright (c) 2021-2022 RBB S.r.l
+// opensource@mintlayer.org
+// SPDX-License-Identifier: MIT
+// Licensed under the MIT License;
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://github.com/mintlayer/mintlayer-core/blob/master/LICENSE
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::BTreeMap;
+
+use common::{
+    chain::block::Block,
+    primitives::{BlockHeight, Id},
+};
+
+/// Number of messages of a given type and their total size
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct MessageStats {
+    pub count: u64,
+    pub bytes: u64,
+}
+
+impl MessageStats {
+    pub fn add(&mut self, bytes: usize) {
+        self.count += 1;
+        self.bytes += bytes as u64;
+    }
+}
+
+/// Information about a connected peer returned by [`super::p2p_interface::P2pInterface::get_peer_info()`]
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct ConnectedPeer {
+    /// Unique ID of the peer
+    pub peer_id: String,
+
+    /// Address of the peer
+    pub address: Option<String>,
+
+    /// Is the connection inbound
+    pub inbound: bool,
+
+    /// When the connection was established, in seconds since UNIX epoch
+    pub connected_since: u64,
+
+    /// Protocol version negotiated with the peer
+    pub protocol_version: u32,
+
+    /// User agent of the peer
+    pub user_agent: Option<String>,
+
+    /// ID of the best block the peer is known to have
+    pub best_block: Option<Id<Block>>,
+
+    /// Ban score of the peer
+    pub ban_score: u32,
+
+    /// Round-trip time of the last ping in milliseconds
+    pub ping_latency_ms: Option<u64>,
+
+    /// Messages sent to the peer by message type
+    pub sent: BTreeMap<String, MessageStats>,
+
+    /// Messages received from the peer by message type
+    pub received: BTreeMap<String, MessageStats>,
+}
+
+/// Progress of the block synchronization returned by [`super::p2p_interface::P2pInterface::get_sync_progress()`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct SyncProgress {
+    /// Is the initial block download done
+    pub synced: bool,
+
+    /// Height of the best block of the local node
+    pub block_height: BlockHeight,
+
+    /// Height of the best known header
+    pub header_height: BlockHeight,
+
+    /// Height of the network tip estimated from the headers and the time since the best block
+    pub estimated_tip_height: BlockHeight,
+
+    /// Seconds since the height of the best block last changed
+    pub secs_since_progress: u64,
+
+    /// Number of peers whose best known block is far behind the local best block
+    pub peers_behind: usize,
+}
+
+/// A banned subnet returned by [`super::p2p_interface::P2pInterface::get_banned_subnets()`]
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct BannedSubnet {
+    /// Subnet in the CIDR notation
+    pub subnet: String,
+
+    /// When the ban ends, in seconds since UNIX epoch, the subnet is banned permanently if not set
+    pub banned_till: Option<u64>,
+}
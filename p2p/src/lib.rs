@@ -31,15 +31,16 @@ use tap::TapFallible;
 use tokio::sync::mpsc;
 
 use chainstate::chainstate_interface;
-use common::chain::ChainConfig;
+use common::{chain::ChainConfig, time_getter::TimeGetter};
 use logging::log;
 use mempool::MempoolInterface;
 
 use crate::{
     config::P2pConfig,
     error::{ConversionError, P2pError},
-    event::{PeerManagerEvent, SyncEvent},
+    event::{PeerManagerEvent, SyncControlEvent, SyncEvent},
     net::{ConnectivityService, NetworkingService, SyncingMessagingService},
+    peer_manager::network_time::NetworkTime,
 };
 
 /// Result type with P2P errors
@@ -50,6 +51,9 @@ struct P2p<T: NetworkingService> {
     /// A sender for the peer manager events.
     pub tx_peer_manager: mpsc::UnboundedSender<PeerManagerEvent<T>>,
 
+    /// A sender for the sync manager events.
+    pub tx_sync_manager: mpsc::UnboundedSender<SyncControlEvent<T>>,
+
     /// TX channel for sending syncing/pubsub events
     pub _tx_sync: mpsc::UnboundedSender<SyncEvent>,
 }
@@ -67,7 +71,9 @@ where
         chain_config: Arc<ChainConfig>,
         p2p_config: Arc<P2pConfig>,
         chainstate_handle: subsystem::Handle<Box<dyn chainstate_interface::ChainstateInterface>>,
-        _mempool_handle: subsystem::Handle<Box<dyn MempoolInterface>>,
+        mempool_handle: subsystem::Handle<Box<dyn MempoolInterface>>,
+        network_time: NetworkTime,
+        time_getter: TimeGetter,
     ) -> crate::Result<Self>
     where
         <T as NetworkingService>::Address: FromStr,
@@ -82,6 +88,7 @@ where
             })?,
             Arc::clone(&chain_config),
             Arc::clone(&p2p_config),
+            time_getter.clone(),
         )
         .await?;
 
@@ -98,30 +105,32 @@ where
         let (_tx_sync, _rx_sync) = mpsc::unbounded_channel();
 
         {
-            let chain_config = Arc::clone(&chain_config);
+            let mut peer_manager = peer_manager::PeerManager::<T>::new(
+                Arc::clone(&chain_config),
+                Arc::clone(&p2p_config),
+                conn,
+                rx_peer_manager,
+                tx_p2p_sync.clone(),
+                network_time,
+                time_getter,
+            )?;
             tokio::spawn(async move {
-                peer_manager::PeerManager::<T>::new(
-                    chain_config,
-                    Arc::clone(&p2p_config),
-                    conn,
-                    rx_peer_manager,
-                    tx_p2p_sync,
-                )
-                .run()
-                .await
-                .tap_err(|err| log::error!("PeerManager failed: {err}"))
+                peer_manager.run().await.tap_err(|err| log::error!("PeerManager failed: {err}"))
             });
         }
         {
             let chainstate_handle = chainstate_handle.clone();
             let tx_peer_manager = tx_peer_manager.clone();
             let chain_config = Arc::clone(&chain_config);
+            let p2p_config = Arc::clone(&p2p_config);
 
             tokio::spawn(async move {
                 sync::BlockSyncManager::<T>::new(
                     chain_config,
+                    p2p_config,
                     sync,
                     chainstate_handle,
+                    mempool_handle,
                     rx_p2p_sync,
                     tx_peer_manager,
                 )
@@ -133,6 +142,7 @@ where
 
         Ok(Self {
             tx_peer_manager,
+            tx_sync_manager: tx_p2p_sync,
             _tx_sync,
         })
     }
@@ -147,6 +157,8 @@ pub async fn make_p2p<T>(
     p2p_config: Arc<P2pConfig>,
     chainstate_handle: subsystem::Handle<Box<dyn chainstate_interface::ChainstateInterface>>,
     mempool_handle: subsystem::Handle<Box<dyn MempoolInterface>>,
+    network_time: NetworkTime,
+    time_getter: TimeGetter,
 ) -> crate::Result<Box<dyn P2pInterface>>
 where
     T: NetworkingService + 'static,
@@ -157,6 +169,14 @@ where
     <T as NetworkingService>::PeerId: FromStr,
     <<T as NetworkingService>::PeerId as FromStr>::Err: Debug,
 {
-    let p2p = P2p::<T>::new(chain_config, p2p_config, chainstate_handle, mempool_handle).await?;
+    let p2p = P2p::<T>::new(
+        chain_config,
+        p2p_config,
+        chainstate_handle,
+        mempool_handle,
+        network_time,
+        time_getter,
+    )
+    .await?;
     Ok(Box::new(p2p))
 }
@@ -15,7 +15,10 @@
 
 use chainstate::Locator;
 use common::{
-    chain::block::{Block, BlockHeader},
+    chain::{
+        block::{Block, BlockHeader, BlockReward},
+        signed_transaction::SignedTransaction,
+    },
     primitives::Id,
 };
 use serialization::{Decode, Encode};
@@ -58,12 +61,83 @@ impl BlockListRequest {
     }
 }
 
+#[derive(Debug, Encode, Decode, Clone, PartialEq, Eq)]
+pub struct CompactBlockRequest {
+    block_id: Id<Block>,
+}
+
+impl CompactBlockRequest {
+    pub fn new(block_id: Id<Block>) -> Self {
+        Self { block_id }
+    }
+
+    pub fn block_id(&self) -> &Id<Block> {
+        &self.block_id
+    }
+}
+
+/// Request for the transactions of a compact block that couldn't be found in the mempool
+#[derive(Debug, Encode, Decode, Clone, PartialEq, Eq)]
+pub struct BlockTransactionsRequest {
+    block_id: Id<Block>,
+    indexes: Vec<u32>,
+}
+
+impl BlockTransactionsRequest {
+    pub fn new(block_id: Id<Block>, indexes: Vec<u32>) -> Self {
+        Self { block_id, indexes }
+    }
+
+    pub fn block_id(&self) -> &Id<Block> {
+        &self.block_id
+    }
+
+    pub fn indexes(&self) -> &Vec<u32> {
+        &self.indexes
+    }
+}
+
+/// Request for the addresses of other peers known to the remote node
+#[derive(Debug, Encode, Decode, Clone, PartialEq, Eq)]
+pub struct AddrListRequest {}
+
+impl AddrListRequest {
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+impl Default for AddrListRequest {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 #[derive(Debug, Encode, Decode, Clone, PartialEq, Eq)]
 pub enum Request {
     #[codec(index = 0)]
     HeaderListRequest(HeaderListRequest),
     #[codec(index = 1)]
     BlockListRequest(BlockListRequest),
+    #[codec(index = 2)]
+    CompactBlockRequest(CompactBlockRequest),
+    #[codec(index = 3)]
+    BlockTransactionsRequest(BlockTransactionsRequest),
+    #[codec(index = 4)]
+    AddrListRequest(AddrListRequest),
+}
+
+impl Request {
+    /// Name of the request type, used in the peer statistics
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Request::HeaderListRequest(_) => "HeaderListRequest",
+            Request::BlockListRequest(_) => "BlockListRequest",
+            Request::CompactBlockRequest(_) => "CompactBlockRequest",
+            Request::BlockTransactionsRequest(_) => "BlockTransactionsRequest",
+            Request::AddrListRequest(_) => "AddrListRequest",
+        }
+    }
 }
 
 #[derive(Debug, Encode, Decode, Clone, PartialEq, Eq)]
@@ -104,16 +178,209 @@ impl BlockListResponse {
     }
 }
 
+/// Transaction id shortened to 6 bytes, salted so that collisions can't be forced on all peers
+#[derive(Debug, Encode, Decode, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ShortTxId([u8; 6]);
+
+impl ShortTxId {
+    pub fn new(bytes: [u8; 6]) -> Self {
+        Self(bytes)
+    }
+}
+
+/// A transaction sent as is in a compact block, together with its index in the block
+#[derive(Debug, Encode, Decode, Clone, PartialEq, Eq)]
+pub struct PrefilledTransaction {
+    index: u32,
+    tx: SignedTransaction,
+}
+
+impl PrefilledTransaction {
+    pub fn new(index: u32, tx: SignedTransaction) -> Self {
+        Self { index, tx }
+    }
+
+    pub fn index(&self) -> u32 {
+        self.index
+    }
+
+    pub fn tx(&self) -> &SignedTransaction {
+        &self.tx
+    }
+}
+
+/// A block with its transactions replaced by short ids, except for the prefilled ones. The block
+/// reward is always sent in full.
+#[derive(Debug, Encode, Decode, Clone, PartialEq, Eq)]
+pub struct CompactBlock {
+    header: BlockHeader,
+    salt: u64,
+    block_reward: BlockReward,
+    short_ids: Vec<ShortTxId>,
+    prefilled_txs: Vec<PrefilledTransaction>,
+}
+
+impl CompactBlock {
+    pub fn new(
+        header: BlockHeader,
+        salt: u64,
+        block_reward: BlockReward,
+        short_ids: Vec<ShortTxId>,
+        prefilled_txs: Vec<PrefilledTransaction>,
+    ) -> Self {
+        Self {
+            header,
+            salt,
+            block_reward,
+            short_ids,
+            prefilled_txs,
+        }
+    }
+
+    pub fn header(&self) -> &BlockHeader {
+        &self.header
+    }
+
+    pub fn salt(&self) -> u64 {
+        self.salt
+    }
+
+    pub fn block_reward(&self) -> &BlockReward {
+        &self.block_reward
+    }
+
+    /// Short ids of the transactions that aren't prefilled, in block order
+    pub fn short_ids(&self) -> &Vec<ShortTxId> {
+        &self.short_ids
+    }
+
+    pub fn prefilled_txs(&self) -> &Vec<PrefilledTransaction> {
+        &self.prefilled_txs
+    }
+}
+
+#[derive(Debug, Encode, Decode, Clone, PartialEq, Eq)]
+pub struct BlockTransactionsResponse {
+    block_id: Id<Block>,
+    txs: Vec<SignedTransaction>,
+}
+
+impl BlockTransactionsResponse {
+    pub fn new(block_id: Id<Block>, txs: Vec<SignedTransaction>) -> Self {
+        Self { block_id, txs }
+    }
+
+    pub fn block_id(&self) -> &Id<Block> {
+        &self.block_id
+    }
+
+    pub fn txs(&self) -> &Vec<SignedTransaction> {
+        &self.txs
+    }
+
+    pub fn into_txs(self) -> Vec<SignedTransaction> {
+        self.txs
+    }
+}
+
+/// Address of a peer as gossiped between nodes
+///
+/// The peer ID and the address are sent in their textual form so the message is independent of
+/// the networking backend. The timestamp is the time the address was last seen, in seconds since
+/// the Unix epoch.
+#[derive(Debug, Encode, Decode, Clone, PartialEq, Eq)]
+pub struct PeerAddress {
+    peer_id: String,
+    address: String,
+    timestamp: u64,
+}
+
+impl PeerAddress {
+    pub fn new(peer_id: String, address: String, timestamp: u64) -> Self {
+        Self {
+            peer_id,
+            address,
+            timestamp,
+        }
+    }
+
+    pub fn peer_id(&self) -> &str {
+        &self.peer_id
+    }
+
+    pub fn address(&self) -> &str {
+        &self.address
+    }
+
+    pub fn timestamp(&self) -> u64 {
+        self.timestamp
+    }
+}
+
+#[derive(Debug, Encode, Decode, Clone, PartialEq, Eq)]
+pub struct AddrListResponse {
+    addresses: Vec<PeerAddress>,
+}
+
+impl AddrListResponse {
+    pub fn new(addresses: Vec<PeerAddress>) -> Self {
+        Self { addresses }
+    }
+
+    pub fn addresses(&self) -> &Vec<PeerAddress> {
+        &self.addresses
+    }
+
+    pub fn into_addresses(self) -> Vec<PeerAddress> {
+        self.addresses
+    }
+}
+
 #[derive(Debug, Encode, Decode, Clone, PartialEq, Eq)]
 pub enum Response {
     #[codec(index = 0)]
     HeaderListResponse(HeaderListResponse),
     #[codec(index = 1)]
     BlockListResponse(BlockListResponse),
+    #[codec(index = 2)]
+    CompactBlockResponse(CompactBlock),
+    #[codec(index = 3)]
+    BlockTransactionsResponse(BlockTransactionsResponse),
+    #[codec(index = 4)]
+    AddrListResponse(AddrListResponse),
+}
+
+impl Response {
+    /// Name of the response type, used in the peer statistics
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Response::HeaderListResponse(_) => "HeaderListResponse",
+            Response::BlockListResponse(_) => "BlockListResponse",
+            Response::CompactBlockResponse(_) => "CompactBlockResponse",
+            Response::BlockTransactionsResponse(_) => "BlockTransactionsResponse",
+            Response::AddrListResponse(_) => "AddrListResponse",
+        }
+    }
 }
 
 #[derive(Debug, Encode, Decode, Clone, PartialEq, Eq)]
 pub enum Announcement {
+    /// A new block is announced by its header only; peers that don't have the block yet
+    /// download it with a `BlockListRequest`
     #[codec(index = 0)]
-    Block(Block),
+    Block(BlockHeader),
+    /// A new block is pushed as a compact block (high-bandwidth mode), so that peers can rebuild
+    /// it right away without another round trip
+    #[codec(index = 1)]
+    CompactBlock(CompactBlock),
+}
+
+impl Announcement {
+    /// Name of the announcement type, used in the peer statistics
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Announcement::Block(_) => "BlockAnnouncement",
+            Announcement::CompactBlock(_) => "CompactBlockAnnouncement",
+        }
+    }
 }
@@ -37,6 +37,7 @@ use crate::{
                 self,
                 sync_codec::message_types::{SyncRequest, SyncResponse},
             },
+            constants::KADEMLIA_BOOTSTRAP_INTERVAL,
             types::{self, ControlEvent, Libp2pBehaviourEvent},
         },
     },
@@ -82,6 +83,7 @@ impl Libp2pBackend {
     pub async fn run(&mut self) -> crate::Result<void::Void> {
         log::debug!("starting event loop");
 
+        let mut kademlia_bootstrap = tokio::time::interval(KADEMLIA_BOOTSTRAP_INTERVAL);
         loop {
             tokio::select! {
                 event = self.swarm.select_next_some() => match event {
@@ -112,6 +114,16 @@ impl Libp2pBackend {
                     Some(cmd) => self.on_command(cmd)?,
                     None => return Err(P2pError::ChannelClosed),
                 },
+                _ = kademlia_bootstrap.tick() => self.bootstrap_kademlia(),
+            }
+        }
+    }
+
+    /// Refresh the Kademlia routing table, if Kademlia is enabled
+    fn bootstrap_kademlia(&mut self) {
+        if let Some(kademlia) = self.swarm.behaviour_mut().kademlia.as_mut() {
+            if let Err(err) = kademlia.bootstrap() {
+                log::debug!("Kademlia bootstrap failed: {err:?}");
             }
         }
     }
@@ -262,6 +274,21 @@ impl Libp2pBackend {
         }
     }
 
+    /// Drop a received request, the remote peer sees the request fail once its response
+    /// channel is dropped
+    fn drop_request(
+        &mut self,
+        request_id: RequestId,
+        channel: oneshot::Sender<crate::Result<()>>,
+    ) -> crate::Result<()> {
+        log::trace!("drop request {request_id}");
+
+        if self.swarm.behaviour_mut().pending_reqs.remove(&request_id).is_none() {
+            log::error!("pending request {request_id} doesn't exist");
+        }
+        channel.send(Ok(())).map_err(|_| P2pError::ChannelClosed)
+    }
+
     /// Subscribe to GossipSub topics
     fn subscribe(
         &mut self,
@@ -325,11 +352,30 @@ impl Libp2pBackend {
                 response,
                 channel,
             } => self.send_response(request_id, *response, channel),
+            types::Command::DropRequest {
+                request_id,
+                channel,
+            } => self.drop_request(request_id, channel),
             types::Command::Subscribe { topics, response } => self.subscribe(topics, response),
             types::Command::BanPeer { peer_id, response } => self.ban_peer(peer_id, response),
             types::Command::ListenAddress { response } => {
                 response.send(self.listen_addr.clone()).map_err(|_| P2pError::ChannelClosed)
             }
+            types::Command::ExternalAddresses { response } => {
+                let peer_id = *self.swarm.local_peer_id();
+                let addresses = self
+                    .swarm
+                    .external_addresses()
+                    .map(|record| {
+                        record.addr.clone().with(libp2p::multiaddr::Protocol::P2p(peer_id.into()))
+                    })
+                    .collect();
+                response.send(addresses).map_err(|_| P2pError::ChannelClosed)
+            }
+            types::Command::PingLatency { peer_id, response } => {
+                let latency = self.swarm.behaviour().ping_latencies.get(&peer_id).copied();
+                response.send(latency).map_err(|_| P2pError::ChannelClosed)
+            }
         }
     }
 }
@@ -386,7 +432,7 @@ mod tests {
         .expect("configuration to be valid");
 
         let identify = Identify::new(IdentifyConfig::new(
-            "/mintlayer/0.1.0-13371338".into(),
+            "/mintlayer/0.1.0-13371338/1/1d".into(),
             id_keys.public(),
         ));
 
@@ -401,9 +447,11 @@ mod tests {
             sync,
             connmgr: connection_manager::ConnectionManager::new(),
             discovery: discovery::DiscoveryManager::new(Default::default()).await,
+            kademlia: None.into(),
             events: VecDeque::new(),
             pending_reqs: HashMap::new(),
             waker: None,
+            ping_latencies: HashMap::new(),
         };
 
         SwarmBuilder::new(transport, behaviour, peer_id).build()
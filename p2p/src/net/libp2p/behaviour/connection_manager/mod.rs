@@ -327,6 +327,11 @@ impl NetworkBehaviour for ConnectionManager {
         _other_established: usize,
     ) {
         match endpoint {
+            // connections that weren't dialed through `ConnectionManager` (e.g., by Kademlia
+            // queries) aren't tracked and the front-end isn't informed about them
+            ConnectedPoint::Dialer { .. } if !self.connections.contains_key(peer_id) => {
+                log::debug!("untracked outbound connection established to peer {peer_id}");
+            }
             ConnectedPoint::Dialer { .. } => {
                 if let Err(err) = self.handle_dialer_connection_established(peer_id) {
                     log::error!(
@@ -359,6 +364,11 @@ impl NetworkBehaviour for ConnectionManager {
         _event: <Self::ConnectionHandler as IntoConnectionHandler>::Handler,
         _remaining_established: usize,
     ) {
+        if !self.connections.contains_key(peer_id) {
+            log::debug!("untracked connection closed for peer {peer_id}");
+            return;
+        }
+
         if let Err(err) = self.handle_connection_closed(peer_id) {
             log::error!(
                 "Connection closed unsuccessfully for peer {}: {}",
@@ -0,0 +1,116 @@
+// Copyright (c) 2022 RBB S.r.l
+// opensource@mintlayer.org
+// SPDX-License-Identifier: MIT
+// Licensed under the MIT License;
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://github.com/mintlayer/mintlayer-core/blob/master/LICENSE
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Kademlia DHT discovery behaviour for the libp2p backend
+//!
+//! The protocol name includes the chain magic bytes so that the nodes of different networks
+//! never end up in each other's routing tables.
+
+use common::chain::ChainConfig;
+use libp2p::{
+    kad::{record::store::MemoryStore, Kademlia, KademliaConfig},
+    swarm::behaviour::toggle::Toggle,
+    Multiaddr, PeerId,
+};
+use logging::log;
+
+use crate::config;
+
+/// Get the Kademlia protocol name of the chain
+pub fn protocol_name(chain_config: &ChainConfig) -> String {
+    format!("/mintlayer/kad/{:x}", chain_config.magic_bytes_as_u32())
+}
+
+/// Create the Kademlia behaviour, seeded with the boot nodes
+///
+/// The behaviour is disabled unless enabled in the p2p configuration. Boot node addresses that
+/// don't contain the peer ID can't be added to the routing table and are skipped.
+pub fn make_kademlia(
+    chain_config: &ChainConfig,
+    p2p_config: &config::P2pConfig,
+    local_peer_id: PeerId,
+) -> Toggle<Kademlia<MemoryStore>> {
+    if !*p2p_config.enable_kademlia {
+        return Toggle::from(None);
+    }
+
+    let mut config = KademliaConfig::default();
+    config.set_protocol_name(protocol_name(chain_config).into_bytes());
+    let mut kademlia =
+        Kademlia::with_config(local_peer_id, MemoryStore::new(local_peer_id), config);
+
+    for address in p2p_config.boot_nodes.iter() {
+        let peer = address
+            .parse::<Multiaddr>()
+            .ok()
+            .and_then(|addr| PeerId::try_from_multiaddr(&addr).map(|peer_id| (peer_id, addr)));
+        match peer {
+            Some((peer_id, addr)) => {
+                kademlia.add_address(&peer_id, addr);
+            }
+            None => log::warn!("boot node address {address} has no peer id, not used for Kademlia"),
+        }
+    }
+
+    Toggle::from(Some(kademlia))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn kademlia_disabled() {
+        let kademlia = make_kademlia(
+            &common::chain::config::create_mainnet(),
+            &Default::default(),
+            PeerId::random(),
+        );
+        assert!(!kademlia.is_enabled());
+    }
+
+    #[test]
+    fn kademlia_seeded_from_boot_nodes() {
+        let chain_config = common::chain::config::create_mainnet();
+        let p2p_config = config::P2pConfig {
+            enable_kademlia: true.into(),
+            boot_nodes: vec![
+                "/ip6/::1/tcp/3031/p2p/12D3KooWRn14SemPVxwzdQNg8e8Trythiww1FWrNfPbukYBmZEbJ".into(),
+                "/ip6/::1/tcp/3032".into(),
+            ]
+            .into(),
+            ..Default::default()
+        };
+
+        let mut kademlia = make_kademlia(&chain_config, &p2p_config, PeerId::random());
+        let kademlia = kademlia.as_mut().expect("Kademlia to be enabled");
+        assert_eq!(
+            kademlia.protocol_name(),
+            protocol_name(&chain_config).as_bytes()
+        );
+        assert_eq!(
+            kademlia.kbuckets().map(|bucket| bucket.num_entries()).sum::<usize>(),
+            1
+        );
+    }
+
+    #[test]
+    fn protocol_name_depends_on_chain() {
+        assert_ne!(
+            protocol_name(&common::chain::config::create_mainnet()),
+            protocol_name(&common::chain::config::create_regtest())
+        );
+    }
+}
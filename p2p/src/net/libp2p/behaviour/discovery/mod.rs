@@ -33,6 +33,7 @@ use std::{
     task::{Context, Poll},
 };
 
+pub mod kademlia;
 mod mdns;
 
 pub enum DiscoveryEvent {
@@ -25,19 +25,24 @@ use std::{
     num::NonZeroU32,
     sync::Arc,
     task::{Context, Poll, Waker},
+    time::Duration,
 };
 
 use libp2p::{
     gossipsub::{self, Gossipsub, GossipsubConfigBuilder, MessageAuthenticity, ValidationMode},
-    identify, identity, ping,
+    identify, identity,
+    kad::{record::store::MemoryStore, Kademlia, KademliaEvent},
+    ping,
     request_response::{
         InboundFailure, OutboundFailure, ProtocolSupport, RequestId, RequestResponse,
         RequestResponseConfig, RequestResponseEvent, RequestResponseMessage, ResponseChannel,
     },
     swarm::{
-        ConnectionHandler, IntoConnectionHandler, NetworkBehaviour as Libp2pNetworkBehaviour,
-        NetworkBehaviourAction, NetworkBehaviourEventProcess, PollParameters,
+        behaviour::toggle::Toggle, ConnectionHandler, IntoConnectionHandler,
+        NetworkBehaviour as Libp2pNetworkBehaviour, NetworkBehaviourAction,
+        NetworkBehaviourEventProcess, PollParameters,
     },
+    PeerId,
 };
 
 use common::chain::config::ChainConfig;
@@ -45,7 +50,7 @@ use logging::log;
 use serialization::Decode;
 
 use crate::{
-    config,
+    config, constants,
     error::{P2pError, ProtocolError},
     message,
     net::{
@@ -59,14 +64,32 @@ use crate::{
                 },
             },
             constants::{
-                GOSSIPSUB_HEARTBEAT, GOSSIPSUB_MAX_TRANSMIT_SIZE, PING_INTERVAL, PING_MAX_RETRIES,
-                PING_TIMEOUT, REQ_RESP_TIMEOUT,
+                GOSSIPSUB_HEARTBEAT, PING_INTERVAL, PING_MAX_RETRIES, PING_TIMEOUT,
+                REQ_RESP_TIMEOUT,
             },
             types::{self, ConnectivityEvent, Libp2pBehaviourEvent, SyncingEvent},
         },
+        types::Services,
     },
 };
 
+/// Get the identify protocol string of the node
+///
+/// Besides the chain version and magic bytes, the string carries the highest p2p protocol version
+/// and the services supported by the node because libp2p doesn't have a handshake of its own.
+pub fn identify_protocol(chain_config: &ChainConfig, services: Services) -> String {
+    let version = chain_config.version();
+    format!(
+        "/mintlayer/{}.{}.{}-{:x}/{}/{:x}",
+        version.major,
+        version.minor,
+        version.patch,
+        chain_config.magic_bytes_as_u32(),
+        constants::PROTOCOL_VERSION,
+        services.bits(),
+    )
+}
+
 /// `Libp2pBehaviour` defines the protocols that communicate with peers, such as different streams
 /// (sync, e.g., is a separate stream that's prefixed, at the stream-level, with `SyncingProtocol::protocol_name()`,
 /// which is done through the demultiplexer of streams)
@@ -90,6 +113,7 @@ pub struct Libp2pBehaviour {
     pub connmgr: connection_manager::ConnectionManager,
     pub identify: identify::Identify,
     pub discovery: discovery::DiscoveryManager,
+    pub kademlia: Toggle<Kademlia<MemoryStore>>,
     pub gossipsub: Gossipsub,
     pub ping: ping::Behaviour,
     pub sync: RequestResponse<SyncMessagingCodec>,
@@ -100,6 +124,8 @@ pub struct Libp2pBehaviour {
     pub pending_reqs: HashMap<RequestId, ResponseChannel<SyncResponse>>,
     #[behaviour(ignore)]
     pub waker: Option<Waker>,
+    #[behaviour(ignore)]
+    pub ping_latencies: HashMap<PeerId, Duration>,
 }
 
 pub type Libp2pNetworkBehaviourAction = NetworkBehaviourAction<
@@ -117,19 +143,12 @@ impl Libp2pBehaviour {
         let gossipsub_config = GossipsubConfigBuilder::default()
             .heartbeat_interval(GOSSIPSUB_HEARTBEAT)
             .validation_mode(ValidationMode::Strict)
-            .max_transmit_size(GOSSIPSUB_MAX_TRANSMIT_SIZE)
+            .max_transmit_size(*p2p_config.max_announcement_size)
             .validate_messages()
             .build()
             .expect("configuration to be valid");
 
-        let version = config.version();
-        let protocol = format!(
-            "/mintlayer/{}.{}.{}-{:x}",
-            version.major,
-            version.minor,
-            version.patch,
-            config.magic_bytes_as_u32(),
-        );
+        let protocol = identify_protocol(&config, p2p_config.local_services());
         let mut req_cfg = RequestResponseConfig::default();
         req_cfg.set_request_timeout(REQ_RESP_TIMEOUT);
 
@@ -158,9 +177,15 @@ impl Libp2pBehaviour {
             .expect("configuration to be valid"),
             connmgr: connection_manager::ConnectionManager::new(),
             discovery: discovery::DiscoveryManager::new(Arc::clone(&p2p_config)).await,
+            kademlia: discovery::kademlia::make_kademlia(
+                &config,
+                &p2p_config,
+                id_keys.public().to_peer_id(),
+            ),
             events: VecDeque::new(),
             pending_reqs: HashMap::new(),
             waker: None,
+            ping_latencies: HashMap::new(),
         };
 
         behaviour
@@ -205,6 +230,19 @@ impl NetworkBehaviourEventProcess<identify::IdentifyEvent> for Libp2pBehaviour {
                 log::error!("libp2p-identify error for peer {peer_id}: {error}");
             }
             identify::IdentifyEvent::Received { peer_id, info } => {
+                // the listen addresses of the inbound peers are learned only from identify
+                if let Some(kademlia) = self.kademlia.as_mut() {
+                    if info.protocols.iter().any(|p| p.as_bytes() == kademlia.protocol_name()) {
+                        for address in info.listen_addrs.iter() {
+                            kademlia.add_address(&peer_id, address.clone());
+                        }
+                    }
+                }
+
+                if !self.connmgr.connections().contains_key(&peer_id) {
+                    // the connection was opened by a Kademlia query and isn't tracked
+                    return;
+                }
                 if let Err(err) = self.connmgr.register_identify_info(&peer_id, info) {
                     log::error!("Failed to register `IdentifyInfo` for peer {peer_id}: {err}",);
                 }
@@ -227,8 +265,8 @@ impl NetworkBehaviourEventProcess<ping::PingEvent> for Libp2pBehaviour {
 
         match result {
             Result::Ok(ping::Success::Ping { rtt }) => {
-                // TODO: report rtt to swarm manager?
                 log::debug!("peer {peer} responded to ping, rtt {rtt:?}");
+                self.ping_latencies.insert(peer, rtt);
             }
             Result::Ok(ping::Success::Pong) => {
                 log::trace!("peer {peer} responded to pong");
@@ -421,6 +459,7 @@ impl NetworkBehaviourEventProcess<ConnectionManagerEvent> for Libp2pBehaviour {
                     ))
                 }
                 BehaviourEvent::ConnectionClosed { peer_id } => {
+                    self.ping_latencies.remove(&peer_id);
                     self.add_event(Libp2pBehaviourEvent::Connectivity(
                         ConnectivityEvent::ConnectionClosed { peer_id },
                     ))
@@ -456,3 +495,22 @@ impl NetworkBehaviourEventProcess<discovery::DiscoveryEvent> for Libp2pBehaviour
         }
     }
 }
+
+impl NetworkBehaviourEventProcess<KademliaEvent> for Libp2pBehaviour {
+    /// The peers added to the Kademlia routing table are reported to the front-end as discovered
+    fn inject_event(&mut self, event: KademliaEvent) {
+        match event {
+            KademliaEvent::RoutingUpdated {
+                peer, addresses, ..
+            } => {
+                let peers = addresses.iter().map(|address| (peer, address.clone())).collect();
+                self.add_event(Libp2pBehaviourEvent::Connectivity(
+                    ConnectivityEvent::Discovered { peers },
+                ));
+            }
+            event => {
+                log::trace!("Kademlia event {event:?}");
+            }
+        }
+    }
+}
@@ -22,7 +22,6 @@ use std::time::Duration;
 /// Gossipsub configuration
 // TODO: config or spec?
 pub const GOSSIPSUB_HEARTBEAT: Duration = Duration::from_secs(10);
-pub const GOSSIPSUB_MAX_TRANSMIT_SIZE: usize = 2 * 1024 * 1024;
 
 /// Ping configuration
 /// NOTE: these are not from config but part of Mintlayer's protocol spec
@@ -30,6 +29,9 @@ pub const PING_TIMEOUT: Duration = Duration::from_secs(60);
 pub const PING_INTERVAL: Duration = Duration::from_secs(60);
 pub const PING_MAX_RETRIES: u32 = 3;
 
+/// How often a Kademlia bootstrap query is made to refresh the routing table
+pub const KADEMLIA_BOOTSTRAP_INTERVAL: Duration = Duration::from_secs(5 * 60);
+
 /// Request-response configuration
 pub const REQ_RESP_TIMEOUT: Duration = Duration::from_secs(10);
 pub const MESSAGE_MAX_SIZE: usize = 10 * 1024 * 1024;
@@ -38,13 +38,15 @@ use libp2p::{
 };
 use tokio::sync::{mpsc, oneshot};
 
+use common::time_getter::TimeGetter;
 use logging::log;
 
 use crate::{
     config,
     error::{DialError, P2pError},
     net::{
-        libp2p::backend::Libp2pBackend, AsBannableAddress, IsBannableAddress, NetworkingService,
+        ip_network_group, libp2p::backend::Libp2pBackend, AsBannableAddress, AsNetworkGroup,
+        IsBannableAddress, IsIpv6Address, NetworkingService,
     },
 };
 
@@ -80,6 +82,7 @@ impl NetworkingService for Libp2pService {
         bind_addr: Self::Address,
         chain_config: Arc<common::chain::ChainConfig>,
         p2p_config: Arc<config::P2pConfig>,
+        _time_getter: TimeGetter,
     ) -> crate::Result<(Self::ConnectivityHandle, Self::SyncingMessagingHandle)> {
         let (peer_id, id_keys, noise_keys) = make_libp2p_keys();
         let transport = TokioTcpTransport::new(GenTcpConfig::new().nodelay(true))
@@ -129,7 +132,7 @@ impl NetworkingService for Libp2pService {
 
         Ok((
             Self::ConnectivityHandle::new(peer_id, cmd_tx.clone(), conn_rx),
-            Self::SyncingMessagingHandle::new(cmd_tx, sync_rx),
+            Self::SyncingMessagingHandle::new(cmd_tx, sync_rx, *p2p_config.max_announcement_size),
         ))
     }
 }
@@ -149,6 +152,18 @@ impl IsBannableAddress for Multiaddr {
     }
 }
 
+impl IsIpv6Address for Multiaddr {
+    fn is_ipv6(&self) -> bool {
+        std::matches!(get_ip(self), Some(IpAddr::V6(_)))
+    }
+}
+
+impl AsNetworkGroup for Multiaddr {
+    fn network_group(&self) -> Vec<u8> {
+        get_ip(self).map_or_else(Vec::new, |ip| ip_network_group(&ip))
+    }
+}
+
 fn get_ip(address: &Multiaddr) -> Option<IpAddr> {
     // TODO: using a loop is wrong here. There should be a function that extracts the address from Multiaddr
     for component in address.iter() {
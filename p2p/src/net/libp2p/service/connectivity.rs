@@ -13,7 +13,7 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::{collections::BTreeSet, str::FromStr};
+use std::{collections::BTreeSet, str::FromStr, time::Duration};
 
 use async_trait::async_trait;
 use itertools::Itertools;
@@ -25,6 +25,7 @@ use common::primitives::semver::SemVer;
 use logging::log;
 
 use crate::{
+    constants,
     error::{ConversionError, P2pError, ProtocolError},
     net::{
         self,
@@ -150,17 +151,31 @@ where
 
     fn try_into(self) -> Result<net::types::PeerInfo<T>, Self::Error> {
         let proto = self.protocol_version.clone();
-        let (version, magic_bytes) =
-            match sscanf::scanf!(proto, "/{}/{}.{}.{}-{:x}", String, u8, u8, u16, u32) {
-                Err(_err) => Err(P2pError::ProtocolError(ProtocolError::InvalidProtocol)),
-                Ok((proto, maj, min, pat, magic)) => {
-                    if proto != "mintlayer" {
-                        return Err(P2pError::ProtocolError(ProtocolError::InvalidProtocol));
-                    }
-
-                    Ok((SemVer::new(maj, min, pat), magic.to_le_bytes()))
+        let (version, magic_bytes, protocol_version, services) = match sscanf::scanf!(
+            proto,
+            "/{}/{}.{}.{}-{:x}/{}/{:x}",
+            String,
+            u8,
+            u8,
+            u16,
+            u32,
+            u32,
+            u64
+        ) {
+            Err(_err) => Err(P2pError::ProtocolError(ProtocolError::InvalidProtocol)),
+            Ok((proto, maj, min, pat, magic, protocol_version, services)) => {
+                if proto != "mintlayer" {
+                    return Err(P2pError::ProtocolError(ProtocolError::InvalidProtocol));
                 }
-            }?;
+
+                Ok((
+                    SemVer::new(maj, min, pat),
+                    magic.to_le_bytes(),
+                    protocol_version,
+                    net::types::Services::from_bits(services),
+                ))
+            }
+        }?;
 
         Ok(net::types::PeerInfo {
             peer_id: PeerId::from_public_key(&self.public_key),
@@ -168,6 +183,11 @@ where
             version,
             agent: Some(self.agent_version.clone()),
             protocols: parse_protocols(&self.protocols),
+            // The connection uses the highest protocol version supported by both nodes
+            protocol_version: std::cmp::min(protocol_version, constants::PROTOCOL_VERSION),
+            services,
+            // TODO: libp2p doesn't exchange the peer's local time
+            time: None,
         })
     }
 }
@@ -213,6 +233,12 @@ where
         rx.await.map_err(P2pError::from)
     }
 
+    async fn external_addresses(&self) -> crate::Result<Vec<T::Address>> {
+        let (tx, rx) = oneshot::channel();
+        self.cmd_tx.send(types::Command::ExternalAddresses { response: tx })?;
+        rx.await.map_err(P2pError::from)
+    }
+
     fn peer_id(&self) -> &T::PeerId {
         &self.peer_id
     }
@@ -228,6 +254,15 @@ where
         rx.await.map_err(P2pError::from)?.map_err(P2pError::from)
     }
 
+    async fn ping_latency(&mut self, peer_id: T::PeerId) -> crate::Result<Option<Duration>> {
+        let (tx, rx) = oneshot::channel();
+        self.cmd_tx.send(types::Command::PingLatency {
+            peer_id,
+            response: tx,
+        })?;
+        rx.await.map_err(P2pError::from)
+    }
+
     async fn poll_next(&mut self) -> crate::Result<ConnectivityEvent<T>> {
         match self.conn_rx.recv().await.ok_or(P2pError::ChannelClosed)? {
             types::ConnectivityEvent::OutboundAccepted { address, peer_info } => {
@@ -343,4 +378,42 @@ mod tests {
         ]);
         assert_eq!(expected, parsed);
     }
+
+    #[test]
+    fn identify_info_carries_services() {
+        let config = common::chain::config::create_mainnet();
+        let services = net::types::Services::default().without(net::types::Service::TxRelay);
+        let info = types::IdentifyInfoWrapper::new(libp2p::identify::IdentifyInfo {
+            public_key: libp2p::identity::Keypair::generate_ed25519().public(),
+            protocol_version: net::libp2p::behaviour::identify_protocol(&config, services),
+            agent_version: "".to_string(),
+            listen_addrs: vec![],
+            protocols: vec![],
+            observed_addr: Multiaddr::empty(),
+        });
+
+        let peer_info: net::types::PeerInfo<net::libp2p::Libp2pService> = info.try_into().unwrap();
+        assert_eq!(&peer_info.magic_bytes, config.magic_bytes());
+        assert_eq!(&peer_info.version, config.version());
+        assert_eq!(peer_info.protocol_version, constants::PROTOCOL_VERSION);
+        assert_eq!(peer_info.services, services);
+    }
+
+    #[test]
+    fn identify_info_without_services_rejected() {
+        let info = types::IdentifyInfoWrapper::new(libp2p::identify::IdentifyInfo {
+            public_key: libp2p::identity::Keypair::generate_ed25519().public(),
+            protocol_version: "/mintlayer/0.1.0-13371338".to_string(),
+            agent_version: "".to_string(),
+            listen_addrs: vec![],
+            protocols: vec![],
+            observed_addr: Multiaddr::empty(),
+        });
+
+        let result: Result<net::types::PeerInfo<net::libp2p::Libp2pService>, _> = info.try_into();
+        assert!(matches!(
+            result,
+            Err(P2pError::ProtocolError(ProtocolError::InvalidProtocol))
+        ));
+    }
 }
@@ -26,7 +26,6 @@ use crate::{
     net::{
         libp2p::{
             behaviour::sync_codec::message_types::{SyncRequest, SyncResponse},
-            constants::GOSSIPSUB_MAX_TRANSMIT_SIZE,
             types::{Command, SyncingEvent as P2pSyncingEvent},
         },
         types::{PubSubTopic, SyncingEvent, ValidationResult},
@@ -41,6 +40,9 @@ pub struct Libp2pSyncHandle<T: NetworkingService> {
 
     /// Channel for receiving pubsub events from libp2p backend
     sync_rx: mpsc::UnboundedReceiver<P2pSyncingEvent>,
+
+    /// Maximum size of an announcement
+    max_announcement_size: usize,
     _marker: std::marker::PhantomData<fn() -> T>,
 }
 
@@ -48,10 +50,12 @@ impl<T: NetworkingService> Libp2pSyncHandle<T> {
     pub fn new(
         cmd_tx: mpsc::UnboundedSender<Command>,
         sync_rx: mpsc::UnboundedReceiver<P2pSyncingEvent>,
+        max_announcement_size: usize,
     ) -> Self {
         Self {
             cmd_tx,
             sync_rx,
+            max_announcement_size,
             _marker: Default::default(),
         }
     }
@@ -98,20 +102,32 @@ where
         rx.await?
     }
 
+    async fn drop_request(&mut self, request_id: T::SyncingPeerRequestId) -> crate::Result<()> {
+        let (tx, rx) = oneshot::channel();
+        self.cmd_tx.send(Command::DropRequest {
+            request_id,
+            channel: tx,
+        })?;
+
+        // The first error indicates the channel being closed and the second one is a p2p error.
+        rx.await?
+    }
+
     async fn make_announcement(
         &mut self,
         announcement: message::Announcement,
     ) -> crate::Result<()> {
         let message = announcement.encode();
-        if message.len() > GOSSIPSUB_MAX_TRANSMIT_SIZE {
+        if message.len() > self.max_announcement_size {
             return Err(P2pError::PublishError(PublishError::MessageTooLarge(
                 Some(message.len()),
-                Some(GOSSIPSUB_MAX_TRANSMIT_SIZE),
+                Some(self.max_announcement_size),
             )));
         }
 
         let topic = match &announcement {
             message::Announcement::Block(_) => PubSubTopic::Blocks,
+            message::Announcement::CompactBlock(_) => PubSubTopic::CompactBlocks,
         };
 
         let (response, rx) = oneshot::channel();
@@ -43,10 +43,14 @@ struct Transaction {
 async fn test_connect_peer_id_missing() {
     let config = Arc::new(common::chain::config::create_mainnet());
     let addr: Multiaddr = "/ip6/::1/tcp/8904".parse().unwrap();
-    let (mut service, _) =
-        Libp2pService::start(MakeP2pAddress::make_address(), config, Default::default())
-            .await
-            .unwrap();
+    let (mut service, _) = Libp2pService::start(
+        MakeP2pAddress::make_address(),
+        config,
+        Default::default(),
+        Default::default(),
+    )
+    .await
+    .unwrap();
 
     match service.connect(addr.clone()).await {
         Ok(_) => panic!("connect succeeded without peer id"),
@@ -232,6 +236,7 @@ async fn test_connect_with_timeout() {
             outbound_connection_timeout: 2.into(),
             ..Default::default()
         }),
+        Default::default(),
     )
     .await
     .unwrap();
@@ -0,0 +1,129 @@
+// Copyright (c) 2022 RBB S.r.l
+// opensource@mintlayer.org
+// SPDX-License-Identifier: MIT
+// Licensed under the MIT License;
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://github.com/mintlayer/mintlayer-core/blob/master/LICENSE
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::{sync::Arc, time::Duration};
+
+use libp2p::PeerId;
+use p2p_test_utils::{MakeP2pAddress, MakeTestAddress};
+use tokio::time::timeout;
+
+use crate::{
+    config,
+    net::{
+        libp2p::{service::connectivity::Libp2pConnectivityHandle, Libp2pService},
+        types::ConnectivityEvent,
+        ConnectivityService, NetworkingService,
+    },
+};
+
+async fn start_node(boot_nodes: Vec<String>) -> Libp2pConnectivityHandle<Libp2pService> {
+    let p2p_config = Arc::new(config::P2pConfig {
+        enable_kademlia: true.into(),
+        boot_nodes: boot_nodes.into(),
+        ..Default::default()
+    });
+    let (conn, _) = Libp2pService::start(
+        MakeP2pAddress::make_address(),
+        Arc::new(common::chain::config::create_mainnet()),
+        p2p_config,
+        Default::default(),
+    )
+    .await
+    .unwrap();
+    conn
+}
+
+// wait until the node receives an event accepted by `matches`
+async fn wait_for_event(
+    conn: &mut Libp2pConnectivityHandle<Libp2pService>,
+    matches: impl Fn(&ConnectivityEvent<Libp2pService>) -> bool,
+) {
+    timeout(Duration::from_secs(30), async {
+        loop {
+            if let Ok(event) = conn.poll_next().await {
+                if matches(&event) {
+                    return;
+                }
+            }
+        }
+    })
+    .await
+    .expect("event to be received");
+}
+
+fn discovered(event: &ConnectivityEvent<Libp2pService>, peer_id: &PeerId) -> bool {
+    match event {
+        ConnectivityEvent::Discovered { peers } => {
+            peers.iter().any(|info| info.peer_id == *peer_id)
+        }
+        _ => false,
+    }
+}
+
+// two nodes that know only the same boot node discover each other through the Kademlia DHT
+#[tokio::test]
+async fn discover_through_boot_node() {
+    let mut boot = start_node(vec![]).await;
+    let boot_addr = boot.local_addr().await.unwrap().unwrap().to_string();
+
+    let mut conn1 = start_node(vec![boot_addr.clone()]).await;
+    let peer_id1 = *conn1.peer_id();
+
+    // the boot node learns the listen address of the first node from identify
+    wait_for_event(&mut boot, |event| match event {
+        ConnectivityEvent::InboundAccepted { peer_info, .. } => peer_info.peer_id == peer_id1,
+        _ => false,
+    })
+    .await;
+
+    let mut conn2 = start_node(vec![boot_addr]).await;
+    let boot_id = *boot.peer_id();
+    wait_for_event(&mut conn2, |event| discovered(event, &boot_id)).await;
+    wait_for_event(&mut conn2, |event| discovered(event, &peer_id1)).await;
+}
+
+// the nodes of different networks don't share their routing tables
+#[tokio::test]
+async fn different_networks_dont_mix() {
+    let mut boot = start_node(vec![]).await;
+    let boot_addr = boot.local_addr().await.unwrap().unwrap().to_string();
+
+    let p2p_config = Arc::new(config::P2pConfig {
+        enable_kademlia: true.into(),
+        boot_nodes: vec![boot_addr].into(),
+        ..Default::default()
+    });
+    let (mut conn, _) = Libp2pService::start(
+        MakeP2pAddress::make_address(),
+        Arc::new(common::chain::config::create_regtest()),
+        p2p_config,
+        Default::default(),
+    )
+    .await
+    .unwrap();
+
+    // the boot node is reported when it's added from the configuration
+    let boot_id = *boot.peer_id();
+    wait_for_event(&mut conn, |event| discovered(event, &boot_id)).await;
+
+    // but the boot node never adds the node of the other network to its routing table
+    let peer_id = *conn.peer_id();
+    assert!(timeout(
+        Duration::from_secs(5),
+        wait_for_event(&mut boot, |event| discovered(event, &peer_id))
+    )
+    .await
+    .is_err());
+}
@@ -56,6 +56,8 @@ mod gossipsub;
 #[cfg(test)]
 mod identify;
 #[cfg(test)]
+mod kademlia;
+#[cfg(test)]
 mod mdns;
 #[cfg(test)]
 mod ping;
@@ -97,18 +99,7 @@ pub async fn make_libp2p(
             .build()
             .expect("configuration to be valid");
 
-        let version = config.version();
-        let magic = config.magic_bytes();
-        let protocol = format!(
-            "/mintlayer/{}.{}.{}-{:x}",
-            version.major,
-            version.minor,
-            version.patch,
-            ((magic[0] as u32) << 24)
-                | ((magic[1] as u32) << 16)
-                | ((magic[2] as u32) << 8)
-                | (magic[3] as u32)
-        );
+        let protocol = behaviour::identify_protocol(&config, Default::default());
 
         let mut behaviour = behaviour::Libp2pBehaviour {
             ping: libp2p_ping::Behaviour::new(
@@ -129,10 +120,12 @@ pub async fn make_libp2p(
             )
             .expect("configuration to be valid"),
             connmgr: connection_manager::ConnectionManager::new(),
-            discovery: discovery::DiscoveryManager::new(p2p_config).await,
+            discovery: discovery::DiscoveryManager::new(Arc::clone(&p2p_config)).await,
+            kademlia: discovery::kademlia::make_kademlia(&config, &p2p_config, peer_id),
             events: VecDeque::new(),
             pending_reqs: HashMap::new(),
             waker: None,
+            ping_latencies: HashMap::new(),
         };
 
         for topic in topics.iter() {
@@ -195,18 +188,7 @@ pub async fn make_libp2p_with_ping(
             .build()
             .expect("configuration to be valid");
 
-        let version = config.version();
-        let magic = config.magic_bytes();
-        let protocol = format!(
-            "/mintlayer/{}.{}.{}-{:x}",
-            version.major,
-            version.minor,
-            version.patch,
-            ((magic[0] as u32) << 24)
-                | ((magic[1] as u32) << 16)
-                | ((magic[2] as u32) << 8)
-                | (magic[3] as u32)
-        );
+        let protocol = behaviour::identify_protocol(&config, Default::default());
 
         let mut behaviour = behaviour::Libp2pBehaviour {
             ping,
@@ -223,9 +205,11 @@ pub async fn make_libp2p_with_ping(
             .expect("configuration to be valid"),
             connmgr: connection_manager::ConnectionManager::new(),
             discovery: discovery::DiscoveryManager::new(Arc::clone(&p2p_config)).await,
+            kademlia: discovery::kademlia::make_kademlia(&config, &p2p_config, peer_id),
             events: VecDeque::new(),
             pending_reqs: HashMap::new(),
             waker: None,
+            ping_latencies: HashMap::new(),
         };
 
         for topic in topics.iter() {
@@ -318,18 +302,7 @@ pub fn make_transport_and_keys() -> (Boxed<(PeerId, StreamMuxerBox)>, PeerId, id
 
 #[allow(dead_code)]
 pub fn make_identify(config: common::chain::ChainConfig, id_keys: identity::Keypair) -> Identify {
-    let version = config.version();
-    let magic = config.magic_bytes();
-    let protocol = format!(
-        "/mintlayer/{}.{}.{}-{:x}",
-        version.major,
-        version.minor,
-        version.patch,
-        ((magic[0] as u32) << 24)
-            | ((magic[1] as u32) << 16)
-            | ((magic[2] as u32) << 8)
-            | (magic[3] as u32)
-    );
+    let protocol = behaviour::identify_protocol(&config, Default::default());
 
     Identify::new(IdentifyConfig::new(protocol, id_keys.public()))
 }
@@ -14,6 +14,8 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::time::Duration;
+
 use libp2p::{
     gossipsub::{IdentTopic as Topic, MessageAcceptance, MessageId, TopicHash},
     identify::IdentifyInfo,
@@ -110,6 +112,12 @@ pub enum Command {
         channel: oneshot::Sender<crate::Result<()>>,
     },
 
+    /// Drop a received request without answering it
+    DropRequest {
+        request_id: RequestId,
+        channel: oneshot::Sender<crate::Result<()>>,
+    },
+
     /// Subscribe to gossipsub topics
     Subscribe {
         topics: Vec<Topic>,
@@ -121,11 +129,22 @@ pub enum Command {
         response: oneshot::Sender<Option<Multiaddr>>,
     },
 
+    /// Get the external addresses observed by the remote peers
+    ExternalAddresses {
+        response: oneshot::Sender<Vec<Multiaddr>>,
+    },
+
     /// Ban remote peer
     BanPeer {
         peer_id: PeerId,
         response: oneshot::Sender<crate::Result<()>>,
     },
+
+    /// Get the round-trip time of the last ping sent to the remote peer
+    PingLatency {
+        peer_id: PeerId,
+        response: oneshot::Sender<Option<Duration>>,
+    },
 }
 
 #[derive(Debug)]
@@ -198,6 +217,9 @@ impl From<&net::types::PubSubTopic> for Topic {
         match t {
             net::types::PubSubTopic::Transactions => Topic::new("mintlayer-gossipsub-transactions"),
             net::types::PubSubTopic::Blocks => Topic::new("mintlayer-gossipsub-blocks"),
+            net::types::PubSubTopic::CompactBlocks => {
+                Topic::new("mintlayer-gossipsub-compact-blocks")
+            }
         }
     }
 }
@@ -209,6 +231,7 @@ impl TryFrom<TopicHash> for net::types::PubSubTopic {
         match t.as_str() {
             "mintlayer-gossipsub-transactions" => Ok(net::types::PubSubTopic::Transactions),
             "mintlayer-gossipsub-blocks" => Ok(net::types::PubSubTopic::Blocks),
+            "mintlayer-gossipsub-compact-blocks" => Ok(net::types::PubSubTopic::CompactBlocks),
             _ => Err("Invalid Gossipsub topic"),
         }
     }
@@ -17,14 +17,15 @@
 //!
 //! The backend is modeled after libp2p.
 //!
-//! The peers are required to have unique IDs which they self-assign to themselves
-//! and advertise via the `Hello` message. Until the peer ID has been received, the
-//! peers are distinguished by their socket addresses.
+//! The peer IDs are derived from the static keys that the peers authenticate during the
+//! encrypted handshake and they are also advertised via the `Hello` message. Until the peer
+//! ID has been received, the peers are distinguished by their socket addresses.
 
 use std::{
     collections::{BTreeSet, HashMap},
     io::ErrorKind,
     sync::Arc,
+    time::Duration,
 };
 
 use futures::{future::join_all, FutureExt, TryFutureExt};
@@ -33,8 +34,8 @@ use tokio::{
     time::timeout,
 };
 
-use common::chain::ChainConfig;
-use crypto::random::{make_pseudo_rng, SliceRandom};
+use common::{chain::ChainConfig, time_getter::TimeGetter};
+use crypto::random::{rngs::StdRng, Rng, SeedableRng, SliceRandom};
 use logging::log;
 use serialization::{Decode, Encode};
 
@@ -43,15 +44,14 @@ use crate::{
     message,
     net::{
         mock::{
-            constants::ANNOUNCEMENT_MAX_SIZE,
             peer, request_manager,
-            transport::{MockListener, MockTransport},
+            transport::{MockListener, MockTransport, NoiseKeypair, NoiseStream},
             types::{
                 Command, ConnectivityEvent, Message, MockEvent, MockPeerId, MockPeerInfo,
                 MockRequestId, PeerEvent, SyncingEvent,
             },
         },
-        types::PubSubTopic,
+        types::{PubSubTopic, Service, Services},
         Announcement,
     },
 };
@@ -60,7 +60,9 @@ use crate::{
 struct PeerContext {
     _peer_id: MockPeerId,
     subscriptions: BTreeSet<PubSubTopic>,
+    services: Services,
     tx: mpsc::Sender<MockEvent>,
+    ping_latency: Option<Duration>,
 }
 
 #[derive(Debug)]
@@ -107,11 +109,26 @@ pub struct Backend<T: MockTransport> {
     /// Timeout for outbound operations
     timeout: std::time::Duration,
 
+    /// Maximum size of an announcement accepted from a peer
+    max_announcement_size: usize,
+
+    /// Services advertised to the peers
+    services: Services,
+
     /// Local peer ID
     local_peer_id: MockPeerId,
 
+    /// Static keypair used to authenticate the local node, the local peer ID is derived from it
+    noise_keypair: NoiseKeypair,
+
     /// Request manager for managing inbound/outbound requests and responses
     request_mgr: request_manager::RequestManager,
+
+    /// Random number generator of the backend, the generators of the peers are seeded from it
+    rng: StdRng,
+
+    /// Source of the local time advertised to the peers
+    time_getter: TimeGetter,
 }
 
 impl<T> Backend<T>
@@ -127,8 +144,13 @@ where
         conn_tx: mpsc::Sender<ConnectivityEvent<T>>,
         sync_tx: mpsc::Sender<SyncingEvent>,
         timeout: std::time::Duration,
+        max_announcement_size: usize,
+        services: Services,
+        noise_keypair: NoiseKeypair,
+        rng: StdRng,
+        time_getter: TimeGetter,
     ) -> Self {
-        let local_peer_id = MockPeerId::from_socket_address::<T>(&address);
+        let local_peer_id = noise_keypair.peer_id();
         Self {
             address,
             socket,
@@ -137,11 +159,16 @@ where
             config,
             sync_tx,
             timeout,
+            max_announcement_size,
+            services,
             peers: HashMap::new(),
             pending: HashMap::new(),
             peer_chan: mpsc::channel(64),
             local_peer_id,
+            noise_keypair,
             request_mgr: request_manager::RequestManager::new(),
+            rng,
+            time_getter,
         }
     }
 
@@ -161,7 +188,12 @@ where
             response.send(Ok(())).map_err(|_| P2pError::ChannelClosed)?;
         }
 
-        match timeout(self.timeout, T::connect(address.clone())).await {
+        match timeout(
+            self.timeout,
+            T::connect_from(self.address.clone(), address.clone()),
+        )
+        .await
+        {
             Ok(event) => match event {
                 Ok(socket) => {
                     self.create_peer(
@@ -268,13 +300,16 @@ where
                 })
             })
             .collect();
-        futures.shuffle(&mut make_pseudo_rng());
+        futures.shuffle(&mut self.rng);
 
         join_all(futures).await;
     }
 
     /// Sends the announcement to all peers.
     ///
+    /// Transactions are only relayed if both the local node and the peer advertise the transaction
+    /// relay service.
+    ///
     /// Returns the `InsufficientPeers` error if there are no peers that subscribed to the related
     /// topic.
     async fn announce_data(&mut self, topic: PubSubTopic, message: Vec<u8>) -> crate::Result<()> {
@@ -284,7 +319,10 @@ where
         let mut futures: Vec<_> = self
             .peers
             .iter()
-            .filter(|(_, peer)| peer.subscriptions.contains(&topic))
+            .filter(|(_, peer)| {
+                peer.subscriptions.contains(&topic)
+                    && Self::relays_topic(self.services.intersection(&peer.services), topic)
+            })
             .map(|(id, peer)| {
                 peer.tx
                     .send(MockEvent::SendMessage(Box::new(Message::Announcement {
@@ -295,7 +333,7 @@ where
                     })
             })
             .collect();
-        futures.shuffle(&mut make_pseudo_rng());
+        futures.shuffle(&mut self.rng);
 
         // TODO: We don't really need to return an error here. It is only needed temporarily in
         // order to mimic the libp2p behavior.
@@ -348,6 +386,15 @@ where
             .map_err(P2pError::from)
     }
 
+    /// Returns true if the topic can be relayed to a peer with the given common services
+    fn relays_topic(services: Services, topic: PubSubTopic) -> bool {
+        match topic {
+            PubSubTopic::Transactions => services.has(Service::TxRelay),
+            PubSubTopic::Blocks => true,
+            PubSubTopic::CompactBlocks => services.has(Service::CompactBlocks),
+        }
+    }
+
     fn subscribe_peer(&mut self, peer_id: MockPeerId, topics: BTreeSet<PubSubTopic>) {
         match self.peers.get_mut(&peer_id) {
             Some(peer) => peer.subscriptions = topics.into_iter().collect(),
@@ -361,13 +408,13 @@ where
         announcement: Announcement,
     ) -> crate::Result<()> {
         let size = announcement.encode().len();
-        if size > ANNOUNCEMENT_MAX_SIZE {
+        if size > self.max_announcement_size {
             self.conn_tx
                 .send(ConnectivityEvent::Misbehaved {
                     peer_id,
                     error: P2pError::PublishError(PublishError::MessageTooLarge(
                         Some(size),
-                        Some(ANNOUNCEMENT_MAX_SIZE),
+                        Some(self.max_announcement_size),
                     )),
                 })
                 .await
@@ -430,12 +477,27 @@ where
 
         let tx = self.peer_chan.0.clone();
         let config = Arc::clone(&self.config);
+        let services = self.services;
+        let rng = StdRng::seed_from_u64(self.rng.gen());
+        let time_getter = self.time_getter.clone();
+        let initiator = std::matches!(role, peer::Role::Outbound);
+        let socket = NoiseStream::new(socket, &self.noise_keypair, initiator, config.magic_bytes());
 
         tokio::spawn(async move {
-            if let Err(err) =
-                peer::Peer::<T>::new(local_peer_id, remote_peer_id, role, config, socket, tx, rx)
-                    .start()
-                    .await
+            if let Err(err) = peer::Peer::<T>::new(
+                local_peer_id,
+                remote_peer_id,
+                role,
+                config,
+                services,
+                socket,
+                tx,
+                rx,
+                rng,
+                time_getter,
+            )
+            .start()
+            .await
             {
                 log::error!("peer {remote_peer_id} failed: {err}");
             }
@@ -455,9 +517,30 @@ where
                 network,
                 version,
                 protocols,
+                protocol_version,
+                services,
+                time,
             } => {
                 let (tx, state) = self.pending.remove(&peer_id).expect("peer to exist");
 
+                // The new connection is closed by dropping its channel, the existing connection
+                // to the peer is kept
+                if self.peers.contains_key(&received_id) {
+                    log::debug!(
+                        "peer {received_id} is already connected, closing the new connection"
+                    );
+                    if let ConnectionState::OutboundAccepted { address } = state {
+                        self.conn_tx
+                            .send(ConnectivityEvent::ConnectionError {
+                                address,
+                                error: P2pError::PeerError(PeerError::PeerAlreadyExists),
+                            })
+                            .await
+                            .map_err(P2pError::from)?;
+                    }
+                    return Ok(());
+                }
+
                 match state {
                     ConnectionState::OutboundAccepted { address } => {
                         self.conn_tx
@@ -469,6 +552,9 @@ where
                                     version,
                                     agent: None,
                                     protocols,
+                                    protocol_version,
+                                    services,
+                                    time,
                                 },
                             })
                             .await
@@ -484,6 +570,9 @@ where
                                     version,
                                     agent: None,
                                     protocols,
+                                    protocol_version,
+                                    services,
+                                    time,
                                 },
                             })
                             .await
@@ -496,14 +585,22 @@ where
                     PeerContext {
                         _peer_id: received_id,
                         subscriptions: BTreeSet::new(),
-                        tx,
+                        services,
+                        tx: tx.clone(),
+                        ping_latency: None,
                     },
                 );
                 let _ = self.request_mgr.register_peer(received_id);
+                tx.send(MockEvent::Accepted).await.map_err(P2pError::from)?;
             }
             PeerEvent::MessageReceived { message } => {
                 self.handle_message(peer_id, message).await?;
             }
+            PeerEvent::PingLatency { latency } => {
+                if let Some(peer) = self.peers.get_mut(&peer_id) {
+                    peer.ping_latency = Some(latency);
+                }
+            }
             PeerEvent::ConnectionClosed => {
                 self.peers.remove(&peer_id);
                 self.request_mgr.unregister_peer(&peer_id);
@@ -518,9 +615,17 @@ where
 
     async fn handle_message(&mut self, peer_id: MockPeerId, message: Message) -> crate::Result<()> {
         match message {
-            Message::Handshake(_) => {
+            Message::Handshake(_) | Message::NoiseHandshake(_) => {
                 log::error!("peer {peer_id} sent handshaking message");
             }
+            Message::Ping { .. } | Message::Pong { .. } => {
+                log::error!(
+                    "peer {peer_id} sent a ping message that wasn't handled by the peer task"
+                );
+            }
+            Message::Encrypted(_) => {
+                log::error!("peer {peer_id} sent a message that wasn't decrypted");
+            }
             Message::Request {
                 request_id,
                 request,
@@ -568,6 +673,9 @@ where
                 let res = self.send_response(request_id, message).await;
                 response.send(res).map_err(|_| P2pError::ChannelClosed)?;
             }
+            Command::DropRequest { request_id } => {
+                self.request_mgr.drop_request(&request_id);
+            }
             Command::Subscribe { topics } => {
                 self.subscribe(topics).await;
             }
@@ -579,6 +687,10 @@ where
                 let res = self.announce_data(topic, message).await;
                 response.send(res).map_err(|_| P2pError::ChannelClosed)?;
             }
+            Command::PingLatency { peer_id, response } => {
+                let latency = self.peers.get(&peer_id).and_then(|peer| peer.ping_latency);
+                response.send(latency).map_err(|_| P2pError::ChannelClosed)?;
+            }
         }
         Ok(())
     }
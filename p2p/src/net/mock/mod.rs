@@ -14,17 +14,17 @@
 // limitations under the License.
 
 pub mod backend;
-pub mod constants;
 pub mod peer;
 pub mod request_manager;
 pub mod transport;
 pub mod types;
 
-use std::{marker::PhantomData, sync::Arc};
+use std::{marker::PhantomData, sync::Arc, time::Duration};
 
 use async_trait::async_trait;
 use tokio::sync::{mpsc, oneshot};
 
+use common::time_getter::TimeGetter;
 use logging::log;
 use serialization::Encode;
 
@@ -34,8 +34,7 @@ use crate::{
     message,
     net::{
         mock::{
-            constants::ANNOUNCEMENT_MAX_SIZE,
-            transport::{MockListener, MockTransport},
+            transport::{MockListener, MockTransport, NoiseKeypair},
             types::{MockMessageId, MockPeerId, MockPeerInfo, MockRequestId},
         },
         types::{ConnectivityEvent, PeerInfo, PubSubTopic, SyncingEvent, ValidationResult},
@@ -88,6 +87,9 @@ where
 
     /// RX channel for receiving syncing events
     sync_rx: mpsc::Receiver<types::SyncingEvent>,
+
+    /// Maximum size of an announcement
+    max_announcement_size: usize,
     _marker: PhantomData<fn() -> S>,
 }
 
@@ -104,6 +106,9 @@ where
             version: self.version,
             agent: None,
             protocols: self.protocols.into_iter().collect(),
+            protocol_version: self.protocol_version,
+            services: self.services,
+            time: Some(self.time),
         })
     }
 }
@@ -125,6 +130,7 @@ where
         addr: Self::Address,
         _config: Arc<common::chain::ChainConfig>,
         p2p_config: Arc<config::P2pConfig>,
+        time_getter: TimeGetter,
     ) -> crate::Result<(Self::ConnectivityHandle, Self::SyncingMessagingHandle)> {
         let (cmd_tx, cmd_rx) = mpsc::channel(16);
         let (conn_tx, conn_rx) = mpsc::channel(16);
@@ -132,7 +138,13 @@ where
         let socket = T::bind(addr).await?;
         let local_addr = socket.local_address().expect("to have bind address available");
 
+        let mut rng = p2p_config.make_rng("mock_backend");
+        let noise_keypair = NoiseKeypair::generate_with_rng(&mut rng);
+        let peer_id = noise_keypair.peer_id();
+
         let address = local_addr.clone();
+        let max_announcement_size = *p2p_config.max_announcement_size;
+        let services = p2p_config.local_services();
         tokio::spawn(async move {
             let mut backend = backend::Backend::<T>::new(
                 address,
@@ -142,6 +154,11 @@ where
                 conn_tx,
                 sync_tx,
                 std::time::Duration::from_secs(*p2p_config.outbound_connection_timeout),
+                max_announcement_size,
+                services,
+                noise_keypair,
+                rng,
+                time_getter,
             );
 
             if let Err(err) = backend.run().await {
@@ -149,7 +166,6 @@ where
             }
         });
 
-        let peer_id = MockPeerId::from_socket_address::<T>(&local_addr);
         Ok((
             Self::ConnectivityHandle {
                 local_addr,
@@ -161,6 +177,7 @@ where
             Self::SyncingMessagingHandle {
                 cmd_tx,
                 sync_rx,
+                max_announcement_size,
                 _marker: Default::default(),
             },
         ))
@@ -209,6 +226,11 @@ where
         Ok(Some(self.local_addr.clone()))
     }
 
+    async fn external_addresses(&self) -> crate::Result<Vec<S::Address>> {
+        // The mock handshake doesn't report the observed addresses
+        Ok(Vec::new())
+    }
+
     fn peer_id(&self) -> &S::PeerId {
         &self.peer_id
     }
@@ -227,6 +249,18 @@ where
         rx.await?
     }
 
+    async fn ping_latency(&mut self, peer_id: S::PeerId) -> crate::Result<Option<Duration>> {
+        let (tx, rx) = oneshot::channel();
+        self.cmd_tx
+            .send(types::Command::PingLatency {
+                peer_id,
+                response: tx,
+            })
+            .await?;
+
+        rx.await.map_err(P2pError::from)
+    }
+
     async fn poll_next(&mut self) -> crate::Result<ConnectivityEvent<S>> {
         match self.conn_rx.recv().await.ok_or(P2pError::ChannelClosed)? {
             types::ConnectivityEvent::OutboundAccepted { address, peer_info } => {
@@ -297,20 +331,28 @@ where
         rx.await?
     }
 
+    async fn drop_request(&mut self, request_id: S::SyncingPeerRequestId) -> crate::Result<()> {
+        self.cmd_tx
+            .send(types::Command::DropRequest { request_id })
+            .await
+            .map_err(P2pError::from)
+    }
+
     async fn make_announcement(
         &mut self,
         announcement: message::Announcement,
     ) -> crate::Result<()> {
         let message = announcement.encode();
-        if message.len() > ANNOUNCEMENT_MAX_SIZE {
+        if message.len() > self.max_announcement_size {
             return Err(P2pError::PublishError(PublishError::MessageTooLarge(
                 Some(message.len()),
-                Some(ANNOUNCEMENT_MAX_SIZE),
+                Some(self.max_announcement_size),
             )));
         }
 
         let topic = match &announcement {
             message::Announcement::Block(_) => PubSubTopic::Blocks,
+            message::Announcement::CompactBlock(_) => PubSubTopic::CompactBlocks,
         };
 
         let (response, receiver) = oneshot::channel();
@@ -377,10 +419,13 @@ where
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::net::{
-        self,
-        mock::transport::{ChannelMockTransport, TcpMockTransport},
-        types::{Protocol, ProtocolType},
+    use crate::{
+        constants::PROTOCOL_VERSION,
+        net::{
+            self,
+            mock::transport::{ChannelMockTransport, TcpMockTransport},
+            types::{Protocol, ProtocolType, Service, Services},
+        },
     };
     use common::primitives::semver::SemVer;
     use p2p_test_utils::{MakeChannelAddress, MakeTcpAddress, MakeTestAddress};
@@ -398,6 +443,7 @@ mod tests {
             A::make_address(),
             Arc::clone(&config),
             Arc::clone(&p2p_config),
+            Default::default(),
         )
         .await
         .unwrap();
@@ -406,6 +452,7 @@ mod tests {
             A::make_address(),
             Arc::clone(&config),
             Arc::clone(&p2p_config),
+            Default::default(),
         )
         .await
         .unwrap();
@@ -431,6 +478,9 @@ mod tests {
                     ]
                     .into_iter()
                     .collect(),
+                    protocol_version: PROTOCOL_VERSION,
+                    services: Services::default(),
+                    time: None,
                 }
             );
         } else {
@@ -448,6 +498,127 @@ mod tests {
         connect_to_remote::<MakeChannelAddress, ChannelMockTransport>().await;
     }
 
+    // a second connection to an already connected peer is closed and the existing connection
+    // is kept
+    async fn duplicate_connection<A, T>()
+    where
+        A: MakeTestAddress<Address = T::Address>,
+        T: MockTransport + Debug,
+    {
+        let config = Arc::new(common::chain::config::create_mainnet());
+        let p2p_config: Arc<config::P2pConfig> = Arc::new(Default::default());
+
+        let (mut conn1, _) = MockService::<T>::start(
+            A::make_address(),
+            Arc::clone(&config),
+            Arc::clone(&p2p_config),
+            Default::default(),
+        )
+        .await
+        .unwrap();
+
+        let (mut conn2, _) = MockService::<T>::start(
+            A::make_address(),
+            Arc::clone(&config),
+            Arc::clone(&p2p_config),
+            Default::default(),
+        )
+        .await
+        .unwrap();
+
+        let addr = conn2.local_addr().await.unwrap().unwrap();
+        assert_eq!(conn1.connect(addr.clone()).await, Ok(()));
+        assert!(std::matches!(
+            conn1.poll_next().await,
+            Ok(net::types::ConnectivityEvent::OutboundAccepted { .. })
+        ));
+        assert!(std::matches!(
+            conn2.poll_next().await,
+            Ok(net::types::ConnectivityEvent::InboundAccepted { .. })
+        ));
+
+        assert_eq!(conn1.connect(addr.clone()).await, Ok(()));
+        match conn1.poll_next().await {
+            Ok(net::types::ConnectivityEvent::ConnectionError { address, error }) => {
+                assert_eq!(address, addr);
+                assert_eq!(
+                    error,
+                    P2pError::PeerError(crate::error::PeerError::PeerAlreadyExists)
+                );
+            }
+            event => panic!("invalid event received: {event:?}"),
+        }
+        // neither a new connection nor the closing of the existing one is reported
+        assert!(
+            tokio::time::timeout(Duration::from_millis(500), conn2.poll_next())
+                .await
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn duplicate_connection_tcp() {
+        duplicate_connection::<MakeTcpAddress, TcpMockTransport>().await;
+    }
+
+    #[tokio::test]
+    async fn duplicate_connection_channels() {
+        duplicate_connection::<MakeChannelAddress, ChannelMockTransport>().await;
+    }
+
+    async fn blocks_only_services<A, T>()
+    where
+        A: MakeTestAddress<Address = T::Address>,
+        T: MockTransport + Debug,
+    {
+        let config = Arc::new(common::chain::config::create_mainnet());
+
+        let (mut conn1, _) = MockService::<T>::start(
+            A::make_address(),
+            Arc::clone(&config),
+            Arc::new(Default::default()),
+            Default::default(),
+        )
+        .await
+        .unwrap();
+
+        let (conn2, _) = MockService::<T>::start(
+            A::make_address(),
+            Arc::clone(&config),
+            Arc::new(config::P2pConfig {
+                blocks_only: true.into(),
+                ..Default::default()
+            }),
+            Default::default(),
+        )
+        .await
+        .unwrap();
+
+        let addr = conn2.local_addr().await.unwrap().unwrap();
+        assert_eq!(conn1.connect(addr).await, Ok(()));
+
+        match conn1.poll_next().await.unwrap() {
+            net::types::ConnectivityEvent::OutboundAccepted { peer_info, .. } => {
+                assert!(!peer_info.services.has(Service::TxRelay));
+                assert_eq!(
+                    peer_info.services,
+                    Services::default().without(Service::TxRelay)
+                );
+            }
+            _ => panic!("invalid event received"),
+        }
+    }
+
+    #[tokio::test]
+    async fn blocks_only_services_tcp() {
+        blocks_only_services::<MakeTcpAddress, TcpMockTransport>().await;
+    }
+
+    #[tokio::test]
+    async fn blocks_only_services_channels() {
+        blocks_only_services::<MakeChannelAddress, ChannelMockTransport>().await;
+    }
+
     async fn accept_incoming<A, T>()
     where
         A: MakeTestAddress<Address = T::Address>,
@@ -460,6 +631,7 @@ mod tests {
             A::make_address(),
             Arc::clone(&config),
             Arc::clone(&p2p_config),
+            Default::default(),
         )
         .await
         .unwrap();
@@ -468,6 +640,7 @@ mod tests {
             A::make_address(),
             Arc::clone(&config),
             Arc::clone(&p2p_config),
+            Default::default(),
         )
         .await
         .unwrap();
@@ -522,11 +695,14 @@ mod tests {
             A::make_address(),
             Arc::clone(&config),
             Arc::clone(&p2p_config),
+            Default::default(),
         )
         .await
         .unwrap();
         let (mut conn2, _) =
-            MockService::<T>::start(A::make_address(), config, p2p_config).await.unwrap();
+            MockService::<T>::start(A::make_address(), config, p2p_config, Default::default())
+                .await
+                .unwrap();
 
         let (_res1, res2) = tokio::join!(
             conn1.connect(conn2.local_addr().await.unwrap().unwrap()),
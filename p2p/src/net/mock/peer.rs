@@ -13,22 +13,25 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::sync::Arc;
+use std::{sync::Arc, time::Instant};
 
 use futures::FutureExt;
 use tokio::sync::mpsc;
 
-use common::{chain::ChainConfig, primitives::semver::SemVer};
+use common::{chain::ChainConfig, primitives::semver::SemVer, time_getter::TimeGetter};
+use crypto::random::{rngs::StdRng, Rng};
 use logging::log;
+use utils::ensure;
 
 use crate::{
-    error::{P2pError, ProtocolError},
+    constants,
+    error::{DialError, P2pError, ProtocolError},
     net::{
         mock::{
-            transport::{MockStream, MockTransport},
+            transport::{MockStream, MockTransport, NoiseStream},
             types::{self, MockEvent, MockPeerId, PeerEvent},
         },
-        types::{Protocol, ProtocolType},
+        types::{Protocol, ProtocolType, Services},
     },
 };
 
@@ -47,17 +50,29 @@ pub struct Peer<T: MockTransport> {
     /// Chain config
     config: Arc<ChainConfig>,
 
+    /// Services advertised to the remote peer
+    services: Services,
+
     /// Is the connection inbound or outbound
     role: Role,
 
     /// Peer socket
-    socket: T::Stream,
+    socket: NoiseStream<T::Stream>,
 
     /// TX channel for communicating with backend
     tx: mpsc::Sender<(MockPeerId, PeerEvent)>,
 
     /// RX channel for receiving commands from backend
     rx: mpsc::Receiver<MockEvent>,
+
+    /// Nonce of the ping that the remote hasn't responded to yet and when it was sent
+    pending_ping: Option<(u64, Instant)>,
+
+    /// Random number generator of the ping nonces
+    rng: StdRng,
+
+    /// Source of the local time advertised to the remote peer
+    time_getter: TimeGetter,
 }
 
 impl<T> Peer<T>
@@ -69,36 +84,67 @@ where
         remote_peer_id: MockPeerId,
         role: Role,
         config: Arc<ChainConfig>,
-        socket: T::Stream,
+        services: Services,
+        socket: NoiseStream<T::Stream>,
         tx: mpsc::Sender<(MockPeerId, PeerEvent)>,
         rx: mpsc::Receiver<MockEvent>,
+        rng: StdRng,
+        time_getter: TimeGetter,
     ) -> Self {
         Self {
             local_peer_id,
             remote_peer_id,
             role,
             config,
+            services,
             socket,
             tx,
             rx,
+            pending_ping: None,
+            rng,
+            time_getter,
         }
     }
 
+    /// Authenticate the remote peer and exchange the peer information
+    ///
+    /// The peer ID advertised by the remote peer must match the ID derived from the static
+    /// key used in the encrypted handshake.
+    ///
+    /// Both peers advertise the highest protocol version they support and the connection
+    /// uses the highest version supported by both of them.
     async fn handshake(&mut self) -> crate::Result<()> {
+        let authenticated_peer_id = self.socket.handshake().await?;
+
         match self.role {
             Role::Inbound => {
-                let (peer_id, network, version, protocols) =
+                let (peer_id, network, version, protocols, protocol_version, services, time) =
                     if let Ok(Some(types::Message::Handshake(types::HandshakeMessage::Hello {
                         peer_id,
                         version,
                         network,
                         protocols,
+                        protocol_version,
+                        services,
+                        time,
                     }))) = self.socket.recv().await
                     {
-                        (peer_id, network, version, protocols)
+                        (
+                            peer_id,
+                            network,
+                            version,
+                            protocols,
+                            protocol_version,
+                            services,
+                            time,
+                        )
                     } else {
                         return Err(P2pError::ProtocolError(ProtocolError::InvalidMessage));
                     };
+                ensure!(
+                    peer_id == authenticated_peer_id,
+                    P2pError::DialError(DialError::WrongPeerId)
+                );
 
                 self.socket
                     .send(types::Message::Handshake(
@@ -114,6 +160,9 @@ where
                             ]
                             .into_iter()
                             .collect(),
+                            protocol_version: constants::PROTOCOL_VERSION,
+                            services: self.services,
+                            time: self.time_getter.get_time().as_secs(),
                         },
                     ))
                     .await?;
@@ -126,6 +175,12 @@ where
                             network,
                             version,
                             protocols,
+                            protocol_version: std::cmp::min(
+                                protocol_version,
+                                constants::PROTOCOL_VERSION,
+                            ),
+                            services,
+                            time,
                         },
                     ))
                     .await
@@ -147,22 +202,41 @@ where
                         ]
                         .into_iter()
                         .collect(),
+                        protocol_version: constants::PROTOCOL_VERSION,
+                        services: self.services,
+                        time: self.time_getter.get_time().as_secs(),
                     }))
                     .await?;
 
-                let (peer_id, network, version, protocols) = if let Ok(Some(
-                    types::Message::Handshake(types::HandshakeMessage::HelloAck {
-                        peer_id,
-                        version,
-                        network,
-                        protocols,
-                    }),
-                )) = self.socket.recv().await
-                {
-                    (peer_id, network, version, protocols)
-                } else {
-                    return Err(P2pError::ProtocolError(ProtocolError::InvalidMessage));
-                };
+                let (peer_id, network, version, protocols, protocol_version, services, time) =
+                    if let Ok(Some(types::Message::Handshake(
+                        types::HandshakeMessage::HelloAck {
+                            peer_id,
+                            version,
+                            network,
+                            protocols,
+                            protocol_version,
+                            services,
+                            time,
+                        },
+                    ))) = self.socket.recv().await
+                    {
+                        (
+                            peer_id,
+                            network,
+                            version,
+                            protocols,
+                            protocol_version,
+                            services,
+                            time,
+                        )
+                    } else {
+                        return Err(P2pError::ProtocolError(ProtocolError::InvalidMessage));
+                    };
+                ensure!(
+                    peer_id == authenticated_peer_id,
+                    P2pError::DialError(DialError::WrongPeerId)
+                );
 
                 self.tx
                     .send((
@@ -172,6 +246,12 @@ where
                             network,
                             version,
                             protocols,
+                            protocol_version: std::cmp::min(
+                                protocol_version,
+                                constants::PROTOCOL_VERSION,
+                            ),
+                            services,
+                            time,
                         },
                     ))
                     .await
@@ -190,6 +270,40 @@ where
             .map_err(P2pError::from)
     }
 
+    async fn send_ping(&mut self) -> crate::Result<()> {
+        if self.pending_ping.is_some() {
+            log::debug!(
+                "peer {} didn't respond to the previous ping",
+                self.remote_peer_id
+            );
+        }
+
+        let nonce = self.rng.gen::<u64>();
+        self.pending_ping = Some((nonce, Instant::now()));
+        self.socket.send(types::Message::Ping { nonce }).await
+    }
+
+    async fn pong_received(&mut self, nonce: u64) -> crate::Result<()> {
+        match self.pending_ping {
+            Some((expected, sent_at)) if expected == nonce => {
+                self.pending_ping = None;
+                self.tx
+                    .send((
+                        self.remote_peer_id,
+                        types::PeerEvent::PingLatency {
+                            latency: sent_at.elapsed(),
+                        },
+                    ))
+                    .await
+                    .map_err(P2pError::from)
+            }
+            _ => {
+                log::debug!("peer {} sent an unexpected pong", self.remote_peer_id);
+                Ok(())
+            }
+        }
+    }
+
     pub async fn start(&mut self) -> crate::Result<()> {
         // handshake with remote peer and send peer's info to backend
         if let Err(err) = self.handshake().await {
@@ -197,9 +311,26 @@ where
             return self.destroy_peer().await;
         }
 
+        // No events are sent for the peer until the backend has accepted the connection. It
+        // closes the channel instead if the peer is already connected, so that the events of
+        // the rejected connection can't be mistaken for the events of the existing one.
+        match self.rx.recv().await {
+            Some(MockEvent::Accepted) => {}
+            _ => {
+                log::debug!("connection to peer {} rejected", self.remote_peer_id);
+                return Ok(());
+            }
+        }
+
+        let mut ping_interval = tokio::time::interval_at(
+            tokio::time::Instant::now() + constants::PING_INTERVAL,
+            constants::PING_INTERVAL,
+        );
+
         loop {
             tokio::select! {
                 event = self.rx.recv().fuse() => match event.ok_or(P2pError::ChannelClosed)? {
+                    MockEvent::Accepted => {}
                     MockEvent::Disconnect => return self.destroy_peer().await,
                     MockEvent::SendMessage(message) => self.socket.send(*message).await?,
                 },
@@ -209,6 +340,10 @@ where
                         return self.destroy_peer().await;
                     }
                     Ok(None) => {},
+                    Ok(Some(types::Message::Ping { nonce })) => {
+                        self.socket.send(types::Message::Pong { nonce }).await?;
+                    }
+                    Ok(Some(types::Message::Pong { nonce })) => self.pong_received(nonce).await?,
                     Ok(Some(message)) => {
                         self.tx
                             .send((
@@ -220,7 +355,8 @@ where
                             .await
                             .map_err(P2pError::from)?;
                     }
-                }
+                },
+                _ = ping_interval.tick() => self.send_ping().await?,
             }
         }
     }
@@ -232,12 +368,13 @@ mod tests {
     use crate::{
         message,
         net::mock::{
-            transport::{ChannelMockTransport, MockListener, TcpMockTransport},
+            transport::{ChannelMockTransport, MockListener, NoiseKeypair, TcpMockTransport},
             types,
         },
     };
     use chainstate::Locator;
     use common::primitives::semver::SemVer;
+    use crypto::random::SeedableRng;
     use futures::FutureExt;
     use p2p_test_utils::{MakeChannelAddress, MakeTcpAddress, MakeTestAddress};
 
@@ -246,12 +383,15 @@ mod tests {
         A: MakeTestAddress<Address = T::Address>,
         T: MockTransport,
     {
-        let (socket1, mut socket2) = get_two_connected_sockets::<A, T>().await;
         let config = Arc::new(common::chain::config::create_mainnet());
+        let keypair1 = NoiseKeypair::generate();
+        let keypair2 = NoiseKeypair::generate();
+        let (socket1, mut socket2) =
+            get_two_connected_sockets::<A, T>(&config, &keypair1, &keypair2, Role::Inbound).await;
         let (tx1, mut rx1) = mpsc::channel(16);
         let (_tx2, rx2) = mpsc::channel(16);
-        let peer_id1 = MockPeerId::random();
-        let peer_id2 = MockPeerId::random();
+        let peer_id1 = keypair1.peer_id();
+        let peer_id2 = keypair2.peer_id();
         let peer_id3 = MockPeerId::random();
 
         let mut peer = Peer::<T>::new(
@@ -259,9 +399,12 @@ mod tests {
             peer_id3,
             Role::Inbound,
             Arc::clone(&config),
+            Services::default(),
             socket1,
             tx1,
             rx2,
+            StdRng::from_entropy(),
+            Default::default(),
         );
 
         let handle = tokio::spawn(async move {
@@ -269,6 +412,7 @@ mod tests {
             peer
         });
 
+        socket2.handshake().await.unwrap();
         assert!(socket2.recv().now_or_never().is_none());
         assert!(socket2
             .send(types::Message::Handshake(types::HandshakeMessage::Hello {
@@ -282,6 +426,9 @@ mod tests {
                 ]
                 .into_iter()
                 .collect(),
+                protocol_version: constants::PROTOCOL_VERSION,
+                services: Services::default(),
+                time: 1_600_000_000,
             }))
             .await
             .is_ok());
@@ -302,6 +449,9 @@ mod tests {
                     ]
                     .into_iter()
                     .collect(),
+                    protocol_version: constants::PROTOCOL_VERSION,
+                    services: Services::default(),
+                    time: 1_600_000_000,
                 }
             ))
         );
@@ -317,17 +467,111 @@ mod tests {
         handshake_inbound::<MakeChannelAddress, ChannelMockTransport>().await;
     }
 
+    // newer peer with services unknown to the local node
+    async fn handshake_negotiate_version<A, T>()
+    where
+        A: MakeTestAddress<Address = T::Address>,
+        T: MockTransport,
+    {
+        let config = Arc::new(common::chain::config::create_mainnet());
+        let keypair1 = NoiseKeypair::generate();
+        let keypair2 = NoiseKeypair::generate();
+        let (socket1, mut socket2) =
+            get_two_connected_sockets::<A, T>(&config, &keypair1, &keypair2, Role::Inbound).await;
+        let (tx1, mut rx1) = mpsc::channel(16);
+        let (_tx2, rx2) = mpsc::channel(16);
+        let peer_id2 = keypair2.peer_id();
+        let peer_id3 = MockPeerId::random();
+
+        let mut peer = Peer::<T>::new(
+            keypair1.peer_id(),
+            peer_id3,
+            Role::Inbound,
+            Arc::clone(&config),
+            Services::default(),
+            socket1,
+            tx1,
+            rx2,
+            StdRng::from_entropy(),
+            TimeGetter::new(Arc::new(|| std::time::Duration::from_secs(1_700_000_000))),
+        );
+
+        let handle = tokio::spawn(async move {
+            peer.handshake().await.unwrap();
+            peer
+        });
+
+        let services = Services::from_bits(1 << 40).with(crate::net::types::Service::Pruned);
+        socket2.handshake().await.unwrap();
+        socket2
+            .send(types::Message::Handshake(types::HandshakeMessage::Hello {
+                peer_id: peer_id2,
+                version: *config.version(),
+                network: *config.magic_bytes(),
+                protocols: vec![],
+                protocol_version: constants::PROTOCOL_VERSION + 1,
+                services,
+                time: 1_600_000_000,
+            }))
+            .await
+            .unwrap();
+
+        match socket2.recv().await {
+            Ok(Some(types::Message::Handshake(types::HandshakeMessage::HelloAck {
+                protocol_version,
+                services,
+                time,
+                ..
+            }))) => {
+                assert_eq!(protocol_version, constants::PROTOCOL_VERSION);
+                assert_eq!(services, Services::default());
+                assert_eq!(time, 1_700_000_000);
+            }
+            _ => panic!("invalid message"),
+        }
+
+        let _peer = handle.await;
+        assert_eq!(
+            rx1.try_recv(),
+            Ok((
+                peer_id3,
+                types::PeerEvent::PeerInfoReceived {
+                    peer_id: peer_id2,
+                    network: *config.magic_bytes(),
+                    version: *config.version(),
+                    protocols: vec![],
+                    protocol_version: constants::PROTOCOL_VERSION,
+                    services,
+                    time: 1_600_000_000,
+                }
+            ))
+        );
+    }
+
+    #[tokio::test]
+    async fn handshake_negotiate_version_tcp() {
+        handshake_negotiate_version::<MakeTcpAddress, TcpMockTransport>().await;
+    }
+
+    #[tokio::test]
+    async fn handshake_negotiate_version_channels() {
+        handshake_negotiate_version::<MakeChannelAddress, ChannelMockTransport>().await;
+    }
+
     async fn handshake_outbound<A, T>()
     where
         A: MakeTestAddress<Address = T::Address>,
         T: MockTransport,
     {
-        let (socket1, mut socket2) = get_two_connected_sockets::<A, T>().await;
         let config = Arc::new(common::chain::config::create_mainnet());
+        let keypair1 = NoiseKeypair::generate();
+        let keypair2 = NoiseKeypair::generate();
+        let (socket1, mut socket2) =
+            get_two_connected_sockets::<A, T>(&config, &keypair1, &keypair2, Role::Outbound).await;
         let (tx1, mut rx1) = mpsc::channel(16);
         let (_tx2, rx2) = mpsc::channel(16);
-        let peer_id1 = MockPeerId::random();
-        let peer_id2 = MockPeerId::random();
+        let peer_id1 = keypair1.peer_id();
+        let peer_id2 = keypair2.peer_id();
         let peer_id3 = MockPeerId::random();
 
         let mut peer = Peer::<T>::new(
@@ -335,9 +579,12 @@ mod tests {
             peer_id3,
             Role::Outbound,
             Arc::clone(&config),
+            Services::default(),
             socket1,
             tx1,
             rx2,
+            StdRng::from_entropy(),
+            Default::default(),
         );
 
         let handle = tokio::spawn(async move {
@@ -345,6 +592,7 @@ mod tests {
             peer
         });
 
+        socket2.handshake().await.unwrap();
         if let Some(_message) = socket2.recv().await.unwrap() {
             assert!(socket2
                 .send(types::Message::Handshake(
@@ -359,6 +607,9 @@ mod tests {
                         ]
                         .into_iter()
                         .collect(),
+                        protocol_version: constants::PROTOCOL_VERSION,
+                        services: Services::default(),
+                        time: 1_600_000_000,
                     }
                 ))
                 .await
@@ -381,6 +632,9 @@ mod tests {
                     ]
                     .into_iter()
                     .collect(),
+                    protocol_version: constants::PROTOCOL_VERSION,
+                    services: Services::default(),
+                    time: 1_600_000_000,
                 }
             ))
         );
@@ -401,12 +655,15 @@ mod tests {
         A: MakeTestAddress<Address = T::Address>,
         T: MockTransport,
     {
-        let (socket1, mut socket2) = get_two_connected_sockets::<A, T>().await;
         let config = Arc::new(common::chain::config::create_mainnet());
+        let keypair1 = NoiseKeypair::generate();
+        let keypair2 = NoiseKeypair::generate();
+        let (socket1, mut socket2) =
+            get_two_connected_sockets::<A, T>(&config, &keypair1, &keypair2, Role::Inbound).await;
         let (tx1, _rx1) = mpsc::channel(16);
         let (_tx2, rx2) = mpsc::channel(16);
-        let peer_id1 = MockPeerId::random();
-        let peer_id2 = MockPeerId::random();
+        let peer_id1 = keypair1.peer_id();
+        let peer_id2 = keypair2.peer_id();
         let peer_id3 = MockPeerId::random();
 
         let mut peer = Peer::<T>::new(
@@ -414,13 +671,17 @@ mod tests {
             peer_id3,
             Role::Inbound,
             Arc::clone(&config),
+            Services::default(),
             socket1,
             tx1,
             rx2,
+            StdRng::from_entropy(),
+            Default::default(),
         );
 
         let handle = tokio::spawn(async move { peer.handshake().await });
 
+        socket2.handshake().await.unwrap();
         assert!(socket2.recv().now_or_never().is_none());
         assert!(socket2
             .send(types::Message::Handshake(types::HandshakeMessage::Hello {
@@ -434,6 +695,9 @@ mod tests {
                 ]
                 .into_iter()
                 .collect(),
+                protocol_version: constants::PROTOCOL_VERSION,
+                services: Services::default(),
+                time: 1_600_000_000,
             }))
             .await
             .is_ok());
@@ -456,25 +720,32 @@ mod tests {
         A: MakeTestAddress<Address = T::Address>,
         T: MockTransport,
     {
-        let (socket1, mut socket2) = get_two_connected_sockets::<A, T>().await;
         let config = Arc::new(common::chain::config::create_mainnet());
+        let keypair1 = NoiseKeypair::generate();
+        let keypair2 = NoiseKeypair::generate();
+        let (socket1, mut socket2) =
+            get_two_connected_sockets::<A, T>(&config, &keypair1, &keypair2, Role::Inbound).await;
         let (tx1, _rx1) = mpsc::channel(16);
         let (_tx2, rx2) = mpsc::channel(16);
-        let peer_id1 = MockPeerId::random();
-        let peer_id2 = MockPeerId::random();
+        let peer_id1 = keypair1.peer_id();
+        let peer_id2 = keypair2.peer_id();
 
         let mut peer = Peer::<T>::new(
             peer_id1,
             peer_id2,
             Role::Inbound,
             Arc::clone(&config),
+            Services::default(),
             socket1,
             tx1,
             rx2,
+            StdRng::from_entropy(),
+            Default::default(),
         );
 
         let handle = tokio::spawn(async move { peer.handshake().await });
 
+        socket2.handshake().await.unwrap();
         assert!(socket2.recv().now_or_never().is_none());
         socket2
             .send(types::Message::Request {
@@ -502,7 +773,78 @@ mod tests {
         invalid_handshake_message::<MakeChannelAddress, ChannelMockTransport>().await;
     }
 
-    pub async fn get_two_connected_sockets<A, T>() -> (T::Stream, T::Stream)
+    // the peer ID in the `Hello` message doesn't match the key used in the encrypted handshake
+    async fn handshake_wrong_peer_id<A, T>()
+    where
+        A: MakeTestAddress<Address = T::Address>,
+        T: MockTransport,
+    {
+        let config = Arc::new(common::chain::config::create_mainnet());
+        let keypair1 = NoiseKeypair::generate();
+        let keypair2 = NoiseKeypair::generate();
+        let (socket1, mut socket2) =
+            get_two_connected_sockets::<A, T>(&config, &keypair1, &keypair2, Role::Inbound).await;
+        let (tx1, _rx1) = mpsc::channel(16);
+        let (_tx2, rx2) = mpsc::channel(16);
+
+        let mut peer = Peer::<T>::new(
+            keypair1.peer_id(),
+            MockPeerId::random(),
+            Role::Inbound,
+            Arc::clone(&config),
+            Services::default(),
+            socket1,
+            tx1,
+            rx2,
+            StdRng::from_entropy(),
+            Default::default(),
+        );
+
+        let handle = tokio::spawn(async move { peer.handshake().await });
+
+        socket2.handshake().await.unwrap();
+        socket2
+            .send(types::Message::Handshake(types::HandshakeMessage::Hello {
+                peer_id: MockPeerId::random(),
+                version: *config.version(),
+                network: *config.magic_bytes(),
+                protocols: [
+                    Protocol::new(ProtocolType::PubSub, SemVer::new(1, 1, 0)),
+                    Protocol::new(ProtocolType::Ping, SemVer::new(1, 0, 0)),
+                    Protocol::new(ProtocolType::Sync, SemVer::new(0, 1, 0)),
+                ]
+                .into_iter()
+                .collect(),
+                protocol_version: constants::PROTOCOL_VERSION,
+                services: Services::default(),
+                time: 1_600_000_000,
+            }))
+            .await
+            .unwrap();
+
+        assert_eq!(
+            handle.await.unwrap(),
+            Err(P2pError::DialError(DialError::WrongPeerId)),
+        );
+    }
+
+    #[tokio::test]
+    async fn handshake_wrong_peer_id_tcp() {
+        handshake_wrong_peer_id::<MakeTcpAddress, TcpMockTransport>().await;
+    }
+
+    #[tokio::test]
+    async fn handshake_wrong_peer_id_channels() {
+        handshake_wrong_peer_id::<MakeChannelAddress, ChannelMockTransport>().await;
+    }
+
+    /// Return two connected sockets, the first one for a peer with the given role
+    pub async fn get_two_connected_sockets<A, T>(
+        config: &ChainConfig,
+        keypair1: &NoiseKeypair,
+        keypair2: &NoiseKeypair,
+        role: Role,
+    ) -> (NoiseStream<T::Stream>, NoiseStream<T::Stream>)
     where
         A: MakeTestAddress<Address = T::Address>,
         T: MockTransport,
@@ -512,6 +854,17 @@ mod tests {
         let peer_fut = T::connect(server.local_address().unwrap());
 
         let (res1, res2) = tokio::join!(server.accept(), peer_fut);
-        (res1.unwrap().0, res2.unwrap())
+        let (inbound, outbound) = (res1.unwrap().0, res2.unwrap());
+        let magic_bytes = config.magic_bytes();
+        match role {
+            Role::Inbound => (
+                NoiseStream::new(inbound, keypair1, false, magic_bytes),
+                NoiseStream::new(outbound, keypair2, true, magic_bytes),
+            ),
+            Role::Outbound => (
+                NoiseStream::new(outbound, keypair1, true, magic_bytes),
+                NoiseStream::new(inbound, keypair2, false, magic_bytes),
+            ),
+        }
     }
 }
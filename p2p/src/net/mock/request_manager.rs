@@ -102,11 +102,14 @@ impl RequestManager {
         request_id: &types::MockRequestId,
         response: message::Response,
     ) -> Option<(types::MockPeerId, Box<types::Message>)> {
-        if let Some((peer_id, request_id)) = self.ephemeral.remove(request_id) {
+        if let Some((peer_id, remote_request_id)) = self.ephemeral.remove(request_id) {
+            if let Some(ephemerals) = self.ephemerals.get_mut(&peer_id) {
+                ephemerals.remove(request_id);
+            }
             return Some((
                 peer_id,
                 Box::new(types::Message::Response {
-                    request_id,
+                    request_id: remote_request_id,
                     response,
                 }),
             ));
@@ -115,6 +118,15 @@ impl RequestManager {
         None
     }
 
+    /// Drop inbound request without creating a response for it
+    pub fn drop_request(&mut self, request_id: &types::MockRequestId) {
+        if let Some((peer_id, _)) = self.ephemeral.remove(request_id) {
+            if let Some(ephemerals) = self.ephemerals.get_mut(&peer_id) {
+                ephemerals.remove(request_id);
+            }
+        }
+    }
+
     /// Register inbound request
     ///
     /// The request ID is stored into a temporary storage holding all pending
@@ -13,7 +13,12 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::{collections::BTreeMap, io, sync::Mutex};
+use std::{
+    collections::BTreeMap,
+    io,
+    net::{IpAddr, Ipv6Addr},
+    sync::Mutex,
+};
 
 use async_trait::async_trait;
 use once_cell::sync::Lazy;
@@ -29,7 +34,7 @@ use crate::{
             transport::{MockListener, MockStream, MockTransport},
             types::Message,
         },
-        AsBannableAddress, IsBannableAddress,
+        AsBannableAddress, AsIpAddress, AsNetworkGroup, IsBannableAddress, IsIpv6Address,
     },
     P2pError, Result,
 };
@@ -166,12 +171,30 @@ impl AsBannableAddress for Address {
     }
 }
 
+impl AsIpAddress for Address {
+    fn as_ip_address(&self) -> IpAddr {
+        IpAddr::V6(Ipv6Addr::from(u128::from(*self)))
+    }
+}
+
 impl IsBannableAddress for Address {
     fn is_bannable(&self) -> bool {
         true
     }
 }
 
+impl IsIpv6Address for Address {
+    fn is_ipv6(&self) -> bool {
+        false
+    }
+}
+
+impl AsNetworkGroup for Address {
+    fn network_group(&self) -> Vec<u8> {
+        self.to_be_bytes().to_vec()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -14,11 +14,13 @@
 // limitations under the License.
 
 mod channel;
+mod noise;
 mod tcp;
 mod traits;
 
 pub use self::{
     channel::{ChannelMockListener, ChannelMockStream, ChannelMockTransport},
+    noise::{NoiseKeypair, NoiseStream},
     tcp::TcpMockTransport,
     traits::{MockListener, MockStream, MockTransport},
 };
@@ -0,0 +1,440 @@
+// Copyright (c) 2022 RBB S.r.l
+// opensource@mintlayer.org
+// SPDX-License-Identifier: MIT
+// Licensed under the MIT License;
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://github.com/mintlayer/mintlayer-core/blob/master/LICENSE
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Authentication and encryption layer for the mock transports
+//!
+//! Connections are upgraded with the `Noise_XX_25519_ChaChaPoly_BLAKE2s` handshake during
+//! which both peers learn and authenticate the static public key of the other side. The peer ID
+//! is derived from that key so a peer can't impersonate another one. The magic bytes of the
+//! chain are used as the handshake prologue which makes the handshake fail if the peers
+//! belong to different networks.
+//!
+//! After the handshake, each message is encoded, split into Noise transport messages and
+//! encrypted before it's sent over the underlying stream.
+
+use async_trait::async_trait;
+use snow::{
+    params::{DHChoice, NoiseParams},
+    resolvers::{CryptoResolver, DefaultResolver},
+    Builder, HandshakeState, TransportState,
+};
+
+use crypto::random::Rng;
+use serialization::{Decode, Encode};
+
+use crate::{
+    error::{DialError, P2pError, ProtocolError},
+    net::mock::{
+        transport::MockStream,
+        types::{Message, MockPeerId},
+    },
+    Result,
+};
+
+/// Noise protocol used for the handshake
+const NOISE_PARAMS: &str = "Noise_XX_25519_ChaChaPoly_BLAKE2s";
+
+/// Maximum size of a single Noise message
+const NOISE_MAX_MESSAGE_SIZE: usize = 65535;
+
+/// Size of the authentication tag of an encrypted Noise message
+const NOISE_TAG_SIZE: usize = 16;
+
+fn noise_params() -> NoiseParams {
+    NOISE_PARAMS.parse().expect("Noise parameters to be valid")
+}
+
+fn handshake_error(err: impl ToString) -> P2pError {
+    P2pError::DialError(DialError::HandshakeFailed(err.to_string()))
+}
+
+/// Static keypair that identifies the local node
+pub struct NoiseKeypair {
+    private: Vec<u8>,
+    public: Vec<u8>,
+}
+
+impl NoiseKeypair {
+    /// Generate a new random keypair
+    pub fn generate() -> Self {
+        let keypair = Builder::new(noise_params())
+            .generate_keypair()
+            .expect("Noise keypair generation to succeed");
+        Self {
+            private: keypair.private,
+            public: keypair.public,
+        }
+    }
+
+    /// Generate a keypair from the given random number generator
+    ///
+    /// The same keypair is generated from the generators with the same seed.
+    pub fn generate_with_rng(rng: &mut impl Rng) -> Self {
+        let private: [u8; 32] = rng.gen();
+        let mut dh = DefaultResolver
+            .resolve_dh(&DHChoice::Curve25519)
+            .expect("Curve25519 to be supported");
+        dh.set(&private);
+        Self {
+            private: private.to_vec(),
+            public: dh.pubkey().to_vec(),
+        }
+    }
+
+    pub fn public_key(&self) -> &[u8] {
+        &self.public
+    }
+
+    /// Peer ID of the node owning the keypair
+    pub fn peer_id(&self) -> MockPeerId {
+        MockPeerId::from_public_key(&self.public)
+    }
+}
+
+enum NoiseState {
+    Handshake(Box<HandshakeState>),
+    Transport(Box<TransportState>),
+    Failed,
+}
+
+/// A stream that authenticates the remote peer and encrypts all messages sent over it
+///
+/// [`NoiseStream::handshake()`] must be called before any messages can be sent or received.
+pub struct NoiseStream<S: MockStream> {
+    stream: S,
+    state: NoiseState,
+}
+
+impl<S: MockStream> NoiseStream<S> {
+    /// Create a new stream
+    ///
+    /// The side that opened the connection must be the `initiator` of the handshake.
+    pub fn new(stream: S, keypair: &NoiseKeypair, initiator: bool, magic_bytes: &[u8]) -> Self {
+        let builder = Builder::new(noise_params())
+            .local_private_key(&keypair.private)
+            .prologue(magic_bytes);
+        let state = if initiator {
+            builder.build_initiator()
+        } else {
+            builder.build_responder()
+        }
+        .expect("Noise handshake state to be valid");
+
+        Self {
+            stream,
+            state: NoiseState::Handshake(Box::new(state)),
+        }
+    }
+
+    /// Perform the handshake and return the peer ID of the remote peer
+    pub async fn handshake(&mut self) -> Result<MockPeerId> {
+        let mut state = match std::mem::replace(&mut self.state, NoiseState::Failed) {
+            NoiseState::Handshake(state) => *state,
+            _ => return Err(handshake_error("handshake already performed")),
+        };
+
+        // -> e
+        // <- e, ee, s, es
+        // -> s, se
+        if state.is_initiator() {
+            self.write_handshake_message(&mut state).await?;
+            self.read_handshake_message(&mut state).await?;
+            self.write_handshake_message(&mut state).await?;
+        } else {
+            self.read_handshake_message(&mut state).await?;
+            self.write_handshake_message(&mut state).await?;
+            self.read_handshake_message(&mut state).await?;
+        }
+
+        let peer_id = MockPeerId::from_public_key(
+            state
+                .get_remote_static()
+                .ok_or_else(|| handshake_error("no remote static key"))?,
+        );
+        self.state = NoiseState::Transport(Box::new(
+            state.into_transport_mode().map_err(handshake_error)?,
+        ));
+        Ok(peer_id)
+    }
+
+    async fn write_handshake_message(&mut self, state: &mut HandshakeState) -> Result<()> {
+        let mut buf = vec![0u8; NOISE_MAX_MESSAGE_SIZE];
+        let len = state.write_message(&[], &mut buf).map_err(handshake_error)?;
+        buf.truncate(len);
+        self.stream.send(Message::NoiseHandshake(buf)).await
+    }
+
+    async fn read_handshake_message(&mut self, state: &mut HandshakeState) -> Result<()> {
+        let message = match self.stream.recv().await? {
+            Some(Message::NoiseHandshake(message)) => message,
+            _ => return Err(handshake_error("unexpected handshake message")),
+        };
+        let mut buf = vec![0u8; NOISE_MAX_MESSAGE_SIZE];
+        state.read_message(&message, &mut buf).map_err(handshake_error)?;
+        Ok(())
+    }
+
+    fn transport(&mut self) -> Result<&mut TransportState> {
+        match &mut self.state {
+            NoiseState::Transport(transport) => Ok(transport.as_mut()),
+            _ => Err(P2pError::ProtocolError(ProtocolError::InvalidState(
+                "Handshake",
+                "Transport",
+            ))),
+        }
+    }
+}
+
+#[async_trait]
+impl<S: MockStream> MockStream for NoiseStream<S> {
+    async fn send(&mut self, msg: Message) -> Result<()> {
+        let transport = self.transport()?;
+        let frames = msg
+            .encode()
+            .chunks(NOISE_MAX_MESSAGE_SIZE - NOISE_TAG_SIZE)
+            .map(|chunk| {
+                let mut buf = vec![0u8; chunk.len() + NOISE_TAG_SIZE];
+                let len = transport
+                    .write_message(chunk, &mut buf)
+                    .map_err(|_| P2pError::Other("Failed to encrypt message"))?;
+                buf.truncate(len);
+                Ok(buf)
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        self.stream.send(Message::Encrypted(frames)).await
+    }
+
+    async fn recv(&mut self) -> Result<Option<Message>> {
+        let frames = match self.stream.recv().await? {
+            Some(Message::Encrypted(frames)) => frames,
+            Some(_) => return Err(P2pError::ProtocolError(ProtocolError::InvalidMessage)),
+            None => return Ok(None),
+        };
+
+        let transport = self.transport()?;
+        let mut data = Vec::new();
+        for frame in frames {
+            let mut buf = vec![0u8; frame.len()];
+            let len = transport
+                .read_message(&frame, &mut buf)
+                .map_err(|_| P2pError::ProtocolError(ProtocolError::InvalidMessage))?;
+            data.extend_from_slice(&buf[..len]);
+        }
+
+        Ok(Some(Message::decode(&mut &data[..])?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::net::{
+        message::{BlockListRequest, Request},
+        mock::{
+            transport::{ChannelMockTransport, MockListener, MockTransport, TcpMockTransport},
+            types::MockRequestId,
+        },
+    };
+    use p2p_test_utils::{MakeChannelAddress, MakeTcpAddress, MakeTestAddress};
+
+    const MAGIC_BYTES: [u8; 4] = [1, 2, 3, 4];
+
+    async fn connected_streams<A, T>(
+        keypair1: &NoiseKeypair,
+        keypair2: &NoiseKeypair,
+        magic_bytes: [u8; 4],
+    ) -> (NoiseStream<T::Stream>, NoiseStream<T::Stream>)
+    where
+        A: MakeTestAddress<Address = T::Address>,
+        T: MockTransport,
+    {
+        let mut server = T::bind(A::make_address()).await.unwrap();
+        let peer_fut = T::connect(server.local_address().unwrap());
+        let (server_res, peer_res) = tokio::join!(server.accept(), peer_fut);
+
+        (
+            NoiseStream::new(peer_res.unwrap(), keypair1, true, &MAGIC_BYTES),
+            NoiseStream::new(server_res.unwrap().0, keypair2, false, &magic_bytes),
+        )
+    }
+
+    async fn send_recv<A, T>()
+    where
+        A: MakeTestAddress<Address = T::Address>,
+        T: MockTransport,
+    {
+        let keypair1 = NoiseKeypair::generate();
+        let keypair2 = NoiseKeypair::generate();
+        let (mut stream1, mut stream2) =
+            connected_streams::<A, T>(&keypair1, &keypair2, MAGIC_BYTES).await;
+
+        let (res1, res2) = tokio::join!(stream1.handshake(), stream2.handshake());
+        assert_eq!(res1, Ok(keypair2.peer_id()));
+        assert_eq!(res2, Ok(keypair1.peer_id()));
+
+        let request_id = MockRequestId::new(1337u64);
+        let request = Request::BlockListRequest(BlockListRequest::new(vec![]));
+        stream1
+            .send(Message::Request {
+                request_id,
+                request: request.clone(),
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(
+            stream2.recv().await.unwrap().unwrap(),
+            Message::Request {
+                request_id,
+                request,
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn send_recv_tcp() {
+        send_recv::<MakeTcpAddress, TcpMockTransport>().await;
+    }
+
+    #[tokio::test]
+    async fn send_recv_channels() {
+        send_recv::<MakeChannelAddress, ChannelMockTransport>().await;
+    }
+
+    // keypairs generated from generators with the same seed are equal and can be used for the
+    // handshake
+    async fn seeded_keypairs<A, T>()
+    where
+        A: MakeTestAddress<Address = T::Address>,
+        T: MockTransport,
+    {
+        let mut rng = test_utils::random::make_seedable_rng(123.into());
+        let keypair1 = NoiseKeypair::generate_with_rng(&mut rng);
+        let keypair2 = NoiseKeypair::generate_with_rng(&mut rng);
+        let mut rng = test_utils::random::make_seedable_rng(123.into());
+        assert_eq!(
+            NoiseKeypair::generate_with_rng(&mut rng).peer_id(),
+            keypair1.peer_id()
+        );
+        assert_ne!(keypair1.peer_id(), keypair2.peer_id());
+
+        let (mut stream1, mut stream2) =
+            connected_streams::<A, T>(&keypair1, &keypair2, MAGIC_BYTES).await;
+        let (res1, res2) = tokio::join!(stream1.handshake(), stream2.handshake());
+        assert_eq!(res1, Ok(keypair2.peer_id()));
+        assert_eq!(res2, Ok(keypair1.peer_id()));
+    }
+
+    #[tokio::test]
+    async fn seeded_keypairs_tcp() {
+        seeded_keypairs::<MakeTcpAddress, TcpMockTransport>().await;
+    }
+
+    #[tokio::test]
+    async fn seeded_keypairs_channels() {
+        seeded_keypairs::<MakeChannelAddress, ChannelMockTransport>().await;
+    }
+
+    // a message larger than one Noise message is split into several encrypted frames
+    async fn send_recv_large<A, T>()
+    where
+        A: MakeTestAddress<Address = T::Address>,
+        T: MockTransport,
+    {
+        let keypair1 = NoiseKeypair::generate();
+        let keypair2 = NoiseKeypair::generate();
+        let (mut stream1, mut stream2) =
+            connected_streams::<A, T>(&keypair1, &keypair2, MAGIC_BYTES).await;
+        let (res1, res2) = tokio::join!(stream1.handshake(), stream2.handshake());
+        assert!(res1.is_ok() && res2.is_ok());
+
+        let payload = vec![0xab; 3 * NOISE_MAX_MESSAGE_SIZE];
+        stream2.send(Message::NoiseHandshake(payload.clone())).await.unwrap();
+        assert_eq!(
+            stream1.recv().await.unwrap(),
+            Some(Message::NoiseHandshake(payload))
+        );
+    }
+
+    #[tokio::test]
+    async fn send_recv_large_tcp() {
+        send_recv_large::<MakeTcpAddress, TcpMockTransport>().await;
+    }
+
+    #[tokio::test]
+    async fn send_recv_large_channels() {
+        send_recv_large::<MakeChannelAddress, ChannelMockTransport>().await;
+    }
+
+    async fn different_network<A, T>()
+    where
+        A: MakeTestAddress<Address = T::Address>,
+        T: MockTransport,
+    {
+        let keypair1 = NoiseKeypair::generate();
+        let keypair2 = NoiseKeypair::generate();
+        let (mut stream1, mut stream2) =
+            connected_streams::<A, T>(&keypair1, &keypair2, [4, 3, 2, 1]).await;
+
+        let (res1, res2) = tokio::join!(async move { stream1.handshake().await }, async move {
+            stream2.handshake().await
+        },);
+        assert!(std::matches!(
+            res1,
+            Err(P2pError::DialError(DialError::HandshakeFailed(_)))
+        ));
+        assert!(res2.is_err());
+    }
+
+    #[tokio::test]
+    async fn different_network_tcp() {
+        different_network::<MakeTcpAddress, TcpMockTransport>().await;
+    }
+
+    #[tokio::test]
+    async fn different_network_channels() {
+        different_network::<MakeChannelAddress, ChannelMockTransport>().await;
+    }
+
+    async fn send_before_handshake<A, T>()
+    where
+        A: MakeTestAddress<Address = T::Address>,
+        T: MockTransport,
+    {
+        let keypair1 = NoiseKeypair::generate();
+        let keypair2 = NoiseKeypair::generate();
+        let (mut stream1, _stream2) =
+            connected_streams::<A, T>(&keypair1, &keypair2, MAGIC_BYTES).await;
+
+        assert_eq!(
+            stream1.send(Message::NoiseHandshake(vec![])).await,
+            Err(P2pError::ProtocolError(ProtocolError::InvalidState(
+                "Handshake",
+                "Transport"
+            )))
+        );
+    }
+
+    #[tokio::test]
+    async fn send_before_handshake_tcp() {
+        send_before_handshake::<MakeTcpAddress, TcpMockTransport>().await;
+    }
+
+    #[tokio::test]
+    async fn send_before_handshake_channels() {
+        send_before_handshake::<MakeChannelAddress, ChannelMockTransport>().await;
+    }
+}
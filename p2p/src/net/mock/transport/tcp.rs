@@ -31,11 +31,12 @@ use serialization::{Decode, Encode};
 use crate::{
     constants::MAX_MESSAGE_SIZE,
     net::{
+        ip_network_group,
         mock::{
             transport::{MockListener, MockStream, MockTransport},
             types::Message,
         },
-        AsBannableAddress, IsBannableAddress,
+        AsBannableAddress, AsNetworkGroup, IsBannableAddress, IsIpv6Address,
     },
     P2pError, Result,
 };
@@ -192,6 +193,18 @@ impl IsBannableAddress for SocketAddr {
     }
 }
 
+impl IsIpv6Address for SocketAddr {
+    fn is_ipv6(&self) -> bool {
+        std::matches!(self, SocketAddr::V6(_))
+    }
+}
+
+impl AsNetworkGroup for SocketAddr {
+    fn network_group(&self) -> Vec<u8> {
+        ip_network_group(&self.ip())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
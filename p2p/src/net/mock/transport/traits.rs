@@ -18,7 +18,10 @@ use std::{fmt::Debug, hash::Hash, str::FromStr};
 use async_trait::async_trait;
 
 use crate::{
-    net::{mock::types::Message, AsBannableAddress, IsBannableAddress},
+    net::{
+        mock::types::Message, AsBannableAddress, AsIpAddress, AsNetworkGroup, IsBannableAddress,
+        IsIpv6Address,
+    },
     Result,
 };
 
@@ -35,10 +38,12 @@ pub trait MockTransport: Send + 'static {
         + ToString
         + FromStr
         + AsBannableAddress<BannableAddress = Self::BannableAddress>
-        + IsBannableAddress;
+        + IsBannableAddress
+        + IsIpv6Address
+        + AsNetworkGroup;
 
     /// A bannable address format.
-    type BannableAddress: Debug + Eq + Ord + Send;
+    type BannableAddress: Debug + Eq + Ord + Send + AsIpAddress;
 
     /// A listener type.
     type Listener: MockListener<Self::Stream, Self::Address>;
@@ -51,6 +56,17 @@ pub trait MockTransport: Send + 'static {
 
     /// Open a connection to the given address.
     async fn connect(address: Self::Address) -> Result<Self::Stream>;
+
+    /// Open a connection to the given address from the node listening on `local_address`.
+    ///
+    /// Only transports that need to know which node opens the connection, like a simulated
+    /// network that can be partitioned, have to override this.
+    async fn connect_from(
+        _local_address: Self::Address,
+        address: Self::Address,
+    ) -> Result<Self::Stream> {
+        Self::connect(address).await
+    }
 }
 
 /// An abstraction layer over some kind of network connection.
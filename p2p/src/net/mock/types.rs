@@ -17,6 +17,7 @@ use std::{
     collections::{hash_map::DefaultHasher, BTreeSet},
     hash::{Hash, Hasher},
     str::FromStr,
+    time::Duration,
 };
 
 use tokio::sync::oneshot;
@@ -30,7 +31,7 @@ use crate::{
     net::{
         self,
         mock::transport::MockTransport,
-        types::{Protocol, PubSubTopic},
+        types::{Protocol, PubSubTopic, Services},
     },
 };
 
@@ -58,6 +59,10 @@ pub enum Command<T: MockTransport> {
         message: message::Response,
         response: oneshot::Sender<crate::Result<()>>,
     },
+    /// Drop a request received from a remote peer without answering it
+    DropRequest {
+        request_id: MockRequestId,
+    },
     Subscribe {
         topics: BTreeSet<PubSubTopic>,
     },
@@ -66,6 +71,10 @@ pub enum Command<T: MockTransport> {
         message: Vec<u8>,
         response: oneshot::Sender<crate::Result<()>>,
     },
+    PingLatency {
+        peer_id: MockPeerId,
+        response: oneshot::Sender<Option<Duration>>,
+    },
 }
 
 pub enum SyncingEvent {
@@ -163,6 +172,14 @@ impl MockPeerId {
         addr.hash(&mut hasher);
         Self(hasher.finish())
     }
+
+    /// Derive the peer ID from the static public key of the peer
+    pub fn from_public_key(public_key: &[u8]) -> Self {
+        let hash = crypto::hash::hash::<crypto::hash::Blake2b32, _>(public_key);
+        let mut bytes = [0u8; 8];
+        bytes.copy_from_slice(&hash[..8]);
+        Self(u64::from_le_bytes(bytes))
+    }
 }
 
 impl std::fmt::Display for MockPeerId {
@@ -183,6 +200,9 @@ pub struct MockPeerInfo {
     pub version: common::primitives::semver::SemVer,
     pub agent: Option<String>,
     pub protocols: Vec<Protocol>,
+    pub protocol_version: u32,
+    pub services: Services,
+    pub time: u64,
 }
 
 #[derive(Debug, PartialEq, Eq)]
@@ -193,8 +213,14 @@ pub enum PeerEvent {
         network: [u8; 4],
         version: semver::SemVer,
         protocols: Vec<Protocol>,
+        protocol_version: u32,
+        services: Services,
+        time: u64,
     },
 
+    /// Remote responded to a ping
+    PingLatency { latency: Duration },
+
     /// Connection closed to remote
     ConnectionClosed,
 
@@ -205,6 +231,8 @@ pub enum PeerEvent {
 /// Events sent by the mock backend to peers
 #[derive(Debug)]
 pub enum MockEvent {
+    /// The connection has been accepted after the handshake, sent before any other event
+    Accepted,
     Disconnect,
     SendMessage(Box<Message>),
 }
@@ -216,12 +244,22 @@ pub enum HandshakeMessage {
         version: common::primitives::semver::SemVer,
         network: [u8; 4],
         protocols: Vec<Protocol>,
+        /// The highest protocol version supported by the sender
+        protocol_version: u32,
+        services: Services,
+        /// Local time of the sender in seconds since the UNIX epoch
+        time: u64,
     },
     HelloAck {
         peer_id: MockPeerId,
         version: common::primitives::semver::SemVer,
         network: [u8; 4],
         protocols: Vec<Protocol>,
+        /// The highest protocol version supported by the sender
+        protocol_version: u32,
+        services: Services,
+        /// Local time of the sender in seconds since the UNIX epoch
+        time: u64,
     },
 }
 
@@ -242,4 +280,16 @@ pub enum Message {
     Announcement {
         announcement: message::Announcement,
     },
+    /// Ping sent periodically to measure the round-trip time
+    Ping {
+        nonce: u64,
+    },
+    /// Response to a ping
+    Pong {
+        nonce: u64,
+    },
+    /// Noise handshake message, see [`crate::net::mock::transport::NoiseStream`]
+    NoiseHandshake(Vec<u8>),
+    /// Encrypted message, split into Noise transport messages
+    Encrypted(Vec<Vec<u8>>),
 }
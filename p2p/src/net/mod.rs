@@ -20,12 +20,16 @@ pub mod types;
 use std::{
     fmt::{Debug, Display},
     hash::Hash,
+    net::IpAddr,
     str::FromStr,
     sync::Arc,
+    time::Duration,
 };
 
 use async_trait::async_trait;
 
+use common::time_getter::TimeGetter;
+
 use crate::{config, message, message::Announcement};
 
 /// [NetworkingService] provides the low-level network interface
@@ -52,13 +56,15 @@ pub trait NetworkingService {
         + ToString
         + FromStr
         + AsBannableAddress<BannableAddress = Self::BannableAddress>
-        + IsBannableAddress;
+        + IsBannableAddress
+        + IsIpv6Address
+        + AsNetworkGroup;
 
     /// An address type that can be banned.
     ///
     /// Usually it is part of the `NetworkingService::Address`. For example for a socket address
     /// that consists of an IP address and a port we want to ban the IP address.
-    type BannableAddress: Debug + Eq + Ord + Send;
+    type BannableAddress: Debug + Eq + Ord + Send + AsIpAddress;
 
     /// Unique ID assigned to a peer on the network
     type PeerId: Copy + Debug + Display + Eq + Hash + Send + Sync + ToString + FromStr;
@@ -85,10 +91,13 @@ pub trait NetworkingService {
     /// `chain_config` - chain config of the node
     ///
     /// `timeout` - timeout for outbound connections
+    ///
+    /// `time_getter` - source of the local time advertised to the peers
     async fn start(
         bind_addr: Self::Address,
         chain_config: Arc<common::chain::ChainConfig>,
         p2p_config: Arc<config::P2pConfig>,
+        time_getter: TimeGetter,
     ) -> crate::Result<(Self::ConnectivityHandle, Self::SyncingMessagingHandle)>;
 }
 
@@ -120,12 +129,21 @@ where
     /// If the address isn't available yet, `None` is returned
     async fn local_addr(&self) -> crate::Result<Option<T::Address>>;
 
+    /// Return the addresses other nodes reach the local node at, as discovered by the network
+    /// service provider
+    async fn external_addresses(&self) -> crate::Result<Vec<T::Address>>;
+
     /// Return peer id of the local node
     fn peer_id(&self) -> &T::PeerId;
 
     /// Ban peer
     async fn ban_peer(&mut self, peer_id: T::PeerId) -> crate::Result<()>;
 
+    /// Return the round-trip time of the last ping sent to the peer
+    ///
+    /// If the peer hasn't responded to a ping yet, `None` is returned
+    async fn ping_latency(&mut self, peer_id: T::PeerId) -> crate::Result<Option<Duration>>;
+
     /// Poll events from the network service provider
     ///
     /// There are three types of events that can be received:
@@ -164,6 +182,15 @@ where
         response: message::Response,
     ) -> crate::Result<()>;
 
+    /// Drop a received request without answering it
+    ///
+    /// The state kept for the request by the backend is released, the remote peer
+    /// doesn't receive a response.
+    ///
+    /// # Arguments
+    /// * `request_id` - ID of the request that is dropped
+    async fn drop_request(&mut self, request_id: T::SyncingPeerRequestId) -> crate::Result<()>;
+
     /// Publishes an announcement on the network.
     async fn make_announcement(&mut self, announcement: Announcement) -> crate::Result<()>;
 
@@ -196,8 +223,61 @@ pub trait AsBannableAddress {
     fn as_bannable(&self) -> Self::BannableAddress;
 }
 
+/// Returns the IP address of a bannable address.
+///
+/// Used to match the address against the banned and whitelisted subnets.
+pub trait AsIpAddress {
+    fn as_ip_address(&self) -> IpAddr;
+}
+
+impl AsIpAddress for IpAddr {
+    fn as_ip_address(&self) -> IpAddr {
+        *self
+    }
+}
+
 // TODO: This is only needed because `libp2p::MultiAddr` can contain no IP address.
 /// Checks if an address can be converted to bannable.
 pub trait IsBannableAddress {
     fn is_bannable(&self) -> bool;
 }
+
+/// Checks if an address is an IPv6 address.
+///
+/// Used to sort the addresses received from other peers into [`types::AddrInfo`].
+pub trait IsIpv6Address {
+    fn is_ipv6(&self) -> bool;
+}
+
+/// Returns the network group of an address.
+///
+/// Addresses from the same network group are likely to be controlled by the same operator, so
+/// the peer manager tries to spread its connections over as many groups as possible.
+pub trait AsNetworkGroup {
+    fn network_group(&self) -> Vec<u8>;
+}
+
+/// Returns true if the IP address is only reachable locally or within a private network
+pub fn is_local_ip(ip: &IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            ip.is_loopback() || ip.is_private() || ip.is_link_local() || ip.is_unspecified()
+        }
+        IpAddr::V6(ip) => {
+            ip.is_loopback()
+                || ip.is_unspecified()
+                // Unique local addresses, fc00::/7
+                || (ip.segments()[0] & 0xfe00) == 0xfc00
+                // Link-local addresses, fe80::/10
+                || (ip.segments()[0] & 0xffc0) == 0xfe80
+        }
+    }
+}
+
+/// Returns the network group of an IP address: its IPv4 /16 or IPv6 /32 subnet
+pub fn ip_network_group(ip: &IpAddr) -> Vec<u8> {
+    match ip {
+        IpAddr::V4(ip) => [&[4], &ip.octets()[..2]].concat(),
+        IpAddr::V6(ip) => [&[6], &ip.octets()[..4]].concat(),
+    }
+}
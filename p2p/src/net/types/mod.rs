@@ -14,8 +14,12 @@
 // limitations under the License.
 
 pub use protocol::{Protocol, ProtocolType};
+pub use services::{Service, Services};
+pub use subnet::Subnet;
 
 mod protocol;
+mod services;
+mod subnet;
 
 use std::{collections::BTreeSet, fmt::Display};
 
@@ -60,6 +64,15 @@ pub struct PeerInfo<T: NetworkingService> {
 
     /// A set of supported protocols.
     pub protocols: BTreeSet<Protocol>,
+
+    /// Protocol version negotiated with the peer
+    pub protocol_version: u32,
+
+    /// Services advertised by the peer
+    pub services: Services,
+
+    /// Local time of the peer in seconds since the UNIX epoch at the time of the handshake
+    pub time: Option<u64>,
 }
 
 impl<T: NetworkingService> Display for PeerInfo<T> {
@@ -73,6 +86,8 @@ impl<T: NetworkingService> Display for PeerInfo<T> {
             "--> User agent: {}",
             self.agent.as_ref().unwrap_or(&"No user agent".to_string())
         )?;
+        writeln!(f, "--> Protocol version: {}", self.protocol_version)?;
+        writeln!(f, "--> Services: {}", self.services)?;
         write!(f, "--> Protocols: ")?;
 
         for protocol in &self.protocols {
@@ -197,6 +212,10 @@ pub enum PubSubTopic {
 
     /// Blocks
     Blocks,
+
+    /// Compact blocks pushed in high-bandwidth mode, only relayed to the peers that support
+    /// compact blocks
+    CompactBlocks,
 }
 
 /// Validation result for an incoming PubSub message
@@ -0,0 +1,160 @@
+// Copyright (c) 2021-2022 RBB S.r.l
+// opensource@mintlayer.org
+// SPDX-License-Identifier: MIT
+// Licensed under the MIT License;
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://github.com/mintlayer/mintlayer-core/blob/master/LICENSE
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::fmt::{self, Display, Formatter};
+
+use serialization::{Decode, Encode};
+
+/// Optional features that a node can provide to its peers.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+#[repr(u64)]
+pub enum Service {
+    /// The node stores the full block history and can serve any block.
+    FullNode = 1 << 0,
+    /// The node only stores recent blocks.
+    Pruned = 1 << 1,
+    /// The node relays transactions.
+    TxRelay = 1 << 2,
+    /// The node supports compact block relay.
+    CompactBlocks = 1 << 3,
+    /// The node shares the addresses of known peers.
+    AddrRelay = 1 << 4,
+}
+
+impl Service {
+    const ALL: [Service; 5] = [
+        Service::FullNode,
+        Service::Pruned,
+        Service::TxRelay,
+        Service::CompactBlocks,
+        Service::AddrRelay,
+    ];
+}
+
+/// A set of services advertised by a node during the handshake.
+///
+/// Unknown bits are preserved so that the services introduced by newer nodes don't prevent older
+/// nodes from connecting to them.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Encode, Decode)]
+pub struct Services(u64);
+
+impl Services {
+    /// Constructs an empty set of services.
+    pub const fn none() -> Self {
+        Self(0)
+    }
+
+    /// Constructs a set of services from raw service bits.
+    pub const fn from_bits(bits: u64) -> Self {
+        Self(bits)
+    }
+
+    /// Returns the raw service bits.
+    pub const fn bits(&self) -> u64 {
+        self.0
+    }
+
+    /// Returns a copy of the set with the given service added.
+    pub const fn with(self, service: Service) -> Self {
+        Self(self.0 | service as u64)
+    }
+
+    /// Returns a copy of the set with the given service removed.
+    pub const fn without(self, service: Service) -> Self {
+        Self(self.0 & !(service as u64))
+    }
+
+    /// Returns true if the given service is in the set.
+    pub const fn has(&self, service: Service) -> bool {
+        self.0 & service as u64 != 0
+    }
+
+    /// Returns the services that are in both sets.
+    pub const fn intersection(&self, other: &Services) -> Self {
+        Self(self.0 & other.0)
+    }
+}
+
+/// The services provided by this implementation.
+impl Default for Services {
+    fn default() -> Self {
+        Self::none()
+            .with(Service::FullNode)
+            .with(Service::TxRelay)
+            .with(Service::CompactBlocks)
+            .with(Service::AddrRelay)
+    }
+}
+
+impl FromIterator<Service> for Services {
+    fn from_iter<I: IntoIterator<Item = Service>>(iter: I) -> Self {
+        iter.into_iter().fold(Self::none(), Self::with)
+    }
+}
+
+impl Display for Services {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let mut first = true;
+        for service in Service::ALL.iter().filter(|service| self.has(**service)) {
+            if !first {
+                write!(f, " ")?;
+            }
+            write!(f, "{:?}", service)?;
+            first = false;
+        }
+
+        let unknown = self.0 & !Service::ALL.iter().fold(0, |bits, service| bits | *service as u64);
+        if unknown != 0 {
+            if !first {
+                write!(f, " ")?;
+            }
+            write!(f, "Unknown({unknown:#x})")?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn common_services() {
+        let local = Services::default();
+        let remote: Services = [Service::Pruned, Service::CompactBlocks].into_iter().collect();
+
+        let common = local.intersection(&remote);
+        assert!(common.has(Service::CompactBlocks));
+        assert!(!common.has(Service::FullNode));
+        assert!(!common.has(Service::Pruned));
+        assert!(!common.has(Service::AddrRelay));
+    }
+
+    #[test]
+    fn remove_service() {
+        let services = Services::default().without(Service::TxRelay);
+        assert!(!services.has(Service::TxRelay));
+        assert!(services.has(Service::FullNode));
+        assert_eq!(services.without(Service::TxRelay), services);
+    }
+
+    #[test]
+    fn unknown_services_are_preserved() {
+        let services = Services::from_bits(1 << 63).with(Service::TxRelay);
+        assert_eq!(services.bits(), (1 << 63) | Service::TxRelay as u64);
+        assert_eq!(services.to_string(), "TxRelay Unknown(0x8000000000000000)");
+    }
+}
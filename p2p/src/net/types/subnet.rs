@@ -0,0 +1,135 @@
+// Copyright (c) 2021-2022 RBB S.r.l
+// opensource@mintlayer.org
+// SPDX-License-Identifier: MIT
+// Licensed under the MIT License;
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://github.com/mintlayer/mintlayer-core/blob/master/LICENSE
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::{
+    fmt::{self, Display, Formatter},
+    net::{IpAddr, Ipv4Addr, Ipv6Addr},
+    str::FromStr,
+};
+
+use crate::error::ConversionError;
+
+/// A range of IP addresses in the CIDR notation, e.g. `192.168.0.0/24` or `2001:db8::/64`.
+///
+/// A single address without the prefix length is a subnet of one address. The host bits of the
+/// address are cleared, so `10.0.0.1/8` and `10.0.0.0/8` are the same subnet.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Subnet {
+    address: IpAddr,
+    prefix_len: u8,
+}
+
+impl Subnet {
+    /// Constructs a subnet, returns `None` if the prefix is longer than the address.
+    pub fn new(address: IpAddr, prefix_len: u8) -> Option<Self> {
+        let address = match address {
+            IpAddr::V4(ip) if prefix_len <= 32 => {
+                let mask = u32::MAX.checked_shl(32 - u32::from(prefix_len)).unwrap_or(0);
+                IpAddr::V4(Ipv4Addr::from(u32::from(ip) & mask))
+            }
+            IpAddr::V6(ip) if prefix_len <= 128 => {
+                let mask = u128::MAX.checked_shl(128 - u32::from(prefix_len)).unwrap_or(0);
+                IpAddr::V6(Ipv6Addr::from(u128::from(ip) & mask))
+            }
+            IpAddr::V4(_) | IpAddr::V6(_) => return None,
+        };
+        Some(Self {
+            address,
+            prefix_len,
+        })
+    }
+
+    /// Constructs a subnet that contains only the given address.
+    pub fn single(address: IpAddr) -> Self {
+        let prefix_len = match address {
+            IpAddr::V4(_) => 32,
+            IpAddr::V6(_) => 128,
+        };
+        Self {
+            address,
+            prefix_len,
+        }
+    }
+
+    /// Returns true if the address belongs to the subnet.
+    pub fn contains(&self, address: &IpAddr) -> bool {
+        match Self::new(*address, self.prefix_len) {
+            Some(subnet) => subnet.address == self.address,
+            None => false,
+        }
+    }
+}
+
+impl FromStr for Subnet {
+    type Err = ConversionError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || ConversionError::InvalidSubnet(s.to_string());
+
+        match s.split_once('/') {
+            Some((address, prefix_len)) => {
+                let address = address.parse::<IpAddr>().map_err(|_| invalid())?;
+                let prefix_len = prefix_len.parse::<u8>().map_err(|_| invalid())?;
+                Self::new(address, prefix_len).ok_or_else(invalid)
+            }
+            None => s.parse::<IpAddr>().map(Self::single).map_err(|_| invalid()),
+        }
+    }
+}
+
+impl Display for Subnet {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.address, self.prefix_len)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_and_display() {
+        let subnet = "10.1.2.3/24".parse::<Subnet>().unwrap();
+        assert_eq!(subnet.to_string(), "10.1.2.0/24");
+
+        let subnet = "2001:db8:1:2:3:4:5:6/64".parse::<Subnet>().unwrap();
+        assert_eq!(subnet.to_string(), "2001:db8:1:2::/64");
+
+        let subnet = "10.1.2.3".parse::<Subnet>().unwrap();
+        assert_eq!(subnet.to_string(), "10.1.2.3/32");
+
+        assert!("10.1.2.3/33".parse::<Subnet>().is_err());
+        assert!("::1/129".parse::<Subnet>().is_err());
+        assert!("10.1.2/8".parse::<Subnet>().is_err());
+        assert!("10.1.2.3/".parse::<Subnet>().is_err());
+    }
+
+    #[test]
+    fn contains() {
+        let subnet = "192.168.1.0/24".parse::<Subnet>().unwrap();
+        assert!(subnet.contains(&"192.168.1.0".parse().unwrap()));
+        assert!(subnet.contains(&"192.168.1.255".parse().unwrap()));
+        assert!(!subnet.contains(&"192.168.2.1".parse().unwrap()));
+        assert!(!subnet.contains(&"::ffff:192.168.1.1".parse().unwrap()));
+
+        let subnet = "2001:db8:0:1::/64".parse::<Subnet>().unwrap();
+        assert!(subnet.contains(&"2001:db8:0:1:ffff::1".parse().unwrap()));
+        assert!(!subnet.contains(&"2001:db8:0:2::1".parse().unwrap()));
+
+        let subnet = "0.0.0.0/0".parse::<Subnet>().unwrap();
+        assert!(subnet.contains(&"1.2.3.4".parse().unwrap()));
+        assert!(!subnet.contains(&"::1".parse().unwrap()));
+    }
+}
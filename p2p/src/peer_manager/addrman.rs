@@ -0,0 +1,400 @@
+// Copyright (c) 2022 RBB S.r.l
+// opensource@mintlayer.org
+// SPDX-License-Identifier: MIT
+// Licensed under the MIT License;
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://github.com/mintlayer/mintlayer-core/blob/master/LICENSE
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Address manager
+//!
+//! The known addresses are kept in two tables, similarly to Bitcoin Core: the "new" table holds
+//! the addresses that have been discovered but haven't been connected to yet and the "tried"
+//! table holds the addresses that an outbound connection has succeeded to. Both tables consist of
+//! fixed-size buckets and the bucket of an address is selected by hashing its network group with
+//! a secret key, so the addresses of one network group (or the addresses announced by one peer)
+//! can fill only a few buckets and can't push the other addresses out of the tables.
+
+use std::{
+    collections::{hash_map::DefaultHasher, BTreeSet, HashMap},
+    hash::{Hash, Hasher},
+};
+
+use crypto::random::{rngs::StdRng, seq::IteratorRandom, Rng, SeedableRng};
+
+use crate::net::{AsNetworkGroup, NetworkingService};
+
+/// Number of buckets in the new table
+const NEW_BUCKET_COUNT: usize = 256;
+
+/// Number of buckets in the tried table
+const TRIED_BUCKET_COUNT: usize = 64;
+
+/// Maximum number of addresses in one bucket
+const BUCKET_SIZE: usize = 64;
+
+/// Number of new buckets the addresses announced by one network group are spread over
+const NEW_BUCKETS_PER_SOURCE_GROUP: u64 = 32;
+
+/// Number of tried buckets the addresses of one network group are spread over
+const TRIED_BUCKETS_PER_GROUP: u64 = 8;
+
+/// Addresses in the new table are forgotten after this many failed connection attempts
+const MAX_NEW_ATTEMPTS: u32 = 3;
+
+/// Addresses in the tried table are forgotten after this many failed connection attempts
+const MAX_TRIED_ATTEMPTS: u32 = 10;
+
+/// How many random picks are made before falling back to scanning all addresses
+const SELECT_ATTEMPTS: usize = 64;
+
+/// The table an address is in
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Table {
+    New,
+    Tried,
+}
+
+#[derive(Debug)]
+struct AddressInfo<P> {
+    /// Peer ID the address belongs to
+    peer_id: P,
+
+    /// Table and bucket the address is in
+    table: Table,
+    bucket: usize,
+
+    /// Number of failed connection attempts since the last successful one
+    attempts: u32,
+}
+
+pub struct AddressManager<T: NetworkingService> {
+    /// Secret key used to select the buckets
+    key: u64,
+
+    /// All known addresses
+    addresses: HashMap<T::Address, AddressInfo<T::PeerId>>,
+
+    /// Buckets of the new table
+    new: Vec<Vec<T::Address>>,
+
+    /// Buckets of the tried table
+    tried: Vec<Vec<T::Address>>,
+
+    /// Random number generator used to select the addresses
+    rng: StdRng,
+}
+
+impl<T: NetworkingService> Default for AddressManager<T> {
+    fn default() -> Self {
+        Self::new(StdRng::from_entropy())
+    }
+}
+
+impl<T: NetworkingService> AddressManager<T> {
+    /// Create an empty address manager, the bucket key is drawn from `rng`
+    pub fn new(mut rng: StdRng) -> Self {
+        Self {
+            key: rng.gen(),
+            addresses: HashMap::new(),
+            new: vec![Vec::new(); NEW_BUCKET_COUNT],
+            tried: vec![Vec::new(); TRIED_BUCKET_COUNT],
+            rng,
+        }
+    }
+
+    /// Get the number of addresses in the table
+    pub fn count(&self, table: Table) -> usize {
+        self.addresses.values().filter(|info| info.table == table).count()
+    }
+
+    /// Get the table the address is in, if it's known
+    pub fn table(&self, address: &T::Address) -> Option<Table> {
+        self.addresses.get(address).map(|info| info.table)
+    }
+
+    fn hash(&self, data: impl Hash) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        self.key.hash(&mut hasher);
+        data.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    fn new_bucket(&self, address: &T::Address, source_group: &[u8]) -> usize {
+        let group = address.network_group();
+        let slot = self.hash((&group, source_group)) % NEW_BUCKETS_PER_SOURCE_GROUP;
+        (self.hash((source_group, slot)) % NEW_BUCKET_COUNT as u64) as usize
+    }
+
+    fn tried_bucket(&self, address: &T::Address) -> usize {
+        let group = address.network_group();
+        let slot = self.hash(address) % TRIED_BUCKETS_PER_GROUP;
+        (self.hash((&group, slot)) % TRIED_BUCKET_COUNT as u64) as usize
+    }
+
+    fn bucket_mut(&mut self, table: Table, bucket: usize) -> &mut Vec<T::Address> {
+        match table {
+            Table::New => &mut self.new[bucket],
+            Table::Tried => &mut self.tried[bucket],
+        }
+    }
+
+    /// Add a discovered address to the new table
+    ///
+    /// `source_group` is the network group of the peer that announced the address. If the bucket
+    /// is full, the address with the most failed connection attempts is replaced, preferring
+    /// the oldest one. The replaced address is forgotten and returned.
+    pub fn add(
+        &mut self,
+        address: T::Address,
+        peer_id: T::PeerId,
+        source_group: &[u8],
+    ) -> Option<T::Address> {
+        if self.addresses.contains_key(&address) {
+            return None;
+        }
+
+        let bucket = self.new_bucket(&address, source_group);
+        let mut forgotten = None;
+        if self.new[bucket].len() >= BUCKET_SIZE {
+            let evicted = self.new[bucket]
+                .iter()
+                .enumerate()
+                .max_by_key(|(index, address)| {
+                    (self.addresses[*address].attempts, std::cmp::Reverse(*index))
+                })
+                .map(|(_, address)| address.clone())
+                .expect("bucket to be full");
+            self.remove(&evicted);
+            forgotten = Some(evicted);
+        }
+
+        self.new[bucket].push(address.clone());
+        self.addresses.insert(
+            address,
+            AddressInfo {
+                peer_id,
+                table: Table::New,
+                bucket,
+                attempts: 0,
+            },
+        );
+        forgotten
+    }
+
+    /// Forget an address
+    pub fn remove(&mut self, address: &T::Address) {
+        if let Some(info) = self.addresses.remove(address) {
+            self.bucket_mut(info.table, info.bucket).retain(|a| a != address);
+        }
+    }
+
+    /// Move the address an outbound connection succeeded to into the tried table
+    ///
+    /// If the bucket is full, its oldest address is moved back to the new table. If that makes
+    /// room in the new table by replacing another address, the replaced address is forgotten
+    /// and returned.
+    pub fn mark_tried(&mut self, address: T::Address, peer_id: T::PeerId) -> Option<T::Address> {
+        if let Some(info) = self.addresses.get_mut(&address) {
+            info.attempts = 0;
+            if info.table == Table::Tried {
+                return None;
+            }
+        }
+        self.remove(&address);
+
+        let bucket = self.tried_bucket(&address);
+        let mut forgotten = None;
+        if self.tried[bucket].len() >= BUCKET_SIZE {
+            let evicted = self.tried[bucket].remove(0);
+            if let Some(info) = self.addresses.remove(&evicted) {
+                let group = evicted.network_group();
+                forgotten = self.add(evicted, info.peer_id, &group);
+            }
+        }
+
+        self.tried[bucket].push(address.clone());
+        self.addresses.insert(
+            address,
+            AddressInfo {
+                peer_id,
+                table: Table::Tried,
+                bucket,
+                attempts: 0,
+            },
+        );
+        forgotten
+    }
+
+    /// Record a failed connection attempt, the address is forgotten after too many failures
+    ///
+    /// Returns whether the address was forgotten.
+    pub fn attempt_failed(&mut self, address: &T::Address) -> bool {
+        let forget = match self.addresses.get_mut(address) {
+            Some(info) => {
+                info.attempts += 1;
+                match info.table {
+                    Table::New => info.attempts >= MAX_NEW_ATTEMPTS,
+                    Table::Tried => info.attempts >= MAX_TRIED_ATTEMPTS,
+                }
+            }
+            None => false,
+        };
+        if forget {
+            self.remove(address);
+        }
+        forget
+    }
+
+    /// Select a random address that is not in any of the excluded network groups and that
+    /// is accepted by `accept`
+    ///
+    /// Both tables are picked from with equal probability unless `new_only` is set. A random
+    /// non-empty bucket is picked first and then a random address in it, so the groups that
+    /// have many addresses are not more likely to be selected than the groups that have few.
+    pub fn select(
+        &mut self,
+        new_only: bool,
+        excluded_groups: &BTreeSet<Vec<u8>>,
+        mut accept: impl FnMut(&T::Address, &T::PeerId) -> bool,
+    ) -> Option<(T::Address, T::PeerId)> {
+        let mut is_acceptable = |address: &T::Address, info: &AddressInfo<T::PeerId>| {
+            (!new_only || info.table == Table::New)
+                && !excluded_groups.contains(&address.network_group())
+                && accept(address, &info.peer_id)
+        };
+
+        let tables = if new_only || self.tried.iter().all(Vec::is_empty) {
+            vec![&self.new]
+        } else {
+            vec![&self.new, &self.tried]
+        };
+
+        for _ in 0..SELECT_ATTEMPTS {
+            let table = tables[self.rng.gen_range(0..tables.len())];
+            let address = table
+                .iter()
+                .filter(|bucket| !bucket.is_empty())
+                .choose(&mut self.rng)
+                .and_then(|bucket| bucket.iter().choose(&mut self.rng));
+            if let Some(address) = address {
+                let info = &self.addresses[address];
+                if is_acceptable(address, info) {
+                    return Some((address.clone(), info.peer_id));
+                }
+            }
+        }
+
+        self.addresses
+            .iter()
+            .find(|(address, info)| is_acceptable(address, info))
+            .map(|(address, info)| (address.clone(), info.peer_id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::SocketAddr;
+
+    use super::*;
+    use crate::net::mock::{transport::TcpMockTransport, types::MockPeerId, MockService};
+
+    type Service = MockService<TcpMockTransport>;
+
+    fn make_address(s: &str) -> SocketAddr {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn new_and_tried() {
+        let mut addrman = AddressManager::<Service>::default();
+        let peer_id = MockPeerId::random();
+        let address = make_address("1.2.3.4:3031");
+
+        addrman.add(address, peer_id, &address.network_group());
+        assert_eq!(addrman.table(&address), Some(Table::New));
+
+        addrman.mark_tried(address, peer_id);
+        assert_eq!(addrman.table(&address), Some(Table::Tried));
+        assert_eq!(addrman.count(Table::New), 0);
+        assert_eq!(addrman.count(Table::Tried), 1);
+
+        // adding an address that is already known doesn't move it back
+        addrman.add(address, peer_id, &address.network_group());
+        assert_eq!(addrman.table(&address), Some(Table::Tried));
+    }
+
+    #[test]
+    fn failed_attempts() {
+        let mut addrman = AddressManager::<Service>::default();
+        let address = make_address("1.2.3.4:3031");
+
+        addrman.add(address, MockPeerId::random(), &address.network_group());
+        for _ in 0..MAX_NEW_ATTEMPTS {
+            assert_eq!(addrman.table(&address), Some(Table::New));
+            addrman.attempt_failed(&address);
+        }
+        assert_eq!(addrman.table(&address), None);
+    }
+
+    // the addresses announced by one source fill a limited number of buckets
+    // and can't push out the addresses announced by others
+    #[test]
+    fn source_bucket_limit() {
+        let mut addrman = AddressManager::<Service>::default();
+        let source_group = make_address("6.6.6.6:3031").network_group();
+
+        for a in 0..=255 {
+            for b in 0..=255 {
+                let address = make_address(&format!("{a}.{b}.1.1:3031"));
+                addrman.add(address, MockPeerId::random(), &source_group);
+            }
+        }
+        assert!(addrman.count(Table::New) <= NEW_BUCKETS_PER_SOURCE_GROUP as usize * BUCKET_SIZE);
+
+        let address = make_address("7.7.7.7:3031");
+        addrman.add(address, MockPeerId::random(), &address.network_group());
+        assert_eq!(addrman.table(&address), Some(Table::New));
+    }
+
+    #[test]
+    fn select_excludes_groups() {
+        let mut addrman = AddressManager::<Service>::default();
+
+        for i in 0..10 {
+            let address = make_address(&format!("10.10.1.{i}:3031"));
+            addrman.add(address, MockPeerId::random(), &address.network_group());
+        }
+        let other = make_address("20.20.1.1:3031");
+        addrman.add(other, MockPeerId::random(), &other.network_group());
+
+        let mut excluded = BTreeSet::from([make_address("10.10.0.1:3031").network_group()]);
+        for _ in 0..10 {
+            let (selected, _) = addrman.select(false, &excluded, |_, _| true).unwrap();
+            assert_eq!(selected, other);
+        }
+
+        excluded.insert(other.network_group());
+        assert_eq!(addrman.select(false, &excluded, |_, _| true), None);
+    }
+
+    #[test]
+    fn select_new_only() {
+        let mut addrman = AddressManager::<Service>::default();
+        let address = make_address("10.10.1.1:3031");
+
+        addrman.mark_tried(address, MockPeerId::random());
+        assert_eq!(addrman.select(true, &BTreeSet::new(), |_, _| true), None);
+        assert_eq!(
+            addrman.select(false, &BTreeSet::new(), |_, _| true).map(|(address, _)| address),
+            Some(address)
+        );
+    }
+}
@@ -0,0 +1,144 @@
+// Copyright (c) 2022 RBB S.r.l
+// opensource@mintlayer.org
+// SPDX-License-Identifier: MIT
+// Licensed under the MIT License;
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://github.com/mintlayer/mintlayer-core/blob/master/LICENSE
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Selection of the inbound peer to evict when all inbound connection slots are taken
+//!
+//! The selection closely follows the eviction logic of Bitcoin Core: the peers are protected
+//! from eviction in several rounds based on characteristics that are hard for an attacker to
+//! fake at scale, so that filling the inbound slots with junk connections doesn't push out
+//! the honest peers.
+
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap},
+    hash::{Hash, Hasher},
+    time::{Duration, Instant},
+};
+
+/// Number of peers protected by their (keyed) network group
+const PROTECTED_BY_NETWORK_GROUP: usize = 4;
+
+/// Number of peers protected by having the lowest response time
+const PROTECTED_BY_RESPONSE_TIME: usize = 8;
+
+/// Number of peers protected by having most recently delivered a new block
+const PROTECTED_BY_BLOCKS: usize = 4;
+
+/// Statistics of an active connection
+#[derive(Debug, Clone)]
+pub struct ConnectionInfo {
+    /// Whether the connection was initiated by the local node
+    pub outbound: bool,
+
+    /// Network group of the remote address
+    pub network_group: Vec<u8>,
+
+    /// When the connection was established
+    pub connected_since: Instant,
+
+    /// The lowest time it took the peer to respond to a request
+    pub min_response_time: Option<Duration>,
+
+    /// When the peer last delivered a block that was new to the local node
+    pub last_block_time: Option<Instant>,
+}
+
+impl ConnectionInfo {
+    pub fn new(outbound: bool, network_group: Vec<u8>, now: Instant) -> Self {
+        Self {
+            outbound,
+            network_group,
+            connected_since: now,
+            min_response_time: None,
+            last_block_time: None,
+        }
+    }
+
+    /// Record the time it took the peer to respond to a request
+    pub fn response_received(&mut self, response_time: Duration) {
+        self.min_response_time =
+            Some(self.min_response_time.map_or(response_time, |min| min.min(response_time)));
+    }
+
+    /// Record that the peer delivered a new block
+    pub fn block_delivered(&mut self, now: Instant) {
+        self.last_block_time = Some(now);
+    }
+}
+
+/// Remove the `count` candidates with the greatest `key` from the list of candidates
+fn protect<P, K: Ord>(
+    candidates: &mut Vec<(P, &ConnectionInfo)>,
+    count: usize,
+    key: impl FnMut(&(P, &ConnectionInfo)) -> K,
+) {
+    candidates.sort_by_key(key);
+    candidates.truncate(candidates.len().saturating_sub(count));
+}
+
+/// Select the inbound peer to evict from the given candidates
+///
+/// The candidates must not include outbound or reserved peers. `group_key` is a secret
+/// random value that prevents an attacker from predicting which network groups are protected.
+///
+/// The peers are protected from eviction in the following order:
+/// - [`PROTECTED_BY_NETWORK_GROUP`] peers by their keyed network group
+/// - [`PROTECTED_BY_RESPONSE_TIME`] peers with the lowest response time
+/// - [`PROTECTED_BY_BLOCKS`] peers that most recently delivered a new block
+/// - half of the remaining peers with the longest uptime
+///
+/// The youngest peer of the network group with the most remaining peers is then selected.
+/// Returns `None` if all candidates are protected.
+pub fn select_for_eviction<P: Copy>(
+    candidates: &[(P, &ConnectionInfo)],
+    group_key: u64,
+) -> Option<P> {
+    let mut candidates = candidates.to_vec();
+
+    protect(&mut candidates, PROTECTED_BY_NETWORK_GROUP, |(_, info)| {
+        let mut hasher = DefaultHasher::new();
+        group_key.hash(&mut hasher);
+        info.network_group.hash(&mut hasher);
+        hasher.finish()
+    });
+    protect(&mut candidates, PROTECTED_BY_RESPONSE_TIME, |(_, info)| {
+        std::cmp::Reverse(info.min_response_time.unwrap_or(Duration::MAX))
+    });
+    protect(&mut candidates, PROTECTED_BY_BLOCKS, |(_, info)| {
+        info.last_block_time
+    });
+    let half = candidates.len() / 2;
+    protect(&mut candidates, half, |(_, info)| {
+        std::cmp::Reverse(info.connected_since)
+    });
+
+    let mut groups: HashMap<&[u8], Vec<(P, &ConnectionInfo)>> = HashMap::new();
+    for (peer_id, info) in candidates {
+        groups.entry(info.network_group.as_slice()).or_default().push((peer_id, info));
+    }
+
+    // prefer the group with the youngest connection if there are several groups of the same size
+    let youngest = |peers: &Vec<(P, &ConnectionInfo)>| {
+        peers.iter().map(|(_, info)| info.connected_since).max()
+    };
+    groups
+        .into_values()
+        .max_by(|a, b| a.len().cmp(&b.len()).then_with(|| youngest(a).cmp(&youngest(b))))
+        .and_then(|peers| {
+            peers
+                .into_iter()
+                .max_by_key(|(_, info)| info.connected_since)
+                .map(|(peer_id, _)| peer_id)
+        })
+}
@@ -21,42 +21,77 @@
 
 #![allow(rustdoc::private_intra_doc_links)]
 
+pub mod addrman;
+pub mod eviction;
 pub mod helpers;
+pub mod network_time;
 pub mod peerdb;
+pub mod peerdb_storage;
+pub mod rate_limiter;
+pub mod stats;
 
 use std::{
-    collections::{BTreeSet, HashMap},
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
     fmt::Debug,
     str::FromStr,
     sync::Arc,
-    time::Duration,
+    time::{Duration, Instant},
 };
 
+use crypto::random::Rng;
 use futures::FutureExt;
 use tokio::sync::{mpsc, oneshot};
 
 use chainstate::ban_score::BanScore;
-use common::{chain::ChainConfig, primitives::semver::SemVer};
+use common::{chain::ChainConfig, primitives::semver::SemVer, time_getter::TimeGetter};
 use logging::log;
 use utils::ensure;
 
 use crate::{
     config::P2pConfig,
-    error::{P2pError, PeerError, ProtocolError},
+    constants,
+    error::{ConversionError, P2pError, PeerError, ProtocolError},
     event::{PeerManagerEvent, SyncControlEvent},
+    interface::types::{BannedSubnet, ConnectedPeer, MessageStats},
+    message::PeerAddress,
     net::{
         self,
-        types::{Protocol, ProtocolType},
-        AsBannableAddress, ConnectivityService, IsBannableAddress, NetworkingService,
+        types::{Protocol, ProtocolType, Subnet},
+        AsBannableAddress, AsIpAddress, AsNetworkGroup, ConnectivityService, IsBannableAddress,
+        IsIpv6Address, NetworkingService,
     },
 };
 
-/// Maximum number of connections the [`PeerManager`] is allowed to have open
-const MAX_ACTIVE_CONNECTIONS: usize = 128;
-
 /// Lower bound for how often [`PeerManager::heartbeat()`] is called
 const PEER_MGR_HEARTBEAT_INTERVAL: Duration = Duration::from_secs(30);
 
+/// Boot nodes are dialed if the number of active connections is less than this
+const MIN_ACTIVE_CONNECTIONS: usize = 8;
+
+/// How long to wait before dialing a boot node or a reserved node again
+const BOOT_NODE_REDIAL_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Maximum number of addresses sent or accepted in one address list message
+const MAX_ADDR_PER_MESSAGE: usize = 1000;
+
+/// How many addresses a peer may send at once before being rate limited
+const ADDR_RATE_LIMIT_BURST: usize = 1000;
+
+/// How many addresses per second a peer may send after the burst has been used up
+const ADDR_RATE_LIMIT_PER_SEC: f64 = 0.1;
+
+/// Addresses with a timestamp further in the future are considered to have an invalid timestamp
+const MAX_ADDR_TIME_DRIFT: Duration = Duration::from_secs(10 * 60);
+
+/// An address with an invalid timestamp is treated as if it was last seen this long ago
+const INVALID_ADDR_TIME_PENALTY: Duration = Duration::from_secs(5 * 24 * 60 * 60);
+
+/// Addresses that haven't been seen for longer than this are not accepted
+const MAX_ADDR_AGE: Duration = Duration::from_secs(30 * 24 * 60 * 60);
+
+/// How often a feeler connection is made to test an address from the new table
+const FEELER_INTERVAL: Duration = Duration::from_secs(2 * 60);
+
 pub struct PeerManager<T>
 where
     T: NetworkingService,
@@ -65,7 +100,7 @@ where
     chain_config: Arc<ChainConfig>,
 
     /// P2P configuration.
-    _p2p_config: Arc<P2pConfig>,
+    p2p_config: Arc<P2pConfig>,
 
     /// Handle for sending/receiving connectivity events
     peer_connectivity_handle: T::ConnectivityHandle,
@@ -81,6 +116,51 @@ where
 
     /// Peer database
     peerdb: peerdb::PeerDb<T>,
+
+    /// Connected peers whose address list request has already been answered
+    addr_requests_answered: HashSet<T::PeerId>,
+
+    /// Rate limiters for the addresses received from connected peers
+    addr_rate_limiters: HashMap<T::PeerId, rate_limiter::TokenBucket>,
+
+    /// Nodes dialed at startup and when the number of active connections is low
+    boot_nodes: Vec<T::Address>,
+
+    /// Nodes that are always kept connected and are never banned
+    reserved_nodes: HashSet<T::Address>,
+
+    /// Peer IDs of the reserved nodes, learned when the outbound connections to them succeed
+    reserved_peer_ids: HashSet<T::PeerId>,
+
+    /// Configured addresses of the local node that are announced to the peers
+    external_addresses: Vec<T::Address>,
+
+    /// When the boot nodes and the reserved nodes were last dialed
+    last_dialed: HashMap<T::Address, Instant>,
+
+    /// Statistics of the active connections, used to select the inbound peer to evict
+    connections: HashMap<T::PeerId, eviction::ConnectionInfo>,
+
+    /// Evicted peers whose connections are being closed, they don't count towards the limits
+    pending_disconnects: HashSet<T::PeerId>,
+
+    /// Secret key used to randomize the network groups protected from eviction
+    eviction_key: u64,
+
+    /// Statistics of the active connections, reported by the RPC
+    stats: HashMap<T::PeerId, stats::PeerStats>,
+
+    /// Pending feeler connections, closed as soon as they are established
+    feelers: HashSet<T::Address>,
+
+    /// When the last feeler connection was made
+    last_feeler: Instant,
+
+    /// Clock offsets of the outbound peers, used to adjust the local time
+    time_offsets: network_time::TimeOffsets<T::PeerId>,
+
+    /// Source of the local time
+    time_getter: TimeGetter,
 }
 
 impl<T> PeerManager<T>
@@ -96,16 +176,154 @@ where
         handle: T::ConnectivityHandle,
         rx_peer_manager: mpsc::UnboundedReceiver<PeerManagerEvent<T>>,
         tx_sync: mpsc::UnboundedSender<SyncControlEvent<T>>,
-    ) -> Self {
-        Self {
+        network_time: network_time::NetworkTime,
+        time_getter: TimeGetter,
+    ) -> crate::Result<Self> {
+        let boot_nodes = parse_addresses::<T>(&p2p_config.boot_nodes)?;
+        let reserved_nodes = parse_addresses::<T>(&p2p_config.reserved_nodes)?;
+        let external_addresses = parse_addresses::<T>(&p2p_config.external_addresses)?;
+
+        let mut peerdb = peerdb::PeerDb::new(Arc::clone(&p2p_config), time_getter.clone());
+        for subnet in parse_subnets(&p2p_config.banned_subnets)? {
+            peerdb.ban_subnet(subnet, None);
+        }
+        for subnet in parse_subnets(&p2p_config.whitelisted_subnets)? {
+            peerdb.add_to_whitelist(subnet);
+        }
+
+        Ok(Self {
             peer_connectivity_handle: handle,
             rx_peer_manager,
             tx_sync,
-            peerdb: peerdb::PeerDb::new(Arc::clone(&p2p_config)),
+            peerdb,
             pending: HashMap::new(),
+            addr_requests_answered: HashSet::new(),
+            addr_rate_limiters: HashMap::new(),
+            boot_nodes,
+            reserved_nodes: reserved_nodes.into_iter().collect(),
+            reserved_peer_ids: HashSet::new(),
+            external_addresses,
+            last_dialed: HashMap::new(),
+            connections: HashMap::new(),
+            pending_disconnects: HashSet::new(),
+            eviction_key: p2p_config.make_rng("eviction").gen(),
+            stats: HashMap::new(),
+            feelers: HashSet::new(),
+            last_feeler: Instant::now(),
+            time_offsets: network_time::TimeOffsets::new(network_time),
+            time_getter,
             chain_config,
-            _p2p_config: p2p_config,
+            p2p_config,
+        })
+    }
+
+    /// Checks if the peer is one of the reserved nodes
+    ///
+    /// The peer is matched by its peer ID if the reserved node has been connected to before or
+    /// by its IP address, the port of an inbound connection differs from the configured one.
+    fn is_reserved_peer(&self, peer_id: &T::PeerId) -> bool {
+        self.reserved_peer_ids.contains(peer_id)
+            || self
+                .peerdb
+                .peer_address(peer_id)
+                .map_or(false, |address| self.is_reserved_address(address))
+    }
+
+    /// Checks if the address has the same IP address as one of the reserved nodes
+    fn is_reserved_address(&self, address: &T::Address) -> bool {
+        address.is_bannable()
+            && self.reserved_nodes.iter().any(|reserved| {
+                reserved.is_bannable() && reserved.as_bannable() == address.as_bannable()
+            })
+    }
+
+    /// Checks if the peer's address belongs to one of the whitelisted subnets
+    fn is_whitelisted_peer(&self, peer_id: &T::PeerId) -> bool {
+        self.peerdb.peer_address(peer_id).map_or(false, |address| {
+            address.is_bannable() && self.peerdb.is_address_whitelisted(&address.as_bannable())
+        })
+    }
+
+    /// Get the connected peers whose address belongs to the subnet
+    fn active_peers_in_subnet(&self, subnet: &Subnet) -> Vec<T::PeerId> {
+        self.peerdb
+            .active_peers()
+            .into_iter()
+            .filter(|(_, context)| {
+                context.address.as_ref().map_or(false, |address| {
+                    address.is_bannable() && subnet.contains(&address.as_bannable().as_ip_address())
+                })
+            })
+            .map(|(peer_id, _)| *peer_id)
+            .collect()
+    }
+
+    /// Ban a subnet for the given duration or permanently and disconnect its peers
+    ///
+    /// Reserved and whitelisted peers stay connected.
+    async fn ban_subnet(
+        &mut self,
+        subnet: Subnet,
+        duration: Option<Duration>,
+    ) -> crate::Result<()> {
+        self.peerdb.ban_subnet_persistent(subnet, duration);
+
+        for peer_id in self.active_peers_in_subnet(&subnet) {
+            if self.is_reserved_peer(&peer_id) || self.is_whitelisted_peer(&peer_id) {
+                continue;
+            }
+
+            log::info!("disconnect peer {peer_id} from banned subnet {subnet}");
+            self.peer_connectivity_handle.disconnect(peer_id).await?;
+        }
+        Ok(())
+    }
+
+    /// Remove the ban of a subnet
+    fn unban_subnet(&mut self, subnet: Subnet) -> crate::Result<()> {
+        ensure!(
+            self.peerdb.unban_subnet(&subnet),
+            P2pError::PeerError(PeerError::SubnetNotBanned(subnet.to_string())),
+        );
+        Ok(())
+    }
+
+    /// Get the banned subnets along with the time their bans end at
+    fn banned_subnets(&mut self) -> Vec<BannedSubnet> {
+        self.peerdb
+            .banned_subnets()
+            .into_iter()
+            .map(|(subnet, banned_till)| BannedSubnet {
+                subnet: subnet.to_string(),
+                banned_till: banned_till.map(|till| till.as_secs()),
+            })
+            .collect()
+    }
+
+    /// Add a subnet to the whitelist
+    fn add_to_whitelist(&mut self, subnet: Subnet) -> crate::Result<()> {
+        self.peerdb.add_to_whitelist_persistent(subnet);
+        self.whitelist_changed(&subnet)
+    }
+
+    /// Remove a subnet from the whitelist
+    fn remove_from_whitelist(&mut self, subnet: Subnet) -> crate::Result<()> {
+        ensure!(
+            self.peerdb.remove_from_whitelist(&subnet),
+            P2pError::PeerError(PeerError::SubnetNotWhitelisted(subnet.to_string())),
+        );
+        self.whitelist_changed(&subnet)
+    }
+
+    /// Let the sync manager know which of the connected peers of the subnet are whitelisted now
+    fn whitelist_changed(&self, subnet: &Subnet) -> crate::Result<()> {
+        for peer_id in self.active_peers_in_subnet(subnet) {
+            self.tx_sync.send(SyncControlEvent::Whitelisted(
+                peer_id,
+                self.is_whitelisted_peer(&peer_id),
+            ))?;
         }
+        Ok(())
     }
 
     /// Update the list of known peers or known peer's list of addresses
@@ -163,19 +381,13 @@ where
         version == self.chain_config.version()
     }
 
-    /// Handle connection established event
-    ///
-    /// The event is received from the networking backend and it's either a result of an incoming
-    /// connection from a remote peer or a response to an outbound connection that was initiated
-    /// by the node as result of the peer manager maintenance.
-    fn accept_connection(
-        &mut self,
-        address: T::Address,
-        info: net::types::PeerInfo<T>,
-    ) -> crate::Result<()> {
-        let peer_id = info.peer_id;
-        log::debug!("peer {peer_id} connected, address {address:?}, {info}");
+    /// Verify that the negotiated protocol version is still supported by the local node
+    fn validate_protocol_version(&self, protocol_version: u32) -> bool {
+        protocol_version >= constants::MIN_PROTOCOL_VERSION
+    }
 
+    /// Verify that the peer is on the same network and speaks a compatible protocol
+    fn validate_peer_info(&self, info: &net::types::PeerInfo<T>) -> crate::Result<()> {
         ensure!(
             info.magic_bytes == *self.chain_config.magic_bytes(),
             P2pError::ProtocolError(ProtocolError::DifferentNetwork(
@@ -190,17 +402,48 @@ where
                 info.version
             ))
         );
+        ensure!(
+            self.validate_protocol_version(info.protocol_version),
+            P2pError::ProtocolError(ProtocolError::UnsupportedProtocolVersion(
+                constants::MIN_PROTOCOL_VERSION,
+                info.protocol_version,
+            ))
+        );
         ensure!(
             self.validate_supported_protocols(&info.protocols),
             P2pError::ProtocolError(ProtocolError::Incompatible),
         );
+        Ok(())
+    }
+
+    /// Handle connection established event
+    ///
+    /// The event is received from the networking backend and it's either a result of an incoming
+    /// connection from a remote peer or a response to an outbound connection that was initiated
+    /// by the node as result of the peer manager maintenance.
+    fn accept_connection(
+        &mut self,
+        address: T::Address,
+        info: net::types::PeerInfo<T>,
+    ) -> crate::Result<()> {
+        let peer_id = info.peer_id;
+        log::debug!("peer {peer_id} connected, address {address:?}, {info}");
+
+        self.validate_peer_info(&info)?;
         ensure!(
             !self.peerdb.is_active_peer(&info.peer_id),
             P2pError::PeerError(PeerError::PeerAlreadyExists),
         );
 
+        let services = info.services;
+        let connected_since = self.time_getter.get_time();
         self.peerdb.peer_connected(address, info);
-        self.tx_sync.send(SyncControlEvent::Connected(peer_id)).map_err(P2pError::from)
+        self.stats.insert(peer_id, stats::PeerStats::new(connected_since));
+        self.tx_sync.send(SyncControlEvent::Connected(peer_id, services))?;
+        if self.is_whitelisted_peer(&peer_id) {
+            self.tx_sync.send(SyncControlEvent::Whitelisted(peer_id, true))?;
+        }
+        Ok(())
     }
 
     /// Validate inbound peer connection
@@ -209,16 +452,20 @@ where
     /// on to the generic connection validator as these connections haven't gone
     /// through the same validation as outbound connections.
     ///
-    /// This function verifies that neither address the nor the peer ID are on the
-    /// list of banned IPs/peer IDs. It also checks that the maximum number of
-    /// connections `PeerManager` is configured to have has not been reached.
-    fn accept_inbound_connection(
+    /// This function verifies that the peer is valid, not already connected and that neither
+    /// the address nor the peer ID are on the list of banned IPs/peer IDs. Only then it checks
+    /// that the maximum number of connections `PeerManager` is configured to have has not been
+    /// reached and if it has, tries to evict one of the existing inbound peers to make room
+    /// for the new one.
+    async fn accept_inbound_connection(
         &mut self,
         address: T::Address,
         info: net::types::PeerInfo<T>,
     ) -> crate::Result<()> {
         log::debug!("validate inbound connection, inbound address {address:?}");
 
+        // the new peer must be valid before any existing peer is evicted to make room for it
+        self.validate_peer_info(&info)?;
         ensure!(
             !self.peerdb.is_active_peer(&info.peer_id),
             P2pError::PeerError(PeerError::PeerAlreadyExists),
@@ -236,16 +483,141 @@ where
             P2pError::PeerError(PeerError::BannedAddress(address.to_string())),
         );
 
-        // if the maximum number of connections is reached, the connection cannot be
-        // accepted even if it's valid. The peer is still reported to the PeerDb which
-        // knows of all peers and later on if the number of connections falls below
-        // the desired threshold, `PeerManager::heartbeat()` may connect to this peer.
-        if self.peerdb.active_peer_count() >= MAX_ACTIVE_CONNECTIONS {
-            self.peerdb.register_peer_info(address, info);
-            return Err(P2pError::PeerError(PeerError::TooManyPeers));
+        // if the maximum number of connections is reached and no inbound peer can be evicted,
+        // the connection cannot be accepted even if it's valid. The peer is still reported
+        // to the PeerDb which knows of all peers and later on if the number of connections
+        // falls below the desired threshold, `PeerManager::heartbeat()` may connect to this peer.
+        let inbound_count = self.connections.values().filter(|info| !info.outbound).count();
+        if self.active_peer_count() >= *self.p2p_config.max_connections
+            || inbound_count >= *self.p2p_config.max_inbound_connections
+        {
+            match self.select_for_eviction() {
+                Some(evicted) => {
+                    log::info!(
+                        "evict peer {evicted} to make room for peer {}",
+                        info.peer_id
+                    );
+                    self.evict(evicted).await?;
+                }
+                None => {
+                    self.peerdb.register_peer_info(address, info);
+                    return Err(P2pError::PeerError(PeerError::TooManyPeers));
+                }
+            }
+        }
+
+        let peer_id = info.peer_id;
+        let network_group = address.network_group();
+        self.accept_connection(address, info)?;
+        self.connections.insert(
+            peer_id,
+            eviction::ConnectionInfo::new(false, network_group, Instant::now()),
+        );
+        Ok(())
+    }
+
+    /// Handle an outbound connection that was accepted by the remote peer
+    fn accept_outbound_connection(
+        &mut self,
+        address: T::Address,
+        info: net::types::PeerInfo<T>,
+    ) -> crate::Result<()> {
+        let peer_id = info.peer_id;
+        let peer_time = info.time;
+        let network_group = address.network_group();
+        let reserved = self.reserved_nodes.contains(&address);
+        self.accept_connection(address.clone(), info)?;
+        if reserved {
+            self.reserved_peer_ids.insert(peer_id);
+        }
+        self.peerdb.mark_address_tried(address, peer_id);
+        if let Some(peer_time) = peer_time {
+            match network_time::clock_offset(peer_time, self.time_getter.get_time()) {
+                Some(offset) => self.time_offsets.add(peer_id, offset),
+                None => log::debug!("ignore the invalid time {peer_time} of peer {peer_id}"),
+            }
+        }
+        self.connections.insert(
+            peer_id,
+            eviction::ConnectionInfo::new(true, network_group, Instant::now()),
+        );
+        Ok(())
+    }
+
+    /// Handle a feeler connection that was accepted by the remote peer
+    ///
+    /// If the peer passes the same validation as regular connections, the address is moved into
+    /// the tried table. The connection is closed right away, unless the peer is already connected
+    /// over a regular connection, which would be closed together with it.
+    async fn accept_feeler_connection(
+        &mut self,
+        address: T::Address,
+        info: net::types::PeerInfo<T>,
+    ) -> crate::Result<()> {
+        let peer_id = info.peer_id;
+        log::debug!("feeler connection to {address:?} succeeded, peer {peer_id}");
+
+        if self.peerdb.is_active_peer(&peer_id) {
+            log::debug!("feeler peer {peer_id} is already connected");
+            self.peerdb.remove_pending(&address);
+            return Ok(());
+        }
+
+        let result = self.validate_peer_info(&info);
+        if result.is_ok() {
+            self.peerdb.register_peer_info(address.clone(), info);
+            self.peerdb.mark_address_tried(address.clone(), peer_id);
         }
+        self.peerdb.remove_pending(&address);
+        self.peer_connectivity_handle.disconnect(peer_id).await?;
+        result
+    }
+
+    /// Returns the network group an outbound connection to the address counts towards
+    ///
+    /// Local and private addresses are exempt from the grouping, as they usually belong to the
+    /// operator's own nodes and would otherwise share a single group.
+    fn outbound_network_group(address: &T::Address) -> Option<Vec<u8>> {
+        let is_local =
+            address.is_bannable() && net::is_local_ip(&address.as_bannable().as_ip_address());
+        (!is_local).then(|| address.network_group())
+    }
 
-        self.accept_connection(address, info)
+    /// Get the number of active peers, not counting the evicted peers that are being disconnected
+    fn active_peer_count(&self) -> usize {
+        self.peerdb
+            .active_peers()
+            .into_iter()
+            .filter(|(peer_id, _)| !self.pending_disconnects.contains(peer_id))
+            .count()
+    }
+
+    /// Disconnect an evicted peer
+    ///
+    /// The peer is marked as pending disconnect right away, so it no longer counts towards
+    /// the connection limits and it can't be selected for eviction again while the connection
+    /// is being closed.
+    async fn evict(&mut self, peer_id: T::PeerId) -> crate::Result<()> {
+        self.pending_disconnects.insert(peer_id);
+        self.connections.remove(&peer_id);
+        self.peer_connectivity_handle.disconnect(peer_id).await
+    }
+
+    /// Select an inbound peer to evict, see [`eviction::select_for_eviction()`]
+    ///
+    /// Outbound peers, reserved peers and whitelisted peers are never evicted.
+    fn select_for_eviction(&self) -> Option<T::PeerId> {
+        let candidates = self
+            .connections
+            .iter()
+            .filter(|(peer_id, info)| {
+                !info.outbound
+                    && !self.is_reserved_peer(peer_id)
+                    && !self.is_whitelisted_peer(peer_id)
+            })
+            .map(|(peer_id, info)| (*peer_id, info))
+            .collect::<Vec<_>>();
+        eviction::select_for_eviction(&candidates, self.eviction_key)
     }
 
     /// Close connection to a remote peer
@@ -258,15 +630,205 @@ where
 
         self.tx_sync.send(SyncControlEvent::Disconnected(peer_id))?;
         self.peerdb.peer_disconnected(&peer_id);
+        self.addr_requests_answered.remove(&peer_id);
+        self.addr_rate_limiters.remove(&peer_id);
+        self.connections.remove(&peer_id);
+        self.pending_disconnects.remove(&peer_id);
+        self.stats.remove(&peer_id);
+        self.time_offsets.remove(&peer_id);
+        Ok(())
+    }
+
+    /// Collect the information and statistics of the connected peers
+    ///
+    /// The ping latency of a peer is left empty if the backend fails to report it.
+    async fn peer_info(&mut self) -> Vec<ConnectedPeer> {
+        let mut peers = self
+            .peerdb
+            .active_peers()
+            .into_iter()
+            .map(|(peer_id, context)| {
+                let stats = self.stats.get(peer_id);
+                let peer = ConnectedPeer {
+                    peer_id: peer_id.to_string(),
+                    address: context.address.as_ref().map(ToString::to_string),
+                    inbound: self.connections.get(peer_id).map_or(false, |info| !info.outbound),
+                    connected_since: stats.map_or(0, |stats| stats.connected_since.as_secs()),
+                    protocol_version: context.info.protocol_version,
+                    user_agent: context.info.agent.clone(),
+                    best_block: stats.and_then(|stats| stats.best_block),
+                    ban_score: context.score,
+                    ping_latency_ms: None,
+                    sent: stats.map_or_else(Default::default, |stats| to_named_stats(&stats.sent)),
+                    received: stats
+                        .map_or_else(Default::default, |stats| to_named_stats(&stats.received)),
+                };
+                (*peer_id, peer)
+            })
+            .collect::<Vec<_>>();
+
+        for (peer_id, peer) in peers.iter_mut() {
+            match self.peer_connectivity_handle.ping_latency(*peer_id).await {
+                Ok(latency) => {
+                    peer.ping_latency_ms = latency.map(|latency| latency.as_millis() as u64)
+                }
+                Err(err) => log::debug!("failed to get the ping latency of peer {peer_id}: {err}"),
+            }
+        }
+
+        peers.into_iter().map(|(_, peer)| peer).collect()
+    }
+
+    /// Get the addresses to send to a peer in response to its address list request
+    ///
+    /// The configured external addresses of the local node and the ones discovered by the
+    /// networking backend are always included so that the peers learn where the local node
+    /// can be reached. The address the local socket is bound to isn't announced, it's usually
+    /// not reachable from the outside. Each peer gets its request answered only once per
+    /// connection, subsequent requests receive an empty response.
+    async fn get_addresses(&mut self, peer_id: T::PeerId) -> crate::Result<Vec<PeerAddress>> {
+        if !self.addr_requests_answered.insert(peer_id) {
+            log::debug!("peer {peer_id} has already received the address list");
+            return Ok(vec![]);
+        }
+
+        let now = self.time_getter.get_time();
+
+        let mut external_addresses = self.external_addresses.clone();
+        for address in self.peer_connectivity_handle.external_addresses().await? {
+            if !external_addresses.contains(&address) {
+                external_addresses.push(address);
+            }
+        }
+        let local_peer_id = self.peer_connectivity_handle.peer_id().to_string();
+        let local_addresses = external_addresses
+            .into_iter()
+            .take(MAX_ADDR_PER_MESSAGE)
+            .map(|address| {
+                PeerAddress::new(local_peer_id.clone(), address.to_string(), now.as_secs())
+            })
+            .collect::<Vec<_>>();
+
+        let limit = MAX_ADDR_PER_MESSAGE - local_addresses.len();
+        let known_addresses = self
+            .peerdb
+            .known_addresses(limit, now)
+            .into_iter()
+            .filter(|(id, _, _)| *id != peer_id)
+            .map(|(id, address, last_seen)| {
+                PeerAddress::new(id.to_string(), address.to_string(), last_seen.as_secs())
+            });
+
+        Ok(local_addresses.into_iter().chain(known_addresses).collect())
+    }
+
+    /// Handle the addresses received from a peer
+    ///
+    /// Sending more than [`MAX_ADDR_PER_MESSAGE`] addresses in one message is a protocol
+    /// violation. Addresses exceeding the rate limit of the peer are silently dropped unless
+    /// the peer is whitelisted.
+    fn addresses_received(
+        &mut self,
+        peer_id: T::PeerId,
+        addresses: Vec<PeerAddress>,
+    ) -> crate::Result<()> {
+        ensure!(
+            addresses.len() <= MAX_ADDR_PER_MESSAGE,
+            P2pError::ProtocolError(ProtocolError::TooManyAddresses(addresses.len())),
+        );
+
+        let now = Instant::now();
+        let accepted = if self.is_whitelisted_peer(&peer_id) {
+            addresses.len()
+        } else {
+            self.addr_rate_limiters
+                .entry(peer_id)
+                .or_insert_with(|| {
+                    rate_limiter::TokenBucket::new(
+                        ADDR_RATE_LIMIT_BURST,
+                        ADDR_RATE_LIMIT_PER_SEC,
+                        now,
+                    )
+                })
+                .take(addresses.len(), now)
+        };
+        if accepted < addresses.len() {
+            log::debug!(
+                "peer {peer_id} exceeded the address rate limit, {} addresses ignored",
+                addresses.len() - accepted
+            );
+        }
+
+        let now = self.time_getter.get_time();
+        let source = self.peerdb.peer_address(&peer_id).cloned();
+        addresses
+            .into_iter()
+            .take(accepted)
+            .for_each(|address| self.address_received(address, source.clone(), now));
+
         Ok(())
     }
 
+    /// Validate an address received from a peer and pass it on to the `PeerDb`
+    ///
+    /// Addresses that can't be parsed, that point to the local node or to a banned address or
+    /// that haven't been seen for too long are ignored. `source` is the address of the peer
+    /// that sent the address, if known.
+    fn address_received(
+        &mut self,
+        address: PeerAddress,
+        source: Option<T::Address>,
+        now: Duration,
+    ) {
+        let (peer_id, addr) = match (
+            address.peer_id().parse::<T::PeerId>(),
+            address.address().parse::<T::Address>(),
+        ) {
+            (Ok(peer_id), Ok(addr)) => (peer_id, addr),
+            _ => {
+                log::trace!("ignoring invalid address {address:?}");
+                return;
+            }
+        };
+
+        if peer_id == *self.peer_connectivity_handle.peer_id()
+            || !addr.is_bannable()
+            || self.peerdb.is_address_banned(&addr.as_bannable())
+        {
+            return;
+        }
+
+        let mut last_seen = Duration::from_secs(address.timestamp());
+        if last_seen > now + MAX_ADDR_TIME_DRIFT {
+            last_seen = now.saturating_sub(INVALID_ADDR_TIME_PENALTY);
+        }
+        if now.saturating_sub(last_seen) > MAX_ADDR_AGE {
+            return;
+        }
+
+        let (ip4, ip6) = if addr.is_ipv6() {
+            (vec![], vec![addr.clone()])
+        } else {
+            (vec![addr.clone()], vec![])
+        };
+        self.peerdb
+            .peer_discovered_from(&net::types::AddrInfo { peer_id, ip4, ip6 }, source.as_ref());
+        self.peerdb.address_seen(addr, last_seen);
+    }
+
     /// Adjust peer score
     ///
     /// If after adjustment the peer score is more than the ban threshold, the peer is banned
     /// which makes the `PeerDb` mark is banned and prevents any further connections with the peer
     /// and also bans the peer in the networking backend.
     async fn adjust_peer_score(&mut self, peer_id: T::PeerId, score: u32) -> crate::Result<()> {
+        if self.is_reserved_peer(&peer_id) || self.is_whitelisted_peer(&peer_id) {
+            log::debug!(
+                "ignoring score adjustment {score} for reserved/whitelisted peer {peer_id}"
+            );
+            return Ok(());
+        }
+
         log::debug!("adjusting score for peer {peer_id}, adjustment {score}");
 
         if self.peerdb.adjust_peer_score(&peer_id, score) {
@@ -288,6 +850,7 @@ where
         if let Some(Some(channel)) = self.pending.remove(&address) {
             channel.send(Err(error)).map_err(|_| P2pError::ChannelClosed)?;
         }
+        self.feelers.remove(&address);
 
         self.peerdb.report_outbound_failure(address);
         Ok(())
@@ -321,6 +884,43 @@ where
         self.peer_connectivity_handle.connect(address).await
     }
 
+    /// Dial the given boot/reserved node addresses
+    ///
+    /// Addresses that are already connected, that are being connected to or that have been
+    /// dialed less than [`BOOT_NODE_REDIAL_INTERVAL`] ago are skipped.
+    async fn dial_nodes(&mut self, addresses: Vec<T::Address>) -> crate::Result<()> {
+        let now = Instant::now();
+        let connected = self
+            .peerdb
+            .active_peers()
+            .into_iter()
+            .filter_map(|(_, context)| context.address.clone())
+            .collect::<HashSet<_>>();
+
+        for address in addresses {
+            let recently_dialed = self.last_dialed.get(&address).map_or(false, |dialed| {
+                now.duration_since(*dialed) < BOOT_NODE_REDIAL_INTERVAL
+            });
+            if connected.contains(&address)
+                || self.pending.contains_key(&address)
+                || recently_dialed
+            {
+                continue;
+            }
+
+            log::debug!("dial boot/reserved node at address {address:?}");
+            self.last_dialed.insert(address.clone(), now);
+            match self.connect(address.clone()).await {
+                Ok(_) => {
+                    self.pending.insert(address, None);
+                }
+                Err(err) => self.handle_result(None, Err(err)).await?,
+            }
+        }
+
+        Ok(())
+    }
+
     /// Maintains the peer manager state.
     ///
     /// `PeerManager::heartbeat()` is called every time a network/control event is received
@@ -334,26 +934,50 @@ where
     /// low-reputation peers and establishing new connections with peers that have higher
     /// reputation. It also updates peer scores and forgets those peers that are no longer needed.
     ///
-    /// TODO: IP address diversity check?
-    /// TODO: exploratory peer connections?
     /// TODO: close connection with low-score peers in favor of peers with higher score?
     ///
-    /// The process starts by first checking if the number of active connections is less than
-    /// the number of desired connections and there are available peers, the function tries to
-    /// establish new connections. After that it updates the peer scores and discards any records
-    /// that no longer need to be stored.
+    /// The process starts by first checking if the number of outbound connections is less than
+    /// the configured outbound target and there are available peers, the function tries to
+    /// establish new connections. At most one outbound connection is made to each network group.
+    /// If all outbound slots are taken, a short-lived feeler connection is made now and then to
+    /// an address from the new table to test it. After that it updates the peer scores and
+    /// discards any records that no longer need to be stored.
     async fn heartbeat(&mut self) -> crate::Result<()> {
         // TODO: check when was the last update and exit early if this update is to soon
 
+        let reserved_nodes = self.reserved_nodes.iter().cloned().collect();
+        self.dial_nodes(reserved_nodes).await?;
+
+        if self.active_peer_count() < MIN_ACTIVE_CONNECTIONS {
+            self.dial_nodes(self.boot_nodes.clone()).await?;
+        }
+
+        // feeler connections don't occupy the outbound slots
+        let pending_count = self.pending.len().saturating_sub(self.feelers.len());
+        let outbound_count = self.connections.values().filter(|info| info.outbound).count();
         let npeers = std::cmp::min(
-            self.peerdb.idle_peer_count(),
-            MAX_ACTIVE_CONNECTIONS
-                .saturating_sub(self.peerdb.idle_peer_count())
-                .saturating_sub(self.pending.len()),
+            self.p2p_config
+                .max_outbound_connections
+                .saturating_sub(outbound_count + pending_count),
+            self.p2p_config
+                .max_connections
+                .saturating_sub(self.active_peer_count() + pending_count),
         );
 
+        // at most one outbound connection is made to each network group so that a single
+        // subnet can't take over all outbound slots
+        let mut excluded_groups = self
+            .connections
+            .iter()
+            .filter(|(_, info)| info.outbound)
+            .filter_map(|(peer_id, _)| self.peerdb.peer_address(peer_id))
+            .chain(self.pending.keys())
+            .filter_map(Self::outbound_network_group)
+            .collect::<BTreeSet<_>>();
+
         for _ in 0..npeers {
-            if let Some(addr) = self.peerdb.take_best_peer_addr()? {
+            if let Some(addr) = self.peerdb.take_best_peer_addr(&excluded_groups)? {
+                excluded_groups.extend(Self::outbound_network_group(&addr));
                 match self.connect(addr.clone()).await {
                     Ok(_) => {
                         self.pending.insert(addr, None);
@@ -366,6 +990,23 @@ where
             }
         }
 
+        if npeers == 0 && self.last_feeler.elapsed() >= FEELER_INTERVAL {
+            self.last_feeler = Instant::now();
+            if let Some(addr) = self.peerdb.take_feeler_addr()? {
+                log::debug!("making a feeler connection to {addr:?}");
+                match self.connect(addr.clone()).await {
+                    Ok(_) => {
+                        self.pending.insert(addr.clone(), None);
+                        self.feelers.insert(addr);
+                    }
+                    Err(err) => {
+                        self.peerdb.report_outbound_failure(addr);
+                        self.handle_result(None, Err(err)).await?;
+                    }
+                }
+            }
+        }
+
         // TODO: update peer scores
 
         Ok(())
@@ -422,6 +1063,9 @@ where
     /// This is done to prevent the `PeerManager` from stalling in case the network doesn't
     /// have any events.
     pub async fn run(&mut self) -> crate::Result<void::Void> {
+        // dial the boot nodes and the reserved nodes right away
+        self.heartbeat().await?;
+
         loop {
             tokio::select! {
                 event = self.rx_peer_manager.recv().fuse() => match event.ok_or(P2pError::ChannelClosed)? {
@@ -450,8 +1094,31 @@ where
                             .send(self.adjust_peer_score(peer_id, score).await)
                             .map_err(|_| P2pError::ChannelClosed)?;
                     }
+                    PeerManagerEvent::BanSubnet(subnet, duration, response) => {
+                        let res = self.ban_subnet(subnet, duration).await;
+                        response.send(res).map_err(|_| P2pError::ChannelClosed)?;
+                    }
+                    PeerManagerEvent::UnbanSubnet(subnet, response) => {
+                        let res = self.unban_subnet(subnet);
+                        response.send(res).map_err(|_| P2pError::ChannelClosed)?;
+                    }
+                    PeerManagerEvent::GetBannedSubnets(response) => {
+                        response.send(self.banned_subnets()).map_err(|_| P2pError::ChannelClosed)?;
+                    }
+                    PeerManagerEvent::AddToWhitelist(subnet, response) => {
+                        let res = self.add_to_whitelist(subnet);
+                        response.send(res).map_err(|_| P2pError::ChannelClosed)?;
+                    }
+                    PeerManagerEvent::RemoveFromWhitelist(subnet, response) => {
+                        let res = self.remove_from_whitelist(subnet);
+                        response.send(res).map_err(|_| P2pError::ChannelClosed)?;
+                    }
+                    PeerManagerEvent::GetWhitelist(response) => {
+                        let whitelist = self.peerdb.whitelist().iter().map(ToString::to_string).collect();
+                        response.send(whitelist).map_err(|_| P2pError::ChannelClosed)?;
+                    }
                     PeerManagerEvent::GetPeerCount(response) => {
-                        response.send(self.peerdb.active_peer_count()).map_err(|_| P2pError::ChannelClosed)?;
+                        response.send(self.active_peer_count()).map_err(|_| P2pError::ChannelClosed)?;
                     }
                     PeerManagerEvent::GetBindAddress(response) => {
                         let addr = self.peer_connectivity_handle.local_addr();
@@ -462,6 +1129,17 @@ where
                     PeerManagerEvent::GetPeerId(response) => response
                         .send(self.peer_connectivity_handle.peer_id().to_string())
                         .map_err(|_| P2pError::ChannelClosed)?,
+                    PeerManagerEvent::GetAddresses(peer_id, response) => {
+                        let addresses = self.get_addresses(peer_id).await?;
+                        response.send(addresses).map_err(|_| P2pError::ChannelClosed)?;
+                    }
+                    PeerManagerEvent::AddressesReceived(peer_id, addresses, response) => {
+                        log::debug!("received {} addresses from peer {peer_id}", addresses.len());
+
+                        response
+                            .send(self.addresses_received(peer_id, addresses))
+                            .map_err(|_| P2pError::ChannelClosed)?;
+                    }
                     PeerManagerEvent::GetConnectedPeers(response) => {
                         let peers = self.peerdb
                             .active_peers()
@@ -470,13 +1148,42 @@ where
                             .collect::<Vec<_>>();
                         response.send(peers).map_err(|_| P2pError::ChannelClosed)?
                     }
+                    PeerManagerEvent::GetPeerInfo(response) => {
+                        let peers = self.peer_info().await;
+                        response.send(peers).map_err(|_| P2pError::ChannelClosed)?
+                    }
+                    PeerManagerEvent::BestKnownBlock(peer_id, block_id) => {
+                        if let Some(stats) = self.stats.get_mut(&peer_id) {
+                            stats.best_block = Some(block_id);
+                        }
+                    }
+                    PeerManagerEvent::MessageSent(peer_id, kind, bytes) => {
+                        if let Some(stats) = self.stats.get_mut(&peer_id) {
+                            stats.message_sent(kind, bytes);
+                        }
+                    }
+                    PeerManagerEvent::MessageReceived(peer_id, kind, bytes) => {
+                        if let Some(stats) = self.stats.get_mut(&peer_id) {
+                            stats.message_received(kind, bytes);
+                        }
+                    }
+                    PeerManagerEvent::ResponseTime(peer_id, response_time) => {
+                        if let Some(info) = self.connections.get_mut(&peer_id) {
+                            info.response_received(response_time);
+                        }
+                    }
+                    PeerManagerEvent::BlockDelivered(peer_id) => {
+                        if let Some(info) = self.connections.get_mut(&peer_id) {
+                            info.block_delivered(Instant::now());
+                        }
+                    }
                 },
                 event = self.peer_connectivity_handle.poll_next() => match event {
                     Ok(event) => match event {
                         net::types::ConnectivityEvent::InboundAccepted { address, peer_info } => {
                             let peer_id = peer_info.peer_id;
 
-                            match self.accept_inbound_connection(address, peer_info) {
+                            match self.accept_inbound_connection(address, peer_info).await {
                                 Ok(_) => {},
                                 Err(P2pError::ChannelClosed) => return Err(P2pError::ChannelClosed),
                                 Err(P2pError::PeerError(err)) => {
@@ -494,7 +1201,11 @@ where
                         }
                         net::types::ConnectivityEvent::OutboundAccepted { address, peer_info } => {
                             let peer_id = peer_info.peer_id;
-                            let res = self.accept_connection(address.clone(), peer_info);
+                            let res = if self.feelers.remove(&address) {
+                                self.accept_feeler_connection(address.clone(), peer_info).await
+                            } else {
+                                self.accept_outbound_connection(address.clone(), peer_info)
+                            };
                             self.handle_result(Some(peer_id), res).await?;
 
                             match self.pending.remove(&address) {
@@ -536,5 +1247,30 @@ where
     }
 }
 
+/// Convert the per-message-type statistics into the form returned by the RPC
+fn to_named_stats(stats: &BTreeMap<&'static str, MessageStats>) -> BTreeMap<String, MessageStats> {
+    stats.iter().map(|(kind, stats)| (kind.to_string(), *stats)).collect()
+}
+
+/// Parse the boot/reserved node addresses from the configuration
+fn parse_addresses<T: NetworkingService>(addresses: &[String]) -> crate::Result<Vec<T::Address>> {
+    addresses
+        .iter()
+        .map(|address| {
+            address.parse::<T::Address>().map_err(|_| {
+                P2pError::ConversionError(ConversionError::InvalidAddress(address.clone()))
+            })
+        })
+        .collect()
+}
+
+/// Parse the banned/whitelisted subnets from the configuration
+fn parse_subnets(subnets: &[String]) -> crate::Result<Vec<Subnet>> {
+    subnets
+        .iter()
+        .map(|subnet| subnet.parse::<Subnet>().map_err(P2pError::ConversionError))
+        .collect()
+}
+
 #[cfg(test)]
 mod tests;
@@ -0,0 +1,220 @@
+// Copyright (c) 2022 RBB S.r.l
+// opensource@mintlayer.org
+// SPDX-License-Identifier: MIT
+// Licensed under the MIT License;
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://github.com/mintlayer/mintlayer-core/blob/master/LICENSE
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Network-adjusted time
+//!
+//! Peers report their local time during the handshake. The median offset of the outbound peers'
+//! clocks from the local clock is used to adjust the local clock so that the block timestamps
+//! are checked against the same time as the rest of the network. Only outbound peers are used
+//! because the inbound connections are chosen by the remote side.
+
+use std::{
+    collections::HashMap,
+    hash::Hash,
+    sync::{
+        atomic::{AtomicI64, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use common::time_getter::TimeGetter;
+use logging::log;
+
+/// The local clock is never adjusted by more than this
+const MAX_TIME_OFFSET: Duration = Duration::from_secs(70 * 60);
+
+/// How many outbound peers must report their time before the local clock is adjusted
+const MIN_TIME_SAMPLES: usize = 3;
+
+/// Offset of the network time from the local time, shared between the peer manager and the
+/// subsystems that need the network-adjusted time
+#[derive(Debug, Clone, Default)]
+pub struct NetworkTime {
+    offset: Arc<AtomicI64>,
+}
+
+impl NetworkTime {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Offset of the network time from the local time in seconds
+    pub fn offset(&self) -> i64 {
+        self.offset.load(Ordering::Relaxed)
+    }
+
+    /// Create a time getter that returns the time of `time_getter` adjusted by the offset
+    pub fn adjust(&self, time_getter: TimeGetter) -> TimeGetter {
+        let offset = Arc::clone(&self.offset);
+        TimeGetter::new(Arc::new(move || {
+            adjust_time(time_getter.get_time(), offset.load(Ordering::Relaxed))
+        }))
+    }
+
+    fn set_offset(&self, offset: i64) {
+        self.offset.store(offset, Ordering::Relaxed);
+    }
+}
+
+fn adjust_time(time: Duration, offset: i64) -> Duration {
+    if offset >= 0 {
+        time + Duration::from_secs(offset as u64)
+    } else {
+        time.saturating_sub(Duration::from_secs(offset.unsigned_abs()))
+    }
+}
+
+/// Returns the offset of the peer's clock from the local clock in seconds
+///
+/// `None` is returned if the peer's time, in seconds since the Unix epoch, is too large to be
+/// compared with the local time.
+pub fn clock_offset(peer_time: u64, local_time: Duration) -> Option<i64> {
+    let peer_time = i64::try_from(peer_time).ok()?;
+    let local_time = i64::try_from(local_time.as_secs()).ok()?;
+    Some(peer_time.saturating_sub(local_time))
+}
+
+/// Clock offsets reported by the outbound peers
+pub struct TimeOffsets<P> {
+    network_time: NetworkTime,
+    offsets: HashMap<P, i64>,
+    clock_warning: bool,
+}
+
+impl<P: Eq + Hash> TimeOffsets<P> {
+    pub fn new(network_time: NetworkTime) -> Self {
+        Self {
+            network_time,
+            offsets: HashMap::new(),
+            clock_warning: false,
+        }
+    }
+
+    /// Record the offset of the peer's clock from the local clock in seconds
+    pub fn add(&mut self, peer_id: P, offset: i64) {
+        self.offsets.insert(peer_id, offset);
+        self.update();
+    }
+
+    /// Forget the offset of a disconnected peer
+    pub fn remove(&mut self, peer_id: &P) {
+        if self.offsets.remove(peer_id).is_some() {
+            self.update();
+        }
+    }
+
+    /// Recalculate the network time offset as the median of the recorded offsets
+    ///
+    /// The offset is capped at [`MAX_TIME_OFFSET`]. If the median is larger than that, the local
+    /// clock is most likely wrong and a warning is logged.
+    fn update(&mut self) {
+        if self.offsets.len() < MIN_TIME_SAMPLES {
+            self.network_time.set_offset(0);
+            return;
+        }
+
+        let mut offsets = self.offsets.values().copied().collect::<Vec<_>>();
+        offsets.sort_unstable();
+        let median = offsets[offsets.len() / 2];
+
+        let max_offset = MAX_TIME_OFFSET.as_secs() as i64;
+        let clock_warning = median.abs() > max_offset;
+        if clock_warning && !self.clock_warning {
+            log::warn!(
+                "the local clock differs from the network time by {median} seconds, \
+                check that the date and time of the computer are correct"
+            );
+        }
+        self.clock_warning = clock_warning;
+
+        let offset = median.clamp(-max_offset, max_offset);
+        if offset != self.network_time.offset() {
+            log::debug!("network time offset changed to {offset} seconds");
+        }
+        self.network_time.set_offset(offset);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn make_offsets(network_time: &NetworkTime, offsets: &[i64]) -> TimeOffsets<usize> {
+        let mut time_offsets = TimeOffsets::new(network_time.clone());
+        for (peer_id, offset) in offsets.iter().enumerate() {
+            time_offsets.add(peer_id, *offset);
+        }
+        time_offsets
+    }
+
+    #[test]
+    fn too_few_samples() {
+        let network_time = NetworkTime::new();
+        let _offsets = make_offsets(&network_time, &[100, 200]);
+        assert_eq!(network_time.offset(), 0);
+    }
+
+    #[test]
+    fn median_offset() {
+        let network_time = NetworkTime::new();
+        let mut offsets = make_offsets(&network_time, &[-50, 100, 10, 20, 30000]);
+        assert_eq!(network_time.offset(), 20);
+
+        offsets.remove(&2);
+        assert_eq!(network_time.offset(), 100);
+
+        offsets.remove(&0);
+        offsets.remove(&1);
+        assert_eq!(network_time.offset(), 0);
+    }
+
+    #[test]
+    fn offset_is_capped() {
+        let max_offset = MAX_TIME_OFFSET.as_secs() as i64;
+
+        let network_time = NetworkTime::new();
+        let _offsets = make_offsets(&network_time, &[max_offset * 2; 3]);
+        assert_eq!(network_time.offset(), max_offset);
+
+        let network_time = NetworkTime::new();
+        let _offsets = make_offsets(&network_time, &[-max_offset * 2; 3]);
+        assert_eq!(network_time.offset(), -max_offset);
+    }
+
+    #[test]
+    fn offset_of_peer_clock() {
+        let local_time = Duration::from_secs(1_000_000);
+        assert_eq!(clock_offset(1_000_060, local_time), Some(60));
+        assert_eq!(clock_offset(0, local_time), Some(-1_000_000));
+        assert_eq!(clock_offset(u64::MAX, local_time), None);
+    }
+
+    #[test]
+    fn adjusted_time_getter() {
+        let network_time = NetworkTime::new();
+        let time_getter =
+            network_time.adjust(TimeGetter::new(Arc::new(|| Duration::from_secs(1000))));
+        assert_eq!(time_getter.get_time(), Duration::from_secs(1000));
+
+        let mut offsets = make_offsets(&network_time, &[60, 60, 60]);
+        assert_eq!(time_getter.get_time(), Duration::from_secs(1060));
+
+        for peer_id in 0..3 {
+            offsets.add(peer_id, -2000);
+        }
+        assert_eq!(time_getter.get_time(), Duration::ZERO);
+    }
+}
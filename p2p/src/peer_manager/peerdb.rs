@@ -25,21 +25,29 @@
 //! connected. Idle peers are discovered through various peer discovery mechanisms and they are
 //! used by [`crate::peer_manager::PeerManager::heartbeat()`] to establish new outbound connections
 //! if the actual number of active connection is less than the desired number of connections.
+//! The addresses to dial are selected by the [`AddressManager`] which keeps them bucketed by
+//! their network group.
 //!
 //! TODO: reserved peers
 
 use std::{
-    collections::{hash_map::Entry, BTreeMap, HashMap, HashSet, VecDeque},
+    collections::{hash_map::Entry, BTreeMap, BTreeSet, HashMap, HashSet, VecDeque},
+    net::IpAddr,
     sync::Arc,
-    time::{Duration, SystemTime, UNIX_EPOCH},
+    time::Duration,
 };
 
+use common::time_getter::TimeGetter;
 use logging::log;
 
 use crate::{
     config,
     error::P2pError,
-    net::{types, AsBannableAddress, NetworkingService},
+    net::{
+        types::{self, Subnet},
+        AsBannableAddress, AsIpAddress, AsNetworkGroup, IsBannableAddress, NetworkingService,
+    },
+    peer_manager::{addrman::AddressManager, peerdb_storage::PeerDbStorage},
 };
 
 const BAN_DURATION: Duration = Duration::from_secs(60 * 60 * 24);
@@ -117,20 +125,49 @@ pub struct PeerDb<T: NetworkingService> {
     /// Pending connections
     pending: HashMap<T::Address, T::PeerId>,
 
-    /// Banned addresses along with the duration of the ban.
+    /// Banned subnets along with the duration of the ban.
     ///
     /// The duration represents the `UNIX_EPOCH + duration` time point, so the ban should end
-    /// when `current_time > ban_duration`.
-    banned: BTreeMap<T::BannableAddress, Duration>,
+    /// when `current_time > ban_duration`. Subnets without the duration are banned permanently.
+    /// Banned peers are banned by the subnet of their single address.
+    banned: BTreeMap<Subnet, Option<Duration>>,
+
+    /// Subnets whose peers are never banned
+    whitelist: BTreeSet<Subnet>,
+
+    /// The bans and whitelist entries that are kept across restarts
+    storage: PeerDbStorage,
+
+    /// The time the discovered addresses were last seen, as reported by the peers that
+    /// gossiped them.
+    ///
+    /// The duration represents the `UNIX_EPOCH + duration` time point. Only the addresses
+    /// known to the address manager are kept, they are forgotten together.
+    last_seen: HashMap<T::Address, Duration>,
+
+    /// New and tried tables of the addresses used for outbound connections
+    addrman: AddressManager<T>,
+
+    time_getter: TimeGetter,
 }
 
 impl<T: NetworkingService> PeerDb<T> {
-    pub fn new(p2p_config: Arc<config::P2pConfig>) -> Self {
+    /// Creates the peer database with the bans and whitelist entries saved in the peer database
+    /// file, if any
+    pub fn new(p2p_config: Arc<config::P2pConfig>, time_getter: TimeGetter) -> Self {
+        let mut storage = PeerDbStorage::open((*p2p_config.peerdb_path).clone());
+        storage.remove_expired_bans(time_getter.get_time());
+
         Self {
             peers: Default::default(),
             available: Default::default(),
             pending: Default::default(),
-            banned: Default::default(),
+            banned: storage.banned().clone(),
+            whitelist: storage.whitelist().clone(),
+            storage,
+            last_seen: Default::default(),
+            addrman: AddressManager::new(p2p_config.make_rng("addrman")),
+            time_getter,
             p2p_config,
         }
     }
@@ -142,12 +179,7 @@ impl<T: NetworkingService> PeerDb<T> {
 
     /// Get the number of active peers
     pub fn active_peer_count(&self) -> usize {
-        self.peers
-            .len()
-            .checked_sub(self.pending.len())
-            .and_then(|acc| acc.checked_sub(self.available.len()))
-            .and_then(|acc| acc.checked_sub(self.banned.len()))
-            .expect("`PeerDb` state to be consistent")
+        self.peers.values().filter(|peer| std::matches!(peer, Peer::Active(_))).count()
     }
 
     pub fn active_peers(&self) -> Vec<(&T::PeerId, &PeerContext<T>)> {
@@ -176,21 +208,119 @@ impl<T: NetworkingService> PeerDb<T> {
     }
 
     /// Checks if the given address is banned.
+    ///
+    /// An address is banned if it belongs to any of the banned subnets and to none of the
+    /// whitelisted ones.
     pub fn is_address_banned(&mut self, address: &T::BannableAddress) -> bool {
-        if let Some(banned_till) = self.banned.get(address) {
-            // Check if the ban has expired.
-            let now = SystemTime::now()
-                .duration_since(UNIX_EPOCH)
-                // This can fail only if `SystemTime::now()` returns the time before `UNIX_EPOCH`.
-                .expect("Invalid system time");
-            if now > *banned_till {
-                self.banned.remove(address);
-            } else {
-                return true;
+        self.remove_expired_bans();
+
+        let address = address.as_ip_address();
+        !self.is_ip_whitelisted(&address)
+            && self.banned.keys().any(|subnet| subnet.contains(&address))
+    }
+
+    /// Checks if the given address belongs to any of the whitelisted subnets.
+    pub fn is_address_whitelisted(&self, address: &T::BannableAddress) -> bool {
+        self.is_ip_whitelisted(&address.as_ip_address())
+    }
+
+    fn is_ip_whitelisted(&self, address: &IpAddr) -> bool {
+        self.whitelist.iter().any(|subnet| subnet.contains(address))
+    }
+
+    /// Remove the bans that have expired
+    fn remove_expired_bans(&mut self) {
+        let now = self.time_getter.get_time();
+        self.banned
+            .retain(|_, banned_till| banned_till.map_or(true, |till| now <= till));
+        self.storage.remove_expired_bans(now);
+    }
+
+    /// Ban a subnet for the given duration or permanently if the duration is not given
+    pub fn ban_subnet(&mut self, subnet: Subnet, duration: Option<Duration>) {
+        log::info!("ban subnet {subnet} for {duration:?}");
+        let banned_till = duration.map(|duration| self.time_getter.get_time() + duration);
+        self.banned.insert(subnet, banned_till);
+    }
+
+    /// Ban a subnet like [`Self::ban_subnet`] and save the ban to the peer database file
+    pub fn ban_subnet_persistent(&mut self, subnet: Subnet, duration: Option<Duration>) {
+        self.ban_subnet(subnet, duration);
+        self.storage.ban_subnet(subnet, self.banned[&subnet]);
+    }
+
+    /// Remove the ban of a subnet, returns false if the subnet wasn't banned
+    ///
+    /// The banned peers that belong to the subnet become available for connections again.
+    pub fn unban_subnet(&mut self, subnet: &Subnet) -> bool {
+        self.storage.unban_subnet(subnet);
+        if self.banned.remove(subnet).is_none() {
+            return false;
+        }
+
+        let unbanned = self
+            .peers
+            .iter()
+            .filter_map(|(peer_id, peer)| match peer {
+                Peer::Banned(BannedPeer::Known(PeerContext {
+                    address: Some(address),
+                    ..
+                })) if address.is_bannable()
+                    && subnet.contains(&address.as_bannable().as_ip_address()) =>
+                {
+                    Some(*peer_id)
+                }
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+
+        for peer_id in unbanned {
+            if let Some(Peer::Banned(BannedPeer::Known(mut context))) = self.peers.remove(&peer_id)
+            {
+                log::info!("unban peer {peer_id}");
+                context.score = 0;
+                self.peers.insert(peer_id, Peer::Idle(context));
+                self.available.insert(peer_id);
             }
         }
+        true
+    }
 
-        false
+    /// Get the banned subnets along with the time points their bans end at
+    pub fn banned_subnets(&mut self) -> Vec<(Subnet, Option<Duration>)> {
+        self.remove_expired_bans();
+        self.banned
+            .iter()
+            .map(|(subnet, banned_till)| (*subnet, *banned_till))
+            .collect()
+    }
+
+    /// Add a subnet to the whitelist
+    pub fn add_to_whitelist(&mut self, subnet: Subnet) {
+        log::info!("add subnet {subnet} to the whitelist");
+        self.whitelist.insert(subnet);
+    }
+
+    /// Add a subnet to the whitelist and save it to the peer database file
+    pub fn add_to_whitelist_persistent(&mut self, subnet: Subnet) {
+        self.add_to_whitelist(subnet);
+        self.storage.add_to_whitelist(subnet);
+    }
+
+    /// Remove a subnet from the whitelist, returns false if the subnet wasn't whitelisted
+    pub fn remove_from_whitelist(&mut self, subnet: &Subnet) -> bool {
+        self.storage.remove_from_whitelist(subnet);
+        self.whitelist.remove(subnet)
+    }
+
+    /// Get the whitelisted subnets
+    pub fn whitelist(&self) -> Vec<Subnet> {
+        self.whitelist.iter().copied().collect()
+    }
+
+    /// Get the address of the peer, if known
+    pub fn peer_address(&self, peer_id: &T::PeerId) -> Option<&T::Address> {
+        self.peers.get(peer_id).and_then(|peer| peer.address())
     }
 
     /// Checks if the peer is active.
@@ -198,44 +328,86 @@ impl<T: NetworkingService> PeerDb<T> {
         std::matches!(self.peers.get(peer_id), Some(Peer::Active(_)))
     }
 
-    /// Get socket address of the next best peer (TODO: in terms of peer score)
-    // TODO: rewrite all of this
-    pub fn take_best_peer_addr(&mut self) -> crate::Result<Option<T::Address>> {
-        // TODO: improve peer selection
-        let peer_id = match self.available.iter().next() {
-            Some(peer_id) => *peer_id,
-            None => return Ok(None),
-        };
+    /// Get the address of the next peer to dial
+    ///
+    /// The address is selected from the new and tried tables of the address manager and it must
+    /// not belong to any of the `excluded_groups`, so that the outbound connections are spread
+    /// across different network groups.
+    pub fn take_best_peer_addr(
+        &mut self,
+        excluded_groups: &BTreeSet<Vec<u8>>,
+    ) -> crate::Result<Option<T::Address>> {
+        self.take_addr(false, excluded_groups)
+    }
 
-        match self.peers.get_mut(&peer_id) {
-            Some(Peer::Idle(_info) | Peer::Active(_info)) => {
-                // TODO: implement
-                Ok(None)
-            }
-            Some(Peer::Banned(_info)) => {
-                // TODO: implement
-                Ok(None)
-            }
-            Some(Peer::Discovered(addr_info)) => addr_info.pop_front().map_or(Ok(None), |addr| {
+    /// Get the address for a short-lived feeler connection from the new table
+    pub fn take_feeler_addr(&mut self) -> crate::Result<Option<T::Address>> {
+        self.take_addr(true, &BTreeSet::new())
+    }
+
+    fn take_addr(
+        &mut self,
+        new_only: bool,
+        excluded_groups: &BTreeSet<Vec<u8>>,
+    ) -> crate::Result<Option<T::Address>> {
+        let (available, pending) = (&self.available, &self.pending);
+        let selected = self.addrman.select(new_only, excluded_groups, |address, peer_id| {
+            available.contains(peer_id) && !pending.contains_key(address)
+        });
+
+        match selected {
+            Some((address, peer_id)) => {
+                if !self.peers.contains_key(&peer_id) {
+                    return Err(P2pError::DatabaseFailure);
+                }
                 self.available.remove(&peer_id);
-                self.pending.insert(addr.clone(), peer_id);
-                Ok(Some(addr))
-            }),
-            None => Err(P2pError::DatabaseFailure),
+                self.pending.insert(address.clone(), peer_id);
+                Ok(Some(address))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Move the address an outbound connection succeeded to into the tried table
+    pub fn mark_address_tried(&mut self, address: T::Address, peer_id: T::PeerId) {
+        if let Some(forgotten) = self.addrman.mark_tried(address, peer_id) {
+            self.last_seen.remove(&forgotten);
         }
     }
 
     /// Discover new peer addresses
     pub fn peer_discovered(&mut self, info: &types::AddrInfo<T>) {
+        self.peer_discovered_from(info, None)
+    }
+
+    /// Discover new peer addresses announced by the peer at `source`
+    ///
+    /// The network group of the source selects the buckets of the new table the addresses are
+    /// added to, so one peer can't fill the whole table. Locally discovered addresses use their
+    /// own network group.
+    pub fn peer_discovered_from(&mut self, info: &types::AddrInfo<T>, source: Option<&T::Address>) {
+        for address in info.ip6.iter().chain(info.ip4.iter()) {
+            if address.is_bannable() && self.is_address_banned(&address.as_bannable()) {
+                continue;
+            }
+            let source_group = source.unwrap_or(address).network_group();
+            if let Some(forgotten) = self.addrman.add(address.clone(), info.peer_id, &source_group)
+            {
+                self.last_seen.remove(&forgotten);
+            }
+        }
+
         match self.peers.entry(info.peer_id) {
             Entry::Occupied(mut entry) => match entry.get_mut() {
                 Peer::Discovered(addr_info) => {
-                    // TODO: duplicates
-                    addr_info.extend(info.ip6.clone());
-                    addr_info.extend(info.ip4.clone());
+                    for address in info.ip6.iter().chain(info.ip4.iter()) {
+                        if !addr_info.contains(address) {
+                            addr_info.push_back(address.clone());
+                        }
+                    }
                 }
-                Peer::Idle(_info) | Peer::Active(_info) => {
-                    // TODO: update existing information of a known peer
+                Peer::Idle(context) | Peer::Active(context) => {
+                    context.addresses.extend(info.ip6.iter().chain(info.ip4.iter()).cloned());
                 }
                 Peer::Banned(_info) => {
                     // TODO: update existing information of a known peer
@@ -250,6 +422,52 @@ impl<T: NetworkingService> PeerDb<T> {
         }
     }
 
+    /// Record the time an address was last seen
+    ///
+    /// The most recent time point is kept if the address is reported more than once. Addresses
+    /// unknown to the address manager are ignored.
+    pub fn address_seen(&mut self, address: T::Address, timestamp: Duration) {
+        if self.addrman.table(&address).is_none() {
+            return;
+        }
+        let last_seen = self.last_seen.entry(address).or_insert(timestamp);
+        *last_seen = std::cmp::max(*last_seen, timestamp);
+    }
+
+    /// Get the time an address was last seen, if it has been reported by a peer
+    pub fn address_last_seen(&self, address: &T::Address) -> Option<Duration> {
+        self.last_seen.get(address).copied()
+    }
+
+    /// Get at most `limit` addresses of non-banned peers along with the time they were last seen
+    ///
+    /// Addresses that have no recorded time point (i.e., discovered locally) are reported
+    /// as seen at `now`.
+    pub fn known_addresses(
+        &self,
+        limit: usize,
+        now: Duration,
+    ) -> Vec<(T::PeerId, T::Address, Duration)> {
+        self.peers
+            .iter()
+            .flat_map(|(peer_id, peer)| {
+                let addresses: Box<dyn Iterator<Item = &T::Address>> = match peer {
+                    Peer::Discovered(addresses) => Box::new(addresses.iter()),
+                    Peer::Idle(context) | Peer::Active(context) => {
+                        Box::new(context.addresses.iter())
+                    }
+                    Peer::Banned(_) => Box::new(std::iter::empty()),
+                };
+                addresses.map(move |address| (*peer_id, address))
+            })
+            .take(limit)
+            .map(|(peer_id, address)| {
+                let last_seen = self.last_seen.get(address).copied().unwrap_or(now);
+                (peer_id, address.clone(), last_seen)
+            })
+            .collect()
+    }
+
     /// Expire discovered peer addresses
     pub fn expire_peers(&mut self, _peers: &[types::AddrInfo<T>]) {
         // TODO: implement
@@ -259,10 +477,28 @@ impl<T: NetworkingService> PeerDb<T> {
     ///
     /// When [`crate::peer_manager::PeerManager::heartbeat()`] has initiated an outbound connection
     /// and the connection is refused, it's reported back to the `PeerDb` so it knows to update
-    /// the peer information accordingly by forgetting the address and adjusting the peer score
-    /// appropriately.
-    pub fn report_outbound_failure(&mut self, _address: T::Address) {
-        // TODO: implement
+    /// the peer information accordingly. The peer becomes available again and the address is
+    /// forgotten after too many failed attempts.
+    pub fn report_outbound_failure(&mut self, address: T::Address) {
+        self.remove_pending(&address);
+        if self.addrman.attempt_failed(&address) {
+            self.last_seen.remove(&address);
+        }
+    }
+
+    /// Forget the pending connection to the address
+    ///
+    /// The peer the address was selected for becomes available again, unless it has been
+    /// connected or banned in the meantime.
+    pub fn remove_pending(&mut self, address: &T::Address) {
+        if let Some(peer_id) = self.pending.remove(address) {
+            if std::matches!(
+                self.peers.get(&peer_id),
+                Some(Peer::Discovered(_) | Peer::Idle(_))
+            ) {
+                self.available.insert(peer_id);
+            }
+        }
     }
 
     /// Register peer information to `PeerDb`
@@ -369,6 +605,11 @@ impl<T: NetworkingService> PeerDb<T> {
     }
 
     /// Changes the peer state to `Peer::Banned` and bans it for 24 hours.
+    ///
+    /// IPv4 peers are banned by their address. IPv6 peers are banned by the subnet of the
+    /// configured prefix length, as a single host usually gets a whole /64 and could otherwise
+    /// evade the ban by switching to another address. The ban is saved to the peer database
+    /// file, so that the peer can't get around it by waiting for the node to restart.
     pub fn ban_peer(&mut self, peer_id: &T::PeerId) {
         if let Some(entry) = self.peers.remove(peer_id) {
             let entry = match entry {
@@ -399,17 +640,22 @@ impl<T: NetworkingService> PeerDb<T> {
         if let Some(address) =
             self.peers.get(peer_id).and_then(|p| p.address()).map(|a| a.as_bannable())
         {
-            let ban_till = SystemTime::now()
-                .duration_since(UNIX_EPOCH)
-                // This can fail only if `SystemTime::now()` returns the time before `UNIX_EPOCH`.
-                .expect("Invalid system time")
-                + BAN_DURATION;
-            self.banned.insert(address, ban_till);
+            let subnet = self.ban_subnet_of(address.as_ip_address());
+            self.ban_subnet_persistent(subnet, Some(BAN_DURATION));
         } else {
             log::error!("Failed to get address for peer {}", peer_id);
         }
     }
 
+    /// Returns the subnet a misbehaving peer with the given address is banned by
+    fn ban_subnet_of(&self, address: IpAddr) -> Subnet {
+        match address {
+            IpAddr::V4(_) => Subnet::single(address),
+            IpAddr::V6(_) => Subnet::new(address, *self.p2p_config.ban_ipv6_prefix_len)
+                .unwrap_or_else(|| Subnet::single(address)),
+        }
+    }
+
     /// Adjust peer score
     ///
     /// If the peer is known, update its existing peer score and if it is not
@@ -0,0 +1,167 @@
+// Copyright (c) 2022 RBB S.r.l
+// opensource@mintlayer.org
+// SPDX-License-Identifier: MIT
+// Licensed under the MIT License;
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://github.com/mintlayer/mintlayer-core/blob/master/LICENSE
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Persistent storage of the peer database
+//!
+//! The subnet bans and whitelist entries created over RPC and the automatic bans of misbehaving
+//! peers are saved to a file, so that they survive restarts of the node. The entries given in
+//! the configuration aren't saved.
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use logging::log;
+use serialization::{Decode, Encode};
+
+use crate::net::types::Subnet;
+
+/// Name of the peer database file inside of the data directory
+pub const PEERDB_FILE_NAME: &str = "peerdb.dat";
+
+/// Contents of the peer database file
+///
+/// The subnets are kept in the CIDR notation.
+#[derive(Debug, Default, Encode, Decode)]
+struct StoredEntries {
+    /// Banned subnets along with the time points their bans end at, in seconds since the Unix
+    /// epoch
+    banned: Vec<(String, Option<u64>)>,
+
+    /// Whitelisted subnets
+    whitelist: Vec<String>,
+}
+
+#[derive(Debug, Default)]
+pub struct PeerDbStorage {
+    /// The file the entries are saved to, nothing is saved if not set
+    path: Option<PathBuf>,
+
+    /// Banned subnets along with the `UNIX_EPOCH + duration` time points their bans end at
+    banned: BTreeMap<Subnet, Option<Duration>>,
+
+    /// Whitelisted subnets
+    whitelist: BTreeSet<Subnet>,
+}
+
+impl PeerDbStorage {
+    /// Loads the entries saved in the file, if any
+    ///
+    /// A file that can't be read is logged and the storage starts empty.
+    pub fn open(path: Option<PathBuf>) -> Self {
+        let mut storage = Self {
+            path,
+            ..Default::default()
+        };
+
+        if let Some(path) = storage.path.as_ref().filter(|path| path.exists()) {
+            match load(path) {
+                Ok(entries) => storage.restore(entries),
+                Err(err) => log::warn!("failed to load the peer database from {path:?}: {err}"),
+            }
+        }
+        storage
+    }
+
+    /// Get the saved bans along with the time points they end at
+    pub fn banned(&self) -> &BTreeMap<Subnet, Option<Duration>> {
+        &self.banned
+    }
+
+    /// Get the saved whitelist entries
+    pub fn whitelist(&self) -> &BTreeSet<Subnet> {
+        &self.whitelist
+    }
+
+    pub fn ban_subnet(&mut self, subnet: Subnet, banned_till: Option<Duration>) {
+        self.banned.insert(subnet, banned_till);
+        self.save();
+    }
+
+    pub fn unban_subnet(&mut self, subnet: &Subnet) {
+        if self.banned.remove(subnet).is_some() {
+            self.save();
+        }
+    }
+
+    /// Forget the bans that have ended before `now`
+    pub fn remove_expired_bans(&mut self, now: Duration) {
+        let count = self.banned.len();
+        self.banned
+            .retain(|_, banned_till| banned_till.map_or(true, |till| now <= till));
+        if self.banned.len() != count {
+            self.save();
+        }
+    }
+
+    pub fn add_to_whitelist(&mut self, subnet: Subnet) {
+        if self.whitelist.insert(subnet) {
+            self.save();
+        }
+    }
+
+    pub fn remove_from_whitelist(&mut self, subnet: &Subnet) {
+        if self.whitelist.remove(subnet) {
+            self.save();
+        }
+    }
+
+    fn restore(&mut self, entries: StoredEntries) {
+        let parse = |subnet: &str| {
+            subnet
+                .parse::<Subnet>()
+                .map_err(|err| log::warn!("invalid subnet in the peer database: {err}"))
+                .ok()
+        };
+
+        self.banned = entries
+            .banned
+            .iter()
+            .filter_map(|(subnet, banned_till)| {
+                parse(subnet).map(|subnet| (subnet, banned_till.map(Duration::from_secs)))
+            })
+            .collect();
+        self.whitelist = entries.whitelist.iter().filter_map(|subnet| parse(subnet)).collect();
+    }
+
+    fn save(&self) {
+        let path = match &self.path {
+            Some(path) => path,
+            None => return,
+        };
+
+        let entries = StoredEntries {
+            banned: self
+                .banned
+                .iter()
+                .map(|(subnet, banned_till)| {
+                    (subnet.to_string(), banned_till.map(|till| till.as_secs()))
+                })
+                .collect(),
+            whitelist: self.whitelist.iter().map(ToString::to_string).collect(),
+        };
+        if let Err(err) = std::fs::write(path, entries.encode()) {
+            log::error!("failed to save the peer database to {path:?}: {err}");
+        }
+    }
+}
+
+fn load(path: &Path) -> std::io::Result<StoredEntries> {
+    let data = std::fs::read(path)?;
+    StoredEntries::decode(&mut data.as_slice())
+        .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err))
+}
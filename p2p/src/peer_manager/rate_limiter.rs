@@ -0,0 +1,71 @@
+// Copyright (c) 2022 RBB S.r.l
+// opensource@mintlayer.org
+// SPDX-License-Identifier: MIT
+// Licensed under the MIT License;
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://github.com/mintlayer/mintlayer-core/blob/master/LICENSE
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Token bucket used to rate limit the amount of data accepted from a peer
+
+use std::time::Instant;
+
+/// A token bucket that holds at most `capacity` tokens and is refilled at `rate` tokens
+/// per second
+#[derive(Debug)]
+pub struct TokenBucket {
+    capacity: f64,
+    rate: f64,
+    tokens: f64,
+    last_update: Instant,
+}
+
+impl TokenBucket {
+    /// Create a new bucket that is initially full
+    pub fn new(capacity: usize, rate: f64, now: Instant) -> Self {
+        Self {
+            capacity: capacity as f64,
+            rate,
+            tokens: capacity as f64,
+            last_update: now,
+        }
+    }
+
+    /// Try to take `amount` tokens from the bucket
+    ///
+    /// Returns the number of tokens that were actually taken which is less than `amount`
+    /// if the bucket doesn't hold enough tokens.
+    pub fn take(&mut self, amount: usize, now: Instant) -> usize {
+        self.refill(now);
+
+        let taken = std::cmp::min(amount, self.tokens as usize);
+        self.tokens -= taken as f64;
+        taken
+    }
+
+    /// Take `amount` tokens from the bucket only if it holds enough of them
+    ///
+    /// Returns `false` and leaves the bucket untouched otherwise.
+    pub fn try_take(&mut self, amount: usize, now: Instant) -> bool {
+        self.refill(now);
+
+        if (amount as f64) > self.tokens {
+            return false;
+        }
+        self.tokens -= amount as f64;
+        true
+    }
+
+    fn refill(&mut self, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.last_update).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.rate).min(self.capacity);
+        self.last_update = now;
+    }
+}
@@ -0,0 +1,56 @@
+// Copyright (c) 2022 RBB S.r.l
+// opensource@mintlayer.org
+// SPDX-License-Identifier: MIT
+// Licensed under the MIT License;
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://github.com/mintlayer/mintlayer-core/blob/master/LICENSE
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Statistics of the connected peers, reported by the `p2p.get_peer_info` RPC
+
+use std::{collections::BTreeMap, time::Duration};
+
+use common::{chain::block::Block, primitives::Id};
+
+use crate::interface::types::MessageStats;
+
+#[derive(Debug)]
+pub struct PeerStats {
+    /// When the connection was established, as the time since UNIX epoch
+    pub connected_since: Duration,
+
+    /// The best block the peer is known to have
+    pub best_block: Option<Id<Block>>,
+
+    /// Messages sent to the peer by message type
+    pub sent: BTreeMap<&'static str, MessageStats>,
+
+    /// Messages received from the peer by message type
+    pub received: BTreeMap<&'static str, MessageStats>,
+}
+
+impl PeerStats {
+    pub fn new(connected_since: Duration) -> Self {
+        Self {
+            connected_since,
+            best_block: None,
+            sent: BTreeMap::new(),
+            received: BTreeMap::new(),
+        }
+    }
+
+    pub fn message_sent(&mut self, kind: &'static str, bytes: usize) {
+        self.sent.entry(kind).or_default().add(bytes);
+    }
+
+    pub fn message_received(&mut self, kind: &'static str, bytes: usize) {
+        self.received.entry(kind).or_default().add(bytes);
+    }
+}
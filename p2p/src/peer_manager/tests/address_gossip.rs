@@ -0,0 +1,181 @@
+// Copyright (c) 2022 RBB S.r.l
+// opensource@mintlayer.org
+// SPDX-License-Identifier: MIT
+// Licensed under the MIT License;
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://github.com/mintlayer/mintlayer-core/blob/master/LICENSE
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::{
+    sync::Arc,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use common::chain::config;
+use p2p_test_utils::{MakeChannelAddress, MakeP2pAddress, MakeTcpAddress, MakeTestAddress};
+
+use crate::{
+    error::{P2pError, ProtocolError},
+    message::PeerAddress,
+    net::{
+        self,
+        libp2p::Libp2pService,
+        mock::{
+            transport::{ChannelMockTransport, TcpMockTransport},
+            types::MockPeerId,
+            MockService,
+        },
+        ConnectivityService, NetworkingService,
+    },
+    peer_manager::{
+        rate_limiter::TokenBucket,
+        tests::{make_peer_manager, make_peer_manager_custom},
+        MAX_ADDR_PER_MESSAGE,
+    },
+    P2pConfig,
+};
+
+fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs()
+}
+
+// the address list sent to a peer starts with the configured external addresses of the local
+// node and it is sent only once per connection; the receiver adds the announced address to its
+// peer database
+async fn self_announcement<A, T>()
+where
+    A: MakeTestAddress<Address = T::Address>,
+    T: NetworkingService + 'static + std::fmt::Debug,
+    T::ConnectivityHandle: ConnectivityService<T>,
+    <T as net::NetworkingService>::Address: std::str::FromStr,
+    <<T as net::NetworkingService>::Address as std::str::FromStr>::Err: std::fmt::Debug,
+{
+    let config = Arc::new(config::create_mainnet());
+    let external_address = A::make_address();
+    let p2p_config = P2pConfig {
+        external_addresses: vec![external_address.to_string()].into(),
+        ..Default::default()
+    };
+    let mut pm1 = make_peer_manager::<T>(A::make_address(), Arc::clone(&config)).await;
+    let mut pm2 =
+        make_peer_manager_custom::<T>(A::make_address(), Arc::clone(&config), p2p_config).await;
+
+    let peer_id1 = *pm1.peer_connectivity_handle.peer_id();
+    let peer_id2 = *pm2.peer_connectivity_handle.peer_id();
+
+    let addresses = pm2.get_addresses(peer_id1).await.unwrap();
+    assert_eq!(addresses.len(), 1);
+    assert_eq!(addresses[0].peer_id(), peer_id2.to_string());
+    assert_eq!(addresses[0].address(), external_address.to_string());
+    assert!(pm2.get_addresses(peer_id1).await.unwrap().is_empty());
+
+    assert_eq!(pm1.addresses_received(peer_id2, addresses), Ok(()));
+    assert_eq!(pm1.peerdb.idle_peer_count(), 1);
+    assert_eq!(
+        pm1.peerdb.take_best_peer_addr(&Default::default()),
+        Ok(Some(external_address))
+    );
+
+    // the address the socket is bound to isn't announced
+    let mut pm3 = make_peer_manager::<T>(A::make_address(), config).await;
+    assert!(pm3.get_addresses(peer_id1).await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn self_announcement_libp2p() {
+    self_announcement::<MakeP2pAddress, Libp2pService>().await;
+}
+
+#[tokio::test]
+async fn self_announcement_mock_tcp() {
+    self_announcement::<MakeTcpAddress, MockService<TcpMockTransport>>().await;
+}
+
+#[tokio::test]
+async fn self_announcement_mock_channels() {
+    self_announcement::<MakeChannelAddress, MockService<ChannelMockTransport>>().await;
+}
+
+// the local node's own address and addresses with invalid timestamps are ignored
+#[tokio::test]
+async fn invalid_addresses_ignored() {
+    let config = Arc::new(config::create_mainnet());
+    let mut pm1 =
+        make_peer_manager::<MockService<TcpMockTransport>>(MakeTcpAddress::make_address(), config)
+            .await;
+
+    let peer_id1 = *pm1.peer_connectivity_handle.peer_id();
+    let addr1 = pm1.peer_connectivity_handle.local_addr().await.unwrap().unwrap();
+    let sender = MockPeerId::random();
+
+    let addresses = vec![
+        PeerAddress::new(peer_id1.to_string(), addr1.to_string(), now()),
+        PeerAddress::new("invalid".to_string(), "[::1]:8000".to_string(), now()),
+        PeerAddress::new("1".to_string(), "invalid".to_string(), now()),
+        PeerAddress::new("2".to_string(), "[::1]:8001".to_string(), 0),
+    ];
+    assert_eq!(pm1.addresses_received(sender, addresses), Ok(()));
+    assert_eq!(pm1.peerdb.idle_peer_count(), 0);
+
+    // an address from the future is accepted but it's treated as an old one
+    let future = now() + 60 * 60;
+    let addresses = vec![PeerAddress::new("3".to_string(), "[::1]:8002".to_string(), future)];
+    assert_eq!(pm1.addresses_received(sender, addresses), Ok(()));
+    assert_eq!(pm1.peerdb.idle_peer_count(), 1);
+
+    let now = Duration::from_secs(now());
+    let known = pm1.peerdb.known_addresses(MAX_ADDR_PER_MESSAGE, now);
+    assert_eq!(known.len(), 1);
+    assert!(known[0].2 < now);
+}
+
+// sending too many addresses at once is a protocol error and the addresses exceeding the
+// rate limit are ignored
+#[tokio::test]
+async fn address_limits() {
+    let config = Arc::new(config::create_mainnet());
+    let mut pm1 =
+        make_peer_manager::<MockService<TcpMockTransport>>(MakeTcpAddress::make_address(), config)
+            .await;
+    let sender = MockPeerId::random();
+
+    let addresses = (0..=MAX_ADDR_PER_MESSAGE)
+        .map(|i| PeerAddress::new(i.to_string(), format!("[::1]:{}", 8000 + i), now()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        pm1.addresses_received(sender, addresses.clone()),
+        Err(P2pError::ProtocolError(ProtocolError::TooManyAddresses(
+            MAX_ADDR_PER_MESSAGE + 1
+        )))
+    );
+    assert_eq!(pm1.peerdb.idle_peer_count(), 0);
+
+    let (first, second) = addresses.split_at(MAX_ADDR_PER_MESSAGE);
+    assert_eq!(pm1.addresses_received(sender, first.to_vec()), Ok(()));
+    assert_eq!(pm1.peerdb.idle_peer_count(), MAX_ADDR_PER_MESSAGE);
+
+    assert_eq!(pm1.addresses_received(sender, second.to_vec()), Ok(()));
+    assert_eq!(pm1.peerdb.idle_peer_count(), MAX_ADDR_PER_MESSAGE);
+}
+
+#[test]
+fn token_bucket() {
+    let start = Instant::now();
+    let mut bucket = TokenBucket::new(10, 0.5, start);
+
+    assert_eq!(bucket.take(8, start), 8);
+    assert_eq!(bucket.take(8, start), 2);
+    assert_eq!(bucket.take(1, start), 0);
+
+    assert_eq!(bucket.take(8, start + Duration::from_secs(4)), 2);
+
+    // the bucket never holds more than its capacity
+    assert_eq!(bucket.take(20, start + Duration::from_secs(1000)), 10);
+}
@@ -19,6 +19,7 @@ use common::{chain::config, primitives::semver::SemVer};
 use p2p_test_utils::{MakeChannelAddress, MakeP2pAddress, MakeTcpAddress, MakeTestAddress};
 
 use crate::{
+    constants::PROTOCOL_VERSION,
     error::{P2pError, PeerError},
     net::{
         self,
@@ -28,8 +29,8 @@ use crate::{
             types::MockPeerId,
             MockService,
         },
-        types::{Protocol, ProtocolType},
-        AsBannableAddress, ConnectivityService, NetworkingService,
+        types::{Protocol, ProtocolType, Services, Subnet},
+        AsBannableAddress, AsIpAddress, ConnectivityService, NetworkingService,
     },
     peer_manager::helpers::connect_services,
     peer_manager::tests::{default_protocols, make_peer_manager},
@@ -56,7 +57,7 @@ where
         &mut pm2.peer_connectivity_handle,
     )
     .await;
-    pm2.accept_inbound_connection(address, peer_info).unwrap();
+    pm2.accept_inbound_connection(address, peer_info).await.unwrap();
 
     let peer_id = *pm1.peer_connectivity_handle.peer_id();
     assert_eq!(pm2.adjust_peer_score(peer_id, 1000).await, Ok(()));
@@ -106,7 +107,7 @@ where
         &mut pm2.peer_connectivity_handle,
     )
     .await;
-    pm2.accept_inbound_connection(address, peer_info).unwrap();
+    pm2.accept_inbound_connection(address, peer_info).await.unwrap();
 
     let peer_id = *pm1.peer_connectivity_handle.peer_id();
     assert_eq!(pm2.adjust_peer_score(peer_id, 1000).await, Ok(()));
@@ -170,7 +171,7 @@ where
         &mut pm2.peer_connectivity_handle,
     )
     .await;
-    pm2.accept_inbound_connection(address, peer_info).unwrap();
+    pm2.accept_inbound_connection(address, peer_info).await.unwrap();
 
     let peer_id = *pm1.peer_connectivity_handle.peer_id();
     assert_eq!(pm2.adjust_peer_score(peer_id, 1000).await, Ok(()));
@@ -240,6 +241,9 @@ where
             version: common::primitives::semver::SemVer::new(0, 1, 0),
             agent: None,
             protocols: default_protocols(),
+            protocol_version: PROTOCOL_VERSION,
+            services: Services::default(),
+            time: None,
         },
     );
     assert_eq!(peer_manager.handle_result(Some(peer_id), res).await, Ok(()));
@@ -255,6 +259,9 @@ where
             version: common::primitives::semver::SemVer::new(0, 1, 0),
             agent: None,
             protocols: default_protocols(),
+            protocol_version: PROTOCOL_VERSION,
+            services: Services::default(),
+            time: None,
         },
     );
     assert_eq!(peer_manager.handle_result(Some(peer_id), res).await, Ok(()));
@@ -269,6 +276,9 @@ where
             version: common::primitives::semver::SemVer::new(1, 1, 1),
             agent: None,
             protocols: default_protocols(),
+            protocol_version: PROTOCOL_VERSION,
+            services: Services::default(),
+            time: None,
         },
     );
     assert_eq!(peer_manager.handle_result(Some(peer_id), res).await, Ok(()));
@@ -289,6 +299,9 @@ where
             ]
             .into_iter()
             .collect(),
+            protocol_version: PROTOCOL_VERSION,
+            services: Services::default(),
+            time: None,
         },
     );
     assert_eq!(peer_manager.handle_result(Some(peer_id), res).await, Ok(()));
@@ -334,64 +347,84 @@ where
     let mut peer_manager = make_peer_manager::<S>(A::make_address(), Arc::clone(&config)).await;
 
     // invalid magic bytes
-    let res = peer_manager.accept_inbound_connection(
-        peer_address.clone(),
-        net::types::PeerInfo::<S> {
-            peer_id,
-            magic_bytes: [1, 2, 3, 4],
-            version: common::primitives::semver::SemVer::new(0, 1, 0),
-            agent: None,
-            protocols: default_protocols(),
-        },
-    );
+    let res = peer_manager
+        .accept_inbound_connection(
+            peer_address.clone(),
+            net::types::PeerInfo::<S> {
+                peer_id,
+                magic_bytes: [1, 2, 3, 4],
+                version: common::primitives::semver::SemVer::new(0, 1, 0),
+                agent: None,
+                protocols: default_protocols(),
+                protocol_version: PROTOCOL_VERSION,
+                services: Services::default(),
+                time: None,
+            },
+        )
+        .await;
     assert_eq!(peer_manager.handle_result(Some(peer_id), res).await, Ok(()));
     assert!(!peer_manager.peerdb.is_active_peer(&peer_id));
 
     // invalid version
-    let res = peer_manager.accept_inbound_connection(
-        peer_address.clone(),
-        net::types::PeerInfo::<S> {
-            peer_id,
-            magic_bytes: *config.magic_bytes(),
-            version: common::primitives::semver::SemVer::new(1, 1, 1),
-            agent: None,
-            protocols: default_protocols(),
-        },
-    );
+    let res = peer_manager
+        .accept_inbound_connection(
+            peer_address.clone(),
+            net::types::PeerInfo::<S> {
+                peer_id,
+                magic_bytes: *config.magic_bytes(),
+                version: common::primitives::semver::SemVer::new(1, 1, 1),
+                agent: None,
+                protocols: default_protocols(),
+                protocol_version: PROTOCOL_VERSION,
+                services: Services::default(),
+                time: None,
+            },
+        )
+        .await;
     assert_eq!(peer_manager.handle_result(Some(peer_id), res).await, Ok(()));
     assert!(!peer_manager.peerdb.is_active_peer(&peer_id));
 
     // protocol missing
-    let res = peer_manager.accept_inbound_connection(
-        peer_address.clone(),
-        net::types::PeerInfo::<S> {
-            peer_id,
-            magic_bytes: *config.magic_bytes(),
-            version: common::primitives::semver::SemVer::new(0, 1, 0),
-            agent: None,
-            protocols: [
-                Protocol::new(ProtocolType::PubSub, SemVer::new(1, 0, 0)),
-                Protocol::new(ProtocolType::PubSub, SemVer::new(1, 1, 0)),
-                Protocol::new(ProtocolType::Ping, SemVer::new(1, 0, 0)),
-            ]
-            .into_iter()
-            .collect(),
-        },
-    );
+    let res = peer_manager
+        .accept_inbound_connection(
+            peer_address.clone(),
+            net::types::PeerInfo::<S> {
+                peer_id,
+                magic_bytes: *config.magic_bytes(),
+                version: common::primitives::semver::SemVer::new(0, 1, 0),
+                agent: None,
+                protocols: [
+                    Protocol::new(ProtocolType::PubSub, SemVer::new(1, 0, 0)),
+                    Protocol::new(ProtocolType::PubSub, SemVer::new(1, 1, 0)),
+                    Protocol::new(ProtocolType::Ping, SemVer::new(1, 0, 0)),
+                ]
+                .into_iter()
+                .collect(),
+                protocol_version: PROTOCOL_VERSION,
+                services: Services::default(),
+                time: None,
+            },
+        )
+        .await;
     assert_eq!(peer_manager.handle_result(Some(peer_id), res).await, Ok(()));
     assert!(!peer_manager.peerdb.is_active_peer(&peer_id));
 
     // valid connection
-    let res = peer_manager.accept_inbound_connection(
-        peer_address.clone(),
-        net::types::PeerInfo::<S> {
-            peer_id,
-            magic_bytes: *config.magic_bytes(),
-            version: common::primitives::semver::SemVer::new(0, 1, 0),
-            agent: None,
-            protocols: default_protocols(),
-        },
-    );
+    let res = peer_manager
+        .accept_inbound_connection(
+            peer_address.clone(),
+            net::types::PeerInfo::<S> {
+                peer_id,
+                magic_bytes: *config.magic_bytes(),
+                version: common::primitives::semver::SemVer::new(0, 1, 0),
+                agent: None,
+                protocols: default_protocols(),
+                protocol_version: PROTOCOL_VERSION,
+                services: Services::default(),
+                time: None,
+            },
+        )
+        .await;
     assert_eq!(peer_manager.handle_result(Some(peer_id), res).await, Ok(()));
     assert!(!peer_manager.peerdb.is_address_banned(&peer_address.as_bannable()));
 }
@@ -476,3 +509,113 @@ async fn inbound_connection_invalid_magic_mock_channels() {
     inbound_connection_invalid_magic::<MakeChannelAddress, MockService<ChannelMockTransport>>()
         .await;
 }
+
+// banning the subnet of a connected peer closes the connection
+async fn ban_subnet_of_connected_peer<A, T>()
+where
+    A: MakeTestAddress<Address = T::Address>,
+    T: NetworkingService + 'static + std::fmt::Debug,
+    T::ConnectivityHandle: ConnectivityService<T>,
+    <T as net::NetworkingService>::Address: std::str::FromStr,
+    <<T as net::NetworkingService>::Address as std::str::FromStr>::Err: std::fmt::Debug,
+{
+    let addr1 = A::make_address();
+    let addr2 = A::make_address();
+
+    let config = Arc::new(config::create_mainnet());
+    let mut pm1 = make_peer_manager::<T>(addr1, Arc::clone(&config)).await;
+    let mut pm2 = make_peer_manager::<T>(addr2, config).await;
+
+    let (address, peer_info) = connect_services::<T>(
+        &mut pm1.peer_connectivity_handle,
+        &mut pm2.peer_connectivity_handle,
+    )
+    .await;
+    let bannable_address = address.as_bannable();
+    let subnet = Subnet::single(bannable_address.as_ip_address());
+    pm2.accept_inbound_connection(address, peer_info).await.unwrap();
+
+    assert_eq!(pm2.ban_subnet(subnet, None).await, Ok(()));
+    assert!(pm2.peerdb.is_address_banned(&bannable_address));
+    assert!(std::matches!(
+        pm2.peer_connectivity_handle.poll_next().await,
+        Ok(net::types::ConnectivityEvent::ConnectionClosed { .. })
+    ));
+
+    assert_eq!(pm2.unban_subnet(subnet), Ok(()));
+    assert!(!pm2.peerdb.is_address_banned(&bannable_address));
+    assert_eq!(
+        pm2.unban_subnet(subnet),
+        Err(P2pError::PeerError(PeerError::SubnetNotBanned(
+            subnet.to_string()
+        )))
+    );
+}
+
+#[tokio::test]
+async fn ban_subnet_of_connected_peer_libp2p() {
+    ban_subnet_of_connected_peer::<MakeP2pAddress, Libp2pService>().await;
+}
+
+#[tokio::test]
+async fn ban_subnet_of_connected_peer_mock_tcp() {
+    ban_subnet_of_connected_peer::<MakeTcpAddress, MockService<TcpMockTransport>>().await;
+}
+
+#[tokio::test]
+async fn ban_subnet_of_connected_peer_mock_channels() {
+    ban_subnet_of_connected_peer::<MakeChannelAddress, MockService<ChannelMockTransport>>().await;
+}
+
+// whitelisted peers are neither banned nor disconnected
+async fn whitelisted_peer_not_banned<A, T>()
+where
+    A: MakeTestAddress<Address = T::Address>,
+    T: NetworkingService + 'static + std::fmt::Debug,
+    T::ConnectivityHandle: ConnectivityService<T>,
+    <T as net::NetworkingService>::Address: std::str::FromStr,
+    <<T as net::NetworkingService>::Address as std::str::FromStr>::Err: std::fmt::Debug,
+{
+    let addr1 = A::make_address();
+    let addr2 = A::make_address();
+
+    let config = Arc::new(config::create_mainnet());
+    let mut pm1 = make_peer_manager::<T>(addr1, Arc::clone(&config)).await;
+    let mut pm2 = make_peer_manager::<T>(addr2, config).await;
+
+    let (address, peer_info) = connect_services::<T>(
+        &mut pm1.peer_connectivity_handle,
+        &mut pm2.peer_connectivity_handle,
+    )
+    .await;
+    let bannable_address = address.as_bannable();
+    let subnet = Subnet::single(bannable_address.as_ip_address());
+    pm2.accept_inbound_connection(address, peer_info).await.unwrap();
+    assert_eq!(pm2.add_to_whitelist(subnet), Ok(()));
+
+    let peer_id = *pm1.peer_connectivity_handle.peer_id();
+    assert_eq!(pm2.adjust_peer_score(peer_id, 1000).await, Ok(()));
+    assert_eq!(pm2.ban_subnet(subnet, None).await, Ok(()));
+    assert!(!pm2.peerdb.is_address_banned(&bannable_address));
+    assert!(pm2.peerdb.is_active_peer(&peer_id));
+    assert_eq!(pm2.select_for_eviction(), None);
+
+    // the ban applies once the subnet is removed from the whitelist
+    assert_eq!(pm2.remove_from_whitelist(subnet), Ok(()));
+    assert!(pm2.peerdb.is_address_banned(&bannable_address));
+}
+
+#[tokio::test]
+async fn whitelisted_peer_not_banned_libp2p() {
+    whitelisted_peer_not_banned::<MakeP2pAddress, Libp2pService>().await;
+}
+
+#[tokio::test]
+async fn whitelisted_peer_not_banned_mock_tcp() {
+    whitelisted_peer_not_banned::<MakeTcpAddress, MockService<TcpMockTransport>>().await;
+}
+
+#[tokio::test]
+async fn whitelisted_peer_not_banned_mock_channels() {
+    whitelisted_peer_not_banned::<MakeChannelAddress, MockService<ChannelMockTransport>>().await;
+}
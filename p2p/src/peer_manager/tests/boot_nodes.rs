@@ -0,0 +1,238 @@
+// Copyright (c) 2022 RBB S.r.l
+// opensource@mintlayer.org
+// SPDX-License-Identifier: MIT
+// Licensed under the MIT License;
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://github.com/mintlayer/mintlayer-core/blob/master/LICENSE
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::sync::Arc;
+
+use common::chain::config;
+use p2p_test_utils::{MakeChannelAddress, MakeP2pAddress, MakeTcpAddress, MakeTestAddress};
+
+use crate::{
+    constants::PROTOCOL_VERSION,
+    error::{ConversionError, P2pError},
+    net::{
+        self,
+        libp2p::Libp2pService,
+        mock::{
+            transport::{ChannelMockTransport, TcpMockTransport},
+            types::MockPeerId,
+            MockService,
+        },
+        types::Services,
+        AsBannableAddress, ConnectivityService, NetworkingService,
+    },
+    peer_manager::{
+        network_time::NetworkTime,
+        tests::{default_protocols, make_peer_manager, make_peer_manager_custom},
+        PeerManager,
+    },
+    P2pConfig,
+};
+
+#[tokio::test]
+async fn invalid_boot_node_address() {
+    let config = Arc::new(config::create_mainnet());
+    let p2p_config = Arc::new(P2pConfig {
+        boot_nodes: vec!["invalid".to_string()].into(),
+        ..Default::default()
+    });
+    let (conn, _) = MockService::<TcpMockTransport>::start(
+        MakeTcpAddress::make_address(),
+        Arc::clone(&config),
+        Arc::clone(&p2p_config),
+        Default::default(),
+    )
+    .await
+    .unwrap();
+    let (_, rx) = tokio::sync::mpsc::unbounded_channel();
+    let (tx_sync, _rx_sync) = tokio::sync::mpsc::unbounded_channel();
+
+    assert_eq!(
+        PeerManager::<MockService<TcpMockTransport>>::new(
+            config,
+            p2p_config,
+            conn,
+            rx,
+            tx_sync,
+            NetworkTime::new(),
+            Default::default(),
+        )
+        .err(),
+        Some(P2pError::ConversionError(ConversionError::InvalidAddress(
+            "invalid".to_string()
+        )))
+    );
+}
+
+// the boot nodes are dialed when the peer manager has no active connections
+async fn boot_node_dialed<A, T>()
+where
+    A: MakeTestAddress<Address = T::Address>,
+    T: NetworkingService + 'static + std::fmt::Debug,
+    T::ConnectivityHandle: ConnectivityService<T>,
+    <T as net::NetworkingService>::Address: std::str::FromStr,
+    <<T as net::NetworkingService>::Address as std::str::FromStr>::Err: std::fmt::Debug,
+{
+    let config = Arc::new(config::create_mainnet());
+    let mut pm2 = make_peer_manager::<T>(A::make_address(), Arc::clone(&config)).await;
+    let addr2 = pm2.peer_connectivity_handle.local_addr().await.unwrap().unwrap();
+
+    let p2p_config = P2pConfig {
+        boot_nodes: vec![addr2.to_string()].into(),
+        ..Default::default()
+    };
+    let mut pm1 = make_peer_manager_custom::<T>(A::make_address(), config, p2p_config).await;
+
+    tokio::spawn(async move {
+        loop {
+            assert!(pm2.peer_connectivity_handle.poll_next().await.is_ok());
+        }
+    });
+
+    pm1.heartbeat().await.unwrap();
+    assert!(pm1.pending.contains_key(&addr2));
+
+    // the boot node isn't dialed again while the connection is pending
+    pm1.heartbeat().await.unwrap();
+    assert_eq!(pm1.pending.len(), 1);
+
+    assert!(std::matches!(
+        pm1.peer_connectivity_handle.poll_next().await,
+        Ok(net::types::ConnectivityEvent::OutboundAccepted { .. })
+    ));
+}
+
+#[tokio::test]
+async fn boot_node_dialed_libp2p() {
+    boot_node_dialed::<MakeP2pAddress, Libp2pService>().await;
+}
+
+#[tokio::test]
+async fn boot_node_dialed_mock_tcp() {
+    boot_node_dialed::<MakeTcpAddress, MockService<TcpMockTransport>>().await;
+}
+
+#[tokio::test]
+async fn boot_node_dialed_mock_channels() {
+    boot_node_dialed::<MakeChannelAddress, MockService<ChannelMockTransport>>().await;
+}
+
+// the reserved nodes are dialed automatically and they are never banned
+async fn reserved_node_not_banned<A, T>()
+where
+    A: MakeTestAddress<Address = T::Address>,
+    T: NetworkingService + 'static + std::fmt::Debug,
+    T::ConnectivityHandle: ConnectivityService<T>,
+    <T as net::NetworkingService>::Address: std::str::FromStr,
+    <<T as net::NetworkingService>::Address as std::str::FromStr>::Err: std::fmt::Debug,
+{
+    let config = Arc::new(config::create_mainnet());
+    let mut pm2 = make_peer_manager::<T>(A::make_address(), Arc::clone(&config)).await;
+    let addr2 = pm2.peer_connectivity_handle.local_addr().await.unwrap().unwrap();
+
+    let p2p_config = P2pConfig {
+        reserved_nodes: vec![addr2.to_string()].into(),
+        ..Default::default()
+    };
+    let mut pm1 = make_peer_manager_custom::<T>(A::make_address(), config, p2p_config).await;
+
+    tokio::spawn(async move {
+        loop {
+            assert!(pm2.peer_connectivity_handle.poll_next().await.is_ok());
+        }
+    });
+
+    pm1.heartbeat().await.unwrap();
+    let (address, peer_info) = match pm1.peer_connectivity_handle.poll_next().await {
+        Ok(net::types::ConnectivityEvent::OutboundAccepted { address, peer_info }) => {
+            (address, peer_info)
+        }
+        event => panic!("expected `OutboundAccepted`, got {event:?}"),
+    };
+    let peer_id = peer_info.peer_id;
+    pm1.accept_connection(address, peer_info).unwrap();
+
+    assert_eq!(pm1.adjust_peer_score(peer_id, 1000).await, Ok(()));
+    assert!(!pm1.peerdb.is_address_banned(&addr2.as_bannable()));
+    assert!(pm1.peerdb.is_active_peer(&peer_id));
+}
+
+#[tokio::test]
+async fn reserved_node_not_banned_libp2p() {
+    reserved_node_not_banned::<MakeP2pAddress, Libp2pService>().await;
+}
+
+#[tokio::test]
+async fn reserved_node_not_banned_mock_tcp() {
+    reserved_node_not_banned::<MakeTcpAddress, MockService<TcpMockTransport>>().await;
+}
+
+#[tokio::test]
+async fn reserved_node_not_banned_mock_channels() {
+    reserved_node_not_banned::<MakeChannelAddress, MockService<ChannelMockTransport>>().await;
+}
+
+// the reserved nodes are recognized by their IP address when they connect from another port
+// and by their peer ID once an outbound connection to them has succeeded
+#[tokio::test]
+async fn reserved_node_matched_by_ip_and_peer_id() {
+    type T = MockService<TcpMockTransport>;
+
+    let config = Arc::new(config::create_mainnet());
+    let p2p_config = P2pConfig {
+        reserved_nodes: vec!["127.0.0.1:3031".to_string()].into(),
+        ..Default::default()
+    };
+    let mut pm =
+        make_peer_manager_custom::<T>(MakeTcpAddress::make_address(), config, p2p_config).await;
+
+    let magic_bytes = *pm.chain_config.magic_bytes();
+    let version = *pm.chain_config.version();
+    let make_peer_info = |peer_id| net::types::PeerInfo::<T> {
+        peer_id,
+        magic_bytes,
+        version,
+        agent: None,
+        protocols: default_protocols(),
+        protocol_version: PROTOCOL_VERSION,
+        services: Services::default(),
+        time: None,
+    };
+
+    let inbound_id = MockPeerId::random();
+    pm.peerdb.peer_connected(
+        "127.0.0.1:50000".parse().unwrap(),
+        make_peer_info(inbound_id),
+    );
+    assert!(pm.is_reserved_peer(&inbound_id));
+
+    let other_id = MockPeerId::random();
+    pm.peerdb
+        .peer_connected("127.0.0.2:3031".parse().unwrap(), make_peer_info(other_id));
+    assert!(!pm.is_reserved_peer(&other_id));
+
+    // the reserved node is still recognized after it reconnects from another address
+    let outbound_id = MockPeerId::random();
+    pm.accept_outbound_connection(
+        "127.0.0.1:3031".parse().unwrap(),
+        make_peer_info(outbound_id),
+    )
+    .unwrap();
+    pm.close_connection(outbound_id).unwrap();
+    pm.peerdb.peer_connected(
+        "127.0.0.3:50000".parse().unwrap(),
+        make_peer_info(outbound_id),
+    );
+    assert!(pm.is_reserved_peer(&outbound_id));
+}
@@ -22,6 +22,7 @@ use common::{chain::config, primitives::semver::SemVer};
 use p2p_test_utils::{MakeChannelAddress, MakeP2pAddress, MakeTcpAddress, MakeTestAddress};
 
 use crate::{
+    constants::PROTOCOL_VERSION,
     error::{DialError, P2pError, ProtocolError},
     event::PeerManagerEvent,
     net::{
@@ -32,14 +33,16 @@ use crate::{
             types::MockPeerId,
             MockService,
         },
-        types::{Protocol, ProtocolType},
+        types::{Protocol, ProtocolType, Services},
         ConnectivityService, NetworkingService,
     },
     peer_manager::{
         self,
         helpers::connect_services,
+        network_time,
         tests::{default_protocols, make_peer_manager},
     },
+    P2pConfig,
 };
 
 // try to connect to an address that no one listening on and verify it fails
@@ -141,6 +144,31 @@ async fn test_auto_connect_mock_channels() {
     test_auto_connect::<MakeChannelAddress, MockService<ChannelMockTransport>>().await;
 }
 
+// the local and private addresses don't form network groups, so more than one outbound
+// connection is made to them
+#[tokio::test]
+async fn heartbeat_local_addresses_not_grouped() {
+    let config = Arc::new(config::create_mainnet());
+    let mut pm =
+        make_peer_manager::<MockService<TcpMockTransport>>(MakeTcpAddress::make_address(), config)
+            .await;
+
+    let addresses = (1..=3)
+        .map(|i| format!("127.0.0.{i}:1").parse().unwrap())
+        .collect::<Vec<SocketAddr>>();
+    for address in &addresses {
+        pm.peer_discovered(&[net::types::AddrInfo {
+            peer_id: MockPeerId::random(),
+            ip4: vec![*address],
+            ip6: vec![],
+        }]);
+    }
+    pm.heartbeat().await.unwrap();
+
+    assert_eq!(pm.pending.len(), addresses.len());
+    assert!(addresses.iter().all(|address| pm.pending.contains_key(address)));
+}
+
 async fn connect_outbound_same_network<A, T>()
 where
     A: MakeTestAddress<Address = T::Address>,
@@ -225,6 +253,17 @@ async fn test_validate_supported_protocols() {
     ));
 }
 
+#[tokio::test]
+async fn test_validate_protocol_version() {
+    let config = Arc::new(config::create_mainnet());
+    let peer_manager =
+        make_peer_manager::<Libp2pService>(MakeP2pAddress::make_address(), config).await;
+
+    assert!(peer_manager.validate_protocol_version(PROTOCOL_VERSION));
+    assert!(peer_manager.validate_protocol_version(crate::constants::MIN_PROTOCOL_VERSION));
+    assert!(!peer_manager.validate_protocol_version(crate::constants::MIN_PROTOCOL_VERSION - 1));
+}
+
 async fn connect_outbound_different_network<A, T>()
 where
     A: MakeTestAddress<Address = T::Address>,
@@ -288,7 +327,10 @@ where
         &mut pm2.peer_connectivity_handle,
     )
     .await;
-    assert_eq!(pm2.accept_inbound_connection(address, peer_info), Ok(()));
+    assert_eq!(
+        pm2.accept_inbound_connection(address, peer_info).await,
+        Ok(())
+    );
 }
 
 #[tokio::test]
@@ -306,6 +348,61 @@ async fn connect_inbound_same_network_mock_channel() {
     connect_inbound_same_network::<MakeChannelAddress, MockService<ChannelMockTransport>>().await;
 }
 
+// verify that the peer information reported by the RPC describes the accepted inbound connection
+async fn peer_info_inbound_connection<A, T>()
+where
+    A: MakeTestAddress<Address = T::Address>,
+    T: NetworkingService + 'static + std::fmt::Debug,
+    T::ConnectivityHandle: ConnectivityService<T>,
+    <T as net::NetworkingService>::Address: std::str::FromStr,
+    <<T as net::NetworkingService>::Address as std::str::FromStr>::Err: std::fmt::Debug,
+{
+    let addr1 = A::make_address();
+    let addr2 = A::make_address();
+
+    let config = Arc::new(config::create_mainnet());
+    let mut pm1 = make_peer_manager::<T>(addr1, Arc::clone(&config)).await;
+    let mut pm2 = make_peer_manager::<T>(addr2, config).await;
+
+    let (address, peer_info) = connect_services::<T>(
+        &mut pm1.peer_connectivity_handle,
+        &mut pm2.peer_connectivity_handle,
+    )
+    .await;
+    let peer_id = peer_info.peer_id;
+    assert_eq!(
+        pm2.accept_inbound_connection(address, peer_info).await,
+        Ok(())
+    );
+
+    pm2.stats.get_mut(&peer_id).unwrap().message_sent("HeaderListRequest", 100);
+
+    let peers = pm2.peer_info().await.unwrap();
+    assert_eq!(peers.len(), 1);
+    assert_eq!(peers[0].peer_id, peer_id.to_string());
+    assert!(peers[0].inbound);
+    assert_eq!(peers[0].protocol_version, PROTOCOL_VERSION);
+    assert_eq!(peers[0].ban_score, 0);
+    assert_eq!(peers[0].sent["HeaderListRequest"].count, 1);
+    assert_eq!(peers[0].sent["HeaderListRequest"].bytes, 100);
+    assert!(peers[0].received.is_empty());
+}
+
+#[tokio::test]
+async fn peer_info_inbound_connection_libp2p() {
+    peer_info_inbound_connection::<MakeP2pAddress, Libp2pService>().await;
+}
+
+#[tokio::test]
+async fn peer_info_inbound_connection_mock_tcp() {
+    peer_info_inbound_connection::<MakeTcpAddress, MockService<TcpMockTransport>>().await;
+}
+
+#[tokio::test]
+async fn peer_info_inbound_connection_mock_channels() {
+    peer_info_inbound_connection::<MakeChannelAddress, MockService<ChannelMockTransport>>().await;
+}
+
 async fn connect_inbound_different_network<A, T>()
 where
     A: MakeTestAddress<Address = T::Address>,
@@ -331,7 +428,7 @@ where
     .await;
 
     assert_eq!(
-        pm2.accept_inbound_connection(address, peer_info),
+        pm2.accept_inbound_connection(address, peer_info).await,
         Err(P2pError::ProtocolError(ProtocolError::DifferentNetwork(
             [1, 2, 3, 4],
             *config::create_mainnet().magic_bytes(),
@@ -423,7 +520,7 @@ where
     });
     assert_eq!(
         pm1.peerdb.active_peer_count(),
-        peer_manager::MAX_ACTIVE_CONNECTIONS
+        *P2pConfig::default().max_connections
     );
 
     let (_address, _peer_info) = connect_services::<T>(
@@ -449,13 +546,16 @@ where
 #[tokio::test]
 async fn inbound_connection_too_many_peers_libp2p() {
     let config = Arc::new(config::create_mainnet());
-    let peers = (0..peer_manager::MAX_ACTIVE_CONNECTIONS)
+    let peers = (0..*P2pConfig::default().max_connections)
         .map(|_| net::types::PeerInfo {
             peer_id: PeerId::random(),
             magic_bytes: *config.magic_bytes(),
             version: common::primitives::semver::SemVer::new(0, 1, 0),
             agent: None,
             protocols: default_protocols(),
+            protocol_version: PROTOCOL_VERSION,
+            services: Services::default(),
+            time: None,
         })
         .collect::<Vec<_>>();
 
@@ -465,7 +565,7 @@ async fn inbound_connection_too_many_peers_libp2p() {
 #[tokio::test]
 async fn inbound_connection_too_many_peers_mock_tcp() {
     let config = Arc::new(config::create_mainnet());
-    let peers = (0..peer_manager::MAX_ACTIVE_CONNECTIONS)
+    let peers = (0..*P2pConfig::default().max_connections)
         .map(|_| net::types::PeerInfo::<MockService<TcpMockTransport>> {
             peer_id: MockPeerId::random(),
             magic_bytes: *config.magic_bytes(),
@@ -477,6 +577,9 @@ async fn inbound_connection_too_many_peers_mock_tcp() {
             ]
             .into_iter()
             .collect(),
+            protocol_version: PROTOCOL_VERSION,
+            services: Services::default(),
+            time: None,
         })
         .collect::<Vec<_>>();
 
@@ -486,7 +589,7 @@ async fn inbound_connection_too_many_peers_mock_tcp() {
 #[tokio::test]
 async fn inbound_connection_too_many_peers_mock_channels() {
     let config = Arc::new(config::create_mainnet());
-    let peers = (0..peer_manager::MAX_ACTIVE_CONNECTIONS)
+    let peers = (0..*P2pConfig::default().max_connections)
         .map(
             |_| net::types::PeerInfo::<MockService<ChannelMockTransport>> {
                 peer_id: MockPeerId::random(),
@@ -499,6 +602,9 @@ async fn inbound_connection_too_many_peers_mock_channels() {
                 ]
                 .into_iter()
                 .collect(),
+                protocol_version: PROTOCOL_VERSION,
+                services: Services::default(),
+                time: None,
             },
         )
         .collect::<Vec<_>>();
@@ -577,7 +683,14 @@ where
 {
     let config = Arc::new(config::create_mainnet());
     let p2p_config = Arc::new(Default::default());
-    let (conn, _) = T::start(addr1, Arc::clone(&config), Arc::clone(&p2p_config)).await.unwrap();
+    let (conn, _) = T::start(
+        addr1,
+        Arc::clone(&config),
+        Arc::clone(&p2p_config),
+        Default::default(),
+    )
+    .await
+    .unwrap();
     let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
     let (tx_sync, mut rx_sync) = tokio::sync::mpsc::unbounded_channel();
 
@@ -587,7 +700,10 @@ where
         conn,
         rx,
         tx_sync,
-    );
+        network_time::NetworkTime::new(),
+        Default::default(),
+    )
+    .unwrap();
 
     tokio::spawn(async move {
         loop {
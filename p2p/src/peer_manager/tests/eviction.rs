@@ -0,0 +1,366 @@
+// Copyright (c) 2022 RBB S.r.l
+// opensource@mintlayer.org
+// SPDX-License-Identifier: MIT
+// Licensed under the MIT License;
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://github.com/mintlayer/mintlayer-core/blob/master/LICENSE
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use common::chain::config;
+use p2p_test_utils::{MakeChannelAddress, MakeP2pAddress, MakeTcpAddress, MakeTestAddress};
+
+use crate::{
+    error::{P2pError, PeerError, ProtocolError},
+    net::{
+        self,
+        libp2p::Libp2pService,
+        mock::{
+            transport::{ChannelMockTransport, TcpMockTransport},
+            MockService,
+        },
+        ConnectivityService, NetworkingService,
+    },
+    peer_manager::{
+        eviction::{select_for_eviction, ConnectionInfo},
+        helpers::connect_services,
+        tests::make_peer_manager_custom,
+    },
+    P2pConfig,
+};
+
+fn make_candidates(
+    groups: impl Iterator<Item = u8>,
+    first_connected: Instant,
+) -> Vec<(u64, ConnectionInfo)> {
+    groups
+        .enumerate()
+        .map(|(i, group)| {
+            let connected_since = first_connected + Duration::from_secs(i as u64);
+            (
+                i as u64,
+                ConnectionInfo::new(false, vec![group], connected_since),
+            )
+        })
+        .collect()
+}
+
+fn select(candidates: &[(u64, ConnectionInfo)]) -> Option<u64> {
+    let candidates = candidates.iter().map(|(id, info)| (*id, info)).collect::<Vec<_>>();
+    select_for_eviction(&candidates, 1337)
+}
+
+#[test]
+fn nothing_to_evict() {
+    assert_eq!(select(&[]), None);
+
+    // 4 peers are protected by network group, 8 by response time and 4 by blocks
+    let candidates = make_candidates(0..16, Instant::now());
+    assert_eq!(select(&candidates), None);
+
+    let candidates = make_candidates(0..17, Instant::now());
+    assert!(select(&candidates).is_some());
+}
+
+// the attacker can't push out the honest peers by opening lots of connections from one subnet
+#[test]
+fn attacker_evicted() {
+    let now = Instant::now();
+    let mut candidates = make_candidates(0..20, now);
+    candidates.iter_mut().for_each(|(_, info)| {
+        info.response_received(Duration::from_millis(50));
+    });
+
+    let attacker = make_candidates(
+        std::iter::repeat(100).take(50),
+        now + Duration::from_secs(60),
+    )
+    .into_iter()
+    .map(|(id, info)| (id + 1000, info));
+    candidates.extend(attacker);
+
+    let evicted = select(&candidates).unwrap();
+    assert!(evicted >= 1000);
+}
+
+// the peer that recently delivered a block is protected even if it's the youngest one
+#[test]
+fn block_delivery_protects() {
+    let now = Instant::now();
+    let mut candidates = make_candidates(std::iter::repeat(1).take(17), now);
+    let youngest = candidates.len() - 1;
+    candidates[youngest].1.block_delivered(now);
+
+    let evicted = select(&candidates).unwrap();
+    assert_ne!(evicted, youngest as u64);
+}
+
+// inbound connections beyond the configured limit are rejected if no peer can be evicted
+async fn inbound_connection_limit<A, T>()
+where
+    A: MakeTestAddress<Address = T::Address>,
+    T: NetworkingService + 'static + std::fmt::Debug,
+    T::ConnectivityHandle: ConnectivityService<T>,
+    <T as net::NetworkingService>::Address: std::str::FromStr,
+    <<T as net::NetworkingService>::Address as std::str::FromStr>::Err: std::fmt::Debug,
+{
+    let config = Arc::new(config::create_mainnet());
+    let mut pm1 =
+        make_peer_manager_custom::<T>(A::make_address(), Arc::clone(&config), Default::default())
+            .await;
+    let mut pm2 = make_peer_manager_custom::<T>(
+        A::make_address(),
+        Arc::clone(&config),
+        P2pConfig {
+            max_inbound_connections: 1.into(),
+            ..Default::default()
+        },
+    )
+    .await;
+    let mut pm3 =
+        make_peer_manager_custom::<T>(A::make_address(), config, Default::default()).await;
+
+    let (address, peer_info) = connect_services::<T>(
+        &mut pm1.peer_connectivity_handle,
+        &mut pm2.peer_connectivity_handle,
+    )
+    .await;
+    assert_eq!(
+        pm2.accept_inbound_connection(address, peer_info).await,
+        Ok(())
+    );
+
+    let (address, peer_info) = connect_services::<T>(
+        &mut pm3.peer_connectivity_handle,
+        &mut pm2.peer_connectivity_handle,
+    )
+    .await;
+    assert_eq!(
+        pm2.accept_inbound_connection(address, peer_info).await,
+        Err(P2pError::PeerError(PeerError::TooManyPeers))
+    );
+}
+
+#[tokio::test]
+async fn inbound_connection_limit_libp2p() {
+    inbound_connection_limit::<MakeP2pAddress, Libp2pService>().await;
+}
+
+#[tokio::test]
+async fn inbound_connection_limit_mock_tcp() {
+    inbound_connection_limit::<MakeTcpAddress, MockService<TcpMockTransport>>().await;
+}
+
+#[tokio::test]
+async fn inbound_connection_limit_mock_channels() {
+    inbound_connection_limit::<MakeChannelAddress, MockService<ChannelMockTransport>>().await;
+}
+
+// an evicted peer stops counting towards the connection limits as soon as it's selected,
+// before its connection is closed
+async fn evicted_peer_not_counted<A, T>()
+where
+    A: MakeTestAddress<Address = T::Address>,
+    T: NetworkingService + 'static + std::fmt::Debug,
+    T::ConnectivityHandle: ConnectivityService<T>,
+    <T as net::NetworkingService>::Address: std::str::FromStr,
+    <<T as net::NetworkingService>::Address as std::str::FromStr>::Err: std::fmt::Debug,
+{
+    let config = Arc::new(config::create_mainnet());
+    let mut pm1 =
+        make_peer_manager_custom::<T>(A::make_address(), Arc::clone(&config), Default::default())
+            .await;
+    let mut pm2 = make_peer_manager_custom::<T>(
+        A::make_address(),
+        Arc::clone(&config),
+        P2pConfig {
+            max_connections: 1.into(),
+            max_inbound_connections: 1.into(),
+            ..Default::default()
+        },
+    )
+    .await;
+    let mut pm3 =
+        make_peer_manager_custom::<T>(A::make_address(), config, Default::default()).await;
+
+    let (address1, peer_info1) = connect_services::<T>(
+        &mut pm1.peer_connectivity_handle,
+        &mut pm2.peer_connectivity_handle,
+    )
+    .await;
+    let (address3, peer_info3) = connect_services::<T>(
+        &mut pm3.peer_connectivity_handle,
+        &mut pm2.peer_connectivity_handle,
+    )
+    .await;
+
+    let peer_id1 = peer_info1.peer_id;
+    assert_eq!(
+        pm2.accept_inbound_connection(address1, peer_info1).await,
+        Ok(())
+    );
+    assert_eq!(pm2.active_peer_count(), 1);
+
+    pm2.evict(peer_id1).await.unwrap();
+    assert!(pm2.peerdb.is_active_peer(&peer_id1));
+    assert_eq!(pm2.active_peer_count(), 0);
+    assert_eq!(pm2.select_for_eviction(), None);
+
+    assert_eq!(
+        pm2.accept_inbound_connection(address3, peer_info3).await,
+        Ok(())
+    );
+    assert_eq!(pm2.active_peer_count(), 1);
+
+    pm2.close_connection(peer_id1).unwrap();
+    assert!(pm2.pending_disconnects.is_empty());
+    assert_eq!(pm2.active_peer_count(), 1);
+}
+
+#[tokio::test]
+async fn evicted_peer_not_counted_libp2p() {
+    evicted_peer_not_counted::<MakeP2pAddress, Libp2pService>().await;
+}
+
+#[tokio::test]
+async fn evicted_peer_not_counted_mock_tcp() {
+    evicted_peer_not_counted::<MakeTcpAddress, MockService<TcpMockTransport>>().await;
+}
+
+#[tokio::test]
+async fn evicted_peer_not_counted_mock_channels() {
+    evicted_peer_not_counted::<MakeChannelAddress, MockService<ChannelMockTransport>>().await;
+}
+
+// an invalid inbound peer is rejected before any existing peer is evicted to make room for it
+async fn invalid_peer_not_evicting<A, T>()
+where
+    A: MakeTestAddress<Address = T::Address>,
+    T: NetworkingService + 'static + std::fmt::Debug,
+    T::ConnectivityHandle: ConnectivityService<T>,
+    <T as net::NetworkingService>::Address: std::str::FromStr,
+    <<T as net::NetworkingService>::Address as std::str::FromStr>::Err: std::fmt::Debug,
+{
+    let config = Arc::new(config::create_mainnet());
+    let mut pm1 =
+        make_peer_manager_custom::<T>(A::make_address(), Arc::clone(&config), Default::default())
+            .await;
+    let mut pm2 = make_peer_manager_custom::<T>(
+        A::make_address(),
+        Arc::clone(&config),
+        P2pConfig {
+            max_connections: 1.into(),
+            max_inbound_connections: 1.into(),
+            ..Default::default()
+        },
+    )
+    .await;
+    let mut pm3 = make_peer_manager_custom::<T>(
+        A::make_address(),
+        Arc::new(config::Builder::test_chain().magic_bytes([1, 2, 3, 4]).build()),
+        Default::default(),
+    )
+    .await;
+
+    let (address1, peer_info1) = connect_services::<T>(
+        &mut pm1.peer_connectivity_handle,
+        &mut pm2.peer_connectivity_handle,
+    )
+    .await;
+    let (address3, peer_info3) = connect_services::<T>(
+        &mut pm3.peer_connectivity_handle,
+        &mut pm2.peer_connectivity_handle,
+    )
+    .await;
+
+    let peer_id1 = peer_info1.peer_id;
+    assert_eq!(
+        pm2.accept_inbound_connection(address1, peer_info1).await,
+        Ok(())
+    );
+
+    assert_eq!(
+        pm2.accept_inbound_connection(address3, peer_info3).await,
+        Err(P2pError::ProtocolError(ProtocolError::DifferentNetwork(
+            *config.magic_bytes(),
+            [1, 2, 3, 4],
+        )))
+    );
+    assert!(pm2.pending_disconnects.is_empty());
+    assert!(pm2.connections.contains_key(&peer_id1));
+    assert_eq!(pm2.active_peer_count(), 1);
+}
+
+#[tokio::test]
+async fn invalid_peer_not_evicting_libp2p() {
+    invalid_peer_not_evicting::<MakeP2pAddress, Libp2pService>().await;
+}
+
+#[tokio::test]
+async fn invalid_peer_not_evicting_mock_tcp() {
+    invalid_peer_not_evicting::<MakeTcpAddress, MockService<TcpMockTransport>>().await;
+}
+
+#[tokio::test]
+async fn invalid_peer_not_evicting_mock_channels() {
+    invalid_peer_not_evicting::<MakeChannelAddress, MockService<ChannelMockTransport>>().await;
+}
+
+// no new outbound connections are dialed once the outbound target has been reached
+async fn outbound_connection_target<A, T>()
+where
+    A: MakeTestAddress<Address = T::Address>,
+    T: NetworkingService + 'static + std::fmt::Debug,
+    T::ConnectivityHandle: ConnectivityService<T>,
+    <T as net::NetworkingService>::Address: std::str::FromStr,
+    <<T as net::NetworkingService>::Address as std::str::FromStr>::Err: std::fmt::Debug,
+{
+    let config = Arc::new(config::create_mainnet());
+    let mut pm1 = make_peer_manager_custom::<T>(
+        A::make_address(),
+        Arc::clone(&config),
+        P2pConfig {
+            max_outbound_connections: 0.into(),
+            ..Default::default()
+        },
+    )
+    .await;
+    let pm2 = make_peer_manager_custom::<T>(A::make_address(), config, Default::default()).await;
+
+    let addr = pm2.peer_connectivity_handle.local_addr().await.unwrap().unwrap();
+    pm1.peer_discovered(&[net::types::AddrInfo {
+        peer_id: *pm2.peer_connectivity_handle.peer_id(),
+        ip4: vec![],
+        ip6: vec![addr],
+    }]);
+    pm1.heartbeat().await.unwrap();
+
+    assert!(pm1.pending.is_empty());
+    assert_eq!(pm1.peerdb.idle_peer_count(), 1);
+}
+
+#[tokio::test]
+async fn outbound_connection_target_libp2p() {
+    outbound_connection_target::<MakeP2pAddress, Libp2pService>().await;
+}
+
+#[tokio::test]
+async fn outbound_connection_target_mock_tcp() {
+    outbound_connection_target::<MakeTcpAddress, MockService<TcpMockTransport>>().await;
+}
+
+#[tokio::test]
+async fn outbound_connection_target_mock_channels() {
+    outbound_connection_target::<MakeChannelAddress, MockService<ChannelMockTransport>>().await;
+}
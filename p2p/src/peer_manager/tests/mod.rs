@@ -13,8 +13,11 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+mod address_gossip;
 mod ban;
+mod boot_nodes;
 mod connections;
+mod eviction;
 mod peerdb;
 
 use std::{collections::BTreeSet, fmt::Debug, str::FromStr, sync::Arc};
@@ -26,7 +29,7 @@ use crate::{
         types::{Protocol, ProtocolType},
         ConnectivityService, NetworkingService,
     },
-    peer_manager::PeerManager,
+    peer_manager::{network_time::NetworkTime, PeerManager},
     P2pConfig,
 };
 
@@ -40,7 +43,29 @@ where
     <T as NetworkingService>::Address: FromStr,
     <<T as NetworkingService>::Address as FromStr>::Err: Debug,
 {
-    let (conn, _) = T::start(addr, Arc::clone(&config), Default::default()).await.unwrap();
+    make_peer_manager_custom::<T>(addr, config, Default::default()).await
+}
+
+async fn make_peer_manager_custom<T>(
+    addr: T::Address,
+    config: Arc<common::chain::ChainConfig>,
+    p2p_config: P2pConfig,
+) -> PeerManager<T>
+where
+    T: NetworkingService + 'static,
+    T::ConnectivityHandle: ConnectivityService<T>,
+    <T as NetworkingService>::Address: FromStr,
+    <<T as NetworkingService>::Address as FromStr>::Err: Debug,
+{
+    let p2p_config = Arc::new(p2p_config);
+    let (conn, _) = T::start(
+        addr,
+        Arc::clone(&config),
+        Arc::clone(&p2p_config),
+        Default::default(),
+    )
+    .await
+    .unwrap();
     let (_, rx) = tokio::sync::mpsc::unbounded_channel();
     let (tx_sync, mut rx_sync) = tokio::sync::mpsc::unbounded_channel();
 
@@ -50,8 +75,16 @@ where
         }
     });
 
-    let p2p_config = Arc::new(P2pConfig::default());
-    PeerManager::<T>::new(Arc::clone(&config), p2p_config, conn, rx, tx_sync)
+    PeerManager::<T>::new(
+        Arc::clone(&config),
+        p2p_config,
+        conn,
+        rx,
+        tx_sync,
+        NetworkTime::new(),
+        Default::default(),
+    )
+    .unwrap()
 }
 
 /// Returns a set of minimal required protocols.
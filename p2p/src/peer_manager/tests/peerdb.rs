@@ -13,14 +13,22 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::collections::HashMap;
+use std::{
+    collections::{BTreeSet, HashMap},
+    time::Duration,
+};
 
 use libp2p::{multiaddr, Multiaddr, PeerId};
 
 use super::*;
 use crate::{
     config,
-    net::{libp2p::Libp2pService, types, AsBannableAddress},
+    constants::PROTOCOL_VERSION,
+    net::{
+        libp2p::Libp2pService,
+        types::{self, Subnet},
+        AsBannableAddress, AsNetworkGroup,
+    },
     peer_manager::peerdb::{Peer, PeerDb},
 };
 
@@ -35,6 +43,9 @@ fn make_peer_info() -> (PeerId, types::PeerInfo<Libp2pService>) {
             version: common::primitives::semver::SemVer::new(0, 1, 0),
             agent: None,
             protocols: default_protocols(),
+            protocol_version: PROTOCOL_VERSION,
+            services: types::Services::default(),
+            time: None,
         },
     )
 }
@@ -78,7 +89,8 @@ fn add_banned_peer(peerdb: &mut PeerDb<Libp2pService>) -> PeerId {
 
 #[test]
 fn num_active_peers() {
-    let mut peerdb = PeerDb::<Libp2pService>::new(Arc::new(config::P2pConfig::default()));
+    let mut peerdb =
+        PeerDb::<Libp2pService>::new(Arc::new(config::P2pConfig::default()), Default::default());
 
     assert_eq!(peerdb.idle_peer_count(), 0);
     assert_eq!(peerdb.active_peer_count(), 0);
@@ -125,7 +137,8 @@ fn num_active_peers() {
 
 #[test]
 fn is_active_peer() {
-    let mut peerdb = PeerDb::<Libp2pService>::new(Arc::new(config::P2pConfig::default()));
+    let mut peerdb =
+        PeerDb::<Libp2pService>::new(Arc::new(config::P2pConfig::default()), Default::default());
 
     let id1 = add_active_peer(&mut peerdb);
     assert!(peerdb.is_active_peer(&id1));
@@ -142,7 +155,8 @@ fn is_active_peer() {
 
 #[test]
 fn adjust_peer_score_normal_threshold() {
-    let mut peerdb = PeerDb::<Libp2pService>::new(Arc::new(config::P2pConfig::default()));
+    let mut peerdb =
+        PeerDb::<Libp2pService>::new(Arc::new(config::P2pConfig::default()), Default::default());
 
     let id = add_active_peer(&mut peerdb);
     assert!(peerdb.adjust_peer_score(&id, 100));
@@ -157,7 +171,7 @@ fn adjust_peer_score_higher_threshold() {
         ban_threshold: 200.into(),
         ..Default::default()
     };
-    let mut peerdb = PeerDb::<Libp2pService>::new(Arc::new(config));
+    let mut peerdb = PeerDb::<Libp2pService>::new(Arc::new(config), Default::default());
 
     let id = add_active_peer(&mut peerdb);
     assert!(!peerdb.adjust_peer_score(&id, 100));
@@ -172,7 +186,7 @@ fn adjust_peer_score_lower_threshold() {
         ban_threshold: 20.into(),
         ..Default::default()
     };
-    let mut peerdb = PeerDb::<Libp2pService>::new(Arc::new(config));
+    let mut peerdb = PeerDb::<Libp2pService>::new(Arc::new(config), Default::default());
 
     let id = add_active_peer(&mut peerdb);
     assert!(peerdb.adjust_peer_score(&id, 30));
@@ -182,7 +196,8 @@ fn adjust_peer_score_lower_threshold() {
 
 #[test]
 fn ban_peer() {
-    let mut peerdb = PeerDb::<Libp2pService>::new(Arc::new(config::P2pConfig::default()));
+    let mut peerdb =
+        PeerDb::<Libp2pService>::new(Arc::new(config::P2pConfig::default()), Default::default());
 
     // idle peer
     let id = add_banned_peer(&mut peerdb);
@@ -221,7 +236,8 @@ fn ban_peer() {
 
 #[test]
 fn peer_disconnected_unknown() {
-    let mut peerdb = PeerDb::<Libp2pService>::new(Arc::new(config::P2pConfig::default()));
+    let mut peerdb =
+        PeerDb::<Libp2pService>::new(Arc::new(config::P2pConfig::default()), Default::default());
 
     // unknown peer doesn't cause any changes
     assert_eq!(peerdb.peers().len(), 0);
@@ -231,7 +247,8 @@ fn peer_disconnected_unknown() {
 
 #[test]
 fn peer_disconnected_idle() {
-    let mut peerdb = PeerDb::<Libp2pService>::new(Arc::new(config::P2pConfig::default()));
+    let mut peerdb =
+        PeerDb::<Libp2pService>::new(Arc::new(config::P2pConfig::default()), Default::default());
 
     // idle peer
     let id = add_idle_peer(&mut peerdb);
@@ -242,7 +259,8 @@ fn peer_disconnected_idle() {
 
 #[test]
 fn peer_disconnected_discovered() {
-    let mut peerdb = PeerDb::<Libp2pService>::new(Arc::new(config::P2pConfig::default()));
+    let mut peerdb =
+        PeerDb::<Libp2pService>::new(Arc::new(config::P2pConfig::default()), Default::default());
 
     let id = add_discovered_peer(&mut peerdb);
     peerdb.peer_disconnected(&id);
@@ -255,7 +273,8 @@ fn peer_disconnected_discovered() {
 
 #[test]
 fn peer_disconnected_banned() {
-    let mut peerdb = PeerDb::<Libp2pService>::new(Arc::new(config::P2pConfig::default()));
+    let mut peerdb =
+        PeerDb::<Libp2pService>::new(Arc::new(config::P2pConfig::default()), Default::default());
 
     let id = add_banned_peer(&mut peerdb);
     peerdb.peer_disconnected(&id);
@@ -267,7 +286,8 @@ fn peer_disconnected_banned() {
 
 #[test]
 fn peer_disconnected_active() {
-    let mut peerdb = PeerDb::<Libp2pService>::new(Arc::new(config::P2pConfig::default()));
+    let mut peerdb =
+        PeerDb::<Libp2pService>::new(Arc::new(config::P2pConfig::default()), Default::default());
 
     let id = add_active_peer(&mut peerdb);
     peerdb.peer_disconnected(&id);
@@ -277,7 +297,8 @@ fn peer_disconnected_active() {
 
 #[test]
 fn peer_connected_discovered() {
-    let mut peerdb = PeerDb::<Libp2pService>::new(Arc::new(config::P2pConfig::default()));
+    let mut peerdb =
+        PeerDb::<Libp2pService>::new(Arc::new(config::P2pConfig::default()), Default::default());
     let remote_addr: Multiaddr = "/ip6/::1/tcp/8888".parse().unwrap();
 
     // register information for a discovered peer
@@ -291,7 +312,7 @@ fn peer_connected_discovered() {
         peerdb.peers().get(&peer_id),
         Some(Peer::Discovered(_))
     ));
-    assert!(peerdb.take_best_peer_addr().unwrap().is_some());
+    let remote_addr = peerdb.take_best_peer_addr(&Default::default()).unwrap().unwrap();
 
     assert!(peerdb.pending().contains_key(&remote_addr));
     peerdb.peer_connected(remote_addr.clone(), info);
@@ -306,7 +327,8 @@ fn peer_connected_discovered() {
 
 #[test]
 fn peer_connected_idle() {
-    let mut peerdb = PeerDb::<Libp2pService>::new(Arc::new(config::P2pConfig::default()));
+    let mut peerdb =
+        PeerDb::<Libp2pService>::new(Arc::new(config::P2pConfig::default()), Default::default());
     let remote_addr: Multiaddr = "/ip6/::1/tcp/8888".parse().unwrap();
 
     let (id, info) = make_peer_info();
@@ -328,7 +350,8 @@ fn peer_connected_idle() {
 
 #[test]
 fn peer_connected_unknown() {
-    let mut peerdb = PeerDb::<Libp2pService>::new(Arc::new(config::P2pConfig::default()));
+    let mut peerdb =
+        PeerDb::<Libp2pService>::new(Arc::new(config::P2pConfig::default()), Default::default());
     let remote_addr: Multiaddr = "/ip6/::1/tcp/8888".parse().unwrap();
 
     let (id, info) = make_peer_info();
@@ -349,7 +372,8 @@ fn peer_connected_unknown() {
 
 #[test]
 fn peer_connected_active() {
-    let mut peerdb = PeerDb::<Libp2pService>::new(Arc::new(config::P2pConfig::default()));
+    let mut peerdb =
+        PeerDb::<Libp2pService>::new(Arc::new(config::P2pConfig::default()), Default::default());
 
     // active peer
     let id1 = add_active_peer(&mut peerdb);
@@ -366,7 +390,8 @@ fn peer_connected_active() {
 
 #[test]
 fn peer_connected_banned() {
-    let mut peerdb = PeerDb::<Libp2pService>::new(Arc::new(config::P2pConfig::default()));
+    let mut peerdb =
+        PeerDb::<Libp2pService>::new(Arc::new(config::P2pConfig::default()), Default::default());
 
     let id2 = add_banned_peer(&mut peerdb);
     let (_id, mut info2) = make_peer_info();
@@ -382,7 +407,8 @@ fn peer_connected_banned() {
 
 #[test]
 fn register_peer_info_discovered_peer() {
-    let mut peerdb = PeerDb::<Libp2pService>::new(Arc::new(config::P2pConfig::default()));
+    let mut peerdb =
+        PeerDb::<Libp2pService>::new(Arc::new(config::P2pConfig::default()), Default::default());
     let remote_addr: Multiaddr = "/ip6/::1/tcp/8888".parse().unwrap();
 
     // register information for a discovered peer
@@ -396,7 +422,7 @@ fn register_peer_info_discovered_peer() {
         peerdb.peers().get(&peer_id),
         Some(Peer::Discovered(_))
     ));
-    assert!(peerdb.take_best_peer_addr().unwrap().is_some());
+    let remote_addr = peerdb.take_best_peer_addr(&Default::default()).unwrap().unwrap();
 
     assert!(peerdb.pending().get(&remote_addr).is_some());
     peerdb.register_peer_info(remote_addr, info);
@@ -410,7 +436,8 @@ fn register_peer_info_discovered_peer() {
 // for idle peers the information is updated
 #[test]
 fn register_peer_info_idle_peer() {
-    let mut peerdb = PeerDb::<Libp2pService>::new(Arc::new(config::P2pConfig::default()));
+    let mut peerdb =
+        PeerDb::<Libp2pService>::new(Arc::new(config::P2pConfig::default()), Default::default());
 
     let id = add_idle_peer(&mut peerdb);
     if let Some(Peer::Idle(ctx)) = peerdb.peers().get(&id) {
@@ -435,7 +462,8 @@ fn register_peer_info_idle_peer() {
 
 #[test]
 fn register_peer_info_unknown_peer() {
-    let mut peerdb = PeerDb::<Libp2pService>::new(Arc::new(config::P2pConfig::default()));
+    let mut peerdb =
+        PeerDb::<Libp2pService>::new(Arc::new(config::P2pConfig::default()), Default::default());
 
     let (id, info) = make_peer_info();
     assert!(peerdb.peers().get(&id).is_none());
@@ -446,7 +474,8 @@ fn register_peer_info_unknown_peer() {
 
 #[test]
 fn register_peer_info_active() {
-    let mut peerdb = PeerDb::<Libp2pService>::new(Arc::new(config::P2pConfig::default()));
+    let mut peerdb =
+        PeerDb::<Libp2pService>::new(Arc::new(config::P2pConfig::default()), Default::default());
 
     let id1 = add_active_peer(&mut peerdb);
     let (_id, info1) = make_peer_info();
@@ -461,7 +490,8 @@ fn register_peer_info_active() {
 
 #[test]
 fn register_peer_info_banned() {
-    let mut peerdb = PeerDb::<Libp2pService>::new(Arc::new(config::P2pConfig::default()));
+    let mut peerdb =
+        PeerDb::<Libp2pService>::new(Arc::new(config::P2pConfig::default()), Default::default());
 
     // banned peer
     let id2 = add_banned_peer(&mut peerdb);
@@ -477,7 +507,7 @@ fn register_peer_info_banned() {
 
 #[test]
 fn peer_discovered_libp2p() {
-    let mut peerdb = PeerDb::new(Arc::new(config::P2pConfig::default()));
+    let mut peerdb = PeerDb::new(Arc::new(config::P2pConfig::default()), Default::default());
 
     let id_1: libp2p::PeerId = PeerId::random();
     let id_2: libp2p::PeerId = PeerId::random();
@@ -587,3 +617,263 @@ fn peer_discovered_libp2p() {
         vec!["/ip6/::1/tcp/9097".parse().unwrap()],
     );
 }
+
+#[test]
+fn peer_discovered_duplicate_addresses() {
+    let mut peerdb =
+        PeerDb::<Libp2pService>::new(Arc::new(config::P2pConfig::default()), Default::default());
+    let peer_id = PeerId::random();
+    let address: Multiaddr = "/ip6/::1/tcp/9090".parse().unwrap();
+
+    for _ in 0..2 {
+        peerdb.peer_discovered(&types::AddrInfo {
+            peer_id,
+            ip4: vec![],
+            ip6: vec![address.clone()],
+        });
+    }
+
+    match peerdb.peers().get(&peer_id) {
+        Some(Peer::Discovered(addresses)) => assert_eq!(addresses, &[address]),
+        _ => panic!("invalid peer type"),
+    }
+}
+
+#[test]
+fn ban_subnet() {
+    let mut peerdb =
+        PeerDb::<Libp2pService>::new(Arc::new(config::P2pConfig::default()), Default::default());
+
+    let subnet = "160.9.0.0/16".parse::<Subnet>().unwrap();
+    peerdb.ban_subnet(subnet, None);
+    assert!(peerdb.is_address_banned(&"160.9.112.44".parse().unwrap()));
+    assert!(!peerdb.is_address_banned(&"160.10.0.1".parse().unwrap()));
+
+    let subnet6 = "2001:db8:0:1::/64".parse::<Subnet>().unwrap();
+    peerdb.ban_subnet(subnet6, Some(Duration::from_secs(60)));
+    assert!(peerdb.is_address_banned(&"2001:db8:0:1::2".parse().unwrap()));
+    assert!(!peerdb.is_address_banned(&"2001:db8:0:2::2".parse().unwrap()));
+    assert_eq!(peerdb.banned_subnets().len(), 2);
+
+    assert!(peerdb.unban_subnet(&subnet));
+    assert!(!peerdb.unban_subnet(&subnet));
+    assert!(!peerdb.is_address_banned(&"160.9.112.44".parse().unwrap()));
+}
+
+#[test]
+fn unban_subnet_of_banned_peer() {
+    let mut peerdb =
+        PeerDb::<Libp2pService>::new(Arc::new(config::P2pConfig::default()), Default::default());
+
+    let id = add_banned_peer(&mut peerdb);
+    assert!(peerdb.unban_subnet(&"160.9.112.46".parse().unwrap()));
+
+    assert!(std::matches!(peerdb.peers().get(&id), Some(Peer::Idle(_))));
+    assert!(peerdb.available().contains(&id));
+}
+
+#[test]
+fn whitelisted_address_not_banned() {
+    let mut peerdb =
+        PeerDb::<Libp2pService>::new(Arc::new(config::P2pConfig::default()), Default::default());
+
+    peerdb.ban_subnet("160.9.0.0/16".parse().unwrap(), None);
+    peerdb.add_to_whitelist("160.9.112.0/24".parse().unwrap());
+    assert!(!peerdb.is_address_banned(&"160.9.112.44".parse().unwrap()));
+    assert!(peerdb.is_address_banned(&"160.9.113.44".parse().unwrap()));
+    assert!(peerdb.is_address_whitelisted(&"160.9.112.44".parse().unwrap()));
+}
+
+// a misbehaving IPv6 peer is banned together with the rest of its /64
+#[test]
+fn ban_ipv6_peer_subnet() {
+    let mut peerdb =
+        PeerDb::<Libp2pService>::new(Arc::new(config::P2pConfig::default()), Default::default());
+
+    add_banned_peer_address(&mut peerdb, "/ip6/2001:db8:0:1::2".parse().unwrap());
+    assert!(peerdb.is_address_banned(&"2001:db8:0:1::3".parse().unwrap()));
+    assert!(!peerdb.is_address_banned(&"2001:db8:0:2::2".parse().unwrap()));
+
+    // IPv4 peers are banned by their address only
+    add_banned_peer_address(&mut peerdb, "/ip4/160.9.112.46".parse().unwrap());
+    assert!(peerdb.is_address_banned(&"160.9.112.46".parse().unwrap()));
+    assert!(!peerdb.is_address_banned(&"160.9.112.47".parse().unwrap()));
+}
+
+#[test]
+fn ban_ipv6_prefix_len_configurable() {
+    let config = config::P2pConfig {
+        ban_ipv6_prefix_len: 128.into(),
+        ..Default::default()
+    };
+    let mut peerdb = PeerDb::<Libp2pService>::new(Arc::new(config), Default::default());
+
+    add_banned_peer_address(&mut peerdb, "/ip6/2001:db8:0:1::2".parse().unwrap());
+    assert!(peerdb.is_address_banned(&"2001:db8:0:1::2".parse().unwrap()));
+    assert!(!peerdb.is_address_banned(&"2001:db8:0:1::3".parse().unwrap()));
+}
+
+// the bans and whitelist entries created over RPC are restored after a restart, while the
+// others are not
+#[test]
+fn persistent_bans_and_whitelist() {
+    let dir = std::env::temp_dir().join(format!("p2p-peerdb-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let config = Arc::new(config::P2pConfig {
+        peerdb_path: Some(dir.join("peerdb.dat")).into(),
+        ..Default::default()
+    });
+
+    let stored = "160.9.0.0/16".parse::<Subnet>().unwrap();
+    let unbanned = "160.10.0.0/16".parse::<Subnet>().unwrap();
+    let whitelisted = "160.9.112.0/24".parse::<Subnet>().unwrap();
+    {
+        let mut peerdb = PeerDb::<Libp2pService>::new(Arc::clone(&config), Default::default());
+        peerdb.ban_subnet_persistent(stored, None);
+        peerdb.ban_subnet_persistent(unbanned, Some(Duration::from_secs(60)));
+        assert!(peerdb.unban_subnet(&unbanned));
+        peerdb.ban_subnet("160.11.0.0/16".parse().unwrap(), None);
+        peerdb.add_to_whitelist_persistent(whitelisted);
+        peerdb.add_to_whitelist("160.9.113.0/24".parse().unwrap());
+    }
+
+    let mut peerdb = PeerDb::<Libp2pService>::new(config, Default::default());
+    std::fs::remove_dir_all(&dir).unwrap();
+    assert_eq!(peerdb.banned_subnets(), vec![(stored, None)]);
+    assert_eq!(peerdb.whitelist(), vec![whitelisted]);
+}
+
+// the subnet of a misbehaving peer stays banned after a restart
+#[test]
+fn misbehaviour_bans_persisted() {
+    let dir = std::env::temp_dir().join(format!("p2p-peerdb-bans-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let config = Arc::new(config::P2pConfig {
+        peerdb_path: Some(dir.join("peerdb.dat")).into(),
+        ..Default::default()
+    });
+
+    {
+        let mut peerdb = PeerDb::<Libp2pService>::new(Arc::clone(&config), Default::default());
+        add_banned_peer_address(&mut peerdb, "/ip6/2001:db8:0:1::2".parse().unwrap());
+    }
+
+    let mut peerdb = PeerDb::<Libp2pService>::new(config, Default::default());
+    std::fs::remove_dir_all(&dir).unwrap();
+    assert_eq!(
+        peerdb
+            .banned_subnets()
+            .into_iter()
+            .map(|(subnet, _)| subnet)
+            .collect::<Vec<_>>(),
+        vec!["2001:db8:0:1::/64".parse::<Subnet>().unwrap()]
+    );
+    assert!(peerdb.is_address_banned(&"2001:db8:0:1::3".parse().unwrap()));
+}
+
+// only one address of each network group is selected if the group of
+// the selected address is excluded from the following selections
+#[test]
+fn take_best_peer_addr_spreads_groups() {
+    let mut peerdb =
+        PeerDb::<Libp2pService>::new(Arc::new(config::P2pConfig::default()), Default::default());
+
+    for i in 0..10 {
+        peerdb.peer_discovered(&types::AddrInfo {
+            peer_id: PeerId::random(),
+            ip4: vec![format!("/ip4/160.9.112.{i}/tcp/3031").parse().unwrap()],
+            ip6: vec![],
+        });
+    }
+    let other: Multiaddr = "/ip4/161.9.112.1/tcp/3031".parse().unwrap();
+    peerdb.peer_discovered(&types::AddrInfo {
+        peer_id: PeerId::random(),
+        ip4: vec![other.clone()],
+        ip6: vec![],
+    });
+
+    let mut excluded_groups = BTreeSet::new();
+    while let Some(address) = peerdb.take_best_peer_addr(&excluded_groups).unwrap() {
+        assert!(excluded_groups.insert(address.network_group()));
+    }
+    assert_eq!(excluded_groups.len(), 2);
+    assert!(excluded_groups.contains(&other.network_group()));
+}
+
+#[test]
+fn report_outbound_failure() {
+    let mut peerdb =
+        PeerDb::<Libp2pService>::new(Arc::new(config::P2pConfig::default()), Default::default());
+    let peer_id = PeerId::random();
+    let address: Multiaddr = "/ip4/160.9.112.44/tcp/3031".parse().unwrap();
+
+    peerdb.peer_discovered(&types::AddrInfo {
+        peer_id,
+        ip4: vec![address.clone()],
+        ip6: vec![],
+    });
+    assert_eq!(
+        peerdb.take_best_peer_addr(&Default::default()),
+        Ok(Some(address.clone()))
+    );
+    assert!(!peerdb.available().contains(&peer_id));
+
+    // the peer becomes available again and its address is retried
+    peerdb.report_outbound_failure(address.clone());
+    assert!(peerdb.available().contains(&peer_id));
+    assert!(peerdb.pending().is_empty());
+    assert_eq!(
+        peerdb.take_best_peer_addr(&Default::default()),
+        Ok(Some(address))
+    );
+}
+
+// the address of a feeler connection to an already connected peer can be selected again
+#[test]
+fn remove_pending_feeler_addr() {
+    let mut peerdb =
+        PeerDb::<Libp2pService>::new(Arc::new(config::P2pConfig::default()), Default::default());
+    let peer_id = PeerId::random();
+    let address: Multiaddr = "/ip4/160.9.112.44/tcp/3031".parse().unwrap();
+
+    peerdb.peer_discovered(&types::AddrInfo {
+        peer_id,
+        ip4: vec![address.clone()],
+        ip6: vec![],
+    });
+    assert_eq!(peerdb.take_feeler_addr(), Ok(Some(address.clone())));
+    assert_eq!(peerdb.take_feeler_addr(), Ok(None));
+
+    peerdb.remove_pending(&address);
+    assert!(peerdb.pending().is_empty());
+    assert!(peerdb.available().contains(&peer_id));
+    assert_eq!(peerdb.take_feeler_addr(), Ok(Some(address)));
+}
+
+// the time an address was last seen is kept only as long as the address manager knows
+// the address
+#[test]
+fn last_seen_forgotten_with_address() {
+    let mut peerdb =
+        PeerDb::<Libp2pService>::new(Arc::new(config::P2pConfig::default()), Default::default());
+    let address: Multiaddr = "/ip4/160.9.112.47/tcp/3031".parse().unwrap();
+    let last_seen = Duration::from_secs(1_600_000_000);
+
+    peerdb.address_seen(address.clone(), last_seen);
+    assert_eq!(peerdb.address_last_seen(&address), None);
+
+    peerdb.peer_discovered(&types::AddrInfo {
+        peer_id: PeerId::random(),
+        ip4: vec![address.clone()],
+        ip6: vec![],
+    });
+    peerdb.address_seen(address.clone(), last_seen);
+    assert_eq!(peerdb.address_last_seen(&address), Some(last_seen));
+
+    // addresses in the new table are forgotten after three failed attempts
+    for _ in 0..3 {
+        peerdb.report_outbound_failure(address.clone());
+    }
+    assert_eq!(peerdb.address_last_seen(&address), None);
+    assert_eq!(peerdb.take_best_peer_addr(&Default::default()), Ok(None));
+}
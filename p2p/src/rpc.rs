@@ -13,7 +13,12 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use crate::error::P2pError;
+use common::{chain::Block, primitives::Id};
+
+use crate::{
+    error::P2pError,
+    interface::types::{BannedSubnet, ConnectedPeer, SyncProgress},
+};
 use subsystem::subsystem::CallError;
 
 #[rpc::rpc(server, namespace = "p2p")]
@@ -41,6 +46,42 @@ trait P2pRpc {
     /// Get peer IDs of connected peers
     #[method(name = "get_connected_peers")]
     async fn get_connected_peers(&self) -> rpc::Result<Vec<String>>;
+
+    /// Get detailed information and statistics of the connected peers
+    #[method(name = "get_peer_info")]
+    async fn get_peer_info(&self) -> rpc::Result<Vec<ConnectedPeer>>;
+
+    /// Get the progress of the block synchronization
+    #[method(name = "get_sync_progress")]
+    async fn get_sync_progress(&self) -> rpc::Result<SyncProgress>;
+
+    /// Request a block from the connected peers and submit it to the chainstate
+    #[method(name = "fetch_block")]
+    async fn fetch_block(&self, id: Id<Block>) -> rpc::Result<()>;
+
+    /// Ban a subnet, e.g. `10.0.0.0/8`, for the given number of seconds or permanently
+    #[method(name = "ban_subnet")]
+    async fn ban_subnet(&self, subnet: String, duration_secs: Option<u64>) -> rpc::Result<()>;
+
+    /// Remove the ban of a subnet
+    #[method(name = "unban_subnet")]
+    async fn unban_subnet(&self, subnet: String) -> rpc::Result<()>;
+
+    /// Get the banned subnets
+    #[method(name = "get_banned_subnets")]
+    async fn get_banned_subnets(&self) -> rpc::Result<Vec<BannedSubnet>>;
+
+    /// Add a subnet to the whitelist, its peers are never banned or evicted
+    #[method(name = "add_to_whitelist")]
+    async fn add_to_whitelist(&self, subnet: String) -> rpc::Result<()>;
+
+    /// Remove a subnet from the whitelist
+    #[method(name = "remove_from_whitelist")]
+    async fn remove_from_whitelist(&self, subnet: String) -> rpc::Result<()>;
+
+    /// Get the whitelisted subnets
+    #[method(name = "get_whitelist")]
+    async fn get_whitelist(&self) -> rpc::Result<Vec<String>>;
 }
 
 #[async_trait::async_trait]
@@ -74,6 +115,51 @@ impl P2pRpcServer for super::P2pHandle {
         let res = self.call_async(|this| Box::pin(this.get_connected_peers())).await;
         handle_error(res)
     }
+
+    async fn get_peer_info(&self) -> rpc::Result<Vec<ConnectedPeer>> {
+        let res = self.call_async(|this| Box::pin(this.get_peer_info())).await;
+        handle_error(res)
+    }
+
+    async fn get_sync_progress(&self) -> rpc::Result<SyncProgress> {
+        let res = self.call_async(|this| Box::pin(this.get_sync_progress())).await;
+        handle_error(res)
+    }
+
+    async fn fetch_block(&self, id: Id<Block>) -> rpc::Result<()> {
+        let res = self.call_async(|this| Box::pin(this.fetch_block(id))).await;
+        handle_error(res)
+    }
+
+    async fn ban_subnet(&self, subnet: String, duration_secs: Option<u64>) -> rpc::Result<()> {
+        let res = self.call_async(|this| Box::pin(this.ban_subnet(subnet, duration_secs))).await;
+        handle_error(res)
+    }
+
+    async fn unban_subnet(&self, subnet: String) -> rpc::Result<()> {
+        let res = self.call_async(|this| Box::pin(this.unban_subnet(subnet))).await;
+        handle_error(res)
+    }
+
+    async fn get_banned_subnets(&self) -> rpc::Result<Vec<BannedSubnet>> {
+        let res = self.call_async(|this| Box::pin(this.get_banned_subnets())).await;
+        handle_error(res)
+    }
+
+    async fn add_to_whitelist(&self, subnet: String) -> rpc::Result<()> {
+        let res = self.call_async(|this| Box::pin(this.add_to_whitelist(subnet))).await;
+        handle_error(res)
+    }
+
+    async fn remove_from_whitelist(&self, subnet: String) -> rpc::Result<()> {
+        let res = self.call_async(|this| Box::pin(this.remove_from_whitelist(subnet))).await;
+        handle_error(res)
+    }
+
+    async fn get_whitelist(&self) -> rpc::Result<Vec<String>> {
+        let res = self.call_async(|this| Box::pin(this.get_whitelist())).await;
+        handle_error(res)
+    }
 }
 
 fn handle_error<T>(e: Result<Result<T, P2pError>, CallError>) -> rpc::Result<T> {
@@ -0,0 +1,329 @@
+// Copyright (c) 2022 RBB S.r.l
+// opensource@mintlayer.org
+// SPDX-License-Identifier: MIT
+// Licensed under the MIT License;
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://github.com/mintlayer/mintlayer-core/blob/master/LICENSE
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Capture of the syncing messages exchanged with peers
+//!
+//! When enabled, every request, response and announcement sent to or received from a peer is
+//! appended to a file named after the peer in the capture directory. Each record is SCALE-encoded
+//! and self-delimiting, so a capture file is just a sequence of [`CapturedMessage`]s that can be
+//! read back with [`read_capture_file`] and replayed against a fresh sync manager.
+//!
+//! The files are written by a separate writer task so that the disk access doesn't slow down
+//! syncing. If the writer falls behind, the messages that don't fit into its queue are dropped.
+//! The capture stops once the total size of the files reaches the configured limit.
+
+use std::{
+    collections::HashMap,
+    fmt::Display,
+    fs::{self, File, OpenOptions},
+    io::{BufWriter, Write},
+    path::{Path, PathBuf},
+    sync::mpsc::{self, Receiver, SyncSender, TrySendError},
+};
+
+use tokio::sync::oneshot;
+
+use common::primitives::time;
+use logging::log;
+use serialization::{Decode, Encode};
+
+use crate::message::{Announcement, Request, Response};
+
+/// Name of the capture directory inside of the data directory
+pub const MESSAGE_CAPTURE_DIR_NAME: &str = "p2p_capture";
+
+/// Extension of the capture files
+const CAPTURE_FILE_EXTENSION: &str = "capture";
+
+/// The number of messages waiting to be written before the new ones are dropped
+const CAPTURE_QUEUE_SIZE: usize = 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Encode, Decode)]
+pub enum Direction {
+    /// The message was received from the peer
+    Inbound,
+
+    /// The message was sent to the peer
+    Outbound,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Encode, Decode)]
+pub enum CapturedPayload {
+    Request(Request),
+    Response(Response),
+    Announcement(Announcement),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Encode, Decode)]
+pub struct CapturedMessage {
+    /// Time the message was sent or received, in milliseconds since the Unix epoch
+    pub timestamp: u64,
+
+    /// ID of the remote peer
+    pub peer_id: String,
+
+    pub direction: Direction,
+
+    pub payload: CapturedPayload,
+}
+
+enum WriterCommand {
+    /// Append the message to the capture file of its peer
+    Write(CapturedMessage),
+
+    /// Close the capture file of the peer
+    Close(String),
+
+    /// Flush all open files and respond once it's done
+    Flush(oneshot::Sender<()>),
+}
+
+/// Returns the path of the capture file of a peer
+fn capture_file_path(dir: &Path, peer_id: &impl Display) -> PathBuf {
+    dir.join(format!("{peer_id}.{CAPTURE_FILE_EXTENSION}"))
+}
+
+/// Sender of the captured messages to the writer task
+pub struct MessageCapture {
+    dir: PathBuf,
+    tx: SyncSender<WriterCommand>,
+}
+
+impl MessageCapture {
+    /// Creates the capture directory if it doesn't exist yet and starts the writer task
+    ///
+    /// The capture stops once the files take up `max_size` bytes.
+    pub fn new(dir: &Path, max_size: u64) -> crate::Result<Self> {
+        fs::create_dir_all(dir)?;
+
+        let (tx, rx) = mpsc::sync_channel(CAPTURE_QUEUE_SIZE);
+        let writer = CaptureWriter {
+            dir: dir.to_path_buf(),
+            files: HashMap::new(),
+            written: 0,
+            max_size,
+        };
+        tokio::task::spawn_blocking(move || writer.run(rx));
+
+        Ok(Self {
+            dir: dir.to_path_buf(),
+            tx,
+        })
+    }
+
+    /// Returns the path of the capture file of a peer
+    pub fn file_path(&self, peer_id: &impl Display) -> PathBuf {
+        capture_file_path(&self.dir, peer_id)
+    }
+
+    /// Queues a message to be appended to the capture file of the peer
+    ///
+    /// Failing to capture a message must not affect syncing, so errors are only logged.
+    pub fn record(
+        &mut self,
+        peer_id: &impl Display,
+        direction: Direction,
+        payload: CapturedPayload,
+    ) {
+        let message = CapturedMessage {
+            timestamp: time::get().as_millis() as u64,
+            peer_id: peer_id.to_string(),
+            direction,
+            payload,
+        };
+
+        self.send(WriterCommand::Write(message));
+    }
+
+    /// Closes the capture file of a disconnected peer
+    pub fn close(&mut self, peer_id: &impl Display) {
+        self.send(WriterCommand::Close(peer_id.to_string()));
+    }
+
+    /// Waits until the queued messages are written to the files
+    pub async fn flush(&mut self) {
+        let (tx, rx) = oneshot::channel();
+        self.send(WriterCommand::Flush(tx));
+        let _ = rx.await;
+    }
+
+    fn send(&mut self, command: WriterCommand) {
+        match self.tx.try_send(command) {
+            Ok(()) => {}
+            Err(TrySendError::Full(_)) => {
+                log::warn!("the message capture queue is full, a message isn't captured")
+            }
+            // The writer has stopped because the size limit is reached or it has failed
+            Err(TrySendError::Disconnected(_)) => {}
+        }
+    }
+}
+
+/// Writer task of the capture files
+struct CaptureWriter {
+    dir: PathBuf,
+    files: HashMap<String, BufWriter<File>>,
+
+    /// The number of bytes written to all files
+    written: u64,
+    max_size: u64,
+}
+
+impl CaptureWriter {
+    fn run(mut self, rx: Receiver<WriterCommand>) {
+        while let Ok(command) = rx.recv() {
+            match command {
+                WriterCommand::Write(message) => {
+                    let data = message.encode();
+                    if self.written.saturating_add(data.len() as u64) > self.max_size {
+                        log::warn!(
+                            "the message capture reached its size limit of {} bytes and is stopped",
+                            self.max_size
+                        );
+                        break;
+                    }
+
+                    match self.write(&message.peer_id, &data) {
+                        Ok(()) => self.written += data.len() as u64,
+                        Err(err) => log::error!(
+                            "failed to capture a message of peer {}: {err}",
+                            message.peer_id
+                        ),
+                    }
+                }
+                WriterCommand::Close(peer_id) => {
+                    if let Some(mut file) = self.files.remove(&peer_id) {
+                        if let Err(err) = file.flush() {
+                            log::error!(
+                                "failed to write the capture file of peer {peer_id}: {err}"
+                            );
+                        }
+                    }
+                }
+                WriterCommand::Flush(response) => {
+                    self.flush();
+                    let _ = response.send(());
+                }
+            }
+        }
+
+        self.flush();
+    }
+
+    fn write(&mut self, peer_id: &str, data: &[u8]) -> std::io::Result<()> {
+        let file = match self.files.get_mut(peer_id) {
+            Some(file) => file,
+            None => {
+                let path = capture_file_path(&self.dir, &peer_id);
+                let file = OpenOptions::new().create(true).append(true).open(path)?;
+                self.files.entry(peer_id.to_string()).or_insert_with(|| BufWriter::new(file))
+            }
+        };
+
+        file.write_all(data)
+    }
+
+    fn flush(&mut self) {
+        for (peer_id, file) in self.files.iter_mut() {
+            if let Err(err) = file.flush() {
+                log::error!("failed to write the capture file of peer {peer_id}: {err}");
+            }
+        }
+    }
+}
+
+/// Reads all messages of a capture file
+pub fn read_capture_file(path: &Path) -> crate::Result<Vec<CapturedMessage>> {
+    let data = fs::read(path)?;
+    let mut input = data.as_slice();
+
+    let mut messages = Vec::new();
+    while !input.is_empty() {
+        messages.push(CapturedMessage::decode(&mut input)?);
+    }
+    Ok(messages)
+}
+
+#[cfg(test)]
+mod tests {
+    use common::primitives::{Id, H256};
+
+    use super::*;
+    use crate::message::{BlockListRequest, HeaderListResponse};
+
+    #[tokio::test]
+    async fn write_and_read() {
+        let dir = std::env::temp_dir().join(format!("p2p-capture-{}", std::process::id()));
+        let mut capture = MessageCapture::new(&dir, u64::MAX).unwrap();
+
+        let payloads = [
+            (
+                Direction::Outbound,
+                CapturedPayload::Request(Request::BlockListRequest(BlockListRequest::new(vec![
+                    Id::new(H256([0x07; 32])),
+                ]))),
+            ),
+            (
+                Direction::Inbound,
+                CapturedPayload::Response(Response::HeaderListResponse(HeaderListResponse::new(
+                    Vec::new(),
+                ))),
+            ),
+        ];
+        for (direction, payload) in payloads.iter().cloned() {
+            capture.record(&"peer", direction, payload);
+        }
+        capture.close(&"peer");
+        capture.flush().await;
+
+        let messages = read_capture_file(&capture.file_path(&"peer")).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(messages.len(), payloads.len());
+        for (message, (direction, payload)) in messages.into_iter().zip(payloads) {
+            assert_eq!(message.peer_id, "peer");
+            assert_eq!(message.direction, direction);
+            assert_eq!(message.payload, payload);
+        }
+    }
+
+    #[tokio::test]
+    async fn capture_stops_at_size_limit() {
+        let dir = std::env::temp_dir().join(format!("p2p-capture-limit-{}", std::process::id()));
+        let payload =
+            CapturedPayload::Request(Request::BlockListRequest(BlockListRequest::new(vec![
+                Id::new(H256([0x07; 32])),
+            ])));
+        let message_size = CapturedMessage {
+            timestamp: 0,
+            peer_id: "peer".to_string(),
+            direction: Direction::Inbound,
+            payload: payload.clone(),
+        }
+        .encode()
+        .len() as u64;
+
+        // Only two messages fit into the limit
+        let mut capture = MessageCapture::new(&dir, message_size * 5 / 2).unwrap();
+        for _ in 0..4 {
+            capture.record(&"peer", Direction::Inbound, payload.clone());
+        }
+        capture.flush().await;
+
+        let messages = read_capture_file(&capture.file_path(&"peer")).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(messages.len(), 2);
+    }
+}
@@ -0,0 +1,244 @@
+// Copyright (c) 2022 RBB S.r.l
+// opensource@mintlayer.org
+// SPDX-License-Identifier: MIT
+// Licensed under the MIT License;
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://github.com/mintlayer/mintlayer-core/blob/master/LICENSE
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Compact block relay: blocks are sent with their transactions replaced by short ids and the
+//! receiver rebuilds them from its mempool, downloading only the transactions it's missing.
+
+use std::collections::{hash_map::Entry, HashMap};
+
+use common::{
+    chain::{block::Block, signed_transaction::SignedTransaction, Transaction},
+    primitives::{id::hash_encoded, Id, Idable},
+};
+
+use crate::message::{CompactBlock, PrefilledTransaction, ShortTxId};
+
+/// Calculates the short id of a transaction of the given block
+pub fn short_tx_id(block_id: &Id<Block>, salt: u64, tx_id: &Id<Transaction>) -> ShortTxId {
+    let hash = hash_encoded(&(block_id, salt, tx_id));
+    let mut bytes = [0; 6];
+    bytes.copy_from_slice(&hash.as_bytes()[..6]);
+    ShortTxId::new(bytes)
+}
+
+/// Creates a compact version of the block, sending the transactions at `prefilled_indexes` in full
+pub fn make_compact_block(block: &Block, salt: u64, prefilled_indexes: &[u32]) -> CompactBlock {
+    let block_id = block.get_id();
+    let mut short_ids = Vec::new();
+    let mut prefilled_txs = Vec::new();
+    for (index, tx) in (0..).zip(block.transactions()) {
+        if prefilled_indexes.contains(&index) {
+            prefilled_txs.push(PrefilledTransaction::new(index, tx.clone()));
+        } else {
+            short_ids.push(short_tx_id(&block_id, salt, &tx.transaction().get_id()));
+        }
+    }
+    CompactBlock::new(
+        block.header().clone(),
+        salt,
+        block.block_reward().clone(),
+        short_ids,
+        prefilled_txs,
+    )
+}
+
+/// A block that is being rebuilt from a compact block
+pub struct PartialBlock {
+    compact_block: CompactBlock,
+    txs: Vec<Option<SignedTransaction>>,
+}
+
+impl PartialBlock {
+    /// Fills in the prefilled transactions and the ones found among `mempool_txs`.
+    ///
+    /// Returns `None` if the compact block is malformed or if short ids collide, in which case
+    /// the full block has to be downloaded instead.
+    pub fn new(compact_block: CompactBlock, mempool_txs: Vec<SignedTransaction>) -> Option<Self> {
+        let tx_count = compact_block.short_ids().len() + compact_block.prefilled_txs().len();
+        let mut txs: Vec<Option<SignedTransaction>> = vec![None; tx_count];
+
+        for prefilled in compact_block.prefilled_txs() {
+            let slot = txs.get_mut(usize::try_from(prefilled.index()).ok()?)?;
+            if slot.is_some() {
+                return None;
+            }
+            *slot = Some(prefilled.tx().clone());
+        }
+
+        // Map the short ids to the slots of the transactions that aren't prefilled
+        let block_id = compact_block.header().block_id();
+        let mut slots = HashMap::new();
+        let empty_slots = txs.iter().enumerate().filter(|(_, tx)| tx.is_none()).map(|(i, _)| i);
+        for (short_id, slot) in compact_block.short_ids().iter().zip(empty_slots) {
+            if slots.insert(*short_id, slot).is_some() {
+                return None;
+            }
+        }
+
+        let mut found = HashMap::new();
+        for tx in mempool_txs {
+            let short_id = short_tx_id(&block_id, compact_block.salt(), &tx.transaction().get_id());
+            if let Some(slot) = slots.get(&short_id) {
+                match found.entry(*slot) {
+                    Entry::Vacant(entry) => {
+                        entry.insert(tx);
+                    }
+                    // Two mempool transactions with the same short id
+                    Entry::Occupied(_) => return None,
+                }
+            }
+        }
+        for (slot, tx) in found {
+            txs[slot] = Some(tx);
+        }
+
+        Some(Self { compact_block, txs })
+    }
+
+    pub fn block_id(&self) -> Id<Block> {
+        self.compact_block.header().block_id()
+    }
+
+    /// Indexes of the transactions that still have to be downloaded
+    pub fn missing_indexes(&self) -> Vec<u32> {
+        self.txs
+            .iter()
+            .enumerate()
+            .filter(|(_, tx)| tx.is_none())
+            .map(|(i, _)| i as u32)
+            .collect()
+    }
+
+    /// Fills in the downloaded transactions, given in the order of `missing_indexes()`.
+    /// Returns `false` if the number of transactions doesn't match.
+    pub fn fill_missing(&mut self, missing_txs: Vec<SignedTransaction>) -> bool {
+        let missing_indexes = self.missing_indexes();
+        if missing_indexes.len() != missing_txs.len() {
+            return false;
+        }
+        for (index, tx) in missing_indexes.into_iter().zip(missing_txs) {
+            self.txs[index as usize] = Some(tx);
+        }
+        true
+    }
+
+    /// Assembles the block. Returns `None` if transactions are still missing or if the result
+    /// doesn't match the header, e.g. because of a short id collision with a wrong transaction.
+    pub fn into_block(self) -> Option<Block> {
+        let txs = self.txs.into_iter().collect::<Option<Vec<_>>>()?;
+        let header = self.compact_block.header();
+        let block = Block::new(
+            txs,
+            *header.prev_block_id(),
+            header.timestamp(),
+            header.consensus_data().clone(),
+            self.compact_block.block_reward().clone(),
+        )
+        .ok()?;
+        (block.get_id() == header.block_id()).then_some(block)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common::{
+        chain::{
+            block::{timestamp::BlockTimestamp, BlockReward, ConsensusData},
+            signature::inputsig::InputWitness,
+            tokens::OutputValue,
+            Destination, OutPointSourceId, OutputPurpose, TxInput, TxOutput,
+        },
+        primitives::{Amount, H256},
+    };
+
+    fn make_tx(n: u8) -> SignedTransaction {
+        let tx = Transaction::new(
+            0,
+            vec![TxInput::new(OutPointSourceId::Transaction(Id::new(H256([n; 32]))), 0)],
+            vec![TxOutput::new(
+                OutputValue::Coin(Amount::from_atoms(n.into())),
+                OutputPurpose::Transfer(Destination::AnyoneCanSpend),
+            )],
+            0,
+        )
+        .unwrap();
+        SignedTransaction::new(tx, vec![InputWitness::NoSignature(None)]).unwrap()
+    }
+
+    fn make_block(txs: Vec<SignedTransaction>) -> Block {
+        Block::new(
+            txs,
+            Id::new(H256([0x01; 32])),
+            BlockTimestamp::from_int_seconds(1337),
+            ConsensusData::None,
+            BlockReward::new(Vec::new()),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn rebuild_from_mempool() {
+        let txs: Vec<_> = (0..5).map(make_tx).collect();
+        let block = make_block(txs.clone());
+        let compact_block = make_compact_block(&block, 42, &[]);
+
+        let mut mempool_txs = txs;
+        mempool_txs.push(make_tx(10));
+        let partial = PartialBlock::new(compact_block, mempool_txs).unwrap();
+        assert!(partial.missing_indexes().is_empty());
+        assert_eq!(partial.into_block(), Some(block));
+    }
+
+    #[test]
+    fn download_missing() {
+        let txs: Vec<_> = (0..5).map(make_tx).collect();
+        let block = make_block(txs.clone());
+        let compact_block = make_compact_block(&block, 7, &[0]);
+
+        let mempool_txs = vec![txs[1].clone(), txs[3].clone()];
+        let mut partial = PartialBlock::new(compact_block, mempool_txs).unwrap();
+        assert_eq!(partial.missing_indexes(), vec![2, 4]);
+        assert!(!partial.fill_missing(vec![txs[2].clone()]));
+        assert!(partial.fill_missing(vec![txs[2].clone(), txs[4].clone()]));
+        assert_eq!(partial.into_block(), Some(block));
+    }
+
+    #[test]
+    fn wrong_transaction_detected() {
+        let txs: Vec<_> = (0..2).map(make_tx).collect();
+        let block = make_block(txs.clone());
+        let mut partial = PartialBlock::new(make_compact_block(&block, 1, &[]), vec![]).unwrap();
+        assert!(partial.fill_missing(vec![txs[0].clone(), make_tx(9)]));
+        assert_eq!(partial.into_block(), None);
+    }
+
+    #[test]
+    fn duplicate_prefilled_index_rejected() {
+        let txs: Vec<_> = (0..2).map(make_tx).collect();
+        let block = make_block(txs.clone());
+        let compact_block = CompactBlock::new(
+            block.header().clone(),
+            0,
+            block.block_reward().clone(),
+            vec![],
+            vec![
+                PrefilledTransaction::new(0, txs[0].clone()),
+                PrefilledTransaction::new(0, txs[1].clone()),
+            ],
+        );
+        assert!(PartialBlock::new(compact_block, vec![]).is_none());
+    }
+}
@@ -0,0 +1,505 @@
+// Copyright (c) 2022 RBB S.r.l
+// opensource@mintlayer.org
+// SPDX-License-Identifier: MIT
+// Licensed under the MIT License;
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://github.com/mintlayer/mintlayer-core/blob/master/LICENSE
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Block download scheduler
+//!
+//! The headers received from peers form a queue of missing blocks. The blocks are spread across
+//! all peers that know about them, with a bounded number of requests in flight per peer, and are
+//! handed over to chainstate in the order of the headers, regardless of the order in which they
+//! arrive.
+
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+    hash::Hash,
+    time::{Duration, Instant},
+};
+
+use common::{
+    chain::block::{Block, BlockHeader},
+    primitives::{Id, Idable},
+};
+
+/// Per-peer download bookkeeping
+struct PeerDownloads {
+    /// Blocks the peer has sent headers for
+    known: HashSet<Id<Block>>,
+
+    /// Blocks requested from the peer for which no response has been received yet, including
+    /// the ones that have since been re-assigned to other peers
+    requested: HashSet<Id<Block>>,
+
+    /// Number of blocks currently assigned to the peer
+    in_flight: usize,
+
+    /// The peer has stalled the download and isn't assigned any blocks until then
+    stalled_until: Option<Instant>,
+}
+
+/// A block request assigned to a peer
+struct InFlight<P> {
+    peer_id: P,
+    requested_at: Instant,
+}
+
+pub struct DownloadScheduler<P> {
+    /// Maximum number of blocks assigned to a single peer at a time
+    max_in_flight: usize,
+
+    /// How long a peer may take to deliver a block before it's considered stalled
+    stall_timeout: Duration,
+
+    /// How long a stalled peer isn't assigned any blocks
+    stall_cooldown: Duration,
+
+    /// Sequence number given to the next new block, in the order the headers were received
+    next_seq: u64,
+
+    /// Blocks that haven't been handed over to chainstate yet, in order
+    pending: BTreeMap<u64, Id<Block>>,
+
+    /// Sequence numbers of the pending blocks
+    seqs: HashMap<Id<Block>, u64>,
+
+    /// Pending blocks that are neither requested nor received
+    queued: BTreeSet<u64>,
+
+    /// Pending blocks that are requested but not received
+    in_flight: HashMap<Id<Block>, InFlight<P>>,
+
+    /// Blocks that have been received out of order, along with the peer that sent them
+    received: HashMap<u64, (P, Block)>,
+
+    peers: HashMap<P, PeerDownloads>,
+}
+
+impl<P: Copy + Eq + Hash> DownloadScheduler<P> {
+    pub fn new(max_in_flight: usize, stall_timeout: Duration, stall_cooldown: Duration) -> Self {
+        Self {
+            max_in_flight,
+            stall_timeout,
+            stall_cooldown,
+            next_seq: 0,
+            pending: BTreeMap::new(),
+            seqs: HashMap::new(),
+            queued: BTreeSet::new(),
+            in_flight: HashMap::new(),
+            received: HashMap::new(),
+            peers: HashMap::new(),
+        }
+    }
+
+    /// Returns `true` if there are no blocks left to download
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+
+    /// Returns `true` if the block is being downloaded
+    pub fn contains(&self, block_id: &Id<Block>) -> bool {
+        self.seqs.contains_key(block_id)
+    }
+
+    /// Returns `true` if the block has been requested from the peer and not received yet
+    pub fn is_requested_from(&self, peer_id: &P, block_id: &Id<Block>) -> bool {
+        self.peers.get(peer_id).map_or(false, |peer| peer.requested.contains(block_id))
+    }
+
+    /// Returns `true` if nothing is assigned to the peer and no queued block can be assigned to it
+    pub fn is_peer_done(&self, peer_id: &P) -> bool {
+        match self.peers.get(peer_id) {
+            Some(peer) => {
+                peer.in_flight == 0
+                    && self.queued.iter().all(|seq| !peer.known.contains(&self.pending[seq]))
+            }
+            None => true,
+        }
+    }
+
+    /// Queues the blocks of headers received from the peer that aren't already queued
+    ///
+    /// The headers must be in order and contain only blocks that chainstate doesn't have.
+    pub fn add_headers(&mut self, peer_id: P, headers: &[BlockHeader]) {
+        let peer = self.peers.entry(peer_id).or_insert_with(|| PeerDownloads {
+            known: HashSet::new(),
+            requested: HashSet::new(),
+            in_flight: 0,
+            stalled_until: None,
+        });
+
+        for header in headers {
+            let block_id = header.get_id();
+            peer.known.insert(block_id);
+
+            if !self.seqs.contains_key(&block_id) {
+                let seq = self.next_seq;
+                self.next_seq += 1;
+                self.pending.insert(seq, block_id);
+                self.seqs.insert(block_id, seq);
+                self.queued.insert(seq);
+            }
+        }
+    }
+
+    /// Assigns queued blocks to the peers that know about them, preferring the peers with the
+    /// fewest blocks in flight. Peers that have recently stalled the download are skipped.
+    ///
+    /// Returns the block requests that have to be sent.
+    pub fn schedule(&mut self, now: Instant) -> Vec<(P, Id<Block>)> {
+        let mut assigned = Vec::new();
+
+        for seq in self.queued.iter().copied().collect::<Vec<_>>() {
+            let block_id = self.pending[&seq];
+            let peer_id = self
+                .peers
+                .iter()
+                .filter(|(_, peer)| {
+                    peer.in_flight < self.max_in_flight
+                        && peer.known.contains(&block_id)
+                        && peer.stalled_until.map_or(true, |until| until <= now)
+                })
+                .min_by_key(|(_, peer)| peer.in_flight)
+                .map(|(peer_id, _)| *peer_id);
+
+            if let Some(peer_id) = peer_id {
+                let peer = self.peers.get_mut(&peer_id).expect("peer to exist");
+                peer.in_flight += 1;
+                peer.requested.insert(block_id);
+                self.queued.remove(&seq);
+                self.in_flight.insert(
+                    block_id,
+                    InFlight {
+                        peer_id,
+                        requested_at: now,
+                    },
+                );
+                assigned.push((peer_id, block_id));
+            } else if self.peers.values().all(|peer| peer.in_flight >= self.max_in_flight) {
+                break;
+            }
+        }
+
+        assigned
+    }
+
+    /// Registers a block received from the peer
+    ///
+    /// Returns the blocks that are ready to be processed, in order, along with the peers that
+    /// sent them. Blocks that are no longer pending, e.g. because the download was abandoned,
+    /// are dropped.
+    pub fn block_received(&mut self, peer_id: P, block: Block) -> Vec<(P, Block)> {
+        let block_id = block.get_id();
+        if let Some(peer) = self.peers.get_mut(&peer_id) {
+            peer.requested.remove(&block_id);
+        }
+
+        let seq = match self.seqs.get(&block_id) {
+            Some(seq) if !self.received.contains_key(seq) => *seq,
+            _ => return Vec::new(),
+        };
+
+        // The block may have been re-assigned to a different peer in the meantime
+        if let Some(in_flight) = self.in_flight.remove(&block_id) {
+            if let Some(peer) = self.peers.get_mut(&in_flight.peer_id) {
+                peer.in_flight -= 1;
+            }
+        }
+        self.queued.remove(&seq);
+        self.received.insert(seq, (peer_id, block));
+
+        let mut ready = Vec::new();
+        while let Some((&seq, _)) = self.pending.iter().next() {
+            match self.received.remove(&seq) {
+                Some((peer_id, block)) => {
+                    let block_id = self.pending.remove(&seq).expect("block to be pending");
+                    self.seqs.remove(&block_id);
+                    ready.push((peer_id, block));
+                }
+                None => break,
+            }
+        }
+        ready
+    }
+
+    /// Puts the block back to the queue if it's assigned to the peer
+    pub fn release(&mut self, peer_id: &P, block_id: &Id<Block>) {
+        if self
+            .in_flight
+            .get(block_id)
+            .map_or(false, |in_flight| &in_flight.peer_id == peer_id)
+        {
+            self.in_flight.remove(block_id);
+            self.queued.insert(self.seqs[block_id]);
+            if let Some(peer) = self.peers.get_mut(peer_id) {
+                peer.in_flight -= 1;
+            }
+        }
+    }
+
+    /// Puts the block back to the queue after the peer has responded that it doesn't have it
+    ///
+    /// The block isn't assigned to the peer again. If no other peer knows about the block, the
+    /// download is abandoned.
+    pub fn block_unavailable(&mut self, peer_id: &P, block_id: &Id<Block>) {
+        self.release(peer_id, block_id);
+        if let Some(peer) = self.peers.get_mut(peer_id) {
+            peer.requested.remove(block_id);
+            peer.known.remove(block_id);
+        }
+
+        let unreachable = self.seqs.contains_key(block_id)
+            && self.peers.values().all(|peer| !peer.known.contains(block_id));
+        if unreachable {
+            self.abandon();
+        }
+    }
+
+    /// Puts all blocks assigned to the peer back to the queue
+    ///
+    /// The peer is still allowed to deliver them later.
+    pub fn release_peer(&mut self, peer_id: &P) {
+        let block_ids = self
+            .in_flight
+            .iter()
+            .filter(|(_, in_flight)| &in_flight.peer_id == peer_id)
+            .map(|(block_id, _)| *block_id)
+            .collect::<Vec<_>>();
+        for block_id in block_ids {
+            self.release(peer_id, &block_id);
+        }
+    }
+
+    /// Puts all blocks assigned to a stalled peer back to the queue and doesn't assign it any
+    /// blocks for the stall cooldown, so that they go to other peers
+    ///
+    /// The peer is still allowed to deliver them later.
+    pub fn release_stalled_peer(&mut self, peer_id: &P, now: Instant) {
+        self.release_peer(peer_id);
+        if let Some(peer) = self.peers.get_mut(peer_id) {
+            peer.stalled_until = Some(now + self.stall_cooldown);
+        }
+    }
+
+    /// Removes a disconnected peer and puts its blocks back to the queue
+    ///
+    /// If some blocks can no longer be downloaded from any peer, the download is abandoned.
+    pub fn remove_peer(&mut self, peer_id: &P) {
+        self.release_peer(peer_id);
+        self.peers.remove(peer_id);
+
+        let unreachable = self.queued.iter().any(|seq| {
+            let block_id = &self.pending[seq];
+            self.peers.values().all(|peer| !peer.known.contains(block_id))
+        });
+        if unreachable {
+            self.abandon();
+        }
+    }
+
+    /// Drops all pending blocks, e.g. after one of them turned out to be invalid
+    ///
+    /// Requests that are still in flight are remembered so that their responses are ignored.
+    pub fn abandon(&mut self) {
+        self.pending.clear();
+        self.seqs.clear();
+        self.queued.clear();
+        self.in_flight.clear();
+        self.received.clear();
+        for peer in self.peers.values_mut() {
+            peer.known.clear();
+            peer.in_flight = 0;
+        }
+    }
+
+    /// Returns the peers that have had a block in flight for longer than the stall timeout
+    pub fn stalled_peers(&self, now: Instant) -> Vec<P> {
+        let mut stalled = Vec::new();
+        for in_flight in self.in_flight.values() {
+            if now.saturating_duration_since(in_flight.requested_at) > self.stall_timeout
+                && !stalled.contains(&in_flight.peer_id)
+            {
+                stalled.push(in_flight.peer_id);
+            }
+        }
+        stalled
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common::{
+        chain::{
+            block::{timestamp::BlockTimestamp, BlockReward, ConsensusData},
+            GenBlock,
+        },
+        primitives::H256,
+    };
+
+    const MAX_IN_FLIGHT: usize = 2;
+    const STALL_TIMEOUT: Duration = Duration::from_secs(10);
+    const STALL_COOLDOWN: Duration = Duration::from_secs(60);
+
+    fn make_blocks(count: usize) -> Vec<Block> {
+        let mut prev_block_id: Id<GenBlock> = Id::new(H256([0x01; 32]));
+        let mut blocks = Vec::new();
+        for i in 0..count {
+            let block = Block::new(
+                vec![],
+                prev_block_id,
+                BlockTimestamp::from_int_seconds(i as u64),
+                ConsensusData::None,
+                BlockReward::new(Vec::new()),
+            )
+            .unwrap();
+            prev_block_id = block.get_id().into();
+            blocks.push(block);
+        }
+        blocks
+    }
+
+    fn headers(blocks: &[Block]) -> Vec<BlockHeader> {
+        blocks.iter().map(|block| block.header().clone()).collect()
+    }
+
+    #[test]
+    fn spreads_blocks_across_peers() {
+        let blocks = make_blocks(5);
+        let mut scheduler = DownloadScheduler::new(MAX_IN_FLIGHT, STALL_TIMEOUT, STALL_COOLDOWN);
+        scheduler.add_headers(1u64, &headers(&blocks));
+        scheduler.add_headers(2u64, &headers(&blocks));
+
+        let assigned = scheduler.schedule(Instant::now());
+        assert_eq!(assigned.len(), 2 * MAX_IN_FLIGHT);
+        for peer_id in [1, 2] {
+            assert_eq!(
+                assigned.iter().filter(|(id, _)| *id == peer_id).count(),
+                MAX_IN_FLIGHT
+            );
+        }
+        assert!(scheduler.schedule(Instant::now()).is_empty());
+    }
+
+    #[test]
+    fn blocks_released_in_order() {
+        let blocks = make_blocks(3);
+        let mut scheduler = DownloadScheduler::new(MAX_IN_FLIGHT, STALL_TIMEOUT, STALL_COOLDOWN);
+        scheduler.add_headers(1u64, &headers(&blocks));
+        scheduler.add_headers(2u64, &headers(&blocks));
+        let assigned = scheduler.schedule(Instant::now());
+        assert_eq!(assigned.len(), 3);
+
+        let peer_of =
+            |block: &Block| assigned.iter().find(|(_, id)| id == &block.get_id()).unwrap().0;
+
+        assert!(scheduler.block_received(peer_of(&blocks[2]), blocks[2].clone()).is_empty());
+        assert!(scheduler.block_received(peer_of(&blocks[1]), blocks[1].clone()).is_empty());
+        let ready = scheduler.block_received(peer_of(&blocks[0]), blocks[0].clone());
+        assert_eq!(
+            ready.into_iter().map(|(_, block)| block).collect::<Vec<_>>(),
+            blocks
+        );
+        assert!(scheduler.is_empty());
+    }
+
+    #[test]
+    fn only_known_blocks_assigned() {
+        let blocks = make_blocks(3);
+        let mut scheduler = DownloadScheduler::new(MAX_IN_FLIGHT, STALL_TIMEOUT, STALL_COOLDOWN);
+        scheduler.add_headers(1u64, &headers(&blocks[..1]));
+        scheduler.add_headers(2u64, &headers(&blocks));
+
+        let assigned = scheduler.schedule(Instant::now());
+        assert_eq!(
+            assigned,
+            vec![(1, blocks[0].get_id()), (2, blocks[1].get_id()), (2, blocks[2].get_id())]
+        );
+        assert!(!scheduler.is_peer_done(&1));
+        scheduler.block_received(1, blocks[0].clone());
+        assert!(scheduler.is_peer_done(&1));
+        assert!(!scheduler.is_peer_done(&2));
+    }
+
+    #[test]
+    fn stalled_peer_blocks_reassigned() {
+        let blocks = make_blocks(2);
+        let mut scheduler = DownloadScheduler::new(MAX_IN_FLIGHT, STALL_TIMEOUT, STALL_COOLDOWN);
+        scheduler.add_headers(1u64, &headers(&blocks));
+        let now = Instant::now();
+        assert_eq!(scheduler.schedule(now).len(), 2);
+
+        assert!(scheduler.stalled_peers(now + STALL_TIMEOUT).is_empty());
+        let later = now + 2 * STALL_TIMEOUT;
+        assert_eq!(scheduler.stalled_peers(later), vec![1]);
+
+        scheduler.add_headers(2u64, &headers(&blocks));
+        scheduler.release_stalled_peer(&1, later);
+        let assigned = scheduler.schedule(later);
+        assert_eq!(assigned.len(), 2);
+        assert!(assigned.iter().all(|(peer_id, _)| *peer_id == 2));
+
+        // A late response from the stalled peer is still accepted
+        assert!(scheduler.is_requested_from(&1, &blocks[0].get_id()));
+        assert_eq!(scheduler.block_received(1, blocks[0].clone()).len(), 1);
+    }
+
+    #[test]
+    fn stalled_peer_skipped_until_cooldown_ends() {
+        let blocks = make_blocks(2);
+        let mut scheduler = DownloadScheduler::new(MAX_IN_FLIGHT, STALL_TIMEOUT, STALL_COOLDOWN);
+        scheduler.add_headers(1u64, &headers(&blocks));
+        let now = Instant::now();
+        assert_eq!(scheduler.schedule(now).len(), 2);
+
+        let later = now + 2 * STALL_TIMEOUT;
+        scheduler.release_stalled_peer(&1, later);
+        assert!(scheduler.schedule(later).is_empty());
+        assert!(scheduler.schedule(later + STALL_COOLDOWN / 2).is_empty());
+
+        let assigned = scheduler.schedule(later + STALL_COOLDOWN);
+        assert_eq!(assigned.len(), 2);
+        assert!(assigned.iter().all(|(peer_id, _)| *peer_id == 1));
+    }
+
+    #[test]
+    fn unavailable_block_reassigned() {
+        let blocks = make_blocks(1);
+        let mut scheduler = DownloadScheduler::new(MAX_IN_FLIGHT, STALL_TIMEOUT, STALL_COOLDOWN);
+        scheduler.add_headers(1u64, &headers(&blocks));
+        scheduler.add_headers(2u64, &headers(&blocks));
+        let now = Instant::now();
+        let assigned = scheduler.schedule(now);
+        assert_eq!(assigned.len(), 1);
+        let (peer_id, block_id) = assigned[0];
+        let other_peer_id = if peer_id == 1 { 2 } else { 1 };
+
+        scheduler.block_unavailable(&peer_id, &block_id);
+        assert!(!scheduler.is_requested_from(&peer_id, &block_id));
+        assert_eq!(scheduler.schedule(now), vec![(other_peer_id, block_id)]);
+
+        // The download is abandoned once no peer has the block
+        scheduler.block_unavailable(&other_peer_id, &block_id);
+        assert!(scheduler.is_empty());
+    }
+
+    #[test]
+    fn abandon_on_unreachable_blocks() {
+        let blocks = make_blocks(2);
+        let mut scheduler = DownloadScheduler::new(MAX_IN_FLIGHT, STALL_TIMEOUT, STALL_COOLDOWN);
+        scheduler.add_headers(1u64, &headers(&blocks));
+        scheduler.add_headers(2u64, &headers(&blocks[..1]));
+        scheduler.remove_peer(&1);
+        assert!(scheduler.is_empty());
+        assert!(!scheduler.contains(&blocks[0].get_id()));
+    }
+}
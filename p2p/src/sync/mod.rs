@@ -16,44 +16,90 @@
 //! This module is responsible for both initial syncing and further blocks processing (the reaction
 //! to block announcement from peers and the announcement of blocks produced by this node).
 
+pub mod capture;
 pub mod peer;
 
+mod compact_block;
+mod download;
+mod progress;
 mod request;
 
-use std::{collections::HashMap, sync::Arc};
+use std::{
+    collections::HashMap,
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
+use crypto::random::{rngs::StdRng, Rng};
 use futures::FutureExt;
 use tokio::sync::{mpsc, oneshot};
 use void::Void;
 
-use chainstate::{ban_score::BanScore, chainstate_interface, BlockError, ChainstateError, Locator};
+use chainstate::{
+    ban_score::BanScore, chainstate_interface, BlockError, ChainstateError, Locator,
+    OrphanCheckError,
+};
 use common::{
     chain::{
         block::{Block, BlockHeader},
         config::ChainConfig,
+        signed_transaction::SignedTransaction,
+        GenBlock,
     },
-    primitives::{Id, Idable},
+    primitives::{time, BlockHeight, Id, Idable},
 };
 use logging::log;
+use mempool::MempoolInterface;
+use serialization::Encode;
 use utils::ensure;
 
 use crate::{
-    error::{P2pError, PeerError, ProtocolError},
+    config::P2pConfig,
+    error::{P2pError, PeerError, ProtocolError, PublishError},
     event::{PeerManagerEvent, SyncControlEvent},
+    interface::types::SyncProgress,
     message::{self, Announcement},
     net::{
         self,
-        types::{PubSubTopic, SyncingEvent, ValidationResult},
+        types::{PubSubTopic, Service, Services, SyncingEvent, ValidationResult},
         NetworkingService, SyncingMessagingService,
     },
+    peer_manager::rate_limiter::TokenBucket,
 };
 
-// TODO: from config? global constant?
-const HEADER_LIMIT: usize = 2000;
-
 // TODO: this comes from spec?
 const RETRY_LIMIT: usize = 3;
 
+/// Maximum number of blocks requested from a single peer at a time during the initial sync
+const MAX_BLOCKS_IN_FLIGHT_PER_PEER: usize = 16;
+
+/// A peer that doesn't deliver a requested block within this time is penalized and its blocks
+/// are requested from other peers
+const BLOCK_DOWNLOAD_STALL_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// A peer that has stalled the block download isn't asked for blocks for this long
+const BLOCK_DOWNLOAD_STALL_COOLDOWN: Duration = Duration::from_secs(120);
+
+/// How often the block downloads, the requests and the chain are checked for stalled peers
+const STALL_CHECK_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Time a peer has to respond to a header request
+const HEADER_REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Time a peer has to respond to a block request
+const BLOCK_REQUEST_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Time a peer has to respond to the other requests
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// If the best block doesn't change for this long before the node is synced, the peers that
+/// should be providing the blocks are disconnected so that the peer manager can replace them
+const CHAIN_STALL_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// A peer whose best known block is more than this many blocks behind the local best block
+/// can't help the local node to sync
+const MAX_PEER_HEIGHT_LAG: u64 = 1000;
+
 // TODO: add more tests
 // TODO: cache locator and invalidate it when `NewTip` event is received
 
@@ -76,12 +122,16 @@ pub enum SyncState {
 /// It keeps track of the state of each individual peer and holds an intermediary block index
 /// which represents the local block index of every peer it's connected to.
 ///
-/// Currently its only mode of operation is greedy so it will download all changes from every
-/// peer it's connected to and actively keep track of the peer's state.
+/// The blocks of the headers received from all peers are downloaded in parallel by the
+/// [`download::DownloadScheduler`], while blocks announced later are downloaded one at a time
+/// from the announcing peer.
 pub struct BlockSyncManager<T: NetworkingService> {
     /// Chain config
     config: Arc<ChainConfig>,
 
+    /// P2P config
+    p2p_config: Arc<P2pConfig>,
+
     /// Syncing state of the local node
     state: SyncState,
 
@@ -100,8 +150,41 @@ pub struct BlockSyncManager<T: NetworkingService> {
     /// Subsystem handle to Chainstate
     chainstate_handle: subsystem::Handle<Box<dyn chainstate_interface::ChainstateInterface>>,
 
+    /// Subsystem handle to Mempool, used to rebuild compact blocks
+    mempool_handle: subsystem::Handle<Box<dyn MempoolInterface>>,
+
     /// Pending requests
     requests: HashMap<T::SyncingPeerRequestId, request::RequestState<T>>,
+
+    /// Compact blocks waiting for their missing transactions, by the peer that sent them
+    partial_blocks: HashMap<T::PeerId, compact_block::PartialBlock>,
+
+    /// Blocks being downloaded from multiple peers
+    downloads: download::DownloadScheduler<T::PeerId>,
+
+    /// Rate limiters for the requests received from peers
+    request_rate_limiters: HashMap<T::PeerId, TokenBucket>,
+
+    /// Rate limiters for the number of blocks requested by peers
+    block_request_rate_limiters: HashMap<T::PeerId, TokenBucket>,
+
+    /// Rate limiters for the block bytes sent to peers
+    block_upload_rate_limiters: HashMap<T::PeerId, TokenBucket>,
+
+    /// Writer of the captured messages, if the capture is enabled
+    capture: Option<capture::MessageCapture>,
+
+    /// When the local best block last changed
+    chain_progress: progress::ChainProgress,
+
+    /// Height of the best header received from peers
+    best_header_height: BlockHeight,
+
+    /// Blocks requested over RPC, with the peers that haven't been asked for them yet
+    block_fetches: HashMap<Id<Block>, Vec<T::PeerId>>,
+
+    /// Random number generator of the compact block salts
+    rng: StdRng,
 }
 
 /// Syncing manager
@@ -112,20 +195,46 @@ where
 {
     pub fn new(
         config: Arc<ChainConfig>,
+        p2p_config: Arc<P2pConfig>,
         handle: T::SyncingMessagingHandle,
         chainstate_handle: subsystem::Handle<Box<dyn chainstate_interface::ChainstateInterface>>,
+        mempool_handle: subsystem::Handle<Box<dyn MempoolInterface>>,
         rx_sync: mpsc::UnboundedReceiver<SyncControlEvent<T>>,
         tx_peer_manager: mpsc::UnboundedSender<PeerManagerEvent<T>>,
     ) -> Self {
+        let capture = (*p2p_config.message_capture_dir).as_ref().and_then(|dir| {
+            capture::MessageCapture::new(dir, *p2p_config.message_capture_max_size)
+                .map_err(|err| log::error!("failed to enable the message capture: {err}"))
+                .ok()
+        });
+
+        let rng = p2p_config.make_rng("sync");
+
         Self {
             config,
+            p2p_config,
             peer_sync_handle: handle,
             rx_sync,
             tx_peer_manager,
             chainstate_handle,
+            mempool_handle,
             peers: Default::default(),
             requests: HashMap::new(),
+            partial_blocks: HashMap::new(),
+            downloads: download::DownloadScheduler::new(
+                MAX_BLOCKS_IN_FLIGHT_PER_PEER,
+                BLOCK_DOWNLOAD_STALL_TIMEOUT,
+                BLOCK_DOWNLOAD_STALL_COOLDOWN,
+            ),
+            request_rate_limiters: HashMap::new(),
+            block_request_rate_limiters: HashMap::new(),
+            block_upload_rate_limiters: HashMap::new(),
+            capture,
+            chain_progress: progress::ChainProgress::new(BlockHeight::zero(), Instant::now()),
+            best_header_height: BlockHeight::zero(),
+            block_fetches: HashMap::new(),
             state: SyncState::Uninitialized,
+            rng,
         }
     }
 
@@ -143,7 +252,13 @@ where
     }
 
     /// Register peer to the `SyncManager`
-    pub async fn register_peer(&mut self, peer_id: T::PeerId) -> crate::Result<()> {
+    ///
+    /// Only the services advertised by the peer that the local node also supports are used.
+    pub async fn register_peer(
+        &mut self,
+        peer_id: T::PeerId,
+        services: Services,
+    ) -> crate::Result<()> {
         ensure!(
             !self.peers.contains_key(&peer_id),
             P2pError::PeerError(PeerError::PeerAlreadyExists),
@@ -161,14 +276,31 @@ where
         .map(|_| {
             self.peers.insert(
                 peer_id,
-                peer::PeerContext::new_with_locator(peer_id, locator),
+                peer::PeerContext::new_with_locator(
+                    peer_id,
+                    locator,
+                    services.intersection(&self.p2p_config.local_services()),
+                ),
             );
         })
     }
 
     /// Unregister peer from the `SyncManager`
     pub fn unregister_peer(&mut self, peer_id: T::PeerId) {
-        self.peers.remove(&peer_id);
+        if let Some(peer) = self.peers.remove(&peer_id) {
+            if let peer::PeerSyncState::UploadingBlocks(block_id) = peer.state() {
+                self.block_fetches.remove(block_id);
+            }
+        }
+        self.partial_blocks.remove(&peer_id);
+        self.downloads.remove_peer(&peer_id);
+        self.request_rate_limiters.remove(&peer_id);
+        self.block_request_rate_limiters.remove(&peer_id);
+        self.block_upload_rate_limiters.remove(&peer_id);
+        self.requests.retain(|_, request| request.peer_id != peer_id);
+        if let Some(capture) = &mut self.capture {
+            capture.close(&peer_id);
+        }
     }
 
     /// Process header request
@@ -180,9 +312,15 @@ where
     ) -> crate::Result<()> {
         log::debug!("send header response to peer {peer_id}, request_id: {request_id:?}");
 
+        // The first block of the locator is the best block of the peer
+        if let Some(block_id) = locator.iter().next() {
+            let block_id = *block_id;
+            self.register_known_block(peer_id, block_id).await?;
+        }
+
         // TODO: check if remote has already asked for these headers?
         let headers = self.chainstate_handle.call(move |this| this.get_headers(locator)).await??;
-        self.send_header_response(request_id, headers).await
+        self.send_header_response(peer_id, request_id, headers).await
     }
 
     /// Process block request
@@ -208,27 +346,113 @@ where
         let block_result =
             self.chainstate_handle.call(move |this| this.get_block(block_id)).await?;
 
+        // An empty response tells the peer that the block isn't known, so that it can ask
+        // other peers for it
         match block_result {
-            Ok(Some(block)) => self.send_block_response(request_id, vec![block]).await,
-            Ok(None) => {
-                // TODO: check if remote has already asked for these headers?
-                Err(P2pError::ProtocolError(ProtocolError::InvalidMessage))
+            Ok(Some(block)) => {
+                if !self.take_block_upload_tokens(&peer_id, block.encoded_size()) {
+                    self.peer_sync_handle.drop_request(request_id).await?;
+                    return Err(P2pError::ProtocolError(
+                        ProtocolError::RequestRateLimitExceeded,
+                    ));
+                }
+                self.send_block_response(peer_id, request_id, vec![block]).await
             }
+            Ok(None) => self.send_block_response(peer_id, request_id, vec![]).await,
             Err(err) => Err(P2pError::ChainstateError(err)),
         }
     }
 
+    /// Process compact block request
+    pub async fn process_compact_block_request(
+        &mut self,
+        peer_id: T::PeerId,
+        request_id: T::SyncingPeerRequestId,
+        block_id: Id<Block>,
+    ) -> crate::Result<()> {
+        ensure!(
+            self.peers.contains_key(&peer_id),
+            P2pError::PeerError(PeerError::PeerDoesntExist),
+        );
+
+        let block = self
+            .chainstate_handle
+            .call(move |this| this.get_block(block_id))
+            .await??
+            .ok_or(P2pError::ProtocolError(ProtocolError::InvalidMessage))?;
+        let salt = self.rng.gen::<u64>();
+        let compact_block = compact_block::make_compact_block(&block, salt, &[]);
+        self.send_compact_block_response(peer_id, request_id, compact_block).await
+    }
+
+    /// Process request for transactions of a compact block
+    pub async fn process_block_transactions_request(
+        &mut self,
+        peer_id: T::PeerId,
+        request_id: T::SyncingPeerRequestId,
+        request: message::BlockTransactionsRequest,
+    ) -> crate::Result<()> {
+        ensure!(
+            self.peers.contains_key(&peer_id),
+            P2pError::PeerError(PeerError::PeerDoesntExist),
+        );
+
+        let block_id = *request.block_id();
+        let block = self
+            .chainstate_handle
+            .call(move |this| this.get_block(block_id))
+            .await??
+            .ok_or(P2pError::ProtocolError(ProtocolError::InvalidMessage))?;
+        let txs = request
+            .indexes()
+            .iter()
+            .map(|index| {
+                usize::try_from(*index)
+                    .ok()
+                    .and_then(|index| block.transactions().get(index))
+                    .cloned()
+                    .ok_or(P2pError::ProtocolError(ProtocolError::InvalidMessage))
+            })
+            .collect::<crate::Result<Vec<_>>>()?;
+        self.send_block_transactions_response(peer_id, request_id, block_id, txs).await
+    }
+
+    /// Process request for the addresses of other peers
+    ///
+    /// The addresses are provided by the peer manager, which also decides how often the
+    /// peer is allowed to ask for them.
+    pub async fn process_addr_list_request(
+        &mut self,
+        peer_id: T::PeerId,
+        request_id: T::SyncingPeerRequestId,
+    ) -> crate::Result<()> {
+        let (tx, rx) = oneshot::channel();
+        self.tx_peer_manager
+            .send(PeerManagerEvent::GetAddresses(peer_id, tx))
+            .map_err(P2pError::from)?;
+        let addresses = rx.await.map_err(P2pError::from)?;
+        self.send_addr_list_response(peer_id, request_id, addresses).await
+    }
+
+    /// Process response to an address list request by passing the addresses to the peer manager
+    pub async fn process_addr_list_response(
+        &mut self,
+        peer_id: T::PeerId,
+        addresses: Vec<message::PeerAddress>,
+    ) -> crate::Result<()> {
+        let (tx, rx) = oneshot::channel();
+        self.tx_peer_manager
+            .send(PeerManagerEvent::AddressesReceived(peer_id, addresses, tx))
+            .map_err(P2pError::from)?;
+        rx.await.map_err(P2pError::from)?
+    }
+
     /// Validate incoming header response
     async fn validate_header_response(
         &mut self,
         peer_id: &T::PeerId,
         headers: Vec<BlockHeader>,
-    ) -> crate::Result<Option<BlockHeader>> {
-        ensure!(
-            headers.len() <= HEADER_LIMIT,
-            P2pError::ProtocolError(ProtocolError::InvalidMessage),
-        );
-
+    ) -> crate::Result<Vec<BlockHeader>> {
         let peer = self
             .peers
             .get_mut(peer_id)
@@ -236,7 +460,8 @@ where
 
         // empty response means that local and remote are in sync
         if headers.is_empty() {
-            return Ok(None);
+            peer.register_header_response();
+            return Ok(headers);
         }
 
         // verify that the first headers attaches to local and chain
@@ -268,8 +493,8 @@ where
             .await?
         {
             Ok(headers) => {
-                peer.register_header_response(&headers);
-                Ok(peer.get_header_for_download())
+                peer.register_header_response();
+                Ok(headers)
             }
             Err(err) => Err(P2pError::ChainstateError(err)),
         }
@@ -281,40 +506,308 @@ where
         peer_id: T::PeerId,
         headers: Vec<BlockHeader>,
     ) -> crate::Result<()> {
-        match self.validate_header_response(&peer_id, headers).await {
-            Ok(Some(header)) => self.send_block_request(peer_id, header.get_id(), 0).await,
-            Ok(None) => {
-                self.peers
-                    .get_mut(&peer_id)
-                    .ok_or(P2pError::PeerError(PeerError::PeerDoesntExist))?
-                    .set_state(peer::PeerSyncState::Idle);
-                Ok(())
+        let headers = self.validate_header_response(&peer_id, headers).await?;
+        if let Some(header) = headers.last() {
+            self.report_to_peer_manager(PeerManagerEvent::BestKnownBlock(peer_id, header.get_id()));
+            self.register_headers_height(peer_id, &headers).await?;
+        }
+
+        // Pruned peers may not have the blocks, so they're downloaded from full nodes only
+        if self.peer_supports(&peer_id, Service::FullNode) {
+            self.downloads.add_headers(peer_id, &headers);
+        }
+        self.schedule_block_downloads().await
+    }
+
+    /// Record that a peer has the given block if the local node knows its height
+    async fn register_known_block(
+        &mut self,
+        peer_id: T::PeerId,
+        block_id: Id<GenBlock>,
+    ) -> crate::Result<()> {
+        let index = self
+            .chainstate_handle
+            .call(move |this| this.get_gen_block_index(&block_id))
+            .await??;
+        if let (Some(index), Some(peer)) = (index, self.peers.get_mut(&peer_id)) {
+            peer.register_known_height(index.block_height());
+        }
+        Ok(())
+    }
+
+    /// Record the height of the last of the new headers received from a peer
+    async fn register_headers_height(
+        &mut self,
+        peer_id: T::PeerId,
+        headers: &[BlockHeader],
+    ) -> crate::Result<()> {
+        let parent_id = *headers[0].prev_block_id();
+        let parent = self
+            .chainstate_handle
+            .call(move |this| this.get_gen_block_index(&parent_id))
+            .await??;
+        let height = match parent.and_then(|p| p.block_height().checked_add(headers.len() as u64)) {
+            Some(height) => height,
+            None => return Ok(()),
+        };
+
+        self.best_header_height = std::cmp::max(self.best_header_height, height);
+        if let Some(peer) = self.peers.get_mut(&peer_id) {
+            peer.register_known_height(height);
+        }
+        Ok(())
+    }
+
+    /// Send requests for the blocks the download scheduler assigned to peers
+    async fn schedule_block_downloads(&mut self) -> crate::Result<()> {
+        for (peer_id, block_id) in self.downloads.schedule(Instant::now()) {
+            log::trace!("send block request to {peer_id}, block id {block_id}");
+
+            let (request, request_type) = self.make_block_request(vec![block_id]);
+            self.send_request(peer_id, request, request_type, 0).await?;
+        }
+        Ok(())
+    }
+
+    /// Penalize the peers that don't deliver the requested blocks in time and request the blocks
+    /// from other peers
+    async fn process_stalled_downloads(&mut self) -> crate::Result<()> {
+        let now = Instant::now();
+        let stalled_peers = self.downloads.stalled_peers(now);
+        if stalled_peers.is_empty() {
+            return Ok(());
+        }
+
+        for peer_id in stalled_peers {
+            log::warn!("peer {peer_id} stalled the block download");
+
+            self.downloads.release_stalled_peer(&peer_id, now);
+            self.handle_error(
+                peer_id,
+                Err(P2pError::ProtocolError(ProtocolError::StalledBlockDownload)),
+            )
+            .await?;
+        }
+        self.schedule_block_downloads().await
+    }
+
+    /// Returns true if the service is supported by both the local node and the peer
+    fn peer_supports(&self, peer_id: &T::PeerId, service: Service) -> bool {
+        self.peers.get(peer_id).map_or(false, |peer| peer.services().has(service))
+    }
+
+    /// Send peer statistics to the peer manager
+    ///
+    /// The statistics are only used to select the inbound peers to evict and to report
+    /// the peer information over RPC, so a failure to deliver them is not an error.
+    fn report_to_peer_manager(&self, event: PeerManagerEvent<T>) {
+        let _ = self.tx_peer_manager.send(event);
+    }
+
+    /// Get the sender, the type and the encoded size of a message received from a peer
+    fn received_message_info(event: &SyncingEvent<T>) -> Option<(T::PeerId, &'static str, usize)> {
+        match event {
+            SyncingEvent::Request {
+                peer_id, request, ..
+            } => Some((*peer_id, request.kind(), request.encoded_size())),
+            SyncingEvent::Response {
+                peer_id, response, ..
+            } => Some((*peer_id, response.kind(), response.encoded_size())),
+            SyncingEvent::Announcement {
+                peer_id,
+                announcement,
+                ..
+            } => Some((*peer_id, announcement.kind(), announcement.encoded_size())),
+            SyncingEvent::Error { .. } => None,
+        }
+    }
+
+    /// Record a message received from a peer if the capture is enabled
+    fn capture_received(&mut self, event: &SyncingEvent<T>) {
+        let capture = match &mut self.capture {
+            Some(capture) => capture,
+            None => return,
+        };
+
+        let (peer_id, payload) = match event {
+            SyncingEvent::Request {
+                peer_id, request, ..
+            } => (peer_id, capture::CapturedPayload::Request(request.clone())),
+            SyncingEvent::Response {
+                peer_id, response, ..
+            } => (
+                peer_id,
+                capture::CapturedPayload::Response(response.clone()),
+            ),
+            SyncingEvent::Announcement {
+                peer_id,
+                announcement,
+                ..
+            } => (
+                peer_id,
+                capture::CapturedPayload::Announcement(announcement.clone()),
+            ),
+            SyncingEvent::Error { .. } => return,
+        };
+        capture.record(peer_id, capture::Direction::Inbound, payload);
+    }
+
+    /// Publish an announcement, capturing it for every connected peer if the capture is enabled
+    async fn make_announcement(&mut self, announcement: Announcement) -> crate::Result<()> {
+        if let Some(capture) = &mut self.capture {
+            for peer_id in self.peers.keys() {
+                capture.record(
+                    peer_id,
+                    capture::Direction::Outbound,
+                    capture::CapturedPayload::Announcement(announcement.clone()),
+                );
             }
-            Err(err) => Err(err),
         }
+        self.peer_sync_handle.make_announcement(announcement).await
+    }
+
+    /// Take the tokens for sending `size` bytes of blocks to the peer from its upload rate limiter
+    ///
+    /// Returns `false` if the peer has exceeded the limit. Whitelisted peers aren't rate limited.
+    fn take_block_upload_tokens(&mut self, peer_id: &T::PeerId, size: usize) -> bool {
+        if self.peers.get(peer_id).map_or(false, |peer| peer.is_whitelisted()) {
+            return true;
+        }
+        let limiter = self.block_upload_rate_limiters.entry(*peer_id).or_insert_with(|| {
+            TokenBucket::new(
+                *self.p2p_config.block_upload_rate_limit_burst,
+                *self.p2p_config.block_upload_rate_limit_per_sec,
+                Instant::now(),
+            )
+        });
+        limiter.try_take(size, Instant::now())
+    }
+
+    /// Check that a message received from a peer is within the size and count limits of its
+    /// type and that the peer doesn't send requests faster than allowed unless it's whitelisted
+    ///
+    /// Block list requests are rate limited by the number of requested blocks rather than by
+    /// the number of requests, a syncing peer sends them back to back. The bytes of the blocks
+    /// sent in response are limited separately when the request is processed.
+    fn check_message_limits(
+        &mut self,
+        peer_id: T::PeerId,
+        event: &SyncingEvent<T>,
+        size: usize,
+    ) -> crate::Result<()> {
+        let whitelisted = self.peers.get(&peer_id).map_or(false, |peer| peer.is_whitelisted());
+        let (kind, size_limit) = match event {
+            SyncingEvent::Request { request, .. } => {
+                if let message::Request::BlockListRequest(inner) = request {
+                    check_item_count(
+                        request.kind(),
+                        inner.block_ids().len(),
+                        *self.p2p_config.max_blocks_per_message,
+                    )?;
+                    let limiter =
+                        self.block_request_rate_limiters.entry(peer_id).or_insert_with(|| {
+                            TokenBucket::new(
+                                *self.p2p_config.block_request_rate_limit_burst,
+                                *self.p2p_config.block_request_rate_limit_per_sec,
+                                Instant::now(),
+                            )
+                        });
+                    ensure!(
+                        whitelisted || limiter.try_take(inner.block_ids().len(), Instant::now()),
+                        P2pError::ProtocolError(ProtocolError::RequestRateLimitExceeded),
+                    );
+                } else {
+                    let limiter = self.request_rate_limiters.entry(peer_id).or_insert_with(|| {
+                        TokenBucket::new(
+                            *self.p2p_config.request_rate_limit_burst,
+                            *self.p2p_config.request_rate_limit_per_sec,
+                            Instant::now(),
+                        )
+                    });
+                    ensure!(
+                        whitelisted || limiter.take(1, Instant::now()) == 1,
+                        P2pError::ProtocolError(ProtocolError::RequestRateLimitExceeded),
+                    );
+                }
+                (request.kind(), *self.p2p_config.max_message_size)
+            }
+            SyncingEvent::Response { response, .. } => match response {
+                message::Response::HeaderListResponse(inner) => {
+                    check_item_count(
+                        response.kind(),
+                        inner.headers().len(),
+                        *self.p2p_config.max_headers_per_message,
+                    )?;
+                    (response.kind(), *self.p2p_config.max_message_size)
+                }
+                message::Response::BlockListResponse(inner) => {
+                    check_item_count(
+                        response.kind(),
+                        inner.blocks().len(),
+                        *self.p2p_config.max_blocks_per_message,
+                    )?;
+                    (response.kind(), *self.p2p_config.max_block_list_size)
+                }
+                _ => (response.kind(), *self.p2p_config.max_message_size),
+            },
+            SyncingEvent::Announcement { announcement, .. } => {
+                (announcement.kind(), *self.p2p_config.max_announcement_size)
+            }
+            SyncingEvent::Error { .. } => return Ok(()),
+        };
+
+        ensure!(
+            size <= size_limit,
+            P2pError::ProtocolError(ProtocolError::MessageTooLarge(kind, size, size_limit)),
+        );
+        Ok(())
     }
 
     /// Validate incoming block response
+    ///
+    /// A block whose parent isn't known is kept in the orphan pool of the chainstate until its
+    /// missing ancestors are downloaded.
     async fn validate_block_response(
         &mut self,
         peer_id: &T::PeerId,
         blocks: Vec<Block>,
-    ) -> crate::Result<Option<BlockHeader>> {
+    ) -> crate::Result<()> {
+        let block = blocks.into_iter().next().expect("block to exist");
+        let header = block.header().clone();
+        let is_orphan = !self.is_parent_known(&header).await?;
+
         let peer = self
             .peers
             .get_mut(peer_id)
             .ok_or(P2pError::PeerError(PeerError::PeerDoesntExist))?;
 
-        let block = blocks.into_iter().next().expect("block to exist");
-        let header = block.header().clone();
+        if is_orphan {
+            peer.register_block_response(&header)?;
+            log::debug!("block {} from peer {peer_id} is an orphan", header.get_id());
+            return Self::process_orphan_block(&self.chainstate_handle, block).await;
+        }
 
-        let result = match self
-            .chainstate_handle
+        let is_new = Self::process_block(&self.chainstate_handle, block).await?;
+        let result = peer.register_block_response(&header);
+        if is_new {
+            self.report_to_peer_manager(PeerManagerEvent::BlockDelivered(*peer_id));
+        }
+        result
+    }
+
+    /// Check the block and add it to chainstate
+    ///
+    /// Returns `false` if the block was already known to the chainstate.
+    async fn process_block(
+        chainstate_handle: &subsystem::Handle<Box<dyn chainstate_interface::ChainstateInterface>>,
+        block: Block,
+    ) -> crate::Result<bool> {
+        let result = match chainstate_handle
             .call(move |this| this.preliminary_block_check(block))
             .await?
         {
             Ok(block) => {
-                self.chainstate_handle
+                chainstate_handle
                     .call_mut(move |this| this.process_block(block, chainstate::BlockSource::Peer))
                     .await?
             }
@@ -322,12 +815,169 @@ where
         };
 
         match result {
-            Ok(_) => {}
-            Err(ChainstateError::ProcessBlockError(BlockError::BlockAlreadyExists(_id))) => {}
-            Err(err) => return Err(P2pError::ChainstateError(err)),
+            Ok(_) => Ok(true),
+            Err(ChainstateError::ProcessBlockError(BlockError::BlockAlreadyExists(_id))) => {
+                Ok(false)
+            }
+            Err(err) => Err(P2pError::ChainstateError(err)),
+        }
+    }
+
+    /// Add a block whose parent isn't known to the orphan pool of the chainstate
+    ///
+    /// The block is connected by the chainstate once its parent has been processed.
+    async fn process_orphan_block(
+        chainstate_handle: &subsystem::Handle<Box<dyn chainstate_interface::ChainstateInterface>>,
+        block: Block,
+    ) -> crate::Result<()> {
+        let result = chainstate_handle
+            .call_mut(move |this| this.process_block(block, chainstate::BlockSource::Peer))
+            .await?;
+
+        match result {
+            Ok(_)
+            | Err(ChainstateError::ProcessBlockError(BlockError::OrphanCheckFailed(
+                OrphanCheckError::PeerOrphan,
+            ))) => Ok(()),
+            Err(err) => Err(P2pError::ChainstateError(err)),
+        }
+    }
+
+    /// Returns true if the parent of the block is known to the chainstate
+    async fn is_parent_known(&self, header: &BlockHeader) -> crate::Result<bool> {
+        let prev_block_id = *header.prev_block_id();
+        let index = self
+            .chainstate_handle
+            .call(move |this| this.get_gen_block_index(&prev_block_id))
+            .await??;
+        Ok(index.is_some())
+    }
+
+    /// Request a block from the connected peers
+    ///
+    /// The block is requested from one idle full node at a time until some peer delivers it.
+    /// Once received, the block is processed like an announced block.
+    pub async fn fetch_block(&mut self, block_id: Id<Block>) -> crate::Result<()> {
+        self.fetch_block_excluding(block_id, None).await
+    }
+
+    /// Request a block like [`Self::fetch_block`], without asking the `excluded` peer for it
+    async fn fetch_block_excluding(
+        &mut self,
+        block_id: Id<Block>,
+        excluded: Option<T::PeerId>,
+    ) -> crate::Result<()> {
+        let is_known = self
+            .chainstate_handle
+            .call(move |this| this.get_block_index(&block_id))
+            .await??
+            .is_some();
+        let is_orphan = self
+            .chainstate_handle
+            .call(move |this| this.is_already_an_orphan(&block_id))
+            .await?;
+        if is_known || is_orphan || self.is_block_requested(&block_id) {
+            log::debug!("block {block_id} is already known or requested");
+            return Ok(());
+        }
+
+        let peers = self
+            .peers
+            .iter()
+            .filter(|(peer_id, peer)| {
+                Some(**peer_id) != excluded
+                    && peer.state() == &peer::PeerSyncState::Idle
+                    && peer.services().has(Service::FullNode)
+            })
+            .map(|(peer_id, _)| *peer_id)
+            .collect::<Vec<_>>();
+        ensure!(!peers.is_empty(), P2pError::PeerError(PeerError::NoPeers));
+
+        self.block_fetches.insert(block_id, peers);
+        self.fetch_from_next_peer(block_id).await
+    }
+
+    /// Request a fetched block from the next peer that hasn't been asked for it yet
+    async fn fetch_from_next_peer(&mut self, block_id: Id<Block>) -> crate::Result<()> {
+        let peers = match self.block_fetches.get_mut(&block_id) {
+            Some(peers) => peers,
+            None => return Ok(()),
+        };
+
+        while let Some(peer_id) = peers.pop() {
+            let is_idle = self
+                .peers
+                .get(&peer_id)
+                .map_or(false, |peer| peer.state() == &peer::PeerSyncState::Idle);
+            if is_idle {
+                log::debug!("fetch block {block_id} from peer {peer_id}");
+                return self.send_block_request(peer_id, block_id, 0).await;
+            }
         }
 
-        peer.register_block_response(&header)
+        log::warn!("failed to fetch block {block_id}, no peer has it");
+        self.block_fetches.remove(&block_id);
+        Ok(())
+    }
+
+    /// Process a block downloaded by the download scheduler
+    ///
+    /// The blocks that are ready are processed in order. If one of them is invalid, the peer that
+    /// sent it is penalized and the rest of the download is abandoned, as the following blocks
+    /// can't be connected anymore.
+    async fn process_downloaded_block(
+        &mut self,
+        peer_id: T::PeerId,
+        block: Block,
+    ) -> crate::Result<()> {
+        for (sender_id, block) in self.downloads.block_received(peer_id, block) {
+            match Self::process_block(&self.chainstate_handle, block).await {
+                Ok(true) => {
+                    self.report_to_peer_manager(PeerManagerEvent::BlockDelivered(sender_id))
+                }
+                Ok(false) => {}
+                Err(err) => {
+                    log::error!(
+                        "downloaded block from peer {sender_id} is invalid, abandon download"
+                    );
+                    self.downloads.abandon();
+                    self.handle_error(sender_id, Err(err)).await?;
+                    break;
+                }
+            }
+        }
+
+        let is_idle =
+            self.peers.get(&peer_id).map(|peer| peer.state()) == Some(&peer::PeerSyncState::Idle);
+        if is_idle && self.downloads.is_peer_done(&peer_id) {
+            // all blocks from peer received, ask if peer knows of any new headers
+            let locator = self.chainstate_handle.call(|this| this.get_locator()).await??;
+            self.send_header_request(peer_id, locator, 0).await?;
+        }
+        self.schedule_block_downloads().await
+    }
+
+    /// Process an empty response to a block request, the peer doesn't have the requested blocks
+    ///
+    /// The blocks that are being downloaded from the peer are requested from other peers.
+    pub async fn process_unavailable_blocks(
+        &mut self,
+        peer_id: T::PeerId,
+        block_ids: Vec<Id<Block>>,
+    ) -> crate::Result<()> {
+        let downloaded = block_ids
+            .into_iter()
+            .filter(|block_id| self.downloads.is_requested_from(&peer_id, block_id))
+            .collect::<Vec<_>>();
+        if downloaded.is_empty() {
+            return self.process_block_response(peer_id, Vec::new()).await;
+        }
+
+        for block_id in downloaded {
+            log::debug!("peer {peer_id} doesn't have block {block_id}, request it elsewhere");
+            self.downloads.block_unavailable(&peer_id, &block_id);
+        }
+        self.schedule_block_downloads().await
     }
 
     /// Process block response
@@ -336,20 +986,163 @@ where
         peer_id: T::PeerId,
         blocks: Vec<Block>,
     ) -> crate::Result<()> {
+        // A peer that doesn't have the requested blocks responds with an empty list
+        if blocks.is_empty() {
+            if let Some(peer::PeerSyncState::UploadingBlocks(block_id)) =
+                self.peers.get(&peer_id).map(|peer| peer.state())
+            {
+                let block_id = *block_id;
+                log::debug!("peer {peer_id} doesn't have block {block_id}");
+                if let Some(peer) = self.peers.get_mut(&peer_id) {
+                    peer.set_state(peer::PeerSyncState::Idle);
+                }
+                if self.block_fetches.contains_key(&block_id) {
+                    return self.fetch_from_next_peer(block_id).await;
+                }
+
+                // The block was announced by the peer or requested after a compact block from it
+                // couldn't be rebuilt, it's requested from the other peers instead
+                return match self.fetch_block_excluding(block_id, Some(peer_id)).await {
+                    Err(P2pError::PeerError(PeerError::NoPeers)) => {
+                        log::warn!("failed to fetch block {block_id}, no other peer to ask");
+                        Ok(())
+                    }
+                    result => result,
+                };
+            }
+
+            log::debug!("peer {peer_id} doesn't have the requested blocks");
+            return Ok(());
+        }
+
         // TODO: remove the limitation of sending only one block, and allow sending multiple blocks (up to a cap)
         ensure!(
             blocks.len() == 1,
             P2pError::ProtocolError(ProtocolError::InvalidMessage),
         );
 
-        match self.validate_block_response(&peer_id, blocks).await {
-            Ok(Some(next_block)) => self.send_block_request(peer_id, next_block.get_id(), 0).await,
-            Ok(None) => {
-                // last block from peer received, ask if peer knows of any new headers
-                let locator = self.chainstate_handle.call(|this| this.get_locator()).await??;
-                self.send_header_request(peer_id, locator, 0).await
+        let block_id = blocks[0].get_id();
+        if self.downloads.is_requested_from(&peer_id, &block_id) {
+            let block = blocks.into_iter().next().expect("block to exist");
+            return self.process_downloaded_block(peer_id, block).await;
+        }
+
+        self.validate_block_response(&peer_id, blocks).await?;
+        self.block_fetches.remove(&block_id);
+
+        // the announced or fetched block received, ask if peer knows of any new headers, which
+        // also brings in the missing ancestors of an orphan
+        let locator = self.chainstate_handle.call(|this| this.get_locator()).await??;
+        self.send_header_request(peer_id, locator, 0).await
+    }
+
+    /// Process compact block response
+    pub async fn process_compact_block_response(
+        &mut self,
+        peer_id: T::PeerId,
+        compact_block: message::CompactBlock,
+    ) -> crate::Result<()> {
+        let block_id = compact_block.header().block_id();
+        match self.peers.get(&peer_id).map(|peer| peer.state()) {
+            Some(peer::PeerSyncState::UploadingBlocks(expected)) if expected == &block_id => {}
+            Some(_) => return Err(P2pError::ProtocolError(ProtocolError::InvalidMessage)),
+            None => return Err(P2pError::PeerError(PeerError::PeerDoesntExist)),
+        }
+
+        self.rebuild_compact_block(peer_id, compact_block).await
+    }
+
+    /// Process a compact block pushed by a peer in high-bandwidth mode like a response to a
+    /// compact block request
+    async fn process_pushed_compact_block(
+        &mut self,
+        peer_id: T::PeerId,
+        compact_block: message::CompactBlock,
+    ) -> crate::Result<()> {
+        let block_id = compact_block.header().block_id();
+        self.peers
+            .get_mut(&peer_id)
+            .ok_or(P2pError::PeerError(PeerError::PeerDoesntExist))?
+            .set_state(peer::PeerSyncState::UploadingBlocks(block_id));
+
+        let result = self.rebuild_compact_block(peer_id, compact_block).await;
+        if result.is_err() {
+            if let Some(peer) = self.peers.get_mut(&peer_id) {
+                if peer.state() == &peer::PeerSyncState::UploadingBlocks(block_id) {
+                    peer.set_state(peer::PeerSyncState::Idle);
+                }
+            }
+        }
+        result
+    }
+
+    /// Rebuilds the block from the mempool and requests the missing transactions, if any. If the
+    /// block can't be rebuilt, the full block is requested instead.
+    async fn rebuild_compact_block(
+        &mut self,
+        peer_id: T::PeerId,
+        compact_block: message::CompactBlock,
+    ) -> crate::Result<()> {
+        let block_id = compact_block.header().block_id();
+        let mempool_txs = self
+            .mempool_handle
+            .call_async(|this| this.get_all())
+            .await?
+            .unwrap_or_else(|err| {
+                log::error!("Failed to get mempool transactions: {err}");
+                Vec::new()
+            });
+
+        match compact_block::PartialBlock::new(compact_block, mempool_txs) {
+            Some(partial_block) => {
+                let missing_indexes = partial_block.missing_indexes();
+                if missing_indexes.is_empty() {
+                    self.process_rebuilt_block(peer_id, block_id, partial_block).await
+                } else {
+                    self.partial_blocks.insert(peer_id, partial_block);
+                    self.send_block_transactions_request(peer_id, block_id, missing_indexes).await
+                }
+            }
+            None => {
+                log::debug!("failed to rebuild compact block {block_id}, request full block");
+                self.send_block_request(peer_id, block_id, 0).await
+            }
+        }
+    }
+
+    /// Process response with the missing transactions of a compact block
+    pub async fn process_block_transactions_response(
+        &mut self,
+        peer_id: T::PeerId,
+        block_id: Id<Block>,
+        txs: Vec<SignedTransaction>,
+    ) -> crate::Result<()> {
+        let mut partial_block = match self.partial_blocks.remove(&peer_id) {
+            Some(partial_block) if partial_block.block_id() == block_id => partial_block,
+            _ => return Err(P2pError::ProtocolError(ProtocolError::InvalidMessage)),
+        };
+
+        if partial_block.fill_missing(txs) {
+            self.process_rebuilt_block(peer_id, block_id, partial_block).await
+        } else {
+            Err(P2pError::ProtocolError(ProtocolError::InvalidMessage))
+        }
+    }
+
+    /// Process a block rebuilt from a compact block like any other block response, or fall back
+    /// to requesting the full block if the result doesn't match the header
+    async fn process_rebuilt_block(
+        &mut self,
+        peer_id: T::PeerId,
+        block_id: Id<Block>,
+        partial_block: compact_block::PartialBlock,
+    ) -> crate::Result<()> {
+        match partial_block.into_block() {
+            Some(block) => self.process_block_response(peer_id, vec![block]).await,
+            None => {
+                log::debug!("rebuilt compact block {block_id} doesn't match, request full block");
+                self.send_block_request(peer_id, block_id, 0).await
             }
-            Err(err) => Err(err),
         }
     }
 
@@ -365,6 +1158,11 @@ where
             return Ok(());
         }
 
+        if !self.downloads.is_empty() {
+            self.state = SyncState::DownloadingBlocks;
+            return Ok(());
+        }
+
         for peer in self.peers.values() {
             match peer.state() {
                 peer::PeerSyncState::UploadingBlocks(_) => {
@@ -380,7 +1178,9 @@ where
         }
 
         self.state = SyncState::Done;
-        self.peer_sync_handle.subscribe(&[PubSubTopic::Blocks]).await
+        self.peer_sync_handle
+            .subscribe(&[PubSubTopic::Blocks, PubSubTopic::CompactBlocks])
+            .await
     }
 
     pub async fn process_error(
@@ -393,57 +1193,222 @@ where
             net::types::RequestResponseError::Timeout => {
                 if let Some(request) = self.requests.remove(&request_id) {
                     log::warn!("outbound request {request_id:?} for peer {peer_id} timed out");
+                    self.process_request_timeout(peer_id, request).await?;
+                }
+            }
+        }
 
-                    if request.retry_count == RETRY_LIMIT {
-                        log::error!(
-                            "peer {peer_id} failed to respond to request, close connection"
-                        );
-                        self.unregister_peer(peer_id);
-                        // TODO: global event system
-                        let (tx, rx) = oneshot::channel();
-                        self.tx_peer_manager
-                            .send(PeerManagerEvent::Disconnect(peer_id, tx))
-                            .map_err(P2pError::from)?;
-                        return rx.await.map_err(P2pError::from)?;
-                    }
+        Ok(())
+    }
 
-                    match request.request_type {
-                        request::RequestType::GetHeaders => {
-                            let locator =
-                                self.chainstate_handle.call(|this| this.get_locator()).await??;
-                            self.send_header_request(peer_id, locator, request.retry_count + 1)
-                                .await?;
-                        }
-                        request::RequestType::GetBlocks(block_ids) => {
-                            assert_eq!(block_ids.len(), 1);
-                            self.send_block_request(
-                                peer_id,
-                                *block_ids.get(0).expect("block id to exist"),
-                                request.retry_count + 1,
-                            )
-                            .await?;
-                        }
-                    }
+    /// Resend a request that the peer didn't respond to or disconnect the peer if it has
+    /// already been resent too many times
+    async fn process_request_timeout(
+        &mut self,
+        peer_id: T::PeerId,
+        request: request::RequestState<T>,
+    ) -> crate::Result<()> {
+        if request.retry_count == RETRY_LIMIT {
+            log::error!("peer {peer_id} failed to respond to request, close connection");
+            return self.disconnect_peer(peer_id).await;
+        }
+
+        match request.request_type {
+            request::RequestType::GetHeaders => {
+                let locator = self.chainstate_handle.call(|this| this.get_locator()).await??;
+                self.send_header_request(peer_id, locator, request.retry_count + 1).await?;
+            }
+            request::RequestType::GetBlocks(block_ids) => {
+                assert_eq!(block_ids.len(), 1);
+                let block_id = *block_ids.get(0).expect("block id to exist");
+                if self.downloads.is_requested_from(&peer_id, &block_id) {
+                    // let the download scheduler pick a peer for the block again
+                    self.downloads.release(&peer_id, &block_id);
+                    self.schedule_block_downloads().await?;
+                } else {
+                    self.send_block_request(peer_id, block_id, request.retry_count + 1).await?;
                 }
             }
+            request::RequestType::GetCompactBlock(block_id) => {
+                self.send_compact_block_request(peer_id, block_id, request.retry_count + 1)
+                    .await?;
+            }
+            request::RequestType::GetBlockTransactions(block_id) => {
+                // Fall back to downloading the full block
+                self.partial_blocks.remove(&peer_id);
+                self.send_block_request(peer_id, block_id, request.retry_count + 1).await?;
+            }
+            request::RequestType::GetAddresses => {
+                // Addresses are only requested once per connection
+                log::debug!("peer {peer_id} didn't respond to address list request");
+            }
         }
 
         Ok(())
     }
 
+    /// Process the requests whose deadline has passed as if they had timed out
+    pub async fn process_expired_requests(&mut self, now: Instant) -> crate::Result<()> {
+        let (expired, pending): (HashMap<_, _>, HashMap<_, _>) = std::mem::take(&mut self.requests)
+            .into_iter()
+            .partition(|(_, request)| request.deadline <= now);
+        self.requests = pending;
+
+        for (request_id, request) in expired {
+            // The peer may have been disconnected because of an earlier expired request
+            let peer_id = request.peer_id;
+            if !self.peers.contains_key(&peer_id) {
+                continue;
+            }
+            log::warn!("outbound request {request_id:?} for peer {peer_id} expired");
+
+            let result = self.process_request_timeout(peer_id, request).await;
+            self.handle_error(peer_id, result).await?;
+        }
+        Ok(())
+    }
+
+    /// Disconnect the peers that stall the sync if the local chain hasn't advanced for
+    /// [`CHAIN_STALL_TIMEOUT`] before the node is synced
+    ///
+    /// These are the peers the headers or blocks are expected from and the peers that are too far
+    /// behind to help. The peer manager replaces the disconnected peers with new ones.
+    ///
+    /// The chain is only considered stalled if a better chain is known to exist, either from the
+    /// received headers or from the heights the peers have, as no new blocks are expected otherwise.
+    pub async fn process_stalled_sync(&mut self, now: Instant) -> crate::Result<()> {
+        let height = self.chainstate_handle.call(|this| this.get_best_block_height()).await??;
+        self.chain_progress.update(height, now);
+
+        let is_stalled = self.state != SyncState::Done
+            && self.chain_progress.is_stalled(now, CHAIN_STALL_TIMEOUT);
+        if !is_stalled {
+            return Ok(());
+        }
+
+        if !self.is_better_chain_known(height) {
+            // The chain is idle, start a new window so that the peers get the full timeout to
+            // deliver a better chain once one is announced
+            self.chain_progress.restart(now);
+            return Ok(());
+        }
+
+        let stalled_peers = self
+            .peers
+            .iter()
+            .filter(|(peer_id, peer)| {
+                !self.downloads.is_peer_done(peer_id)
+                    || Self::is_peer_behind(peer, height)
+                    || std::matches!(
+                        peer.state(),
+                        peer::PeerSyncState::UploadingHeaders(_)
+                            | peer::PeerSyncState::UploadingBlocks(_)
+                    )
+            })
+            .map(|(peer_id, _)| *peer_id)
+            .collect::<Vec<_>>();
+
+        for peer_id in stalled_peers {
+            log::warn!(
+                "the chain hasn't advanced for {CHAIN_STALL_TIMEOUT:?}, disconnect peer {peer_id}"
+            );
+
+            let result = self.disconnect_peer(peer_id).await;
+            self.handle_error(peer_id, result).await?;
+        }
+        self.chain_progress.restart(now);
+        Ok(())
+    }
+
+    /// Returns true if a header or a peer's best block above the local best block is known
+    fn is_better_chain_known(&self, local_height: BlockHeight) -> bool {
+        self.best_header_height > local_height
+            || self
+                .peers
+                .values()
+                .any(|peer| peer.best_known_height().map_or(false, |height| height > local_height))
+    }
+
+    /// Returns true if the best known block of the peer is too far behind the local best block
+    fn is_peer_behind(peer: &peer::PeerContext<T>, local_height: BlockHeight) -> bool {
+        peer.best_known_height().map_or(false, |height| {
+            u64::from(height).saturating_add(MAX_PEER_HEIGHT_LAG) < u64::from(local_height)
+        })
+    }
+
+    /// Unregister the peer and ask the peer manager to close the connection
+    async fn disconnect_peer(&mut self, peer_id: T::PeerId) -> crate::Result<()> {
+        self.unregister_peer(peer_id);
+        // TODO: global event system
+        let (tx, rx) = oneshot::channel();
+        self.tx_peer_manager
+            .send(PeerManagerEvent::Disconnect(peer_id, tx))
+            .map_err(P2pError::from)?;
+        rx.await.map_err(P2pError::from)?
+    }
+
+    /// Get the progress of the block synchronization
+    pub async fn sync_progress(&self) -> crate::Result<SyncProgress> {
+        let best_block = self.chainstate_handle.call(|this| this.get_best_block_index()).await??;
+        let block_height = best_block.block_height();
+        let header_height = std::cmp::max(self.best_header_height, block_height);
+        let now = Instant::now();
+
+        Ok(SyncProgress {
+            synced: self.state == SyncState::Done,
+            block_height,
+            header_height,
+            estimated_tip_height: progress::estimate_tip_height(
+                block_height,
+                best_block.block_timestamp().as_duration_since_epoch(),
+                header_height,
+                time::get(),
+                *self.config.target_block_spacing(),
+            ),
+            secs_since_progress: self.chain_progress.time_since_change(now).as_secs(),
+            peers_behind: self
+                .peers
+                .values()
+                .filter(|peer| Self::is_peer_behind(peer, block_height))
+                .count(),
+        })
+    }
+
+    /// Process an announcement and report the validation result of the message, a message that
+    /// caused the peer to be penalized is rejected
     pub async fn process_announcement(
         &mut self,
         peer_id: T::PeerId,
         message_id: T::SyncingMessageId,
         announcement: Announcement,
     ) -> crate::Result<()> {
-        // TODO: Discuss if we should announce blocks or headers, because announcing
-        // blocks seems wasteful, in the sense that it's possible for peers to get
-        // blocks again, and again, wasting their bandwidth.
-        match announcement {
-            Announcement::Block(block) => {
-                self.process_block_announcement(peer_id, message_id, block).await
+        let (header, compact_block) = match announcement {
+            Announcement::Block(header) => (header, None),
+            Announcement::CompactBlock(compact_block) => {
+                (compact_block.header().clone(), Some(compact_block))
             }
+        };
+
+        let (validation_result, result) =
+            match self.process_block_announcement(peer_id, header, compact_block).await {
+                Ok(validation_result) => (validation_result, Ok(())),
+                Err(err) => (Self::announcement_validation_result(&err), Err(err)),
+            };
+        self.peer_sync_handle
+            .report_validation_result(peer_id, message_id, validation_result)
+            .await?;
+        result
+    }
+
+    fn announcement_validation_result(err: &P2pError) -> ValidationResult {
+        let ban_score = match err {
+            P2pError::ChainstateError(ChainstateError::ProcessBlockError(err)) => err.ban_score(),
+            err => err.ban_score(),
+        };
+        if ban_score > 0 {
+            ValidationResult::Reject
+        } else {
+            ValidationResult::Ignore
         }
     }
 
@@ -520,90 +1485,191 @@ where
         log::info!("Starting SyncManager");
 
         let mut block_rx = self.subscribe_to_chainstate_events().await?;
+        let mut stall_check = tokio::time::interval(STALL_CHECK_INTERVAL);
 
         loop {
             tokio::select! {
-                event = self.peer_sync_handle.poll_next() => match event? {
-                    SyncingEvent::Request {
-                        peer_id,
-                        request_id,
-                        request,
-                    } => match request {
-                        message::Request::HeaderListRequest(request) => {
-                            log::debug!("process header request (id {request_id:?}) from peer {peer_id}");
-                            log::trace!("locator: {:#?}", request.locator());
-
-                            let result = self.process_header_request(
-                                peer_id,
-                                request_id,
-                                request.into_locator(),
-                            ).await;
-                            self.handle_error(peer_id, result).await?;
+                event = self.peer_sync_handle.poll_next() => {
+                    let event = event?;
+                    if let Some((peer_id, kind, size)) = Self::received_message_info(&event) {
+                        self.report_to_peer_manager(PeerManagerEvent::MessageReceived(peer_id, kind, size));
+                        self.capture_received(&event);
+
+                        if let Err(err) = self.check_message_limits(peer_id, &event, size) {
+                            // A rejected request is dropped, so that the backend doesn't keep
+                            // waiting for its response
+                            if let SyncingEvent::Request { request_id, .. } = event {
+                                log::debug!("dropping request {request_id:?} from peer {peer_id}: {err}");
+                                self.peer_sync_handle.drop_request(request_id).await?;
+                            }
+                            self.handle_error(peer_id, Err(err)).await?;
+                            continue;
                         }
-                        message::Request::BlockListRequest(request) => {
-                            log::debug!("process block request (id {request_id:?}) from peer {peer_id}");
-                            log::trace!("requested block ids: {:#?}", request.block_ids());
+                    }
 
-                            let result = self.process_block_request(
-                                peer_id,
-                                request_id,
-                                request.into_block_ids(),
-                            ).await;
-                            self.handle_error(peer_id, result).await?;
-                        }
-                    },
-                    SyncingEvent::Response {
-                        peer_id,
-                        request_id,
-                        response,
-                    } => match response {
-                        message::Response::HeaderListResponse(response) => {
-                            log::debug!("process header response (id {request_id:?}) from peer {peer_id}");
-                            log::trace!("received headers: {:#?}", response.headers());
-
-                            let result = self.process_header_response(peer_id, response.into_headers()).await;
+                    match event {
+                        SyncingEvent::Request {
+                            peer_id,
+                            request_id,
+                            request,
+                        } => match request {
+                            message::Request::HeaderListRequest(request) => {
+                                log::debug!("process header request (id {request_id:?}) from peer {peer_id}");
+                                log::trace!("locator: {:#?}", request.locator());
+
+                                let result = self.process_header_request(
+                                    peer_id,
+                                    request_id,
+                                    request.into_locator(),
+                                ).await;
+                                self.handle_error(peer_id, result).await?;
+                            }
+                            message::Request::BlockListRequest(request) => {
+                                log::debug!("process block request (id {request_id:?}) from peer {peer_id}");
+                                log::trace!("requested block ids: {:#?}", request.block_ids());
+
+                                let result = self.process_block_request(
+                                    peer_id,
+                                    request_id,
+                                    request.into_block_ids(),
+                                ).await;
+                                self.handle_error(peer_id, result).await?;
+                            }
+                            message::Request::CompactBlockRequest(request) => {
+                                log::debug!("process compact block request (id {request_id:?}) from peer {peer_id}");
+
+                                let result = self.process_compact_block_request(
+                                    peer_id,
+                                    request_id,
+                                    *request.block_id(),
+                                ).await;
+                                self.handle_error(peer_id, result).await?;
+                            }
+                            message::Request::BlockTransactionsRequest(request) => {
+                                log::debug!("process block transactions request (id {request_id:?}) from peer {peer_id}");
+
+                                let result = self.process_block_transactions_request(
+                                    peer_id,
+                                    request_id,
+                                    request,
+                                ).await;
+                                self.handle_error(peer_id, result).await?;
+                            }
+                            message::Request::AddrListRequest(_) => {
+                                log::debug!("process address list request (id {request_id:?}) from peer {peer_id}");
+
+                                let result = self.process_addr_list_request(peer_id, request_id).await;
+                                self.handle_error(peer_id, result).await?;
+                            }
+                        },
+                        SyncingEvent::Response {
+                            peer_id,
+                            request_id,
+                            response,
+                        } => {
+                            let request = self.requests.remove(&request_id);
+                            if let Some(request) = &request {
+                                self.report_to_peer_manager(PeerManagerEvent::ResponseTime(peer_id, request.sent_at.elapsed()));
+                            }
+                            match response {
+                                message::Response::HeaderListResponse(response) => {
+                                    log::debug!("process header response (id {request_id:?}) from peer {peer_id}");
+                                    log::trace!("received headers: {:#?}", response.headers());
+
+                                    let result = self.process_header_response(peer_id, response.into_headers()).await;
+                                    self.handle_error(peer_id, result).await?;
+                                }
+                                message::Response::BlockListResponse(response) => {
+                                    log::debug!("process block response (id {request_id:?}) from peer {peer_id}");
+                                    log::trace!(
+                                        "# of received blocks: {}, block ids: {:#?}",
+                                        response.blocks().len(),
+                                        response.blocks().iter().map(|block| block.get_id()).collect::<Vec<_>>(),
+                                    );
+
+                                    let result = match request.map(|request| request.request_type) {
+                                        Some(request::RequestType::GetBlocks(block_ids)) if response.blocks().is_empty() => {
+                                            self.process_unavailable_blocks(peer_id, block_ids).await
+                                        }
+                                        _ => self.process_block_response(peer_id, response.into_blocks()).await,
+                                    };
+                                    self.handle_error(peer_id, result).await?;
+                                }
+                                message::Response::CompactBlockResponse(compact_block) => {
+                                    log::debug!("process compact block response (id {request_id:?}) from peer {peer_id}");
+
+                                    let result = self.process_compact_block_response(peer_id, compact_block).await;
+                                    self.handle_error(peer_id, result).await?;
+                                }
+                                message::Response::BlockTransactionsResponse(response) => {
+                                    log::debug!("process block transactions response (id {request_id:?}) from peer {peer_id}");
+
+                                    let block_id = *response.block_id();
+                                    let result = self.process_block_transactions_response(peer_id, block_id, response.into_txs()).await;
+                                    self.handle_error(peer_id, result).await?;
+                                }
+                                message::Response::AddrListResponse(response) => {
+                                    log::debug!("process address list response (id {request_id:?}) from peer {peer_id}");
+                                    log::trace!("# of received addresses: {}", response.addresses().len());
+
+                                    let result = self.process_addr_list_response(peer_id, response.into_addresses()).await;
+                                    self.handle_error(peer_id, result).await?;
+                                }
+                            }
+                        },
+                        SyncingEvent::Error {
+                            peer_id,
+                            request_id,
+                            error,
+                        } => {
+                            let result = self.process_error(peer_id, request_id, error).await;
                             self.handle_error(peer_id, result).await?;
-                        }
-                        message::Response::BlockListResponse(response) => {
-                            log::debug!("process block response (id {request_id:?}) from peer {peer_id}");
-                            log::trace!(
-                                "# of received blocks: {}, block ids: {:#?}",
-                                response.blocks().len(),
-                                response.blocks().iter().map(|block| block.get_id()).collect::<Vec<_>>(),
-                            );
-
-                            let result = self.process_block_response(peer_id, response.into_blocks()).await;
+                        },
+                        SyncingEvent::Announcement{ peer_id, message_id, announcement } => {
+                            let result = self.process_announcement(peer_id, message_id, announcement).await;
                             self.handle_error(peer_id, result).await?;
                         }
-                    },
-                    SyncingEvent::Error {
-                        peer_id,
-                        request_id,
-                        error,
-                    } => {
-                        let result = self.process_error(peer_id, request_id, error).await;
-                        self.handle_error(peer_id, result).await?;
-                    },
-                    SyncingEvent::Announcement{ peer_id, message_id, announcement } => {
-                        self.process_announcement(peer_id, message_id, announcement).await?;
                     }
                 },
                 event = self.rx_sync.recv().fuse() => match event.ok_or(P2pError::ChannelClosed)? {
-                    SyncControlEvent::Connected(peer_id) => {
-                        log::debug!("register peer {peer_id} to sync manager");
-                        let result = self.register_peer(peer_id).await;
+                    SyncControlEvent::Connected(peer_id, services) => {
+                        log::debug!("register peer {peer_id} to sync manager, services: {services}");
+                        let result = match self.register_peer(peer_id, services).await {
+                            Ok(()) if self.peer_supports(&peer_id, Service::AddrRelay) => {
+                                self.send_addr_list_request(peer_id).await
+                            }
+                            result => result,
+                        };
                         self.handle_error(peer_id, result).await?;
                     }
                     SyncControlEvent::Disconnected(peer_id) => {
                         log::debug!("unregister peer {peer_id} from sync manager");
                         self.unregister_peer(peer_id)
                     }
+                    SyncControlEvent::Whitelisted(peer_id, whitelisted) => {
+                        if let Some(peer) = self.peers.get_mut(&peer_id) {
+                            peer.set_whitelisted(whitelisted);
+                        }
+                    }
+                    SyncControlEvent::GetSyncProgress(tx) => {
+                        let progress = self.sync_progress().await?;
+                        let _ = tx.send(progress);
+                    }
+                    SyncControlEvent::FetchBlock(block_id, tx) => {
+                        let _ = tx.send(self.fetch_block(block_id).await);
+                    }
                 },
+                _ = stall_check.tick() => {
+                    let now = Instant::now();
+                    self.process_stalled_downloads().await?;
+                    self.process_expired_requests(now).await?;
+                    self.process_stalled_sync(now).await?;
+                }
                 block_id = block_rx.recv().fuse(), if self.state == SyncState::Done => {
                     let block_id = block_id.ok_or(P2pError::ChannelClosed)?;
 
                     match self.chainstate_handle.call(move |this| this.get_block(block_id)).await?? {
-                        Some(block) => self.peer_sync_handle.make_announcement(Announcement::Block(block)).await?,
+                        Some(block) => self.announce_block(&block).await?,
                         None => log::error!("CRITICAL: best block not available"),
                     }
                 }
@@ -638,24 +1704,79 @@ where
         Ok(rx)
     }
 
+    /// Returns `true` if the block has already been requested from some peer
+    fn is_block_requested(&self, block_id: &Id<Block>) -> bool {
+        self.downloads.contains(block_id)
+            || self.requests.values().any(|request| match &request.request_type {
+                request::RequestType::GetBlocks(block_ids) => block_ids.contains(block_id),
+                request::RequestType::GetCompactBlock(id)
+                | request::RequestType::GetBlockTransactions(id) => id == block_id,
+                request::RequestType::GetHeaders | request::RequestType::GetAddresses => false,
+            })
+    }
+
+    /// Announces a new block produced or accepted by the local node. In high-bandwidth mode the
+    /// block is pushed as a compact block to the peers that support compact blocks, the header is
+    /// announced to all peers.
+    async fn announce_block(&mut self, block: &Block) -> crate::Result<()> {
+        if *self.p2p_config.high_bandwidth_compact_blocks {
+            let salt = self.rng.gen::<u64>();
+            let announcement =
+                Announcement::CompactBlock(compact_block::make_compact_block(block, salt, &[]));
+            match self.make_announcement(announcement).await {
+                Ok(()) | Err(P2pError::PublishError(PublishError::InsufficientPeers)) => {}
+                Err(err) => return Err(err),
+            }
+        }
+        self.make_announcement(Announcement::Block(block.header().clone())).await
+    }
+
+    /// Validates the announced header and downloads the block body from the announcing peer if
+    /// the block is new, unless the peer has pushed the block as a compact block already. Invalid
+    /// headers are penalized the same way invalid blocks are.
+    ///
+    /// A compact block is only accepted from a peer that supports compact blocks, otherwise only
+    /// its header is used.
     async fn process_block_announcement(
         &mut self,
         peer_id: T::PeerId,
-        message_id: T::SyncingMessageId,
-        block: Block,
-    ) -> crate::Result<()> {
-        let result = match self
-            .chainstate_handle
-            .call(move |this| this.preliminary_block_check(block))
-            .await?
-        {
-            Ok(block) => {
-                self.chainstate_handle
-                    .call_mut(move |this| this.process_block(block, chainstate::BlockSource::Peer))
-                    .await?
-            }
-            Err(err) => Err(err),
+        header: BlockHeader,
+        compact_block: Option<message::CompactBlock>,
+    ) -> crate::Result<ValidationResult> {
+        let block_id = header.get_id();
+        let is_new = {
+            let header = header.clone();
+            !self
+                .chainstate_handle
+                .call(move |this| this.filter_already_existing_blocks(vec![header]))
+                .await??
+                .is_empty()
         };
+        if !is_new {
+            self.report_to_peer_manager(PeerManagerEvent::BestKnownBlock(peer_id, block_id));
+            return Ok(ValidationResult::Ignore);
+        }
+
+        // The announced block doesn't connect to the local chain, so its missing ancestors are
+        // requested with the headers from the announcing peer. A peer that is busy asks for
+        // new headers once it's done.
+        if !self.is_parent_known(&header).await? {
+            let is_idle = self
+                .peers
+                .get(&peer_id)
+                .map_or(false, |peer| peer.state() == &peer::PeerSyncState::Idle);
+            if is_idle {
+                log::debug!("parent of block {block_id} from peer {peer_id} is unknown");
+                let locator = self.chainstate_handle.call(|this| this.get_locator()).await??;
+                self.send_header_request(peer_id, locator, 0).await?;
+            }
+            return Ok(ValidationResult::Ignore);
+        }
+
+        let result = self
+            .chainstate_handle
+            .call(move |this| this.preliminary_header_check(header))
+            .await?;
 
         let score = match result {
             Ok(_) => 0,
@@ -664,6 +1785,7 @@ where
                 ChainstateError::ProcessBlockError(err) => err.ban_score(),
                 ChainstateError::FailedToReadProperty(_) => 0,
                 ChainstateError::BootstrapError(_) => 0,
+                ChainstateError::FailedToCalculateWorkRequired(_) => 0,
             },
         };
 
@@ -676,11 +1798,44 @@ where
             let _ = rx.await.map_err(P2pError::from)?;
         }
 
-        self.peer_sync_handle
-            .report_validation_result(peer_id, message_id, ValidationResult::Ignore)
-            .await
+        if result.is_err() {
+            return Ok(ValidationResult::Reject);
+        }
+
+        self.report_to_peer_manager(PeerManagerEvent::BestKnownBlock(peer_id, block_id));
+
+        // A peer that is busy uploading headers or blocks asks for new headers once it's done,
+        // so the block is fetched then
+        let is_idle = self
+            .peers
+            .get(&peer_id)
+            .map_or(false, |peer| peer.state() == &peer::PeerSyncState::Idle);
+        if is_idle && !self.is_block_requested(&block_id) {
+            let supports_compact_blocks = self.peer_supports(&peer_id, Service::CompactBlocks);
+            match compact_block {
+                Some(compact_block) if supports_compact_blocks => {
+                    self.process_pushed_compact_block(peer_id, compact_block).await?
+                }
+                _ if supports_compact_blocks => {
+                    self.send_compact_block_request(peer_id, block_id, 0).await?
+                }
+                _ => self.send_block_request(peer_id, block_id, 0).await?,
+            }
+        }
+
+        // The block is announced to other peers by the local node once it has been processed
+        Ok(ValidationResult::Ignore)
     }
 }
 
+/// Check that a message doesn't carry more items than allowed for its type
+fn check_item_count(kind: &'static str, count: usize, limit: usize) -> crate::Result<()> {
+    ensure!(
+        count <= limit,
+        P2pError::ProtocolError(ProtocolError::TooManyItems(kind, count, limit)),
+    );
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests;
@@ -15,14 +15,13 @@
 
 use crate::{
     error::{P2pError, ProtocolError},
-    net::NetworkingService,
+    net::{types::Services, NetworkingService},
 };
 use chainstate::Locator;
 use common::{
     chain::block::{Block, BlockHeader},
-    primitives::{Id, Idable},
+    primitives::{BlockHeight, Id, Idable},
 };
-use std::collections::VecDeque;
 use utils::ensure;
 
 /// State of the peer
@@ -52,41 +51,44 @@ where
     /// State of the peer
     state: PeerSyncState,
 
-    /// List of block headers indicating which blocks
-    /// still need to be downloaded from the remote peer
-    work: VecDeque<BlockHeader>,
+    /// Services supported by both the local node and the peer
+    services: Services,
+
+    /// Height of the best block the peer is known to have
+    best_known_height: Option<BlockHeight>,
+
+    /// Whether the peer is whitelisted and exempt from the request rate limits
+    whitelisted: bool,
 }
 
 impl<T: NetworkingService> PeerContext<T> {
-    pub fn new(_peer_id: T::PeerId) -> Self {
+    pub fn new(_peer_id: T::PeerId, services: Services) -> Self {
         Self {
             _peer_id,
             state: PeerSyncState::Unknown,
-            work: VecDeque::new(),
+            services: services.intersection(&Services::default()),
+            best_known_height: None,
+            whitelisted: false,
         }
     }
 
-    pub fn new_with_locator(_peer_id: T::PeerId, locator: Locator) -> Self {
+    pub fn new_with_locator(_peer_id: T::PeerId, locator: Locator, services: Services) -> Self {
         Self {
             _peer_id,
             state: PeerSyncState::UploadingHeaders(locator),
-            work: VecDeque::new(),
+            services: services.intersection(&Services::default()),
+            best_known_height: None,
+            whitelisted: false,
         }
     }
 
-    pub fn register_header_response(&mut self, headers: &[BlockHeader]) {
+    /// The blocks of the received headers are downloaded by the download scheduler, so the peer
+    /// becomes idle
+    pub fn register_header_response(&mut self) {
         self.state = PeerSyncState::Idle;
-        self.work = VecDeque::from(headers.to_vec());
-    }
-
-    pub fn get_header_for_download(&mut self) -> Option<BlockHeader> {
-        self.get_next_block()
     }
 
-    pub fn register_block_response(
-        &mut self,
-        header: &BlockHeader,
-    ) -> crate::Result<Option<BlockHeader>> {
+    pub fn register_block_response(&mut self, header: &BlockHeader) -> crate::Result<()> {
         match &self.state {
             PeerSyncState::UploadingBlocks(expected) => {
                 ensure!(
@@ -94,7 +96,7 @@ impl<T: NetworkingService> PeerContext<T> {
                     P2pError::ProtocolError(ProtocolError::InvalidMessage),
                 );
 
-                Ok(self.get_next_block())
+                Ok(())
             }
             PeerSyncState::Idle | PeerSyncState::Unknown | PeerSyncState::UploadingHeaders(_) => {
                 Err(P2pError::ProtocolError(ProtocolError::InvalidMessage))
@@ -102,10 +104,6 @@ impl<T: NetworkingService> PeerContext<T> {
         }
     }
 
-    fn get_next_block(&mut self) -> Option<BlockHeader> {
-        self.work.pop_front()
-    }
-
     /// Set peer state
     pub fn set_state(&mut self, state: PeerSyncState) {
         self.state = state;
@@ -115,12 +113,44 @@ impl<T: NetworkingService> PeerContext<T> {
     pub fn state(&self) -> &PeerSyncState {
         &self.state
     }
+
+    /// Get the services that can be used with the peer
+    pub fn services(&self) -> Services {
+        self.services
+    }
+
+    /// Get the height of the best block the peer is known to have
+    pub fn best_known_height(&self) -> Option<BlockHeight> {
+        self.best_known_height
+    }
+
+    /// Returns true if the peer is whitelisted
+    pub fn is_whitelisted(&self) -> bool {
+        self.whitelisted
+    }
+
+    /// Add the peer to or remove it from the whitelist
+    pub fn set_whitelisted(&mut self, whitelisted: bool) {
+        self.whitelisted = whitelisted;
+    }
+
+    /// Record that the peer has a block of the given height
+    pub fn register_known_height(&mut self, height: BlockHeight) {
+        self.best_known_height = std::cmp::max(self.best_known_height, Some(height));
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::net::mock::{transport::TcpMockTransport, types, MockService};
+    use crate::net::{
+        mock::{
+            transport::TcpMockTransport,
+            types::{self, MockPeerId},
+            MockService,
+        },
+        types::Service,
+    };
     use common::chain::block::{
         consensus_data::ConsensusData, timestamp::BlockTimestamp, BlockReward,
     };
@@ -128,9 +158,10 @@ mod tests {
 
     fn new_mock_peersyncstate() -> PeerContext<MockService<TcpMockTransport>> {
         let addr: SocketAddr = "[::1]:8888".parse().unwrap();
-        PeerContext::<MockService<TcpMockTransport>>::new(types::MockPeerId::from_socket_address::<
-            TcpMockTransport,
-        >(&addr))
+        PeerContext::<MockService<TcpMockTransport>>::new(
+            types::MockPeerId::from_socket_address::<TcpMockTransport>(&addr),
+            Services::default(),
+        )
     }
 
     #[test]
@@ -139,6 +170,19 @@ mod tests {
         assert_eq!(peer.state, PeerSyncState::Unknown);
     }
 
+    #[test]
+    fn only_common_services_are_used() {
+        let services =
+            Services::from_bits(1 << 40).with(Service::Pruned).with(Service::CompactBlocks);
+        let peer =
+            PeerContext::<MockService<TcpMockTransport>>::new(MockPeerId::random(), services);
+
+        assert!(peer.services().has(Service::CompactBlocks));
+        assert!(!peer.services().has(Service::Pruned));
+        assert!(!peer.services().has(Service::FullNode));
+        assert_eq!(peer.services().bits(), Service::CompactBlocks as u64);
+    }
+
     #[test]
     fn test_set_state() {
         let mut peer = new_mock_peersyncstate();
@@ -157,4 +201,14 @@ mod tests {
         peer.set_state(PeerSyncState::UploadingBlocks(header.get_id()));
         assert_eq!(peer.state, PeerSyncState::UploadingBlocks(header.get_id()));
     }
+
+    #[test]
+    fn best_known_height_only_increases() {
+        let mut peer = new_mock_peersyncstate();
+        assert_eq!(peer.best_known_height(), None);
+
+        peer.register_known_height(BlockHeight::new(10));
+        peer.register_known_height(BlockHeight::new(5));
+        assert_eq!(peer.best_known_height(), Some(BlockHeight::new(10)));
+    }
 }
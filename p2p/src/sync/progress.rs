@@ -0,0 +1,142 @@
+// Copyright (c) 2022 RBB S.r.l
+// opensource@mintlayer.org
+// SPDX-License-Identifier: MIT
+// Licensed under the MIT License;
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://github.com/mintlayer/mintlayer-core/blob/master/LICENSE
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Tracking of the progress of the local chain
+
+use std::time::{Duration, Instant};
+
+use common::primitives::BlockHeight;
+
+/// Remembers when the height of the local best block last changed
+pub struct ChainProgress {
+    height: BlockHeight,
+    changed_at: Instant,
+}
+
+impl ChainProgress {
+    pub fn new(height: BlockHeight, now: Instant) -> Self {
+        Self {
+            height,
+            changed_at: now,
+        }
+    }
+
+    /// Records the current height of the best block
+    pub fn update(&mut self, height: BlockHeight, now: Instant) {
+        if height != self.height {
+            self.height = height;
+            self.changed_at = now;
+        }
+    }
+
+    /// Starts a new time window without a change of the height, used after the peers that
+    /// stalled the chain have been replaced
+    pub fn restart(&mut self, now: Instant) {
+        self.changed_at = now;
+    }
+
+    /// Returns the time since the height last changed
+    pub fn time_since_change(&self, now: Instant) -> Duration {
+        now.saturating_duration_since(self.changed_at)
+    }
+
+    /// Returns true if the height hasn't changed for `window`
+    pub fn is_stalled(&self, now: Instant, window: Duration) -> bool {
+        self.time_since_change(now) >= window
+    }
+}
+
+/// Estimates the height of the network tip from the local best block and the best header
+///
+/// Blocks that should have been produced since the best block, given the target block spacing,
+/// are assumed to exist.
+pub fn estimate_tip_height(
+    block_height: BlockHeight,
+    block_time: Duration,
+    header_height: BlockHeight,
+    now: Duration,
+    block_spacing: Duration,
+) -> BlockHeight {
+    let spacing = std::cmp::max(block_spacing.as_secs(), 1);
+    let missing_blocks = now.saturating_sub(block_time).as_secs() / spacing;
+    let estimate = block_height.checked_add(missing_blocks).unwrap_or_else(BlockHeight::max);
+    std::cmp::max(estimate, header_height)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WINDOW: Duration = Duration::from_secs(60);
+
+    #[test]
+    fn stalled_without_height_change() {
+        let now = Instant::now();
+        let mut progress = ChainProgress::new(BlockHeight::new(1), now);
+
+        progress.update(BlockHeight::new(1), now + WINDOW / 2);
+        assert!(!progress.is_stalled(now + WINDOW / 2, WINDOW));
+        assert!(progress.is_stalled(now + WINDOW, WINDOW));
+
+        progress.update(BlockHeight::new(2), now + WINDOW);
+        assert!(!progress.is_stalled(now + WINDOW, WINDOW));
+
+        progress.restart(now + 3 * WINDOW);
+        assert!(!progress.is_stalled(now + 3 * WINDOW, WINDOW));
+    }
+
+    #[test]
+    fn tip_estimate() {
+        let spacing = Duration::from_secs(120);
+        let block_time = Duration::from_secs(1_000_000);
+
+        // 10 blocks are expected to have been produced since the best block
+        let now = block_time + spacing * 10;
+        assert_eq!(
+            estimate_tip_height(
+                BlockHeight::new(5),
+                block_time,
+                BlockHeight::new(7),
+                now,
+                spacing
+            ),
+            BlockHeight::new(15)
+        );
+
+        // Known headers take precedence over the estimate
+        assert_eq!(
+            estimate_tip_height(
+                BlockHeight::new(5),
+                block_time,
+                BlockHeight::new(20),
+                now,
+                spacing
+            ),
+            BlockHeight::new(20)
+        );
+
+        // A best block from the future doesn't break the estimate
+        assert_eq!(
+            estimate_tip_height(
+                BlockHeight::new(5),
+                now,
+                BlockHeight::new(5),
+                block_time,
+                spacing
+            ),
+            BlockHeight::new(5)
+        );
+    }
+}
@@ -24,18 +24,46 @@ pub enum RequestType {
 
     /// Block request
     GetBlocks(Vec<Id<Block>>),
+
+    /// Compact block request
+    GetCompactBlock(Id<Block>),
+
+    /// Request for the missing transactions of a compact block
+    GetBlockTransactions(Id<Block>),
+
+    /// Request for the addresses of other peers
+    GetAddresses,
+}
+
+impl RequestType {
+    /// Time the peer has to respond to the request
+    pub fn timeout(&self) -> Duration {
+        match self {
+            RequestType::GetHeaders => HEADER_REQUEST_TIMEOUT,
+            RequestType::GetBlocks(_) => BLOCK_REQUEST_TIMEOUT,
+            RequestType::GetCompactBlock(_)
+            | RequestType::GetBlockTransactions(_)
+            | RequestType::GetAddresses => REQUEST_TIMEOUT,
+        }
+    }
 }
 
 /// Request state
 pub struct RequestState<T: NetworkingService> {
     /// Unique ID of the remote peer
-    pub(super) _peer_id: T::PeerId,
+    pub(super) peer_id: T::PeerId,
 
     /// Request type
     pub(super) request_type: RequestType,
 
     /// How many times the request has been sent
     pub(super) retry_count: usize,
+
+    /// When the request was sent
+    pub(super) sent_at: Instant,
+
+    /// When the request is considered timed out if the peer hasn't responded
+    pub(super) deadline: Instant,
 }
 
 impl<T> BlockSyncManager<T>
@@ -105,13 +133,28 @@ where
         request_type: RequestType,
         retry_count: usize,
     ) -> crate::Result<()> {
+        self.report_to_peer_manager(PeerManagerEvent::MessageSent(
+            peer_id,
+            request.kind(),
+            request.encoded_size(),
+        ));
+        if let Some(capture) = &mut self.capture {
+            capture.record(
+                &peer_id,
+                capture::Direction::Outbound,
+                capture::CapturedPayload::Request(request.clone()),
+            );
+        }
         let request_id = self.peer_sync_handle.send_request(peer_id, request).await?;
+        let sent_at = Instant::now();
         self.requests.insert(
             request_id,
             RequestState {
-                _peer_id: peer_id,
+                peer_id,
+                deadline: sent_at + request_type.timeout(),
                 request_type,
                 retry_count,
+                sent_at,
             },
         );
         Ok(())
@@ -152,6 +195,86 @@ where
         Ok(())
     }
 
+    /// Send compact block request to remote peer
+    ///
+    /// Send compact block request to remote peer and update the state to `UploadingBlocks`.
+    ///
+    /// # Arguments
+    /// * `peer_id` - peer ID of the remote node
+    /// * `block_id` - ID of the block that is requested
+    /// * `retry_count` - how many times the request has been resent
+    pub async fn send_compact_block_request(
+        &mut self,
+        peer_id: T::PeerId,
+        block_id: Id<Block>,
+        retry_count: usize,
+    ) -> crate::Result<()> {
+        ensure!(
+            self.peers.contains_key(&peer_id),
+            P2pError::PeerError(PeerError::PeerDoesntExist),
+        );
+
+        log::trace!(
+            "send compact block request to {peer_id}, retry count {retry_count}, block id {block_id}"
+        );
+
+        let request =
+            message::Request::CompactBlockRequest(message::CompactBlockRequest::new(block_id));
+        self.send_request(
+            peer_id,
+            request,
+            RequestType::GetCompactBlock(block_id),
+            retry_count,
+        )
+        .await?;
+
+        self.peers
+            .get_mut(&peer_id)
+            .ok_or(P2pError::PeerError(PeerError::PeerDoesntExist))?
+            .set_state(peer::PeerSyncState::UploadingBlocks(block_id));
+        Ok(())
+    }
+
+    /// Send request for the transactions of a compact block that are missing locally
+    ///
+    /// # Arguments
+    /// * `peer_id` - peer ID of the remote node
+    /// * `block_id` - ID of the block the transactions belong to
+    /// * `indexes` - indexes of the missing transactions in the block
+    pub async fn send_block_transactions_request(
+        &mut self,
+        peer_id: T::PeerId,
+        block_id: Id<Block>,
+        indexes: Vec<u32>,
+    ) -> crate::Result<()> {
+        log::trace!(
+            "send block transactions request to {peer_id}, block id {block_id}, {} transactions",
+            indexes.len()
+        );
+
+        let request = message::Request::BlockTransactionsRequest(
+            message::BlockTransactionsRequest::new(block_id, indexes),
+        );
+        self.send_request(
+            peer_id,
+            request,
+            RequestType::GetBlockTransactions(block_id),
+            0,
+        )
+        .await
+    }
+
+    /// Send request for the addresses of other peers known to the remote peer
+    ///
+    /// # Arguments
+    /// * `peer_id` - peer ID of the remote node
+    pub async fn send_addr_list_request(&mut self, peer_id: T::PeerId) -> crate::Result<()> {
+        log::trace!("send address list request to {peer_id}");
+
+        let request = message::Request::AddrListRequest(message::AddrListRequest::new());
+        self.send_request(peer_id, request, RequestType::GetAddresses, 0).await
+    }
+
     /// Send header request to remote peer
     ///
     /// Send header request to remote peer and update the state to `UploadingHeaders`.
@@ -185,16 +308,40 @@ where
         Ok(())
     }
 
+    /// Helper function for sending a response to remote
+    async fn send_response(
+        &mut self,
+        peer_id: T::PeerId,
+        request_id: T::SyncingPeerRequestId,
+        response: message::Response,
+    ) -> crate::Result<()> {
+        self.report_to_peer_manager(PeerManagerEvent::MessageSent(
+            peer_id,
+            response.kind(),
+            response.encoded_size(),
+        ));
+        if let Some(capture) = &mut self.capture {
+            capture.record(
+                &peer_id,
+                capture::Direction::Outbound,
+                capture::CapturedPayload::Response(response.clone()),
+            );
+        }
+        self.peer_sync_handle.send_response(request_id, response).await
+    }
+
     /// Send header response to remote peer
     ///
     /// The header request that is removed from remote peer contains
     /// a locator object. Local node uses this object to find common
     ///
     /// # Arguments
+    /// * `peer_id` - peer ID of the remote node
     /// * `request_id` - ID of the request that this is a response to
     /// * `headers` - headers that the remote requested
     pub async fn send_header_response(
         &mut self,
+        peer_id: T::PeerId,
         request_id: T::SyncingPeerRequestId,
         headers: Vec<BlockHeader>,
     ) -> crate::Result<()> {
@@ -202,7 +349,7 @@ where
 
         // TODO: save sent header IDs somewhere and validate future requests against those?
         let message = self.make_header_response(headers);
-        self.peer_sync_handle.send_response(request_id, message).await
+        self.send_response(peer_id, request_id, message).await
     }
 
     /// Send header response to remote peer
@@ -211,10 +358,12 @@ where
     /// a locator object. Local node uses this object to find common
     ///
     /// # Arguments
+    /// * `peer_id` - peer ID of the remote node
     /// * `request_id` - ID of the request that this is a response to
     /// * `headers` - headers that the remote requested
     pub async fn send_block_response(
         &mut self,
+        peer_id: T::PeerId,
         request_id: T::SyncingPeerRequestId,
         blocks: Vec<Block>,
     ) -> crate::Result<()> {
@@ -222,6 +371,65 @@ where
 
         // TODO: save sent block IDs somewhere and validate future requests against those?
         let message = self.make_block_response(blocks);
-        self.peer_sync_handle.send_response(request_id, message).await
+        self.send_response(peer_id, request_id, message).await
+    }
+
+    /// Send compact block response to remote peer
+    ///
+    /// # Arguments
+    /// * `peer_id` - peer ID of the remote node
+    /// * `request_id` - ID of the request that this is a response to
+    /// * `compact_block` - the requested block in compact form
+    pub async fn send_compact_block_response(
+        &mut self,
+        peer_id: T::PeerId,
+        request_id: T::SyncingPeerRequestId,
+        compact_block: message::CompactBlock,
+    ) -> crate::Result<()> {
+        log::trace!("send compact block response, request id {request_id:?}");
+
+        let message = message::Response::CompactBlockResponse(compact_block);
+        self.send_response(peer_id, request_id, message).await
+    }
+
+    /// Send block transactions response to remote peer
+    ///
+    /// # Arguments
+    /// * `peer_id` - peer ID of the remote node
+    /// * `request_id` - ID of the request that this is a response to
+    /// * `block_id` - ID of the block the transactions belong to
+    /// * `txs` - the requested transactions
+    pub async fn send_block_transactions_response(
+        &mut self,
+        peer_id: T::PeerId,
+        request_id: T::SyncingPeerRequestId,
+        block_id: Id<Block>,
+        txs: Vec<SignedTransaction>,
+    ) -> crate::Result<()> {
+        log::trace!("send block transactions response, request id {request_id:?}");
+
+        let message = message::Response::BlockTransactionsResponse(
+            message::BlockTransactionsResponse::new(block_id, txs),
+        );
+        self.send_response(peer_id, request_id, message).await
+    }
+
+    /// Send address list response to remote peer
+    ///
+    /// # Arguments
+    /// * `peer_id` - peer ID of the remote node
+    /// * `request_id` - ID of the request that this is a response to
+    /// * `addresses` - addresses of the peers known to the local node
+    pub async fn send_addr_list_response(
+        &mut self,
+        peer_id: T::PeerId,
+        request_id: T::SyncingPeerRequestId,
+        addresses: Vec<message::PeerAddress>,
+    ) -> crate::Result<()> {
+        log::trace!("send address list response, request id {request_id:?}");
+
+        let message =
+            message::Response::AddrListResponse(message::AddrListResponse::new(addresses));
+        self.send_response(peer_id, request_id, message).await
     }
 }
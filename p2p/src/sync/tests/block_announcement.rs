@@ -0,0 +1,98 @@
+// Copyright (c) 2022 RBB S.r.l
+// opensource@mintlayer.org
+// SPDX-License-Identifier: MIT
+// Licensed under the MIT License;
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://github.com/mintlayer/mintlayer-core/blob/master/LICENSE
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use super::*;
+use crate::{
+    net::mock::{
+        transport::{ChannelMockTransport, TcpMockTransport},
+        MockService,
+    },
+    peer_manager::helpers::connect_services,
+};
+use p2p_test_utils::{
+    MakeChannelAddress, MakeP2pAddress, MakeTcpAddress, MakeTestAddress, TestBlockInfo,
+};
+
+// a compact block pushed by a peer that doesn't support compact blocks isn't accepted, the full
+// block is requested instead
+async fn compact_block_without_service<A, T>()
+where
+    A: MakeTestAddress<Address = T::Address>,
+    T: NetworkingService + 'static,
+    T::ConnectivityHandle: ConnectivityService<T>,
+    T::SyncingMessagingHandle: SyncingMessagingService<T>,
+{
+    let config = Arc::new(common::chain::config::create_unit_test_config());
+
+    let (mut mgr1, mut conn1, _sync1, _pm1) = make_sync_manager::<T>(A::make_address()).await;
+    let (_mgr2, mut conn2, _sync2, _pm2) = make_sync_manager::<T>(A::make_address()).await;
+    connect_services::<T>(&mut conn1, &mut conn2).await;
+
+    let peer_id = *conn2.peer_id();
+    let locator = mgr1.chainstate_handle.call(|this| this.get_locator()).await.unwrap().unwrap();
+    let mut peer = peer::PeerContext::new_with_locator(
+        peer_id,
+        locator,
+        Services::default().without(Service::CompactBlocks),
+    );
+    peer.set_state(peer::PeerSyncState::Idle);
+    mgr1.peers.insert(peer_id, peer);
+
+    let block = p2p_test_utils::create_n_blocks(
+        Arc::clone(&config),
+        TestBlockInfo::from_genesis(config.genesis_block()),
+        1,
+    )
+    .pop()
+    .unwrap();
+    let block_id = block.get_id();
+    let compact_block = compact_block::make_compact_block(&block, 0, &[]);
+
+    assert!(matches!(
+        mgr1.process_block_announcement(peer_id, block.header().clone(), Some(compact_block))
+            .await,
+        Ok(ValidationResult::Ignore)
+    ));
+    assert!(
+        mgr1.requests.values().any(|request| request.peer_id == peer_id
+            && matches!(
+                &request.request_type,
+                request::RequestType::GetBlocks(block_ids) if block_ids == &vec![block_id]
+            ))
+    );
+    assert_eq!(
+        mgr1.chainstate_handle
+            .call(|this| this.get_best_block_id())
+            .await
+            .unwrap()
+            .unwrap(),
+        config.genesis_block_id()
+    );
+}
+
+#[tokio::test]
+async fn compact_block_without_service_libp2p() {
+    compact_block_without_service::<MakeP2pAddress, Libp2pService>().await;
+}
+
+#[tokio::test]
+async fn compact_block_without_service_mock_tcp() {
+    compact_block_without_service::<MakeTcpAddress, MockService<TcpMockTransport>>().await;
+}
+
+#[tokio::test]
+async fn compact_block_without_service_mock_channels() {
+    compact_block_without_service::<MakeChannelAddress, MockService<ChannelMockTransport>>().await;
+}
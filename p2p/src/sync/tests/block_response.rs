@@ -17,10 +17,13 @@ use chainstate::ChainstateError;
 use common::chain::block::consensus_data::PoWData;
 
 use super::*;
-use crate::net::mock::{
-    transport::{ChannelMockTransport, TcpMockTransport},
-    types::MockPeerId,
-    MockService,
+use crate::{
+    net::mock::{
+        transport::{ChannelMockTransport, TcpMockTransport},
+        types::MockPeerId,
+        MockService,
+    },
+    peer_manager::helpers::connect_services,
 };
 use p2p_test_utils::{
     MakeChannelAddress, MakeP2pAddress, MakeTcpAddress, MakeTestAddress, TestBlockInfo,
@@ -89,10 +92,7 @@ where
         .unwrap()
         .set_state(peer::PeerSyncState::UploadingBlocks(first.get_id()));
 
-    assert_eq!(
-        mgr.validate_block_response(&peer_id, blocks).await,
-        Ok(None),
-    );
+    assert_eq!(mgr.validate_block_response(&peer_id, blocks).await, Ok(()));
 }
 
 #[tokio::test]
@@ -185,12 +185,9 @@ where
 
     assert_eq!(
         mgr.validate_block_response(&peer_id, blocks.clone()).await,
-        Ok(None),
-    );
-    assert_eq!(
-        mgr.validate_block_response(&peer_id, blocks).await,
-        Ok(None),
+        Ok(()),
     );
+    assert_eq!(mgr.validate_block_response(&peer_id, blocks).await, Ok(()));
 }
 
 #[tokio::test]
@@ -269,3 +266,136 @@ async fn invalid_block_mock_tcp() {
 async fn invalid_block_mock_channels() {
     invalid_block::<MakeChannelAddress, MockPeerId, MockService<ChannelMockTransport>>().await;
 }
+
+// a peer that doesn't have the requested block responds with an empty list and the block is
+// requested from another peer without penalizing the first one
+async fn empty_block_response_reschedules<A, T>()
+where
+    A: MakeTestAddress<Address = T::Address>,
+    T: NetworkingService + 'static,
+    T::ConnectivityHandle: ConnectivityService<T>,
+    T::SyncingMessagingHandle: SyncingMessagingService<T>,
+{
+    let config = Arc::new(common::chain::config::create_unit_test_config());
+
+    let (mut mgr1, mut conn1, _sync1, mut pm1) = make_sync_manager::<T>(A::make_address()).await;
+    let (_mgr2, mut conn2, _sync2, _pm2) = make_sync_manager::<T>(A::make_address()).await;
+    let (_mgr3, mut conn3, _sync3, _pm3) = make_sync_manager::<T>(A::make_address()).await;
+
+    connect_services::<T>(&mut conn1, &mut conn2).await;
+    connect_services::<T>(&mut conn1, &mut conn3).await;
+    let peer_ids = [*conn2.peer_id(), *conn3.peer_id()];
+    for peer_id in peer_ids {
+        register_peer(&mut mgr1, peer_id).await;
+    }
+
+    let blocks = p2p_test_utils::create_n_blocks(
+        Arc::clone(&config),
+        TestBlockInfo::from_genesis(config.genesis_block()),
+        1,
+    );
+    let headers = vec![blocks[0].header().clone()];
+    for peer_id in peer_ids {
+        mgr1.downloads.add_headers(peer_id, &headers);
+    }
+
+    let scheduled = mgr1.downloads.schedule(Instant::now());
+    assert_eq!(scheduled.len(), 1);
+    let (peer_id, block_id) = scheduled[0];
+    let other_peer_id = *peer_ids.iter().find(|id| **id != peer_id).unwrap();
+
+    assert_eq!(
+        mgr1.process_unavailable_blocks(peer_id, vec![block_id]).await,
+        Ok(())
+    );
+    assert!(!mgr1.downloads.is_requested_from(&peer_id, &block_id));
+    assert!(mgr1.downloads.is_requested_from(&other_peer_id, &block_id));
+    assert!(!std::matches!(
+        pm1.try_recv(),
+        Ok(PeerManagerEvent::AdjustPeerScore(_, _, _))
+    ));
+}
+
+#[tokio::test]
+async fn empty_block_response_reschedules_libp2p() {
+    empty_block_response_reschedules::<MakeP2pAddress, Libp2pService>().await;
+}
+
+#[tokio::test]
+async fn empty_block_response_reschedules_mock_tcp() {
+    empty_block_response_reschedules::<MakeTcpAddress, MockService<TcpMockTransport>>().await;
+}
+
+#[tokio::test]
+async fn empty_block_response_reschedules_mock_channels() {
+    empty_block_response_reschedules::<MakeChannelAddress, MockService<ChannelMockTransport>>()
+        .await;
+}
+
+// an announced block that the peer doesn't have after all is requested from another peer
+async fn empty_block_response_refetches_announced_block<A, T>()
+where
+    A: MakeTestAddress<Address = T::Address>,
+    T: NetworkingService + 'static,
+    T::ConnectivityHandle: ConnectivityService<T>,
+    T::SyncingMessagingHandle: SyncingMessagingService<T>,
+{
+    let (mut mgr1, mut conn1, _sync1, _pm1) = make_sync_manager::<T>(A::make_address()).await;
+    let (_mgr2, mut conn2, _sync2, _pm2) = make_sync_manager::<T>(A::make_address()).await;
+    let (mut mgr3, mut conn3, _sync3, _pm3) = make_sync_manager::<T>(A::make_address()).await;
+
+    connect_services::<T>(&mut conn1, &mut conn2).await;
+    connect_services::<T>(&mut conn1, &mut conn3).await;
+    let (peer_id2, peer_id3) = (*conn2.peer_id(), *conn3.peer_id());
+    for peer_id in [peer_id2, peer_id3] {
+        register_peer(&mut mgr1, peer_id).await;
+        mgr1.peers.get_mut(&peer_id).unwrap().set_state(peer::PeerSyncState::Idle);
+    }
+
+    let block_id = Id::<Block>::new(common::primitives::H256([0x07; 32]));
+    mgr1.peers
+        .get_mut(&peer_id2)
+        .unwrap()
+        .set_state(peer::PeerSyncState::UploadingBlocks(block_id));
+
+    assert_eq!(mgr1.process_block_response(peer_id2, vec![]).await, Ok(()));
+    assert_eq!(
+        mgr1.peers.get(&peer_id2).unwrap().state(),
+        &peer::PeerSyncState::Idle
+    );
+
+    match mgr3.peer_sync_handle.poll_next().await.unwrap() {
+        net::types::SyncingEvent::Request {
+            peer_id,
+            request: message::Request::BlockListRequest(request),
+            ..
+        } => {
+            assert_eq!(&peer_id, conn1.peer_id());
+            assert_eq!(request.block_ids(), &vec![block_id]);
+        }
+        event => panic!("invalid event received: {event:?}"),
+    }
+}
+
+#[tokio::test]
+async fn empty_block_response_refetches_announced_block_libp2p() {
+    empty_block_response_refetches_announced_block::<MakeP2pAddress, Libp2pService>().await;
+}
+
+#[tokio::test]
+async fn empty_block_response_refetches_announced_block_mock_tcp() {
+    empty_block_response_refetches_announced_block::<
+        MakeTcpAddress,
+        MockService<TcpMockTransport>,
+    >()
+    .await;
+}
+
+#[tokio::test]
+async fn empty_block_response_refetches_announced_block_mock_channels() {
+    empty_block_response_refetches_announced_block::<
+        MakeChannelAddress,
+        MockService<ChannelMockTransport>,
+    >()
+    .await;
+}
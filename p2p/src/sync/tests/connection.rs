@@ -38,7 +38,7 @@ where
 
     assert_eq!(mgr.peers.len(), 1);
     assert_eq!(
-        mgr.register_peer(peer_id).await,
+        mgr.register_peer(peer_id, Services::default()).await,
         Err(P2pError::PeerError(PeerError::PeerAlreadyExists))
     );
 }
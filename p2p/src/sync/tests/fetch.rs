@@ -0,0 +1,232 @@
+// Copyright (c) 2022 RBB S.r.l
+// opensource@mintlayer.org
+// SPDX-License-Identifier: MIT
+// Licensed under the MIT License;
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://github.com/mintlayer/mintlayer-core/blob/master/LICENSE
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use super::*;
+use crate::{
+    message::*,
+    net::mock::{
+        transport::{ChannelMockTransport, TcpMockTransport},
+        MockService,
+    },
+    peer_manager::helpers::connect_services,
+};
+use p2p_test_utils::{
+    MakeChannelAddress, MakeP2pAddress, MakeTcpAddress, MakeTestAddress, TestBlockInfo,
+};
+
+// a block can't be fetched if there are no peers to ask
+async fn fetch_block_without_peers<A, T>()
+where
+    A: MakeTestAddress<Address = T::Address>,
+    T: NetworkingService + 'static,
+    T::ConnectivityHandle: ConnectivityService<T>,
+    T::SyncingMessagingHandle: SyncingMessagingService<T>,
+{
+    let config = Arc::new(common::chain::config::create_unit_test_config());
+    let (mut mgr, _conn, _sync, _pm) = make_sync_manager::<T>(A::make_address()).await;
+
+    let block = p2p_test_utils::create_block(
+        Arc::clone(&config),
+        TestBlockInfo::from_genesis(config.genesis_block()),
+    );
+    assert_eq!(
+        mgr.fetch_block(block.get_id()).await,
+        Err(P2pError::PeerError(PeerError::NoPeers)),
+    );
+}
+
+#[tokio::test]
+async fn fetch_block_without_peers_libp2p() {
+    fetch_block_without_peers::<MakeP2pAddress, Libp2pService>().await;
+}
+
+#[tokio::test]
+async fn fetch_block_without_peers_mock_tcp() {
+    fetch_block_without_peers::<MakeTcpAddress, MockService<TcpMockTransport>>().await;
+}
+
+#[tokio::test]
+async fn fetch_block_without_peers_mock_channels() {
+    fetch_block_without_peers::<MakeChannelAddress, MockService<ChannelMockTransport>>().await;
+}
+
+// a block whose parent isn't known is kept as an orphan and connected once the parent arrives
+async fn orphan_block_connected<A, P, T>()
+where
+    A: MakeTestAddress<Address = T::Address>,
+    P: MakeTestPeerId<PeerId = T::PeerId>,
+    T: NetworkingService + 'static,
+    T::ConnectivityHandle: ConnectivityService<T>,
+    T::SyncingMessagingHandle: SyncingMessagingService<T>,
+{
+    let peer_id = P::random();
+    let config = Arc::new(common::chain::config::create_unit_test_config());
+    let (mut mgr, _conn, _sync, _pm) = make_sync_manager::<T>(A::make_address()).await;
+    register_peer(&mut mgr, peer_id).await;
+
+    let blocks = p2p_test_utils::create_n_blocks(
+        Arc::clone(&config),
+        TestBlockInfo::from_genesis(config.genesis_block()),
+        2,
+    );
+    let (parent_id, orphan_id) = (blocks[0].get_id(), blocks[1].get_id());
+
+    mgr.peers
+        .get_mut(&peer_id)
+        .unwrap()
+        .set_state(peer::PeerSyncState::UploadingBlocks(orphan_id));
+    assert_eq!(
+        mgr.validate_block_response(&peer_id, vec![blocks[1].clone()]).await,
+        Ok(()),
+    );
+    assert!(mgr
+        .chainstate_handle
+        .call(move |this| this.is_already_an_orphan(&orphan_id))
+        .await
+        .unwrap());
+
+    mgr.peers
+        .get_mut(&peer_id)
+        .unwrap()
+        .set_state(peer::PeerSyncState::UploadingBlocks(parent_id));
+    assert_eq!(
+        mgr.validate_block_response(&peer_id, vec![blocks[0].clone()]).await,
+        Ok(()),
+    );
+    let best_block_id = mgr
+        .chainstate_handle
+        .call(|this| this.get_best_block_id())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(best_block_id, Id::<GenBlock>::from(orphan_id));
+}
+
+#[tokio::test]
+async fn orphan_block_connected_libp2p() {
+    orphan_block_connected::<MakeP2pAddress, PeerId, Libp2pService>().await;
+}
+
+#[tokio::test]
+async fn orphan_block_connected_mock_tcp() {
+    orphan_block_connected::<MakeTcpAddress, MockPeerId, MockService<TcpMockTransport>>().await;
+}
+
+#[tokio::test]
+async fn orphan_block_connected_mock_channels() {
+    orphan_block_connected::<MakeChannelAddress, MockPeerId, MockService<ChannelMockTransport>>()
+        .await;
+}
+
+// the block is requested from a peer and submitted to the chainstate, unless the peer responds
+// that it doesn't have the block
+async fn fetch_block_from_peer<A, T>(peer_has_block: bool)
+where
+    A: MakeTestAddress<Address = T::Address>,
+    T: NetworkingService + std::fmt::Debug + 'static,
+    T::ConnectivityHandle: ConnectivityService<T>,
+    T::SyncingMessagingHandle: SyncingMessagingService<T>,
+{
+    let config = Arc::new(common::chain::config::create_unit_test_config());
+    let (mut mgr1, mut conn1, _sync1, _pm1) = make_sync_manager::<T>(A::make_address()).await;
+    let (mut mgr2, mut conn2, _sync2, _pm2) = make_sync_manager::<T>(A::make_address()).await;
+
+    // connect the two managers together so that they can exchange messages
+    connect_services::<T>(&mut conn1, &mut conn2).await;
+    register_peer(&mut mgr1, *conn2.peer_id()).await;
+    register_peer(&mut mgr2, *conn1.peer_id()).await;
+    mgr1.peers
+        .get_mut(conn2.peer_id())
+        .unwrap()
+        .set_state(peer::PeerSyncState::Idle);
+
+    let blocks = p2p_test_utils::create_n_blocks(
+        Arc::clone(&config),
+        TestBlockInfo::from_genesis(config.genesis_block()),
+        1,
+    );
+    let block_id = blocks[0].get_id();
+    if peer_has_block {
+        p2p_test_utils::import_blocks(&mgr2.chainstate_handle, blocks).await;
+    }
+
+    mgr1.fetch_block(block_id).await.unwrap();
+    assert!(mgr1.block_fetches.contains_key(&block_id));
+
+    match mgr2.peer_sync_handle.poll_next().await.unwrap() {
+        net::types::SyncingEvent::Request {
+            peer_id,
+            request_id,
+            request: Request::BlockListRequest(request),
+        } => {
+            assert_eq!(request.block_ids(), &vec![block_id]);
+            mgr2.process_block_request(peer_id, request_id, request.into_block_ids())
+                .await
+                .unwrap();
+        }
+        event => panic!("invalid event received: {event:?}"),
+    }
+
+    match mgr1.peer_sync_handle.poll_next().await.unwrap() {
+        net::types::SyncingEvent::Response {
+            peer_id,
+            request_id: _,
+            response: Response::BlockListResponse(response),
+        } => {
+            assert_eq!(response.blocks().len(), usize::from(peer_has_block));
+            mgr1.process_block_response(peer_id, response.into_blocks()).await.unwrap();
+        }
+        event => panic!("invalid event received: {event:?}"),
+    }
+
+    assert!(mgr1.block_fetches.is_empty());
+    let block_index = mgr1
+        .chainstate_handle
+        .call(move |this| this.get_block_index(&block_id))
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(block_index.is_some(), peer_has_block);
+}
+
+#[tokio::test]
+async fn fetch_block_from_peer_libp2p() {
+    fetch_block_from_peer::<MakeP2pAddress, Libp2pService>(true).await;
+}
+
+#[tokio::test]
+async fn fetch_block_from_peer_mock_tcp() {
+    fetch_block_from_peer::<MakeTcpAddress, MockService<TcpMockTransport>>(true).await;
+}
+
+#[tokio::test]
+async fn fetch_block_from_peer_mock_channels() {
+    fetch_block_from_peer::<MakeChannelAddress, MockService<ChannelMockTransport>>(true).await;
+}
+
+#[tokio::test]
+async fn fetch_unknown_block_libp2p() {
+    fetch_block_from_peer::<MakeP2pAddress, Libp2pService>(false).await;
+}
+
+#[tokio::test]
+async fn fetch_unknown_block_mock_tcp() {
+    fetch_block_from_peer::<MakeTcpAddress, MockService<TcpMockTransport>>(false).await;
+}
+
+#[tokio::test]
+async fn fetch_unknown_block_mock_channels() {
+    fetch_block_from_peer::<MakeChannelAddress, MockService<ChannelMockTransport>>(false).await;
+}
@@ -87,7 +87,7 @@ where
 
     assert_eq!(
         mgr.validate_header_response(&peer_id, vec![]).await,
-        Ok(None),
+        Ok(vec![]),
     );
 }
 
@@ -132,11 +132,10 @@ where
     .iter()
     .map(|block| block.header().clone())
     .collect::<Vec<_>>();
-    let first = headers[0].clone();
 
     assert_eq!(
-        mgr.validate_header_response(&peer_id, headers).await,
-        Ok(Some(first)),
+        mgr.validate_header_response(&peer_id, headers.clone()).await,
+        Ok(headers),
     );
 }
 
@@ -0,0 +1,328 @@
+// Copyright (c) 2022 RBB S.r.l
+// opensource@mintlayer.org
+// SPDX-License-Identifier: MIT
+// Licensed under the MIT License;
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://github.com/mintlayer/mintlayer-core/blob/master/LICENSE
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use super::*;
+use crate::{
+    message::*,
+    net::mock::{
+        transport::{ChannelMockTransport, TcpMockTransport},
+        MockService,
+    },
+    peer_manager::helpers::connect_services,
+};
+use p2p_test_utils::{
+    MakeChannelAddress, MakeP2pAddress, MakeTcpAddress, MakeTestAddress, TestBlockInfo,
+};
+
+// send `requests` from the second manager and check each of them against the limits of the first
+async fn check_requests<A, T>(
+    p2p_config: P2pConfig,
+    requests: Vec<Request>,
+) -> Vec<crate::Result<()>>
+where
+    A: MakeTestAddress<Address = T::Address>,
+    T: NetworkingService + std::fmt::Debug + 'static,
+    T::ConnectivityHandle: ConnectivityService<T>,
+    T::SyncingMessagingHandle: SyncingMessagingService<T>,
+{
+    let addr1 = A::make_address();
+    let addr2 = A::make_address();
+
+    let (mut mgr1, mut conn1, _sync1, _pm1) = make_sync_manager::<T>(addr1).await;
+    let (mut mgr2, mut conn2, _sync2, _pm2) = make_sync_manager::<T>(addr2).await;
+    mgr1.p2p_config = Arc::new(p2p_config);
+
+    connect_services::<T>(&mut conn1, &mut conn2).await;
+
+    let mut results = Vec::new();
+    for request in requests {
+        mgr2.peer_sync_handle.send_request(*conn1.peer_id(), request).await.unwrap();
+
+        let event = mgr1.peer_sync_handle.poll_next().await.unwrap();
+        let (peer_id, _kind, size) = BlockSyncManager::<T>::received_message_info(&event).unwrap();
+        assert_eq!(&peer_id, conn2.peer_id());
+        results.push(mgr1.check_message_limits(peer_id, &event, size));
+    }
+    results
+}
+
+fn header_list_request() -> Request {
+    Request::HeaderListRequest(HeaderListRequest::new(Locator::new(vec![])))
+}
+
+// the requests that exceed the burst are rejected if the bucket isn't refilled
+async fn request_rate_limit<A, T>()
+where
+    A: MakeTestAddress<Address = T::Address>,
+    T: NetworkingService + std::fmt::Debug + 'static,
+    T::ConnectivityHandle: ConnectivityService<T>,
+    T::SyncingMessagingHandle: SyncingMessagingService<T>,
+{
+    let p2p_config = P2pConfig {
+        request_rate_limit_burst: 2.into(),
+        request_rate_limit_per_sec: 0.0.into(),
+        ..Default::default()
+    };
+    let requests = vec![header_list_request(), header_list_request(), header_list_request()];
+
+    assert_eq!(
+        check_requests::<A, T>(p2p_config, requests).await,
+        vec![
+            Ok(()),
+            Ok(()),
+            Err(P2pError::ProtocolError(
+                ProtocolError::RequestRateLimitExceeded
+            )),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn request_rate_limit_libp2p() {
+    request_rate_limit::<MakeP2pAddress, Libp2pService>().await;
+}
+
+#[tokio::test]
+async fn request_rate_limit_mock_tcp() {
+    request_rate_limit::<MakeTcpAddress, MockService<TcpMockTransport>>().await;
+}
+
+#[tokio::test]
+async fn request_rate_limit_mock_channels() {
+    request_rate_limit::<MakeChannelAddress, MockService<ChannelMockTransport>>().await;
+}
+
+// block list requests are rate limited by the number of requested blocks, separately from
+// the other requests
+async fn block_request_rate_limit<A, T>()
+where
+    A: MakeTestAddress<Address = T::Address>,
+    T: NetworkingService + std::fmt::Debug + 'static,
+    T::ConnectivityHandle: ConnectivityService<T>,
+    T::SyncingMessagingHandle: SyncingMessagingService<T>,
+{
+    let p2p_config = P2pConfig {
+        request_rate_limit_burst: 1.into(),
+        request_rate_limit_per_sec: 0.0.into(),
+        block_request_rate_limit_burst: 3.into(),
+        block_request_rate_limit_per_sec: 0.0.into(),
+        ..Default::default()
+    };
+    let block_id = Id::<Block>::new(common::primitives::H256([0x04; 32]));
+    let requests = vec![
+        Request::BlockListRequest(BlockListRequest::new(vec![block_id])),
+        header_list_request(),
+        Request::BlockListRequest(BlockListRequest::new(vec![block_id, block_id])),
+        Request::BlockListRequest(BlockListRequest::new(vec![block_id])),
+    ];
+
+    assert_eq!(
+        check_requests::<A, T>(p2p_config, requests).await,
+        vec![
+            Ok(()),
+            Ok(()),
+            Ok(()),
+            Err(P2pError::ProtocolError(
+                ProtocolError::RequestRateLimitExceeded
+            )),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn block_request_rate_limit_libp2p() {
+    block_request_rate_limit::<MakeP2pAddress, Libp2pService>().await;
+}
+
+#[tokio::test]
+async fn block_request_rate_limit_mock_tcp() {
+    block_request_rate_limit::<MakeTcpAddress, MockService<TcpMockTransport>>().await;
+}
+
+#[tokio::test]
+async fn block_request_rate_limit_mock_channels() {
+    block_request_rate_limit::<MakeChannelAddress, MockService<ChannelMockTransport>>().await;
+}
+
+// a block isn't sent if its size exceeds the remaining upload limit of the peer
+async fn block_upload_rate_limit<A, T>()
+where
+    A: MakeTestAddress<Address = T::Address>,
+    T: NetworkingService + std::fmt::Debug + 'static,
+    T::ConnectivityHandle: ConnectivityService<T>,
+    T::SyncingMessagingHandle: SyncingMessagingService<T>,
+{
+    let config = Arc::new(common::chain::config::create_unit_test_config());
+    let (mut mgr1, mut conn1, _sync1, _pm1) = make_sync_manager::<T>(A::make_address()).await;
+    let (mut mgr2, mut conn2, _sync2, _pm2) = make_sync_manager::<T>(A::make_address()).await;
+
+    connect_services::<T>(&mut conn1, &mut conn2).await;
+    register_peer(&mut mgr1, *conn2.peer_id()).await;
+
+    let blocks = p2p_test_utils::create_n_blocks(
+        Arc::clone(&config),
+        TestBlockInfo::from_genesis(config.genesis_block()),
+        1,
+    );
+    let block_id = blocks[0].get_id();
+    let block_size = blocks[0].encoded_size();
+    p2p_test_utils::import_blocks(&mgr1.chainstate_handle, blocks).await;
+    mgr1.p2p_config = Arc::new(P2pConfig {
+        block_upload_rate_limit_burst: (block_size + block_size / 2).into(),
+        block_upload_rate_limit_per_sec: 0.0.into(),
+        ..Default::default()
+    });
+
+    let mut results = Vec::new();
+    for _ in 0..2 {
+        mgr2.peer_sync_handle
+            .send_request(
+                *conn1.peer_id(),
+                Request::BlockListRequest(BlockListRequest::new(vec![block_id])),
+            )
+            .await
+            .unwrap();
+        match mgr1.peer_sync_handle.poll_next().await.unwrap() {
+            net::types::SyncingEvent::Request {
+                peer_id,
+                request_id,
+                request: Request::BlockListRequest(request),
+            } => {
+                results.push(
+                    mgr1.process_block_request(peer_id, request_id, request.into_block_ids()).await,
+                );
+            }
+            event => panic!("invalid event received: {event:?}"),
+        }
+    }
+
+    assert_eq!(
+        results,
+        vec![
+            Ok(()),
+            Err(P2pError::ProtocolError(
+                ProtocolError::RequestRateLimitExceeded
+            )),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn block_upload_rate_limit_libp2p() {
+    block_upload_rate_limit::<MakeP2pAddress, Libp2pService>().await;
+}
+
+#[tokio::test]
+async fn block_upload_rate_limit_mock_tcp() {
+    block_upload_rate_limit::<MakeTcpAddress, MockService<TcpMockTransport>>().await;
+}
+
+#[tokio::test]
+async fn block_upload_rate_limit_mock_channels() {
+    block_upload_rate_limit::<MakeChannelAddress, MockService<ChannelMockTransport>>().await;
+}
+
+// block list requests can't ask for more blocks than allowed
+async fn too_many_blocks_requested<A, T>()
+where
+    A: MakeTestAddress<Address = T::Address>,
+    T: NetworkingService + std::fmt::Debug + 'static,
+    T::ConnectivityHandle: ConnectivityService<T>,
+    T::SyncingMessagingHandle: SyncingMessagingService<T>,
+{
+    let p2p_config = P2pConfig {
+        max_blocks_per_message: 1.into(),
+        ..Default::default()
+    };
+    let block_id = Id::<Block>::new(common::primitives::H256([0x04; 32]));
+    let requests = vec![
+        Request::BlockListRequest(BlockListRequest::new(vec![block_id])),
+        Request::BlockListRequest(BlockListRequest::new(vec![block_id, block_id])),
+    ];
+
+    assert_eq!(
+        check_requests::<A, T>(p2p_config, requests).await,
+        vec![
+            Ok(()),
+            Err(P2pError::ProtocolError(ProtocolError::TooManyItems(
+                "BlockListRequest",
+                2,
+                1
+            ))),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn too_many_blocks_requested_libp2p() {
+    too_many_blocks_requested::<MakeP2pAddress, Libp2pService>().await;
+}
+
+#[tokio::test]
+async fn too_many_blocks_requested_mock_tcp() {
+    too_many_blocks_requested::<MakeTcpAddress, MockService<TcpMockTransport>>().await;
+}
+
+#[tokio::test]
+async fn too_many_blocks_requested_mock_channels() {
+    too_many_blocks_requested::<MakeChannelAddress, MockService<ChannelMockTransport>>().await;
+}
+
+// messages larger than the limit of their type are rejected
+async fn message_too_large<A, T>()
+where
+    A: MakeTestAddress<Address = T::Address>,
+    T: NetworkingService + std::fmt::Debug + 'static,
+    T::ConnectivityHandle: ConnectivityService<T>,
+    T::SyncingMessagingHandle: SyncingMessagingService<T>,
+{
+    let request = header_list_request();
+    let size = request.encoded_size();
+    let p2p_config = P2pConfig {
+        max_message_size: size.into(),
+        ..Default::default()
+    };
+    let genesis_id = common::chain::config::create_unit_test_config().genesis_block_id();
+    let large_request =
+        Request::HeaderListRequest(HeaderListRequest::new(Locator::new(vec![genesis_id])));
+    let large_size = large_request.encoded_size();
+
+    assert_eq!(
+        check_requests::<A, T>(p2p_config, vec![request, large_request]).await,
+        vec![
+            Ok(()),
+            Err(P2pError::ProtocolError(ProtocolError::MessageTooLarge(
+                "HeaderListRequest",
+                large_size,
+                size
+            ))),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn message_too_large_libp2p() {
+    message_too_large::<MakeP2pAddress, Libp2pService>().await;
+}
+
+#[tokio::test]
+async fn message_too_large_mock_tcp() {
+    message_too_large::<MakeTcpAddress, MockService<TcpMockTransport>>().await;
+}
+
+#[tokio::test]
+async fn message_too_large_mock_channels() {
+    message_too_large::<MakeChannelAddress, MockService<ChannelMockTransport>>().await;
+}
@@ -23,9 +23,13 @@ use crate::{
     net::{libp2p::Libp2pService, mock::types::MockPeerId, ConnectivityService},
 };
 
+mod block_announcement;
 mod block_response;
 mod connection;
+mod fetch;
 mod header_response;
+mod limits;
+mod progress;
 mod request_response;
 
 async fn make_sync_manager<T>(
@@ -62,10 +66,26 @@ where
     tokio::spawn(async move { man.main().await });
 
     let config = Arc::new(common::chain::config::create_unit_test_config());
-    let (conn, sync) = T::start(addr, Arc::clone(&config), Default::default()).await.unwrap();
+    let (conn, sync) = T::start(
+        addr,
+        Arc::clone(&config),
+        Default::default(),
+        Default::default(),
+    )
+    .await
+    .unwrap();
+    let mempool = p2p_test_utils::start_mempool(Arc::clone(&config), handle.clone()).await;
 
     (
-        BlockSyncManager::<T>::new(Arc::clone(&config), sync, handle, rx_p2p_sync, tx_pm),
+        BlockSyncManager::<T>::new(
+            Arc::clone(&config),
+            Default::default(),
+            sync,
+            handle,
+            mempool,
+            rx_p2p_sync,
+            tx_pm,
+        ),
         conn,
         tx_p2p_sync,
         rx_pm,
@@ -81,7 +101,7 @@ where
 
     mgr.peers.insert(
         peer_id,
-        peer::PeerContext::new_with_locator(peer_id, locator),
+        peer::PeerContext::new_with_locator(peer_id, locator, Services::default()),
     );
 }
 
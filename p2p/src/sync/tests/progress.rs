@@ -0,0 +1,215 @@
+// Copyright (c) 2022 RBB S.r.l
+// opensource@mintlayer.org
+// SPDX-License-Identifier: MIT
+// Licensed under the MIT License;
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://github.com/mintlayer/mintlayer-core/blob/master/LICENSE
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use super::*;
+use crate::{
+    message::*,
+    net::mock::{
+        transport::{ChannelMockTransport, TcpMockTransport},
+        MockService,
+    },
+    peer_manager::helpers::connect_services,
+};
+use p2p_test_utils::{MakeChannelAddress, MakeP2pAddress, MakeTcpAddress, MakeTestAddress};
+
+// a header request that isn't answered before its deadline is sent again
+async fn test_request_deadline<A, T>()
+where
+    A: MakeTestAddress<Address = T::Address>,
+    T: NetworkingService + std::fmt::Debug + 'static,
+    T::ConnectivityHandle: ConnectivityService<T>,
+    T::SyncingMessagingHandle: SyncingMessagingService<T>,
+{
+    let addr1 = A::make_address();
+    let addr2 = A::make_address();
+
+    let (mut mgr1, mut conn1, _sync1, _pm1) = make_sync_manager::<T>(addr1).await;
+    let (mut mgr2, mut conn2, _sync2, _pm2) = make_sync_manager::<T>(addr2).await;
+
+    connect_services::<T>(&mut conn1, &mut conn2).await;
+    mgr1.register_peer(*conn2.peer_id(), Services::default()).await.unwrap();
+
+    for _ in 0..2 {
+        match mgr2.peer_sync_handle.poll_next().await.unwrap() {
+            net::types::SyncingEvent::Request {
+                peer_id: _,
+                request_id: _,
+                request: Request::HeaderListRequest(_),
+            } => {}
+            event => panic!("invalid event received: {event:?}"),
+        }
+
+        // nothing expires before the deadline
+        mgr1.process_expired_requests(Instant::now()).await.unwrap();
+        assert_eq!(mgr1.requests.len(), 1);
+
+        mgr1.process_expired_requests(Instant::now() + HEADER_REQUEST_TIMEOUT)
+            .await
+            .unwrap();
+    }
+
+    let request = mgr1.requests.values().next().unwrap();
+    assert_eq!(request.peer_id, *conn2.peer_id());
+    assert_eq!(request.retry_count, 2);
+}
+
+#[tokio::test]
+async fn test_request_deadline_libp2p() {
+    test_request_deadline::<MakeP2pAddress, Libp2pService>().await;
+}
+
+#[tokio::test]
+async fn test_request_deadline_mock_tcp() {
+    test_request_deadline::<MakeTcpAddress, MockService<TcpMockTransport>>().await;
+}
+
+#[tokio::test]
+async fn test_request_deadline_mock_channels() {
+    test_request_deadline::<MakeChannelAddress, MockService<ChannelMockTransport>>().await;
+}
+
+// the peers the headers are expected from are disconnected if the chain doesn't advance while a
+// better chain is known
+async fn test_stalled_sync<A, P, T>()
+where
+    A: MakeTestAddress<Address = T::Address>,
+    P: MakeTestPeerId<PeerId = T::PeerId>,
+    T: NetworkingService + 'static,
+    T::ConnectivityHandle: ConnectivityService<T>,
+    T::SyncingMessagingHandle: SyncingMessagingService<T>,
+{
+    let addr = A::make_address();
+    let peer_id = P::random();
+
+    let (mut mgr, _conn, _sync, mut pm) = make_sync_manager::<T>(addr).await;
+    register_peer(&mut mgr, peer_id).await;
+    mgr.peers.get_mut(&peer_id).unwrap().register_known_height(BlockHeight::new(10));
+
+    let handle = tokio::spawn(async move {
+        loop {
+            match pm.recv().await {
+                Some(PeerManagerEvent::Disconnect(peer_id, tx)) => {
+                    tx.send(Ok(())).unwrap();
+                    return peer_id;
+                }
+                Some(PeerManagerEvent::MessageSent(..)) => {}
+                _ => panic!("invalid event received"),
+            }
+        }
+    });
+
+    let now = Instant::now();
+    mgr.process_stalled_sync(now).await.unwrap();
+    assert_eq!(mgr.peers.len(), 1);
+
+    mgr.process_stalled_sync(now + CHAIN_STALL_TIMEOUT).await.unwrap();
+    assert!(mgr.peers.is_empty());
+    assert_eq!(handle.await.unwrap(), peer_id);
+}
+
+#[tokio::test]
+async fn test_stalled_sync_libp2p() {
+    test_stalled_sync::<MakeP2pAddress, PeerId, Libp2pService>().await;
+}
+
+#[tokio::test]
+async fn test_stalled_sync_mock_tcp() {
+    test_stalled_sync::<MakeTcpAddress, MockPeerId, MockService<TcpMockTransport>>().await;
+}
+
+#[tokio::test]
+async fn test_stalled_sync_mock_channels() {
+    test_stalled_sync::<MakeChannelAddress, MockPeerId, MockService<ChannelMockTransport>>().await;
+}
+
+// nobody is disconnected if the chain is idle because no better chain is known
+async fn test_idle_chain_not_stalled<A, P, T>()
+where
+    A: MakeTestAddress<Address = T::Address>,
+    P: MakeTestPeerId<PeerId = T::PeerId>,
+    T: NetworkingService + 'static,
+    T::ConnectivityHandle: ConnectivityService<T>,
+    T::SyncingMessagingHandle: SyncingMessagingService<T>,
+{
+    let addr = A::make_address();
+    let peer_id = P::random();
+
+    let (mut mgr, _conn, _sync, mut pm) = make_sync_manager::<T>(addr).await;
+    register_peer(&mut mgr, peer_id).await;
+    mgr.peers.get_mut(&peer_id).unwrap().register_known_height(BlockHeight::zero());
+
+    let now = Instant::now();
+    for i in 0..3 {
+        mgr.process_stalled_sync(now + CHAIN_STALL_TIMEOUT * i).await.unwrap();
+    }
+    assert_eq!(mgr.peers.len(), 1);
+
+    while let Ok(event) = pm.try_recv() {
+        assert!(
+            !std::matches!(event, PeerManagerEvent::Disconnect(..)),
+            "unexpected disconnect"
+        );
+    }
+}
+
+#[tokio::test]
+async fn test_idle_chain_not_stalled_libp2p() {
+    test_idle_chain_not_stalled::<MakeP2pAddress, PeerId, Libp2pService>().await;
+}
+
+#[tokio::test]
+async fn test_idle_chain_not_stalled_mock_tcp() {
+    test_idle_chain_not_stalled::<MakeTcpAddress, MockPeerId, MockService<TcpMockTransport>>()
+        .await;
+}
+
+#[tokio::test]
+async fn test_idle_chain_not_stalled_mock_channels() {
+    test_idle_chain_not_stalled::<MakeChannelAddress, MockPeerId, MockService<ChannelMockTransport>>()
+        .await;
+}
+
+// a fresh node reports its genesis height and that it isn't synced yet
+async fn test_sync_progress<A, T>()
+where
+    A: MakeTestAddress<Address = T::Address>,
+    T: NetworkingService + 'static,
+    T::ConnectivityHandle: ConnectivityService<T>,
+    T::SyncingMessagingHandle: SyncingMessagingService<T>,
+{
+    let addr = A::make_address();
+    let (mgr, _conn, _sync, _pm) = make_sync_manager::<T>(addr).await;
+
+    let progress = mgr.sync_progress().await.unwrap();
+    assert!(!progress.synced);
+    assert_eq!(progress.block_height, BlockHeight::zero());
+    assert_eq!(progress.header_height, BlockHeight::zero());
+    assert_eq!(progress.peers_behind, 0);
+}
+
+#[tokio::test]
+async fn test_sync_progress_libp2p() {
+    test_sync_progress::<MakeP2pAddress, Libp2pService>().await;
+}
+
+#[tokio::test]
+async fn test_sync_progress_mock_tcp() {
+    test_sync_progress::<MakeTcpAddress, MockService<TcpMockTransport>>().await;
+}
+
+#[tokio::test]
+async fn test_sync_progress_mock_channels() {
+    test_sync_progress::<MakeChannelAddress, MockService<ChannelMockTransport>>().await;
+}
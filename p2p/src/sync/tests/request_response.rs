@@ -198,7 +198,7 @@ where
     let peer2_id = *conn2.peer_id();
 
     tokio::spawn(async move {
-        mgr1.register_peer(peer2_id).await.unwrap();
+        mgr1.register_peer(peer2_id, Services::default()).await.unwrap();
 
         match mgr1.peer_sync_handle.poll_next().await.unwrap() {
             net::types::SyncingEvent::Error {
@@ -277,7 +277,7 @@ where
     let _peer2_id = *conn2.peer_id();
 
     tokio::spawn(async move {
-        mgr1.register_peer(_peer2_id).await.unwrap();
+        mgr1.register_peer(_peer2_id, Services::default()).await.unwrap();
 
         for _ in 0..4 {
             match mgr1.peer_sync_handle.poll_next().await.unwrap() {
@@ -293,9 +293,15 @@ where
             }
         }
 
+        // skip the message statistics reported for the retried requests
+        let mut event = pm_rx.try_recv();
+        while std::matches!(event, Ok(PeerManagerEvent::MessageSent(..))) {
+            event = pm_rx.try_recv();
+        }
+
         let (_tx, rx) = oneshot::channel();
         assert!(std::matches!(
-            pm_rx.try_recv(),
+            event,
             Ok(PeerManagerEvent::Disconnect(_peer2_id, _tx))
         ));
         assert_eq!(rx.await, Ok(()));
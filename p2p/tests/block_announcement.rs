@@ -46,16 +46,25 @@ where
     S::ConnectivityHandle: ConnectivityService<S>,
 {
     let config = Arc::new(common::chain::config::create_mainnet());
-    let (mut conn1, mut sync1) =
-        S::start(A::make_address(), Arc::clone(&config), Default::default())
-            .await
-            .unwrap();
+    let (mut conn1, mut sync1) = S::start(
+        A::make_address(),
+        Arc::clone(&config),
+        Default::default(),
+        Default::default(),
+    )
+    .await
+    .unwrap();
 
     let (mut peer1, mut peer2, mut peer3) = {
         let mut peers = futures::future::join_all((0..3).map(|_| async {
-            let res = S::start(A::make_address(), Arc::clone(&config), Default::default())
-                .await
-                .unwrap();
+            let res = S::start(
+                A::make_address(),
+                Arc::clone(&config),
+                Default::default(),
+                Default::default(),
+            )
+            .await
+            .unwrap();
             (res.0, res.1)
         }))
         .await;
@@ -88,7 +97,9 @@ where
                     ConsensusData::None,
                     BlockReward::new(Vec::new()),
                 )
-                .unwrap(),
+                .unwrap()
+                .header()
+                .clone(),
             ))
             .await;
 
@@ -0,0 +1,72 @@
+// Copyright (c) 2022 RBB S.r.l
+// opensource@mintlayer.org
+// SPDX-License-Identifier: MIT
+// Licensed under the MIT License;
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://github.com/mintlayer/mintlayer-core/blob/master/LICENSE
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::{fs, sync::Arc};
+
+use chainstate::Locator;
+use common::{
+    chain::{config::create_unit_test_config, Block},
+    primitives::{Id, H256},
+};
+use p2p::{
+    config::P2pConfig,
+    message::{BlockListRequest, HeaderListRequest, Request},
+    sync::capture::{CapturedPayload, Direction, MessageCapture},
+};
+use p2p_test_utils::{
+    replay::{replay_capture_file, ReplayReport},
+    start_chainstate,
+};
+
+// A captured request that exceeds the limits is penalized again when it's replayed, while the
+// messages sent by the local node are ignored.
+#[tokio::test]
+async fn replay_too_many_blocks_requested() {
+    let dir = std::env::temp_dir().join(format!("p2p-replay-{}", std::process::id()));
+    let mut capture = MessageCapture::new(&dir, u64::MAX).unwrap();
+
+    let block_id = Id::<Block>::new(H256([0x04; 32]));
+    let max_blocks = *P2pConfig::default().max_blocks_per_message;
+    capture.record(
+        &"peer",
+        Direction::Outbound,
+        CapturedPayload::Request(Request::HeaderListRequest(HeaderListRequest::new(
+            Locator::new(vec![]),
+        ))),
+    );
+    capture.record(
+        &"peer",
+        Direction::Inbound,
+        CapturedPayload::Request(Request::BlockListRequest(BlockListRequest::new(vec![
+            block_id;
+            max_blocks + 1
+        ]))),
+    );
+    capture.close(&"peer");
+    capture.flush().await;
+
+    let config = Arc::new(create_unit_test_config());
+    let chainstate = start_chainstate(Arc::clone(&config)).await;
+    let report = replay_capture_file(config, chainstate, &capture.file_path(&"peer")).await;
+    fs::remove_dir_all(&dir).unwrap();
+
+    assert_eq!(
+        report,
+        ReplayReport {
+            score_adjustments: vec![100],
+            disconnect_requested: false,
+        }
+    );
+}
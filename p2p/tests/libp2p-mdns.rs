@@ -40,6 +40,7 @@ async fn test_libp2p_peer_discovery() {
             .into(),
             ..Default::default()
         }),
+        Default::default(),
     )
     .await
     .unwrap();
@@ -55,6 +56,7 @@ async fn test_libp2p_peer_discovery() {
             .into(),
             ..Default::default()
         }),
+        Default::default(),
     )
     .await
     .unwrap();
@@ -0,0 +1,184 @@
+// Copyright (c) 2022 RBB S.r.l
+// opensource@mintlayer.org
+// SPDX-License-Identifier: MIT
+// Licensed under the MIT License;
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// https://github.com/mintlayer/mintlayer-core/blob/master/LICENSE
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::{sync::Arc, time::Duration};
+
+use rstest::rstest;
+
+use common::{
+    chain::{config::create_unit_test_config, GenBlock},
+    primitives::{Id, Idable},
+};
+use crypto::random::{seq::IteratorRandom, Rng};
+use p2p::constants::PING_INTERVAL;
+use p2p_test_utils::{
+    import_blocks,
+    simulation::{LinkConditions, SimulatedNetwork},
+    TestBlockInfo,
+};
+use test_utils::random::{make_seedable_rng, Seed};
+
+const SYNC_TIMEOUT: Duration = Duration::from_secs(600);
+
+async fn all_nodes_synced(net: &SimulatedNetwork, nodes: &[usize]) -> bool {
+    let best = net.best_block_id(nodes[0]).await;
+    for &node in &nodes[1..] {
+        if net.best_block_id(node).await != best {
+            return false;
+        }
+    }
+    true
+}
+
+// A node that connects to a peer with a longer chain downloads it even if the links are slow and
+// jittery.
+#[rstest]
+#[trace]
+#[case(Seed::from_entropy())]
+#[tokio::test(start_paused = true)]
+async fn blocks_sync_between_nodes(#[case] seed: Seed) {
+    let config = Arc::new(create_unit_test_config());
+    let mut net = SimulatedNetwork::new(Arc::clone(&config), seed);
+    net.set_conditions(LinkConditions {
+        latency: Duration::from_millis(200),
+        jitter: Duration::from_millis(100),
+        loss_rate: 0.0,
+    });
+
+    let nodes = [net.add_node().await, net.add_node().await];
+
+    let blocks = net.create_blocks(TestBlockInfo::from_genesis(config.genesis_block()), 10);
+    let best: Id<GenBlock> = blocks.last().unwrap().get_id().into();
+    import_blocks(&net.node(nodes[0]).chainstate, blocks).await;
+
+    net.connect(nodes[1], nodes[0]).await.unwrap();
+
+    assert!(net.wait_until(SYNC_TIMEOUT, || all_nodes_synced(&net, &nodes)).await);
+    assert_eq!(net.best_block_id(nodes[1]).await, best);
+}
+
+// Downloading a long chain doesn't trip the request limits of the peer, neither node penalizes the
+// other one.
+#[rstest]
+#[trace]
+#[case(Seed::from_entropy())]
+#[tokio::test(start_paused = true)]
+async fn full_sync_without_penalties(#[case] seed: Seed) {
+    let config = Arc::new(create_unit_test_config());
+    let mut net = SimulatedNetwork::new(Arc::clone(&config), seed);
+
+    let nodes = [net.add_node().await, net.add_node().await];
+
+    let blocks = net.create_blocks(TestBlockInfo::from_genesis(config.genesis_block()), 1000);
+    let best: Id<GenBlock> = blocks.last().unwrap().get_id().into();
+    import_blocks(&net.node(nodes[0]).chainstate, blocks).await;
+
+    net.connect(nodes[1], nodes[0]).await.unwrap();
+
+    assert!(net.wait_until(SYNC_TIMEOUT, || all_nodes_synced(&net, &nodes)).await);
+    assert_eq!(net.best_block_id(nodes[1]).await, best);
+
+    for &node in &nodes {
+        let peers = net.node(node).p2p.get_peer_info().await.unwrap();
+        assert_eq!(peers.len(), 1);
+        assert_eq!(peers[0].ban_score, 0);
+    }
+}
+
+// Two halves of the network build different chains while partitioned and all nodes converge on the
+// longer one once the partition heals.
+#[rstest]
+#[trace]
+#[case(Seed::from_entropy())]
+#[tokio::test(start_paused = true)]
+async fn partition_and_reorg(#[case] seed: Seed) {
+    let config = Arc::new(create_unit_test_config());
+    let mut net = SimulatedNetwork::new(Arc::clone(&config), seed);
+
+    let nodes = [
+        net.add_node().await,
+        net.add_node().await,
+        net.add_node().await,
+        net.add_node().await,
+    ];
+    net.connect(nodes[1], nodes[0]).await.unwrap();
+    net.connect(nodes[3], nodes[2]).await.unwrap();
+    net.connect(nodes[2], nodes[0]).await.unwrap();
+
+    net.partition(&[&nodes[..2], &nodes[2..]]);
+
+    // The connection between the halves is reset by the first ping that can't be delivered
+    let net_ref = &net;
+    assert!(
+        net.wait_until(PING_INTERVAL * 3, || async move {
+            net_ref.peer_count(nodes[0]).await == 1 && net_ref.peer_count(nodes[2]).await == 1
+        })
+        .await
+    );
+
+    let genesis = TestBlockInfo::from_genesis(config.genesis_block());
+    let short_chain = net.create_blocks(genesis.clone(), 3);
+    let long_chain = net.create_blocks(genesis, 5);
+    let best: Id<GenBlock> = long_chain.last().unwrap().get_id().into();
+    for &node in &nodes[..2] {
+        import_blocks(&net.node(node).chainstate, short_chain.clone()).await;
+    }
+    for &node in &nodes[2..] {
+        import_blocks(&net.node(node).chainstate, long_chain.clone()).await;
+    }
+    assert!(!all_nodes_synced(&net, &nodes).await);
+
+    net.heal();
+    net.connect(nodes[2], nodes[0]).await.unwrap();
+
+    assert!(net.wait_until(SYNC_TIMEOUT, || all_nodes_synced(&net, &nodes)).await);
+    assert_eq!(net.best_block_id(nodes[0]).await, best);
+}
+
+// A chain mined by one node reaches every node of a larger network in which each node is only
+// connected to a few random peers.
+#[rstest]
+#[trace]
+#[case(Seed::from_entropy())]
+#[tokio::test(start_paused = true)]
+async fn large_network_sync(#[case] seed: Seed) {
+    const NODE_COUNT: usize = 40;
+    const OUTBOUND_PEERS: usize = 3;
+
+    let config = Arc::new(create_unit_test_config());
+    let mut net = SimulatedNetwork::new(Arc::clone(&config), seed);
+    let mut rng = make_seedable_rng(seed);
+
+    let mut nodes = Vec::new();
+    for _ in 0..NODE_COUNT {
+        nodes.push(net.add_node().await);
+    }
+
+    // Every node connects to random nodes started before it, so the network is connected
+    for i in 1..NODE_COUNT {
+        let peers = (0..i).choose_multiple(&mut rng, OUTBOUND_PEERS);
+        for peer in peers {
+            net.connect(nodes[i], nodes[peer]).await.unwrap();
+        }
+    }
+
+    let miner = nodes[rng.gen_range(0..NODE_COUNT)];
+    let blocks = net.create_blocks(TestBlockInfo::from_genesis(config.genesis_block()), 20);
+    let best: Id<GenBlock> = blocks.last().unwrap().get_id().into();
+    import_blocks(&net.node(miner).chainstate, blocks).await;
+
+    assert!(net.wait_until(SYNC_TIMEOUT, || all_nodes_synced(&net, &nodes)).await);
+    assert_eq!(net.best_block_id(nodes[0]).await, best);
+}